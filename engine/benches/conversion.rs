@@ -0,0 +1,85 @@
+//! Conversion of the AQL results into juniper values, run with `cargo bench`
+//!
+//! The engine is a binary crate, so only the self-contained modules are benchmarked, the AQL
+//! generation and the dispatch of the operations need a library target first
+
+use criterion::{black_box, criterion_group, criterion_main, BenchmarkId, Criterion};
+use juniper::{DefaultScalarValue, Object, Value};
+use serde_json::{json, Value as JsonValue};
+
+#[path = "../src/api/schema/values.rs"]
+#[allow(dead_code)]
+mod values;
+
+use values::DocumentValue;
+
+/// The results of a list operation, as returned by ArangoDB
+fn results(count: usize) -> String {
+	let documents: Vec<JsonValue> = (0..count)
+		.map(|i| {
+			json!({
+				"_key": i.to_string(),
+				"title": format!("Book {}", i),
+				"pages": i * 3,
+				"rating": i as f64 / 7.0,
+				"available": i % 2 == 0,
+				"tags": ["fiction", "classic"],
+				"author": { "name": "Someone", "born": 1900 },
+			})
+		})
+		.collect();
+
+	serde_json::to_string(&documents).unwrap()
+}
+
+/// The conversion through a `serde_json::Value` tree, as the mutations still do
+fn convert(value: &JsonValue) -> Value<DefaultScalarValue> {
+	match value {
+		JsonValue::Null => Value::null(),
+		JsonValue::Bool(v) => Value::scalar(*v),
+		JsonValue::Number(n) => match n.as_i64() {
+			Some(v) => Value::scalar(v.clamp(i32::MIN as i64, i32::MAX as i64) as i32),
+			None => Value::scalar(n.as_f64().unwrap_or_default()),
+		},
+		JsonValue::String(s) => Value::scalar(s.clone()),
+		JsonValue::Array(items) => Value::list(items.iter().map(convert).collect()),
+		JsonValue::Object(fields) => {
+			let mut object = Object::with_capacity(fields.len());
+
+			for (key, value) in fields {
+				object.add_field(key, convert(value));
+			}
+
+			Value::Object(object)
+		}
+	}
+}
+
+fn conversion(c: &mut Criterion) {
+	let mut group = c.benchmark_group("results");
+
+	for count in [10, 1_000, 10_000] {
+		let body = results(count);
+
+		group.bench_with_input(BenchmarkId::new("through_json", count), &body, |b, body| {
+			b.iter(|| {
+				let documents: Vec<JsonValue> = serde_json::from_str(body).unwrap();
+
+				black_box(documents.iter().map(convert).collect::<Vec<_>>())
+			})
+		});
+
+		group.bench_with_input(BenchmarkId::new("direct", count), &body, |b, body| {
+			b.iter(|| {
+				black_box(
+					serde_json::from_str::<Vec<DocumentValue<DefaultScalarValue>>>(body).unwrap(),
+				)
+			})
+		});
+	}
+
+	group.finish();
+}
+
+criterion_group!(benches, conversion);
+criterion_main!(benches);
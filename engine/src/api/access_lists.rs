@@ -0,0 +1,64 @@
+use std::collections::HashMap;
+
+use lazy_static::lazy_static;
+
+use crate::lib::CONFIG;
+
+/// Role, or operation, matching every role or operation
+const WILDCARD: &str = "*";
+
+lazy_static! {
+	static ref ALLOWLIST: HashMap<String, Vec<String>> = parse_lists(&CONFIG.operation_allowlist);
+	static ref DENYLIST: HashMap<String, Vec<String>> = parse_lists(&CONFIG.operation_denylist);
+}
+
+/// Operations per role, written as `role:operation|operation`
+fn parse_lists(entries: &Option<Vec<String>>) -> HashMap<String, Vec<String>> {
+	let mut lists: HashMap<String, Vec<String>> = HashMap::new();
+
+	for entry in entries.iter().flatten() {
+		match entry.split_once(':') {
+			Some((role, operations)) => lists.entry(role.trim().to_string()).or_default().extend(
+				operations
+					.split('|')
+					.map(|operation| operation.trim().to_string())
+					.filter(|operation| !operation.is_empty()),
+			),
+			None => tracing::warn!(entry = %entry, "Ignoring an operation list entry without role"),
+		}
+	}
+
+	lists
+}
+
+fn is_listed(list: &HashMap<String, Vec<String>>, role: &str, operation: &str) -> bool {
+	list.get(role)
+		.map(|operations| {
+			operations
+				.iter()
+				.any(|listed| listed == operation || listed == WILDCARD)
+		})
+		.unwrap_or(false)
+}
+
+/// Whether a role may run the operation: a role with an allowlist only runs the operations
+/// it lists, and the wildcard denylist does not apply to the operations a role allows
+fn role_allows(role: &str, operation: &str) -> bool {
+	let allowed = is_listed(&ALLOWLIST, role, operation);
+
+	if ALLOWLIST.contains_key(role) && !allowed {
+		return false;
+	}
+
+	!is_listed(&DENYLIST, role, operation)
+		&& (allowed || !is_listed(&DENYLIST, WILDCARD, operation))
+}
+
+/// Whether one of the roles may run the operation, requests without roles are only
+/// subject to the wildcard denylist
+pub fn is_operation_allowed(roles: Option<&[String]>, operation: &str) -> bool {
+	match roles {
+		Some(roles) if !roles.is_empty() => roles.iter().any(|role| role_allows(role, operation)),
+		_ => !is_listed(&DENYLIST, WILDCARD, operation),
+	}
+}
@@ -0,0 +1,348 @@
+use std::sync::Arc;
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use juniper::{
+	Executor, FieldError, LookAheadMethods, LookAheadSelection, LookAheadValue, Object,
+	ScalarValue, Value,
+};
+use lazy_static::lazy_static;
+use reqwest::Client;
+use rust_arango::AqlQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::api::remote::{json_to_value, RemoteKind, RemoteSchema};
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{error_extensions, ErrorCode};
+use crate::lib::database::arango::ensure_collection;
+use crate::lib::database::DATABASE;
+use crate::lib::CONFIG;
+
+/// Collection holding the actions, identified by their name
+pub const ACTIONS_COLLECTION: &str = "alchemy_actions";
+
+lazy_static! {
+	static ref CLIENT: Client = Client::new();
+}
+
+/// Custom root fields declared in SDL and resolved by a REST endpoint
+#[derive(Serialize, Deserialize, Clone, Debug, GraphQLObject)]
+#[graphql(name = "Action")]
+#[serde(rename_all = "camelCase")]
+pub struct ActionDefinition {
+	pub name: String,
+	/// Types of the action, its fields are declared in `type Query` or `type Mutation`
+	pub definition: String,
+	/// Receives a POST of the field, its arguments and the session, answers with the result
+	pub url: String,
+	/// Defaults to `ACTION_TIMEOUT_MS`
+	pub timeout_ms: Option<i32>,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct ActionInput {
+	pub name: String,
+	pub definition: String,
+	pub url: String,
+	pub timeout_ms: Option<i32>,
+}
+
+impl From<ActionInput> for ActionDefinition {
+	fn from(input: ActionInput) -> Self {
+		ActionDefinition {
+			name: input.name,
+			definition: input.definition,
+			url: input.url,
+			timeout_ms: input.timeout_ms,
+		}
+	}
+}
+
+fn is_http_url(url: &str) -> bool {
+	url.starts_with("http://") || url.starts_with("https://")
+}
+
+pub async fn get_actions() -> Result<Vec<ActionDefinition>, Error> {
+	ensure_collection(ACTIONS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR action IN @@collection
+				SORT action.name
+				RETURN UNSET(action, '_id', '_key', '_rev')",
+		)
+		.bind_var("@collection", ACTIONS_COLLECTION)
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
+
+/// Store an action, replacing the one with the same name, the schema must be reloaded to expose it
+pub async fn set_action(action: ActionInput) -> Result<(), Error> {
+	let action = ActionDefinition::from(action);
+
+	if !is_http_url(&action.url) {
+		return Err(anyhow!(
+			"Invalid URL {} of the action {}",
+			action.url,
+			action.name
+		));
+	}
+
+	RemoteSchema::from_sdl(
+		&action.name,
+		&action.url,
+		RemoteKind::Action,
+		&action.definition,
+	)?;
+
+	ensure_collection(ACTIONS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"UPSERT { name: @action.name }
+				INSERT @action
+				REPLACE @action
+				IN @@collection",
+		)
+		.bind_var("@collection", ACTIONS_COLLECTION)
+		.bind_var("action", serde_json::to_value(&action)?)
+		.build();
+
+	let _: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
+
+pub async fn delete_action(name: String) -> Result<(), Error> {
+	ensure_collection(ACTIONS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR action IN @@collection
+				FILTER action.name == @name
+				REMOVE action IN @@collection",
+		)
+		.bind_var("@collection", ACTIONS_COLLECTION)
+		.bind_var("name", name)
+		.build();
+
+	let _: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
+
+/// The schemas of the stored actions, the invalid ones are left out
+pub async fn load_actions() -> Vec<Arc<RemoteSchema>> {
+	let actions = match get_actions().await {
+		Ok(actions) => actions,
+		Err(e) => {
+			tracing::error!(error = %e, "Failed to load the actions");
+
+			return Vec::new();
+		}
+	};
+
+	actions
+		.into_iter()
+		.filter_map(|action| {
+			match RemoteSchema::from_sdl(
+				&action.name,
+				&action.url,
+				RemoteKind::Action,
+				&action.definition,
+			) {
+				Ok(mut schema) => {
+					schema.timeout = action
+						.timeout_ms
+						.map(|timeout_ms| Duration::from_millis(timeout_ms.max(0) as u64));
+
+					Some(Arc::new(schema))
+				}
+				Err(e) => {
+					tracing::error!(action = %action.name, error = %e, "Ignoring an invalid action");
+
+					None
+				}
+			}
+		})
+		.collect()
+}
+
+fn lookahead_to_json<S: ScalarValue>(value: &LookAheadValue<S>) -> JsonValue {
+	match value {
+		LookAheadValue::Null => JsonValue::Null,
+		LookAheadValue::Enum(name) => JsonValue::from(*name),
+		LookAheadValue::Scalar(scalar) => {
+			if let Some(v) = scalar.as_int() {
+				JsonValue::from(v)
+			} else if let Some(v) = scalar.as_float() {
+				JsonValue::from(v)
+			} else if let Some(v) = scalar.as_boolean() {
+				JsonValue::from(v)
+			} else {
+				JsonValue::from(scalar.as_string().unwrap_or_default())
+			}
+		}
+		LookAheadValue::List(items) => items.iter().map(lookahead_to_json).collect(),
+		LookAheadValue::Object(fields) => JsonValue::Object(
+			fields
+				.iter()
+				.map(|(name, value)| (name.to_string(), lookahead_to_json(value)))
+				.collect(),
+		),
+	}
+}
+
+/// Keep the selected fields of the answer, under their aliases
+fn project<S: ScalarValue>(
+	schema: &RemoteSchema,
+	type_name: Option<&str>,
+	value: JsonValue,
+	selection: &LookAheadSelection<S>,
+) -> Value<S> {
+	let children = selection.children();
+
+	if children.is_empty() {
+		return json_to_value(value);
+	}
+
+	match value {
+		JsonValue::Array(items) => Value::list(
+			items
+				.into_iter()
+				.map(|item| project(schema, type_name, item, selection))
+				.collect(),
+		),
+		JsonValue::Object(fields) => {
+			let actual_type = fields
+				.get("__typename")
+				.and_then(|name| name.as_str())
+				.or(type_name)
+				.map(str::to_string);
+
+			let mut object = Object::with_capacity(children.len());
+
+			for child in children {
+				if let (Some(applies), Some(actual)) = (child.applies_for(), &actual_type) {
+					if applies != actual {
+						continue;
+					}
+				}
+
+				let name = child.field_original_name();
+				let response_name = child.field_alias().unwrap_or(name);
+
+				let value = if name == "__typename" {
+					actual_type
+						.clone()
+						.map(Value::scalar)
+						.unwrap_or_else(Value::null)
+				} else {
+					let field_type = actual_type
+						.as_deref()
+						.and_then(|actual| schema.field_type(actual, name));
+
+					project(
+						schema,
+						field_type,
+						fields.get(name).cloned().unwrap_or(JsonValue::Null),
+						child,
+					)
+				};
+
+				object.add_field(response_name, value);
+			}
+
+			Value::Object(object)
+		}
+		value => json_to_value(value),
+	}
+}
+
+/// POST the field to the endpoint of its action, along with the forwarded headers
+pub async fn resolve_action<S>(
+	schema: &RemoteSchema,
+	mutation: bool,
+	executor: &Executor<'_, '_, Context, S>,
+) -> Result<Value<S>, FieldError<S>>
+where
+	S: ScalarValue,
+{
+	let selection = executor.look_ahead();
+	let field_name = selection.field_original_name();
+
+	let input: JsonMap<String, JsonValue> = selection
+		.arguments()
+		.iter()
+		.map(|argument| {
+			(
+				argument.name().to_string(),
+				lookahead_to_json(argument.value()),
+			)
+		})
+		.collect();
+
+	let context = executor.context();
+
+	let session = context.claims.as_ref().map(|claims| {
+		json!({
+			"userId": claims.user_id,
+			"roles": claims.roles,
+			"claims": claims.claims,
+		})
+	});
+
+	let request = context.remote_headers.iter().fold(
+		CLIENT.post(&schema.url).timeout(
+			schema
+				.timeout
+				.unwrap_or_else(|| Duration::from_millis(CONFIG.action_timeout_ms)),
+		),
+		|request, (name, value)| request.header(name.as_str(), value.as_str()),
+	);
+
+	let response = request
+		.json(&json!({
+			"action": { "name": schema.name, "field": field_name },
+			"input": input,
+			"session": session,
+		}))
+		.send()
+		.await
+		.map_err(|e| {
+			tracing::error!(action = %schema.name, error = %e, "Action request failed");
+
+			FieldError::new(
+				format!("Action {} is unavailable", schema.name),
+				Value::Object(error_extensions(ErrorCode::Internal)),
+			)
+		})?;
+
+	let status = response.status();
+
+	let body: JsonValue = response.json().await.map_err(|_| {
+		FieldError::new(
+			format!("Action {} answered with invalid JSON", schema.name),
+			Value::Object(error_extensions(ErrorCode::Internal)),
+		)
+	})?;
+
+	// The endpoint reports errors as `{ "message": ..., "extensions": ... }`
+	if !status.is_success() {
+		return Err(FieldError::new(
+			body["message"]
+				.as_str()
+				.map(str::to_string)
+				.unwrap_or_else(|| format!("Action {} failed with {}", schema.name, status)),
+			json_to_value(body["extensions"].clone()),
+		));
+	}
+
+	let type_name = schema
+		.root_type(mutation)
+		.and_then(|root| schema.field_type(root, field_name));
+
+	Ok(project(schema, type_name, body, &selection))
+}
@@ -0,0 +1,431 @@
+use std::collections::HashMap;
+
+use actix_web::{error::ErrorBadRequest, Error as ActixError};
+use juniper::parser::parse_document_source;
+use juniper::{
+	DefaultScalarValue, Definition, InputValue, OperationType, ScalarValue, Selection, Type,
+	Variables,
+};
+use lazy_static::lazy_static;
+use serde_json::Value as JsonValue;
+
+use crate::api::schema::Schema;
+use crate::lib::CONFIG;
+
+/// Root fields exposing the schema
+const INTROSPECTION_FIELDS: [&str; 2] = ["__schema", "__type"];
+
+lazy_static! {
+	/// Costs of the fields and arguments, see `graphql_costs`
+	static ref COSTS: HashMap<String, u64> = CONFIG
+		.graphql_costs
+		.iter()
+		.flatten()
+		.filter_map(|entry| {
+			let (key, cost) = entry.rsplit_once('=')?;
+
+			match cost.trim().parse() {
+				Ok(cost) => Some((key.trim().to_string(), cost)),
+				Err(_) => {
+					tracing::warn!(cost = %entry, "Ignoring an invalid GraphQL cost");
+
+					None
+				}
+			}
+		})
+		.collect();
+}
+
+type Selections<'a> = [Selection<'a, DefaultScalarValue>];
+
+/// A parsed GraphQL document, with its fragments indexed by name
+struct Analysis<'a> {
+	/// The selections of every operation, along with the name of their root type
+	operations: Vec<(&'static str, &'a Selections<'a>)>,
+	fragments: HashMap<&'a str, &'a Selections<'a>>,
+	fragment_types: HashMap<&'a str, &'a str>,
+}
+
+impl<'a> Analysis<'a> {
+	fn new(definitions: &'a [Definition<'a, DefaultScalarValue>]) -> Self {
+		let mut operations = Vec::new();
+		let mut fragments = HashMap::new();
+		let mut fragment_types = HashMap::new();
+
+		for definition in definitions {
+			match definition {
+				Definition::Operation(operation) => {
+					let root = match operation.item.operation_type {
+						OperationType::Query => "Query",
+						OperationType::Mutation => "Mutation",
+						OperationType::Subscription => "Subscription",
+					};
+
+					operations.push((root, operation.item.selection_set.as_slice()))
+				}
+				Definition::Fragment(fragment) => {
+					fragments.insert(
+						fragment.item.name.item,
+						fragment.item.selection_set.as_slice(),
+					);
+					fragment_types
+						.insert(fragment.item.name.item, fragment.item.type_condition.item);
+				}
+			}
+		}
+
+		Analysis {
+			operations,
+			fragments,
+			fragment_types,
+		}
+	}
+
+	/// Whether a field of the selections, or of the fragments they spread, has one of the names
+	fn selects_any(
+		&self,
+		selections: &Selections<'a>,
+		names: &[&str],
+		visited: &mut Vec<&'a str>,
+	) -> bool {
+		selections.iter().any(|selection| match selection {
+			Selection::Field(field) => {
+				names.contains(&field.item.name.item)
+					|| field
+						.item
+						.selection_set
+						.as_deref()
+						.map(|selections| self.selects_any(selections, names, visited))
+						.unwrap_or(false)
+			}
+			Selection::InlineFragment(fragment) => {
+				self.selects_any(&fragment.item.selection_set, names, visited)
+			}
+			Selection::FragmentSpread(spread) => {
+				let name = spread.item.name.item;
+
+				// Fragment cycles are reported by the validation of juniper
+				if visited.contains(&name) {
+					return false;
+				}
+
+				visited.push(name);
+
+				self.fragments
+					.get(name)
+					.map(|selections| self.selects_any(selections, names, visited))
+					.unwrap_or(false)
+			}
+		})
+	}
+
+	/// Nesting depth of the selections, introspection fields are not counted
+	fn depth(&self, selections: &Selections<'a>, path: &mut Vec<&'a str>) -> usize {
+		selections
+			.iter()
+			.map(|selection| match selection {
+				Selection::Field(field) if field.item.name.item.starts_with("__") => 0,
+				Selection::Field(field) => {
+					1 + field
+						.item
+						.selection_set
+						.as_deref()
+						.map(|selections| self.depth(selections, path))
+						.unwrap_or(0)
+				}
+				Selection::InlineFragment(fragment) => {
+					self.depth(&fragment.item.selection_set, path)
+				}
+				Selection::FragmentSpread(spread) => {
+					let name = spread.item.name.item;
+
+					if path.contains(&name) {
+						return 0;
+					}
+
+					path.push(name);
+
+					let depth = self
+						.fragments
+						.get(name)
+						.map(|selections| self.depth(selections, path))
+						.unwrap_or(0);
+
+					path.pop();
+
+					depth
+				}
+			})
+			.max()
+			.unwrap_or(0)
+	}
+
+	/// Estimated cost of the selections on a type, the selections of list fields
+	/// are counted once per item they may return
+	fn cost(
+		&self,
+		schema: &Schema,
+		type_name: Option<&str>,
+		selections: &Selections<'a>,
+		variables: &Variables,
+		path: &mut Vec<&'a str>,
+	) -> u64 {
+		selections
+			.iter()
+			.map(|selection| match selection {
+				Selection::Field(field) if field.item.name.item.starts_with("__") => 0,
+				Selection::Field(field) => {
+					let name = field.item.name.item;
+					let field_type = type_name
+						.and_then(|type_name| schema.schema.concrete_type_by_name(type_name))
+						.and_then(|meta| meta.field_by_name(name))
+						.map(|meta| &meta.field_type);
+
+					let arguments = field
+						.item
+						.arguments
+						.as_ref()
+						.map(|arguments| arguments.item.items.as_slice())
+						.unwrap_or_default();
+
+					let mut cost = field_cost(type_name, name, field.item.selection_set.is_some());
+
+					for (argument, _) in arguments {
+						cost += argument_cost(type_name, name, argument.item);
+					}
+
+					let children = match &field.item.selection_set {
+						Some(selections) => self.cost(
+							schema,
+							field_type.map(|field_type| field_type.innermost_name()),
+							selections,
+							variables,
+							path,
+						),
+						None => 0,
+					};
+
+					let multiplier = match field_type {
+						Some(Type::List(_)) | Some(Type::NonNullList(_)) => arguments
+							.iter()
+							.find(|(argument, _)| argument.item == "limit")
+							.and_then(|(_, value)| int_value(&value.item, variables))
+							.map(|limit| limit.max(0) as u64)
+							.unwrap_or(CONFIG.graphql_default_list_size),
+						_ => 1,
+					};
+
+					cost + multiplier.saturating_mul(children)
+				}
+				Selection::InlineFragment(fragment) => self.cost(
+					schema,
+					fragment
+						.item
+						.type_condition
+						.as_ref()
+						.map(|condition| condition.item)
+						.or(type_name),
+					&fragment.item.selection_set,
+					variables,
+					path,
+				),
+				Selection::FragmentSpread(spread) => {
+					let name = spread.item.name.item;
+
+					if path.contains(&name) {
+						return 0;
+					}
+
+					path.push(name);
+
+					let cost = self
+						.fragments
+						.get(name)
+						.map(|selections| {
+							let fragment_type = self.fragment_types.get(name).copied();
+
+							self.cost(schema, fragment_type, selections, variables, path)
+						})
+						.unwrap_or(0);
+
+					path.pop();
+
+					cost
+				}
+			})
+			.fold(0, u64::saturating_add)
+	}
+}
+
+/// The configured cost of a field, objects cost 1 and scalars nothing by default
+fn field_cost(type_name: Option<&str>, field: &str, is_object: bool) -> u64 {
+	type_name
+		.and_then(|type_name| COSTS.get(&format!("{}.{}", type_name, field)))
+		.or_else(|| COSTS.get(field))
+		.copied()
+		.unwrap_or(if is_object { 1 } else { 0 })
+}
+
+/// The configured cost of passing an argument to a field, nothing by default
+fn argument_cost(type_name: Option<&str>, field: &str, argument: &str) -> u64 {
+	type_name
+		.and_then(|type_name| COSTS.get(&format!("{}.{}({})", type_name, field, argument)))
+		.or_else(|| COSTS.get(&format!("{}({})", field, argument)))
+		.copied()
+		.unwrap_or(0)
+}
+
+fn int_value(value: &InputValue, variables: &Variables) -> Option<i32> {
+	match value {
+		InputValue::Scalar(scalar) => scalar.as_int(),
+		InputValue::Variable(name) => variables.get(name)?.as_int_value(),
+		_ => None,
+	}
+}
+
+/// Check a GraphQL document against the configured restrictions, documents that cannot be
+/// parsed are left to juniper so that it reports the syntax errors
+pub fn check_operations(
+	document: &str,
+	variables: &Variables,
+	schema: &Schema,
+) -> Result<(), ActixError> {
+	let definitions = match parse_document_source(document, &schema.schema) {
+		Ok(definitions) => definitions,
+		Err(_) => return Ok(()),
+	};
+
+	let analysis = Analysis::new(&definitions);
+
+	if !CONFIG.introspection_enabled()
+		&& analysis.operations.iter().any(|(_, selections)| {
+			analysis.selects_any(selections, &INTROSPECTION_FIELDS, &mut Vec::new())
+		}) {
+		return Err(ErrorBadRequest("GraphQL introspection is disabled"));
+	}
+
+	if let Some(max_depth) = CONFIG.graphql_max_depth {
+		let depth = analysis
+			.operations
+			.iter()
+			.map(|(_, selections)| analysis.depth(selections, &mut Vec::new()))
+			.max()
+			.unwrap_or(0);
+
+		if depth > max_depth {
+			return Err(ErrorBadRequest(format!(
+				"GraphQL document exceeds a depth of {}",
+				max_depth
+			)));
+		}
+	}
+
+	if let Some(max_cost) = CONFIG.graphql_max_cost {
+		let cost = analysis
+			.operations
+			.iter()
+			.map(|(root, selections)| {
+				analysis.cost(schema, Some(root), selections, variables, &mut Vec::new())
+			})
+			.max()
+			.unwrap_or(0);
+
+		if cost > max_cost {
+			if !CONFIG.graphql_cost_log_only {
+				return Err(ErrorBadRequest(format!(
+					"GraphQL document costs {}, more than the budget of {}",
+					cost, max_cost
+				)));
+			}
+
+			tracing::warn!(cost, max_cost, "GraphQL document exceeds the cost budget");
+		}
+	}
+
+	Ok(())
+}
+
+/// Whether the operation a document runs is a mutation, the operation is selected by its name
+/// when there is one, documents that cannot be parsed are left to juniper
+pub fn runs_mutation(document: &str, operation_name: Option<&str>, schema: &Schema) -> bool {
+	let definitions = match parse_document_source(document, &schema.schema) {
+		Ok(definitions) => definitions,
+		Err(_) => return false,
+	};
+
+	definitions.iter().any(|definition| match definition {
+		Definition::Operation(operation) => {
+			matches!(operation.item.operation_type, OperationType::Mutation)
+				&& operation_name
+					.map(|name| operation.item.name.as_ref().map(|n| n.item) == Some(name))
+					.unwrap_or(true)
+		}
+		Definition::Fragment(_) => false,
+	})
+}
+
+/// The response key of the root field of a query selecting a single one, the operation is
+/// selected by its name when there is one
+pub fn single_root_field(
+	document: &str,
+	operation_name: Option<&str>,
+	schema: &Schema,
+) -> Option<String> {
+	let definitions = parse_document_source(document, &schema.schema).ok()?;
+
+	let mut operations = definitions
+		.iter()
+		.filter_map(|definition| match definition {
+			Definition::Operation(operation) => Some(&operation.item),
+			Definition::Fragment(_) => None,
+		})
+		.filter(|operation| {
+			operation_name
+				.map(|name| operation.name.as_ref().map(|n| n.item) == Some(name))
+				.unwrap_or(true)
+		});
+
+	let operation = operations.next()?;
+
+	if operations.next().is_some() || !matches!(operation.operation_type, OperationType::Query) {
+		return None;
+	}
+
+	match operation.selection_set.as_slice() {
+		[Selection::Field(field)] if !field.item.name.item.starts_with("__") => Some(
+			field
+				.item
+				.alias
+				.as_ref()
+				.unwrap_or(&field.item.name)
+				.item
+				.to_string(),
+		),
+		_ => None,
+	}
+}
+
+/// Check every operation of a request, which may be a batch, encoded as JSON
+pub fn check_request(source: &str, schema: &Schema) -> Result<(), ActixError> {
+	let value: JsonValue = serde_json::from_str(source).map_err(ErrorBadRequest)?;
+
+	let requests = match &value {
+		JsonValue::Array(requests) => requests.iter().collect(),
+		request => vec![request],
+	};
+
+	for request in requests {
+		if let Some(query) = request.get("query").and_then(|query| query.as_str()) {
+			let variables: Variables = match request.get("variables") {
+				Some(JsonValue::Null) | None => Variables::new(),
+				Some(variables) => {
+					serde_json::from_value(variables.clone()).map_err(ErrorBadRequest)?
+				}
+			};
+
+			check_operations(query, &variables, schema)?;
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,233 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::{anyhow, Error};
+use rust_arango::AqlQuery;
+use serde::Deserialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::api::auth::{AuthError, Claims};
+use crate::lib::database::arango::ensure_collection;
+use crate::lib::database::DATABASE;
+use crate::lib::CONFIG;
+
+/// Collection holding the API keys, identified by the SHA-256 of the key
+pub const API_KEYS_COLLECTION: &str = "alchemy_api_keys";
+
+/// An API key stored in the database
+#[derive(Deserialize)]
+struct StoredApiKey {
+	name: String,
+	#[serde(default)]
+	roles: Vec<String>,
+	tenant: Option<String>,
+}
+
+/// An API key of the configuration, written as `key`, `key:role|role`, `key:role|role:expires_at`
+/// or `key:role|role:expires_at:tenant` where `expires_at` is a UNIX timestamp
+struct StaticApiKey<'a> {
+	key: &'a str,
+	roles: Vec<String>,
+	expires_at: Option<u64>,
+	tenant: Option<String>,
+}
+
+impl<'a> StaticApiKey<'a> {
+	fn parse(entry: &'a str) -> Self {
+		let mut parts = entry.splitn(4, ':');
+
+		StaticApiKey {
+			key: parts.next().unwrap_or_default(),
+			roles: parts
+				.next()
+				.map(|roles| {
+					roles
+						.split('|')
+						.filter(|role| !role.is_empty())
+						.map(String::from)
+						.collect()
+				})
+				.unwrap_or_default(),
+			expires_at: parts.next().and_then(|expires_at| expires_at.parse().ok()),
+			tenant: parts
+				.next()
+				.filter(|tenant| !tenant.is_empty())
+				.map(String::from),
+		}
+	}
+
+	fn is_expired(&self) -> bool {
+		let now = SystemTime::now()
+			.duration_since(UNIX_EPOCH)
+			.map(|now| now.as_secs())
+			.unwrap_or_default();
+
+		self.expires_at
+			.map(|expires_at| expires_at <= now)
+			.unwrap_or(false)
+	}
+}
+
+pub fn hash_api_key(key: &str) -> String {
+	hex::encode(Sha256::digest(key.as_bytes()))
+}
+
+/// Check an API key against the configured keys, then against the stored ones. The digests of the
+/// configured keys are compared, so that the time taken tells nothing about how much of a key was
+/// guessed
+pub async fn verify_api_key(key: &str) -> Result<Claims, AuthError> {
+	let digest = Sha256::digest(key.as_bytes());
+
+	for entry in CONFIG.api_keys.iter().flatten() {
+		let api_key = StaticApiKey::parse(entry);
+
+		if Sha256::digest(api_key.key.as_bytes()) == digest {
+			if api_key.is_expired() {
+				return Err(AuthError::InvalidApiKey);
+			}
+
+			return Ok(Claims {
+				user_id: None,
+				roles: api_key.roles,
+				tenant: api_key.tenant,
+				api_key: Some(hex::encode(digest)),
+				claims: JsonMap::new(),
+			});
+		}
+	}
+
+	if !CONFIG.api_keys_from_database {
+		return Err(AuthError::InvalidApiKey);
+	}
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR api_key IN @@collection
+				FILTER api_key.key_hash == @hash
+				FILTER api_key.expires_at == null OR DATE_TIMESTAMP(api_key.expires_at) > DATE_NOW()
+				LIMIT 1
+				RETURN api_key",
+		)
+		.bind_var("@collection", API_KEYS_COLLECTION)
+		.bind_var("hash", hash_api_key(key))
+		.build();
+
+	let stored: Vec<StoredApiKey> = DATABASE
+		.get()
+		.await
+		.database
+		.aql_query(query)
+		.await
+		.map_err(|e| {
+			tracing::error!(error = ?e, "Failed to look up the API key");
+
+			AuthError::InvalidApiKey
+		})?;
+
+	match stored.into_iter().next() {
+		Some(api_key) => Ok(Claims {
+			user_id: Some(api_key.name.clone()),
+			roles: api_key.roles,
+			tenant: api_key.tenant,
+			api_key: Some(api_key.name),
+			claims: JsonMap::new(),
+		}),
+		None => Err(AuthError::InvalidApiKey),
+	}
+}
+
+/// An API key stored in the database, the key itself is only known to its owner
+#[derive(Deserialize, GraphQLObject)]
+pub struct ApiKeyInfo {
+	pub name: String,
+	#[serde(default)]
+	pub roles: Vec<String>,
+	/// ISO 8601 date after which the key is refused
+	pub expires_at: Option<String>,
+	/// The tenant whose collections the key uses, when collections are prefixed per tenant
+	pub tenant: Option<String>,
+}
+
+pub async fn list_api_keys() -> Result<Vec<ApiKeyInfo>, Error> {
+	ensure_collection(API_KEYS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR api_key IN @@collection
+				SORT api_key.name
+				RETURN api_key",
+		)
+		.bind_var("@collection", API_KEYS_COLLECTION)
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
+
+/// Store a new API key and return it, only its hash is kept
+pub async fn create_api_key(
+	name: String,
+	roles: Vec<String>,
+	expires_at: Option<String>,
+	tenant: Option<String>,
+) -> Result<String, Error> {
+	ensure_collection(API_KEYS_COLLECTION).await?;
+
+	let key = format!("alchemy_{}", Uuid::new_v4().to_simple());
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR api_key IN @@collection
+				FILTER api_key.name == @name
+				LIMIT 1
+				RETURN api_key.name",
+		)
+		.bind_var("@collection", API_KEYS_COLLECTION)
+		.bind_var("name", name.clone())
+		.build();
+
+	let existing: Vec<String> = DATABASE.get().await.database.aql_query(query).await?;
+
+	if !existing.is_empty() {
+		return Err(anyhow!("An API key named {} already exists", name));
+	}
+
+	let query = AqlQuery::builder()
+		.query("INSERT @api_key INTO @@collection")
+		.bind_var("@collection", API_KEYS_COLLECTION)
+		.bind_var(
+			"api_key",
+			json!({
+				"name": name,
+				"roles": roles,
+				"expires_at": expires_at,
+				"tenant": tenant,
+				"key_hash": hash_api_key(&key),
+			}),
+		)
+		.build();
+
+	let _inserted: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(key)
+}
+
+/// Remove an API key, returning whether it existed
+pub async fn revoke_api_key(name: String) -> Result<bool, Error> {
+	ensure_collection(API_KEYS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR api_key IN @@collection
+				FILTER api_key.name == @name
+				REMOVE api_key IN @@collection
+				RETURN OLD.name",
+		)
+		.bind_var("@collection", API_KEYS_COLLECTION)
+		.bind_var("name", name)
+		.build();
+
+	let removed: Vec<String> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(!removed.is_empty())
+}
@@ -0,0 +1,177 @@
+use std::cell::RefCell;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use chrono::{DateTime, Utc};
+use juniper::{Executor, LookAheadMethods, ScalarValue};
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::schema::context::Context;
+use crate::lib::database::backend::{record_query_timings, QueryTiming};
+use crate::lib::CONFIG;
+
+tokio::task_local! {
+	static RESOLVERS: RefCell<Vec<ResolverTiming>>;
+}
+
+/// The timing of a root field resolver
+struct ResolverTiming {
+	path: String,
+	parent_type: &'static str,
+	field_name: String,
+	return_type: String,
+	started: Instant,
+	duration: Duration,
+}
+
+/// The timings of a request in the Apollo tracing format, returned in `extensions.tracing`
+pub struct ApolloTrace {
+	start_time: DateTime<Utc>,
+	started: Instant,
+	duration: Duration,
+	resolvers: Vec<ResolverTiming>,
+	queries: Vec<QueryTiming>,
+}
+
+/// Run the execution of a request, timing its resolvers and queries when tracing is enabled
+pub async fn trace_request<F: Future>(f: F) -> (F::Output, Option<ApolloTrace>) {
+	if !CONFIG.graphql_tracing {
+		return (f.await, None);
+	}
+
+	let start_time = Utc::now();
+	let started = Instant::now();
+
+	RESOLVERS
+		.scope(RefCell::new(Vec::new()), async {
+			let (output, queries) = record_query_timings(f).await;
+			let resolvers = RESOLVERS.with(|resolvers| resolvers.take());
+
+			let trace = ApolloTrace {
+				start_time,
+				started,
+				duration: started.elapsed(),
+				resolvers,
+				queries,
+			};
+
+			(output, Some(trace))
+		})
+		.await
+}
+
+/// Run a root field resolver, timed when the request is traced
+pub async fn trace_resolver<S, F>(
+	executor: &Executor<'_, '_, Context, S>,
+	parent_type: &'static str,
+	field_name: &str,
+	f: F,
+) -> F::Output
+where
+	S: ScalarValue,
+	F: Future,
+{
+	if RESOLVERS.try_with(|_| ()).is_err() {
+		return f.await;
+	}
+
+	let started = Instant::now();
+	let output = f.await;
+
+	let return_type = executor
+		.schema()
+		.concrete_type_by_name(parent_type)
+		.and_then(|parent| parent.field_by_name(field_name))
+		.map(|field| field.field_type.to_string())
+		.unwrap_or_default();
+
+	let timing = ResolverTiming {
+		path: executor.look_ahead().field_name().to_string(),
+		parent_type,
+		field_name: field_name.to_string(),
+		return_type,
+		started,
+		duration: started.elapsed(),
+	};
+
+	let _ = RESOLVERS.try_with(|resolvers| resolvers.borrow_mut().push(timing));
+
+	output
+}
+
+/// Durations are reported in nanoseconds
+fn nanos(duration: Duration) -> u64 {
+	duration.as_nanos() as u64
+}
+
+impl ApolloTrace {
+	/// Nanoseconds since the start of the request
+	fn offset(&self, instant: Instant) -> u64 {
+		nanos(instant.saturating_duration_since(self.started))
+	}
+
+	pub fn to_json(&self) -> JsonValue {
+		let end_time = self.start_time
+			+ chrono::Duration::from_std(self.duration)
+				.unwrap_or_else(|_| chrono::Duration::zero());
+
+		let resolvers: Vec<JsonValue> = self
+			.resolvers
+			.iter()
+			.map(|resolver| {
+				json!({
+					"path": [resolver.path],
+					"parentType": resolver.parent_type,
+					"fieldName": resolver.field_name,
+					"returnType": resolver.return_type,
+					"startOffset": self.offset(resolver.started),
+					"duration": nanos(resolver.duration),
+				})
+			})
+			.collect();
+
+		// The AQL queries are not part of the Apollo format, they are listed next to it
+		let queries: Vec<JsonValue> = self
+			.queries
+			.iter()
+			.map(|query| {
+				json!({
+					"tag": query.tag,
+					"startOffset": self.offset(query.started),
+					"duration": nanos(query.query_time + query.decode_time),
+					"queryDuration": nanos(query.query_time),
+					"decodeDuration": nanos(query.decode_time),
+				})
+			})
+			.collect();
+
+		json!({
+			"version": 1,
+			"startTime": self.start_time.to_rfc3339(),
+			"endTime": end_time.to_rfc3339(),
+			"duration": nanos(self.duration),
+			"execution": { "resolvers": resolvers },
+			"queries": queries,
+		})
+	}
+
+	/// Add the trace to the extensions of a response, or of every response of a batch
+	pub fn attach(&self, response: &mut JsonValue) {
+		let trace = self.to_json();
+
+		let responses = match response {
+			JsonValue::Array(responses) => responses.iter_mut().collect(),
+			response => vec![response],
+		};
+
+		for response in responses {
+			if let Some(response) = response.as_object_mut() {
+				let extensions = response.entry("extensions").or_insert_with(|| json!({}));
+
+				if let Some(extensions) = extensions.as_object_mut() {
+					extensions.insert("tracing".to_string(), trace.clone());
+				}
+			}
+		}
+	}
+}
@@ -0,0 +1,161 @@
+use juniper::{Arguments, Executor, ScalarValue};
+use rust_arango::{AqlQuery, ClientError};
+use serde::Deserialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::api::schema::context::Context;
+use crate::api::schema::inputs::JsonInput;
+use crate::lib::database::DATABASE;
+use crate::lib::CONFIG;
+
+/// Collection recording every mutation of the generated API
+pub const AUDIT_COLLECTION: &str = "alchemy_audit";
+
+const REDACTED: &str = "[REDACTED]";
+
+/// A recorded mutation
+#[derive(Deserialize, GraphQLObject)]
+pub struct AuditEntry {
+	pub operation: String,
+	/// JSON encoded arguments, with the redacted properties replaced
+	pub arguments: String,
+	pub actor: Option<String>,
+	pub status: String,
+	pub error: Option<String>,
+	pub timestamp: String,
+}
+
+/// Filters of the audit log search, every one of them is optional
+#[derive(GraphQLInputObject, Default)]
+pub struct AuditFilter {
+	pub operation: Option<String>,
+	pub actor: Option<String>,
+	pub status: Option<String>,
+	/// ISO 8601 dates bounding the timestamps
+	pub since: Option<String>,
+	pub until: Option<String>,
+}
+
+/// Replace the values of the redacted properties, at any depth
+fn redact(value: &mut JsonValue) {
+	match value {
+		JsonValue::Object(fields) => {
+			for (name, value) in fields.iter_mut() {
+				let redacted = CONFIG
+					.audit_redacted_arguments
+					.iter()
+					.any(|redacted| redacted.eq_ignore_ascii_case(name));
+
+				if redacted {
+					*value = JsonValue::String(String::from(REDACTED));
+				} else {
+					redact(value);
+				}
+			}
+		}
+		JsonValue::Array(items) => items.iter_mut().for_each(redact),
+		_ => {}
+	}
+}
+
+/// The arguments of a mutation field, as declared by the schema
+fn collect_arguments<S>(
+	executor: &Executor<Context, S>,
+	field_name: &str,
+	arguments: &Arguments<S>,
+) -> JsonValue
+where
+	S: ScalarValue,
+{
+	let names = executor
+		.schema()
+		.concrete_type_by_name("Mutation")
+		.and_then(|meta| meta.field_by_name(field_name))
+		.and_then(|field| field.arguments.as_ref())
+		.map(|arguments| {
+			arguments
+				.iter()
+				.map(|argument| argument.name.clone())
+				.collect::<Vec<_>>()
+		})
+		.unwrap_or_default();
+
+	let mut values = JsonMap::new();
+
+	for name in names {
+		if let Some(JsonInput(value)) = arguments.get::<JsonInput>(&name) {
+			values.insert(name, value);
+		}
+	}
+
+	let mut values = JsonValue::Object(values);
+	redact(&mut values);
+
+	values
+}
+
+/// Record a mutation in the audit log, failures are logged but do not fail the mutation
+pub async fn record_audit<S>(
+	executor: &Executor<'_, '_, Context, S>,
+	field_name: &str,
+	arguments: &Arguments<'_, S>,
+	error: Option<String>,
+) where
+	S: ScalarValue,
+{
+	let actor = executor
+		.context()
+		.claims
+		.as_ref()
+		.and_then(|claims| claims.user_id.clone());
+
+	let query = AqlQuery::builder()
+		.query("INSERT MERGE(@entry, { timestamp: DATE_ISO8601(DATE_NOW()) }) INTO @@collection")
+		.bind_var("@collection", AUDIT_COLLECTION)
+		.bind_var(
+			"entry",
+			json!({
+				"operation": field_name,
+				"arguments": collect_arguments(executor, field_name, arguments).to_string(),
+				"actor": actor,
+				"status": if error.is_some() { "error" } else { "success" },
+				"error": error,
+			}),
+		)
+		.build();
+
+	let result: Result<Vec<JsonValue>, _> = DATABASE.get().await.database.aql_query(query).await;
+
+	if let Err(e) = result {
+		tracing::error!(operation = %field_name, error = ?e, "Failed to record the mutation in the audit log");
+	}
+}
+
+/// Search the audit log, most recent entries first
+pub async fn search_audit_log(
+	filter: AuditFilter,
+	limit: i32,
+) -> Result<Vec<AuditEntry>, ClientError> {
+	let query = AqlQuery::builder()
+		.query(
+			"FOR entry IN @@collection
+				FILTER @operation == null OR entry.operation == @operation
+				FILTER @actor == null OR entry.actor == @actor
+				FILTER @status == null OR entry.status == @status
+				FILTER @since == null OR entry.timestamp >= @since
+				FILTER @until == null OR entry.timestamp <= @until
+				SORT entry.timestamp DESC
+				LIMIT @limit
+				RETURN entry",
+		)
+		.bind_var("@collection", AUDIT_COLLECTION)
+		.bind_var("operation", json!(filter.operation))
+		.bind_var("actor", json!(filter.actor))
+		.bind_var("status", json!(filter.status))
+		.bind_var("since", json!(filter.since))
+		.bind_var("until", json!(filter.until))
+		.bind_var("limit", limit.max(0))
+		.build();
+
+	DATABASE.get().await.database.aql_query(query).await
+}
@@ -0,0 +1,442 @@
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::time::{Duration, Instant};
+
+use actix_web::{
+	dev::{ServiceRequest, ServiceResponse},
+	error::InternalError,
+	http::header::{AUTHORIZATION, WWW_AUTHENTICATE},
+	Error as ActixError, HttpMessage, HttpResponse as ActixResponse,
+};
+use jsonwebtoken::{decode, decode_header, Algorithm, DecodingKey, Validation};
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tokio::sync::RwLock;
+
+use crate::api::api_keys::verify_api_key;
+use crate::api::auth_webhook::{forwarded_headers, verify_with_webhook};
+use crate::api::oidc::{mapped_roles, provider};
+use crate::api::rate_limit::{
+	charge_api_key, charge_ip, check_role_budget, client_ip, too_many_requests, ChargedRequest,
+	API_KEY_HEADER,
+};
+use crate::api::sessions::session_claims;
+use crate::lib::CONFIG;
+
+/// The JWKS is fetched again for an unknown key at most this often
+const JWKS_MIN_REFRESH: Duration = Duration::from_secs(60);
+
+lazy_static! {
+	static ref JWKS: RwLock<JwksCache> = RwLock::new(JwksCache::default());
+}
+
+/// The verified claims of a request
+#[derive(Clone, Debug, Default)]
+pub struct Claims {
+	pub user_id: Option<String>,
+	pub roles: Vec<String>,
+	pub tenant: Option<String>,
+	/// The verified API key, by name, configured keys are named after their hash
+	pub api_key: Option<String>,
+	/// Every claim of the token
+	pub claims: JsonMap<String, JsonValue>,
+}
+
+impl Claims {
+	/// The claims of a request without credentials
+	pub fn anonymous(role: &str) -> Self {
+		Claims {
+			roles: vec![role.to_string()],
+			..Default::default()
+		}
+	}
+
+	/// Map the claims of a token, or of the auth webhook, using the configured claim names
+	pub fn from_token(claims: JsonMap<String, JsonValue>) -> Self {
+		let mut roles: Vec<String> = match claims.get(&CONFIG.jwt_roles_claim) {
+			Some(JsonValue::Array(roles)) => roles
+				.iter()
+				.filter_map(|role| role.as_str().map(String::from))
+				.collect(),
+			Some(JsonValue::String(roles)) => roles
+				.split(',')
+				.map(|role| role.trim().to_string())
+				.filter(|role| !role.is_empty())
+				.collect(),
+			_ => Vec::new(),
+		};
+
+		for role in mapped_roles(&claims) {
+			if !roles.contains(&role) {
+				roles.push(role);
+			}
+		}
+
+		Claims {
+			user_id: claim_to_string(claims.get(&CONFIG.jwt_user_id_claim)),
+			roles,
+			tenant: claim_to_string(claims.get(&CONFIG.jwt_tenant_claim)),
+			api_key: None,
+			claims,
+		}
+	}
+}
+
+fn claim_to_string(claim: Option<&JsonValue>) -> Option<String> {
+	match claim? {
+		JsonValue::String(value) => Some(value.clone()),
+		JsonValue::Number(value) => Some(value.to_string()),
+		_ => None,
+	}
+}
+
+#[derive(Debug)]
+pub enum AuthError {
+	MissingToken,
+	InvalidToken(String),
+	UnknownKey,
+	InvalidApiKey,
+	Webhook(String),
+}
+
+/// Why a client is refused
+#[derive(Debug)]
+pub enum AccessError {
+	Unauthenticated(AuthError),
+	/// The client ran out of requests, it may retry once the duration elapsed
+	RateLimited(Duration),
+}
+
+/// The credentials of a client, from the headers of a request or the connection_init payload of a
+/// subscription
+#[derive(Default)]
+pub struct Credentials {
+	pub authorization: Option<String>,
+	pub api_key: Option<String>,
+	/// Sent to the auth webhook
+	pub headers: Vec<(String, String)>,
+	/// The claims of the session cookie, only read over HTTP where the CSRF token is checked
+	pub session: Option<Claims>,
+	/// The IP of the client
+	pub address: Option<String>,
+	/// Whether the IP is charged by the rate limit of the HTTP routes rather than here
+	pub ip_limited: bool,
+}
+
+impl fmt::Display for AuthError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			AuthError::MissingToken => write!(f, "Missing bearer token"),
+			AuthError::InvalidToken(reason) => write!(f, "Invalid token: {}", reason),
+			AuthError::UnknownKey => write!(f, "No key verifies the token"),
+			AuthError::InvalidApiKey => write!(f, "Invalid or expired API key"),
+			AuthError::Webhook(reason) => write!(f, "Auth webhook failed: {}", reason),
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct Jwk {
+	kid: Option<String>,
+	kty: String,
+	n: Option<String>,
+	e: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct JwkSet {
+	keys: Vec<Jwk>,
+}
+
+#[derive(Default)]
+struct JwksCache {
+	keys: HashMap<String, DecodingKey>,
+	fetched_at: Option<Instant>,
+}
+
+impl JwksCache {
+	fn find(&self, kid: Option<&str>) -> Option<DecodingKey> {
+		match kid {
+			Some(kid) => self.keys.get(kid).cloned(),
+			// Tokens without a key ID are only accepted from a single key set
+			None if self.keys.len() == 1 => self.keys.values().next().cloned(),
+			None => None,
+		}
+	}
+}
+
+async fn fetch_jwks(url: &str) -> reqwest::Result<HashMap<String, DecodingKey>> {
+	let set = reqwest::get(url)
+		.await?
+		.error_for_status()?
+		.json::<JwkSet>()
+		.await?;
+
+	Ok(set
+		.keys
+		.into_iter()
+		.filter(|jwk| jwk.kty == "RSA")
+		.filter_map(|jwk| {
+			let key = DecodingKey::from_rsa_components(jwk.n.as_ref()?, jwk.e.as_ref()?).ok()?;
+
+			Some((jwk.kid.unwrap_or_default(), key))
+		})
+		.collect())
+}
+
+/// The key of the JWKS matching the token, fetching the set again when it is unknown
+/// or older than the maximum age, so that rotated keys are picked up and dropped
+async fn jwks_key(url: &str, kid: Option<&str>) -> Result<DecodingKey, AuthError> {
+	let max_age = Duration::from_secs(CONFIG.jwt_jwks_max_age_secs);
+
+	{
+		let cache = JWKS.read().await;
+		let expired = cache
+			.fetched_at
+			.map(|fetched_at| fetched_at.elapsed() >= max_age)
+			.unwrap_or(true);
+
+		if let (Some(key), false) = (cache.find(kid), expired) {
+			return Ok(key);
+		}
+	}
+
+	let mut cache = JWKS.write().await;
+
+	let stale = cache
+		.fetched_at
+		.map(|fetched_at| {
+			let elapsed = fetched_at.elapsed();
+
+			elapsed >= max_age || (cache.find(kid).is_none() && elapsed >= JWKS_MIN_REFRESH)
+		})
+		.unwrap_or(true);
+
+	if stale {
+		match fetch_jwks(url).await {
+			Ok(keys) => cache.keys = keys,
+			Err(e) => tracing::warn!(url = %url, reason = %e, "Failed to fetch the JWKS"),
+		}
+
+		cache.fetched_at = Some(Instant::now());
+	}
+
+	cache.find(kid).ok_or(AuthError::UnknownKey)
+}
+
+fn algorithm() -> Algorithm {
+	match (CONFIG.jwt_algorithm, &CONFIG.jwt_secret) {
+		(Some(algorithm), _) => algorithm,
+		(None, Some(_)) => Algorithm::HS256,
+		// Keys of a JWKS, discovered or not, are RSA keys
+		(None, None) => Algorithm::RS256,
+	}
+}
+
+async fn decoding_key(algorithm: Algorithm, kid: Option<&str>) -> Result<DecodingKey, AuthError> {
+	if let Some(secret) = &CONFIG.jwt_secret {
+		let key = match algorithm {
+			Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+				Ok(DecodingKey::from_secret(secret.as_bytes()))
+			}
+			Algorithm::ES256 | Algorithm::ES384 => DecodingKey::from_ec_pem(secret.as_bytes()),
+			Algorithm::EdDSA => DecodingKey::from_ed_pem(secret.as_bytes()),
+			_ => DecodingKey::from_rsa_pem(secret.as_bytes()),
+		};
+
+		return key.map_err(|e| AuthError::InvalidToken(e.to_string()));
+	}
+
+	if let Some(url) = &CONFIG.jwt_jwks_url {
+		return jwks_key(url, kid).await;
+	}
+
+	match provider().await {
+		Some(provider) => jwks_key(&provider.jwks_uri, kid).await,
+		None => Err(AuthError::UnknownKey),
+	}
+}
+
+/// Verify the signature and the registered claims of a JWT
+pub async fn verify_token(token: &str) -> Result<Claims, AuthError> {
+	let header = decode_header(token).map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+	let algorithm = algorithm();
+
+	let key = decoding_key(algorithm, header.kid.as_deref()).await?;
+
+	let mut validation = Validation::new(algorithm);
+
+	let issuer = match &CONFIG.jwt_issuer {
+		Some(issuer) => Some(issuer.clone()),
+		None => provider().await.map(|provider| provider.issuer),
+	};
+
+	if let Some(issuer) = issuer {
+		validation.set_issuer(&[issuer]);
+	}
+
+	if let Some(audience) = CONFIG
+		.jwt_audience
+		.as_ref()
+		.or(CONFIG.oidc_client_id.as_ref())
+	{
+		validation.set_audience(&[audience]);
+	}
+
+	let data = decode::<JsonMap<String, JsonValue>>(token, &key, &validation)
+		.map_err(|e| AuthError::InvalidToken(e.to_string()))?;
+
+	Ok(Claims::from_token(data.claims))
+}
+
+/// The token of a `Bearer` authorization value
+pub fn bearer_token(authorization: &str) -> Option<&str> {
+	let (scheme, token) = authorization.trim().split_once(' ')?;
+
+	if scheme.eq_ignore_ascii_case("bearer") && !token.trim().is_empty() {
+		Some(token.trim())
+	} else {
+		None
+	}
+}
+
+/// Authenticate with the API key when one is sent, with the bearer token otherwise,
+/// then with the session cookie, requests without credentials get the anonymous role when there is one
+pub async fn authenticate(
+	authorization: Option<&str>,
+	api_key: Option<&str>,
+	session: Option<Claims>,
+) -> Result<Claims, AuthError> {
+	match (api_key, authorization.and_then(bearer_token)) {
+		(Some(api_key), _) => verify_api_key(api_key).await,
+		(None, Some(token)) => verify_token(token).await,
+		(None, None) => match (session, &CONFIG.anonymous_role) {
+			(Some(claims), _) => Ok(claims),
+			(None, Some(role)) => Ok(Claims::anonymous(role)),
+			(None, None) => Err(AuthError::MissingToken),
+		},
+	}
+}
+
+/// Authenticate a client, with the auth webhook or its credentials, and charge it to its budgets,
+/// HTTP, the subscriptions and gRPC all go through it so that they enforce the same policy.
+/// A client sending an API key is charged to the budget of the verified key, the others to their
+/// IP, `None` when authentication is disabled
+pub async fn authorize(credentials: Credentials) -> Result<Option<Claims>, AccessError> {
+	let address = credentials.address.as_deref();
+	// The key is only verified when authentication is enabled, it identifies no one otherwise
+	let keyed = CONFIG.auth_enabled() && credentials.api_key.is_some();
+
+	if !keyed && !credentials.ip_limited {
+		charge_ip(address).map_err(AccessError::RateLimited)?;
+	}
+
+	if !CONFIG.auth_enabled() {
+		return Ok(None);
+	}
+
+	let result = match &CONFIG.auth_webhook_url {
+		Some(url) => verify_with_webhook(url, credentials.headers).await,
+		None => {
+			authenticate(
+				credentials.authorization.as_deref(),
+				credentials.api_key.as_deref(),
+				credentials.session,
+			)
+			.await
+		}
+	};
+
+	let claims = match result {
+		Ok(claims) => claims,
+		Err(e) => {
+			// A refused key costs a token of the IP, guessing keys is then limited like any
+			// anonymous request
+			if keyed && !credentials.ip_limited {
+				charge_ip(address).map_err(AccessError::RateLimited)?;
+			}
+
+			return Err(AccessError::Unauthenticated(e));
+		}
+	};
+
+	if keyed {
+		charge_api_key(&claims, address).map_err(AccessError::RateLimited)?;
+	}
+
+	let client = claims
+		.user_id
+		.as_deref()
+		.or(address)
+		.unwrap_or_default()
+		.to_string();
+
+	check_role_budget(Some(&claims), &client).map_err(AccessError::RateLimited)?;
+
+	Ok(Some(claims))
+}
+
+/// Verify the API key or the bearer token of the request when authentication is enabled,
+/// the claims are then available in the request extensions
+pub async fn with_authentication<B, F>(
+	req: ServiceRequest,
+	call: impl FnOnce(ServiceRequest) -> F,
+) -> Result<ServiceResponse<B>, ActixError>
+where
+	F: Future<Output = Result<ServiceResponse<B>, ActixError>>,
+{
+	if !CONFIG.auth_enabled() {
+		return call(req).await;
+	}
+
+	let header = |name| {
+		req.headers()
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.map(String::from)
+	};
+
+	let credentials = Credentials {
+		authorization: header(AUTHORIZATION.as_str()),
+		api_key: header(API_KEY_HEADER),
+		headers: forwarded_headers(req.headers()),
+		session: session_claims(&req),
+		address: client_ip(&req),
+		ip_limited: true,
+	};
+
+	let keyed = credentials.api_key.is_some();
+
+	match authorize(credentials).await {
+		Ok(claims) => {
+			// The verified key was charged, `with_rate_limit` does not charge the IP again
+			if keyed {
+				req.extensions_mut().insert(ChargedRequest);
+			}
+
+			if let Some(claims) = claims {
+				req.extensions_mut().insert(claims);
+			}
+
+			call(req).await
+		}
+		Err(AccessError::RateLimited(retry_after)) => Err(too_many_requests(retry_after)),
+		Err(AccessError::Unauthenticated(e)) => {
+			tracing::warn!(reason = %e, "Unauthenticated request");
+
+			let mut response = ActixResponse::Unauthorized();
+
+			response.insert_header((WWW_AUTHENTICATE, "Bearer"));
+
+			let response = if CONFIG.error_details() {
+				response.body(e.to_string())
+			} else {
+				response.finish()
+			};
+
+			Err(InternalError::from_response(e.to_string(), response).into())
+		}
+	}
+}
@@ -0,0 +1,86 @@
+use std::time::Duration;
+
+use actix_web::http::header::{self, HeaderMap};
+use lazy_static::lazy_static;
+use reqwest::{Client, StatusCode};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::api::auth::{AuthError, Claims};
+use crate::lib::config::AuthWebhookMethod;
+use crate::lib::CONFIG;
+
+/// Headers describing the connection rather than the client, they are not forwarded
+const SKIPPED_HEADERS: [header::HeaderName; 5] = [
+	header::HOST,
+	header::CONNECTION,
+	header::CONTENT_LENGTH,
+	header::CONTENT_TYPE,
+	header::TRANSFER_ENCODING,
+];
+
+lazy_static! {
+	static ref CLIENT: Client = Client::builder()
+		.timeout(Duration::from_millis(CONFIG.auth_webhook_timeout_ms))
+		.build()
+		.expect("Failed to build the auth webhook client");
+}
+
+/// Whether a header of a request is sent to the webhook
+pub fn is_forwarded(name: &str) -> bool {
+	!SKIPPED_HEADERS
+		.iter()
+		.any(|skipped| skipped.as_str() == name)
+}
+
+/// The headers of a request that are sent to the webhook
+pub fn forwarded_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+	headers
+		.iter()
+		.filter(|(name, _)| is_forwarded(name.as_str()))
+		.filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+		.collect()
+}
+
+/// Ask the auth webhook for the identity of a request, it answers with a JSON object of claims
+/// named like the JWT claims, and with 401 or 403 when the request is not authenticated
+pub async fn verify_with_webhook(
+	url: &str,
+	headers: Vec<(String, String)>,
+) -> Result<Claims, AuthError> {
+	let request = match CONFIG.auth_webhook_method {
+		AuthWebhookMethod::Get => headers
+			.iter()
+			.fold(CLIENT.get(url), |request, (name, value)| {
+				request.header(name.as_str(), value.as_str())
+			}),
+		AuthWebhookMethod::Post => {
+			let headers: JsonMap<String, JsonValue> = headers
+				.into_iter()
+				.map(|(name, value)| (name, JsonValue::String(value)))
+				.collect();
+
+			CLIENT.post(url).json(&json!({ "headers": headers }))
+		}
+	};
+
+	let response = request.send().await.map_err(|e| {
+		tracing::error!(url = %url, error = %e, "Failed to call the auth webhook");
+
+		AuthError::Webhook(String::from("unavailable"))
+	})?;
+
+	match response.status() {
+		StatusCode::OK => match response.json::<JsonValue>().await {
+			Ok(JsonValue::Object(claims)) => Ok(Claims::from_token(claims)),
+			_ => Err(AuthError::Webhook(String::from("invalid response"))),
+		},
+		StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => {
+			Err(AuthError::Webhook(String::from("rejected")))
+		}
+		status => {
+			tracing::error!(url = %url, status = %status, "Unexpected status of the auth webhook");
+
+			Err(AuthError::Webhook(format!("status {}", status.as_u16())))
+		}
+	}
+}
@@ -0,0 +1,144 @@
+use std::cell::RefCell;
+use std::collections::{HashMap, HashSet};
+use std::future::Future;
+use std::sync::Mutex;
+use std::time::Instant;
+
+use lazy_static::lazy_static;
+use sha2::{Digest, Sha256};
+
+use crate::api::query_cache::{clear_query_results, invalidate_query_results};
+use crate::lib::reload::runtime;
+use crate::lib::CONFIG;
+
+lazy_static! {
+	static ref RESPONSES: Mutex<HashMap<String, CachedResponse>> = Mutex::new(HashMap::new());
+}
+
+tokio::task_local! {
+	static TRACE: RefCell<ExecutionTrace>;
+}
+
+/// What a request did while it was executed
+#[derive(Default, Debug)]
+pub struct ExecutionTrace {
+	/// The root fields that were resolved
+	pub fields: HashSet<String>,
+	/// Every collection the resolved fields may have read
+	pub collections: HashSet<String>,
+	pub mutated: bool,
+}
+
+struct CachedResponse {
+	body: Vec<u8>,
+	collections: HashSet<String>,
+	expires_at: Instant,
+}
+
+/// Run a future while tracing the fields and collections it resolves
+pub async fn trace_execution<F: Future>(f: F) -> (F::Output, ExecutionTrace) {
+	TRACE
+		.scope(RefCell::new(ExecutionTrace::default()), async {
+			let output = f.await;
+			let trace = TRACE.with(|trace| trace.take());
+
+			(output, trace)
+		})
+		.await
+}
+
+/// Record a root field resolved by the current request and the collections it depends on
+pub fn record_read<'a>(field: &str, collections: impl Iterator<Item = &'a String>) {
+	let _ = TRACE.try_with(|trace| {
+		let mut trace = trace.borrow_mut();
+
+		trace.fields.insert(field.to_string());
+		trace.collections.extend(collections.cloned());
+	});
+}
+
+pub fn record_mutation() {
+	let _ = TRACE.try_with(|trace| trace.borrow_mut().mutated = true);
+}
+
+/// The cache key of a request, responses are only shared within a scope of identical roles and claims
+pub fn response_cache_key(source: &str, scope: Option<&str>) -> String {
+	let mut hasher = Sha256::new();
+
+	hasher.update(scope.unwrap_or_default().as_bytes());
+	hasher.update([0]);
+	hasher.update(source.as_bytes());
+
+	hex::encode(hasher.finalize())
+}
+
+pub fn get_cached_response(key: &str) -> Option<Vec<u8>> {
+	let mut responses = RESPONSES.lock().unwrap();
+
+	match responses.get(key) {
+		Some(response) if response.expires_at > Instant::now() => Some(response.body.clone()),
+		Some(_) => {
+			responses.remove(key);
+
+			None
+		}
+		None => None,
+	}
+}
+
+/// Cache the response of a request that only read data, for the shortest TTL of its root fields
+pub fn store_response(key: String, body: Vec<u8>, trace: ExecutionTrace) {
+	if trace.mutated || trace.fields.is_empty() {
+		return;
+	}
+
+	let settings = runtime();
+
+	let ttl = trace
+		.fields
+		.iter()
+		.filter_map(|field| settings.response_cache_ttls.get(field))
+		.min()
+		.cloned()
+		.unwrap_or(settings.response_cache_ttl);
+
+	if ttl.is_zero() {
+		return;
+	}
+
+	let now = Instant::now();
+	let mut responses = RESPONSES.lock().unwrap();
+
+	if responses.len() >= CONFIG.response_cache_max_entries {
+		responses.retain(|_, response| response.expires_at > now);
+
+		if responses.len() >= CONFIG.response_cache_max_entries {
+			return;
+		}
+	}
+
+	responses.insert(
+		key,
+		CachedResponse {
+			body,
+			collections: trace.collections,
+			expires_at: now + ttl,
+		},
+	);
+}
+
+/// Drop every cached response, after the schema changed
+pub fn clear_responses() {
+	RESPONSES.lock().unwrap().clear();
+	clear_query_results();
+}
+
+/// Drop the cached responses that depend on a collection
+pub fn invalidate_collection(collection: &str) {
+	RESPONSES
+		.lock()
+		.unwrap()
+		.retain(|_, response| !response.collections.contains(collection));
+
+	invalidate_query_results(collection);
+}
@@ -0,0 +1,212 @@
+use std::collections::HashMap;
+use std::time::Duration;
+
+use actix_web::rt;
+use anyhow::{anyhow, Error};
+use lazy_static::lazy_static;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::api::cache::invalidate_collection;
+use crate::lib::changes::{publish_change, ChangeEvent, ChangeOperation};
+use crate::lib::tenants::schema_collections;
+use crate::lib::CONFIG;
+
+/// WAL marker of an inserted or replaced document
+const DOCUMENT_OPERATION: i64 = 2300;
+/// WAL marker of a removed document
+const DOCUMENT_REMOVE: i64 = 2302;
+
+lazy_static! {
+	static ref CLIENT: Client = Client::new();
+}
+
+#[derive(Deserialize)]
+struct LastTick {
+	tick: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CollectionInfo {
+	name: String,
+	globally_unique_id: String,
+}
+
+#[derive(Deserialize)]
+struct CollectionList {
+	result: Vec<CollectionInfo>,
+}
+
+/// A line of the WAL tail
+#[derive(Deserialize)]
+struct WalMarker {
+	#[serde(rename = "type")]
+	kind: i64,
+	cuid: Option<String>,
+	#[serde(default)]
+	data: JsonValue,
+}
+
+/// Tails the write-ahead log of the database to notice the changes of every writer
+struct ChangeFeed {
+	tick: String,
+	/// Collection names by globally unique id, the WAL only refers to the ids
+	collections: HashMap<String, String>,
+}
+
+fn api_url(path: &str) -> String {
+	format!(
+		"{}/_db/{}/_api/{}",
+		CONFIG.db_host.trim_end_matches('/'),
+		CONFIG.db_name,
+		path
+	)
+}
+
+async fn get(path: &str, query: &[(&str, &str)]) -> Result<reqwest::Response, Error> {
+	let response = CLIENT
+		.get(api_url(path))
+		.basic_auth(&CONFIG.db_user, Some(&CONFIG.db_pass))
+		.query(query)
+		.send()
+		.await?;
+
+	if !response.status().is_success() {
+		return Err(anyhow!(
+			"ArangoDB answered {} to {}",
+			response.status(),
+			path
+		));
+	}
+
+	Ok(response)
+}
+
+impl ChangeFeed {
+	/// Start from the current end of the WAL, earlier changes are not replayed
+	async fn new() -> Result<ChangeFeed, Error> {
+		let last: LastTick = get("wal/lastTick", &[]).await?.json().await?;
+
+		let mut feed = ChangeFeed {
+			tick: last.tick,
+			collections: HashMap::new(),
+		};
+
+		feed.load_collections().await?;
+
+		Ok(feed)
+	}
+
+	async fn load_collections(&mut self) -> Result<(), Error> {
+		let list: CollectionList = get("collection", &[("excludeSystem", "true")])
+			.await?
+			.json()
+			.await?;
+
+		self.collections = list
+			.result
+			.into_iter()
+			.map(|collection| (collection.globally_unique_id, collection.name))
+			.collect();
+
+		Ok(())
+	}
+
+	async fn collection_name(&mut self, cuid: &str) -> Result<Option<String>, Error> {
+		if !self.collections.contains_key(cuid) {
+			self.load_collections().await?;
+		}
+
+		Ok(self.collections.get(cuid).cloned())
+	}
+
+	/// Read the changes since the last poll and publish them
+	async fn poll(&mut self) -> Result<(), Error> {
+		let response = get("wal/tail", &[("from", self.tick.as_str())]).await?;
+
+		let last_included = response
+			.headers()
+			.get("x-arango-replication-lastincluded")
+			.and_then(|value| value.to_str().ok())
+			.filter(|tick| *tick != "0")
+			.map(str::to_string);
+
+		let body = response.text().await?;
+
+		for line in body.lines().filter(|line| !line.is_empty()) {
+			let marker: WalMarker = serde_json::from_str(line)?;
+
+			let operation = match marker.kind {
+				DOCUMENT_OPERATION => ChangeOperation::Update,
+				DOCUMENT_REMOVE => ChangeOperation::Remove,
+				_ => continue,
+			};
+
+			let (cuid, key) = match (&marker.cuid, marker.data["_key"].as_str()) {
+				(Some(cuid), Some(key)) => (cuid, key),
+				_ => continue,
+			};
+
+			// System and Alchemy collections are not exposed by the generated API
+			let collection = match self.collection_name(cuid).await? {
+				Some(name) if !name.starts_with("alchemy_") => name,
+				_ => continue,
+			};
+
+			// The caches refer to the collections of the schema, without the tenant prefix
+			for name in schema_collections(&collection) {
+				invalidate_collection(&name);
+			}
+
+			publish_change(ChangeEvent {
+				collection,
+				key: key.to_string(),
+				operation,
+			});
+		}
+
+		if let Some(tick) = last_included {
+			self.tick = tick;
+		}
+
+		Ok(())
+	}
+}
+
+/// Whether the subscriptions and the cache follow the WAL rather than the mutations of Alchemy
+pub fn change_feed_enabled() -> bool {
+	CONFIG.change_feed_enabled
+}
+
+/// Poll the WAL in the background, the changes made outside of Alchemy reach the subscriptions
+/// and invalidate the cached responses, inserts are published as updates since the WAL does not
+/// tell them apart
+pub fn start_change_feed() {
+	if !change_feed_enabled() {
+		return;
+	}
+
+	rt::spawn(async {
+		let mut feed = match ChangeFeed::new().await {
+			Ok(feed) => feed,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to start the change feed");
+
+				return;
+			}
+		};
+
+		let mut interval =
+			rt::time::interval(Duration::from_millis(CONFIG.change_feed_interval_ms));
+
+		loop {
+			interval.tick().await;
+
+			if let Err(e) = feed.poll().await {
+				tracing::error!(error = %e, "Failed to poll the change feed");
+			}
+		}
+	});
+}
@@ -0,0 +1,105 @@
+use std::future::Future;
+
+use actix_web::{
+	body::{BodySize, MessageBody},
+	dev::{ServiceRequest, ServiceResponse},
+	http::header::{HeaderValue, ACCEPT_ENCODING, CONTENT_ENCODING, CONTENT_TYPE},
+	Error as ActixError,
+};
+
+use crate::lib::CONFIG;
+
+/// The encodings the compression middleware supports
+const ENCODINGS: [&str; 4] = ["br", "zstd", "gzip", "deflate"];
+
+/// The first enabled encoding the client accepts, the quality values only tell whether it does
+fn preferred_encoding(accept_encoding: &str) -> Option<&'static str> {
+	let accepted: Vec<&str> = accept_encoding
+		.split(',')
+		.filter_map(|item| {
+			let mut parameters = item.split(';');
+			let encoding = parameters.next()?.trim();
+
+			let quality = parameters
+				.find_map(|parameter| parameter.trim().strip_prefix("q="))
+				.and_then(|quality| quality.trim().parse::<f32>().ok())
+				.unwrap_or(1.0);
+
+			Some(encoding).filter(|_| quality > 0.0)
+		})
+		.collect();
+
+	CONFIG
+		.compression_algorithms
+		.iter()
+		.filter_map(|algorithm| {
+			ENCODINGS
+				.iter()
+				.find(|encoding| encoding.eq_ignore_ascii_case(algorithm.trim()))
+				.copied()
+		})
+		.find(|encoding| {
+			accepted
+				.iter()
+				.any(|accepted| accepted == encoding || *accepted == "*")
+		})
+}
+
+/// Narrow the encodings accepted by the client to the enabled one it prefers, before the
+/// compression middleware picks one
+pub async fn with_preferred_encoding<B, F>(
+	mut req: ServiceRequest,
+	call: impl FnOnce(ServiceRequest) -> F,
+) -> Result<ServiceResponse<B>, ActixError>
+where
+	F: Future<Output = Result<ServiceResponse<B>, ActixError>>,
+{
+	let encoding = req
+		.headers()
+		.get(ACCEPT_ENCODING)
+		.and_then(|value| value.to_str().ok())
+		.and_then(preferred_encoding)
+		.unwrap_or("identity");
+
+	req.headers_mut()
+		.insert(ACCEPT_ENCODING, HeaderValue::from_static(encoding));
+
+	call(req).await
+}
+
+/// Leave the responses too small to benefit from compression, and those of the excluded content
+/// types, uncompressed, the middleware does not encode a response that names its encoding
+pub async fn with_compression_rules<B, F>(
+	req: ServiceRequest,
+	call: impl FnOnce(ServiceRequest) -> F,
+) -> Result<ServiceResponse<B>, ActixError>
+where
+	B: MessageBody,
+	F: Future<Output = Result<ServiceResponse<B>, ActixError>>,
+{
+	let mut response = call(req).await?;
+
+	let too_small = match response.response().body().size() {
+		BodySize::Sized(size) => size < CONFIG.compression_min_size,
+		_ => false,
+	};
+
+	let excluded = response
+		.headers()
+		.get(CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+		.map_or(false, |content_type| {
+			CONFIG
+				.compression_excluded_types
+				.iter()
+				.any(|excluded| content_type.starts_with(excluded.as_str()))
+		});
+
+	if (too_small || excluded) && !response.headers().contains_key(CONTENT_ENCODING) {
+		response
+			.headers_mut()
+			.insert(CONTENT_ENCODING, HeaderValue::from_static("identity"));
+	}
+
+	Ok(response)
+}
@@ -0,0 +1,606 @@
+use std::convert::Infallible;
+use std::net::SocketAddr;
+
+use actix_web::{
+	web::{self, Data},
+	Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use bytes::{BufMut, Bytes, BytesMut};
+use convert_case::{Case, Casing};
+use hyper::header::{HeaderMap, HeaderValue, AUTHORIZATION, CONTENT_TYPE};
+use hyper::server::conn::AddrStream;
+use hyper::service::{make_service_fn, service_fn};
+use hyper::{Body, Request, Response, Server};
+use prost::Message;
+use prost_reflect::{DescriptorPool, DynamicMessage, SerializeOptions};
+use prost_types::field_descriptor_proto::{Label, Type as ProtoType};
+use prost_types::{
+	DescriptorProto, FieldDescriptorProto, FileDescriptorProto, MethodDescriptorProto,
+	ServiceDescriptorProto,
+};
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::auth::{authorize, AccessError, Credentials};
+use crate::api::auth_webhook::is_forwarded;
+use crate::api::rate_limit::API_KEY_HEADER;
+use crate::api::rest::execute_operation;
+use crate::api::schema::context::Context;
+use crate::api::schema::Schemas;
+use crate::lib::database::api::{DbEntity, DbScalarType};
+use crate::lib::schema::SchemaOperation;
+use crate::lib::CONFIG;
+use crate::meta::auth::require_admin;
+
+/// Package of the generated services, e.g. `alchemy.BookService`
+const PACKAGE: &str = "alchemy";
+
+/// gRPC status codes, see https://grpc.github.io/grpc/core/md_doc_statuscodes.html
+const OK: u16 = 0;
+const INVALID_ARGUMENT: u16 = 3;
+const NOT_FOUND: u16 = 5;
+const PERMISSION_DENIED: u16 = 7;
+const RESOURCE_EXHAUSTED: u16 = 8;
+const UNIMPLEMENTED: u16 = 12;
+const INTERNAL: u16 = 13;
+const UNAUTHENTICATED: u16 = 16;
+
+/// A field of a generated message
+struct ProtoField {
+	name: String,
+	proto_type: ProtoType,
+	/// Name of the message type, for `ProtoType::Message`
+	type_name: Option<String>,
+	repeated: bool,
+}
+
+impl ProtoField {
+	fn scalar(name: &str, proto_type: ProtoType) -> Self {
+		ProtoField {
+			name: name.to_string(),
+			proto_type,
+			type_name: None,
+			repeated: false,
+		}
+	}
+
+	fn message(name: &str, type_name: &str, repeated: bool) -> Self {
+		ProtoField {
+			name: name.to_string(),
+			proto_type: ProtoType::Message,
+			type_name: Some(type_name.to_string()),
+			repeated,
+		}
+	}
+
+	fn source_type(&self) -> &str {
+		match self.proto_type {
+			ProtoType::Int32 => "int32",
+			ProtoType::Double => "double",
+			ProtoType::Bool => "bool",
+			ProtoType::Message => self.type_name.as_deref().unwrap_or_default(),
+			_ => "string",
+		}
+	}
+}
+
+struct ProtoMessage {
+	name: String,
+	fields: Vec<ProtoField>,
+}
+
+struct ProtoMethod {
+	name: &'static str,
+	operation: SchemaOperation,
+	input: String,
+	output: String,
+}
+
+struct ProtoService {
+	/// The collection the service runs on
+	collection: String,
+	name: String,
+	methods: Vec<ProtoMethod>,
+}
+
+/// The messages and services generated from the entities
+struct ProtoFile {
+	messages: Vec<ProtoMessage>,
+	services: Vec<ProtoService>,
+}
+
+/// Objects and nested lists are sent as JSON strings, like GraphQL does
+fn property_field(name: &str, scalar_type: &DbScalarType) -> ProtoField {
+	let proto_type = |scalar_type: &DbScalarType| match scalar_type {
+		DbScalarType::Int => ProtoType::Int32,
+		DbScalarType::Float => ProtoType::Double,
+		DbScalarType::Boolean => ProtoType::Bool,
+		_ => ProtoType::String,
+	};
+
+	match scalar_type {
+		DbScalarType::Array(item) if !matches!(**item, DbScalarType::Array(_)) => ProtoField {
+			repeated: true,
+			..ProtoField::scalar(name, proto_type(item))
+		},
+		scalar_type => ProtoField::scalar(name, proto_type(scalar_type)),
+	}
+}
+
+fn entity_message(entity: &DbEntity, name: String, input: bool) -> ProtoMessage {
+	ProtoMessage {
+		name,
+		fields: entity
+			.properties
+			.iter()
+			.filter(|property| !input || (!property.managed && !property.readonly))
+			.map(|property| property_field(property.field_name(), &property.scalar_type))
+			.collect(),
+	}
+}
+
+fn proto_file(entities: Vec<(std::sync::Arc<DbEntity>, Vec<SchemaOperation>)>) -> ProtoFile {
+	let mut file = ProtoFile {
+		messages: Vec::new(),
+		services: Vec::new(),
+	};
+
+	for (entity, operations) in entities {
+		let name = entity.name.to_case(Case::Pascal);
+		let input = format!("{}Input", name);
+		let key_request = format!("{}KeyRequest", name);
+
+		file.messages
+			.push(entity_message(&entity, name.clone(), false));
+		file.messages
+			.push(entity_message(&entity, input.clone(), true));
+		file.messages.push(ProtoMessage {
+			name: key_request.clone(),
+			fields: vec![ProtoField::scalar("key", ProtoType::String)],
+		});
+		file.messages.push(ProtoMessage {
+			name: format!("List{}Request", name),
+			fields: vec![ProtoField::scalar("limit", ProtoType::Int32)],
+		});
+		file.messages.push(ProtoMessage {
+			name: format!("List{}Response", name),
+			fields: vec![ProtoField::message("items", &name, true)],
+		});
+		file.messages.push(ProtoMessage {
+			name: format!("Create{}Request", name),
+			fields: vec![ProtoField::message("data", &input, false)],
+		});
+		file.messages.push(ProtoMessage {
+			name: format!("Update{}Request", name),
+			fields: vec![
+				ProtoField::scalar("key", ProtoType::String),
+				ProtoField::message("data", &input, false),
+			],
+		});
+
+		let method = |name: &'static str, operation, input: String, output: String| ProtoMethod {
+			name,
+			operation,
+			input,
+			output,
+		};
+
+		let methods = vec![
+			method(
+				"Get",
+				SchemaOperation::Get,
+				key_request.clone(),
+				name.clone(),
+			),
+			method(
+				"List",
+				SchemaOperation::GetAll,
+				format!("List{}Request", name),
+				format!("List{}Response", name),
+			),
+			method(
+				"Create",
+				SchemaOperation::Create,
+				format!("Create{}Request", name),
+				name.clone(),
+			),
+			method(
+				"Update",
+				SchemaOperation::Update,
+				format!("Update{}Request", name),
+				name.clone(),
+			),
+			method("Delete", SchemaOperation::Delete, key_request, name.clone()),
+		];
+
+		file.services.push(ProtoService {
+			collection: entity.collection_name.clone(),
+			name: format!("{}Service", name),
+			methods: methods
+				.into_iter()
+				.filter(|method| operations.contains(&method.operation))
+				.collect(),
+		});
+	}
+
+	file
+}
+
+impl ProtoFile {
+	fn descriptor(&self) -> FileDescriptorProto {
+		let qualified = |name: &str| format!(".{}.{}", PACKAGE, name);
+
+		FileDescriptorProto {
+			name: Some(String::from("alchemy.proto")),
+			package: Some(PACKAGE.to_string()),
+			syntax: Some(String::from("proto3")),
+			message_type: self
+				.messages
+				.iter()
+				.map(|message| DescriptorProto {
+					name: Some(message.name.clone()),
+					field: message
+						.fields
+						.iter()
+						.enumerate()
+						.map(|(index, field)| FieldDescriptorProto {
+							name: Some(field.name.clone()),
+							json_name: Some(field.name.clone()),
+							number: Some(index as i32 + 1),
+							label: Some(match field.repeated {
+								true => Label::Repeated as i32,
+								false => Label::Optional as i32,
+							}),
+							r#type: Some(field.proto_type as i32),
+							type_name: field.type_name.as_deref().map(qualified),
+							..Default::default()
+						})
+						.collect(),
+					..Default::default()
+				})
+				.collect(),
+			service: self
+				.services
+				.iter()
+				.map(|service| ServiceDescriptorProto {
+					name: Some(service.name.clone()),
+					method: service
+						.methods
+						.iter()
+						.map(|method| MethodDescriptorProto {
+							name: Some(method.name.to_string()),
+							input_type: Some(qualified(&method.input)),
+							output_type: Some(qualified(&method.output)),
+							..Default::default()
+						})
+						.collect(),
+					..Default::default()
+				})
+				.collect(),
+			..Default::default()
+		}
+	}
+
+	/// The `.proto` source, for the code generators of the clients
+	fn source(&self) -> String {
+		let mut source = format!("syntax = \"proto3\";\n\npackage {};\n", PACKAGE);
+
+		for message in &self.messages {
+			source.push_str(&format!("\nmessage {} {{\n", message.name));
+
+			for (index, field) in message.fields.iter().enumerate() {
+				source.push_str(&format!(
+					"\t{}{} {} = {};\n",
+					if field.repeated { "repeated " } else { "" },
+					field.source_type(),
+					field.name,
+					index + 1
+				));
+			}
+
+			source.push_str("}\n");
+		}
+
+		for service in &self.services {
+			source.push_str(&format!("\nservice {} {{\n", service.name));
+
+			for method in &service.methods {
+				source.push_str(&format!(
+					"\trpc {}({}) returns ({});\n",
+					method.name, method.input, method.output
+				));
+			}
+
+			source.push_str("}\n");
+		}
+
+		source
+	}
+}
+
+/// The `.proto` of the generated services, served like the SDL
+pub async fn proto_route(
+	req: ActixRequest,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	if !CONFIG.introspection_enabled() {
+		require_admin(&req)?;
+	}
+
+	Ok(ActixResponse::Ok()
+		.content_type("text/plain; charset=utf-8")
+		.body(proto_file(schemas.entities()).source()))
+}
+
+/// Register the route of the `.proto`
+pub fn configure_grpc(config: &mut web::ServiceConfig) {
+	config.service(web::resource(&CONFIG.grpc_proto_path).route(web::get().to(proto_route)));
+}
+
+fn grpc_status(status: actix_web::http::StatusCode) -> u16 {
+	match status.as_u16() {
+		400 => INVALID_ARGUMENT,
+		401 => UNAUTHENTICATED,
+		403 => PERMISSION_DENIED,
+		404 => NOT_FOUND,
+		429 => RESOURCE_EXHAUSTED,
+		_ => INTERNAL,
+	}
+}
+
+/// A trailers-only response, used for the errors
+fn status_response(code: u16, message: &str) -> Response<Body> {
+	let mut response = Response::new(Body::empty());
+	let headers = response.headers_mut();
+
+	headers.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+	headers.insert("grpc-status", HeaderValue::from(code));
+
+	if let Ok(message) = HeaderValue::from_str(message) {
+		headers.insert("grpc-message", message);
+	}
+
+	response
+}
+
+/// Read the message of a unary call, compressed messages are not supported
+fn read_frame(body: &[u8]) -> Result<&[u8], &'static str> {
+	if body.len() < 5 {
+		return Err("Missing message");
+	}
+
+	if body[0] != 0 {
+		return Err("Compressed messages are not supported");
+	}
+
+	let length = u32::from_be_bytes([body[1], body[2], body[3], body[4]]) as usize;
+
+	body.get(5..5 + length).ok_or("Truncated message")
+}
+
+fn write_frame(message: &[u8]) -> Bytes {
+	let mut frame = BytesMut::with_capacity(message.len() + 5);
+
+	frame.put_u8(0);
+	frame.put_u32(message.len() as u32);
+	frame.put_slice(message);
+
+	frame.freeze()
+}
+
+/// The GraphQL variables of a call
+fn call_variables(operation: SchemaOperation, request: JsonValue) -> JsonValue {
+	match operation {
+		SchemaOperation::GetAll => json!({ "limit": request.get("limit") }),
+		SchemaOperation::Create => {
+			json!({ "data": request.get("data").cloned().unwrap_or(json!({})) })
+		}
+		SchemaOperation::Update => json!({
+			"id": request.get("key"),
+			"data": request.get("data").cloned().unwrap_or(json!({})),
+		}),
+		_ => json!({ "id": request.get("key") }),
+	}
+}
+
+/// Authenticate a call and charge it to the budgets of its client, like the HTTP requests
+async fn authenticate_call(
+	headers: &HeaderMap,
+	address: Option<String>,
+) -> Result<Context, Response<Body>> {
+	let header = |name: &str| {
+		headers
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.map(String::from)
+	};
+
+	let forwarded = headers
+		.iter()
+		.filter(|(name, _)| is_forwarded(name.as_str()))
+		.filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+		.collect();
+
+	let credentials = Credentials {
+		authorization: header(AUTHORIZATION.as_str()),
+		api_key: header(API_KEY_HEADER),
+		headers: forwarded,
+		session: None,
+		address,
+		ip_limited: false,
+	};
+
+	match authorize(credentials).await {
+		Ok(claims) => Ok(Context::new(claims)),
+		Err(AccessError::Unauthenticated(e)) => {
+			Err(status_response(UNAUTHENTICATED, &e.to_string()))
+		}
+		Err(AccessError::RateLimited(_)) => {
+			Err(status_response(RESOURCE_EXHAUSTED, "Too many requests"))
+		}
+	}
+}
+
+/// Run a unary call `/alchemy.{Entity}Service/{Method}` through the generated operations
+async fn handle_call(
+	request: Request<Body>,
+	schemas: Data<Schemas>,
+	address: Option<String>,
+) -> Response<Body> {
+	let path = request.uri().path().trim_start_matches('/').to_string();
+
+	let (service_name, method_name) = match path.split_once('/') {
+		Some((service, method)) => (
+			service
+				.strip_prefix(PACKAGE)
+				.and_then(|service| service.strip_prefix('.'))
+				.unwrap_or_default()
+				.to_string(),
+			method.to_string(),
+		),
+		None => return status_response(UNIMPLEMENTED, "Unknown method"),
+	};
+
+	let context = match authenticate_call(request.headers(), address).await {
+		Ok(context) => context,
+		Err(response) => return response,
+	};
+
+	let file = proto_file(schemas.entities());
+
+	let (service, method) = match file
+		.services
+		.iter()
+		.find(|service| service.name == service_name)
+		.and_then(|service| {
+			service
+				.methods
+				.iter()
+				.find(|method| method.name == method_name)
+				.map(|method| (service, method))
+		}) {
+		Some(found) => found,
+		None => return status_response(UNIMPLEMENTED, "Unknown method"),
+	};
+
+	let mut pool = DescriptorPool::new();
+
+	if let Err(e) = pool.add_file_descriptor_proto(file.descriptor()) {
+		tracing::error!(error = %e, "Invalid generated protobuf descriptor");
+
+		return status_response(INTERNAL, "Invalid generated services");
+	}
+
+	let message = |name: &str| pool.get_message_by_name(&format!("{}.{}", PACKAGE, name));
+
+	let (input, output) = match (message(&method.input), message(&method.output)) {
+		(Some(input), Some(output)) => (input, output),
+		_ => return status_response(INTERNAL, "Invalid generated services"),
+	};
+
+	let body = match hyper::body::to_bytes(request.into_body()).await {
+		Ok(body) => body,
+		Err(e) => return status_response(INTERNAL, &e.to_string()),
+	};
+
+	let call = match read_frame(&body)
+		.map_err(String::from)
+		.and_then(|frame| DynamicMessage::decode(input, frame).map_err(|e| e.to_string()))
+	{
+		Ok(call) => call,
+		Err(e) => return status_response(INVALID_ARGUMENT, &e),
+	};
+
+	let options = SerializeOptions::new()
+		.use_proto_field_name(true)
+		.skip_default_fields(true);
+
+	let call = match call.serialize_with_options(serde_json::value::Serializer, &options) {
+		Ok(call) => call,
+		Err(e) => return status_response(INVALID_ARGUMENT, &e.to_string()),
+	};
+
+	let data = match execute_operation(
+		&schemas,
+		&context,
+		String::new(),
+		&service.collection,
+		method.operation,
+		call_variables(method.operation, call),
+	)
+	.await
+	{
+		Ok(data) => data,
+		Err(e) => return status_response(grpc_status(e.status), &e.message),
+	};
+
+	let data = match method.operation {
+		SchemaOperation::GetAll => json!({ "items": data }),
+		_ => data,
+	};
+
+	let reply = match DynamicMessage::deserialize(output, data) {
+		Ok(reply) => reply,
+		Err(e) => return status_response(INTERNAL, &e.to_string()),
+	};
+
+	let (mut sender, body) = Body::channel();
+
+	actix_web::rt::spawn(async move {
+		let _ = sender.send_data(write_frame(&reply.encode_to_vec())).await;
+
+		let mut trailers = HeaderMap::new();
+		trailers.insert("grpc-status", HeaderValue::from(OK));
+
+		let _ = sender.send_trailers(trailers).await;
+	});
+
+	let mut response = Response::new(body);
+	response
+		.headers_mut()
+		.insert(CONTENT_TYPE, HeaderValue::from_static("application/grpc"));
+
+	response
+}
+
+/// Serve the generated services over HTTP/2 on `GRPC_PORT`, next to the HTTP server
+pub fn start_grpc_server(schemas: Data<Schemas>) {
+	let port = match CONFIG.grpc_port {
+		Some(port) => port,
+		None => return,
+	};
+
+	let address: SocketAddr = match format!("{}:{}", CONFIG.app_host, port).parse() {
+		Ok(address) => address,
+		Err(e) => {
+			tracing::error!(error = %e, "Invalid gRPC address");
+
+			return;
+		}
+	};
+
+	actix_web::rt::spawn(async move {
+		let make_service = make_service_fn(move |connection: &AddrStream| {
+			let schemas = schemas.clone();
+			let address = connection.remote_addr().ip().to_string();
+
+			async move {
+				Ok::<_, Infallible>(service_fn(move |request| {
+					let schemas = schemas.clone();
+					let address = Some(address.clone());
+
+					async move { Ok::<_, Infallible>(handle_call(request, schemas, address).await) }
+				}))
+			}
+		});
+
+		tracing::info!(address = %address, "Starting the gRPC server");
+
+		if let Err(e) = Server::bind(&address)
+			.http2_only(true)
+			.serve(make_service)
+			.await
+		{
+			tracing::error!(error = %e, "gRPC server failed");
+		}
+	});
+}
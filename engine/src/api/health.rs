@@ -0,0 +1,41 @@
+use actix_web::{web::Data, HttpResponse as ActixResponse};
+use rust_arango::AqlQuery;
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::schema::Schemas;
+use crate::lib::database::DATABASE;
+
+/// Liveness probe, answers as long as the process is able to serve requests
+pub async fn healthz_route() -> ActixResponse {
+	ActixResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+/// Readiness probe, the instance takes traffic once the schema is loaded and the database answers
+pub async fn readyz_route(schema: Option<Data<Schemas>>) -> ActixResponse {
+	let schema_loaded = schema.is_some();
+	let database_reachable = ping_database().await;
+
+	let body = json!({
+		"status": if schema_loaded && database_reachable { "ok" } else { "unavailable" },
+		"schema": schema_loaded,
+		"database": database_reachable,
+	});
+
+	if schema_loaded && database_reachable {
+		ActixResponse::Ok().json(body)
+	} else {
+		ActixResponse::ServiceUnavailable().json(body)
+	}
+}
+
+async fn ping_database() -> bool {
+	let query = AqlQuery::builder().query("RETURN 1").build();
+
+	DATABASE
+		.get()
+		.await
+		.database
+		.aql_query::<JsonValue>(query)
+		.await
+		.is_ok()
+}
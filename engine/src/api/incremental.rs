@@ -0,0 +1,820 @@
+use std::collections::HashSet;
+use std::convert::Infallible;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::{
+	http::header, rt, web::Bytes, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use futures::channel::mpsc::{unbounded, UnboundedSender};
+use futures::stream::{FuturesUnordered, StreamExt};
+use graphql_parser::query::{
+	Definition, Directive, Document, Field, FragmentDefinition, InlineFragment,
+	OperationDefinition, Selection, SelectionSet, Value,
+};
+use graphql_parser::{parse_query, Pos};
+use juniper::http::{GraphQLBatchRequest, GraphQLRequest};
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::rate_limit::record_rows_read;
+use crate::api::request_id::scope_request_id;
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{mask_internal_errors, timeout_response};
+use crate::api::schema::Schema;
+use crate::lib::CONFIG;
+
+const DEFER: &str = "defer";
+const STREAM: &str = "stream";
+
+/// The boundary of the parts of the multipart responses
+const BOUNDARY: &str = "-";
+
+/// The directives are not known to juniper, they are declared in the SDL for the clients
+pub const INCREMENTAL_DIRECTIVES: &str = "
+directive @defer(label: String, if: Boolean! = true) on FRAGMENT_SPREAD | INLINE_FRAGMENT
+
+directive @stream(label: String, if: Boolean! = true, initialCount: Int = 0) on FIELD
+";
+
+/// A fragment resolved after the initial payload
+struct DeferredFragment {
+	/// The request selecting the fragment, along with the fields leading to it
+	request: GraphQLRequest,
+	/// The response keys of the fields leading to the fragment
+	keys: Vec<String>,
+	label: Option<String>,
+}
+
+/// A list whose items after the first ones are sent after the initial payload
+struct StreamedList {
+	/// The response keys of the fields leading to the list, the list included
+	keys: Vec<String>,
+	initial_count: usize,
+	label: Option<String>,
+}
+
+/// A query split into its initial payload and the parts sent after it. The deferred fragments
+/// are resolved by their own queries once the initial payload is sent, the streamed lists are
+/// resolved with the initial payload and only their delivery is incremental
+pub struct IncrementalRequest {
+	initial: GraphQLRequest,
+	deferred: Vec<DeferredFragment>,
+	streamed: Vec<StreamedList>,
+}
+
+/// A deferred fragment found in the operation
+struct PlannedFragment<'a> {
+	/// The fields and inline fragments leading to the fragment, without their selections
+	route: Vec<Selection<'a, String>>,
+	selection: Selection<'a, String>,
+	keys: Vec<String>,
+	label: Option<String>,
+}
+
+/// The fragments and lists found while the selections of the initial payload are built
+struct Planner<'a, 'v> {
+	variables: &'v JsonValue,
+	span: (Pos, Pos),
+	deferred: Vec<PlannedFragment<'a>>,
+	streamed: Vec<StreamedList>,
+}
+
+impl<'a, 'v> Planner<'a, 'v> {
+	/// The value of a directive argument, the variables replaced by their values
+	fn argument(&self, directive: &Directive<'a, String>, name: &str) -> Option<JsonValue> {
+		let (_, value) = directive
+			.arguments
+			.iter()
+			.find(|(argument, _)| argument == name)?;
+
+		match value {
+			Value::Variable(variable) => self.variables.get(variable).cloned(),
+			Value::Boolean(boolean) => Some(json!(boolean)),
+			Value::Int(number) => number.as_i64().map(|number| json!(number)),
+			Value::String(string) => Some(json!(string)),
+			_ => None,
+		}
+	}
+
+	/// The directive of the selection, unless its `if` argument is false
+	fn enabled<'d>(
+		&self,
+		directives: &'d [Directive<'a, String>],
+		name: &str,
+	) -> Option<&'d Directive<'a, String>> {
+		directives
+			.iter()
+			.find(|directive| directive.name == name)
+			.filter(|directive| {
+				self.argument(directive, "if")
+					.and_then(|value| value.as_bool())
+					.unwrap_or(true)
+			})
+	}
+
+	fn label(&self, directive: &Directive<'a, String>) -> Option<String> {
+		self.argument(directive, "label")
+			.and_then(|value| value.as_str().map(str::to_string))
+	}
+
+	/// The selections of the initial payload, the deferred fragments removed. Only the
+	/// selections of the operation are split, those of the named fragments and of the deferred
+	/// fragments are resolved along with them
+	fn plan(
+		&mut self,
+		set: &SelectionSet<'a, String>,
+		route: &[Selection<'a, String>],
+		keys: &[String],
+	) -> SelectionSet<'a, String> {
+		let mut items = Vec::new();
+
+		for selection in &set.items {
+			match selection {
+				Selection::Field(field) => {
+					let key = field.alias.as_ref().unwrap_or(&field.name).clone();
+					let mut field_keys = keys.to_vec();
+					field_keys.push(key);
+
+					if let Some(directive) = self.enabled(&field.directives, STREAM) {
+						self.streamed.push(StreamedList {
+							keys: field_keys.clone(),
+							initial_count: self
+								.argument(directive, "initialCount")
+								.and_then(|value| value.as_u64())
+								.unwrap_or(0) as usize,
+							label: self.label(directive),
+						});
+					}
+
+					let mut planned = field.clone();
+					planned.directives = without_incremental(&field.directives);
+
+					if !field.selection_set.items.is_empty() {
+						let mut field_route = route.to_vec();
+						field_route.push(Selection::Field(Field {
+							selection_set: self.empty(),
+							..planned.clone()
+						}));
+
+						planned.selection_set =
+							self.plan(&field.selection_set, &field_route, &field_keys);
+					}
+
+					items.push(Selection::Field(planned));
+				}
+				Selection::InlineFragment(fragment) => {
+					if let Some(directive) = self.enabled(&fragment.directives, DEFER) {
+						let mut deferred = selection.clone();
+						strip_selection(&mut deferred);
+
+						self.deferred.push(PlannedFragment {
+							route: route.to_vec(),
+							selection: deferred,
+							keys: keys.to_vec(),
+							label: self.label(directive),
+						});
+
+						continue;
+					}
+
+					let mut planned = fragment.clone();
+					planned.directives = without_incremental(&fragment.directives);
+
+					let mut fragment_route = route.to_vec();
+					fragment_route.push(Selection::InlineFragment(InlineFragment {
+						selection_set: self.empty(),
+						..planned.clone()
+					}));
+
+					planned.selection_set =
+						self.plan(&fragment.selection_set, &fragment_route, keys);
+
+					items.push(Selection::InlineFragment(planned));
+				}
+				Selection::FragmentSpread(spread) => {
+					let mut planned = selection.clone();
+					strip_selection(&mut planned);
+
+					if let Some(directive) = self.enabled(&spread.directives, DEFER) {
+						self.deferred.push(PlannedFragment {
+							route: route.to_vec(),
+							selection: planned,
+							keys: keys.to_vec(),
+							label: self.label(directive),
+						});
+
+						continue;
+					}
+
+					items.push(planned);
+				}
+			}
+		}
+
+		// A selection set cannot be empty, its fields may all be deferred
+		if items.is_empty() {
+			items.push(Selection::Field(Field {
+				position: set.span.0,
+				alias: None,
+				name: String::from("__typename"),
+				arguments: Vec::new(),
+				directives: Vec::new(),
+				selection_set: self.empty(),
+			}));
+		}
+
+		SelectionSet {
+			span: set.span,
+			items,
+		}
+	}
+
+	fn empty(&self) -> SelectionSet<'a, String> {
+		SelectionSet {
+			span: self.span,
+			items: Vec::new(),
+		}
+	}
+
+	/// The selections of a deferred fragment, nested in the fields leading to it
+	fn wrap(
+		&self,
+		route: &[Selection<'a, String>],
+		selection: Selection<'a, String>,
+	) -> SelectionSet<'a, String> {
+		let mut set = SelectionSet {
+			span: self.span,
+			items: vec![selection],
+		};
+
+		for ancestor in route.iter().rev() {
+			let mut ancestor = ancestor.clone();
+
+			match &mut ancestor {
+				Selection::Field(field) => field.selection_set = set,
+				Selection::InlineFragment(fragment) => fragment.selection_set = set,
+				Selection::FragmentSpread(_) => {}
+			}
+
+			set = SelectionSet {
+				span: self.span,
+				items: vec![ancestor],
+			};
+		}
+
+		set
+	}
+}
+
+fn without_incremental<'a>(directives: &[Directive<'a, String>]) -> Vec<Directive<'a, String>> {
+	directives
+		.iter()
+		.filter(|directive| directive.name != DEFER && directive.name != STREAM)
+		.cloned()
+		.collect()
+}
+
+/// Remove the incremental delivery directives of a selection and of all the selections it has
+fn strip_selection(selection: &mut Selection<String>) {
+	match selection {
+		Selection::Field(field) => {
+			field.directives = without_incremental(&field.directives);
+			strip_selection_set(&mut field.selection_set);
+		}
+		Selection::InlineFragment(fragment) => {
+			fragment.directives = without_incremental(&fragment.directives);
+			strip_selection_set(&mut fragment.selection_set);
+		}
+		Selection::FragmentSpread(spread) => {
+			spread.directives = without_incremental(&spread.directives);
+		}
+	}
+}
+
+fn strip_selection_set(set: &mut SelectionSet<String>) {
+	for selection in &mut set.items {
+		strip_selection(selection);
+	}
+}
+
+fn operation_selection_set<'b, 'a>(
+	operation: &'b mut OperationDefinition<'a, String>,
+) -> &'b mut SelectionSet<'a, String> {
+	match operation {
+		OperationDefinition::SelectionSet(set) => set,
+		OperationDefinition::Query(query) => &mut query.selection_set,
+		OperationDefinition::Mutation(mutation) => &mut mutation.selection_set,
+		OperationDefinition::Subscription(subscription) => &mut subscription.selection_set,
+	}
+}
+
+/// A cheap check before the document is parsed, most of them use neither directive
+fn uses_incremental_directives(query: &str) -> bool {
+	query.contains("@defer") || query.contains("@stream")
+}
+
+/// The names of the fragments spread in the selections and the variables they read
+fn collect_references(
+	set: &SelectionSet<String>,
+	fragments: &mut HashSet<String>,
+	variables: &mut HashSet<String>,
+) {
+	for selection in &set.items {
+		let (directives, selection_set) = match selection {
+			Selection::Field(field) => {
+				for (_, value) in &field.arguments {
+					collect_variables(value, variables);
+				}
+
+				(&field.directives, Some(&field.selection_set))
+			}
+			Selection::InlineFragment(fragment) => {
+				(&fragment.directives, Some(&fragment.selection_set))
+			}
+			Selection::FragmentSpread(spread) => {
+				fragments.insert(spread.fragment_name.clone());
+
+				(&spread.directives, None)
+			}
+		};
+
+		for directive in directives {
+			for (_, value) in &directive.arguments {
+				collect_variables(value, variables);
+			}
+		}
+
+		if let Some(selection_set) = selection_set {
+			collect_references(selection_set, fragments, variables);
+		}
+	}
+}
+
+fn collect_variables(value: &Value<String>, variables: &mut HashSet<String>) {
+	match value {
+		Value::Variable(name) => {
+			variables.insert(name.clone());
+		}
+		Value::List(items) => {
+			for item in items {
+				collect_variables(item, variables);
+			}
+		}
+		Value::Object(fields) => {
+			for item in fields.values() {
+				collect_variables(item, variables);
+			}
+		}
+		_ => {}
+	}
+}
+
+/// The document of a query of the operation, with only the fragments and the variables its
+/// selections use, juniper rejects the unused ones
+fn operation_document<'a>(
+	operation: &OperationDefinition<'a, String>,
+	selection_set: SelectionSet<'a, String>,
+	fragments: &[FragmentDefinition<'a, String>],
+) -> String {
+	let mut used_fragments = HashSet::new();
+	let mut used_variables = HashSet::new();
+
+	collect_references(&selection_set, &mut used_fragments, &mut used_variables);
+
+	// The fragments may spread other fragments
+	let mut visited = HashSet::new();
+
+	while let Some(name) = used_fragments.difference(&visited).next().cloned() {
+		if let Some(fragment) = fragments.iter().find(|fragment| fragment.name == name) {
+			collect_references(
+				&fragment.selection_set,
+				&mut used_fragments,
+				&mut used_variables,
+			);
+		}
+
+		visited.insert(name);
+	}
+
+	let mut operation = operation.clone();
+
+	if let OperationDefinition::Query(query) = &mut operation {
+		query
+			.variable_definitions
+			.retain(|definition| used_variables.contains(&definition.name));
+	}
+
+	*operation_selection_set(&mut operation) = selection_set;
+
+	let mut definitions = vec![Definition::Operation(operation)];
+
+	definitions.extend(
+		fragments
+			.iter()
+			.filter(|fragment| used_fragments.contains(&fragment.name))
+			.cloned()
+			.map(Definition::Fragment),
+	);
+
+	Document { definitions }.to_string()
+}
+
+/// Split a query using `@defer` or `@stream` into its initial payload and the parts sent after
+/// it, `None` for the other requests, including the mutations whose fragments cannot be resolved
+/// again
+pub fn plan_incremental(request: &GraphQLRequest) -> Option<IncrementalRequest> {
+	if !uses_incremental_directives(&request.query) {
+		return None;
+	}
+
+	let mut document = parse_query::<String>(&request.query).ok()?;
+
+	let mut fragments = Vec::new();
+	let mut operations = Vec::new();
+
+	for definition in document.definitions.drain(..) {
+		match definition {
+			Definition::Fragment(mut fragment) => {
+				fragment.directives = without_incremental(&fragment.directives);
+				strip_selection_set(&mut fragment.selection_set);
+
+				fragments.push(fragment);
+			}
+			Definition::Operation(operation) => operations.push(operation),
+		}
+	}
+
+	let operation = match request.operation_name.as_deref() {
+		Some(name) => operations.into_iter().find(|operation| {
+			matches!(operation, OperationDefinition::Query(query) if query.name.as_deref() == Some(name))
+		})?,
+		None if operations.len() == 1 => operations.pop()?,
+		None => return None,
+	};
+
+	let selection_set = match &operation {
+		OperationDefinition::SelectionSet(set) => set.clone(),
+		OperationDefinition::Query(query) => query.selection_set.clone(),
+		_ => return None,
+	};
+
+	let variables = serde_json::to_value(&request.variables).unwrap_or_default();
+
+	let mut planner = Planner {
+		variables: &variables,
+		span: selection_set.span,
+		deferred: Vec::new(),
+		streamed: Vec::new(),
+	};
+
+	let initial_set = planner.plan(&selection_set, &[], &[]);
+
+	if planner.deferred.is_empty() && planner.streamed.is_empty() {
+		return None;
+	}
+
+	let query = |document: String| {
+		GraphQLRequest::new(
+			document,
+			request.operation_name.clone(),
+			request.variables.clone(),
+		)
+	};
+
+	let deferred = std::mem::take(&mut planner.deferred)
+		.into_iter()
+		.map(|fragment| DeferredFragment {
+			request: query(operation_document(
+				&operation,
+				planner.wrap(&fragment.route, fragment.selection),
+				&fragments,
+			)),
+			keys: fragment.keys,
+			label: fragment.label,
+		})
+		.collect();
+
+	Some(IncrementalRequest {
+		initial: query(operation_document(&operation, initial_set, &fragments)),
+		deferred,
+		streamed: planner.streamed,
+	})
+}
+
+/// Remove the incremental delivery directives of the requests, their fragments and lists are then
+/// resolved and sent with the rest of the response
+pub fn strip_incremental_directives(request: &mut GraphQLBatchRequest) {
+	match request {
+		GraphQLBatchRequest::Single(request) => strip_request(request),
+		GraphQLBatchRequest::Batch(requests) => requests.iter_mut().for_each(strip_request),
+	}
+}
+
+fn strip_request(request: &mut GraphQLRequest) {
+	if !uses_incremental_directives(&request.query) {
+		return;
+	}
+
+	// The documents that do not parse are left to juniper, which reports their errors
+	let query = match parse_query::<String>(&request.query) {
+		Ok(mut document) => {
+			for definition in &mut document.definitions {
+				match definition {
+					Definition::Operation(operation) => {
+						strip_selection_set(operation_selection_set(operation))
+					}
+					Definition::Fragment(fragment) => {
+						fragment.directives = without_incremental(&fragment.directives);
+						strip_selection_set(&mut fragment.selection_set);
+					}
+				}
+			}
+
+			document.to_string()
+		}
+		Err(_) => return,
+	};
+
+	request.query = query;
+}
+
+/// Whether incremental delivery is enabled and the client reads multipart responses
+pub fn accepts_incremental(req: &ActixRequest) -> bool {
+	CONFIG.graphql_incremental_enabled
+		&& req
+			.headers()
+			.get(header::ACCEPT)
+			.and_then(|value| value.to_str().ok())
+			.map_or(false, |accept| accept.contains("multipart/mixed"))
+}
+
+/// Send the initial payload of the request, then its deferred fragments and the rest of its
+/// streamed lists as they are resolved, each payload in a part of a multipart response
+pub fn incremental_response(
+	incremental: IncrementalRequest,
+	schema: Arc<Schema>,
+	context: Context,
+	request_id: String,
+	client: String,
+) -> ActixResponse {
+	let (sender, receiver) = unbounded::<Result<Bytes, Infallible>>();
+
+	rt::spawn(async move {
+		// The client went away when the parts can no longer be sent, the rest is not resolved
+		let _ = deliver(
+			incremental,
+			&schema,
+			&context,
+			&request_id,
+			&client,
+			&sender,
+		)
+		.await;
+	});
+
+	ActixResponse::Ok()
+		.content_type(format!("multipart/mixed; boundary=\"{}\"", BOUNDARY))
+		.streaming(receiver)
+}
+
+async fn deliver(
+	incremental: IncrementalRequest,
+	schema: &Schema,
+	context: &Context,
+	request_id: &str,
+	client: &str,
+	sender: &UnboundedSender<Result<Bytes, Infallible>>,
+) -> Result<(), ()> {
+	let send = |mut payload: JsonValue| {
+		if !CONFIG.error_details() {
+			mask_internal_errors(&mut payload, request_id);
+
+			if let Some(entries) = payload
+				.get_mut("incremental")
+				.and_then(JsonValue::as_array_mut)
+			{
+				for entry in entries {
+					mask_internal_errors(entry, request_id);
+				}
+			}
+		}
+
+		let body = payload.to_string();
+
+		record_rows_read(context.claims.as_ref(), client, body.as_bytes());
+
+		let part = format!(
+			"\r\n--{}\r\nContent-Type: application/json; charset=utf-8\r\n\r\n{}",
+			BOUNDARY, body
+		);
+
+		sender.unbounded_send(Ok(Bytes::from(part))).map_err(|_| ())
+	};
+
+	let mut response = execute(&incremental.initial, schema, context, request_id).await;
+
+	let mut items = Vec::new();
+
+	if let Some(data) = response.get_mut("data") {
+		for list in &incremental.streamed {
+			split_list(data, &list.keys, list, Vec::new(), &mut items);
+		}
+	}
+
+	// Nothing follows an initial payload without data, the fragments would not be merged
+	let has_next =
+		!response["data"].is_null() && (!items.is_empty() || !incremental.deferred.is_empty());
+
+	response["hasNext"] = json!(has_next);
+	send(response)?;
+
+	if has_next {
+		let mut pending: FuturesUnordered<_> = incremental
+			.deferred
+			.iter()
+			.map(|fragment| async move {
+				let response = execute(&fragment.request, schema, context, request_id).await;
+
+				(fragment, response)
+			})
+			.collect();
+
+		if !items.is_empty() {
+			send(json!({ "incremental": items, "hasNext": !pending.is_empty() }))?;
+		}
+
+		while let Some((fragment, response)) = pending.next().await {
+			send(json!({
+				"incremental": fragment_entries(fragment, response),
+				"hasNext": !pending.is_empty(),
+			}))?;
+		}
+	}
+
+	sender
+		.unbounded_send(Ok(Bytes::from(format!("\r\n--{}--\r\n", BOUNDARY))))
+		.map_err(|_| ())
+}
+
+/// Execute one of the queries of the request within the time allowed to a whole request
+async fn execute(
+	request: &GraphQLRequest,
+	schema: &Schema,
+	context: &Context,
+	request_id: &str,
+) -> JsonValue {
+	let execution = scope_request_id(request_id.to_string(), async {
+		serde_json::to_value(request.execute(schema, context).await).unwrap_or_default()
+	});
+
+	match CONFIG.graphql_request_timeout_ms {
+		Some(timeout) => rt::time::timeout(Duration::from_millis(timeout), execution)
+			.await
+			.unwrap_or_else(|_| timeout_response(request_id)),
+		None => execution.await,
+	}
+}
+
+/// Move the items of a streamed list after its initial count to the payloads sent later, there
+/// is one list per item of the lists leading to it
+fn split_list(
+	value: &mut JsonValue,
+	keys: &[String],
+	list: &StreamedList,
+	path: Vec<JsonValue>,
+	entries: &mut Vec<JsonValue>,
+) {
+	match value {
+		JsonValue::Array(items) => {
+			for (index, item) in items.iter_mut().enumerate() {
+				let mut path = path.clone();
+				path.push(json!(index));
+
+				split_list(item, keys, list, path, entries);
+			}
+		}
+		JsonValue::Object(object) => {
+			let (key, rest) = match keys.split_first() {
+				Some(first) => first,
+				None => return,
+			};
+
+			let value = match object.get_mut(key) {
+				Some(value) => value,
+				None => return,
+			};
+
+			let mut path = path;
+			path.push(json!(key));
+
+			match value {
+				JsonValue::Array(items) if rest.is_empty() => {
+					if items.len() > list.initial_count {
+						path.push(json!(list.initial_count));
+
+						let mut entry = json!({
+							"items": items.split_off(list.initial_count),
+							"path": path,
+						});
+
+						if let Some(label) = &list.label {
+							entry["label"] = json!(label);
+						}
+
+						entries.push(entry);
+					}
+				}
+				value => split_list(value, rest, list, path, entries),
+			}
+		}
+		_ => {}
+	}
+}
+
+/// The objects of the response at the keys, one per item of the lists leading to them
+fn collect_objects(
+	value: &JsonValue,
+	keys: &[String],
+	path: Vec<JsonValue>,
+	objects: &mut Vec<(Vec<JsonValue>, JsonValue)>,
+) {
+	match value {
+		JsonValue::Array(items) => {
+			for (index, item) in items.iter().enumerate() {
+				let mut path = path.clone();
+				path.push(json!(index));
+
+				collect_objects(item, keys, path, objects);
+			}
+		}
+		JsonValue::Object(object) => match keys.split_first() {
+			Some((key, rest)) => {
+				if let Some(value) = object.get(key) {
+					let mut path = path;
+					path.push(json!(key));
+
+					collect_objects(value, rest, path, objects);
+				}
+			}
+			None => objects.push((path, value.clone())),
+		},
+		_ => {}
+	}
+}
+
+/// The entries of a resolved fragment, one per object it was spread on, each with the errors
+/// raised under its path
+fn fragment_entries(fragment: &DeferredFragment, response: JsonValue) -> Vec<JsonValue> {
+	let mut objects = Vec::new();
+
+	collect_objects(&response["data"], &fragment.keys, Vec::new(), &mut objects);
+
+	// The fields leading to the fragment may have failed, the errors are still reported
+	if objects.is_empty() {
+		objects.push((
+			fragment.keys.iter().map(|key| json!(key)).collect(),
+			JsonValue::Null,
+		));
+	}
+
+	let mut entries: Vec<JsonValue> = objects
+		.into_iter()
+		.map(|(path, data)| {
+			let mut entry = json!({ "data": data, "path": path });
+
+			if let Some(label) = &fragment.label {
+				entry["label"] = json!(label);
+			}
+
+			entry
+		})
+		.collect();
+
+	let errors = match response.get("errors").and_then(JsonValue::as_array) {
+		Some(errors) => errors,
+		None => return entries,
+	};
+
+	for error in errors {
+		let error_path = error["path"].as_array().map(Vec::as_slice).unwrap_or(&[]);
+
+		let index = entries
+			.iter()
+			.position(|entry| {
+				entry["path"]
+					.as_array()
+					.map_or(false, |path| error_path.starts_with(path))
+			})
+			.unwrap_or(entries.len() - 1);
+
+		match entries[index]
+			.get_mut("errors")
+			.and_then(JsonValue::as_array_mut)
+		{
+			Some(entry_errors) => entry_errors.push(error.clone()),
+			None => entries[index]["errors"] = json!([error]),
+		}
+	}
+
+	entries
+}
@@ -0,0 +1,247 @@
+use std::collections::HashMap;
+
+use actix_web::{
+	error::{ErrorBadRequest, ErrorPayloadTooLarge, ErrorUnsupportedMediaType},
+	http::{header, Method},
+	web::{Bytes, BytesMut, Payload as ActixPayload},
+	Error as ActixError, HttpRequest as ActixRequest,
+};
+use futures::StreamExt;
+use juniper::http::{GraphQLBatchRequest, GraphQLRequest};
+use juniper::DefaultScalarValue;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::api::persisted::{check_allowed, resolve_persisted_query};
+use crate::api::uploads::{read_multipart_request, PendingUpload};
+use crate::lib::CONFIG;
+
+/// Read the request body, failing as soon as it grows past the configured maximum
+pub async fn read_body(req: &ActixRequest, mut payload: ActixPayload) -> Result<Bytes, ActixError> {
+	let limit = CONFIG.max_body_size;
+
+	let declared_length = req
+		.headers()
+		.get(header::CONTENT_LENGTH)
+		.and_then(|value| value.to_str().ok())
+		.and_then(|value| value.parse::<usize>().ok());
+
+	if declared_length
+		.map(|length| length > limit)
+		.unwrap_or(false)
+	{
+		return Err(ErrorPayloadTooLarge("Request body is too large"));
+	}
+
+	let mut body = BytesMut::new();
+
+	while let Some(chunk) = payload.next().await {
+		let chunk = chunk?;
+
+		if body.len() + chunk.len() > limit {
+			return Err(ErrorPayloadTooLarge("Request body is too large"));
+		}
+
+		body.extend_from_slice(&chunk);
+	}
+
+	Ok(body.freeze())
+}
+
+/// Count the lexical tokens of a GraphQL document, commas and comments are ignored
+fn count_tokens(document: &str) -> usize {
+	let chars: Vec<char> = document.chars().collect();
+	let mut count = 0;
+	let mut i = 0;
+
+	while i < chars.len() {
+		let c = chars[i];
+
+		if c.is_whitespace() || c == ',' || c == '\u{feff}' {
+			i += 1;
+		} else if c == '#' {
+			while i < chars.len() && chars[i] != '\n' && chars[i] != '\r' {
+				i += 1;
+			}
+		} else if c == '"' {
+			count += 1;
+
+			if chars[i..].starts_with(&['"', '"', '"']) {
+				i += 3;
+
+				while i < chars.len() && !chars[i..].starts_with(&['"', '"', '"']) {
+					i += if chars[i] == '\\' { 2 } else { 1 };
+				}
+
+				i += 3;
+			} else {
+				i += 1;
+
+				while i < chars.len() && chars[i] != '"' && chars[i] != '\n' {
+					i += if chars[i] == '\\' { 2 } else { 1 };
+				}
+
+				i += 1;
+			}
+		} else if c.is_alphanumeric() || c == '_' || c == '-' {
+			count += 1;
+			i += 1;
+
+			while i < chars.len() && (chars[i].is_alphanumeric() || matches!(chars[i], '_' | '.')) {
+				i += 1;
+			}
+		} else if chars[i..].starts_with(&['.', '.', '.']) {
+			count += 1;
+			i += 3;
+		} else {
+			count += 1;
+			i += 1;
+		}
+	}
+
+	count
+}
+
+/// Reject documents that are longer or have more tokens than allowed, before juniper parses them
+pub fn check_document(document: &str) -> Result<(), ActixError> {
+	if document.len() > CONFIG.graphql_max_document_length {
+		return Err(ErrorPayloadTooLarge(format!(
+			"GraphQL document exceeds {} characters",
+			CONFIG.graphql_max_document_length
+		)));
+	}
+
+	if count_tokens(document) > CONFIG.graphql_max_tokens {
+		return Err(ErrorPayloadTooLarge(format!(
+			"GraphQL document exceeds {} tokens",
+			CONFIG.graphql_max_tokens
+		)));
+	}
+
+	Ok(())
+}
+
+/// A GraphQL request read from HTTP, along with the files of a multipart request
+pub struct IncomingRequest {
+	pub request: GraphQLBatchRequest<DefaultScalarValue>,
+	/// Stored once the request is validated and authorized
+	pub uploads: Vec<PendingUpload>,
+	/// The request encoded as JSON once persisted queries are resolved
+	pub source: String,
+}
+
+/// Extract the GraphQL request from the query string or the body, checking its size limits
+pub async fn read_graphql_request(
+	req: &ActixRequest,
+	payload: ActixPayload,
+) -> Result<IncomingRequest, ActixError> {
+	if req.method() == Method::GET {
+		let mut parameters: HashMap<String, String> =
+			serde_urlencoded::from_str(req.query_string()).map_err(ErrorBadRequest)?;
+
+		let mut request = JsonMap::new();
+
+		for key in ["query", "operationName"] {
+			if let Some(value) = parameters.remove(key) {
+				request.insert(key.to_string(), JsonValue::String(value));
+			}
+		}
+
+		// Variables and extensions are JSON encoded in the query string
+		for key in ["variables", "extensions"] {
+			if let Some(value) = parameters.remove(key) {
+				request.insert(
+					key.to_string(),
+					serde_json::from_str(&value).map_err(ErrorBadRequest)?,
+				);
+			}
+		}
+
+		return parse_json_request(JsonValue::Object(request), Vec::new()).await;
+	}
+
+	let content_type = req
+		.headers()
+		.get(header::CONTENT_TYPE)
+		.and_then(|value| value.to_str().ok())
+		.unwrap_or("application/json")
+		.to_string();
+
+	if content_type.starts_with("multipart/form-data") {
+		let (value, uploads) = read_multipart_request(req, payload).await?;
+
+		return parse_json_request(value, uploads).await;
+	}
+
+	let body = read_body(req, payload).await?;
+
+	if content_type.starts_with("application/graphql") {
+		let query = String::from_utf8(body.to_vec()).map_err(ErrorBadRequest)?;
+
+		check_document(&query)?;
+		check_allowed(&query)?;
+
+		let source = json!({ "query": query }).to_string();
+		let request = GraphQLRequest::new(query, None, None);
+
+		return Ok(IncomingRequest {
+			request: GraphQLBatchRequest::Single(request),
+			uploads: Vec::new(),
+			source,
+		});
+	}
+
+	if !content_type.starts_with("application/json") {
+		return Err(ErrorUnsupportedMediaType(
+			"Expected application/json, application/graphql or multipart/form-data",
+		));
+	}
+
+	let value: JsonValue = serde_json::from_slice(&body).map_err(ErrorBadRequest)?;
+
+	parse_json_request(value, Vec::new()).await
+}
+
+/// Resolve the persisted queries and check the documents of a JSON encoded request,
+/// which may be a batch, before deserializing it
+async fn parse_json_request(
+	mut value: JsonValue,
+	uploads: Vec<PendingUpload>,
+) -> Result<IncomingRequest, ActixError> {
+	let requests = match &mut value {
+		JsonValue::Array(requests) => {
+			if !CONFIG.graphql_batch_enabled {
+				return Err(ErrorBadRequest("Batched requests are disabled"));
+			}
+
+			if requests.is_empty() || requests.len() > CONFIG.graphql_max_batch_size {
+				return Err(ErrorBadRequest(format!(
+					"Batches must contain between 1 and {} operations",
+					CONFIG.graphql_max_batch_size
+				)));
+			}
+
+			requests.iter_mut().collect()
+		}
+		request => vec![request],
+	};
+
+	for request in requests {
+		resolve_persisted_query(request).await?;
+
+		match request.get("query").and_then(|query| query.as_str()) {
+			Some(query) => {
+				check_document(query)?;
+				check_allowed(query)?;
+			}
+			None => return Err(ErrorBadRequest("Missing the query")),
+		}
+	}
+
+	let source = value.to_string();
+
+	Ok(IncomingRequest {
+		request: serde_json::from_value(value).map_err(ErrorBadRequest)?,
+		uploads,
+		source,
+	})
+}
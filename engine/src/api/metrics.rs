@@ -0,0 +1,220 @@
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::sync::Mutex;
+use std::time::Duration;
+
+use actix_web::{web, HttpResponse};
+use juniper::{ScalarValue, Value};
+use lazy_static::lazy_static;
+
+use crate::lib::database::backend::QueryTiming;
+use crate::lib::CONFIG;
+
+/// Upper bounds of the buckets of the durations, in seconds
+const DURATION_BUCKETS: [f64; 12] = [
+	0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 10.0,
+];
+
+/// Upper bounds of the buckets of the rows returned
+const ROW_BUCKETS: [f64; 8] = [0.0, 1.0, 10.0, 50.0, 100.0, 500.0, 1000.0, 10000.0];
+
+lazy_static! {
+	static ref OPERATIONS: Mutex<BTreeMap<String, OperationMetrics>> = Mutex::new(BTreeMap::new());
+}
+
+struct Histogram {
+	buckets: &'static [f64],
+	counts: Vec<u64>,
+	sum: f64,
+	count: u64,
+}
+
+impl Histogram {
+	fn new(buckets: &'static [f64]) -> Self {
+		Histogram {
+			buckets,
+			counts: vec![0; buckets.len()],
+			sum: 0.0,
+			count: 0,
+		}
+	}
+
+	fn observe(&mut self, value: f64) {
+		for (bound, count) in self.buckets.iter().zip(self.counts.iter_mut()) {
+			if value <= *bound {
+				*count += 1;
+			}
+		}
+
+		self.sum += value;
+		self.count += 1;
+	}
+
+	fn write(&self, output: &mut String, name: &str, operation: &str) {
+		for (bound, count) in self.buckets.iter().zip(&self.counts) {
+			let _ = writeln!(
+				output,
+				"{}_bucket{{operation=\"{}\",le=\"{}\"}} {}",
+				name, operation, bound, count
+			);
+		}
+
+		let _ = writeln!(
+			output,
+			"{}_bucket{{operation=\"{}\",le=\"+Inf\"}} {}",
+			name, operation, self.count
+		);
+		let _ = writeln!(
+			output,
+			"{}_sum{{operation=\"{}\"}} {}",
+			name, operation, self.sum
+		);
+		let _ = writeln!(
+			output,
+			"{}_count{{operation=\"{}\"}} {}",
+			name, operation, self.count
+		);
+	}
+}
+
+/// What was measured for a generated operation since the start
+struct OperationMetrics {
+	duration: Histogram,
+	rows: Histogram,
+	/// Time spent in the database by the queries of an execution
+	aql_time: Histogram,
+	/// Time spent decoding the results of the queries of an execution
+	decode_time: Histogram,
+	errors: u64,
+}
+
+impl OperationMetrics {
+	fn new() -> Self {
+		OperationMetrics {
+			duration: Histogram::new(&DURATION_BUCKETS),
+			rows: Histogram::new(&ROW_BUCKETS),
+			aql_time: Histogram::new(&DURATION_BUCKETS),
+			decode_time: Histogram::new(&DURATION_BUCKETS),
+			errors: 0,
+		}
+	}
+}
+
+/// The documents returned by an operation, the items of a page or a list, or a single one
+pub fn returned_rows<S: ScalarValue>(output: &Value<S>) -> usize {
+	match output {
+		Value::Null => 0,
+		Value::List(items) => items.len(),
+		Value::Object(object) => match object.get_field_value("items") {
+			Some(Value::List(items)) => items.len(),
+			_ => 1,
+		},
+		Value::Scalar(_) => 1,
+	}
+}
+
+/// Record an execution of a generated operation
+pub fn record_operation(
+	operation: &str,
+	duration: Duration,
+	rows: Option<usize>,
+	queries: &[QueryTiming],
+) {
+	if !CONFIG.metrics_enabled {
+		return;
+	}
+
+	let mut operations = OPERATIONS.lock().unwrap();
+	let metrics = operations
+		.entry(operation.to_string())
+		.or_insert_with(OperationMetrics::new);
+
+	metrics.duration.observe(duration.as_secs_f64());
+
+	match rows {
+		Some(rows) => metrics.rows.observe(rows as f64),
+		None => metrics.errors += 1,
+	}
+
+	metrics.aql_time.observe(
+		queries
+			.iter()
+			.map(|query| query.query_time.as_secs_f64())
+			.sum(),
+	);
+	metrics.decode_time.observe(
+		queries
+			.iter()
+			.map(|query| query.decode_time.as_secs_f64())
+			.sum(),
+	);
+}
+
+/// The metrics in the Prometheus text format
+fn render_metrics() -> String {
+	let operations = OPERATIONS.lock().unwrap();
+	let mut output = String::new();
+
+	let histograms: [(&str, &str, fn(&OperationMetrics) -> &Histogram); 4] = [
+		(
+			"alchemy_operation_duration_seconds",
+			"Execution time of the generated operations",
+			|metrics| &metrics.duration,
+		),
+		(
+			"alchemy_operation_rows",
+			"Documents returned by the generated operations",
+			|metrics| &metrics.rows,
+		),
+		(
+			"alchemy_operation_aql_seconds",
+			"Time spent in the database by the queries of the generated operations",
+			|metrics| &metrics.aql_time,
+		),
+		(
+			"alchemy_operation_decode_seconds",
+			"Time spent decoding the results of the queries of the generated operations",
+			|metrics| &metrics.decode_time,
+		),
+	];
+
+	for (name, help, histogram) in histograms {
+		let _ = writeln!(output, "# HELP {} {}", name, help);
+		let _ = writeln!(output, "# TYPE {} histogram", name);
+
+		for (operation, metrics) in operations.iter() {
+			histogram(metrics).write(&mut output, name, operation);
+		}
+	}
+
+	let _ = writeln!(
+		output,
+		"# HELP alchemy_operation_errors_total Failed executions of the generated operations"
+	);
+	let _ = writeln!(output, "# TYPE alchemy_operation_errors_total counter");
+
+	for (operation, metrics) in operations.iter() {
+		let _ = writeln!(
+			output,
+			"alchemy_operation_errors_total{{operation=\"{}\"}} {}",
+			operation, metrics.errors
+		);
+	}
+
+	output
+}
+
+pub async fn metrics_route() -> HttpResponse {
+	HttpResponse::Ok()
+		.content_type("text/plain; version=0.0.4")
+		.body(render_metrics())
+}
+
+/// Register the route of the metrics, when enabled
+pub fn configure_metrics(config: &mut web::ServiceConfig) {
+	if !CONFIG.metrics_enabled {
+		return;
+	}
+
+	config.service(web::resource(&CONFIG.metrics_path).route(web::get().to(metrics_route)));
+}
@@ -1,2 +1,33 @@
+pub mod access_lists;
+pub mod actions;
+pub mod analysis;
+pub mod api_keys;
+pub mod apollo_tracing;
+pub mod audit;
+pub mod auth;
+pub mod auth_webhook;
+pub mod cache;
+pub mod change_feed;
+pub mod compression;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+pub mod health;
+pub mod incremental;
+pub mod limits;
+pub mod metrics;
+pub mod oidc;
+pub mod openapi;
+pub mod persisted;
+pub mod query_cache;
+pub mod rate_limit;
+pub mod remote;
+pub mod request_id;
+pub mod rest;
 pub mod schema;
 pub mod server;
+pub mod sessions;
+pub mod streaming;
+pub mod subscriptions;
+#[cfg(test)]
+pub mod testing;
+pub mod uploads;
@@ -0,0 +1,108 @@
+use lazy_static::lazy_static;
+use serde::Deserialize;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+use tokio::sync::RwLock;
+
+use crate::lib::CONFIG;
+
+lazy_static! {
+	static ref PROVIDER: RwLock<Option<OidcProvider>> = RwLock::new(None);
+	static ref ROLE_MAPPINGS: Vec<RoleMapping> = CONFIG
+		.oidc_role_mappings
+		.iter()
+		.flatten()
+		.filter_map(|entry| RoleMapping::parse(entry))
+		.collect();
+}
+
+/// The parts of the discovery document used to verify the ID tokens
+#[derive(Deserialize, Clone)]
+pub struct OidcProvider {
+	pub issuer: String,
+	pub jwks_uri: String,
+}
+
+/// A role granted when a claim has a value, written as `claim:value=role`
+struct RoleMapping {
+	claim: String,
+	value: String,
+	role: String,
+}
+
+impl RoleMapping {
+	fn parse(entry: &str) -> Option<Self> {
+		let (condition, role) = entry.rsplit_once('=')?;
+		let (claim, value) = condition.split_once(':')?;
+
+		if claim.is_empty() || role.is_empty() {
+			tracing::warn!(mapping = %entry, "Ignoring an invalid OIDC role mapping");
+
+			return None;
+		}
+
+		Some(RoleMapping {
+			claim: claim.trim().to_string(),
+			value: value.trim().to_string(),
+			role: role.trim().to_string(),
+		})
+	}
+
+	/// Whether the claim equals the value, or contains it when it is an array
+	fn matches(&self, claims: &JsonMap<String, JsonValue>) -> bool {
+		let equals = |claim: &JsonValue| match claim {
+			JsonValue::String(claim) => claim == &self.value,
+			JsonValue::Number(_) | JsonValue::Bool(_) => claim.to_string() == self.value,
+			_ => false,
+		};
+
+		match claims.get(&self.claim) {
+			Some(JsonValue::Array(items)) => items.iter().any(equals),
+			Some(claim) => equals(claim),
+			None => false,
+		}
+	}
+}
+
+async fn fetch_provider(issuer_url: &str) -> reqwest::Result<OidcProvider> {
+	let url = format!(
+		"{}/.well-known/openid-configuration",
+		issuer_url.trim_end_matches('/')
+	);
+
+	reqwest::get(url)
+		.await?
+		.error_for_status()?
+		.json::<OidcProvider>()
+		.await
+}
+
+/// The discovered provider, fetched on first use and again after a failure
+pub async fn provider() -> Option<OidcProvider> {
+	let issuer_url = CONFIG.oidc_issuer_url.as_ref()?;
+
+	if let Some(provider) = PROVIDER.read().await.as_ref() {
+		return Some(provider.clone());
+	}
+
+	let mut cache = PROVIDER.write().await;
+
+	if cache.is_none() {
+		match fetch_provider(issuer_url).await {
+			Ok(provider) => *cache = Some(provider),
+			Err(e) => {
+				tracing::warn!(url = %issuer_url, reason = %e, "Failed to fetch the OIDC discovery document")
+			}
+		}
+	}
+
+	cache.clone()
+}
+
+/// The roles granted by the role mappings to the claims of a token
+pub fn mapped_roles(claims: &JsonMap<String, JsonValue>) -> Vec<String> {
+	ROLE_MAPPINGS
+		.iter()
+		.filter(|mapping| mapping.matches(claims))
+		.map(|mapping| mapping.role.clone())
+		.collect()
+}
@@ -0,0 +1,331 @@
+use actix_web::{
+	web::Data, Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::api::schema::Schemas;
+use crate::lib::database::api::{DbEntity, DbScalarType};
+use crate::lib::schema::SchemaOperation;
+use crate::lib::CONFIG;
+use crate::meta::auth::{require_admin, ADMIN_SECRET_HEADER};
+
+fn scalar_schema(scalar_type: &DbScalarType, input: bool) -> JsonValue {
+	match scalar_type {
+		DbScalarType::Array(item) => {
+			json!({ "type": "array", "items": scalar_schema(item, input) })
+		}
+		DbScalarType::Enum(values) => json!({ "type": "string", "enum": values }),
+		DbScalarType::String => json!({ "type": "string" }),
+		DbScalarType::Object => json!({ "type": "object" }),
+		DbScalarType::Float => json!({ "type": "number" }),
+		DbScalarType::Int => json!({ "type": "integer", "format": "int32" }),
+		DbScalarType::Boolean => json!({ "type": "boolean" }),
+		DbScalarType::DateTime => json!({ "type": "string", "format": "date-time" }),
+		DbScalarType::Decimal => json!({ "type": "string", "format": "decimal" }),
+		// The translations are sent by locale, a single one is read
+		DbScalarType::Localized if input => json!({
+			"type": "array",
+			"items": {
+				"type": "object",
+				"properties": {
+					"locale": { "type": "string" },
+					"value": { "type": "string" }
+				},
+				"required": ["locale", "value"]
+			}
+		}),
+		DbScalarType::Localized => json!({ "type": "string" }),
+		DbScalarType::File => json!({ "type": "string", "format": "uri" }),
+		DbScalarType::GeoPoint | DbScalarType::GeoPolygon => json!({
+			"type": "object",
+			"properties": {
+				"type": { "type": "string", "enum": [scalar_type.geometry_type()] },
+				"coordinates": { "type": "array" }
+			}
+		}),
+	}
+}
+
+/// The JSON schema of the documents, or of the bodies of the mutations
+fn entity_schema(entity: &DbEntity, input: bool, partial: bool) -> JsonValue {
+	let mut properties = JsonMap::new();
+	let mut required = Vec::new();
+
+	for property in &entity.properties {
+		if input && (property.managed || property.readonly) {
+			continue;
+		}
+
+		properties.insert(
+			property.field_name().to_string(),
+			scalar_schema(&property.scalar_type, input),
+		);
+
+		if property.required && !partial {
+			required.push(property.field_name().to_string());
+		}
+	}
+
+	let mut schema = json!({ "type": "object", "properties": properties });
+
+	if !required.is_empty() {
+		schema["required"] = json!(required);
+	}
+
+	schema
+}
+
+fn reference(name: &str) -> JsonValue {
+	json!({ "$ref": format!("#/components/schemas/{}", name) })
+}
+
+fn json_content(schema: JsonValue) -> JsonValue {
+	json!({ "application/json": { "schema": schema } })
+}
+
+fn key_parameter() -> JsonValue {
+	json!({ "name": "key", "in": "path", "required": true, "schema": { "type": "string" } })
+}
+
+/// The REST routes of an entity, see `crate::api::rest`
+fn entity_paths(
+	entity: &DbEntity,
+	operations: &[SchemaOperation],
+	paths: &mut JsonMap<String, JsonValue>,
+) {
+	let name = &entity.name;
+	let errors = json!({
+		"400": { "$ref": "#/components/responses/Error" },
+		"403": { "$ref": "#/components/responses/Error" },
+		"404": { "$ref": "#/components/responses/Error" },
+	});
+
+	let mut collection = JsonMap::new();
+	let mut document = JsonMap::new();
+
+	let response = |status: &str, description: String, schema: JsonValue| {
+		let mut responses = errors.clone();
+		responses[status] = json!({ "description": description, "content": json_content(schema) });
+		responses
+	};
+
+	let mut list_responses = response(
+		"200",
+		format!("The {} documents", name),
+		json!({ "type": "array", "items": reference(name) }),
+	);
+	list_responses["200"]["content"]["text/csv"] = json!({ "schema": { "type": "string" } });
+	list_responses["200"]["content"]["text/tab-separated-values"] =
+		json!({ "schema": { "type": "string" } });
+
+	for operation in operations {
+		match operation {
+			SchemaOperation::GetAll => {
+				collection.insert(
+					"get".into(),
+					json!({
+						"tags": [name],
+						"summary": format!("List the {} documents", name),
+						"parameters": [
+							{ "name": "limit", "in": "query", "schema": { "type": "integer" } },
+							{ "name": "format", "in": "query", "schema": { "type": "string", "enum": ["json", "csv", "tsv"] } },
+						],
+						"responses": list_responses.clone(),
+					}),
+				);
+			}
+			SchemaOperation::Create => {
+				collection.insert(
+					"post".into(),
+					json!({
+						"tags": [name],
+						"summary": format!("Create a {} document", name),
+						"requestBody": { "required": true, "content": json_content(reference(&format!("{}Input", name))) },
+						"responses": response("201", format!("The created {}", name), reference(name)),
+					}),
+				);
+			}
+			SchemaOperation::Get => {
+				document.insert(
+					"get".into(),
+					json!({
+						"tags": [name],
+						"summary": format!("Get a {} document", name),
+						"responses": response("200", format!("The {}", name), reference(name)),
+					}),
+				);
+			}
+			SchemaOperation::Update => {
+				document.insert(
+					"patch".into(),
+					json!({
+						"tags": [name],
+						"summary": format!("Update a {} document", name),
+						"requestBody": { "required": true, "content": json_content(reference(&format!("{}UpdateInput", name))) },
+						"responses": response("200", format!("The updated {}", name), reference(name)),
+					}),
+				);
+			}
+			SchemaOperation::Delete => {
+				document.insert(
+					"delete".into(),
+					json!({
+						"tags": [name],
+						"summary": format!("Delete a {} document", name),
+						"responses": response("200", format!("The deleted {}", name), reference(name)),
+					}),
+				);
+			}
+			_ => {}
+		}
+	}
+
+	let base = format!(
+		"{}{}/{}",
+		CONFIG.base_path(),
+		CONFIG.rest_path,
+		entity.collection_name
+	);
+
+	if !collection.is_empty() {
+		paths.insert(base.clone(), JsonValue::Object(collection));
+	}
+
+	if !document.is_empty() {
+		document.insert("parameters".into(), json!([key_parameter()]));
+		paths.insert(format!("{}/{{key}}", base), JsonValue::Object(document));
+	}
+}
+
+/// The GraphQL, health and admin routes
+fn server_paths(paths: &mut JsonMap<String, JsonValue>) {
+	let graphql = json!({
+		"tags": ["GraphQL"],
+		"requestBody": {
+			"required": true,
+			"content": json_content(json!({
+				"type": "object",
+				"required": ["query"],
+				"properties": {
+					"query": { "type": "string" },
+					"operationName": { "type": "string" },
+					"variables": { "type": "object" },
+				},
+			})),
+		},
+		"responses": { "200": { "description": "The GraphQL response", "content": json_content(json!({ "type": "object" })) } },
+	});
+
+	let admin = json!([{ "adminSecret": [] }]);
+	let collection_parameter = json!({ "name": "collection", "in": "path", "required": true, "schema": { "type": "string" } });
+	let format_parameter = json!({ "name": "format", "in": "query", "schema": { "type": "string", "enum": ["jsonl", "csv"] } });
+
+	paths.insert(
+		CONFIG.path(&CONFIG.graphql_path),
+		json!({ "post": merge(&graphql, json!({ "summary": "Run a GraphQL request" })) }),
+	);
+	paths.insert(
+		CONFIG.path("/admin/graphql"),
+		json!({ "post": merge(&graphql, json!({ "summary": "Run a request of the admin API", "tags": ["Admin"], "security": admin })) }),
+	);
+	paths.insert(
+		CONFIG.path("/healthz"),
+		json!({ "get": { "tags": ["Health"], "summary": "Liveness probe", "responses": { "200": { "description": "Alive" } } } }),
+	);
+	paths.insert(
+		CONFIG.path("/readyz"),
+		json!({ "get": { "tags": ["Health"], "summary": "Readiness probe", "responses": { "200": { "description": "Ready" }, "503": { "description": "Not ready" } } } }),
+	);
+	paths.insert(
+		CONFIG.path("/import/{collection}"),
+		json!({ "post": {
+			"tags": ["Admin"],
+			"summary": "Import JSONL or CSV rows into a collection",
+			"security": admin,
+			"parameters": [collection_parameter, format_parameter],
+			"requestBody": { "required": true, "content": { "application/x-ndjson": {}, "text/csv": {} } },
+			"responses": { "200": { "description": "The import report" }, "401": { "description": "Admin secret required" } },
+		} }),
+	);
+	paths.insert(
+		CONFIG.path("/export/{collection}"),
+		json!({ "get": {
+			"tags": ["Admin"],
+			"summary": "Export a collection as JSONL or CSV",
+			"security": admin,
+			"parameters": [collection_parameter, format_parameter, { "name": "filter", "in": "query", "schema": { "type": "string" } }],
+			"responses": { "200": { "description": "The exported rows" }, "401": { "description": "Admin secret required" } },
+		} }),
+	);
+}
+
+fn merge(base: &JsonValue, extra: JsonValue) -> JsonValue {
+	let mut merged = base.clone();
+
+	if let (Some(merged), JsonValue::Object(extra)) = (merged.as_object_mut(), extra) {
+		merged.extend(extra);
+	}
+
+	merged
+}
+
+/// OpenAPI 3 document of the REST routes of the entities and of the server routes
+pub fn openapi_document(schemas: &Schemas) -> JsonValue {
+	let mut paths = JsonMap::new();
+	let mut components = JsonMap::new();
+
+	server_paths(&mut paths);
+
+	if CONFIG.rest_enabled {
+		for (entity, operations) in schemas.entities() {
+			components.insert(entity.name.clone(), entity_schema(&entity, false, false));
+			components.insert(
+				format!("{}Input", entity.name),
+				entity_schema(&entity, true, false),
+			);
+			components.insert(
+				format!("{}UpdateInput", entity.name),
+				entity_schema(&entity, true, true),
+			);
+
+			entity_paths(&entity, &operations, &mut paths);
+		}
+	}
+
+	let mut document = json!({
+		"openapi": "3.0.3",
+		"info": { "title": "Alchemy", "version": env!("CARGO_PKG_VERSION") },
+		"paths": paths,
+		"components": {
+			"schemas": components,
+			"responses": {
+				"Error": {
+					"description": "The request failed",
+					"content": json_content(json!({ "type": "object", "properties": { "error": { "type": "string" } } })),
+				},
+			},
+			"securitySchemes": {
+				"adminSecret": { "type": "apiKey", "in": "header", "name": ADMIN_SECRET_HEADER },
+				"bearerAuth": { "type": "http", "scheme": "bearer", "bearerFormat": "JWT" },
+			},
+		},
+	});
+
+	if CONFIG.auth_enabled() {
+		document["security"] = json!([{ "bearerAuth": [] }]);
+	}
+
+	document
+}
+
+/// The OpenAPI document, served like the SDL
+pub async fn openapi_route(
+	req: ActixRequest,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	if !CONFIG.introspection_enabled() {
+		require_admin(&req)?;
+	}
+
+	Ok(ActixResponse::Ok().json(openapi_document(&schemas)))
+}
@@ -0,0 +1,237 @@
+use std::collections::{HashMap, VecDeque};
+use std::fs;
+use std::path::Path;
+use std::sync::{Mutex, RwLock};
+
+use actix_web::{
+	error::{ErrorBadRequest, ErrorForbidden, InternalError},
+	Error as ActixError, HttpResponse as ActixResponse,
+};
+use anyhow::Error;
+use lazy_static::lazy_static;
+use rust_arango::AqlQuery;
+use serde_json::{json, Value as JsonValue};
+use sha2::{Digest, Sha256};
+
+use crate::lib::database::DATABASE;
+use crate::lib::CONFIG;
+
+/// Collection holding the registered documents, next to the files of the persisted queries path
+pub const PERSISTED_QUERIES_COLLECTION: &str = "alchemy_persisted_queries";
+
+lazy_static! {
+	static ref PERSISTED_QUERIES: Mutex<PersistedQueryCache> =
+		Mutex::new(PersistedQueryCache::new(CONFIG.apq_cache_size));
+	/// Documents registered ahead of time, the only ones allowed in persisted queries only mode
+	static ref REGISTERED_QUERIES: RwLock<HashMap<String, String>> = RwLock::new(HashMap::new());
+}
+
+/// Documents registered by clients, the oldest ones are evicted once the cache is full
+struct PersistedQueryCache {
+	capacity: usize,
+	documents: HashMap<String, String>,
+	order: VecDeque<String>,
+}
+
+impl PersistedQueryCache {
+	fn new(capacity: usize) -> Self {
+		PersistedQueryCache {
+			capacity,
+			documents: HashMap::new(),
+			order: VecDeque::new(),
+		}
+	}
+
+	fn get(&self, hash: &str) -> Option<String> {
+		self.documents.get(hash).cloned()
+	}
+
+	fn insert(&mut self, hash: String, document: String) {
+		if self.capacity == 0 || self.documents.contains_key(&hash) {
+			return;
+		}
+
+		while self.documents.len() >= self.capacity {
+			match self.order.pop_front() {
+				Some(oldest) => self.documents.remove(&oldest),
+				None => break,
+			};
+		}
+
+		self.order.push_back(hash.clone());
+		self.documents.insert(hash, document);
+	}
+}
+
+/// Register the `.graphql` files of a directory and the documents of the persisted queries
+/// collection, returning the number of registered documents
+pub async fn load_persisted_queries(path: &str) -> Result<usize, Error> {
+	let mut documents = Vec::new();
+
+	if Path::new(path).is_dir() {
+		for entry in fs::read_dir(path)? {
+			let path = entry?.path();
+
+			if matches!(
+				path.extension().and_then(|ext| ext.to_str()),
+				Some("graphql") | Some("gql")
+			) {
+				documents.push(fs::read_to_string(&path)?);
+			}
+		}
+	}
+
+	// The documents registered by clients are shared, but not allowed in persisted queries only mode
+	let query = AqlQuery::builder()
+		.query("FOR entry IN @@collection FILTER entry.apq != true RETURN entry.query")
+		.bind_var("@collection", PERSISTED_QUERIES_COLLECTION)
+		.build();
+
+	match DATABASE
+		.get()
+		.await
+		.database
+		.aql_query::<String>(query)
+		.await
+	{
+		Ok(stored) => documents.extend(stored),
+		Err(e) => tracing::debug!(error = ?e, "No persisted queries stored in the database"),
+	}
+
+	let mut registered = REGISTERED_QUERIES.write().unwrap();
+
+	for document in documents {
+		registered.insert(hash_document(&document), document);
+	}
+
+	Ok(registered.len())
+}
+
+/// Reject the documents that were not registered when only persisted queries are allowed
+pub fn check_allowed(document: &str) -> Result<(), ActixError> {
+	if !CONFIG.persisted_queries_only {
+		return Ok(());
+	}
+
+	if REGISTERED_QUERIES
+		.read()
+		.unwrap()
+		.contains_key(&hash_document(document))
+	{
+		Ok(())
+	} else {
+		Err(ErrorForbidden("Only persisted queries are allowed"))
+	}
+}
+
+pub fn hash_document(document: &str) -> String {
+	hex::encode(Sha256::digest(document.as_bytes()))
+}
+
+/// The GraphQL error Apollo clients expect to send the full document again
+fn persisted_query_not_found() -> ActixError {
+	let body = json!({
+		"errors": [{
+			"message": "PersistedQueryNotFound",
+			"extensions": { "code": "PERSISTED_QUERY_NOT_FOUND" },
+		}],
+	});
+
+	InternalError::from_response("PersistedQueryNotFound", ActixResponse::Ok().json(body)).into()
+}
+
+/// Store a document registered by a client, for the other instances
+async fn store_shared_document(hash: &str, document: &str) {
+	let query = AqlQuery::builder()
+		.query("INSERT { _key: @hash, query: @query, apq: true } INTO @@collection OPTIONS { overwriteMode: \"ignore\" }")
+		.bind_var("@collection", PERSISTED_QUERIES_COLLECTION)
+		.bind_var("hash", hash)
+		.bind_var("query", document)
+		.build();
+
+	let stored: Result<Vec<JsonValue>, _> = DATABASE.get().await.database.aql_query(query).await;
+
+	if let Err(e) = stored {
+		tracing::warn!(hash = %hash, error = ?e, "Failed to share the persisted query");
+	}
+}
+
+/// A document registered by a client of another instance
+async fn find_shared_document(hash: &str) -> Option<String> {
+	let query = AqlQuery::builder()
+		.query("FOR entry IN @@collection FILTER entry._key == @hash RETURN entry.query")
+		.bind_var("@collection", PERSISTED_QUERIES_COLLECTION)
+		.bind_var("hash", hash)
+		.build();
+
+	match DATABASE
+		.get()
+		.await
+		.database
+		.aql_query::<String>(query)
+		.await
+	{
+		Ok(documents) => documents.into_iter().next(),
+		Err(e) => {
+			tracing::warn!(hash = %hash, error = ?e, "Failed to read the shared persisted queries");
+
+			None
+		}
+	}
+}
+
+/// Apply the automatic persisted queries protocol to a single JSON request,
+/// filling in the cached document or registering the one sent along with its hash
+pub async fn resolve_persisted_query(request: &mut JsonValue) -> Result<(), ActixError> {
+	if !CONFIG.apq_enabled && !CONFIG.persisted_queries_only {
+		return Ok(());
+	}
+
+	let hash = match request
+		.pointer("/extensions/persistedQuery/sha256Hash")
+		.and_then(|hash| hash.as_str())
+	{
+		Some(hash) => hash.to_lowercase(),
+		None => return Ok(()),
+	};
+
+	match request.get("query").and_then(|query| query.as_str()) {
+		Some(query) => {
+			if hash_document(query) != hash {
+				return Err(ErrorBadRequest("provided sha does not match query"));
+			}
+
+			let known = PERSISTED_QUERIES.lock().unwrap().get(&hash).is_some();
+
+			if !known && CONFIG.apq_shared {
+				store_shared_document(&hash, query).await;
+			}
+
+			PERSISTED_QUERIES
+				.lock()
+				.unwrap()
+				.insert(hash, query.to_string());
+		}
+		None => {
+			let registered = REGISTERED_QUERIES.read().unwrap().get(&hash).cloned();
+
+			let mut document = registered.or_else(|| PERSISTED_QUERIES.lock().unwrap().get(&hash));
+
+			// The hashes learned by the other instances are cached once read
+			if document.is_none() && CONFIG.apq_shared {
+				document = find_shared_document(&hash).await;
+
+				if let Some(ref document) = document {
+					PERSISTED_QUERIES
+						.lock()
+						.unwrap()
+						.insert(hash.clone(), document.clone());
+				}
+			}
+
+			request["query"] = JsonValue::String(document.ok_or_else(persisted_query_not_found)?);
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,210 @@
+use std::collections::{HashMap, HashSet};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use lazy_static::lazy_static;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+use crate::lib::CONFIG;
+
+/// Prefix of the keys written in Redis
+#[cfg(feature = "redis-cache")]
+const REDIS_PREFIX: &str = "alchemy:aql:";
+
+struct CachedResult {
+	body: Vec<u8>,
+	collections: HashSet<String>,
+	expires_at: Instant,
+}
+
+lazy_static! {
+	static ref RESULTS: Mutex<HashMap<String, CachedResult>> = Mutex::new(HashMap::new());
+}
+
+#[cfg(feature = "redis-cache")]
+lazy_static! {
+	static ref REDIS: async_once::AsyncOnce<Option<redis::aio::ConnectionManager>> =
+		async_once::AsyncOnce::new(async {
+			let connection = async {
+				let url = CONFIG.query_cache_redis_url.as_deref().ok_or_else(|| {
+					anyhow::anyhow!("QUERY_CACHE_REDIS_URL must be set with the redis backend")
+				})?;
+
+				let client = redis::Client::open(url)?;
+
+				Ok::<_, anyhow::Error>(redis::aio::ConnectionManager::new(client).await?)
+			};
+
+			match connection.await {
+				Ok(connection) => Some(connection),
+				Err(e) => {
+					tracing::error!(error = %e, "Failed to connect to the query cache, the results are not cached");
+
+					None
+				}
+			}
+		});
+}
+
+/// Whether the results are kept in Redis, shared by the instances, rather than in memory
+fn uses_redis() -> bool {
+	CONFIG.query_cache_backend == "redis"
+}
+
+#[cfg(feature = "redis-cache")]
+fn redis_key(key: &str) -> String {
+	format!("{}{}", REDIS_PREFIX, key)
+}
+
+#[cfg(feature = "redis-cache")]
+fn redis_index(collection: &str) -> String {
+	format!("{}collection:{}", REDIS_PREFIX, collection)
+}
+
+/// The key of an AQL query, results are only shared between requests of the same roles
+pub fn query_cache_key(query: &str, bind_vars: &JsonValue, roles: Option<&[String]>) -> String {
+	let mut hasher = Sha256::new();
+
+	hasher.update(
+		roles
+			.map(|roles| roles.join(","))
+			.unwrap_or_default()
+			.as_bytes(),
+	);
+	hasher.update([0]);
+	hasher.update(query.as_bytes());
+	hasher.update([0]);
+	hasher.update(bind_vars.to_string().as_bytes());
+
+	hex::encode(hasher.finalize())
+}
+
+/// The JSON encoded results of a query, if they are cached
+pub async fn get_cached_result(key: &str) -> Option<Vec<u8>> {
+	if uses_redis() {
+		#[cfg(feature = "redis-cache")]
+		if let Some(connection) = REDIS.get().await {
+			return redis::cmd("GET")
+				.arg(redis_key(key))
+				.query_async::<_, Option<Vec<u8>>>(&mut connection.clone())
+				.await
+				.map_err(|e| tracing::error!(error = %e, "Failed to read the query cache"))
+				.ok()
+				.flatten();
+		}
+
+		return None;
+	}
+
+	let mut results = RESULTS.lock().unwrap();
+
+	match results.get(key) {
+		Some(result) if result.expires_at > Instant::now() => Some(result.body.clone()),
+		Some(_) => {
+			results.remove(key);
+
+			None
+		}
+		None => None,
+	}
+}
+
+/// Cache the results of a query, until the TTL elapses or one of the collections is changed
+pub async fn store_result(key: String, body: Vec<u8>, collections: HashSet<String>, ttl: Duration) {
+	if uses_redis() {
+		#[cfg(feature = "redis-cache")]
+		if let Some(connection) = REDIS.get().await {
+			let key = redis_key(&key);
+
+			let mut pipeline = redis::pipe();
+			pipeline
+				.cmd("SET")
+				.arg(&key)
+				.arg(body)
+				.arg("EX")
+				.arg(ttl.as_secs().max(1));
+
+			// The keys depending on a collection are listed to invalidate them
+			for collection in collections {
+				pipeline.cmd("SADD").arg(redis_index(&collection)).arg(&key);
+			}
+
+			if let Err(e) = pipeline.query_async::<_, ()>(&mut connection.clone()).await {
+				tracing::error!(error = %e, "Failed to write the query cache");
+			}
+		}
+
+		return;
+	}
+
+	let now = Instant::now();
+	let mut results = RESULTS.lock().unwrap();
+
+	if results.len() >= CONFIG.query_cache_max_entries {
+		results.retain(|_, result| result.expires_at > now);
+
+		if results.len() >= CONFIG.query_cache_max_entries {
+			return;
+		}
+	}
+
+	results.insert(
+		key,
+		CachedResult {
+			body,
+			collections,
+			expires_at: now + ttl,
+		},
+	);
+}
+
+#[cfg(feature = "redis-cache")]
+async fn invalidate_redis(collection: String) {
+	let mut connection = match REDIS.get().await {
+		Some(connection) => connection.clone(),
+		None => return,
+	};
+
+	let index = redis_index(&collection);
+
+	let keys: Result<Vec<String>, _> = redis::cmd("SMEMBERS")
+		.arg(&index)
+		.query_async(&mut connection)
+		.await;
+
+	let result = match keys {
+		Ok(keys) => {
+			redis::cmd("DEL")
+				.arg(keys)
+				.arg(&index)
+				.query_async::<_, ()>(&mut connection)
+				.await
+		}
+		Err(e) => Err(e),
+	};
+
+	if let Err(e) = result {
+		tracing::error!(collection = %collection, error = %e, "Failed to invalidate the query cache");
+	}
+}
+
+/// Drop the cached results that depend on a collection, Redis is cleared in the background
+pub fn invalidate_query_results(collection: &str) {
+	if uses_redis() {
+		#[cfg(feature = "redis-cache")]
+		actix_web::rt::spawn(invalidate_redis(collection.to_string()));
+
+		return;
+	}
+
+	RESULTS
+		.lock()
+		.unwrap()
+		.retain(|_, result| !result.collections.contains(collection));
+}
+
+/// Drop every cached result, after the schema changed
+pub fn clear_query_results() {
+	RESULTS.lock().unwrap().clear();
+}
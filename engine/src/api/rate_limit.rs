@@ -0,0 +1,469 @@
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+
+use actix_web::{
+	dev::{ServiceRequest, ServiceResponse},
+	error::InternalError,
+	http::StatusCode,
+	Error as ActixError, HttpMessage, HttpResponse as ActixResponse,
+};
+use lazy_static::lazy_static;
+use serde_json::value::RawValue;
+use serde_json::Value as JsonValue;
+
+use crate::api::auth::Claims;
+use crate::lib::reload::{runtime, RuntimeSettings};
+use crate::lib::CONFIG;
+
+pub const API_KEY_HEADER: &str = "x-api-key";
+
+/// Idle buckets are dropped once the limiter tracks more clients than this
+const MAX_TRACKED_CLIENTS: usize = 10_000;
+
+const SECONDS_PER_DAY: u64 = 86_400;
+
+lazy_static! {
+	/// The limiters of the runtime settings they were built from
+	static ref LIMITERS: RwLock<(Arc<RuntimeSettings>, Arc<Limiters>)> = {
+		let settings = runtime();
+		let limiters = Arc::new(Limiters::new(&settings));
+
+		RwLock::new((settings, limiters))
+	};
+	/// Rows read per client and role on the current day
+	static ref ROWS_READ: Mutex<RowsRead> = Mutex::new(RowsRead::default());
+}
+
+/// The rows read on a day, the counts of the previous days are dropped as soon as it changes
+#[derive(Default)]
+struct RowsRead {
+	day: u64,
+	rows: HashMap<String, u64>,
+}
+
+impl RowsRead {
+	/// The counts of the day
+	fn on(&mut self, day: u64) -> &mut HashMap<String, u64> {
+		if self.day != day {
+			self.day = day;
+			self.rows = HashMap::new();
+		}
+
+		&mut self.rows
+	}
+}
+
+struct Limiters {
+	ip: Option<RateLimiter>,
+	api_key: Option<RateLimiter>,
+	roles: HashMap<String, RoleBudget>,
+}
+
+impl Limiters {
+	fn new(settings: &RuntimeSettings) -> Self {
+		let period = settings.rate_limit_period;
+
+		Limiters {
+			ip: settings
+				.rate_limit_per_ip
+				.map(|requests| RateLimiter::new(requests, period)),
+			api_key: settings
+				.rate_limit_per_api_key
+				.map(|requests| RateLimiter::new(requests, period)),
+			roles: settings
+				.rate_limit_per_role
+				.iter()
+				.map(|entry| RoleBudget::parse(entry, period))
+				.collect(),
+		}
+	}
+}
+
+/// The limiters of the current settings, rebuilt with empty buckets once they are reloaded
+fn limiters() -> Arc<Limiters> {
+	let settings = runtime();
+
+	{
+		let (built_from, limiters) = &*LIMITERS.read().unwrap();
+
+		if Arc::ptr_eq(built_from, &settings) {
+			return limiters.clone();
+		}
+	}
+
+	let limiters = Arc::new(Limiters::new(&settings));
+	*LIMITERS.write().unwrap() = (settings, limiters.clone());
+
+	limiters
+}
+
+/// The budget of the clients of a role, written as `role:requests:rows_per_day`
+struct RoleBudget {
+	limiter: Option<RateLimiter>,
+	rows_per_day: Option<u64>,
+}
+
+impl RoleBudget {
+	fn parse(entry: &str, period: Duration) -> (String, Self) {
+		let mut parts = entry.splitn(3, ':');
+		let role = parts.next().unwrap_or_default().to_string();
+
+		let requests = parts.next().and_then(|requests| requests.parse().ok());
+		let rows_per_day = parts.next().and_then(|rows| rows.parse().ok());
+
+		let budget = RoleBudget {
+			limiter: requests.map(|requests| RateLimiter::new(requests, period)),
+			rows_per_day,
+		};
+
+		(role, budget)
+	}
+}
+
+struct Bucket {
+	tokens: f64,
+	updated_at: Instant,
+}
+
+/// Token bucket per client, refilled continuously up to its capacity
+pub struct RateLimiter {
+	capacity: f64,
+	refill_per_second: f64,
+	period: Duration,
+	buckets: Mutex<HashMap<String, Bucket>>,
+}
+
+impl RateLimiter {
+	pub fn new(requests: u32, period: Duration) -> Self {
+		RateLimiter {
+			capacity: requests as f64,
+			refill_per_second: requests as f64 / period.as_secs_f64(),
+			period,
+			buckets: Mutex::new(HashMap::new()),
+		}
+	}
+
+	/// Take a token for the client, or return how long to wait for the next one
+	pub fn check(&self, client: &str) -> Result<(), Duration> {
+		self.take(client, 1.0)
+	}
+
+	/// Whether the client has a token left, without taking it
+	pub fn peek(&self, client: &str) -> Result<(), Duration> {
+		self.take(client, 0.0)
+	}
+
+	fn take(&self, client: &str, tokens: f64) -> Result<(), Duration> {
+		let now = Instant::now();
+		let mut buckets = self.buckets.lock().unwrap();
+
+		if buckets.len() >= MAX_TRACKED_CLIENTS {
+			let period = self.period;
+
+			buckets.retain(|_, bucket| now.duration_since(bucket.updated_at) < period);
+		}
+
+		let bucket = buckets.entry(client.to_string()).or_insert(Bucket {
+			tokens: self.capacity,
+			updated_at: now,
+		});
+
+		let elapsed = now.duration_since(bucket.updated_at).as_secs_f64();
+
+		bucket.tokens = (bucket.tokens + elapsed * self.refill_per_second).min(self.capacity);
+		bucket.updated_at = now;
+
+		if bucket.tokens >= 1.0 {
+			bucket.tokens -= tokens;
+
+			Ok(())
+		} else if self.refill_per_second > 0.0 {
+			Err(Duration::from_secs_f64(
+				(1.0 - bucket.tokens) / self.refill_per_second,
+			))
+		} else {
+			// A limit of 0 requests is never refilled
+			Err(self.period)
+		}
+	}
+}
+
+/// The 429 of a client out of requests, retrying once the duration elapsed
+pub fn too_many_requests(retry_after: Duration) -> ActixError {
+	tracing::warn!(
+		retry_after_ms = retry_after.as_millis() as u64,
+		"Rate limit exceeded"
+	);
+
+	let response = ActixResponse::TooManyRequests()
+		.insert_header(("Retry-After", retry_after.as_secs().max(1).to_string()))
+		.finish();
+
+	InternalError::from_response("Too many requests", response).into()
+}
+
+fn today() -> (u64, Duration) {
+	let now = SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|now| now.as_secs())
+		.unwrap_or_default();
+
+	(
+		now / SECONDS_PER_DAY,
+		Duration::from_secs(SECONDS_PER_DAY - now % SECONDS_PER_DAY),
+	)
+}
+
+/// The most generous budget of the roles, `None` when one of them is not limited
+fn role_budget<'a>(limiters: &'a Limiters, claims: &Claims) -> Option<(&'a str, &'a RoleBudget)> {
+	let mut budgets = Vec::new();
+
+	for role in &claims.roles {
+		budgets.push(limiters.roles.get_key_value(role)?);
+	}
+
+	budgets
+		.into_iter()
+		.max_by(|(_, a), (_, b)| {
+			let requests = |budget: &RoleBudget| {
+				budget
+					.limiter
+					.as_ref()
+					.map(|limiter| limiter.capacity)
+					.unwrap_or(f64::INFINITY)
+			};
+			let rows = |budget: &RoleBudget| budget.rows_per_day.unwrap_or(u64::MAX);
+
+			requests(a)
+				.partial_cmp(&requests(b))
+				.unwrap_or(std::cmp::Ordering::Equal)
+				.then(rows(a).cmp(&rows(b)))
+		})
+		.map(|(role, budget)| (role.as_str(), budget))
+}
+
+/// Check the budget of the roles of an authenticated client, identified by its user ID or its IP,
+/// or return how long it has to wait
+pub fn check_role_budget(claims: Option<&Claims>, client: &str) -> Result<(), Duration> {
+	let limiters = limiters();
+
+	let (role, budget) = match claims.and_then(|claims| role_budget(&limiters, claims)) {
+		Some(budget) => budget,
+		None => return Ok(()),
+	};
+
+	let key = format!("{}|{}", role, client);
+
+	if let Some(limiter) = &budget.limiter {
+		limiter.check(&key)?;
+	}
+
+	if let Some(rows_per_day) = budget.rows_per_day {
+		let (day, until_tomorrow) = today();
+
+		if let Some(rows) = ROWS_READ.lock().unwrap().on(day).get(&key) {
+			if *rows >= rows_per_day {
+				return Err(until_tomorrow);
+			}
+		}
+	}
+
+	Ok(())
+}
+
+/// Count the rows of a response against the daily quota of the client
+pub fn record_rows_read(claims: Option<&Claims>, client: &str, body: &[u8]) {
+	add_rows_read(claims, client, || {
+		serde_json::from_slice::<JsonValue>(body)
+			.ok()
+			.map(|response| count_rows(&response))
+	});
+}
+
+/// Count the documents of a list written to the response as they are read, along with the
+/// objects nested in them, against the daily quota of the client
+pub fn record_documents_read(claims: Option<&Claims>, client: &str, documents: &[Box<RawValue>]) {
+	add_rows_read(claims, client, || {
+		Some(
+			documents
+				.iter()
+				.filter_map(|document| serde_json::from_str::<JsonValue>(document.get()).ok())
+				.map(|document| count_nested_rows(&document, true))
+				.sum(),
+		)
+	});
+}
+
+/// The rows are only counted when the role has a quota
+fn add_rows_read(claims: Option<&Claims>, client: &str, rows: impl FnOnce() -> Option<u64>) {
+	let limiters = limiters();
+
+	let role = match claims.and_then(|claims| role_budget(&limiters, claims)) {
+		Some((role, budget)) if budget.rows_per_day.is_some() => role,
+		_ => return,
+	};
+
+	let rows = match rows() {
+		Some(rows) => rows,
+		None => return,
+	};
+
+	let (day, _) = today();
+	*ROWS_READ
+		.lock()
+		.unwrap()
+		.on(day)
+		.entry(format!("{}|{}", role, client))
+		.or_insert(0) += rows;
+}
+
+/// The objects of a value returned in lists, the value itself when it is an item of one
+fn count_nested_rows(value: &JsonValue, in_list: bool) -> u64 {
+	match value {
+		JsonValue::Array(items) => items.iter().map(|item| count_nested_rows(item, true)).sum(),
+		JsonValue::Object(fields) => {
+			let nested: u64 = fields
+				.values()
+				.map(|value| count_nested_rows(value, false))
+				.sum();
+
+			nested + if in_list { 1 } else { 0 }
+		}
+		_ => 0,
+	}
+}
+
+/// The objects returned in lists, or as a single result, by the operations of a response
+fn count_rows(response: &JsonValue) -> u64 {
+	match response {
+		JsonValue::Array(responses) => responses.iter().map(count_rows).sum(),
+		response => response
+			.get("data")
+			.and_then(|data| data.as_object())
+			.map(|fields| {
+				fields
+					.values()
+					.map(|value| match value {
+						JsonValue::Object(_) => 1 + count_nested_rows(value, false),
+						value => count_nested_rows(value, false),
+					})
+					.sum()
+			})
+			.unwrap_or(0),
+	}
+}
+
+pub fn client_ip(req: &ServiceRequest) -> Option<String> {
+	req.peer_addr().map(|address| address.ip().to_string())
+}
+
+/// Reject the request with a 429 once its IP ran out of tokens, a request sending an API key is
+/// only charged to its IP when the key is not verified, verified keys have a budget of their own
+pub async fn with_rate_limit<B, F>(
+	req: ServiceRequest,
+	call: impl FnOnce(ServiceRequest) -> F,
+) -> Result<ServiceResponse<B>, ActixError>
+where
+	F: Future<Output = Result<ServiceResponse<B>, ActixError>>,
+{
+	let limiters = limiters();
+	let address = client_ip(&req);
+
+	// The key is only verified when authentication is enabled, it identifies no one otherwise
+	let keyed = CONFIG.auth_enabled() && req.headers().contains_key(API_KEY_HEADER);
+
+	let ip_limiter = match (limiters.ip.as_ref(), address.as_deref()) {
+		(Some(limiter), Some(address)) => Some((limiter, address)),
+		_ => None,
+	};
+
+	if let Some((limiter, address)) = ip_limiter {
+		let outcome = if keyed {
+			limiter.peek(address)
+		} else {
+			limiter.check(address)
+		};
+
+		outcome.map_err(too_many_requests)?;
+	}
+
+	let response = call(req).await;
+
+	// A key that was not charged once verified costs a token, whether it was refused or sent to a
+	// route that does not read keys, guessing keys or secrets is then limited like any anonymous
+	// request
+	if let (true, Some((limiter, address))) = (keyed, ip_limiter) {
+		let charged = match &response {
+			Ok(response) => response.request().extensions().contains::<ChargedRequest>(),
+			Err(e) => e.as_response_error().status_code() == StatusCode::TOO_MANY_REQUESTS,
+		};
+
+		if !charged {
+			let _ = limiter.check(address);
+		}
+	}
+
+	response
+}
+
+/// Marks a request charged by its authentication, to the budget of its key or to its IP
+pub struct ChargedRequest;
+
+/// Take a token of the IP of a client, when requests are limited per IP
+pub fn charge_ip(address: Option<&str>) -> Result<(), Duration> {
+	match (limiters().ip.as_ref(), address) {
+		(Some(limiter), Some(address)) => limiter.check(address),
+		_ => Ok(()),
+	}
+}
+
+/// Charge an authenticated client sending an API key to the budget of the verified key, or to
+/// its IP when the credentials were verified by other means, such as the auth webhook
+pub fn charge_api_key(claims: &Claims, address: Option<&str>) -> Result<(), Duration> {
+	let limiters = limiters();
+
+	match &claims.api_key {
+		Some(api_key) => match &limiters.api_key {
+			Some(limiter) => limiter.check(api_key),
+			None => Ok(()),
+		},
+		None => charge_ip(address),
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use std::time::Duration;
+
+	use super::{RateLimiter, RowsRead};
+
+	#[test]
+	fn limiters_refuse_requests_once_out_of_tokens() {
+		let limiter = RateLimiter::new(1, Duration::from_secs(60));
+
+		assert!(limiter.check("client").is_ok());
+		assert!(limiter.check("client").is_err());
+		assert!(limiter.check("other").is_ok());
+	}
+
+	#[test]
+	fn limiters_of_zero_requests_refuse_every_request() {
+		let limiter = RateLimiter::new(0, Duration::from_secs(60));
+
+		assert_eq!(limiter.check("client"), Err(Duration::from_secs(60)));
+		assert_eq!(limiter.peek("client"), Err(Duration::from_secs(60)));
+	}
+
+	#[test]
+	fn rows_read_on_a_previous_day_are_dropped() {
+		let mut read = RowsRead::default();
+
+		read.on(1).insert(String::from("reader|10.0.0.1"), 5);
+		read.on(1).insert(String::from("reader|10.0.0.2"), 3);
+
+		assert_eq!(read.on(1).len(), 2);
+		assert!(read.on(2).is_empty());
+	}
+}
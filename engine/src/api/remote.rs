@@ -0,0 +1,884 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_web::http::header::HeaderMap;
+use anyhow::{anyhow, Error};
+use graphql_parser::schema::{
+	Definition, Field as SdlField, InputValue as SdlInputValue, Type as SdlType, TypeDefinition,
+};
+use juniper::meta::{Argument, EnumValue, Field, MetaType};
+use juniper::parser::ScalarToken;
+use juniper::{
+	Executor, FieldError, FromInputValue, GraphQLType, GraphQLValue, InputValue, LookAheadMethods,
+	LookAheadSelection, LookAheadValue, Object, ParseScalarResult, ParseScalarValue, Registry,
+	ScalarValue, Type, Value, ID,
+};
+use lazy_static::lazy_static;
+use reqwest::Client;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::actions::{load_actions, resolve_action};
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{error_extensions, ErrorCode};
+use crate::lib::CONFIG;
+
+/// Types of the GraphQL specification, every schema shares them
+const BUILT_IN_SCALARS: [&str; 5] = ["Int", "Float", "String", "Boolean", "ID"];
+
+const INTROSPECTION_QUERY: &str = "
+	query IntrospectionQuery {
+		__schema {
+			queryType { name }
+			mutationType { name }
+			types {
+				kind
+				name
+				description
+				fields(includeDeprecated: true) {
+					name
+					description
+					args { name description type { ...TypeRef } }
+					type { ...TypeRef }
+				}
+				inputFields { name description type { ...TypeRef } }
+				interfaces { ...TypeRef }
+				enumValues(includeDeprecated: true) { name description }
+				possibleTypes { ...TypeRef }
+			}
+		}
+	}
+
+	fragment TypeRef on __Type {
+		kind
+		name
+		ofType {
+			kind
+			name
+			ofType {
+				kind
+				name
+				ofType {
+					kind
+					name
+					ofType { kind name ofType { kind name ofType { kind name } } }
+				}
+			}
+		}
+	}
+";
+
+lazy_static! {
+	static ref CLIENT: Client = Client::builder()
+		.timeout(Duration::from_millis(CONFIG.remote_schema_timeout_ms))
+		.build()
+		.expect("Failed to build the remote schema client");
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct TypeRef {
+	kind: String,
+	name: Option<String>,
+	of_type: Option<Box<TypeRef>>,
+}
+
+impl TypeRef {
+	/// The named type wrapped by the lists and non-null modifiers
+	fn named(&self) -> &str {
+		match &self.of_type {
+			Some(of_type) => of_type.named(),
+			None => self.name.as_deref().unwrap_or_default(),
+		}
+	}
+
+	fn to_type(&self) -> Type<'static> {
+		match (self.kind.as_str(), &self.of_type) {
+			("NON_NULL", Some(of_type)) => match of_type.to_type() {
+				Type::Named(name) => Type::NonNullNamed(name),
+				Type::List(item) => Type::NonNullList(item),
+				item => item,
+			},
+			("LIST", Some(of_type)) => Type::List(Box::new(of_type.to_type())),
+			_ => Type::Named(Cow::Owned(self.named().to_string())),
+		}
+	}
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RemoteInputValue {
+	name: String,
+	description: Option<String>,
+	r#type: TypeRef,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RemoteField {
+	name: String,
+	description: Option<String>,
+	#[serde(default)]
+	args: Vec<RemoteInputValue>,
+	r#type: TypeRef,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+struct RemoteEnumValue {
+	name: String,
+	description: Option<String>,
+}
+
+#[derive(Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+struct RemoteTypeDefinition {
+	kind: String,
+	name: String,
+	description: Option<String>,
+	fields: Option<Vec<RemoteField>>,
+	input_fields: Option<Vec<RemoteInputValue>>,
+	interfaces: Option<Vec<TypeRef>>,
+	enum_values: Option<Vec<RemoteEnumValue>>,
+	possible_types: Option<Vec<TypeRef>>,
+}
+
+#[derive(Deserialize)]
+struct RootTypeName {
+	name: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct IntrospectedSchema {
+	query_type: Option<RootTypeName>,
+	mutation_type: Option<RootTypeName>,
+	types: Vec<RemoteTypeDefinition>,
+}
+
+/// How the root fields of a remote schema are resolved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum RemoteKind {
+	/// Proxied as GraphQL to an endpoint that was introspected
+	GraphQL,
+	/// Declared in the metadata, resolved by a REST endpoint, see `crate::api::actions`
+	Action,
+}
+
+/// The schema of an external GraphQL endpoint, its root fields are merged into the API
+#[derive(Debug)]
+pub struct RemoteSchema {
+	pub name: String,
+	pub url: String,
+	pub kind: RemoteKind,
+	/// Overrides the timeout of the client
+	pub timeout: Option<Duration>,
+	query_type: Option<String>,
+	mutation_type: Option<String>,
+	types: HashMap<String, RemoteTypeDefinition>,
+}
+
+impl RemoteSchema {
+	fn root_fields(&self, root: Option<&String>) -> &[RemoteField] {
+		root.and_then(|root| self.types.get(root))
+			.and_then(|root| root.fields.as_deref())
+			.unwrap_or_default()
+	}
+
+	pub fn query_fields(&self) -> impl Iterator<Item = &str> {
+		self.root_fields(self.query_type.as_ref())
+			.iter()
+			.map(|field| field.name.as_str())
+	}
+
+	pub fn mutation_fields(&self) -> impl Iterator<Item = &str> {
+		self.root_fields(self.mutation_type.as_ref())
+			.iter()
+			.map(|field| field.name.as_str())
+	}
+
+	/// The named type of a field, e.g. `Book` for `[Book!]!`
+	pub fn field_type(&self, type_name: &str, field_name: &str) -> Option<&str> {
+		self.types
+			.get(type_name)?
+			.fields
+			.iter()
+			.flatten()
+			.find(|field| field.name == field_name)
+			.map(|field| field.r#type.named())
+	}
+
+	pub fn root_type(&self, mutation: bool) -> Option<&str> {
+		if mutation {
+			self.mutation_type.as_deref()
+		} else {
+			self.query_type.as_deref()
+		}
+	}
+
+	/// A schema declared in SDL rather than introspected, `type Query` and `type Mutation`
+	/// hold its root fields
+	pub fn from_sdl(name: &str, url: &str, kind: RemoteKind, sdl: &str) -> Result<Self, Error> {
+		let document = graphql_parser::parse_schema::<String>(sdl)
+			.map_err(|e| anyhow!("Invalid SDL of {}: {}", name, e))?;
+
+		let mut types = HashMap::new();
+
+		for definition in document.definitions {
+			let definition = match definition {
+				Definition::TypeDefinition(definition) => definition,
+				Definition::TypeExtension(_) => {
+					return Err(anyhow!("Type extensions are not supported in {}", name))
+				}
+				_ => continue,
+			};
+
+			let definition = sdl_type_definition(definition);
+
+			if BUILT_IN_SCALARS.contains(&definition.name.as_str()) {
+				continue;
+			}
+
+			types.insert(definition.name.clone(), definition);
+		}
+
+		let root = |root: &str| types.contains_key(root).then(|| root.to_string());
+
+		Ok(RemoteSchema {
+			name: name.to_string(),
+			url: url.to_string(),
+			kind,
+			timeout: None,
+			query_type: root("Query"),
+			mutation_type: root("Mutation"),
+			types,
+		})
+	}
+}
+
+fn sdl_type_ref(sdl_type: &SdlType<String>) -> TypeRef {
+	match sdl_type {
+		SdlType::NamedType(name) => TypeRef {
+			kind: String::from("NAMED"),
+			name: Some(name.clone()),
+			of_type: None,
+		},
+		SdlType::ListType(item) => TypeRef {
+			kind: String::from("LIST"),
+			name: None,
+			of_type: Some(Box::new(sdl_type_ref(item))),
+		},
+		SdlType::NonNullType(item) => TypeRef {
+			kind: String::from("NON_NULL"),
+			name: None,
+			of_type: Some(Box::new(sdl_type_ref(item))),
+		},
+	}
+}
+
+fn sdl_input_value(value: SdlInputValue<String>) -> RemoteInputValue {
+	RemoteInputValue {
+		name: value.name,
+		description: value.description,
+		r#type: sdl_type_ref(&value.value_type),
+	}
+}
+
+fn sdl_fields(fields: Vec<SdlField<String>>) -> Option<Vec<RemoteField>> {
+	Some(
+		fields
+			.into_iter()
+			.map(|field| RemoteField {
+				name: field.name,
+				description: field.description,
+				args: field.arguments.into_iter().map(sdl_input_value).collect(),
+				r#type: sdl_type_ref(&field.field_type),
+			})
+			.collect(),
+	)
+}
+
+fn sdl_named(names: Vec<String>) -> Option<Vec<TypeRef>> {
+	Some(
+		names
+			.into_iter()
+			.map(|name| sdl_type_ref(&SdlType::NamedType(name)))
+			.collect(),
+	)
+}
+
+fn sdl_type_definition(definition: TypeDefinition<String>) -> RemoteTypeDefinition {
+	let mut remote = RemoteTypeDefinition {
+		kind: String::new(),
+		name: String::new(),
+		description: None,
+		fields: None,
+		input_fields: None,
+		interfaces: None,
+		enum_values: None,
+		possible_types: None,
+	};
+
+	match definition {
+		TypeDefinition::Scalar(scalar) => {
+			remote.kind = String::from("SCALAR");
+			remote.name = scalar.name;
+			remote.description = scalar.description;
+		}
+		TypeDefinition::Object(object) => {
+			remote.kind = String::from("OBJECT");
+			remote.name = object.name;
+			remote.description = object.description;
+			remote.fields = sdl_fields(object.fields);
+			remote.interfaces = sdl_named(object.implements_interfaces);
+		}
+		TypeDefinition::Interface(interface) => {
+			remote.kind = String::from("INTERFACE");
+			remote.name = interface.name;
+			remote.description = interface.description;
+			remote.fields = sdl_fields(interface.fields);
+		}
+		TypeDefinition::Union(union) => {
+			remote.kind = String::from("UNION");
+			remote.name = union.name;
+			remote.description = union.description;
+			remote.possible_types = sdl_named(union.types);
+		}
+		TypeDefinition::Enum(enumeration) => {
+			remote.kind = String::from("ENUM");
+			remote.name = enumeration.name;
+			remote.description = enumeration.description;
+			remote.enum_values = Some(
+				enumeration
+					.values
+					.into_iter()
+					.map(|value| RemoteEnumValue {
+						name: value.name,
+						description: value.description,
+					})
+					.collect(),
+			);
+		}
+		TypeDefinition::InputObject(input) => {
+			remote.kind = String::from("INPUT_OBJECT");
+			remote.name = input.name;
+			remote.description = input.description;
+			remote.input_fields = Some(input.fields.into_iter().map(sdl_input_value).collect());
+		}
+	}
+
+	remote
+}
+
+async fn introspect(name: &str, url: &str) -> reqwest::Result<RemoteSchema> {
+	let response: JsonValue = CLIENT
+		.post(url)
+		.json(&json!({ "query": INTROSPECTION_QUERY }))
+		.send()
+		.await?
+		.error_for_status()?
+		.json()
+		.await?;
+
+	let schema: IntrospectedSchema = serde_json::from_value(response["data"]["__schema"].clone())
+		.unwrap_or(IntrospectedSchema {
+			query_type: None,
+			mutation_type: None,
+			types: Vec::new(),
+		});
+
+	Ok(RemoteSchema {
+		name: name.to_string(),
+		url: url.to_string(),
+		kind: RemoteKind::GraphQL,
+		timeout: None,
+		query_type: schema.query_type.map(|root| root.name),
+		mutation_type: schema.mutation_type.map(|root| root.name),
+		types: schema
+			.types
+			.into_iter()
+			// The introspection types and scalars of the specification are provided by juniper
+			.filter(|t| !t.name.starts_with("__") && !BUILT_IN_SCALARS.contains(&t.name.as_str()))
+			.map(|t| (t.name.clone(), t))
+			.collect(),
+	})
+}
+
+/// Fetch the schemas of the configured endpoints, written as `name=url`,
+/// the unreachable ones are left out until the schema is reloaded
+pub async fn load_remote_schemas() -> Vec<Arc<RemoteSchema>> {
+	let mut schemas = Vec::new();
+
+	for entry in CONFIG.remote_schemas.iter().flatten() {
+		let (name, url) = match entry.split_once('=') {
+			Some((name, url)) => (name.trim(), url.trim()),
+			None => {
+				tracing::warn!(remote = %entry, "Ignoring an invalid remote schema");
+
+				continue;
+			}
+		};
+
+		match introspect(name, url).await {
+			Ok(schema) => {
+				tracing::info!(remote = %name, types = schema.types.len(), "Remote schema loaded");
+
+				schemas.push(Arc::new(schema));
+			}
+			Err(e) => {
+				tracing::error!(remote = %name, url = %url, error = %e, "Failed to load the remote schema")
+			}
+		}
+	}
+
+	// The actions are declared like remote schemas, after them
+	schemas.extend(load_actions().await);
+
+	schemas
+}
+
+/// The headers of a request forwarded to the remote schemas
+pub fn remote_headers(headers: &HeaderMap) -> Vec<(String, String)> {
+	CONFIG
+		.remote_schema_forward_headers
+		.iter()
+		.filter_map(|name| {
+			let value = headers.get(name.as_str())?.to_str().ok()?;
+
+			Some((name.clone(), value.to_string()))
+		})
+		.collect()
+}
+
+/// A type of a remote schema, registered under its own name
+pub struct RemoteType;
+
+#[derive(Clone)]
+pub struct RemoteTypeInfo {
+	schema: Arc<RemoteSchema>,
+	name: String,
+}
+
+fn type_info(schema: &Arc<RemoteSchema>, name: &str) -> RemoteTypeInfo {
+	RemoteTypeInfo {
+		schema: schema.clone(),
+		name: name.to_string(),
+	}
+}
+
+fn build_field<'r, S>(
+	registry: &mut Registry<'r, S>,
+	schema: &Arc<RemoteSchema>,
+	field: &RemoteField,
+) -> Field<'r, S>
+where
+	S: ScalarValue + 'r,
+{
+	let name = field.name.as_str();
+
+	let mut meta = match field.r#type.named() {
+		"Int" => registry.field::<i32>(name, &()),
+		"Float" => registry.field::<f64>(name, &()),
+		"String" => registry.field::<String>(name, &()),
+		"Boolean" => registry.field::<bool>(name, &()),
+		"ID" => registry.field::<ID>(name, &()),
+		named => registry.field::<RemoteType>(name, &type_info(schema, named)),
+	};
+
+	meta.field_type = field.r#type.to_type();
+
+	for argument in &field.args {
+		meta = meta.argument(build_argument(registry, schema, argument));
+	}
+
+	match &field.description {
+		Some(description) => meta.description(description),
+		None => meta,
+	}
+}
+
+fn build_argument<'r, S>(
+	registry: &mut Registry<'r, S>,
+	schema: &Arc<RemoteSchema>,
+	argument: &RemoteInputValue,
+) -> Argument<'r, S>
+where
+	S: ScalarValue + 'r,
+{
+	let name = argument.name.as_str();
+
+	let mut meta = match argument.r#type.named() {
+		"Int" => registry.arg::<Option<i32>>(name, &()),
+		"Float" => registry.arg::<Option<f64>>(name, &()),
+		"String" => registry.arg::<Option<String>>(name, &()),
+		"Boolean" => registry.arg::<Option<bool>>(name, &()),
+		"ID" => registry.arg::<Option<ID>>(name, &()),
+		named => registry.arg::<Option<RemoteType>>(name, &type_info(schema, named)),
+	};
+
+	meta.arg_type = argument.r#type.to_type();
+
+	match &argument.description {
+		Some(description) => meta.description(description),
+		None => meta,
+	}
+}
+
+impl<S> GraphQLType<S> for RemoteType
+where
+	S: ScalarValue,
+{
+	fn name(info: &Self::TypeInfo) -> Option<&str> {
+		Some(info.name.as_str())
+	}
+
+	fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
+	where
+		S: 'r,
+	{
+		let definition = match info.schema.types.get(&info.name) {
+			Some(definition) => definition,
+			// A dangling reference, exposed as an opaque scalar
+			None => return registry.build_scalar_type::<RemoteType>(info).into_meta(),
+		};
+
+		let fields: Vec<Field<'r, S>> = definition
+			.fields
+			.iter()
+			.flatten()
+			.map(|field| build_field(registry, &info.schema, field))
+			.collect();
+
+		let mut meta = match definition.kind.as_str() {
+			"OBJECT" => {
+				let interfaces: Vec<Type<'r>> = definition
+					.interfaces
+					.iter()
+					.flatten()
+					.map(|interface| {
+						registry.get_type::<RemoteType>(&type_info(&info.schema, interface.named()))
+					})
+					.collect();
+
+				registry
+					.build_object_type::<RemoteType>(info, &fields)
+					.interfaces(&interfaces)
+					.into_meta()
+			}
+			"INTERFACE" => registry
+				.build_interface_type::<RemoteType>(info, &fields)
+				.into_meta(),
+			"UNION" => {
+				let types: Vec<Type<'r>> = definition
+					.possible_types
+					.iter()
+					.flatten()
+					.map(|t| registry.get_type::<RemoteType>(&type_info(&info.schema, t.named())))
+					.collect();
+
+				registry
+					.build_union_type::<RemoteType>(info, &types)
+					.into_meta()
+			}
+			"ENUM" => {
+				let values: Vec<EnumValue> = definition
+					.enum_values
+					.iter()
+					.flatten()
+					.map(|value| match &value.description {
+						Some(description) => EnumValue::new(&value.name).description(description),
+						None => EnumValue::new(&value.name),
+					})
+					.collect();
+
+				registry
+					.build_enum_type::<RemoteType>(info, &values)
+					.into_meta()
+			}
+			"INPUT_OBJECT" => {
+				let arguments: Vec<Argument<'r, S>> = definition
+					.input_fields
+					.iter()
+					.flatten()
+					.map(|field| build_argument(registry, &info.schema, field))
+					.collect();
+
+				registry
+					.build_input_object_type::<RemoteType>(info, &arguments)
+					.into_meta()
+			}
+			_ => registry.build_scalar_type::<RemoteType>(info).into_meta(),
+		};
+
+		let description = definition.description.clone();
+
+		match &mut meta {
+			MetaType::Object(meta) => meta.description = description,
+			MetaType::Interface(meta) => meta.description = description,
+			MetaType::Union(meta) => meta.description = description,
+			MetaType::Enum(meta) => meta.description = description,
+			MetaType::InputObject(meta) => meta.description = description,
+			MetaType::Scalar(meta) => meta.description = description,
+			_ => {}
+		}
+
+		meta
+	}
+}
+
+impl<S> GraphQLValue<S> for RemoteType
+where
+	S: ScalarValue,
+{
+	type Context = Context;
+	type TypeInfo = RemoteTypeInfo;
+
+	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+		<Self as GraphQLType<S>>::name(info)
+	}
+}
+
+/// The values are validated by the remote endpoint, which receives them as written
+impl<S> FromInputValue<S> for RemoteType
+where
+	S: ScalarValue,
+{
+	fn from_input_value(_: &InputValue<S>) -> Option<Self> {
+		Some(RemoteType)
+	}
+}
+
+impl<S> ParseScalarValue<S> for RemoteType
+where
+	S: ScalarValue,
+{
+	fn from_str(value: ScalarToken<'_>) -> ParseScalarResult<'_, S> {
+		match value {
+			ScalarToken::String(_) => <String as ParseScalarValue<S>>::from_str(value),
+			ScalarToken::Int(_) => <i32 as ParseScalarValue<S>>::from_str(value),
+			ScalarToken::Float(_) => <f64 as ParseScalarValue<S>>::from_str(value),
+		}
+	}
+}
+
+/// Build the root fields of a remote schema
+pub fn build_remote_fields<'r, S>(
+	schema: &Arc<RemoteSchema>,
+	mutation: bool,
+	skipped: &[String],
+	registry: &mut Registry<'r, S>,
+) -> Vec<Field<'r, S>>
+where
+	S: ScalarValue + 'r,
+{
+	let root = if mutation {
+		schema.mutation_type.as_ref()
+	} else {
+		schema.query_type.as_ref()
+	};
+
+	schema
+		.root_fields(root)
+		.iter()
+		.filter(|field| !field.name.starts_with("__"))
+		.filter(|field| {
+			if skipped.contains(&field.name) {
+				tracing::warn!(remote = %schema.name, field = %field.name, "Skipping a remote field named like a generated one");
+
+				return false;
+			}
+
+			true
+		})
+		.map(|field| build_field(registry, schema, field))
+		.collect()
+}
+
+fn write_value<S: ScalarValue>(value: &LookAheadValue<S>, out: &mut String) {
+	match value {
+		LookAheadValue::Null => out.push_str("null"),
+		LookAheadValue::Enum(name) => out.push_str(name),
+		LookAheadValue::Scalar(scalar) => {
+			if let Some(v) = scalar.as_int() {
+				out.push_str(&v.to_string())
+			} else if let Some(v) = scalar.as_float() {
+				out.push_str(&v.to_string())
+			} else if let Some(v) = scalar.as_boolean() {
+				out.push_str(&v.to_string())
+			} else {
+				out.push_str(&JsonValue::from(scalar.as_string().unwrap_or_default()).to_string())
+			}
+		}
+		LookAheadValue::List(items) => {
+			out.push('[');
+
+			for (index, item) in items.iter().enumerate() {
+				if index > 0 {
+					out.push(',');
+				}
+
+				write_value(item, out);
+			}
+
+			out.push(']');
+		}
+		LookAheadValue::Object(fields) => {
+			out.push('{');
+
+			for (index, (name, value)) in fields.iter().enumerate() {
+				if index > 0 {
+					out.push(',');
+				}
+
+				out.push_str(name);
+				out.push(':');
+				write_value(value, out);
+			}
+
+			out.push('}');
+		}
+	}
+}
+
+/// Write a selection back as GraphQL, with its arguments inlined since the variables are resolved
+fn write_selection<S: ScalarValue>(selection: &LookAheadSelection<S>, out: &mut String) {
+	if let Some(alias) = selection.field_alias() {
+		out.push_str(alias);
+		out.push(':');
+	}
+
+	out.push_str(selection.field_original_name());
+
+	let arguments = selection.arguments();
+
+	if !arguments.is_empty() {
+		out.push('(');
+
+		for (index, argument) in arguments.iter().enumerate() {
+			if index > 0 {
+				out.push(',');
+			}
+
+			out.push_str(argument.name());
+			out.push(':');
+			write_value(argument.value(), out);
+		}
+
+		out.push(')');
+	}
+
+	let children = selection.children();
+
+	if !children.is_empty() {
+		out.push('{');
+
+		for child in children {
+			match child.applies_for() {
+				Some(type_name) => {
+					out.push_str("...on ");
+					out.push_str(type_name);
+					out.push('{');
+					write_selection(child, out);
+					out.push('}');
+				}
+				None => write_selection(child, out),
+			}
+
+			out.push(' ');
+		}
+
+		out.push('}');
+	}
+}
+
+pub fn json_to_value<S: ScalarValue>(value: JsonValue) -> Value<S> {
+	match value {
+		JsonValue::Null => Value::null(),
+		JsonValue::Bool(value) => Value::scalar(value),
+		JsonValue::Number(number) => match number.as_i64() {
+			Some(int) if int >= i32::MIN as i64 && int <= i32::MAX as i64 => {
+				Value::scalar(int as i32)
+			}
+			_ => Value::scalar(number.as_f64().unwrap_or_default()),
+		},
+		JsonValue::String(value) => Value::scalar(value),
+		JsonValue::Array(items) => Value::list(items.into_iter().map(json_to_value).collect()),
+		JsonValue::Object(fields) => {
+			let mut object = Object::with_capacity(fields.len());
+
+			for (name, value) in fields {
+				object.add_field(name, json_to_value(value));
+			}
+
+			Value::Object(object)
+		}
+	}
+}
+
+/// Send the selection of a root field to its remote endpoint, the value it answers with
+/// is returned as is since the remote schema already resolved it
+pub async fn resolve_remote_field<S>(
+	schema: &RemoteSchema,
+	mutation: bool,
+	executor: &Executor<'_, '_, Context, S>,
+) -> Result<Value<S>, FieldError<S>>
+where
+	S: ScalarValue,
+{
+	if schema.kind == RemoteKind::Action {
+		return resolve_action(schema, mutation, executor).await;
+	}
+
+	let selection = executor.look_ahead();
+
+	let mut document = String::from(if mutation { "mutation{" } else { "query{" });
+	write_selection(&selection, &mut document);
+	document.push('}');
+
+	let response_name = selection.field_name().to_string();
+
+	let request = executor
+		.context()
+		.remote_headers
+		.iter()
+		.fold(CLIENT.post(&schema.url), |request, (name, value)| {
+			request.header(name.as_str(), value.as_str())
+		});
+
+	let response: JsonValue = request
+		.json(&json!({ "query": document }))
+		.send()
+		.await
+		.and_then(|response| response.error_for_status())
+		.map_err(|e| {
+			tracing::error!(remote = %schema.name, error = %e, "Remote schema request failed");
+
+			FieldError::new(
+				format!("Remote schema {} is unavailable", schema.name),
+				Value::Object(error_extensions(ErrorCode::Internal)),
+			)
+		})?
+		.json()
+		.await
+		.map_err(|_| {
+			FieldError::new(
+				format!("Remote schema {} answered with invalid JSON", schema.name),
+				Value::Object(error_extensions(ErrorCode::Internal)),
+			)
+		})?;
+
+	if let Some(error) = response["errors"]
+		.as_array()
+		.and_then(|errors| errors.first())
+	{
+		return Err(FieldError::new(
+			error["message"].as_str().unwrap_or("Remote schema error"),
+			json_to_value(error["extensions"].clone()),
+		));
+	}
+
+	Ok(json_to_value(
+		response["data"][response_name.as_str()].clone(),
+	))
+}
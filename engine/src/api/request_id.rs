@@ -0,0 +1,124 @@
+use std::future::Future;
+
+use actix_web::{
+	body::MessageBody,
+	dev::{ServiceRequest, ServiceResponse},
+	http::header::{HeaderMap, HeaderName, HeaderValue},
+	Error as ActixError, HttpMessage,
+};
+use opentelemetry::{global, propagation::Extractor};
+use tracing::Span;
+use tracing_actix_web::{DefaultRootSpanBuilder, RootSpanBuilder};
+use tracing_opentelemetry::OpenTelemetrySpanExt;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: &str = "x-request-id";
+
+/// Longest request ID accepted from a client, longer ones are replaced
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+tokio::task_local! {
+	static REQUEST_ID: String;
+}
+
+/// The ID of a request, stored in the request extensions
+#[derive(Clone, Debug)]
+pub struct RequestId(pub String);
+
+impl RequestId {
+	/// Reuse the ID sent by the client when it is sensible, generate one otherwise
+	pub fn from_request(req: &ServiceRequest) -> Self {
+		let provided = req
+			.headers()
+			.get(REQUEST_ID_HEADER)
+			.and_then(|value| value.to_str().ok())
+			.filter(|id| !id.is_empty() && id.len() <= MAX_REQUEST_ID_LENGTH);
+
+		match provided {
+			Some(id) => RequestId(id.to_string()),
+			None => RequestId(Uuid::new_v4().to_string()),
+		}
+	}
+}
+
+/// Assign an ID to the request and echo it in the response headers
+pub async fn with_request_id<B, F>(
+	req: ServiceRequest,
+	call: impl FnOnce(ServiceRequest) -> F,
+) -> Result<ServiceResponse<B>, ActixError>
+where
+	B: MessageBody,
+	F: Future<Output = Result<ServiceResponse<B>, ActixError>>,
+{
+	let request_id = RequestId::from_request(&req);
+
+	req.extensions_mut().insert(request_id.clone());
+
+	let mut response = call(req).await?;
+
+	if let Ok(value) = HeaderValue::from_str(&request_id.0) {
+		response
+			.headers_mut()
+			.insert(HeaderName::from_static(REQUEST_ID_HEADER), value);
+	}
+
+	Ok(response)
+}
+
+/// Run a future with the request ID available to the GraphQL resolvers
+pub async fn scope_request_id<F: Future>(request_id: String, f: F) -> F::Output {
+	REQUEST_ID.scope(request_id, f).await
+}
+
+/// The ID of the request being resolved, if any
+pub fn current_request_id() -> Option<String> {
+	REQUEST_ID.try_with(|id| id.clone()).ok()
+}
+
+struct RequestHeaders<'a>(&'a HeaderMap);
+
+impl<'a> Extractor for RequestHeaders<'a> {
+	fn get(&self, key: &str) -> Option<&str> {
+		self.0.get(key).and_then(|value| value.to_str().ok())
+	}
+
+	fn keys(&self) -> Vec<&str> {
+		self.0.keys().map(|key| key.as_str()).collect()
+	}
+}
+
+/// Root span of every HTTP request, carrying the request ID and the remote trace context
+pub struct RequestIdRootSpanBuilder;
+
+impl RootSpanBuilder for RequestIdRootSpanBuilder {
+	fn on_request_start(request: &ServiceRequest) -> Span {
+		let request_id = request
+			.extensions()
+			.get::<RequestId>()
+			.map(|id| id.0.clone())
+			.unwrap_or_default();
+
+		let span = tracing::info_span!(
+			"HTTP request",
+			http.method = %request.method(),
+			http.target = %request.uri(),
+			request_id = %request_id,
+			otel.kind = "server",
+			http.status_code = tracing::field::Empty,
+			otel.status_code = tracing::field::Empty,
+			exception.message = tracing::field::Empty,
+		);
+
+		let parent_context = global::get_text_map_propagator(|propagator| {
+			propagator.extract(&RequestHeaders(request.headers()))
+		});
+
+		span.set_parent(parent_context);
+
+		span
+	}
+
+	fn on_request_end<B>(span: Span, outcome: &Result<ServiceResponse<B>, ActixError>) {
+		DefaultRootSpanBuilder::on_request_end(span, outcome);
+	}
+}
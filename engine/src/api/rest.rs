@@ -0,0 +1,469 @@
+use actix_web::{
+	dev::Service,
+	error::ErrorInternalServerError,
+	http::{header::ACCEPT, StatusCode},
+	web::{self, Data, Json, Path, Query},
+	Error as ActixError, HttpMessage, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use juniper::http::GraphQLRequest;
+use juniper::InputValue;
+use serde::Deserialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::api::auth::{with_authentication, Claims};
+use crate::api::rate_limit::with_rate_limit;
+use crate::api::remote::remote_headers;
+use crate::api::request_id::{scope_request_id, RequestId};
+use crate::api::schema::context::Context;
+use crate::api::schema::localized::accept_language;
+use crate::api::schema::pages::is_paginated;
+use crate::api::schema::{CollectionOperation, Schemas};
+use crate::api::sessions::apply_session_change;
+use crate::lib::database::api::{DbEntity, DbScalarType};
+use crate::lib::schema::SchemaOperation;
+use crate::lib::CONFIG;
+
+/// Representation of the listed documents, flat formats write a column per readable property
+#[derive(Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ListFormat {
+	Json,
+	Csv,
+	Tsv,
+}
+
+impl ListFormat {
+	/// The format asked by the `Accept` header, when no `format` parameter is given
+	fn from_accept(req: &ActixRequest) -> Self {
+		let accept = req
+			.headers()
+			.get(ACCEPT)
+			.and_then(|value| value.to_str().ok())
+			.unwrap_or_default();
+
+		if accept.contains("text/csv") {
+			ListFormat::Csv
+		} else if accept.contains("text/tab-separated-values") {
+			ListFormat::Tsv
+		} else {
+			ListFormat::Json
+		}
+	}
+
+	fn content_type(&self) -> &'static str {
+		match self {
+			ListFormat::Json => "application/json",
+			ListFormat::Csv => "text/csv; charset=utf-8",
+			ListFormat::Tsv => "text/tab-separated-values; charset=utf-8",
+		}
+	}
+}
+
+#[derive(Deserialize)]
+pub struct ListParameters {
+	limit: Option<i32>,
+	format: Option<ListFormat>,
+}
+
+/// The readable properties, relationships are left to GraphQL
+fn readable_fields<'a>(entity: &'a DbEntity, context: &Context) -> Vec<&'a str> {
+	let allowed = context.allowed_fields(entity);
+
+	entity
+		.properties
+		.iter()
+		.filter(|property| {
+			allowed
+				.as_ref()
+				.map(|fields| fields.contains(&property.name.as_str()))
+				.unwrap_or(true)
+		})
+		.map(|property| property.field_name())
+		.collect()
+}
+
+fn selection(entity: &DbEntity, context: &Context) -> String {
+	readable_fields(entity, context)
+		.into_iter()
+		.map(|field| match entity.property_by_field(field) {
+			// Geometries are objects, their members have to be selected
+			Some(property) if property.scalar_type.geometry_type().is_some() => {
+				format!("{} {{ type coordinates }}", field)
+			}
+			_ => field.to_string(),
+		})
+		.collect::<Vec<String>>()
+		.join(" ")
+}
+
+/// The GraphQL document running the operation, its arguments are passed as variables
+fn document(operation: &CollectionOperation, kind: SchemaOperation, context: &Context) -> String {
+	let entity = &operation.entity;
+
+	let (root, variables, arguments) = match kind {
+		SchemaOperation::Get | SchemaOperation::Delete => (
+			if kind == SchemaOperation::Get {
+				"query"
+			} else {
+				"mutation"
+			},
+			String::from("$id: ID!"),
+			"id: $id",
+		),
+		SchemaOperation::Create => (
+			"mutation",
+			format!("$data: {}Input!", entity.name),
+			"data: $data",
+		),
+		SchemaOperation::Update => (
+			"mutation",
+			format!("$id: ID!, $data: {}UpdateInput!", entity.name),
+			"id: $id, data: $data",
+		),
+		_ => ("query", String::from("$limit: Int"), "limit: $limit"),
+	};
+
+	let selection = if is_paginated(kind) {
+		format!("items {{ {} }}", selection(entity, context))
+	} else {
+		selection(entity, context)
+	};
+
+	format!(
+		"{} ({}) {{ {}({}) {{ {} }} }}",
+		root, variables, operation.name, arguments, selection
+	)
+}
+
+/// The body as a mutation input, the properties exposed as JSON strings are encoded
+pub fn input_data(entity: &DbEntity, body: JsonMap<String, JsonValue>) -> JsonValue {
+	JsonValue::Object(
+		body.into_iter()
+			.map(|(field, value)| {
+				let is_object = entity
+					.property_by_field(&field)
+					.map(|property| property.scalar_type == DbScalarType::Object)
+					.unwrap_or(false);
+
+				match value {
+					JsonValue::Null => (field, value),
+					JsonValue::String(_) => (field, value),
+					value if is_object => (field, JsonValue::from(value.to_string())),
+					value => (field, value),
+				}
+			})
+			.collect(),
+	)
+}
+
+fn error_response(status: StatusCode, message: &str) -> ActixResponse {
+	ActixResponse::build(status).json(json!({ "error": message }))
+}
+
+/// A failed operation, with the HTTP status it maps to
+pub struct OperationError {
+	pub status: StatusCode,
+	pub message: String,
+}
+
+impl OperationError {
+	fn new(status: StatusCode, message: impl Into<String>) -> Self {
+		OperationError {
+			status,
+			message: message.into(),
+		}
+	}
+}
+
+/// Run a generated operation through the GraphQL schema of the roles, so that the permissions,
+/// row filters and hooks apply like they do to GraphQL requests
+pub async fn execute_operation(
+	schemas: &Schemas,
+	context: &Context,
+	request_id: String,
+	collection: &str,
+	kind: SchemaOperation,
+	variables: JsonValue,
+) -> Result<JsonValue, OperationError> {
+	let operation = schemas
+		.collection_operation(collection, kind, context.roles())
+		.ok_or_else(|| OperationError::new(StatusCode::NOT_FOUND, "Unknown collection"))?;
+
+	if !operation.allowed {
+		return Err(OperationError::new(
+			StatusCode::FORBIDDEN,
+			format!("Not allowed to run {}", operation.name),
+		));
+	}
+
+	let variables: InputValue = serde_json::from_value(variables)
+		.map_err(|e| OperationError::new(StatusCode::BAD_REQUEST, e.to_string()))?;
+
+	let request = GraphQLRequest::new(document(&operation, kind, context), None, Some(variables));
+
+	let schema = schemas.for_roles(context.roles());
+
+	let response = scope_request_id(request_id, request.execute(&schema, context)).await;
+	let response = serde_json::to_value(&response)
+		.map_err(|e| OperationError::new(StatusCode::INTERNAL_SERVER_ERROR, e.to_string()))?;
+
+	if let Some(error) = response["errors"]
+		.as_array()
+		.and_then(|errors| errors.first())
+	{
+		let message = error["message"].as_str().unwrap_or("Request failed");
+
+		let status = if message.ends_with(" not found") {
+			StatusCode::NOT_FOUND
+		} else if message.starts_with("Not allowed") {
+			StatusCode::FORBIDDEN
+		} else {
+			StatusCode::BAD_REQUEST
+		};
+
+		return Err(OperationError::new(status, message));
+	}
+
+	match response["data"][operation.name.as_str()].clone() {
+		JsonValue::Null => Err(OperationError::new(
+			StatusCode::NOT_FOUND,
+			format!("{} not found", operation.entity.name),
+		)),
+		// The REST lists stay plain arrays
+		data if is_paginated(kind) => Ok(data["items"].clone()),
+		data => Ok(data),
+	}
+}
+
+/// The id and the context of a request
+fn request_context(req: &ActixRequest) -> (String, Context) {
+	let request_id = req
+		.extensions()
+		.get::<RequestId>()
+		.map(|id| id.0.clone())
+		.unwrap_or_default();
+
+	let mut context = Context::new(req.extensions().get::<Claims>().cloned());
+	context.request_id = Some(request_id.clone());
+	context.remote_headers = remote_headers(req.headers());
+	context.locales = accept_language(req.headers());
+
+	(request_id, context)
+}
+
+/// Write the documents as delimited rows, objects and lists are written as JSON
+fn delimited(
+	columns: &[&str],
+	documents: &[JsonValue],
+	delimiter: u8,
+) -> Result<Vec<u8>, csv::Error> {
+	let mut writer = csv::WriterBuilder::new()
+		.delimiter(delimiter)
+		.from_writer(Vec::new());
+
+	writer.write_record(columns)?;
+
+	for document in documents {
+		writer.write_record(columns.iter().map(|column| match &document[*column] {
+			JsonValue::Null => String::new(),
+			JsonValue::String(s) => s.clone(),
+			value => value.to_string(),
+		}))?;
+	}
+
+	writer
+		.into_inner()
+		.map_err(|e| csv::Error::from(e.into_error()))
+}
+
+async fn run_operation(
+	req: &ActixRequest,
+	schemas: &Schemas,
+	collection: &str,
+	kind: SchemaOperation,
+	variables: JsonValue,
+) -> Result<ActixResponse, ActixError> {
+	let (request_id, context) = request_context(req);
+
+	let data =
+		match execute_operation(schemas, &context, request_id, collection, kind, variables).await {
+			Ok(data) => data,
+			Err(e) => return Ok(error_response(e.status, &e.message)),
+		};
+
+	let mut builder = match kind {
+		SchemaOperation::Create => ActixResponse::Created(),
+		_ => ActixResponse::Ok(),
+	};
+
+	if let Some(change) = context.session_change.lock().unwrap().take() {
+		apply_session_change(&mut builder, change);
+	}
+
+	Ok(builder.json(data))
+}
+
+/// `GET /{collection}`, as JSON, CSV or TSV
+pub async fn list_route(
+	req: ActixRequest,
+	collection: Path<String>,
+	parameters: Query<ListParameters>,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	let format = parameters
+		.format
+		.unwrap_or_else(|| ListFormat::from_accept(&req));
+
+	let variables = json!({ "limit": parameters.limit });
+
+	if format == ListFormat::Json {
+		return run_operation(
+			&req,
+			&schemas,
+			&collection,
+			SchemaOperation::GetAll,
+			variables,
+		)
+		.await;
+	}
+
+	let (request_id, context) = request_context(&req);
+
+	let documents = match execute_operation(
+		&schemas,
+		&context,
+		request_id,
+		&collection,
+		SchemaOperation::GetAll,
+		variables,
+	)
+	.await
+	{
+		Ok(JsonValue::Array(documents)) => documents,
+		Ok(_) => Vec::new(),
+		Err(e) => return Ok(error_response(e.status, &e.message)),
+	};
+
+	let entity = match schemas.collection_operation(&collection, SchemaOperation::GetAll, None) {
+		Some(operation) => operation.entity,
+		None => return Ok(error_response(StatusCode::NOT_FOUND, "Unknown collection")),
+	};
+
+	let delimiter = match format {
+		ListFormat::Tsv => b'\t',
+		_ => b',',
+	};
+
+	let body = delimited(&readable_fields(&entity, &context), &documents, delimiter)
+		.map_err(ErrorInternalServerError)?;
+
+	Ok(ActixResponse::Ok()
+		.content_type(format.content_type())
+		.body(body))
+}
+
+/// `GET /{collection}/{key}`
+pub async fn get_route(
+	req: ActixRequest,
+	path: Path<(String, String)>,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	let (collection, key) = path.into_inner();
+
+	run_operation(
+		&req,
+		&schemas,
+		&collection,
+		SchemaOperation::Get,
+		json!({ "id": key }),
+	)
+	.await
+}
+
+/// `POST /{collection}`
+pub async fn create_route(
+	req: ActixRequest,
+	collection: Path<String>,
+	body: Json<JsonMap<String, JsonValue>>,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	let data = match schemas.collection_operation(&collection, SchemaOperation::Create, None) {
+		Some(operation) => input_data(&operation.entity, body.into_inner()),
+		None => JsonValue::Object(body.into_inner()),
+	};
+
+	run_operation(
+		&req,
+		&schemas,
+		&collection,
+		SchemaOperation::Create,
+		json!({ "data": data }),
+	)
+	.await
+}
+
+/// `PATCH /{collection}/{key}`
+pub async fn update_route(
+	req: ActixRequest,
+	path: Path<(String, String)>,
+	body: Json<JsonMap<String, JsonValue>>,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	let (collection, key) = path.into_inner();
+
+	let data = match schemas.collection_operation(&collection, SchemaOperation::Update, None) {
+		Some(operation) => input_data(&operation.entity, body.into_inner()),
+		None => JsonValue::Object(body.into_inner()),
+	};
+
+	run_operation(
+		&req,
+		&schemas,
+		&collection,
+		SchemaOperation::Update,
+		json!({ "id": key, "data": data }),
+	)
+	.await
+}
+
+/// `DELETE /{collection}/{key}`
+pub async fn delete_route(
+	req: ActixRequest,
+	path: Path<(String, String)>,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	let (collection, key) = path.into_inner();
+
+	run_operation(
+		&req,
+		&schemas,
+		&collection,
+		SchemaOperation::Delete,
+		json!({ "id": key }),
+	)
+	.await
+}
+
+/// Register the REST routes of the generated operations, when enabled
+pub fn configure_rest(config: &mut web::ServiceConfig) {
+	if !CONFIG.rest_enabled {
+		return;
+	}
+
+	config
+		.service(
+			web::resource(format!("{}/{{collection}}", CONFIG.rest_path))
+				.wrap_fn(|req, srv| with_authentication(req, |req| srv.call(req)))
+				.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+				.route(web::get().to(list_route))
+				.route(web::post().to(create_route)),
+		)
+		.service(
+			web::resource(format!("{}/{{collection}}/{{key}}", CONFIG.rest_path))
+				.wrap_fn(|req, srv| with_authentication(req, |req| srv.call(req)))
+				.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+				.route(web::get().to(get_route))
+				.route(web::patch().to(update_route))
+				.route(web::delete().to(delete_route)),
+		);
+}
@@ -0,0 +1,203 @@
+use std::sync::Mutex;
+
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::api::auth::Claims;
+use crate::api::sessions::SessionChange;
+use crate::lib::database::api::DbEntity;
+use crate::lib::database::aql::{AQLRowCondition, AQLRowFilter};
+use crate::lib::schema::RowFilterValue;
+use crate::lib::tenants::request_tenant;
+
+/// Claims that differ between tokens of the same identity
+const VOLATILE_CLAIMS: [&str; 4] = ["exp", "iat", "nbf", "jti"];
+
+/// Per-request context of the generated API
+#[derive(Default)]
+pub struct Context {
+	/// The claims of the verified token, if any
+	pub claims: Option<Claims>,
+	/// The id of the request, named in the queries it runs
+	pub request_id: Option<String>,
+	/// Set by the login and logout mutations, applied to the cookies of the response
+	pub session_change: Mutex<Option<SessionChange>>,
+	/// Sent along with the fields proxied to the remote schemas
+	pub remote_headers: Vec<(String, String)>,
+	/// The languages of the request, the preferred first, the localized fields are read in them
+	pub locales: Vec<String>,
+	/// Reported to the client in the extensions of the response, e.g. a lowered limit
+	pub warnings: Mutex<Vec<String>>,
+}
+
+impl Context {
+	pub fn new(claims: Option<Claims>) -> Self {
+		Context {
+			claims,
+			request_id: None,
+			session_change: Mutex::new(None),
+			remote_headers: Vec::new(),
+			locales: Vec::new(),
+			warnings: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// Report a warning to the client, along with the data
+	pub fn warn(&self, warning: String) {
+		self.warnings.lock().unwrap().push(warning);
+	}
+
+	/// Add the warnings to the extensions of the response, the operations of a batch share them
+	pub fn attach_warnings(&self, response: &mut JsonValue) {
+		let warnings = self.warnings.lock().unwrap();
+
+		if warnings.is_empty() {
+			return;
+		}
+
+		let responses = match response {
+			JsonValue::Array(responses) => responses.iter_mut().collect(),
+			response => vec![response],
+		};
+
+		for response in responses {
+			if let Some(response) = response.as_object_mut() {
+				let extensions = response
+					.entry("extensions")
+					.or_insert_with(|| JsonValue::Object(JsonMap::new()));
+
+				if let Some(extensions) = extensions.as_object_mut() {
+					extensions.insert("warnings".to_string(), warnings.clone().into());
+				}
+			}
+		}
+	}
+
+	/// The roles of the verified token, `None` when requests are not authenticated
+	pub fn roles(&self) -> Option<&[String]> {
+		self.claims.as_ref().map(|claims| claims.roles.as_slice())
+	}
+
+	/// The user running the request, if known
+	pub fn user_id(&self) -> Option<String> {
+		self.claims.as_ref()?.user_id.clone()
+	}
+
+	/// The tenant whose collections the request uses, when collections are prefixed per tenant
+	pub fn tenant(&self) -> Option<String> {
+		request_tenant(self.claims.as_ref()?.tenant.as_deref())
+	}
+
+	/// The properties of the entity readable by the request, `None` when they are not restricted
+	pub fn allowed_fields<'a>(&self, entity: &'a DbEntity) -> Option<Vec<&'a str>> {
+		entity.allowed_fields(self.roles()?)
+	}
+
+	/// Identifies the requests that see the same data, row filters may depend on any claim, the
+	/// collections on the tenant and the localized fields on the languages
+	pub fn cache_scope(&self) -> Option<String> {
+		let scope = self.claims.as_ref().map(|claims| {
+			let mut identity = claims.claims.clone();
+
+			for claim in VOLATILE_CLAIMS {
+				identity.remove(claim);
+			}
+
+			format!(
+				"{}|{}|{}",
+				claims.roles.join(","),
+				claims.tenant.as_deref().unwrap_or_default(),
+				JsonValue::Object(identity)
+			)
+		});
+
+		if self.locales.is_empty() {
+			return scope;
+		}
+
+		Some(format!(
+			"{}|{}",
+			scope.unwrap_or_default(),
+			self.locales.join(",")
+		))
+	}
+
+	/// The rows of the entity visible to the request, `None` when they are not restricted
+	pub fn row_filter(&self, entity: &DbEntity) -> Option<AQLRowFilter> {
+		let claims = self.claims.as_ref()?;
+		let filters = entity.row_filters.as_ref()?;
+
+		let mut alternatives = Vec::new();
+
+		for role in &claims.roles {
+			let filter = match filters.iter().find(|filter| &filter.role == role) {
+				Some(filter) => filter,
+				// A role without filter sees every row
+				None => return None,
+			};
+
+			if let Some(conditions) = &filter.conditions {
+				alternatives.push(
+					conditions
+						.iter()
+						.map(|condition| AQLRowCondition {
+							property: condition.property.clone(),
+							operator: condition.operator,
+							value: match &condition.value {
+								RowFilterValue::Claim(path) => {
+									claim_value(&claims.claims, path).cloned()
+								}
+								RowFilterValue::Literal(value) => Some(value.clone()),
+							},
+						})
+						.collect(),
+				);
+			}
+		}
+
+		Some(AQLRowFilter { alternatives })
+	}
+}
+
+fn claim_value<'a>(
+	claims: &'a JsonMap<String, JsonValue>,
+	path: &[String],
+) -> Option<&'a JsonValue> {
+	let (first, rest) = path.split_first()?;
+
+	rest.iter()
+		.try_fold(claims.get(first)?, |value, segment| value.get(segment))
+		.filter(|value| !value.is_null())
+}
+
+impl juniper::Context for Context {}
+
+#[cfg(test)]
+mod tests {
+	use super::Context;
+	use crate::api::auth::Claims;
+
+	fn api_key(name: &str, tenant: &str) -> Claims {
+		Claims {
+			roles: vec![String::from("reader")],
+			tenant: Some(tenant.to_string()),
+			api_key: Some(name.to_string()),
+			..Default::default()
+		}
+	}
+
+	#[test]
+	fn cache_scope_differs_between_tenants() {
+		let acme = Context::new(Some(api_key("acme-key", "acme")));
+		let globex = Context::new(Some(api_key("globex-key", "globex")));
+
+		assert_ne!(acme.cache_scope(), globex.cache_scope());
+	}
+
+	#[test]
+	fn cache_scope_is_shared_within_a_tenant() {
+		let first = Context::new(Some(api_key("first-key", "acme")));
+		let second = Context::new(Some(api_key("second-key", "acme")));
+
+		assert_eq!(first.cache_scope(), second.cache_scope());
+	}
+}
@@ -1,4 +1,125 @@
-use juniper::{FieldError, IntoFieldError, ScalarValue, Value};
+use juniper::{FieldError, IntoFieldError, Object, ScalarValue, Value};
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::request_id::current_request_id;
+use crate::lib::database::backend::BackendError;
+
+/// Machine-readable category of a failure, clients branch on it rather than on the message
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum ErrorCode {
+	NotFound,
+	ValidationFailed,
+	Conflict,
+	Unauthorized,
+	DbUnavailable,
+	/// The operation ran longer than it is allowed to
+	Timeout,
+	Internal,
+}
+
+impl ErrorCode {
+	/// The errors caused by the request, their messages are always meant for the client
+	const CLIENT: [ErrorCode; 4] = [
+		ErrorCode::NotFound,
+		ErrorCode::ValidationFailed,
+		ErrorCode::Conflict,
+		ErrorCode::Unauthorized,
+	];
+
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			ErrorCode::NotFound => "NOT_FOUND",
+			ErrorCode::ValidationFailed => "VALIDATION_FAILED",
+			ErrorCode::Conflict => "CONFLICT",
+			ErrorCode::Unauthorized => "UNAUTHORIZED",
+			ErrorCode::DbUnavailable => "DB_UNAVAILABLE",
+			ErrorCode::Timeout => "TIMEOUT",
+			ErrorCode::Internal => "INTERNAL",
+		}
+	}
+}
+
+/// Message replacing the ones of the internal errors when their details are hidden
+const MASKED_MESSAGE: &str = "Internal server error";
+
+/// Whether a response, or one of a batch, has errors raised by its resolvers
+pub fn has_field_errors(response: &JsonValue) -> bool {
+	match response {
+		JsonValue::Array(responses) => responses.iter().any(has_field_errors),
+		response => response
+			.get("errors")
+			.and_then(JsonValue::as_array)
+			.map_or(false, |errors| !errors.is_empty()),
+	}
+}
+
+/// The response of a request that ran out of time, none of its results are returned
+pub fn timeout_response(request_id: &str) -> JsonValue {
+	json!({
+		"errors": [{
+			"message": "The request exceeded its time limit",
+			"extensions": { "code": ErrorCode::Timeout.as_str(), "requestId": request_id },
+		}],
+	})
+}
+
+/// Replace the messages and extensions of the errors not caused by the request with a generic
+/// message and the request ID, the details are logged instead
+pub fn mask_internal_errors(response: &mut JsonValue, request_id: &str) {
+	if let JsonValue::Array(responses) = response {
+		for response in responses {
+			mask_internal_errors(response, request_id);
+		}
+
+		return;
+	}
+
+	let errors = match response.get_mut("errors").and_then(JsonValue::as_array_mut) {
+		Some(errors) => errors,
+		None => return,
+	};
+
+	for error in errors {
+		// Errors without a path come from parsing and validating the document
+		if error.get("path").is_none() {
+			continue;
+		}
+
+		let is_client_error = error["extensions"]["code"]
+			.as_str()
+			.map(|code| ErrorCode::CLIENT.iter().any(|c| c.as_str() == code))
+			.unwrap_or(false);
+
+		if is_client_error {
+			continue;
+		}
+
+		tracing::error!(request_id = %request_id, error = %error, "Internal error masked");
+
+		error["message"] = json!(MASKED_MESSAGE);
+		error["extensions"] = json!({
+			"code": error["extensions"]["code"]
+				.as_str()
+				.unwrap_or(ErrorCode::Internal.as_str())
+				.to_string(),
+			"requestId": request_id,
+		});
+	}
+}
+
+/// Error extensions carrying the code of the error and the request ID, so that a failure can be
+/// matched with the logs
+pub fn error_extensions<S: ScalarValue>(code: ErrorCode) -> Object<S> {
+	let mut extensions = Object::with_capacity(2);
+
+	extensions.add_field("code", Value::scalar(code.as_str().to_string()));
+
+	if let Some(request_id) = current_request_id() {
+		extensions.add_field("requestId", Value::scalar(request_id));
+	}
+
+	extensions
+}
 
 pub struct NotFoundError {
 	model: String,
@@ -12,6 +133,310 @@ impl NotFoundError {
 
 impl<S: ScalarValue> IntoFieldError<S> for NotFoundError {
 	fn into_field_error(self) -> FieldError<S> {
-		FieldError::new(format!("{} not found", self.model), Value::Null)
+		FieldError::new(
+			format!("{} not found", self.model),
+			Value::Object(error_extensions(ErrorCode::NotFound)),
+		)
+	}
+}
+
+pub struct ForbiddenError {
+	operation: String,
+}
+
+impl ForbiddenError {
+	pub fn new(operation: String) -> Self {
+		Self { operation }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for ForbiddenError {
+	fn into_field_error(self) -> FieldError<S> {
+		FieldError::new(
+			format!("Not allowed to run {}", self.operation),
+			Value::Object(error_extensions(ErrorCode::Unauthorized)),
+		)
+	}
+}
+
+/// A request without a valid tenant while collections are prefixed per tenant
+pub struct MissingTenantError;
+
+impl<S: ScalarValue> IntoFieldError<S> for MissingTenantError {
+	fn into_field_error(self) -> FieldError<S> {
+		FieldError::new(
+			"The request has no tenant",
+			Value::Object(error_extensions(ErrorCode::Unauthorized)),
+		)
+	}
+}
+
+pub struct LimitExceededError {
+	limit: i32,
+	max: i32,
+}
+
+impl LimitExceededError {
+	pub fn new(limit: i32, max: i32) -> Self {
+		Self { limit, max }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for LimitExceededError {
+	fn into_field_error(self) -> FieldError<S> {
+		FieldError::new(
+			format!("limit {} exceeds the maximum of {}", self.limit, self.max),
+			Value::Object(error_extensions(ErrorCode::ValidationFailed)),
+		)
+	}
+}
+
+/// A list operation asked for fewer than 0 documents
+pub struct NegativeLimitError {
+	limit: i32,
+}
+
+impl NegativeLimitError {
+	pub fn new(limit: i32) -> Self {
+		Self { limit }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for NegativeLimitError {
+	fn into_field_error(self) -> FieldError<S> {
+		FieldError::new(
+			format!("limit {} must not be negative", self.limit),
+			Value::Object(error_extensions(ErrorCode::ValidationFailed)),
+		)
+	}
+}
+
+/// An operation that was not resolved in the time allowed to resolvers
+pub struct TimeoutError {
+	operation: String,
+}
+
+impl TimeoutError {
+	pub fn new(operation: String) -> Self {
+		Self { operation }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for TimeoutError {
+	fn into_field_error(self) -> FieldError<S> {
+		FieldError::new(
+			format!("{} exceeded its time limit", self.operation),
+			Value::Object(error_extensions(ErrorCode::Timeout)),
+		)
+	}
+}
+
+/// A date and time argument that is not ISO 8601
+pub struct InvalidDateTimeError {
+	argument: String,
+}
+
+impl InvalidDateTimeError {
+	pub fn new(argument: String) -> Self {
+		Self { argument }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for InvalidDateTimeError {
+	fn into_field_error(self) -> FieldError<S> {
+		let mut extensions = error_extensions(ErrorCode::ValidationFailed);
+
+		extensions.add_field("argument", Value::scalar(self.argument.clone()));
+
+		FieldError::new(
+			format!("{} must be an ISO 8601 date and time", self.argument),
+			Value::Object(extensions),
+		)
+	}
+}
+
+/// A filter argument that cannot be applied to the field it names
+pub struct InvalidFilterError {
+	field: String,
+	message: String,
+}
+
+impl InvalidFilterError {
+	pub fn new(field: String, message: String) -> Self {
+		Self { field, message }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for InvalidFilterError {
+	fn into_field_error(self) -> FieldError<S> {
+		let mut extensions = error_extensions(ErrorCode::ValidationFailed);
+
+		extensions.add_field("field", Value::scalar(self.field));
+
+		FieldError::new(self.message, Value::Object(extensions))
+	}
+}
+
+/// A write conflicting with a unique constraint, named after the GraphQL fields
+pub struct UniqueConstraintViolation {
+	model: String,
+	fields: Vec<String>,
+}
+
+impl UniqueConstraintViolation {
+	pub fn new(model: String, fields: Vec<String>) -> Self {
+		Self { model, fields }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for UniqueConstraintViolation {
+	fn into_field_error(self) -> FieldError<S> {
+		let mut extensions = error_extensions::<S>(ErrorCode::Conflict);
+
+		extensions.add_field(
+			"type",
+			Value::scalar("UniqueConstraintViolation".to_string()),
+		);
+		extensions.add_field(
+			"fields",
+			Value::list(self.fields.iter().cloned().map(Value::scalar).collect()),
+		);
+
+		FieldError::new(
+			format!(
+				"A {} with the same {} already exists",
+				self.model,
+				self.fields.join(", ")
+			),
+			Value::Object(extensions),
+		)
+	}
+}
+
+/// A write referencing a document of another collection that does not exist
+pub struct ReferenceNotFoundError {
+	field: String,
+	collection: String,
+}
+
+impl ReferenceNotFoundError {
+	pub fn new(field: String, collection: String) -> Self {
+		Self { field, collection }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for ReferenceNotFoundError {
+	fn into_field_error(self) -> FieldError<S> {
+		let mut extensions = error_extensions::<S>(ErrorCode::ValidationFailed);
+
+		extensions.add_field("type", Value::scalar("ReferenceNotFound".to_string()));
+		extensions.add_field("field", Value::scalar(self.field.clone()));
+
+		FieldError::new(
+			format!(
+				"{} references a document of {} that does not exist",
+				self.field, self.collection
+			),
+			Value::Object(extensions),
+		)
+	}
+}
+
+/// A written document not satisfying a check of its collection
+pub struct CheckConstraintViolation {
+	name: String,
+	message: String,
+}
+
+impl CheckConstraintViolation {
+	pub fn new(name: String, message: String) -> Self {
+		Self { name, message }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for CheckConstraintViolation {
+	fn into_field_error(self) -> FieldError<S> {
+		let mut extensions = error_extensions::<S>(ErrorCode::ValidationFailed);
+
+		extensions.add_field(
+			"type",
+			Value::scalar("CheckConstraintViolation".to_string()),
+		);
+		extensions.add_field("check", Value::scalar(self.name));
+
+		FieldError::new(self.message, Value::Object(extensions))
+	}
+}
+
+/// ArangoDB error numbers reported as something else than an internal error
+const ERROR_ARANGO_CONFLICT: u16 = 1200;
+const ERROR_ARANGO_DOCUMENT_NOT_FOUND: u16 = 1202;
+pub const ERROR_ARANGO_UNIQUE_CONSTRAINT_VIOLATED: u16 = 1210;
+const ERROR_ARANGO_READ_ONLY: u16 = 1004;
+const ERROR_SHUTTING_DOWN: u16 = 30;
+/// A query killed, e.g. when it exceeds its maximum runtime
+const ERROR_QUERY_KILLED: u16 = 1500;
+/// The cluster errors, e.g. a coordinator not reaching a DB server
+const ERROR_CLUSTER: std::ops::RangeInclusive<u16> = 1400..=1499;
+
+/// A failed query, reported from the error of ArangoDB rather than as a missing document
+pub struct DatabaseError {
+	model: String,
+	error: BackendError,
+}
+
+impl DatabaseError {
+	pub fn new(model: String, error: BackendError) -> Self {
+		Self { model, error }
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for DatabaseError {
+	fn into_field_error(self) -> FieldError<S> {
+		let (code, message) = match &self.error {
+			BackendError::Database {
+				code, error_num, ..
+			} => match *error_num {
+				ERROR_ARANGO_DOCUMENT_NOT_FOUND => {
+					return NotFoundError::new(self.model).into_field_error()
+				}
+				ERROR_ARANGO_CONFLICT => (
+					ErrorCode::Conflict,
+					format!("The {} was changed by another request", self.model),
+				),
+				ERROR_ARANGO_UNIQUE_CONSTRAINT_VIOLATED => (
+					ErrorCode::Conflict,
+					format!("The {} conflicts with an existing one", self.model),
+				),
+				ERROR_QUERY_KILLED => (
+					ErrorCode::Timeout,
+					format!("The query of the {} exceeded its time limit", self.model),
+				),
+				num if num == ERROR_ARANGO_READ_ONLY
+					|| num == ERROR_SHUTTING_DOWN
+					|| ERROR_CLUSTER.contains(&num)
+					|| *code == 503 =>
+				{
+					(
+						ErrorCode::DbUnavailable,
+						"The database is unavailable".to_string(),
+					)
+				}
+				_ => (
+					ErrorCode::Internal,
+					format!("Failed to query the {}", self.model),
+				),
+			},
+			BackendError::Unavailable(_) => (
+				ErrorCode::DbUnavailable,
+				"The database is unavailable".to_string(),
+			),
+			_ => (
+				ErrorCode::Internal,
+				format!("Failed to query the {}", self.model),
+			),
+		};
+
+		FieldError::new(message, Value::Object(error_extensions(code)))
 	}
 }
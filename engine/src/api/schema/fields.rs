@@ -1,14 +1,34 @@
 use crate::api::schema::enums::{DbEnumInfo, GraphQLEnum};
+use actix_web::rt;
 use juniper::meta::{Field, MetaType};
 use juniper::{
 	Arguments, BoxFuture, ExecutionResult, Executor, GraphQLType, GraphQLValue, GraphQLValueAsync,
-	Registry, ScalarValue, Selection, Spanning, Value,
+	InputValue, IntoFieldError, Registry, ScalarValue, Selection, Spanning, Value, Variables,
 };
-
-use crate::api::schema::operations::{OperationData, OperationEntry};
+use serde_json::Value as JsonValue;
+use std::time::{Duration, Instant};
+
+use crate::api::cache::{record_mutation, record_read};
+use crate::api::metrics::{record_operation, returned_rows};
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{ForbiddenError, MissingTenantError, TimeoutError};
+use crate::api::schema::geo::{GeoPoint, GeoPolygon};
+use crate::api::schema::localized::fallback_locales;
+use crate::api::schema::operations::{
+	fetch_document, OperationData, OperationEntry, OperationKind,
+};
+use crate::api::schema::pages::{is_paginated, items_selection, select_page};
+use crate::api::schema::scalars::{DateTime, Decimal};
 use crate::api::schema::{owns_relationship, QueryData};
-use crate::lib::database::api::{DbProperty, DbRelationship, DbRelationshipType, DbScalarType};
+use crate::api::sessions::is_password_property;
+use crate::lib::changes::{ChangeEvent, ChangeOperation};
+use crate::lib::database::api::{
+	DbEntity, DbProperty, DbRelationship, DbRelationshipDirection, DbRelationshipType, DbScalarType,
+};
 use crate::lib::database::aql::{AQLProperty, AQLQuery, AQLQueryRelationship};
+use crate::lib::database::backend::{record_query_timings, with_query_scope, QueryScope};
+use crate::lib::tenants::tenant_collection;
+use crate::lib::CONFIG;
 
 pub struct QueryFieldFactory;
 
@@ -27,7 +47,7 @@ impl QueryFieldFactory {
 
 		let args = operation.arguments_closure;
 
-		for arg in args(registry) {
+		for arg in args(registry, &operation.data) {
 			field = field.argument(arg);
 		}
 
@@ -72,9 +92,9 @@ where
 		let is_array = matches!(property.scalar_type, DbScalarType::Array(_));
 
 		if required && !is_array {
-			registry.field::<T>(property.name.as_str(), info)
+			registry.field::<T>(property.field_name(), info)
 		} else {
-			registry.field::<Option<T>>(property.name.as_str(), info)
+			registry.field::<Option<T>>(property.field_name(), info)
 		}
 	}
 
@@ -109,6 +129,25 @@ where
 		DbScalarType::Float => build_field::<f64, S>(registry, property, property.required, &()),
 		DbScalarType::Int => build_field::<i32, S>(registry, property, property.required, &()),
 		DbScalarType::Boolean => build_field::<bool, S>(registry, property, property.required, &()),
+		DbScalarType::DateTime => {
+			build_field::<DateTime, S>(registry, property, property.required, &())
+		}
+		DbScalarType::Decimal => {
+			build_field::<Decimal, S>(registry, property, property.required, &())
+		}
+		// The stored file reference is exposed as JSON, like objects
+		DbScalarType::File => build_field::<String, S>(registry, property, property.required, &()),
+		DbScalarType::GeoPoint => {
+			build_field::<GeoPoint, S>(registry, property, property.required, &())
+		}
+		DbScalarType::GeoPolygon => {
+			build_field::<GeoPolygon, S>(registry, property, property.required, &())
+		}
+		// A single translation is read, in the languages of the request unless one is given
+		DbScalarType::Localized => {
+			build_field::<String, S>(registry, property, property.required, &())
+				.argument(registry.arg::<Option<String>>("locale", &()))
+		}
 	}
 }
 
@@ -164,7 +203,7 @@ impl<S> GraphQLValue<S> for Entity
 where
 	S: ScalarValue,
 {
-	type Context = ();
+	type Context = Context;
 	type TypeInfo = OperationData<S>;
 
 	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
@@ -186,7 +225,7 @@ impl<'a, S> GraphQLValue<S> for QueryFieldResolver<'a, S>
 where
 	S: ScalarValue + Send + Sync,
 {
-	type Context = ();
+	type Context = Context;
 	type TypeInfo = QueryData<S>;
 
 	fn type_name<'i>(&self, _: &'i Self::TypeInfo) -> Option<&'i str> {
@@ -202,47 +241,216 @@ where
 		&'b self,
 		info: &'b Self::TypeInfo,
 		selection_set: Option<&'b [Selection<S>]>,
-		_executor: &'b Executor<Self::Context, S>,
+		executor: &'b Executor<Self::Context, S>,
 	) -> BoxFuture<'b, ExecutionResult<S>> {
 		Box::pin(resolve_graphql_field(
 			info,
+			executor.context(),
 			self.field_name,
 			self.arguments,
 			selection_set.unwrap(),
+			executor.variables(),
 		))
 	}
 }
 
+/// Phantom GraphQLValue resolving the document of a change pushed to a subscription
+pub struct SubscriptionEventResolver {
+	field_name: String,
+	event: ChangeEvent,
+}
+
+impl SubscriptionEventResolver {
+	pub fn new(field_name: String, event: ChangeEvent) -> Self {
+		SubscriptionEventResolver { field_name, event }
+	}
+}
+
+impl<S> GraphQLValue<S> for SubscriptionEventResolver
+where
+	S: ScalarValue + Send + Sync,
+{
+	type Context = Context;
+	type TypeInfo = QueryData<S>;
+
+	fn type_name<'i>(&self, _: &'i Self::TypeInfo) -> Option<&'i str> {
+		None
+	}
+}
+
+impl<S> GraphQLValueAsync<S> for SubscriptionEventResolver
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn resolve_async<'b>(
+		&'b self,
+		info: &'b Self::TypeInfo,
+		selection_set: Option<&'b [Selection<S>]>,
+		executor: &'b Executor<Self::Context, S>,
+	) -> BoxFuture<'b, ExecutionResult<S>> {
+		Box::pin(async move {
+			// A removed document cannot be fetched anymore
+			if self.event.operation == ChangeOperation::Remove {
+				return Ok(Value::null());
+			}
+
+			let entry = match info.operation_registry.get_operation(&self.field_name) {
+				Some(entry) => entry,
+				None => return Ok(Value::null()),
+			};
+
+			let context = executor.context();
+
+			let mut query = get_query_from_graphql(
+				selection_set.unwrap(),
+				&entry.data.entity.name,
+				&entry.data.entity,
+				info,
+				context,
+				executor.variables(),
+				None,
+			);
+
+			if let Some(filter) = context.row_filter(&entry.data.entity) {
+				query.add_filter(Box::new(filter));
+			}
+
+			query
+				.parameters
+				.insert("id", JsonValue::String(self.event.key.clone()));
+
+			fetch_document(&entry.data, context, query).await
+		})
+	}
+}
+
 async fn resolve_graphql_field<'a, S>(
 	info: &'a QueryData<S>,
+	context: &'a Context,
 	field_name: &str,
 	arguments: &'a Arguments<'a, S>,
 	selection_set: &'a [Selection<'a, S>],
+	variables: &'a Variables<S>,
 ) -> ExecutionResult<S>
 where
 	S: ScalarValue + Send + Sync,
 {
 	if let Some(entry) = info.operation_registry.get_operation(field_name) {
-		let query = get_query_from_graphql(selection_set, &entry.data.entity.name, info, None);
+		if !entry.is_allowed(field_name, context.roles()) {
+			return Err(ForbiddenError::new(field_name.to_string()).into_field_error());
+		}
+
+		if CONFIG.tenant_prefix_enabled && context.tenant().is_none() {
+			return Err(MissingTenantError.into_field_error());
+		}
+
+		match entry.kind {
+			OperationKind::Mutation => record_mutation(),
+			_ => record_read(field_name, entry.data.plan.collections.iter()),
+		}
+
+		let paginated = is_paginated(entry.operation);
+
+		// The documents of a page are read for the selection of its items
+		let mut query = get_query_from_graphql(
+			if paginated {
+				items_selection(selection_set)
+			} else {
+				selection_set
+			},
+			&entry.data.entity.name,
+			&entry.data.entity,
+			info,
+			context,
+			variables,
+			None,
+		);
+
+		if let Some(filter) = context.row_filter(&entry.data.entity) {
+			query.add_filter(Box::new(filter));
+		}
 
 		let closure = entry.closure;
 
-		closure(&entry.data, arguments, query).await
+		// The queries name the operation and the request in a comment, to be traced back from
+		// the query monitor of the database
+		let tag = match &context.request_id {
+			Some(request_id) => format!("alchemy {} request {}", field_name, request_id),
+			None => format!("alchemy {}", field_name),
+		};
+
+		let scope = QueryScope {
+			tag: Some(tag),
+			max_runtime: entry.data.entity.max_runtime(entry.operation),
+		};
+
+		let started = Instant::now();
+		let resolve = record_query_timings(with_query_scope(
+			scope,
+			closure(&entry.data, arguments, context, query),
+		));
+
+		let (output, queries) = match CONFIG.graphql_resolver_timeout_ms {
+			Some(timeout) => {
+				match rt::time::timeout(Duration::from_millis(timeout), resolve).await {
+					Ok(output) => output,
+					Err(_) => {
+						tracing::warn!(operation = %field_name, timeout_ms = timeout, "Resolver timed out");
+
+						record_operation(field_name, started.elapsed(), None, &[]);
+
+						return Err(TimeoutError::new(field_name.to_string()).into_field_error());
+					}
+				}
+			}
+			None => resolve.await,
+		};
+
+		record_operation(
+			field_name,
+			started.elapsed(),
+			output.as_ref().ok().map(returned_rows),
+			&queries,
+		);
+
+		let output = output?;
+
+		if paginated {
+			return Ok(select_page(output, selection_set, &entry.data.page_name));
+		}
+
+		Ok(output)
 	} else {
 		Ok(Value::null())
 	}
 }
 
+/// The entity at the other end of a relationship
+pub fn related_entity<'a>(relationship: &'a DbRelationship, entity_name: &str) -> &'a DbEntity {
+	match relationship.direction {
+		DbRelationshipDirection::Outbound => &relationship.to,
+		DbRelationshipDirection::Inbound => &relationship.from,
+		DbRelationshipDirection::Any if relationship.from.name == entity_name => &relationship.to,
+		DbRelationshipDirection::Any => &relationship.from,
+	}
+}
+
 fn get_query_from_graphql<'a, S>(
 	selection_set: &'a [Selection<'a, S>],
 	entity_name: &'a str,
+	entity: &DbEntity,
 	data: &'a QueryData<S>,
+	context: &'a Context,
+	variables: &Variables<S>,
 	query_id: Option<u32>,
 ) -> AQLQuery<'a>
 where
 	S: ScalarValue + Send + Sync,
 {
 	let mut query = AQLQuery::new(query_id.unwrap_or(1));
+	let allowed_fields = context.allowed_fields(entity);
+
+	query.tenant = context.tenant();
 
 	for selection in selection_set {
 		match *selection {
@@ -255,30 +463,84 @@ where
 
 				let response_name = response_name.to_string();
 
-				if let Some(inner_selection_set) = &f.selection_set {
+				// Only the selected relationships are traversed, each in its own subquery
+				let relationship = f.selection_set.as_ref().and_then(|_| {
+					data.relationships.iter().find(|relationship| {
+						relationship.name == f.name.item
+							&& owns_relationship(relationship, entity_name)
+					})
+				});
+
+				if let (Some(inner_selection_set), Some(relationship)) =
+					(&f.selection_set, relationship)
+				{
+					let related = related_entity(relationship, entity_name);
+
 					let mut inner_query = get_query_from_graphql(
 						inner_selection_set,
-						entity_name,
+						&related.name,
+						related,
 						data,
+						context,
+						variables,
 						Some(query.id + 1),
 					);
 
-					for relationship in &data.relationships {
-						if owns_relationship(&relationship, entity_name) {
-							inner_query.relationship = Some(AQLQueryRelationship {
-								edge: relationship.edge.clone(),
-								variable_name: query.get_variable_name(),
-								direction: relationship.direction.clone(),
-							});
-
-							query.relations.insert(response_name.clone(), inner_query);
+					inner_query.relationship = Some(AQLQueryRelationship {
+						edge: tenant_collection(query.tenant.as_deref(), &relationship.edge),
+						variable_name: query.get_variable_name(),
+						direction: relationship.direction.clone(),
+						graph: relationship
+							.graph
+							.as_ref()
+							.map(|graph| tenant_collection(query.tenant.as_deref(), graph)),
+					});
 
-							break;
-						}
+					if let Some(filter) = context.row_filter(related) {
+						inner_query.add_filter(Box::new(filter));
 					}
+
+					query.relations.insert(response_name.clone(), inner_query);
 				} else {
+					let property = entity.property_by_field(f.name.item);
+
+					// Geometries are objects, they are projected whole
+					let attribute = property
+						.map(|property| property.name.as_str())
+						.unwrap_or(f.name.item);
+
+					let locales = property
+						.filter(|property| property.scalar_type == DbScalarType::Localized)
+						.map(|_| {
+							let requested = f
+								.arguments
+								.as_ref()
+								.and_then(|arguments| {
+									arguments
+										.item
+										.items
+										.iter()
+										.find(|(argument, _)| argument.item == "locale")
+								})
+								.and_then(|(_, value)| string_value(&value.item, variables));
+
+							fallback_locales(requested, &context.locales)
+						});
+
+					// Properties the roles cannot read and password hashes are projected as null
+					let masked = allowed_fields
+						.as_ref()
+						.map(|fields| !fields.contains(&attribute))
+						.unwrap_or(false) || is_password_property(
+						&entity.collection_name,
+						attribute,
+					);
+
 					query.properties.push(AQLProperty {
 						name: response_name,
+						attribute: attribute.to_string(),
+						masked,
+						locales,
 					});
 				}
 			}
@@ -288,3 +550,13 @@ where
 
 	query
 }
+
+fn string_value<'a, S>(value: &'a InputValue<S>, variables: &'a Variables<S>) -> Option<&'a str>
+where
+	S: ScalarValue,
+{
+	match value {
+		InputValue::Variable(name) => variables.get(name)?.as_string_value(),
+		value => value.as_string_value(),
+	}
+}
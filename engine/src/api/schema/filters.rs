@@ -0,0 +1,274 @@
+use juniper::meta::{Argument, MetaType};
+use juniper::{FromInputValue, GraphQLType, GraphQLValue, InputValue, Registry, ScalarValue};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::api::schema::errors::InvalidFilterError;
+use crate::api::schema::inputs::{canonical_decimal, convert_input_to_json};
+use crate::api::schema::scalars::Decimal;
+use crate::lib::database::api::{DbEntity, DbScalarType};
+use crate::lib::database::aql::{AQLCondition, AQLWhere};
+use crate::lib::schema::RowFilterOperator;
+use crate::lib::CONFIG;
+
+/// Comparisons of text, enum and date fields, `_ieq` and `_icontains` ignore the case
+#[derive(GraphQLInputObject)]
+pub struct StringFilter {
+	#[graphql(name = "_eq")]
+	pub eq: Option<String>,
+	#[graphql(name = "_neq")]
+	pub neq: Option<String>,
+	#[graphql(name = "_in")]
+	pub is_in: Option<Vec<String>>,
+	#[graphql(name = "_gt")]
+	pub gt: Option<String>,
+	#[graphql(name = "_gte")]
+	pub gte: Option<String>,
+	#[graphql(name = "_lt")]
+	pub lt: Option<String>,
+	#[graphql(name = "_lte")]
+	pub lte: Option<String>,
+	#[graphql(name = "_contains")]
+	pub contains: Option<String>,
+	#[graphql(name = "_ieq")]
+	pub ieq: Option<String>,
+	#[graphql(name = "_icontains")]
+	pub icontains: Option<String>,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct NumberFilter {
+	#[graphql(name = "_eq")]
+	pub eq: Option<f64>,
+	#[graphql(name = "_neq")]
+	pub neq: Option<f64>,
+	#[graphql(name = "_in")]
+	pub is_in: Option<Vec<f64>>,
+	#[graphql(name = "_gt")]
+	pub gt: Option<f64>,
+	#[graphql(name = "_gte")]
+	pub gte: Option<f64>,
+	#[graphql(name = "_lt")]
+	pub lt: Option<f64>,
+	#[graphql(name = "_lte")]
+	pub lte: Option<f64>,
+}
+
+/// Comparisons of decimals by their value rather than as strings
+#[derive(GraphQLInputObject)]
+pub struct DecimalFilter {
+	#[graphql(name = "_eq")]
+	pub eq: Option<Decimal>,
+	#[graphql(name = "_neq")]
+	pub neq: Option<Decimal>,
+	#[graphql(name = "_in")]
+	pub is_in: Option<Vec<Decimal>>,
+	#[graphql(name = "_gt")]
+	pub gt: Option<Decimal>,
+	#[graphql(name = "_gte")]
+	pub gte: Option<Decimal>,
+	#[graphql(name = "_lt")]
+	pub lt: Option<Decimal>,
+	#[graphql(name = "_lte")]
+	pub lte: Option<Decimal>,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct BooleanFilter {
+	#[graphql(name = "_eq")]
+	pub eq: Option<bool>,
+	#[graphql(name = "_neq")]
+	pub neq: Option<bool>,
+}
+
+/// The filter input of a field, `None` for the types that cannot be compared
+enum FieldFilterType {
+	String,
+	Number,
+	Decimal,
+	Boolean,
+}
+
+impl FieldFilterType {
+	fn of(scalar_type: &DbScalarType) -> Option<Self> {
+		match scalar_type {
+			DbScalarType::String | DbScalarType::Enum(_) | DbScalarType::DateTime => {
+				Some(FieldFilterType::String)
+			}
+			DbScalarType::Int | DbScalarType::Float => Some(FieldFilterType::Number),
+			DbScalarType::Decimal => Some(FieldFilterType::Decimal),
+			DbScalarType::Boolean => Some(FieldFilterType::Boolean),
+			_ => None,
+		}
+	}
+}
+
+pub struct FilterInfo {
+	pub name: String,
+	fields: Vec<(String, FieldFilterType)>,
+}
+
+impl FilterInfo {
+	/// `None` when the entity has no field to filter on
+	pub fn new(entity: &DbEntity) -> Option<Self> {
+		let fields: Vec<(String, FieldFilterType)> = entity
+			.properties
+			.iter()
+			.filter_map(|property| {
+				FieldFilterType::of(&property.scalar_type)
+					.map(|filter_type| (property.field_name().to_string(), filter_type))
+			})
+			.collect();
+
+		if fields.is_empty() {
+			return None;
+		}
+
+		Some(FilterInfo {
+			name: format!("{}Filter", entity.name),
+			fields,
+		})
+	}
+}
+
+/// The `where` argument of an entity, its field filters must all hold
+pub struct EntityFilter(JsonMap<String, JsonValue>);
+
+impl EntityFilter {
+	/// The conditions of the filter on the properties of the entity
+	pub fn into_where(self, entity: &DbEntity) -> Result<AQLWhere, InvalidFilterError> {
+		let mut conditions = Vec::new();
+
+		for (field, operators) in self.0 {
+			let property = entity.property_by_field(&field).ok_or_else(|| {
+				InvalidFilterError::new(field.clone(), format!("{} cannot be filtered", field))
+			})?;
+
+			let operators = match operators {
+				JsonValue::Object(operators) => operators,
+				_ => continue,
+			};
+
+			for (operator, value) in operators {
+				// Unset operators are ignored, like unset arguments
+				if value.is_null() {
+					continue;
+				}
+
+				let decimal = property.scalar_type == DbScalarType::Decimal;
+				let value = match decimal {
+					true => canonical_decimal(&value, property.rules.decimal_scale()),
+					false => value,
+				};
+				let property = property.name.clone();
+
+				let comparison = match operator.as_str() {
+					"_eq" => Some(RowFilterOperator::Equal),
+					"_neq" => Some(RowFilterOperator::NotEqual),
+					"_in" => Some(RowFilterOperator::In),
+					"_gt" => Some(RowFilterOperator::Greater),
+					"_gte" => Some(RowFilterOperator::GreaterOrEqual),
+					"_lt" => Some(RowFilterOperator::Less),
+					"_lte" => Some(RowFilterOperator::LessOrEqual),
+					_ => None,
+				};
+
+				conditions.push(match (comparison, operator.as_str()) {
+					// Decimals are ordered by their value, equal ones are equal canonical strings
+					(
+						Some(
+							operator @ (RowFilterOperator::Greater
+							| RowFilterOperator::GreaterOrEqual
+							| RowFilterOperator::Less
+							| RowFilterOperator::LessOrEqual),
+						),
+						_,
+					) if decimal => AQLCondition::CompareNumber {
+						property,
+						operator,
+						value,
+					},
+					(Some(operator), _) => AQLCondition::Compare {
+						property,
+						operator,
+						value,
+					},
+					(None, "_contains") => AQLCondition::Contains {
+						property,
+						value,
+						insensitive: false,
+					},
+					(None, "_icontains") => AQLCondition::Contains {
+						property,
+						value,
+						insensitive: true,
+					},
+					(None, "_ieq") => AQLCondition::EqualInsensitive { property, value },
+					_ => {
+						return Err(InvalidFilterError::new(
+							field,
+							format!("Unknown filter operator {}", operator),
+						))
+					}
+				});
+			}
+		}
+
+		Ok(AQLWhere {
+			conditions,
+			analyzer: CONFIG.graphql_filter_analyzer.clone(),
+		})
+	}
+}
+
+impl<S> GraphQLType<S> for EntityFilter
+where
+	S: ScalarValue,
+{
+	fn name(info: &Self::TypeInfo) -> Option<&str> {
+		Some(info.name.as_str())
+	}
+
+	fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
+	where
+		S: 'r,
+	{
+		let arguments: Vec<Argument<'r, S>> = info
+			.fields
+			.iter()
+			.map(|(field, filter_type)| match filter_type {
+				FieldFilterType::String => registry.arg::<Option<StringFilter>>(field, &()),
+				FieldFilterType::Number => registry.arg::<Option<NumberFilter>>(field, &()),
+				FieldFilterType::Decimal => registry.arg::<Option<DecimalFilter>>(field, &()),
+				FieldFilterType::Boolean => registry.arg::<Option<BooleanFilter>>(field, &()),
+			})
+			.collect();
+
+		registry
+			.build_input_object_type::<EntityFilter>(info, &arguments)
+			.into_meta()
+	}
+}
+
+impl<S> GraphQLValue<S> for EntityFilter
+where
+	S: ScalarValue,
+{
+	type Context = ();
+	type TypeInfo = FilterInfo;
+
+	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+		<Self as GraphQLType<S>>::name(info)
+	}
+}
+
+impl<S> FromInputValue<S> for EntityFilter
+where
+	S: ScalarValue,
+{
+	fn from_input_value(v: &InputValue<S>) -> Option<Self> {
+		match convert_input_to_json(v)? {
+			JsonValue::Object(filter) => Some(Self(filter)),
+			_ => None,
+		}
+	}
+}
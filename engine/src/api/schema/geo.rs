@@ -0,0 +1,86 @@
+use serde_json::Value as JsonValue;
+
+use crate::lib::database::api::DbScalarType;
+
+/// A GeoJSON point, its coordinates are `[longitude, latitude]`
+#[derive(GraphQLObject)]
+pub struct GeoPoint {
+	#[graphql(name = "type")]
+	pub kind: String,
+	pub coordinates: Vec<f64>,
+}
+
+/// A GeoJSON polygon, an outer ring followed by its holes, each ring closed on its first position
+#[derive(GraphQLObject)]
+pub struct GeoPolygon {
+	#[graphql(name = "type")]
+	pub kind: String,
+	pub coordinates: Vec<Vec<Vec<f64>>>,
+}
+
+/// The type is set by the engine, it is accepted so that read documents can be written back
+#[derive(GraphQLInputObject)]
+pub struct GeoPointInput {
+	#[graphql(name = "type")]
+	pub kind: Option<String>,
+	pub coordinates: Vec<f64>,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct GeoPolygonInput {
+	#[graphql(name = "type")]
+	pub kind: Option<String>,
+	pub coordinates: Vec<Vec<Vec<f64>>>,
+}
+
+/// The documents whose geo field is inside a polygon
+#[derive(GraphQLInputObject)]
+pub struct GeoWithinInput {
+	pub field: String,
+	pub coordinates: Vec<Vec<Vec<f64>>>,
+}
+
+/// The documents closest to a point first, optionally up to a distance in meters
+#[derive(GraphQLInputObject)]
+pub struct GeoNearInput {
+	pub field: String,
+	pub longitude: f64,
+	pub latitude: f64,
+	pub max_distance: Option<f64>,
+}
+
+fn is_position(value: &JsonValue) -> bool {
+	match value.as_array().map(Vec::as_slice) {
+		Some([longitude, latitude]) => match (longitude.as_f64(), latitude.as_f64()) {
+			(Some(longitude), Some(latitude)) => {
+				(-180.0..=180.0).contains(&longitude) && (-90.0..=90.0).contains(&latitude)
+			}
+			_ => false,
+		},
+		_ => false,
+	}
+}
+
+/// A closed ring of at least four positions
+fn is_ring(value: &JsonValue) -> bool {
+	match value.as_array() {
+		Some(positions) => {
+			positions.len() >= 4
+				&& positions.iter().all(is_position)
+				&& positions.first() == positions.last()
+		}
+		None => false,
+	}
+}
+
+/// Whether the coordinates are those of the geometry stored by the property
+pub fn is_valid_geometry(scalar_type: &DbScalarType, coordinates: &JsonValue) -> bool {
+	match scalar_type {
+		DbScalarType::GeoPoint => is_position(coordinates),
+		DbScalarType::GeoPolygon => coordinates
+			.as_array()
+			.map(|rings| !rings.is_empty() && rings.iter().all(is_ring))
+			.unwrap_or(false),
+		_ => false,
+	}
+}
@@ -0,0 +1,10 @@
+/// A change record of an audited collection
+#[derive(GraphQLObject)]
+pub struct HistoryEntry {
+	pub document_key: String,
+	pub operation: String,
+	pub actor: Option<String>,
+	pub timestamp: String,
+	/// JSON encoded `old`/`new` values of the changed properties
+	pub diff: String,
+}
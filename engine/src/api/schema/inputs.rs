@@ -0,0 +1,321 @@
+use juniper::meta::{Argument, MetaType};
+use juniper::{FromInputValue, GraphQLType, GraphQLValue, InputValue, Registry, ScalarValue};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::api::schema::enums::{DbEnumInfo, GraphQLEnum};
+use crate::api::schema::geo::{GeoPointInput, GeoPolygonInput};
+use crate::api::schema::localized::{into_translations, LocalizedStringInput};
+use crate::api::schema::scalars::{DateTime, Decimal, Upload};
+use crate::api::sessions::is_password_property;
+use crate::api::uploads::get_upload;
+use crate::lib::database::api::{DbEntity, DbProperty, DbScalarType};
+use crate::lib::passwords::{hash_password, is_password_hash};
+use crate::lib::schema::DecimalValue;
+
+pub struct EntityInputInfo {
+	pub name: String,
+	pub properties: Vec<DbProperty>,
+	/// Every property is optional, used for updates
+	pub partial: bool,
+}
+
+impl EntityInputInfo {
+	pub fn new(entity: &DbEntity, partial: bool) -> Self {
+		EntityInputInfo {
+			name: format!(
+				"{}{}Input",
+				entity.name,
+				if partial { "Update" } else { "" }
+			),
+			properties: entity.properties.clone(),
+			partial,
+		}
+	}
+}
+
+/// Input object generated from the properties of an entity
+pub struct EntityInput(pub JsonMap<String, JsonValue>);
+
+/// Equal decimals are stored and compared as equal strings, with the digits of the scale
+pub fn canonical_decimal(value: &JsonValue, scale: Option<usize>) -> JsonValue {
+	match value {
+		JsonValue::String(text) => match DecimalValue::parse(text) {
+			Some(decimal) => JsonValue::String(decimal.to_canonical(scale)),
+			None => value.clone(),
+		},
+		// Integers are accepted by the scalar as well
+		JsonValue::Number(number) => {
+			canonical_decimal(&JsonValue::String(number.to_string()), scale)
+		}
+		JsonValue::Array(items) => items
+			.iter()
+			.map(|item| canonical_decimal(item, scale))
+			.collect(),
+		_ => value.clone(),
+	}
+}
+
+impl EntityInput {
+	/// Build the document to store, parsing the properties exposed as JSON strings
+	pub fn into_document(self, entity: &DbEntity) -> JsonValue {
+		// Renamed fields are stored under the name of their property
+		let mut document: JsonMap<String, JsonValue> = self
+			.0
+			.into_iter()
+			.map(|(field, value)| match entity.property_by_field(&field) {
+				Some(property) => (property.name.clone(), value),
+				None => (field, value),
+			})
+			.collect();
+
+		for property in &entity.properties {
+			if is_password_property(&entity.collection_name, &property.name) {
+				hash_password_value(&mut document, &property.name);
+			}
+
+			match &property.scalar_type {
+				DbScalarType::Object => {
+					if let Some(JsonValue::String(raw)) = document.get(&property.name) {
+						if let Ok(value) = serde_json::from_str::<JsonValue>(raw) {
+							document.insert(property.name.clone(), value);
+						}
+					}
+				}
+				DbScalarType::File => {
+					if let Some(value) = document.get_mut(&property.name) {
+						resolve_uploads(value);
+					}
+				}
+				DbScalarType::Decimal => {
+					if let Some(value) = document.get_mut(&property.name) {
+						*value = canonical_decimal(value, property.rules.decimal_scale());
+					}
+				}
+				DbScalarType::Localized => {
+					if let Some(value) = document.get_mut(&property.name) {
+						*value = into_translations(value);
+					}
+				}
+				DbScalarType::Array(item) if **item == DbScalarType::File => {
+					if let Some(value) = document.get_mut(&property.name) {
+						resolve_uploads(value);
+					}
+				}
+				DbScalarType::GeoPoint | DbScalarType::GeoPolygon => {
+					if let Some(JsonValue::Object(geometry)) = document.get_mut(&property.name) {
+						geometry.insert(
+							"type".to_string(),
+							JsonValue::from(property.scalar_type.geometry_type()),
+						);
+					}
+				}
+				_ => {}
+			}
+		}
+
+		JsonValue::Object(document)
+	}
+}
+
+/// Store the password of a user as an Argon2 hash, it is dropped when it cannot be hashed
+fn hash_password_value(document: &mut JsonMap<String, JsonValue>, property: &str) {
+	let password = match document.get(property) {
+		Some(JsonValue::String(password)) if !is_password_hash(password) => password,
+		_ => return,
+	};
+
+	match hash_password(password) {
+		Ok(hash) => {
+			document.insert(property.to_string(), JsonValue::String(hash));
+		}
+		Err(e) => {
+			tracing::error!(error = %e, "Failed to hash the password");
+			document.remove(property);
+		}
+	}
+}
+
+/// Replace the upload IDs by the references of the stored files
+fn resolve_uploads(value: &mut JsonValue) {
+	match value {
+		JsonValue::String(id) => {
+			if let Some(file) = get_upload(id) {
+				*value = serde_json::to_value(file).unwrap_or(JsonValue::Null);
+			}
+		}
+		JsonValue::Array(items) => items.iter_mut().for_each(resolve_uploads),
+		_ => {}
+	}
+}
+
+pub fn build_argument_from_property<'r, S>(
+	registry: &mut Registry<'r, S>,
+	property: &DbProperty,
+	scalar_type: &DbScalarType,
+	enforce_required: bool,
+) -> Argument<'r, S>
+where
+	S: ScalarValue,
+{
+	fn build_argument<'r, T, S>(
+		registry: &mut Registry<'r, S>,
+		property: &DbProperty,
+		required: bool,
+		info: &T::TypeInfo,
+	) -> Argument<'r, S>
+	where
+		S: ScalarValue + 'r,
+		T: GraphQLType<S> + FromInputValue<S>,
+	{
+		let is_array = matches!(property.scalar_type, DbScalarType::Array(_));
+
+		if required && !is_array {
+			registry.arg::<T>(property.field_name(), info)
+		} else {
+			registry.arg::<Option<T>>(property.field_name(), info)
+		}
+	}
+
+	let required = property.required && enforce_required;
+
+	match scalar_type {
+		DbScalarType::Array(t) => {
+			let mut argument = build_argument_from_property(registry, property, &t, false);
+
+			if required {
+				argument.arg_type = juniper::Type::NonNullList(Box::new(argument.arg_type));
+			} else {
+				argument.arg_type = juniper::Type::List(Box::new(argument.arg_type));
+			}
+
+			argument
+		}
+		DbScalarType::Enum(values) => build_argument::<GraphQLEnum, S>(
+			registry,
+			property,
+			required,
+			&DbEnumInfo {
+				name: property.associated_type.clone().unwrap(),
+				properties: values.clone(),
+			},
+		),
+		DbScalarType::String => build_argument::<String, S>(registry, property, required, &()),
+		DbScalarType::Object => build_argument::<String, S>(registry, property, required, &()),
+		DbScalarType::Float => build_argument::<f64, S>(registry, property, required, &()),
+		DbScalarType::Int => build_argument::<i32, S>(registry, property, required, &()),
+		DbScalarType::Boolean => build_argument::<bool, S>(registry, property, required, &()),
+		DbScalarType::DateTime => build_argument::<DateTime, S>(registry, property, required, &()),
+		DbScalarType::Decimal => build_argument::<Decimal, S>(registry, property, required, &()),
+		DbScalarType::File => build_argument::<Upload, S>(registry, property, required, &()),
+		DbScalarType::GeoPoint => {
+			build_argument::<GeoPointInput, S>(registry, property, required, &())
+		}
+		DbScalarType::GeoPolygon => {
+			build_argument::<GeoPolygonInput, S>(registry, property, required, &())
+		}
+		DbScalarType::Localized => {
+			build_argument::<Vec<LocalizedStringInput>, S>(registry, property, required, &())
+		}
+	}
+}
+
+impl<S> GraphQLType<S> for EntityInput
+where
+	S: ScalarValue,
+{
+	fn name(info: &Self::TypeInfo) -> Option<&str> {
+		Some(info.name.as_str())
+	}
+
+	fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
+	where
+		S: 'r,
+	{
+		let mut arguments = Vec::new();
+
+		for property in info.properties.iter().filter(|p| !p.managed && !p.readonly) {
+			arguments.push(build_argument_from_property(
+				registry,
+				property,
+				&property.scalar_type,
+				!info.partial,
+			));
+		}
+
+		registry
+			.build_input_object_type::<EntityInput>(info, &arguments)
+			.into_meta()
+	}
+}
+
+impl<S> GraphQLValue<S> for EntityInput
+where
+	S: ScalarValue,
+{
+	type Context = ();
+	type TypeInfo = EntityInputInfo;
+
+	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+		<Self as GraphQLType<S>>::name(info)
+	}
+}
+
+impl<S> FromInputValue<S> for EntityInput
+where
+	S: ScalarValue,
+{
+	fn from_input_value(v: &InputValue<S>) -> Option<Self> {
+		match convert_input_to_json(v)? {
+			JsonValue::Object(data) => Some(Self(data)),
+			_ => None,
+		}
+	}
+}
+
+/// Any argument converted to JSON, whatever its type
+pub struct JsonInput(pub JsonValue);
+
+impl<S> FromInputValue<S> for JsonInput
+where
+	S: ScalarValue,
+{
+	fn from_input_value(v: &InputValue<S>) -> Option<Self> {
+		convert_input_to_json(v).map(Self)
+	}
+}
+
+pub fn convert_input_to_json<S>(value: &InputValue<S>) -> Option<JsonValue>
+where
+	S: ScalarValue,
+{
+	let json = match value {
+		InputValue::Null => JsonValue::Null,
+		InputValue::Scalar(s) => {
+			if let Some(v) = s.as_int() {
+				JsonValue::from(v)
+			} else if let Some(v) = s.as_float() {
+				JsonValue::from(v)
+			} else if let Some(v) = s.as_boolean() {
+				JsonValue::from(v)
+			} else {
+				JsonValue::from(s.as_string()?)
+			}
+		}
+		InputValue::Enum(e) => JsonValue::from(e.clone()),
+		InputValue::List(items) => JsonValue::Array(
+			items
+				.iter()
+				.map(|i| convert_input_to_json(&i.item))
+				.collect::<Option<Vec<JsonValue>>>()?,
+		),
+		InputValue::Object(fields) => JsonValue::Object(
+			fields
+				.iter()
+				.map(|(k, v)| convert_input_to_json(&v.item).map(|v| (k.item.clone(), v)))
+				.collect::<Option<JsonMap<String, JsonValue>>>()?,
+		),
+		InputValue::Variable(_) => return None,
+	};
+
+	Some(json)
+}
@@ -0,0 +1,117 @@
+use std::cmp::Ordering;
+
+use actix_web::http::header::{HeaderMap, ACCEPT_LANGUAGE};
+use serde_json::Value as JsonValue;
+
+use crate::lib::CONFIG;
+
+/// Languages read from an `Accept-Language` header, the others are ignored
+const MAX_LOCALES: usize = 8;
+
+/// A translation of a localized field
+#[derive(GraphQLInputObject)]
+pub struct LocalizedStringInput {
+	/// A language tag, e.g. `en` or `pt-BR`
+	pub locale: String,
+	pub value: String,
+}
+
+/// The language tags are written in the queries, only letters, digits and hyphens are accepted
+pub fn is_valid_locale(locale: &str) -> bool {
+	!locale.is_empty()
+		&& locale.len() <= 35
+		&& locale
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '_')
+}
+
+/// Language tags are case-insensitive, they are stored lowercased, e.g. `pt-br`
+pub fn normalize_locale(locale: &str) -> String {
+	locale.trim().replace('_', "-").to_ascii_lowercase()
+}
+
+/// The languages of the `Accept-Language` header, the preferred first
+pub fn accept_language(headers: &HeaderMap) -> Vec<String> {
+	let header = match headers
+		.get(ACCEPT_LANGUAGE)
+		.and_then(|value| value.to_str().ok())
+	{
+		Some(header) => header,
+		None => return Vec::new(),
+	};
+
+	let mut languages: Vec<(String, f32)> = header
+		.split(',')
+		.filter_map(|item| {
+			let mut parameters = item.split(';');
+			let locale = parameters.next()?.trim();
+
+			let quality = match parameters.find_map(|parameter| parameter.trim().strip_prefix("q="))
+			{
+				Some(quality) => quality.trim().parse::<f32>().ok()?,
+				None => 1.0,
+			};
+
+			Some((normalize_locale(locale), quality))
+		})
+		// The wildcard is the fallback to any translation, which is always applied
+		.filter(|(locale, quality)| *quality > 0.0 && is_valid_locale(locale))
+		.collect();
+
+	// The sort is stable, languages of the same quality keep the order of the header
+	languages.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+
+	languages
+		.into_iter()
+		.map(|(locale, _)| locale)
+		.take(MAX_LOCALES)
+		.collect()
+}
+
+/// The locales tried in turn to resolve a localized field, the `locale` argument replaces the
+/// languages of the request, a regional language falls back to its base language, e.g. `de-ch`
+/// to `de`, and all of them to the default locale
+pub fn fallback_locales(requested: Option<&str>, preferred: &[String]) -> Vec<String> {
+	let requested: Vec<String> = match requested.map(normalize_locale) {
+		Some(locale) if is_valid_locale(&locale) => vec![locale],
+		_ => preferred.to_vec(),
+	};
+
+	let mut locales: Vec<String> = Vec::new();
+
+	let candidates = requested
+		.iter()
+		.flat_map(|locale| {
+			let base = locale.split('-').next().map(str::to_string);
+
+			std::iter::once(locale.clone()).chain(base)
+		})
+		.chain(CONFIG.default_locale.as_deref().map(normalize_locale));
+
+	for locale in candidates {
+		if is_valid_locale(&locale) && !locales.contains(&locale) {
+			locales.push(locale);
+		}
+	}
+
+	locales
+}
+
+/// The stored object of the translations of a mutation input, keyed by locale, the last of the
+/// translations of a same locale is kept
+pub fn into_translations(value: &JsonValue) -> JsonValue {
+	match value {
+		JsonValue::Array(translations) => JsonValue::Object(
+			translations
+				.iter()
+				.filter_map(|translation| {
+					Some((
+						normalize_locale(translation["locale"].as_str()?),
+						translation["value"].clone(),
+					))
+				})
+				.collect(),
+		),
+		value => value.clone(),
+	}
+}
@@ -1,20 +1,299 @@
+pub mod context;
 pub mod enums;
 pub mod errors;
 pub mod fields;
+pub mod filters;
+pub mod geo;
+pub mod history;
+pub mod inputs;
+pub mod localized;
+pub mod naming;
 pub mod operations;
+pub mod ordering;
+pub mod pages;
+pub mod preflight;
+pub mod scalars;
+pub mod validation;
+pub mod values;
+pub mod versioning;
 
-use crate::api::schema::fields::QueryFieldFactory;
-use crate::api::schema::operations::OperationRegistry;
+use crate::api::apollo_tracing::trace_resolver;
+use crate::api::audit::record_audit;
+use crate::api::cache::clear_responses;
+use crate::api::incremental::INCREMENTAL_DIRECTIVES;
+use crate::api::remote::{
+	build_remote_fields, load_remote_schemas, resolve_remote_field, RemoteSchema,
+};
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{error_extensions, ErrorCode, ForbiddenError, MissingTenantError};
+use crate::api::schema::fields::{QueryFieldFactory, SubscriptionEventResolver};
+use crate::api::schema::operations::{OperationKind, OperationRegistry};
+use crate::api::schema::preflight::{preflight_operations, PreflightFailure};
+use crate::api::sessions::{resolve_session_field, LOGIN_FIELD, LOGOUT_FIELD};
+use futures::{future, StreamExt};
 use juniper::meta::MetaType;
 use juniper::{
-	Arguments, BoxFuture, EmptyMutation, EmptySubscription, ExecutionResult, Executor, GraphQLType,
-	GraphQLValue, GraphQLValueAsync, Registry, RootNode, ScalarValue,
+	Arguments, BoxFuture, DefaultScalarValue, ExecutionError, ExecutionResult, Executor,
+	FieldError, GraphQLSubscriptionValue, GraphQLType, GraphQLValue, GraphQLValueAsync,
+	IntoFieldError, Registry, RootNode, ScalarValue, Value, ValuesStream,
 };
-use std::sync::Arc;
+use std::collections::{HashMap, HashSet};
+use std::sync::{Arc, RwLock};
+use tracing::Instrument;
 
+use crate::lib::changes::subscribe_changes;
 use crate::lib::database::api::*;
+use crate::lib::database::generate_sdl;
+use crate::lib::redaction::set_sensitive_fields;
+use crate::lib::schema::SchemaOperation;
+use crate::lib::tenants::{set_schema_collections, tenant_collection};
+use crate::lib::CONFIG;
+
+pub type Schema = RootNode<'static, Query, Mutation, Subscription>;
+
+/// The schemas of the role combinations seen so far, each one only exposing the granted operations
+pub struct Schemas {
+	current: RwLock<Arc<SchemaSet>>,
+}
+
+/// A generated operation found by collection
+pub struct CollectionOperation {
+	/// The root field of the operation
+	pub name: String,
+	pub entity: Arc<DbEntity>,
+	/// Whether the roles may run it
+	pub allowed: bool,
+}
+
+/// The schemas generated from one version of the metadata
+struct SchemaSet {
+	data: QueryData<DefaultScalarValue>,
+	schemas: RwLock<HashMap<Option<Vec<String>>, Arc<Schema>>>,
+}
+
+impl Schemas {
+	/// The schema of the roles, or the unrestricted one with `None`
+	pub fn for_roles(&self, roles: Option<&[String]>) -> Arc<Schema> {
+		let current = self.current.read().unwrap().clone();
+
+		current.for_roles(roles)
+	}
+
+	/// The generated operation of a collection, for the routes that do not speak GraphQL
+	pub fn collection_operation(
+		&self,
+		collection: &str,
+		operation: SchemaOperation,
+		roles: Option<&[String]>,
+	) -> Option<CollectionOperation> {
+		let current = self.current.read().unwrap().clone();
+
+		current
+			.data
+			.operation_registry
+			.get_operations()
+			.iter()
+			.find(|(_, entry)| {
+				entry.operation == operation && entry.data.entity.collection_name == collection
+			})
+			.map(|(name, entry)| CollectionOperation {
+				name: name.clone(),
+				entity: entry.data.entity.clone(),
+				allowed: entry.is_allowed(name, roles),
+			})
+	}
+
+	/// The names of the generated operations of each collection, sorted
+	pub fn operation_names(&self) -> Vec<(String, Vec<String>)> {
+		let current = self.current.read().unwrap().clone();
+		let mut collections: Vec<(String, Vec<String>)> = Vec::new();
+
+		for (name, entry) in current.data.operation_registry.get_operations() {
+			let collection = &entry.data.entity.collection_name;
+
+			match collections.iter_mut().find(|(c, _)| c == collection) {
+				Some((_, names)) => names.push(name.clone()),
+				None => collections.push((collection.clone(), vec![name.clone()])),
+			}
+		}
+
+		for (_, names) in collections.iter_mut() {
+			names.sort();
+		}
+
+		collections.sort();
+
+		collections
+	}
+
+	/// The relationships of the generated API
+	pub fn relationships(&self) -> Vec<DbRelationship> {
+		self.current.read().unwrap().data.relationships.clone()
+	}
+
+	/// The entities of the generated API, with their enabled operations
+	pub fn entities(&self) -> Vec<(Arc<DbEntity>, Vec<SchemaOperation>)> {
+		let current = self.current.read().unwrap().clone();
+		let mut entities: Vec<(Arc<DbEntity>, Vec<SchemaOperation>)> = Vec::new();
 
-pub type Schema = RootNode<'static, Query, EmptyMutation, EmptySubscription>;
+		for entry in current.data.operation_registry.get_operations().values() {
+			match entities
+				.iter_mut()
+				.find(|(entity, _)| entity.collection_name == entry.data.entity.collection_name)
+			{
+				Some((_, operations)) => operations.push(entry.operation),
+				None => entities.push((entry.data.entity.clone(), vec![entry.operation])),
+			}
+		}
+
+		entities.sort_by(|(a, _), (b, _)| a.collection_name.cmp(&b.collection_name));
+
+		entities
+	}
+
+	/// The SDL of the unrestricted schema
+	pub fn sdl(&self) -> String {
+		let mut sdl = self.for_roles(None).as_schema_language();
+
+		if CONFIG.graphql_incremental_enabled {
+			sdl.push_str(INCREMENTAL_DIRECTIVES);
+		}
+
+		sdl
+	}
+
+	/// Have the database explain the queries of every generated operation, the rejected ones are
+	/// reported
+	pub async fn preflight(&self) -> Vec<PreflightFailure> {
+		let current = self.current.read().unwrap().clone();
+
+		preflight_operations(&current.data.operation_registry).await
+	}
+
+	/// Swap in the schemas generated from the current metadata, the requests
+	/// already running keep the schema they started with, the schemas with operations the
+	/// database rejects are not swapped in
+	pub async fn reload(&self) -> bool {
+		let set = SchemaSet::new(generate_sdl().await, load_remote_schemas().await);
+
+		if CONFIG.preflight_operations
+			&& !preflight_operations(&set.data.operation_registry)
+				.await
+				.is_empty()
+		{
+			tracing::error!("GraphQL schema not reloaded, some generated operations are invalid");
+
+			return false;
+		}
+
+		*self.current.write().unwrap() = Arc::new(set);
+
+		clear_responses();
+
+		tracing::info!("GraphQL schema reloaded");
+
+		true
+	}
+}
+
+impl SchemaSet {
+	fn new(map: DbMap, remotes: Vec<Arc<RemoteSchema>>) -> Self {
+		let mut operation_registry = OperationRegistry::new();
+		let mut sensitive = HashSet::new();
+		let mut collections = HashSet::new();
+
+		for p in map.primitives {
+			match p {
+				DbPrimitive::Entity(t) => {
+					collections.insert(t.collection_name.clone());
+
+					for property in t.properties.iter().filter(|property| property.sensitive) {
+						sensitive.insert(property.name.clone());
+						sensitive.insert(property.field_name().to_string());
+					}
+
+					let mut relationships = Vec::new();
+
+					for relationship in &map.relationships {
+						if owns_relationship(&relationship, &t.name) {
+							relationships.push(relationship.clone())
+						}
+					}
+
+					operation_registry.register_entity(t, Arc::new(relationships));
+				}
+				DbPrimitive::Enum(_) => {}
+			}
+		}
+
+		for relationship in &map.relationships {
+			collections.insert(relationship.edge.clone());
+		}
+
+		set_sensitive_fields(sensitive);
+		set_schema_collections(collections);
+
+		let data = QueryData {
+			operation_registry: Arc::new(operation_registry),
+			relationships: map.relationships.clone(),
+			remotes: Arc::new(remotes),
+			roles: None,
+		};
+
+		let set = SchemaSet {
+			data,
+			schemas: RwLock::new(HashMap::new()),
+		};
+
+		// Build the unrestricted schema upfront so that errors surface before it is used
+		set.for_roles(None);
+
+		set
+	}
+
+	fn for_roles(&self, roles: Option<&[String]>) -> Arc<Schema> {
+		let key = roles.map(|roles| {
+			let mut roles = roles.to_vec();
+
+			roles.sort();
+			roles.dedup();
+			roles
+		});
+
+		if let Some(schema) = self.schemas.read().unwrap().get(&key) {
+			return schema.clone();
+		}
+
+		let data = QueryData {
+			roles: key.clone(),
+			..self.data.clone()
+		};
+
+		let schema = Arc::new(RootNode::new_with_info(
+			Query,
+			Mutation,
+			Subscription,
+			data.clone(),
+			data.clone(),
+			data,
+		));
+
+		self.schemas
+			.write()
+			.unwrap()
+			.entry(key)
+			.or_insert(schema)
+			.clone()
+	}
+}
+
+// The schema is shared by every worker of the HTTP server
+const _: fn() = || {
+	fn assert_send_sync<T: Send + Sync>() {}
+
+	assert_send_sync::<Schema>();
+	assert_send_sync::<Schemas>();
+};
 
 pub fn owns_relationship(relationship: &DbRelationship, entity_name: &str) -> bool {
 	match relationship.direction {
@@ -26,45 +305,94 @@ pub fn owns_relationship(relationship: &DbRelationship, entity_name: &str) -> bo
 	}
 }
 
-pub fn schema(map: DbMap) -> Schema {
-	let mut operation_registry = OperationRegistry::new();
+pub fn schema(map: DbMap, remotes: Vec<Arc<RemoteSchema>>) -> Schemas {
+	Schemas {
+		current: RwLock::new(Arc::new(SchemaSet::new(map, remotes))),
+	}
+}
 
-	for p in map.primitives {
-		match p {
-			DbPrimitive::Entity(t) => {
-				let mut relationships = Vec::new();
+#[derive(Clone)]
+pub struct QueryData<S>
+where
+	S: ScalarValue + Send + Sync,
+{
+	operation_registry: Arc<OperationRegistry<S>>,
+	relationships: Vec<DbRelationship>,
+	/// Schemas of external endpoints, their root fields are merged after the generated ones
+	remotes: Arc<Vec<Arc<RemoteSchema>>>,
+	/// Only the operations granted to these roles are exposed, all of them when `None`
+	roles: Option<Vec<String>>,
+}
 
-				for relationship in &map.relationships {
-					if owns_relationship(&relationship, &t.name) {
-						relationships.push(relationship.clone())
-					}
-				}
+/// Build the fields of a root type from the operations of the given kind
+fn build_root_fields<'r, S>(
+	info: &QueryData<S>,
+	kind: OperationKind,
+	registry: &mut Registry<'r, S>,
+) -> Vec<juniper::meta::Field<'r, S>>
+where
+	S: ScalarValue + Send + Sync + 'r,
+{
+	let mut fields = Vec::new();
 
-				operation_registry.register_entity(t, Arc::new(relationships));
-			}
-			DbPrimitive::Enum(_) => {}
+	for (name, operation) in info.operation_registry.get_operations() {
+		if operation.kind == kind && operation.is_allowed(name, info.roles.as_deref()) {
+			fields.push(QueryFieldFactory::new(name, operation, registry));
 		}
 	}
 
-	RootNode::new_with_info(
-		Query,
-		EmptyMutation::new(),
-		EmptySubscription::new(),
-		QueryData {
-			operation_registry,
-			relationships: map.relationships.clone(),
-		},
-		(),
-		(),
-	)
+	fields
 }
 
-pub struct QueryData<S>
+/// Append the root fields of the remote schemas, the generated fields and the
+/// schemas listed first win when names collide
+fn append_remote_fields<'r, S>(
+	info: &QueryData<S>,
+	mutation: bool,
+	fields: &mut Vec<juniper::meta::Field<'r, S>>,
+	registry: &mut Registry<'r, S>,
+) where
+	S: ScalarValue + Send + Sync + 'r,
+{
+	let mut names: Vec<String> = info
+		.operation_registry
+		.get_operations()
+		.keys()
+		.cloned()
+		.chain(fields.iter().map(|field| field.name.to_string()))
+		.collect();
+
+	for remote in info.remotes.iter() {
+		let remote_fields = build_remote_fields(remote, mutation, &names, registry);
+
+		names.extend(remote_fields.iter().map(|field| field.name.to_string()));
+		fields.extend(remote_fields);
+	}
+}
+
+/// The remote schema a root field is proxied to, `None` for the generated fields
+fn remote_for<'a, S>(
+	info: &'a QueryData<S>,
+	field_name: &str,
+	mutation: bool,
+) -> Option<&'a RemoteSchema>
 where
 	S: ScalarValue + Send + Sync,
 {
-	operation_registry: OperationRegistry<S>,
-	relationships: Vec<DbRelationship>,
+	if info.operation_registry.get_operation(field_name).is_some() {
+		return None;
+	}
+
+	info.remotes
+		.iter()
+		.find(|remote| {
+			if mutation {
+				remote.mutation_fields().any(|name| name == field_name)
+			} else {
+				remote.query_fields().any(|name| name == field_name)
+			}
+		})
+		.map(|remote| remote.as_ref())
 }
 
 pub struct Query;
@@ -81,11 +409,8 @@ where
 	where
 		S: 'r,
 	{
-		let mut queries = Vec::new();
-
-		for (name, operation) in info.operation_registry.get_operations() {
-			queries.push(QueryFieldFactory::new(name, operation, registry));
-		}
+		let mut queries = build_root_fields(info, OperationKind::Query, registry);
+		append_remote_fields(info, false, &mut queries, registry);
 
 		registry
 			.build_object_type::<Query>(info, &queries)
@@ -97,7 +422,7 @@ impl<S> GraphQLValue<S> for Query
 where
 	S: ScalarValue + Send + Sync,
 {
-	type Context = ();
+	type Context = Context;
 	type TypeInfo = QueryData<S>;
 
 	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
@@ -105,6 +430,8 @@ where
 	}
 }
 
+/// Juniper polls the root fields of a query together, each one running its own AQL query, so the
+/// independent operations of a request wait on the database concurrently
 impl<S> GraphQLValueAsync<S> for Query
 where
 	S: ScalarValue + Send + Sync,
@@ -116,13 +443,221 @@ where
 		arguments: &'b Arguments<S>,
 		executor: &'b Executor<Self::Context, S>,
 	) -> BoxFuture<'b, ExecutionResult<S>> {
-		Box::pin(async move {
+		let resolve = async move {
+			if let Some(remote) = remote_for(info, field_name, false) {
+				return resolve_remote_field(remote, false, executor).await;
+			}
+
 			executor
 				.resolve_async(
 					info,
 					&QueryFieldFactory::new_resolver(field_name, arguments),
 				)
 				.await
+		}
+		.instrument(tracing::info_span!(
+			"graphql.resolve",
+			graphql.operation = "query",
+			graphql.field = field_name
+		));
+
+		Box::pin(trace_resolver(executor, "Query", field_name, resolve))
+	}
+}
+
+pub struct Mutation;
+
+impl<S> GraphQLType<S> for Mutation
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn name(_: &Self::TypeInfo) -> Option<&str> {
+		Some("Mutation")
+	}
+
+	fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
+	where
+		S: 'r,
+	{
+		let mut mutations = build_root_fields(info, OperationKind::Mutation, registry);
+
+		if CONFIG.sessions_enabled() {
+			mutations.push(
+				registry
+					.field::<bool>(LOGIN_FIELD, &())
+					.argument(registry.arg::<String>("username", &()))
+					.argument(registry.arg::<String>("password", &())),
+			);
+			mutations.push(registry.field::<bool>(LOGOUT_FIELD, &()));
+		}
+
+		append_remote_fields(info, true, &mut mutations, registry);
+
+		registry
+			.build_object_type::<Mutation>(info, &mutations)
+			.into_meta()
+	}
+}
+
+impl<S> GraphQLValue<S> for Mutation
+where
+	S: ScalarValue + Send + Sync,
+{
+	type Context = Context;
+	type TypeInfo = QueryData<S>;
+
+	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+		<Self as GraphQLType<S>>::name(info)
+	}
+}
+
+impl<S> GraphQLValueAsync<S> for Mutation
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn resolve_field_async<'b>(
+		&'b self,
+		info: &'b Self::TypeInfo,
+		field_name: &'b str,
+		arguments: &'b Arguments<S>,
+		executor: &'b Executor<Self::Context, S>,
+	) -> BoxFuture<'b, ExecutionResult<S>> {
+		let resolve = async move {
+			let result =
+				if CONFIG.sessions_enabled() && [LOGIN_FIELD, LOGOUT_FIELD].contains(&field_name) {
+					resolve_session_field(executor.context(), field_name, arguments).await
+				} else if let Some(remote) = remote_for(info, field_name, true) {
+					resolve_remote_field(remote, true, executor).await
+				} else {
+					executor
+						.resolve_async(
+							info,
+							&QueryFieldFactory::new_resolver(field_name, arguments),
+						)
+						.await
+				};
+
+			if CONFIG.audit_log_enabled {
+				let error = result.as_ref().err().map(|e| e.message().to_string());
+
+				record_audit(executor, field_name, arguments, error).await;
+			}
+
+			result
+		}
+		.instrument(tracing::info_span!(
+			"graphql.resolve",
+			graphql.operation = "mutation",
+			graphql.field = field_name
+		));
+
+		Box::pin(trace_resolver(executor, "Mutation", field_name, resolve))
+	}
+}
+
+pub struct Subscription;
+
+impl<S> GraphQLType<S> for Subscription
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn name(_: &Self::TypeInfo) -> Option<&str> {
+		Some("Subscription")
+	}
+
+	fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
+	where
+		S: 'r,
+	{
+		let subscriptions = build_root_fields(info, OperationKind::Subscription, registry);
+
+		registry
+			.build_object_type::<Subscription>(info, &subscriptions)
+			.into_meta()
+	}
+}
+
+impl<S> GraphQLValue<S> for Subscription
+where
+	S: ScalarValue + Send + Sync,
+{
+	type Context = Context;
+	type TypeInfo = QueryData<S>;
+
+	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+		<Self as GraphQLType<S>>::name(info)
+	}
+}
+
+impl<S> GraphQLSubscriptionValue<S> for Subscription
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn resolve_field_into_stream<'s, 'i, 'ft, 'args, 'e, 'ref_e, 'res, 'f>(
+		&'s self,
+		info: &'i Self::TypeInfo,
+		field_name: &'ft str,
+		arguments: Arguments<'args, S>,
+		executor: &'ref_e Executor<'ref_e, 'e, Self::Context, S>,
+	) -> BoxFuture<'f, Result<Value<ValuesStream<'res, S>>, FieldError<S>>>
+	where
+		's: 'f,
+		'i: 'res,
+		'ft: 'f,
+		'args: 'f,
+		'ref_e: 'res,
+		'e: 'res,
+		'res: 'f,
+	{
+		let field_name = field_name.to_string();
+		let id = arguments.get::<String>("id");
+
+		Box::pin(async move {
+			let entry = info
+				.operation_registry
+				.get_operation(&field_name)
+				.ok_or_else(|| {
+					FieldError::new(
+						format!("Unknown subscription {}", field_name),
+						Value::Object(error_extensions(ErrorCode::NotFound)),
+					)
+				})?;
+
+			if !entry.is_allowed(&field_name, executor.context().roles()) {
+				return Err(ForbiddenError::new(field_name).into_field_error());
+			}
+
+			// The changes are published with the collection of their tenant
+			let tenant = executor.context().tenant();
+
+			if CONFIG.tenant_prefix_enabled && tenant.is_none() {
+				return Err(MissingTenantError.into_field_error());
+			}
+
+			let collection =
+				tenant_collection(tenant.as_deref(), &entry.data.entity.collection_name);
+
+			let stream = subscribe_changes()
+				.filter(move |event| {
+					future::ready(
+						event.collection == collection
+							&& id.as_ref().map(|id| *id == event.key).unwrap_or(true),
+					)
+				})
+				.then(move |event| {
+					let resolver = SubscriptionEventResolver::new(field_name.clone(), event);
+
+					async move {
+						executor
+							.resolve_async(info, &resolver)
+							.await
+							.map_err(ExecutionError::at_origin)
+					}
+				});
+
+			let stream: ValuesStream<'res, S> = Box::pin(stream);
+
+			Ok(Value::Scalar(stream))
 		})
 	}
 }
@@ -0,0 +1,102 @@
+use std::collections::HashMap;
+
+use convert_case::{Case, Casing};
+use lazy_static::lazy_static;
+
+use crate::lib::database::api::DbEntity;
+use crate::lib::schema::is_graphql_name;
+use crate::lib::CONFIG;
+
+/// The operations whose names are rendered from templates, along with their default templates
+const DEFAULT_TEMPLATES: [(&str, &str); 12] = [
+	("get", "get{Singular}"),
+	("getBy", "get{Singular}By{Field}"),
+	("getAll", "getAll{Plural}"),
+	("sample", "sample{Plural}"),
+	("count", "count{Plural}"),
+	("create", "create{Singular}"),
+	("update", "update{Singular}"),
+	("delete", "delete{Singular}"),
+	("history", "{singular}History"),
+	("versions", "{singular}Versions"),
+	("asOf", "{singular}AsOf"),
+	("changed", "{singular}Changed"),
+];
+
+lazy_static! {
+	/// Templates of the operation names, see `graphql_operation_names`
+	static ref TEMPLATES: HashMap<&'static str, String> = {
+		let mut templates: HashMap<&'static str, String> = DEFAULT_TEMPLATES
+			.iter()
+			.map(|(operation, template)| (*operation, template.to_string()))
+			.collect();
+
+		for entry in CONFIG.graphql_operation_names.iter().flatten() {
+			let template = entry
+				.split_once('=')
+				.and_then(|(operation, template)| {
+					Some((templates.get_mut(operation.trim())?, template.trim()))
+				});
+
+			match template {
+				Some((current, template)) if !template.is_empty() => {
+					*current = template.to_string()
+				}
+				_ => tracing::warn!(template = %entry, "Ignoring an invalid operation name template"),
+			}
+		}
+
+		templates
+	};
+}
+
+/// Replace the placeholders of a template, `{singular}` and `{plural}` name one and several
+/// documents of the entity, `{field}` the property a lookup reads, capitalized to start with
+/// an uppercase letter
+fn render(template: &str, entity: &DbEntity, field: &str) -> String {
+	let name = entity.name.to_case(Case::Pascal);
+
+	let singular = entity
+		.singular_name
+		.clone()
+		.unwrap_or_else(|| pluralizer::pluralize(&name, 1, false));
+	let plural = entity
+		.plural_name
+		.clone()
+		.unwrap_or_else(|| pluralizer::pluralize(&name, 2, false));
+
+	template
+		.replace("{singular}", &singular.to_case(Case::Camel))
+		.replace("{Singular}", &singular.to_case(Case::Pascal))
+		.replace("{plural}", &plural.to_case(Case::Camel))
+		.replace("{Plural}", &plural.to_case(Case::Pascal))
+		.replace("{field}", &field.to_case(Case::Camel))
+		.replace("{Field}", &field.to_case(Case::Pascal))
+}
+
+/// The name of a generated operation of an entity, e.g. `getAll` or `getBy`, which falls back
+/// to its default one when the configured template renders an invalid name
+pub fn operation_name(operation: &str, entity: &DbEntity, field: Option<&str>) -> String {
+	let field = field.unwrap_or_default();
+	let name = render(&TEMPLATES[operation], entity, field);
+
+	if is_graphql_name(&name) {
+		return name;
+	}
+
+	let default = DEFAULT_TEMPLATES
+		.iter()
+		.find(|(name, _)| *name == operation)
+		.map(|(_, template)| render(template, entity, field))
+		.unwrap_or_default();
+
+	tracing::warn!(
+		operation,
+		collection = %entity.collection_name,
+		name = %name,
+		default = %default,
+		"Invalid operation name, the default one is used"
+	);
+
+	default
+}
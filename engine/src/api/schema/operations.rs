@@ -1,21 +1,59 @@
+use chrono::{SecondsFormat, Utc};
 use convert_case::Casing;
 use juniper::meta::{Argument, Field};
 use juniper::{
-	Arguments, BoxFuture, ExecutionResult, IntoFieldError, Object, Registry, ScalarValue, Value, ID,
+	Arguments, BoxFuture, ExecutionResult, FieldError, IntoFieldError, Object, Registry,
+	ScalarValue, Value, ID,
 };
-use rust_arango::{AqlQuery, ClientError};
-use serde_json::{Map as JsonMap, Number as JsonNumber, Value as JsonValue};
+use serde_json::{json, Map as JsonMap, Number as JsonNumber, Value as JsonValue};
 use std::collections::HashMap;
 use std::marker::PhantomData;
 use std::sync::Arc;
-
-use crate::api::schema::errors::NotFoundError;
+use tracing::Instrument;
+
+use crate::api::access_lists::is_operation_allowed;
+use crate::api::cache::invalidate_collection;
+use crate::api::change_feed::change_feed_enabled;
+use crate::api::query_cache::{get_cached_result, query_cache_key, store_result};
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{
+	CheckConstraintViolation, DatabaseError, InvalidDateTimeError, InvalidFilterError,
+	LimitExceededError, NegativeLimitError, NotFoundError, ReferenceNotFoundError,
+	UniqueConstraintViolation, ERROR_ARANGO_UNIQUE_CONSTRAINT_VIOLATED,
+};
 use crate::api::schema::fields::Entity;
-use crate::lib::database::api::{DbEntity, DbRelationship};
+use crate::api::schema::filters::{EntityFilter, FilterInfo};
+use crate::api::schema::geo::{is_valid_geometry, GeoNearInput, GeoWithinInput};
+use crate::api::schema::history::HistoryEntry;
+use crate::api::schema::inputs::{
+	build_argument_from_property, canonical_decimal, EntityInput, EntityInputInfo, JsonInput,
+};
+use crate::api::schema::naming::operation_name;
+use crate::api::schema::ordering::{EntityOrderBy, OrderByInfo};
+use crate::api::schema::pages::{
+	build_page, decode_cursor, is_paginated, Cursor, EntityPage, CURSOR_PROPERTY,
+};
+use crate::api::schema::scalars::DateTime;
+use crate::api::schema::validation::validate_input;
+use crate::api::schema::values::DocumentValue;
+use crate::api::schema::versioning::VersionEntry;
+use crate::api::streaming::take_list_stream;
+use crate::lib::changes::{publish_change, ChangeEvent, ChangeOperation};
+use crate::lib::database::api::{DbEntity, DbProperty, DbRelationship, DbScalarType};
 use crate::lib::database::aql::{
-	AQLFilter, AQLOperation, AQLQuery, AQLQueryBind, AQLQueryParameter,
+	AQLCondition, AQLExpiryFilter, AQLFilter, AQLGeoNear, AQLGeoWithin, AQLMutation, AQLNode,
+	AQLOperation, AQLProperty, AQLQuery, AQLQueryBind, AQLQueryParameter, AQLSort, AQLWhere,
 };
-use crate::lib::database::DATABASE;
+use crate::lib::database::backend::{is_explaining, run_query, BackendError, BackendQuery};
+use crate::lib::events::{events_enabled, outbox_enabled, publish_event, OUTBOX_COLLECTION};
+use crate::lib::redaction::redact_aql;
+use crate::lib::schema::{
+	get_history_collection_name, get_versions_collection_name, RowFilterOperator, SchemaOperation,
+};
+use crate::lib::telemetry::aql_span;
+use crate::lib::tenants::{tenant_collection, with_tenant};
+use crate::lib::triggers::{dispatch_triggers, TriggerEvent};
+use crate::lib::CONFIG;
 
 type FutureType<'b, S> = BoxFuture<'b, ExecutionResult<S>>;
 
@@ -26,6 +64,14 @@ where
 	operations: HashMap<String, OperationEntry<S>>,
 }
 
+/// The root type an operation is exposed on
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OperationKind {
+	Query,
+	Mutation,
+	Subscription,
+}
+
 pub struct OperationEntry<S>
 where
 	S: ScalarValue,
@@ -33,11 +79,15 @@ where
 	pub closure: for<'a> fn(
 		&'a OperationData<S>,
 		&'a juniper::Arguments<S>,
+		&'a Context,
 		AQLQuery<'a>,
 	) -> FutureType<'a, S>,
-	pub arguments_closure: for<'a> fn(&mut Registry<'a, S>) -> Vec<Argument<'a, S>>,
+	pub arguments_closure:
+		for<'a> fn(&mut Registry<'a, S>, data: &OperationData<S>) -> Vec<Argument<'a, S>>,
 	pub field_closure:
 		for<'a> fn(&mut Registry<'a, S>, name: &str, data: &OperationData<S>) -> Field<'a, S>,
+	pub kind: OperationKind,
+	pub operation: SchemaOperation,
 
 	pub data: Arc<OperationData<S>>,
 }
@@ -56,12 +106,13 @@ where
 		&'b self,
 		key: &str,
 		arguments: &'b Arguments<S>,
+		context: &'b Context,
 		query: AQLQuery<'b>,
 	) -> Option<FutureType<'b, S>> {
 		self.operations.get(key).map(|o| {
 			let closure = o.closure;
 
-			closure(&o.data, arguments, query)
+			closure(&o.data, arguments, context, query)
 		})
 	}
 
@@ -78,36 +129,151 @@ where
 		entity: Arc<DbEntity>,
 		relationships: Arc<Vec<DbRelationship>>,
 	) {
-		let data = Arc::new(OperationData {
-			entity: entity.clone(),
-			relationships: relationships.clone(),
+		let data = Arc::new(OperationData::new(
+			entity.clone(),
+			relationships.clone(),
+			None,
+		));
+
+		self.register::<Get>(data.clone());
+
+		// Every exposed scalar property unique on its own is also looked up by its value
+		for constraint in &entity.unique {
+			if let [property] = constraint.properties.as_slice() {
+				let comparable = entity
+					.properties
+					.iter()
+					.find(|p| &p.name == property)
+					.map(|p| {
+						!matches!(
+							p.scalar_type,
+							DbScalarType::Array(_)
+								| DbScalarType::Object | DbScalarType::File
+								| DbScalarType::GeoPoint | DbScalarType::GeoPolygon
+								| DbScalarType::Localized
+						)
+					})
+					.unwrap_or(false);
+
+				if !comparable {
+					continue;
+				}
 
-			_phantom: Default::default(),
-		});
+				self.register::<GetBy>(Arc::new(OperationData::new(
+					entity.clone(),
+					relationships.clone(),
+					Some(property.clone()),
+				)));
+			}
+		}
 
-		vec![
-			self.register::<Get>(data.clone()),
-			self.register::<GetAll>(data.clone()),
-		];
+		self.register::<GetAll>(data.clone());
+		self.register::<Sample>(data.clone());
+		self.register::<Count>(data.clone());
+		self.register::<Create>(data.clone());
+		self.register::<Update>(data.clone());
+		self.register::<Delete>(data.clone());
+
+		if entity.audit {
+			self.register::<History>(data.clone());
+		}
+
+		if entity.versioning {
+			self.register::<Versions>(data.clone());
+			self.register::<AsOf>(data.clone());
+		}
+
+		self.register::<Changed>(data.clone());
 	}
 
-	fn register<T: 'static>(&mut self, data: Arc<OperationData<S>>) -> String
+	/// Register the operation unless the entity disabled it
+	fn register<T: 'static>(&mut self, data: Arc<OperationData<S>>)
 	where
 		T: Operation<S>,
 	{
-		let k = T::get_operation_name(&data);
+		if !data.entity.is_enabled(T::get_schema_operation()) {
+			return;
+		}
+
+		// Lookups are named after their property, the rename of `get` is not theirs
+		let k = data
+			.entity
+			.operation_name(T::get_schema_operation())
+			.filter(|_| data.lookup.is_none())
+			.map(String::from)
+			.unwrap_or_else(|| T::get_operation_name(&data));
+
+		if self.operations.contains_key(&k) {
+			tracing::warn!(
+				operation = %k,
+				collection = %data.entity.collection_name,
+				"Operation name already taken, the operation is not generated"
+			);
+
+			return;
+		}
 
 		self.operations.insert(
-			k.clone(),
+			k,
 			OperationEntry {
 				closure: T::call,
 				arguments_closure: T::get_arguments,
 				field_closure: T::build_field,
+				kind: T::get_kind(),
+				operation: T::get_schema_operation(),
 				data,
 			},
 		);
+	}
+}
+
+impl<S> OperationEntry<S>
+where
+	S: ScalarValue,
+{
+	/// Whether the roles may run the operation named `name`, `None` when access is not
+	/// restricted by the collection, the operation lists still apply then
+	pub fn is_allowed(&self, name: &str, roles: Option<&[String]>) -> bool {
+		roles
+			.map(|roles| self.data.entity.is_allowed(roles, self.operation))
+			.unwrap_or(true)
+			&& is_operation_allowed(roles, name)
+	}
+}
+
+/// What the operations of an entity need on every request that does not depend on it, computed
+/// once when the schema is built
+pub struct OperationPlan {
+	/// The collections the operations may read, for the response and query caches
+	pub collections: Vec<String>,
+	/// The filter hiding the expired documents, if the entity opted in
+	pub expiry: Option<(String, u32)>,
+}
+
+impl OperationPlan {
+	fn new(entity: &DbEntity, relationships: &[DbRelationship]) -> Self {
+		let mut collections = vec![entity.collection_name.clone()];
+
+		for relationship in relationships {
+			for collection in [
+				&relationship.edge,
+				&relationship.from.collection_name,
+				&relationship.to.collection_name,
+			] {
+				if !collections.contains(collection) {
+					collections.push(collection.clone());
+				}
+			}
+		}
 
-		k
+		OperationPlan {
+			collections,
+			expiry: entity
+				.expiry
+				.as_ref()
+				.filter(|expiry| expiry.hide_expired)
+				.map(|expiry| (expiry.property.clone(), expiry.expire_after)),
+		}
 	}
 }
 
@@ -117,10 +283,46 @@ where
 {
 	pub entity: Arc<DbEntity>,
 	pub relationships: Arc<Vec<DbRelationship>>,
+	pub plan: OperationPlan,
+	/// Name of the type of the pages of the list operation
+	pub page_name: String,
+	/// The unique property the lookup operation finds a document by
+	pub lookup: Option<String>,
 
 	_phantom: PhantomData<S>,
 }
 
+impl<S> OperationData<S>
+where
+	S: ScalarValue,
+{
+	fn new(
+		entity: Arc<DbEntity>,
+		relationships: Arc<Vec<DbRelationship>>,
+		lookup: Option<String>,
+	) -> Self {
+		OperationData {
+			plan: OperationPlan::new(&entity, &relationships),
+			page_name: format!("{}Page", entity.name),
+			entity,
+			relationships,
+			lookup,
+
+			_phantom: Default::default(),
+		}
+	}
+
+	/// The property of the lookup operation
+	fn lookup_property(&self) -> Option<&DbProperty> {
+		let lookup = self.lookup.as_deref()?;
+
+		self.entity
+			.properties
+			.iter()
+			.find(|property| property.name == lookup)
+	}
+}
+
 pub trait Operation<S>
 where
 	S: ScalarValue,
@@ -129,13 +331,26 @@ where
 	fn call<'b>(
 		data: &'b OperationData<S>,
 		arguments: &'b Arguments<S>,
+		context: &'b Context,
 		query: AQLQuery<'b>,
 	) -> FutureType<'b, S>;
 
 	fn get_operation_name(data: &OperationData<S>) -> String;
 
-	fn get_arguments<'r>(registry: &mut Registry<'r, S>) -> Vec<Argument<'r, S>>;
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>>;
+
+	fn get_kind() -> OperationKind {
+		OperationKind::Query
+	}
+
+	/// The operation permissions refer to
+	fn get_schema_operation() -> SchemaOperation;
 
+	/// The root fields are nullable, the failure of one of them only nulls its own field and the
+	/// others of the request are still returned, alongside the error at its path
 	fn build_field<'r>(
 		registry: &mut Registry<'r, S>,
 		name: &str,
@@ -221,6 +436,248 @@ where
 	Value::Object(object)
 }
 
+/// Run a query on the backend, the documents are deserialized straight into juniper values
+async fn fetch_documents<S>(query: BackendQuery) -> Result<Vec<Value<S>>, BackendError>
+where
+	S: ScalarValue + Send + Sync,
+{
+	Ok(run_query::<DocumentValue<S>>(query)
+		.await?
+		.into_iter()
+		.map(|document| document.0)
+		.collect())
+}
+
+/// Serve the results from the query cache when the metadata sets a TTL for the operation
+async fn with_query_cache<S, F>(
+	data: &OperationData<S>,
+	operation: SchemaOperation,
+	key: String,
+	fetch: F,
+) -> Result<Vec<Value<S>>, BackendError>
+where
+	S: ScalarValue + Send + Sync,
+	F: std::future::Future<Output = Result<Vec<Value<S>>, BackendError>>,
+{
+	let ttl = match data.entity.cache_ttl(operation) {
+		Some(ttl) if !is_explaining() => ttl,
+		_ => return fetch.await,
+	};
+
+	if let Some(body) = get_cached_result(&key).await {
+		if let Ok(results) = serde_json::from_slice::<Vec<DocumentValue<S>>>(&body) {
+			return Ok(results.into_iter().map(|result| result.0).collect());
+		}
+	}
+
+	let results = fetch.await?;
+
+	match serde_json::to_vec(&results) {
+		Ok(body) => {
+			let collections = data.plan.collections.iter().cloned().collect();
+
+			store_result(key, body, collections, ttl).await
+		}
+		Err(e) => tracing::error!(error = %e, "Failed to encode the results to cache"),
+	}
+
+	Ok(results)
+}
+
+/// The limit of a list operation, defaulted and checked against the configured maximum, a
+/// lowered limit is reported to the client
+fn list_limit<S: ScalarValue>(
+	limit: Option<i32>,
+	context: &Context,
+) -> Result<Option<i32>, FieldError<S>> {
+	let limit = limit.or(CONFIG.graphql_list_default_limit);
+
+	if let Some(limit) = limit.filter(|limit| *limit < 0) {
+		return Err(NegativeLimitError::new(limit).into_field_error());
+	}
+
+	match (limit, CONFIG.graphql_list_max_limit) {
+		(Some(limit), Some(max)) if limit > max => {
+			if !CONFIG.graphql_list_clamp_limit {
+				return Err(LimitExceededError::new(limit, max).into_field_error());
+			}
+
+			tracing::warn!(
+				limit,
+				max,
+				"Lowered the limit of a list operation to the maximum"
+			);
+
+			context.warn(format!(
+				"limit {} exceeds the maximum of {}, it was lowered to {}",
+				limit, max, max
+			));
+
+			Ok(Some(max))
+		}
+		(None, Some(max)) => Ok(Some(max)),
+		(limit, _) => Ok(limit),
+	}
+}
+
+/// Apply the `_within` and `_near` filters of a list operation
+fn add_geo_filters<S>(
+	entity: &DbEntity,
+	arguments: &Arguments<S>,
+	query: &mut AQLQuery,
+) -> Result<(), InvalidFilterError>
+where
+	S: ScalarValue,
+{
+	let geo_property = |field: &str| -> Result<&DbProperty, InvalidFilterError> {
+		match entity.property_by_field(field) {
+			Some(property) if property.scalar_type.geometry_type().is_some() => Ok(property),
+			_ => Err(InvalidFilterError::new(
+				field.to_string(),
+				format!("{} is not a geo field", field),
+			)),
+		}
+	};
+
+	if let Some(within) = arguments.get::<GeoWithinInput>("_within") {
+		let property = geo_property(&within.field)?;
+		let coordinates = json!(within.coordinates);
+
+		if !is_valid_geometry(&DbScalarType::GeoPolygon, &coordinates) {
+			return Err(InvalidFilterError::new(
+				within.field,
+				String::from("The coordinates of _within are not a valid polygon"),
+			));
+		}
+
+		query.add_filter(Box::new(AQLGeoWithin {
+			property: property.name.clone(),
+			polygon: json!({ "type": "Polygon", "coordinates": coordinates }),
+		}));
+	}
+
+	if let Some(near) = arguments.get::<GeoNearInput>("_near") {
+		let property = geo_property(&near.field)?;
+
+		if !is_valid_geometry(
+			&DbScalarType::GeoPoint,
+			&json!([near.longitude, near.latitude]),
+		) {
+			return Err(InvalidFilterError::new(
+				near.field,
+				String::from("The coordinates of _near are not a valid point"),
+			));
+		}
+
+		let near = AQLGeoNear {
+			property: property.name.clone(),
+			longitude: near.longitude,
+			latitude: near.latitude,
+			max_distance: near.max_distance,
+		};
+
+		if near.max_distance.is_some() {
+			query.add_filter(Box::new(near.clone()));
+		}
+
+		query.near = Some(near);
+	}
+
+	Ok(())
+}
+
+/// Hide the expired documents of entities that opted in
+fn add_expiry_filter(plan: &OperationPlan, query: &mut AQLQuery) {
+	if let Some((ref property, expire_after)) = plan.expiry {
+		query.add_filter(Box::new(AQLExpiryFilter {
+			property: property.clone(),
+			expire_after,
+		}));
+	}
+}
+
+/// Fetch the document whose key is bound to the `id` query parameter
+pub fn fetch_document<'b, S>(
+	data: &'b OperationData<S>,
+	context: &'b Context,
+	query: AQLQuery<'b>,
+) -> FutureType<'b, S>
+where
+	S: ScalarValue + Send + Sync,
+{
+	fetch_document_by(data, context, query, "_key")
+}
+
+/// The document whose property equals the `id` parameter
+fn fetch_document_by<'b, S>(
+	data: &'b OperationData<S>,
+	context: &'b Context,
+	mut query: AQLQuery<'b>,
+	property: &str,
+) -> FutureType<'b, S>
+where
+	S: ScalarValue + Send + Sync,
+{
+	let time = std::time::Instant::now();
+
+	let entity = &data.entity;
+	let collection = &entity.collection_name;
+
+	query.add_filter(Box::new(AQLFilter {
+		left_node: Box::new(AQLQueryParameter(property.to_string())),
+		operation: AQLOperation::EQUAL,
+		right_node: Box::new(AQLQueryBind("id")),
+	}));
+	query.limit = Some(1);
+
+	add_expiry_filter(&data.plan, &mut query);
+
+	let key = query
+		.parameters
+		.get("id")
+		.cloned()
+		.unwrap_or(JsonValue::Null);
+
+	Box::pin(async move {
+		let query_str = query.to_aql();
+
+		tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
+
+		let cache_key = query_cache_key(
+			&query_str,
+			&json!({ "collection": collection, "tenant": query.tenant, "id": key }),
+			context.roles(),
+		);
+
+		let entries_query = BackendQuery::new(&query_str)
+			.bind_var("@collection", collection.clone())
+			.bind_var(query.get_argument_key("id"), key);
+
+		let entries = with_query_cache(
+			data,
+			SchemaOperation::Get,
+			cache_key,
+			fetch_documents(with_tenant(entries_query, query.tenant.as_deref()))
+				.instrument(aql_span(collection, &query_str)),
+		)
+		.await;
+
+		tracing::info!(operation = "get", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+		return match entries {
+			Ok(data) => match data.into_iter().next() {
+				Some(document) => Ok(document),
+				None => Err(NotFoundError::new(entity.name.clone()).into_field_error()),
+			},
+			Err(e) => {
+				tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+				Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
+			}
+		};
+	})
+}
+
 pub struct Get;
 
 impl<S> Operation<S> for Get
@@ -230,83 +687,103 @@ where
 	fn call<'b>(
 		data: &'b OperationData<S>,
 		arguments: &'b Arguments<S>,
+		context: &'b Context,
 		mut query: AQLQuery<'b>,
 	) -> FutureType<'b, S> {
-		let time = std::time::Instant::now();
-
-		let entity = &data.entity;
-		let collection = &entity.collection_name;
-
-		query.filter = Some(Box::new(AQLFilter {
-			left_node: Box::new(AQLQueryParameter("_key".to_string())),
-			operation: AQLOperation::EQUAL,
-			right_node: Box::new(AQLQueryBind("id")),
-		}));
-		query.limit = Some(1);
+		if let Some(id) = arguments.get::<String>("id") {
+			query.parameters.insert("id", JsonValue::String(id));
+		}
 
-		Box::pin(async move {
-			let query_str = query.to_aql();
+		fetch_document(data, context, query)
+	}
 
-			println!("{}", &query_str);
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("get", &data.entity, None)
+	}
 
-			let entries_query = AqlQuery::builder()
-				.query(&query_str)
-				.bind_var("@collection".to_string(), collection.clone())
-				.bind_var(
-					query.get_argument_key("id"),
-					arguments.get::<String>("id").unwrap(),
-				);
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		_data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![registry.arg::<ID>("id", &())]
+	}
 
-			let entries: Result<Vec<JsonValue>, ClientError> = DATABASE
-				.get()
-				.await
-				.database
-				.aql_query(entries_query.build())
-				.await;
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Get
+	}
 
-			let not_found_error = NotFoundError::new(entity.name.clone()).into_field_error();
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Entity>>(name, &data)
+	}
+}
 
-			println!("SQL: {:?}", time.elapsed());
+/// A document found by the value of a property unique on its own, through its unique index
+pub struct GetBy;
 
-			return match entries {
-				Ok(data) => {
-					if let Some(first) = data.first() {
-						let time2 = std::time::Instant::now();
+impl<S> Operation<S> for GetBy
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let property = match data.lookup_property() {
+			Some(property) => property,
+			None => {
+				return Box::pin(async move {
+					Err(NotFoundError::new(data.entity.name.clone()).into_field_error())
+				})
+			}
+		};
 
-						let ret = Ok(convert_json_to_juniper_value(first.as_object().unwrap()));
+		if let Some(JsonInput(value)) = arguments.get::<JsonInput>(property.field_name()) {
+			let value = match property.scalar_type {
+				DbScalarType::Decimal => canonical_decimal(&value, property.rules.decimal_scale()),
+				_ => value,
+			};
 
-						println!("Conversion: {:?}", time2.elapsed());
+			query.parameters.insert("id", value);
+		}
 
-						return ret;
-					}
+		fetch_document_by(data, context, query, &property.name)
+	}
 
-					Err(not_found_error)
-				}
-				Err(e) => {
-					println!("{:?}", e);
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		let field = data.lookup_property().map(|property| property.field_name());
 
-					Err(not_found_error)
-				}
-			};
-		})
+		operation_name("getBy", &data.entity, field)
 	}
 
-	fn get_operation_name(data: &OperationData<S>) -> String {
-		format!(
-			"get{}",
-			pluralizer::pluralize(
-				data.entity
-					.name
-					.to_case(convert_case::Case::Pascal)
-					.as_str(),
-				1,
-				false,
-			)
-		)
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		data.lookup_property()
+			.map(|property| {
+				let mut argument =
+					build_argument_from_property(registry, property, &property.scalar_type, true);
+
+				// The value is always required, whether the property is or not
+				argument.arg_type = match argument.arg_type {
+					juniper::Type::Named(name) => juniper::Type::NonNullNamed(name),
+					arg_type => arg_type,
+				};
+
+				argument
+			})
+			.into_iter()
+			.collect()
 	}
 
-	fn get_arguments<'r>(registry: &mut Registry<'r, S>) -> Vec<Argument<'r, S>> {
-		vec![registry.arg::<ID>("id", &())]
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Get
 	}
 
 	fn build_field<'r>(
@@ -327,6 +804,7 @@ where
 	fn call<'b>(
 		data: &'b OperationData<S>,
 		arguments: &'b Arguments<S>,
+		context: &'b Context,
 		mut query: AQLQuery<'b>,
 	) -> FutureType<'b, S> {
 		let time = std::time::Instant::now();
@@ -334,69 +812,199 @@ where
 		let entity = &data.entity;
 		let collection = &entity.collection_name;
 
-		query.limit = arguments.get::<i32>("limit");
+		query.limit = match list_limit(arguments.get::<i32>("limit"), context) {
+			Ok(limit) => limit,
+			Err(e) => return Box::pin(async move { Err(e) }),
+		};
+
+		if let Err(e) = add_geo_filters(entity, arguments, &mut query) {
+			return Box::pin(async move { Err(e.into_field_error()) });
+		}
+
+		if let Some(filter) = arguments.get::<EntityFilter>("where") {
+			match filter.into_where(entity) {
+				Ok(filter) => query.add_filter(Box::new(filter)),
+				Err(e) => return Box::pin(async move { Err(e.into_field_error()) }),
+			}
+		}
+
+		if let Some(order_by) = arguments.get::<Vec<EntityOrderBy>>("orderBy") {
+			query.sort = order_by
+				.into_iter()
+				.filter_map(|order_by| order_by.into_sort(entity))
+				.collect();
+		}
+
+		let paginated = is_paginated(SchemaOperation::GetAll);
+		let limit = query.limit;
+
+		// Lists in their default order are sorted by key, so that they continue after a key
+		// through the primary index however far they go
+		let keyset = query.sort.is_empty() && query.near.is_none();
+
+		// Pages are continued from their opaque cursors, an unknown one starts from the first
+		let cursor = match arguments.get::<String>("after") {
+			Some(after) if paginated => decode_cursor(&after),
+			Some(after) => Some(Cursor::Key(after)),
+			None => None,
+		};
+
+		let offset = match cursor {
+			Some(Cursor::Offset(position)) => position + 1,
+			_ => 0,
+		};
+
+		if let Some(Cursor::Key(ref key)) = cursor {
+			if !keyset {
+				let error = InvalidFilterError::new(
+					String::from("after"),
+					String::from("after can only follow the default order by key"),
+				);
+
+				return Box::pin(async move { Err(error.into_field_error()) });
+			}
+
+			query.add_filter(Box::new(AQLWhere {
+				conditions: vec![AQLCondition::Compare {
+					property: String::from("_key"),
+					operator: RowFilterOperator::Greater,
+					value: json!(key),
+				}],
+				analyzer: None,
+			}));
+		}
+
+		if keyset {
+			query.sort.push(AQLSort {
+				property: String::from("_key"),
+				numeric: false,
+				descending: false,
+				nulls_first: None,
+			});
+		}
+
+		if paginated {
+			if keyset {
+				query.properties.push(AQLProperty {
+					name: CURSOR_PROPERTY.to_string(),
+					attribute: String::from("_key"),
+					masked: false,
+					locales: None,
+				});
+			} else {
+				query.offset = Some(offset).filter(|offset| *offset > 0);
+			}
+
+			query.limit = limit.map(|limit| limit + 1);
+		}
+
+		let has_previous_page = matches!(cursor, Some(Cursor::Key(_))) || offset > 0;
+
+		add_expiry_filter(&data.plan, &mut query);
 
 		Box::pin(async move {
 			let query_str = query.to_aql();
 
-			println!("{}", &query_str);
-
-			let entries_query = AqlQuery::builder()
-				.query(&query_str)
-				.bind_var("@collection".to_string(), collection.clone());
+			tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
 
-			let entries: Result<Vec<JsonValue>, ClientError> = DATABASE
-				.get()
-				.await
-				.database
-				.aql_query(entries_query.build())
-				.await;
+			let entries_query = BackendQuery::new(&query_str)
+				.bind_var("@collection", collection.clone())
+				.batch_size(CONFIG.cursor_batch_size);
 
-			let not_found_error = NotFoundError::new(entity.name.clone()).into_field_error();
+			// The list is the whole response, its documents are written to it as they are read
+			let stream = (!paginated && entity.cache_ttl(SchemaOperation::GetAll).is_none())
+				.then(take_list_stream)
+				.flatten();
 
-			println!("SQL: {:?}", time.elapsed());
+			if let Some(stream) = stream {
+				let output = stream
+					.write(with_tenant(entries_query, query.tenant.as_deref()))
+					.instrument(aql_span(collection, &query_str))
+					.await;
 
-			return match entries {
-				Ok(data) => {
-					let mut output = Vec::<Value<S>>::new();
+				tracing::info!(operation = "getAll", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, streamed = true, "AQL query executed");
 
-					let time2 = std::time::Instant::now();
+				return match output {
+					Ok(()) => Ok(Value::list(Vec::new())),
+					Err(e) => {
+						tracing::error!(collection = %collection, error = ?e, "AQL query failed");
 
-					for datum in data {
-						output.push(convert_json_to_juniper_value(datum.as_object().unwrap()));
+						Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
 					}
+				};
+			}
 
-					println!("Conversion: {:?}", time2.elapsed());
+			let cache_key = query_cache_key(
+				&query_str,
+				&json!({ "collection": collection, "tenant": query.tenant }),
+				context.roles(),
+			);
 
-					Ok(Value::list(output))
-				}
+			let output = with_query_cache(
+				data,
+				SchemaOperation::GetAll,
+				cache_key,
+				fetch_documents(with_tenant(entries_query, query.tenant.as_deref()))
+					.instrument(aql_span(collection, &query_str)),
+			)
+			.await;
+
+			tracing::info!(operation = "getAll", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+			return match output {
+				Ok(output) if paginated => Ok(build_page(
+					output,
+					(!keyset).then(|| offset),
+					has_previous_page,
+					limit,
+				)),
+				Ok(output) => Ok(Value::list(output)),
 				Err(e) => {
-					println!("{:?}", e);
+					tracing::error!(collection = %collection, error = ?e, "AQL query failed");
 
-					Err(not_found_error)
+					Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
 				}
 			};
 		})
 	}
 
 	fn get_operation_name(data: &OperationData<S>) -> String {
-		format!(
-			"getAll{}",
-			pluralizer::pluralize(
-				data.entity
-					.name
-					.to_case(convert_case::Case::Pascal)
-					.as_str(),
-				2,
-				false,
-			)
-		)
+		operation_name("getAll", &data.entity, None)
 	}
 
-	fn get_arguments<'r>(registry: &mut Registry<'r, S>) -> Vec<Argument<'r, S>> {
-		vec![
-			registry.arg::<Option<i32>>("limit", &())
-		]
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		let mut arguments = vec![registry.arg::<Option<i32>>("limit", &())];
+
+		// The key of the last document read, or the end cursor of the last page
+		arguments.push(registry.arg::<Option<ID>>("after", &()));
+
+		if let Some(info) = FilterInfo::new(&data.entity) {
+			arguments.push(registry.arg::<Option<EntityFilter>>("where", &info));
+		}
+
+		if let Some(info) = OrderByInfo::new(&data.entity) {
+			arguments.push(registry.arg::<Option<Vec<EntityOrderBy>>>("orderBy", &info));
+		}
+
+		// The geo filters are only offered on entities with geo properties
+		if data
+			.entity
+			.properties
+			.iter()
+			.any(|property| property.scalar_type.geometry_type().is_some())
+		{
+			arguments.push(registry.arg::<Option<GeoWithinInput>>("_within", &()));
+			arguments.push(registry.arg::<Option<GeoNearInput>>("_near", &()));
+		}
+
+		arguments
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::GetAll
 	}
 
 	fn build_field<'r>(
@@ -404,6 +1012,922 @@ where
 		name: &str,
 		data: &OperationData<S>,
 	) -> Field<'r, S> {
-		registry.field::<Vec<Entity>>(name, &data)
+		if is_paginated(SchemaOperation::GetAll) {
+			return registry.field::<Option<EntityPage>>(name, &data);
+		}
+
+		registry.field::<Option<Vec<Entity>>>(name, &data)
+	}
+}
+
+/// Documents picked at random, for previews and datasets
+pub struct Sample;
+
+impl<S> Operation<S> for Sample
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let time = std::time::Instant::now();
+
+		let entity = &data.entity;
+		let collection = &entity.collection_name;
+
+		let size = arguments.get::<i32>("n").unwrap_or_default().max(0);
+
+		query.limit = match list_limit(Some(size), context) {
+			Ok(limit) => limit,
+			Err(e) => return Box::pin(async move { Err(e) }),
+		};
+		query.random = true;
+
+		add_expiry_filter(&data.plan, &mut query);
+
+		// The results are never cached, every call draws new documents
+		Box::pin(async move {
+			let query_str = query.to_aql();
+
+			tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
+
+			let entries_query = BackendQuery::new(&query_str)
+				.bind_var("@collection", collection.clone())
+				.batch_size(CONFIG.cursor_batch_size);
+
+			let output = fetch_documents(with_tenant(entries_query, query.tenant.as_deref()))
+				.instrument(aql_span(collection, &query_str))
+				.await;
+
+			tracing::info!(operation = "sample", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+			return match output {
+				Ok(output) => Ok(Value::list(output)),
+				Err(e) => {
+					tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+					Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
+				}
+			};
+		})
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("sample", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		_data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![registry.arg::<i32>("n", &())]
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Sample
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Vec<Entity>>>(name, &data)
+	}
+}
+
+/// The number of documents matching a filter, counted by the database without reading them
+pub struct Count;
+
+impl<S> Operation<S> for Count
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let time = std::time::Instant::now();
+
+		let entity = &data.entity;
+		let collection = &entity.collection_name;
+
+		if let Some(filter) = arguments.get::<EntityFilter>("where") {
+			match filter.into_where(entity) {
+				Ok(filter) => query.add_filter(Box::new(filter)),
+				Err(e) => return Box::pin(async move { Err(e.into_field_error()) }),
+			}
+		}
+
+		query.count = true;
+
+		add_expiry_filter(&data.plan, &mut query);
+
+		Box::pin(async move {
+			let query_str = query.to_aql();
+
+			tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
+
+			let count_query =
+				BackendQuery::new(&query_str).bind_var("@collection", collection.clone());
+
+			let cache_key = query_cache_key(
+				&query_str,
+				&json!({ "collection": collection, "tenant": query.tenant }),
+				context.roles(),
+			);
+
+			let output = with_query_cache(
+				data,
+				SchemaOperation::Count,
+				cache_key,
+				fetch_documents(with_tenant(count_query, query.tenant.as_deref()))
+					.instrument(aql_span(collection, &query_str)),
+			)
+			.await;
+
+			tracing::info!(operation = "count", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+			return match output {
+				Ok(output) => Ok(output
+					.into_iter()
+					.next()
+					.unwrap_or_else(|| Value::scalar(0))),
+				Err(e) => {
+					tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+					Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
+				}
+			};
+		})
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("count", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		FilterInfo::new(&data.entity)
+			.map(|info| registry.arg::<Option<EntityFilter>>("where", &info))
+			.into_iter()
+			.collect()
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Count
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		_data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<i32>>(name, &())
+	}
+}
+
+/// Run a mutation query and return the changed document
+fn execute_mutation<'b, S>(
+	data: &'b OperationData<S>,
+	context: &'b Context,
+	mut query: AQLQuery<'b>,
+	document: Option<JsonValue>,
+	id: Option<String>,
+) -> FutureType<'b, S>
+where
+	S: ScalarValue + Send + Sync,
+{
+	let time = std::time::Instant::now();
+
+	let entity = &data.entity;
+	let collection = &entity.collection_name;
+
+	let triggers = query
+		.mutation
+		.as_ref()
+		.map(|mutation| entity.triggers_for(mutation.into()))
+		.unwrap_or_default();
+
+	query.outbox = outbox_enabled();
+	query.changes = !triggers.is_empty() || (events_enabled() && !query.outbox);
+
+	Box::pin(async move {
+		// Row filters only let the visible documents be updated or removed
+		if let (Some(filter), Some(id)) = (query.filter.take(), &id) {
+			match is_visible(collection, &query, filter.as_ref(), id).await {
+				Ok(true) => {}
+				Ok(false) => return Err(NotFoundError::new(entity.name.clone()).into_field_error()),
+				Err(e) => {
+					tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+					return Err(DatabaseError::new(entity.name.clone(), e).into_field_error());
+				}
+			}
+		}
+
+		let query_str = query.to_aql();
+
+		tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
+
+		let mut entries_query =
+			BackendQuery::new(&query_str).bind_var("@collection", collection.clone());
+
+		if query.history {
+			entries_query =
+				entries_query.bind_var("@history", get_history_collection_name(collection));
+		}
+
+		if query.versions {
+			entries_query =
+				entries_query.bind_var("@versions", get_versions_collection_name(collection));
+		}
+
+		if query.outbox {
+			entries_query = entries_query
+				.bind_var("@outbox", OUTBOX_COLLECTION)
+				.bind_var(query.get_argument_key("collection"), collection.clone())
+				.bind_var(query.get_argument_key("tenant"), json!(query.tenant))
+				.bind_var(query.get_argument_key("actor"), json!(context.user_id()));
+		}
+
+		if let Some(document) = document {
+			entries_query = entries_query.bind_var(query.get_argument_key("data"), document);
+		}
+
+		if let Some(id) = id {
+			entries_query = entries_query.bind_var(query.get_argument_key("id"), id);
+		}
+
+		let entries = run_query::<JsonValue>(with_tenant(entries_query, query.tenant.as_deref()))
+			.instrument(aql_span(collection, &query_str))
+			.await;
+
+		let not_found_error = NotFoundError::new(entity.name.clone()).into_field_error();
+
+		tracing::info!(operation = "mutation", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+		return match entries {
+			Ok(data) => match data.first() {
+				Some(first) => {
+					invalidate_collection(collection);
+
+					// The change feed publishes the change once it reaches the WAL
+					if let (Some(mutation), Some(key), false) = (
+						&query.mutation,
+						first["key"].as_str(),
+						change_feed_enabled(),
+					) {
+						publish_change(ChangeEvent {
+							collection: tenant_collection(query.tenant.as_deref(), collection),
+							key: key.to_string(),
+							operation: mutation.into(),
+						});
+					}
+
+					if let Some(mutation) = &query.mutation {
+						let event = TriggerEvent {
+							collection: collection.clone(),
+							tenant: query.tenant.clone(),
+							operation: ChangeOperation::from(mutation),
+							old: first["old"].clone(),
+							new: first["new"].clone(),
+							actor: context.user_id(),
+						};
+
+						publish_event(event.clone());
+						dispatch_triggers(triggers, event);
+					}
+
+					match first["document"].as_object() {
+						Some(document) => Ok(convert_json_to_juniper_value(document)),
+						None => Err(not_found_error),
+					}
+				}
+				None => Err(not_found_error),
+			},
+			Err(e) => {
+				if let Some(violation) = unique_violation(entity, &e) {
+					return Err(violation.into_field_error());
+				}
+
+				if let Some(missing) = missing_reference(entity, &e) {
+					return Err(missing.into_field_error());
+				}
+
+				if let Some(violation) = check_violation(entity, &e) {
+					return Err(violation.into_field_error());
+				}
+
+				tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+				Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
+			}
+		};
+	})
+}
+
+/// The unique constraint a failed write conflicts with, found from the name of its index
+fn unique_violation(entity: &DbEntity, error: &BackendError) -> Option<UniqueConstraintViolation> {
+	if error.error_num() != Some(ERROR_ARANGO_UNIQUE_CONSTRAINT_VIOLATED) {
+		return None;
+	}
+
+	let constraint = entity
+		.unique
+		.iter()
+		.find(|constraint| error.message().contains(&constraint.index_name()))?;
+
+	let fields = constraint
+		.properties
+		.iter()
+		.map(|name| {
+			entity
+				.properties
+				.iter()
+				.find(|property| &property.name == name)
+				.map(|property| property.field_name().to_string())
+				.unwrap_or_else(|| name.clone())
+		})
+		.collect();
+
+	Some(UniqueConstraintViolation::new(entity.name.clone(), fields))
+}
+
+/// ArangoDB error number of a failed `ASSERT`
+const ERROR_QUERY_USER_ASSERT: u16 = 1569;
+
+/// The reference a failed write points to a missing document with
+fn missing_reference(entity: &DbEntity, error: &BackendError) -> Option<ReferenceNotFoundError> {
+	if error.error_num() != Some(ERROR_QUERY_USER_ASSERT) {
+		return None;
+	}
+
+	let reference = entity
+		.references
+		.iter()
+		.find(|reference| error.message().contains(&reference.assertion()))?;
+
+	let field = entity
+		.properties
+		.iter()
+		.find(|property| property.name == reference.property)
+		.map(|property| property.field_name())
+		.unwrap_or(&reference.property);
+
+	Some(ReferenceNotFoundError::new(
+		field.to_string(),
+		reference.collection.clone(),
+	))
+}
+
+/// The check a failed write does not satisfy
+fn check_violation(entity: &DbEntity, error: &BackendError) -> Option<CheckConstraintViolation> {
+	if error.error_num() != Some(ERROR_QUERY_USER_ASSERT) {
+		return None;
+	}
+
+	entity
+		.checks
+		.iter()
+		.find(|check| error.message().contains(&check.assertion()))
+		.map(|check| CheckConstraintViolation::new(check.name.clone(), check.get_message()))
+}
+
+/// Whether the document passes the filter
+async fn is_visible(
+	collection: &str,
+	query: &AQLQuery<'_>,
+	filter: &dyn AQLNode,
+	id: &str,
+) -> Result<bool, BackendError> {
+	let query_str = format!(
+		"FOR {variable} IN @@collection FILTER {variable}._key == @key && {filter} LIMIT 1 RETURN true",
+		variable = query.get_variable_name(),
+		filter = filter.describe(query.id)
+	);
+
+	let found: Vec<bool> = run_query(with_tenant(
+		BackendQuery::new(&query_str)
+			.bind_var("@collection", collection)
+			.bind_var("key", id),
+		query.tenant.as_deref(),
+	))
+	.instrument(aql_span(collection, &query_str))
+	.await?;
+
+	Ok(!found.is_empty())
+}
+
+pub struct Create;
+
+impl<S> Operation<S> for Create
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let input = arguments.get::<EntityInput>("data").unwrap();
+
+		if let Err(e) = validate_input(&data.entity, &input) {
+			return Box::pin(async move { Err(e.into_field_error()) });
+		}
+
+		let document = input.into_document(&data.entity);
+
+		query.mutation = Some(AQLMutation::Insert);
+		query.history = data.entity.audit;
+		query.timestamps = data.entity.timestamps;
+		query.references = data.entity.references.clone();
+		query.checks = data.entity.checks.clone();
+
+		execute_mutation(data, context, query, Some(document), None)
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("create", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![registry.arg::<EntityInput>("data", &EntityInputInfo::new(&data.entity, false))]
+	}
+
+	fn get_kind() -> OperationKind {
+		OperationKind::Mutation
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Create
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Entity>>(name, &data)
+	}
+}
+
+pub struct Update;
+
+impl<S> Operation<S> for Update
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let input = arguments.get::<EntityInput>("data").unwrap();
+
+		if let Err(e) = validate_input(&data.entity, &input) {
+			return Box::pin(async move { Err(e.into_field_error()) });
+		}
+
+		let document = input.into_document(&data.entity);
+
+		query.mutation = Some(AQLMutation::Update);
+		query.history = data.entity.audit;
+		query.versions = data.entity.versioning;
+		query.timestamps = data.entity.timestamps;
+		query.references = data.entity.references.clone();
+		query.checks = data.entity.checks.clone();
+
+		execute_mutation(
+			data,
+			context,
+			query,
+			Some(document),
+			arguments.get::<String>("id"),
+		)
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("update", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![
+			registry.arg::<ID>("id", &()),
+			registry.arg::<EntityInput>("data", &EntityInputInfo::new(&data.entity, true)),
+		]
+	}
+
+	fn get_kind() -> OperationKind {
+		OperationKind::Mutation
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Update
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Entity>>(name, &data)
+	}
+}
+
+pub struct Delete;
+
+impl<S> Operation<S> for Delete
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		query.mutation = Some(AQLMutation::Remove);
+		query.history = data.entity.audit;
+		query.versions = data.entity.versioning;
+
+		execute_mutation(data, context, query, None, arguments.get::<String>("id"))
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("delete", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		_data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![registry.arg::<ID>("id", &())]
+	}
+
+	fn get_kind() -> OperationKind {
+		OperationKind::Mutation
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Delete
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Entity>>(name, &data)
+	}
+}
+
+pub struct History;
+
+impl<S> Operation<S> for History
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		_context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let time = std::time::Instant::now();
+
+		let entity = &data.entity;
+		let collection = get_history_collection_name(&entity.collection_name);
+
+		query.filter = Some(Box::new(AQLFilter {
+			left_node: Box::new(AQLQueryParameter("documentKey".to_string())),
+			operation: AQLOperation::EQUAL,
+			right_node: Box::new(AQLQueryBind("id")),
+		}));
+		query.sort = vec![AQLSort {
+			property: "timestamp".to_string(),
+			numeric: false,
+			descending: true,
+			nulls_first: None,
+		}];
+
+		Box::pin(async move {
+			let query_str = query.to_aql();
+
+			tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
+
+			let entries_query = BackendQuery::new(&query_str)
+				.bind_var("@collection", collection.clone())
+				.bind_var(
+					query.get_argument_key("id"),
+					arguments.get::<String>("id").unwrap(),
+				);
+
+			let entries =
+				run_query::<JsonValue>(with_tenant(entries_query, query.tenant.as_deref()))
+					.instrument(aql_span(&collection, &query_str))
+					.await;
+
+			tracing::info!(operation = "history", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+			return match entries {
+				Ok(data) => {
+					let mut output = Vec::<Value<S>>::new();
+
+					for mut datum in data {
+						let datum = datum.as_object_mut().unwrap();
+
+						// The diff is exposed as a JSON string like any other object property
+						if let Some(diff) = datum.get_mut("diff") {
+							*diff = JsonValue::String(diff.to_string());
+						}
+
+						output.push(convert_json_to_juniper_value(datum));
+					}
+
+					Ok(Value::list(output))
+				}
+				Err(e) => {
+					tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+					Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
+				}
+			};
+		})
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("history", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		_data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![registry.arg::<ID>("id", &())]
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::History
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		_data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Vec<HistoryEntry>>>(name, &())
+	}
+}
+
+/// The previous states of a document of a versioned collection, the latest first
+pub struct Versions;
+
+impl<S> Operation<S> for Versions
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		_context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let time = std::time::Instant::now();
+
+		let entity = &data.entity;
+		let collection = get_versions_collection_name(&entity.collection_name);
+
+		query.filter = Some(Box::new(AQLFilter {
+			left_node: Box::new(AQLQueryParameter("documentKey".to_string())),
+			operation: AQLOperation::EQUAL,
+			right_node: Box::new(AQLQueryBind("id")),
+		}));
+		query.sort = vec![AQLSort {
+			property: "version".to_string(),
+			numeric: false,
+			descending: true,
+			nulls_first: None,
+		}];
+
+		Box::pin(async move {
+			let query_str = query.to_aql();
+
+			tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
+
+			let entries_query = BackendQuery::new(&query_str)
+				.bind_var("@collection", collection.clone())
+				.bind_var(
+					query.get_argument_key("id"),
+					arguments.get::<String>("id").unwrap(),
+				);
+
+			let entries =
+				run_query::<JsonValue>(with_tenant(entries_query, query.tenant.as_deref()))
+					.instrument(aql_span(&collection, &query_str))
+					.await;
+
+			tracing::info!(operation = "versions", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+			return match entries {
+				Ok(data) => {
+					let mut output = Vec::<Value<S>>::new();
+
+					for mut datum in data {
+						let datum = datum.as_object_mut().unwrap();
+
+						// The document is exposed as a JSON string like any other object property
+						if let Some(document) = datum.get_mut("document") {
+							*document = JsonValue::String(document.to_string());
+						}
+
+						output.push(convert_json_to_juniper_value(datum));
+					}
+
+					Ok(Value::list(output))
+				}
+				Err(e) => {
+					tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+					Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
+				}
+			};
+		})
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("versions", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		_data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![registry.arg::<ID>("id", &())]
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Versions
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		_data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Vec<VersionEntry>>>(name, &())
+	}
+}
+
+/// A document of a versioned collection as it was at a given time, null if it did not exist
+pub struct AsOf;
+
+impl<S> Operation<S> for AsOf
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		arguments: &'b Arguments<S>,
+		_context: &'b Context,
+		mut query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		let time = std::time::Instant::now();
+
+		let entity = &data.entity;
+		let collection = &entity.collection_name;
+
+		// The times are compared as strings, in the format of the stored ones
+		let at = match arguments
+			.get::<DateTime>("at")
+			.and_then(|at| chrono::DateTime::parse_from_rfc3339(&at.0).ok())
+		{
+			Some(at) => at
+				.with_timezone(&Utc)
+				.to_rfc3339_opts(SecondsFormat::Millis, true),
+			None => {
+				return Box::pin(async move {
+					Err(InvalidDateTimeError::new(String::from("at")).into_field_error())
+				})
+			}
+		};
+
+		query.as_of = true;
+		query.limit = Some(1);
+
+		Box::pin(async move {
+			let query_str = query.to_aql();
+
+			tracing::debug!(collection = %collection, query = %redact_aql(&query_str), "Executing AQL query");
+
+			let entries_query = BackendQuery::new(&query_str)
+				.bind_var("@collection", collection.clone())
+				.bind_var("@versions", get_versions_collection_name(collection))
+				.bind_var(
+					query.get_argument_key("id"),
+					arguments.get::<String>("id").unwrap(),
+				)
+				.bind_var(query.get_argument_key("at"), at);
+
+			let entries = fetch_documents(with_tenant(entries_query, query.tenant.as_deref()))
+				.instrument(aql_span(collection, &query_str))
+				.await;
+
+			tracing::info!(operation = "asOf", entity = %entity.name, collection = %collection, duration_ms = time.elapsed().as_millis() as u64, "AQL query executed");
+
+			return match entries {
+				Ok(data) => Ok(data.into_iter().next().unwrap_or_else(Value::null)),
+				Err(e) => {
+					tracing::error!(collection = %collection, error = ?e, "AQL query failed");
+
+					Err(DatabaseError::new(entity.name.clone(), e).into_field_error())
+				}
+			};
+		})
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("asOf", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		_data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![
+			registry.arg::<ID>("id", &()),
+			registry.arg::<DateTime>("at", &()),
+		]
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::AsOf
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Entity>>(name, &data)
+	}
+}
+
+/// Subscription to the changes of an entity, resolved with `fetch_document` for every change
+pub struct Changed;
+
+impl<S> Operation<S> for Changed
+where
+	S: ScalarValue + Send + Sync,
+{
+	fn call<'b>(
+		data: &'b OperationData<S>,
+		_arguments: &'b Arguments<S>,
+		context: &'b Context,
+		query: AQLQuery<'b>,
+	) -> FutureType<'b, S> {
+		fetch_document(data, context, query)
+	}
+
+	fn get_operation_name(data: &OperationData<S>) -> String {
+		operation_name("changed", &data.entity, None)
+	}
+
+	fn get_arguments<'r>(
+		registry: &mut Registry<'r, S>,
+		_data: &OperationData<S>,
+	) -> Vec<Argument<'r, S>> {
+		vec![registry.arg::<Option<ID>>("id", &())]
+	}
+
+	fn get_kind() -> OperationKind {
+		OperationKind::Subscription
+	}
+
+	fn get_schema_operation() -> SchemaOperation {
+		SchemaOperation::Changed
+	}
+
+	fn build_field<'r>(
+		registry: &mut Registry<'r, S>,
+		name: &str,
+		data: &OperationData<S>,
+	) -> Field<'r, S> {
+		registry.field::<Option<Entity>>(name, &data)
 	}
 }
@@ -0,0 +1,148 @@
+use juniper::meta::{Argument, MetaType};
+use juniper::{FromInputValue, GraphQLType, GraphQLValue, InputValue, Registry, ScalarValue};
+use serde_json::Value as JsonValue;
+
+use crate::api::schema::enums::{DbEnumInfo, GraphQLEnum};
+use crate::api::schema::inputs::convert_input_to_json;
+use crate::lib::database::api::{DbEntity, DbScalarType};
+use crate::lib::database::aql::AQLSort;
+
+#[derive(GraphQLEnum, Clone, Copy, PartialEq, Debug)]
+pub enum OrderDirection {
+	Asc,
+	Desc,
+}
+
+/// Where the documents without a value go, by default first when ascending and last when
+/// descending, as AQL sorts null below every value
+#[derive(GraphQLEnum, Clone, Copy, PartialEq, Debug)]
+pub enum NullsOrder {
+	First,
+	Last,
+}
+
+/// The properties a list can be sorted by, objects and lists have no meaningful order
+fn is_sortable(scalar_type: &DbScalarType) -> bool {
+	matches!(
+		scalar_type,
+		DbScalarType::String
+			| DbScalarType::Enum(_)
+			| DbScalarType::Float
+			| DbScalarType::Int
+			| DbScalarType::Boolean
+			| DbScalarType::DateTime
+			| DbScalarType::Decimal
+	)
+}
+
+pub struct OrderByInfo {
+	pub name: String,
+	pub fields: DbEnumInfo,
+}
+
+impl OrderByInfo {
+	/// `None` when the entity has no property to sort by
+	pub fn new(entity: &DbEntity) -> Option<Self> {
+		let fields: Vec<String> = entity
+			.properties
+			.iter()
+			.filter(|property| is_sortable(&property.scalar_type))
+			.map(|property| property.field_name().to_string())
+			.collect();
+
+		if fields.is_empty() {
+			return None;
+		}
+
+		Some(OrderByInfo {
+			name: format!("{}OrderBy", entity.name),
+			fields: DbEnumInfo {
+				name: format!("{}OrderField", entity.name),
+				properties: fields,
+			},
+		})
+	}
+}
+
+/// A key of the sort of a list, the keys of `orderBy` are applied in order
+pub struct EntityOrderBy {
+	pub field: String,
+	pub direction: OrderDirection,
+	pub nulls: Option<NullsOrder>,
+}
+
+impl EntityOrderBy {
+	/// The sort of the property of the field, `None` when the entity has no such field
+	pub fn into_sort(self, entity: &DbEntity) -> Option<AQLSort> {
+		let property = entity.property_by_field(&self.field)?;
+
+		Some(AQLSort {
+			property: property.name.clone(),
+			numeric: property.scalar_type == DbScalarType::Decimal,
+			descending: self.direction == OrderDirection::Desc,
+			nulls_first: self.nulls.map(|nulls| nulls == NullsOrder::First),
+		})
+	}
+}
+
+impl<S> GraphQLType<S> for EntityOrderBy
+where
+	S: ScalarValue,
+{
+	fn name(info: &Self::TypeInfo) -> Option<&str> {
+		Some(info.name.as_str())
+	}
+
+	fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
+	where
+		S: 'r,
+	{
+		let arguments: [Argument<'r, S>; 3] = [
+			registry.arg::<GraphQLEnum>("field", &info.fields),
+			registry.arg::<Option<OrderDirection>>("direction", &()),
+			registry.arg::<Option<NullsOrder>>("nulls", &()),
+		];
+
+		registry
+			.build_input_object_type::<EntityOrderBy>(info, &arguments)
+			.into_meta()
+	}
+}
+
+impl<S> GraphQLValue<S> for EntityOrderBy
+where
+	S: ScalarValue,
+{
+	type Context = ();
+	type TypeInfo = OrderByInfo;
+
+	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+		<Self as GraphQLType<S>>::name(info)
+	}
+}
+
+impl<S> FromInputValue<S> for EntityOrderBy
+where
+	S: ScalarValue,
+{
+	fn from_input_value(v: &InputValue<S>) -> Option<Self> {
+		let input = convert_input_to_json(v)?;
+
+		let direction = match input["direction"].as_str() {
+			Some("DESC") => OrderDirection::Desc,
+			_ => OrderDirection::Asc,
+		};
+
+		let nulls = match input["nulls"] {
+			JsonValue::String(ref nulls) if nulls == "FIRST" => Some(NullsOrder::First),
+			JsonValue::String(ref nulls) if nulls == "LAST" => Some(NullsOrder::Last),
+			_ => None,
+		};
+
+		Some(EntityOrderBy {
+			field: input["field"].as_str()?.to_string(),
+			direction,
+			nulls,
+		})
+	}
+}
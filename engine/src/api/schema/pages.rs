@@ -0,0 +1,238 @@
+use juniper::meta::MetaType;
+use juniper::{
+	GraphQLType, GraphQLValue, Object, Registry, ScalarValue, Selection, Spanning, Value,
+};
+
+use crate::api::schema::context::Context;
+use crate::api::schema::fields::Entity;
+use crate::api::schema::operations::OperationData;
+use crate::lib::schema::SchemaOperation;
+use crate::lib::CONFIG;
+
+/// Prefixes of the decoded cursors, so that arbitrary strings are not mistaken for positions
+const OFFSET_PREFIX: &str = "offset:";
+const KEY_PREFIX: &str = "key:";
+
+/// Name under which the key of the items is projected, for their cursors
+pub const CURSOR_PROPERTY: &str = "__cursor";
+
+/// Where a page starts, after a key for the lists in their default order by key, after an
+/// offset for the sorted ones
+#[derive(Clone, PartialEq, Debug)]
+pub enum Cursor {
+	Offset(u64),
+	Key(String),
+}
+
+/// Where a page starts and whether other pages surround it
+#[derive(GraphQLObject)]
+pub struct PageInfo {
+	pub has_next_page: bool,
+	pub has_previous_page: bool,
+	pub start_cursor: Option<String>,
+	pub end_cursor: Option<String>,
+}
+
+/// Whether the results of the operation are returned as pages rather than plain lists
+pub fn is_paginated(operation: SchemaOperation) -> bool {
+	CONFIG.graphql_page_info && operation == SchemaOperation::GetAll
+}
+
+/// The opaque cursor of an item
+pub fn encode_cursor(cursor: &Cursor) -> String {
+	base64::encode(match cursor {
+		Cursor::Offset(offset) => format!("{}{}", OFFSET_PREFIX, offset),
+		Cursor::Key(key) => format!("{}{}", KEY_PREFIX, key),
+	})
+}
+
+pub fn decode_cursor(cursor: &str) -> Option<Cursor> {
+	let decoded = String::from_utf8(base64::decode(cursor).ok()?).ok()?;
+
+	if let Some(key) = decoded.strip_prefix(KEY_PREFIX) {
+		return Some(Cursor::Key(key.to_string()));
+	}
+
+	decoded
+		.strip_prefix(OFFSET_PREFIX)?
+		.parse()
+		.ok()
+		.map(Cursor::Offset)
+}
+
+/// The cursor of the item at a position of the page, taken out of the projected item when the
+/// list is paginated by key
+fn take_cursor<S>(item: &mut Value<S>, offset: Option<u64>, position: usize) -> Option<String>
+where
+	S: ScalarValue,
+{
+	if let Some(offset) = offset {
+		return Some(encode_cursor(&Cursor::Offset(offset + position as u64)));
+	}
+
+	let object = match item {
+		Value::Object(object) => object,
+		_ => return None,
+	};
+
+	let key = object
+		.get_field_value(CURSOR_PROPERTY)
+		.and_then(|key| key.as_string_value())
+		.map(String::from);
+
+	*object = std::mem::replace(object, Object::with_capacity(0))
+		.into_iter()
+		.filter(|(name, _)| name != CURSOR_PROPERTY)
+		.collect();
+
+	key.map(|key| encode_cursor(&Cursor::Key(key)))
+}
+
+/// The page of the items read after a key, or from `offset` for the sorted lists, one more
+/// item than the limit is read to know whether a next page exists
+pub fn build_page<S>(
+	mut items: Vec<Value<S>>,
+	offset: Option<u64>,
+	has_previous_page: bool,
+	limit: Option<i32>,
+) -> Value<S>
+where
+	S: ScalarValue,
+{
+	let has_next_page = match limit {
+		Some(limit) if items.len() > limit.max(0) as usize => {
+			items.truncate(limit.max(0) as usize);
+			true
+		}
+		_ => false,
+	};
+
+	let cursors: Vec<Option<String>> = items
+		.iter_mut()
+		.enumerate()
+		.map(|(position, item)| take_cursor(item, offset, position))
+		.collect();
+
+	let cursor = |cursor: Option<&Option<String>>| match cursor.cloned().flatten() {
+		Some(cursor) => Value::scalar(cursor),
+		None => Value::null(),
+	};
+
+	let mut page_info = Object::with_capacity(4);
+	page_info.add_field("hasNextPage", Value::scalar(has_next_page));
+	page_info.add_field("hasPreviousPage", Value::scalar(has_previous_page));
+	page_info.add_field("startCursor", cursor(cursors.first()));
+	page_info.add_field("endCursor", cursor(cursors.last()));
+
+	let mut page = Object::with_capacity(2);
+	page.add_field("items", Value::list(items));
+	page.add_field("pageInfo", Value::Object(page_info));
+
+	Value::Object(page)
+}
+
+/// The selection of the items of a page, the documents are read for it
+pub fn items_selection<'a, S>(selection_set: &'a [Selection<'a, S>]) -> &'a [Selection<'a, S>]
+where
+	S: ScalarValue,
+{
+	selection_set
+		.iter()
+		.find_map(|selection| match selection {
+			Selection::Field(Spanning { item: f, .. }) if f.name.item == "items" => {
+				f.selection_set.as_deref()
+			}
+			_ => None,
+		})
+		.unwrap_or(&[])
+}
+
+/// Keep the selected fields of an object, under their aliases
+fn select_fields<S>(
+	value: &Value<S>,
+	selection_set: &[Selection<S>],
+	type_name: &str,
+	nested: &dyn Fn(&str, &Value<S>, &[Selection<S>]) -> Value<S>,
+) -> Value<S>
+where
+	S: ScalarValue,
+{
+	let mut output = Object::with_capacity(selection_set.len());
+
+	for selection in selection_set {
+		if let Selection::Field(Spanning { item: f, .. }) = selection {
+			let response_name = f.alias.as_ref().unwrap_or(&f.name).item;
+
+			let field = match f.name.item {
+				"__typename" => Value::scalar(type_name.to_string()),
+				name => match value
+					.as_object_value()
+					.and_then(|o| o.get_field_value(name))
+				{
+					Some(field) => nested(name, field, f.selection_set.as_deref().unwrap_or(&[])),
+					None => Value::null(),
+				},
+			};
+
+			output.add_field(response_name, field);
+		}
+	}
+
+	Value::Object(output)
+}
+
+/// Shape a page after the selection of the request, the items already are
+pub fn select_page<S>(page: Value<S>, selection_set: &[Selection<S>], type_name: &str) -> Value<S>
+where
+	S: ScalarValue,
+{
+	select_fields(
+		&page,
+		selection_set,
+		type_name,
+		&|name, field, selection_set| match name {
+			"pageInfo" => select_fields(field, selection_set, "PageInfo", &|_, value, _| {
+				value.clone()
+			}),
+			_ => field.clone(),
+		},
+	)
+}
+
+/// Page of the results of a list operation, with its items and `pageInfo`
+pub struct EntityPage;
+
+impl<S> GraphQLType<S> for EntityPage
+where
+	S: ScalarValue,
+{
+	fn name(info: &Self::TypeInfo) -> Option<&str> {
+		Some(info.page_name.as_str())
+	}
+
+	fn meta<'r>(info: &Self::TypeInfo, registry: &mut Registry<'r, S>) -> MetaType<'r, S>
+	where
+		S: 'r,
+	{
+		let fields = [
+			registry.field::<Vec<Entity>>("items", info),
+			registry.field::<PageInfo>("pageInfo", &()),
+		];
+
+		registry
+			.build_object_type::<EntityPage>(info, &fields)
+			.into_meta()
+	}
+}
+
+impl<S> GraphQLValue<S> for EntityPage
+where
+	S: ScalarValue,
+{
+	type Context = Context;
+	type TypeInfo = OperationData<S>;
+
+	fn type_name<'i>(&self, info: &'i Self::TypeInfo) -> Option<&'i str> {
+		<Self as GraphQLType<S>>::name(info)
+	}
+}
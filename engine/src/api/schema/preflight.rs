@@ -0,0 +1,130 @@
+use std::borrow::Cow;
+
+use juniper::meta::Argument;
+use juniper::{Arguments, DefaultScalarValue, InputValue, Type};
+
+use crate::api::schema::context::Context;
+use crate::api::schema::fields::related_entity;
+use crate::api::schema::operations::OperationRegistry;
+use crate::lib::database::api::{DbEntity, DbRelationship, DbScalarType};
+use crate::lib::database::aql::{AQLProperty, AQLQuery, AQLQueryRelationship};
+use crate::lib::database::backend::explain_queries;
+use crate::lib::redaction::redact_aql;
+
+/// A generated operation whose query the database rejected
+#[derive(Debug)]
+pub struct PreflightFailure {
+	pub operation: String,
+	pub collection: String,
+	/// The rejected query, its sensitive values redacted
+	pub query: String,
+	pub error: String,
+}
+
+/// The arguments the operations read, the queries are only explained so the values merely
+/// have to be of the right type, they are passed as the defaults of the arguments
+fn placeholder_arguments<'a>() -> Option<Vec<Argument<'a, DefaultScalarValue>>> {
+	let argument = |name: &str, value: InputValue| {
+		Argument::new(name, Type::Named(Cow::Borrowed("String"))).default_value(value)
+	};
+
+	Some(vec![
+		argument("id", InputValue::scalar(String::from("preflight"))),
+		argument(
+			"at",
+			InputValue::scalar(String::from("1970-01-01T00:00:00Z")),
+		),
+		argument("data", InputValue::Object(Vec::new())),
+	])
+}
+
+fn project_properties(entity: &DbEntity, query: &mut AQLQuery) {
+	for property in &entity.properties {
+		query.properties.push(AQLProperty {
+			name: property.field_name().to_string(),
+			attribute: property.name.clone(),
+			masked: false,
+			locales: (property.scalar_type == DbScalarType::Localized).then(Vec::new),
+		});
+	}
+}
+
+/// The query of an operation selecting every property of its entity, and every property of
+/// the entities of the relationships it owns
+fn full_query<'a>(entity: &DbEntity, relationships: &[DbRelationship]) -> AQLQuery<'a> {
+	let mut query = AQLQuery::new(1);
+
+	project_properties(entity, &mut query);
+
+	for relationship in relationships {
+		let mut inner_query = AQLQuery::new(query.id + 1);
+
+		project_properties(related_entity(relationship, &entity.name), &mut inner_query);
+
+		inner_query.relationship = Some(AQLQueryRelationship {
+			edge: relationship.edge.clone(),
+			variable_name: query.get_variable_name(),
+			direction: relationship.direction.clone(),
+			graph: relationship.graph.clone(),
+		});
+
+		query
+			.relations
+			.insert(relationship.name.clone(), inner_query);
+	}
+
+	query
+}
+
+/// Render the queries of every registered operation and have the database explain them, the
+/// mutations are explained too, nothing is written
+pub async fn preflight_operations(
+	registry: &OperationRegistry<DefaultScalarValue>,
+) -> Vec<PreflightFailure> {
+	let meta_arguments = placeholder_arguments();
+	let context = Context::new(None);
+
+	let mut names: Vec<&String> = registry.get_operations().keys().collect();
+	names.sort();
+
+	let mut failures = Vec::new();
+
+	for name in names {
+		let entry = &registry.get_operations()[name];
+		let arguments = Arguments::new(None, &meta_arguments);
+		let query = full_query(&entry.data.entity, &entry.data.relationships);
+
+		let closure = entry.closure;
+		let explained = explain_queries(closure(&entry.data, &arguments, &context, query)).await;
+
+		for explained in explained {
+			if let Some(error) = explained.error {
+				failures.push(PreflightFailure {
+					operation: name.clone(),
+					collection: entry.data.entity.collection_name.clone(),
+					query: redact_aql(&explained.query.query),
+					error: error.to_string(),
+				});
+			}
+		}
+	}
+
+	for failure in &failures {
+		tracing::error!(
+			operation = %failure.operation,
+			collection = %failure.collection,
+			query = %failure.query,
+			error = %failure.error,
+			"Generated operation is invalid"
+		);
+	}
+
+	if failures.is_empty() {
+		tracing::info!(
+			operations = registry.get_operations().len(),
+			"Generated operations checked"
+		);
+	}
+
+	failures
+}
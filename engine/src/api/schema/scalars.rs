@@ -0,0 +1,76 @@
+use juniper::{ParseScalarResult, ParseScalarValue, ScalarToken, Value};
+
+use crate::api::uploads::get_upload;
+use crate::lib::schema::DecimalValue;
+use crate::lib::uploads::UploadedFile;
+
+/// An ISO 8601 date and time, stored as a string in ArangoDB
+pub struct DateTime(pub String);
+
+#[juniper::graphql_scalar(description = "An ISO 8601 encoded date and time")]
+impl<S> GraphQLScalar for DateTime
+where
+	S: ScalarValue,
+{
+	fn resolve(&self) -> Value {
+		Value::scalar(self.0.clone())
+	}
+
+	fn from_input_value(value: &InputValue) -> Option<DateTime> {
+		value.as_string_value().map(|s| DateTime(s.to_string()))
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		<String as ParseScalarValue<S>>::from_str(value)
+	}
+}
+
+/// An exact decimal number, stored as a string in ArangoDB so that no digit is lost
+pub struct Decimal(pub String);
+
+#[juniper::graphql_scalar(description = "An exact decimal number, encoded as a string")]
+impl<S> GraphQLScalar for Decimal
+where
+	S: ScalarValue,
+{
+	fn resolve(&self) -> Value {
+		Value::scalar(self.0.clone())
+	}
+
+	// Integers are accepted too, floats are not as they may have lost digits already
+	fn from_input_value(value: &InputValue) -> Option<Decimal> {
+		match value.as_int_value() {
+			Some(value) => Some(Decimal(value.to_string())),
+			None => value
+				.as_string_value()
+				.filter(|value| DecimalValue::parse(value).is_some())
+				.map(|value| Decimal(value.to_string())),
+		}
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		<String as ParseScalarValue<S>>::from_str(value)
+	}
+}
+
+/// A file sent with a GraphQL multipart request
+pub struct Upload(pub UploadedFile);
+
+#[juniper::graphql_scalar(description = "A file sent with a GraphQL multipart request")]
+impl<S> GraphQLScalar for Upload
+where
+	S: ScalarValue,
+{
+	fn resolve(&self) -> Value {
+		Value::scalar(self.0.id.clone())
+	}
+
+	// Only the IDs of the files stored for the current request are accepted
+	fn from_input_value(value: &InputValue) -> Option<Upload> {
+		value.as_string_value().and_then(get_upload).map(Upload)
+	}
+
+	fn from_str<'a>(value: ScalarToken<'a>) -> ParseScalarResult<'a, S> {
+		<String as ParseScalarValue<S>>::from_str(value)
+	}
+}
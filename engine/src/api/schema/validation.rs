@@ -0,0 +1,390 @@
+use juniper::{FieldError, IntoFieldError, ScalarValue, Value};
+use regex::Regex;
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::api::schema::errors::{error_extensions, ErrorCode};
+use crate::api::schema::geo::is_valid_geometry;
+use crate::api::schema::inputs::EntityInput;
+use crate::api::schema::localized::is_valid_locale;
+use crate::lib::database::api::{DbEntity, DbProperty, DbScalarType};
+use crate::lib::schema::DecimalValue;
+
+/// Name of the argument holding the input of the mutations, the root of the error paths
+const INPUT_ARGUMENT: &str = "data";
+
+/// The bound or expression a value was checked against
+pub enum ValidationConstraint {
+	Length(i32),
+	Number(f64),
+	Text(String),
+}
+
+/// A field of a mutation input breaking a rule of its property
+pub struct ValidationError {
+	pub field: String,
+	/// Path of the offending value in the arguments, e.g. `data.tags[2]`
+	pub path: String,
+	pub rule: &'static str,
+	pub constraint: ValidationConstraint,
+	pub message: String,
+}
+
+impl ValidationError {
+	fn new(
+		property: &DbProperty,
+		index: Option<usize>,
+		rule: &'static str,
+		constraint: ValidationConstraint,
+		message: String,
+	) -> Self {
+		let field = property.field_name().to_string();
+
+		let path = match index {
+			Some(index) => format!("{}.{}[{}]", INPUT_ARGUMENT, field, index),
+			None => format!("{}.{}", INPUT_ARGUMENT, field),
+		};
+
+		ValidationError {
+			field,
+			path,
+			rule,
+			constraint,
+			message,
+		}
+	}
+}
+
+impl<S: ScalarValue> IntoFieldError<S> for ValidationError {
+	fn into_field_error(self) -> FieldError<S> {
+		let mut extensions = error_extensions::<S>(ErrorCode::ValidationFailed);
+
+		let constraint = match self.constraint {
+			ValidationConstraint::Length(length) => Value::scalar(length),
+			ValidationConstraint::Number(number) => Value::scalar(number),
+			ValidationConstraint::Text(text) => Value::scalar(text),
+		};
+
+		extensions.add_field("field", Value::scalar(self.field));
+		extensions.add_field("path", Value::scalar(self.path));
+		extensions.add_field("rule", Value::scalar(self.rule.to_string()));
+		extensions.add_field("constraint", constraint);
+
+		FieldError::new(self.message, Value::Object(extensions))
+	}
+}
+
+fn validate_value(
+	property: &DbProperty,
+	index: Option<usize>,
+	value: &JsonValue,
+) -> Result<(), ValidationError> {
+	let rules = &property.rules;
+	let field = property.field_name();
+
+	if property.scalar_type == DbScalarType::Decimal {
+		return validate_decimal(property, index, value);
+	}
+
+	if property.scalar_type == DbScalarType::Localized {
+		return validate_translations(property, value);
+	}
+
+	match value {
+		JsonValue::Object(geometry) => {
+			if let Some(geometry_type) = property.scalar_type.geometry_type() {
+				let coordinates = geometry.get("coordinates").unwrap_or(&JsonValue::Null);
+
+				if !is_valid_geometry(&property.scalar_type, coordinates) {
+					return Err(ValidationError::new(
+						property,
+						index,
+						"geometry",
+						ValidationConstraint::Text(geometry_type.to_string()),
+						format!("{} must be a valid GeoJSON {}", field, geometry_type),
+					));
+				}
+			}
+		}
+		JsonValue::String(s) => {
+			let length = s.chars().count() as i32;
+
+			if let Some(min) = rules.min_length.filter(|min| length < *min) {
+				return Err(ValidationError::new(
+					property,
+					index,
+					"minLength",
+					ValidationConstraint::Length(min),
+					format!("{} must be at least {} characters long", field, min),
+				));
+			}
+
+			if let Some(max) = rules.max_length.filter(|max| length > *max) {
+				return Err(ValidationError::new(
+					property,
+					index,
+					"maxLength",
+					ValidationConstraint::Length(max),
+					format!("{} must be at most {} characters long", field, max),
+				));
+			}
+
+			// The patterns are checked when they are set, an invalid one is never stored
+			if let Some(ref pattern) = rules.pattern {
+				if Regex::new(pattern)
+					.map(|re| !re.is_match(s))
+					.unwrap_or(false)
+				{
+					return Err(ValidationError::new(
+						property,
+						index,
+						"pattern",
+						ValidationConstraint::Text(pattern.clone()),
+						format!("{} must match the pattern {}", field, pattern),
+					));
+				}
+			}
+		}
+		JsonValue::Number(n) => {
+			let n = n.as_f64().unwrap_or_default();
+
+			if let Some(min) = rules.minimum {
+				if rules.exclusive_minimum && n <= min {
+					return Err(ValidationError::new(
+						property,
+						index,
+						"exclusiveMinimum",
+						ValidationConstraint::Number(min),
+						format!("{} must be greater than {}", field, min),
+					));
+				}
+
+				if n < min {
+					return Err(ValidationError::new(
+						property,
+						index,
+						"minimum",
+						ValidationConstraint::Number(min),
+						format!("{} must be at least {}", field, min),
+					));
+				}
+			}
+
+			if let Some(max) = rules.maximum {
+				if rules.exclusive_maximum && n >= max {
+					return Err(ValidationError::new(
+						property,
+						index,
+						"exclusiveMaximum",
+						ValidationConstraint::Number(max),
+						format!("{} must be less than {}", field, max),
+					));
+				}
+
+				if n > max {
+					return Err(ValidationError::new(
+						property,
+						index,
+						"maximum",
+						ValidationConstraint::Number(max),
+						format!("{} must be at most {}", field, max),
+					));
+				}
+			}
+
+			if let Some(multiple_of) = rules.multiple_of {
+				let quotient = n / multiple_of;
+
+				// Tolerates the rounding errors of decimal multiples, e.g. 0.3 of 0.1
+				if (quotient - quotient.round()).abs() > 1e-9 {
+					return Err(ValidationError::new(
+						property,
+						index,
+						"multipleOf",
+						ValidationConstraint::Number(multiple_of),
+						format!("{} must be a multiple of {}", field, multiple_of),
+					));
+				}
+			}
+		}
+		JsonValue::Array(items) => {
+			if let Some(max) = rules.item_maximum {
+				// The first offending item is reported
+				if let Some(position) = items
+					.iter()
+					.position(|item| item.as_f64().map(|v| v > max).unwrap_or(false))
+				{
+					return Err(ValidationError::new(
+						property,
+						Some(position),
+						"maximum",
+						ValidationConstraint::Number(max),
+						format!("The items of {} must be at most {}", field, max),
+					));
+				}
+			}
+
+			if let Some(ref pattern) = rules.item_pattern {
+				if let Ok(re) = Regex::new(pattern) {
+					if let Some(position) = items
+						.iter()
+						.position(|item| item.as_str().map(|s| !re.is_match(s)).unwrap_or(false))
+					{
+						return Err(ValidationError::new(
+							property,
+							Some(position),
+							"pattern",
+							ValidationConstraint::Text(pattern.clone()),
+							format!("The items of {} must match the pattern {}", field, pattern),
+						));
+					}
+				}
+			}
+		}
+		_ => {}
+	}
+
+	Ok(())
+}
+
+/// Check the digits of a decimal against the precision and the scale of its property
+fn validate_decimal(
+	property: &DbProperty,
+	index: Option<usize>,
+	value: &JsonValue,
+) -> Result<(), ValidationError> {
+	let rules = &property.rules;
+	let field = property.field_name();
+
+	let text = match value {
+		JsonValue::String(text) => text.clone(),
+		JsonValue::Number(number) => number.to_string(),
+		_ => return Ok(()),
+	};
+
+	let decimal = match DecimalValue::parse(&text) {
+		Some(decimal) => decimal,
+		None => {
+			return Err(ValidationError::new(
+				property,
+				index,
+				"decimal",
+				ValidationConstraint::Text(text.clone()),
+				format!("{} must be a decimal number", field),
+			))
+		}
+	};
+
+	if let Some(scale) = rules
+		.decimal_scale()
+		.filter(|scale| decimal.scale() > *scale)
+	{
+		return Err(ValidationError::new(
+			property,
+			index,
+			"scale",
+			ValidationConstraint::Length(scale as i32),
+			format!(
+				"{} must have at most {} digits after the point",
+				field, scale
+			),
+		));
+	}
+
+	if let Some(precision) = rules.precision {
+		// The digits of the scale are always stored, they count towards the precision
+		let integer_digits = precision - rules.decimal_scale().unwrap_or(0) as i32;
+
+		if decimal.integer.len() as i32 > integer_digits {
+			return Err(ValidationError::new(
+				property,
+				index,
+				"precision",
+				ValidationConstraint::Length(precision),
+				format!(
+					"{} must have at most {} digits before the point",
+					field,
+					integer_digits.max(0)
+				),
+			));
+		}
+	}
+
+	Ok(())
+}
+
+/// Check the locales of the translations of a localized field, they are written in the queries
+fn validate_translations(property: &DbProperty, value: &JsonValue) -> Result<(), ValidationError> {
+	let translations = match value {
+		JsonValue::Array(translations) => translations,
+		_ => return Ok(()),
+	};
+
+	for (position, translation) in translations.iter().enumerate() {
+		let locale = translation["locale"].as_str().unwrap_or_default();
+
+		if !is_valid_locale(locale.trim()) {
+			return Err(ValidationError::new(
+				property,
+				Some(position),
+				"locale",
+				ValidationConstraint::Text(locale.to_string()),
+				format!("{} is not a valid locale", locale),
+			));
+		}
+	}
+
+	Ok(())
+}
+
+/// Check a mutation input against the rules of the stored schema, before it reaches the database
+pub fn validate_input(entity: &DbEntity, input: &EntityInput) -> Result<(), ValidationError> {
+	// The values by property name, as referenced by the cross-field rules
+	let mut document = JsonMap::new();
+
+	for (field, value) in &input.0 {
+		match entity.property_by_field(field) {
+			Some(property) => {
+				validate_value(property, None, value)?;
+
+				document.insert(property.name.clone(), value.clone());
+			}
+			None if entity.strict => {
+				return Err(ValidationError {
+					field: field.clone(),
+					path: format!("{}.{}", INPUT_ARGUMENT, field),
+					rule: "additionalProperties",
+					constraint: ValidationConstraint::Text(entity.name.clone()),
+					message: format!("{} is not a property of {}", field, entity.name),
+				});
+			}
+			None => {}
+		}
+	}
+
+	// A rule is only checked when the input sets both of its properties
+	for comparison in &entity.validations {
+		if comparison.holds(&document) == Some(false) {
+			let field = entity
+				.properties
+				.iter()
+				.find(|property| property.name == comparison.left)
+				.map(|property| property.field_name())
+				.unwrap_or(&comparison.left);
+
+			return Err(ValidationError {
+				field: field.to_string(),
+				path: format!("{}.{}", INPUT_ARGUMENT, field),
+				rule: "comparison",
+				constraint: ValidationConstraint::Text(format!(
+					"{} {} {}",
+					comparison.left,
+					comparison.operator.as_aql(),
+					comparison.right
+				)),
+				message: comparison.message.clone(),
+			});
+		}
+	}
+
+	Ok(())
+}
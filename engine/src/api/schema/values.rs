@@ -0,0 +1,108 @@
+use std::fmt;
+use std::marker::PhantomData;
+
+use juniper::{Object, ScalarValue, Value};
+use serde::de::{Deserialize, Deserializer, MapAccess, SeqAccess, Visitor};
+
+/// A document of an AQL result, deserialized straight into a juniper value without building a
+/// `serde_json::Value` tree first
+pub struct DocumentValue<S>(pub Value<S>);
+
+struct DocumentVisitor<S>(PhantomData<S>);
+
+/// Integers are exposed as GraphQL `Int`, the out of range values are clamped
+fn clamp(v: i64) -> i32 {
+	v.clamp(i32::MIN as i64, i32::MAX as i64) as i32
+}
+
+impl<'de, S> Visitor<'de> for DocumentVisitor<S>
+where
+	S: ScalarValue,
+{
+	type Value = Value<S>;
+
+	fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+		formatter.write_str("a JSON value")
+	}
+
+	fn visit_unit<E>(self) -> Result<Value<S>, E> {
+		Ok(Value::null())
+	}
+
+	fn visit_none<E>(self) -> Result<Value<S>, E> {
+		Ok(Value::null())
+	}
+
+	fn visit_some<D>(self, deserializer: D) -> Result<Value<S>, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer.deserialize_any(self)
+	}
+
+	fn visit_bool<E>(self, v: bool) -> Result<Value<S>, E> {
+		Ok(Value::scalar(v))
+	}
+
+	fn visit_i64<E>(self, v: i64) -> Result<Value<S>, E> {
+		Ok(Value::scalar(clamp(v)))
+	}
+
+	fn visit_u64<E>(self, v: u64) -> Result<Value<S>, E> {
+		Ok(Value::scalar(v.min(i32::MAX as u64) as i32))
+	}
+
+	fn visit_f64<E>(self, v: f64) -> Result<Value<S>, E> {
+		Ok(Value::scalar(v))
+	}
+
+	fn visit_str<E>(self, v: &str) -> Result<Value<S>, E> {
+		Ok(Value::scalar(v.to_string()))
+	}
+
+	fn visit_string<E>(self, v: String) -> Result<Value<S>, E> {
+		Ok(Value::scalar(v))
+	}
+
+	fn visit_seq<A>(self, mut seq: A) -> Result<Value<S>, A::Error>
+	where
+		A: SeqAccess<'de>,
+	{
+		let mut items = Vec::with_capacity(seq.size_hint().unwrap_or(0));
+
+		while let Some(DocumentValue(item)) = seq.next_element()? {
+			items.push(item);
+		}
+
+		Ok(Value::list(items))
+	}
+
+	fn visit_map<A>(self, mut map: A) -> Result<Value<S>, A::Error>
+	where
+		A: MapAccess<'de>,
+	{
+		let mut object = Object::with_capacity(map.size_hint().unwrap_or(0));
+
+		while let Some((key, DocumentValue(value))) =
+			map.next_entry::<String, DocumentValue<S>>()?
+		{
+			object.add_field(key, value);
+		}
+
+		Ok(Value::Object(object))
+	}
+}
+
+impl<'de, S> Deserialize<'de> for DocumentValue<S>
+where
+	S: ScalarValue,
+{
+	fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+	where
+		D: Deserializer<'de>,
+	{
+		deserializer
+			.deserialize_any(DocumentVisitor(PhantomData))
+			.map(DocumentValue)
+	}
+}
@@ -0,0 +1,14 @@
+/// A previous state of a document of a versioned collection
+#[derive(GraphQLObject)]
+pub struct VersionEntry {
+	pub document_key: String,
+	/// Numbered from 1, the current state of the document is not a version
+	pub version: i32,
+	/// `update` or `delete`, the operation that replaced the state
+	pub operation: String,
+	/// Unknown for the first state of documents without timestamps
+	pub valid_from: Option<String>,
+	pub valid_to: String,
+	/// JSON encoded document
+	pub document: String,
+}
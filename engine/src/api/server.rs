@@ -1,20 +1,394 @@
+use std::time::Duration;
+
 use actix_web::{
+	error::{ErrorInternalServerError, InternalError},
+	http::{header, Method},
+	rt,
 	web::{Data, Payload as ActixPayload},
-	Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+	Error as ActixError, HttpMessage, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
 };
 
-use juniper_actix::{graphql_handler, playground_handler};
+use juniper::http::{GraphQLBatchRequest, GraphQLBatchResponse};
+use juniper_actix::{graphiql_handler, playground_handler};
+use lazy_static::lazy_static;
+use serde_json::Value as JsonValue;
+use sha2::{Digest, Sha256};
+
+use crate::api::analysis::{check_request, runs_mutation, single_root_field};
+use crate::api::apollo_tracing::trace_request;
+use crate::api::auth::Claims;
+use crate::api::cache::{get_cached_response, response_cache_key, store_response, trace_execution};
+use crate::api::incremental::{
+	accepts_incremental, incremental_response, plan_incremental, strip_incremental_directives,
+};
+use crate::api::limits::read_graphql_request;
+use crate::api::rate_limit::{record_rows_read, API_KEY_HEADER};
+use crate::api::remote::remote_headers;
+use crate::api::request_id::{scope_request_id, RequestId};
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{has_field_errors, mask_internal_errors, timeout_response};
+use crate::api::schema::localized::accept_language;
+use crate::api::schema::{Schema, Schemas};
+use crate::api::sessions::apply_session_change;
+use crate::api::streaming::{list_response, ListResponse};
+use crate::api::uploads::{discard_uploads, scope_uploads, store_uploads};
+use crate::lib::config::GraphQLIde;
+use crate::lib::redaction::{redact_document, redact_json};
+use crate::lib::CONFIG;
+use crate::meta::auth::require_admin;
 
-use crate::api::schema::Schema;
+/// The headers the response of a request may depend on, a cached response varies with them
+const CREDENTIAL_HEADERS: [&str; 3] = ["authorization", "cookie", API_KEY_HEADER];
+
+lazy_static! {
+	static ref GRAPHQL_PATH: String = CONFIG.path(&CONFIG.graphql_path);
+	static ref SUBSCRIPTIONS_PATH: String = CONFIG.path(&CONFIG.graphql_subscriptions_path);
+}
 
+#[tracing::instrument(name = "graphql", skip_all)]
 pub async fn graphql_api_route(
 	req: ActixRequest,
 	payload: ActixPayload,
-	schema: Data<Schema>,
+	schemas: Data<Schemas>,
 ) -> Result<ActixResponse, ActixError> {
-	graphql_handler(&schema, &(), req, payload).await
+	let request_id = req
+		.extensions()
+		.get::<RequestId>()
+		.map(|id| id.0.clone())
+		.unwrap_or_default();
+
+	let mut context = Context::new(req.extensions().get::<Claims>().cloned());
+	context.request_id = Some(request_id.clone());
+	context.remote_headers = remote_headers(req.headers());
+	context.locales = accept_language(req.headers());
+
+	let schema = schemas.for_roles(context.roles());
+
+	let client = context
+		.claims
+		.as_ref()
+		.and_then(|claims| claims.user_id.clone())
+		.or_else(|| req.peer_addr().map(|address| address.ip().to_string()))
+		.unwrap_or_default();
+
+	// The document is read here rather than by juniper_actix so that its size is checked first
+	let mut incoming = read_graphql_request(&req, payload).await?;
+
+	check_request(&incoming.source, &schema)?;
+
+	// The responses of GET requests are cached and revalidated, a mutation would run again
+	if let (&Method::GET, GraphQLBatchRequest::Single(request)) = (req.method(), &incoming.request)
+	{
+		if runs_mutation(&request.query, request.operation_name.as_deref(), &schema) {
+			return Err(mutation_over_get());
+		}
+	}
+
+	if CONFIG.log_operations {
+		log_operations(&incoming.request, &request_id);
+	}
+
+	let incremental = match &incoming.request {
+		GraphQLBatchRequest::Single(request) if accepts_incremental(&req) => {
+			plan_incremental(request)
+		}
+		_ => None,
+	};
+
+	if let Some(incremental) = incremental {
+		return Ok(incremental_response(
+			incremental,
+			schema,
+			context,
+			request_id,
+			client,
+		));
+	}
+
+	strip_incremental_directives(&mut incoming.request);
+
+	// The traced responses carry their own timings, they are never served from the cache
+	let cache_key = if CONFIG.response_cache_enabled && !CONFIG.graphql_tracing {
+		Some(response_cache_key(
+			&incoming.source,
+			context.cache_scope().as_deref(),
+		))
+	} else {
+		None
+	};
+
+	let streamed = match &incoming.request {
+		GraphQLBatchRequest::Single(request) if streams_list(&req, cache_key.is_some()) => {
+			single_root_field(&request.query, request.operation_name.as_deref(), &schema)
+				.map(|key| (request.clone(), key))
+		}
+		_ => None,
+	};
+
+	if let Some((request, key)) = streamed {
+		let claims = context.claims.clone();
+
+		// A response failing before its first document is returned as a whole
+		return match list_response(request, key, schema, context, request_id, client.clone()).await
+		{
+			Ok(ListResponse::Streamed(response)) => Ok(response),
+			Ok(ListResponse::Buffered {
+				body,
+				is_ok,
+				session_change,
+			}) => {
+				record_rows_read(claims.as_ref(), &client, &body);
+
+				let mut builder = if is_ok {
+					ActixResponse::Ok()
+				} else {
+					ActixResponse::BadRequest()
+				};
+
+				if let Some(change) = session_change {
+					apply_session_change(&mut builder, change);
+				}
+
+				Ok(builder.content_type("application/json").body(body))
+			}
+			Err(_) => Err(ErrorInternalServerError("The request was abandoned")),
+		};
+	}
+
+	let cached = cache_key.as_deref().and_then(get_cached_response);
+
+	let (body, is_ok, is_complete) = match cached {
+		Some(body) => (body, true, true),
+		None => {
+			// The files are only stored once the request passed its checks
+			let uploads = store_uploads(incoming.uploads).await?;
+
+			let execution = trace_execution(scope_request_id(
+				request_id.clone(),
+				scope_uploads(
+					uploads.clone(),
+					trace_request(execute_request(&incoming.request, &schema, &context)),
+				),
+			));
+
+			// Dropping the execution drops the pending resolvers, which cancels their queries
+			let ((response, apollo_trace), trace) = match CONFIG.graphql_request_timeout_ms {
+				Some(timeout) => {
+					match rt::time::timeout(Duration::from_millis(timeout), execution).await {
+						Ok(output) => output,
+						Err(_) => {
+							tracing::warn!(request_id = %request_id, timeout_ms = timeout, "Request timed out");
+
+							discard_uploads(&uploads).await;
+
+							return Err(request_timeout(&request_id));
+						}
+					}
+				}
+				None => execution.await,
+			};
+
+			// The ETag and the response cache need the complete body, only the lists selected
+			// alone are streamed
+			let mut value = serde_json::to_value(&response)?;
+
+			// A failed root field leaves partial data, which is returned but never cached, the
+			// failure may be transient
+			let is_complete = response.is_ok() && !has_field_errors(&value);
+
+			if !is_complete && returned_no_data(&value) {
+				discard_uploads(&uploads).await;
+			}
+
+			if !CONFIG.error_details() {
+				mask_internal_errors(&mut value, &request_id);
+			}
+
+			context.attach_warnings(&mut value);
+
+			if let Some(apollo_trace) = apollo_trace {
+				apollo_trace.attach(&mut value);
+			}
+
+			let body = serde_json::to_vec(&value)?;
+
+			if let (Some(key), true) = (cache_key, is_complete) {
+				store_response(key, body.clone(), trace);
+			}
+
+			(body, response.is_ok(), is_complete)
+		}
+	};
+
+	record_rows_read(context.claims.as_ref(), &client, &body);
+
+	let mut builder = if is_ok {
+		ActixResponse::Ok()
+	} else {
+		ActixResponse::BadRequest()
+	};
+
+	// Successful GET queries are idempotent, so they can be revalidated by browsers and CDNs
+	if req.method() == Method::GET && is_complete {
+		let etag = format!("\"{}\"", hex::encode(Sha256::digest(&body)));
+
+		if matches_etag(&req, &etag) {
+			return Ok(ActixResponse::NotModified()
+				.insert_header((header::ETAG, etag))
+				.finish());
+		}
+
+		builder.insert_header((header::ETAG, etag));
+		builder.insert_header((header::VARY, CREDENTIAL_HEADERS.join(", ")));
+
+		// The data of a client is never kept by shared caches
+		if carries_credentials(&req) {
+			builder.insert_header((header::CACHE_CONTROL, "private"));
+		} else if let Some(cache_control) = &CONFIG.graphql_get_cache_control {
+			builder.insert_header((header::CACHE_CONTROL, cache_control.as_str()));
+		}
+	}
+
+	if let Some(change) = context.session_change.lock().unwrap().take() {
+		apply_session_change(&mut builder, change);
+	}
+
+	Ok(builder.content_type("application/json").body(body))
+}
+
+/// Whether no root field of the response resolved, the files of the request are then unused
+fn returned_no_data(response: &JsonValue) -> bool {
+	match response {
+		JsonValue::Array(responses) => responses.iter().all(returned_no_data),
+		response => response
+			.get("data")
+			.and_then(|data| data.as_object())
+			.map_or(true, |fields| fields.values().all(JsonValue::is_null)),
+	}
+}
+
+/// Whether the request sends credentials, its response then depends on who sent it
+fn carries_credentials(req: &ActixRequest) -> bool {
+	CREDENTIAL_HEADERS
+		.iter()
+		.any(|name| req.headers().contains_key(*name))
+}
+
+/// Whether the list a query selects alone may be written to the response as it is read, the
+/// responses of GET requests get an ETag, and the cached, traced or timed ones need their whole
+/// body
+fn streams_list(req: &ActixRequest, cached: bool) -> bool {
+	CONFIG.graphql_stream_lists
+		&& req.method() == Method::POST
+		&& !cached
+		&& !CONFIG.graphql_tracing
+		&& CONFIG.graphql_request_timeout_ms.is_none()
+}
+
+/// The 405 of a mutation sent with GET, which only runs queries
+fn mutation_over_get() -> ActixError {
+	InternalError::from_response(
+		"Mutations must be sent with POST",
+		ActixResponse::MethodNotAllowed()
+			.insert_header((header::ALLOW, "POST"))
+			.body("Mutations must be sent with POST"),
+	)
+	.into()
+}
+
+/// The GraphQL error of a request that ran out of time, none of its results are returned
+fn request_timeout(request_id: &str) -> ActixError {
+	InternalError::from_response(
+		"Request timed out",
+		ActixResponse::GatewayTimeout().json(timeout_response(request_id)),
+	)
+	.into()
 }
 
-pub async fn playground_api_route() -> Result<ActixResponse, ActixError> {
-	playground_handler("/api/graphql", Some("/api/graphql_subscriptions")).await
+/// Log the operations of a request, the values of the sensitive fields redacted
+fn log_operations(request: &GraphQLBatchRequest, request_id: &str) {
+	let requests = match request {
+		GraphQLBatchRequest::Single(request) => std::slice::from_ref(request),
+		GraphQLBatchRequest::Batch(requests) => requests.as_slice(),
+	};
+
+	for request in requests {
+		let mut variables = serde_json::to_value(&request.variables).unwrap_or_default();
+		redact_json(&mut variables);
+
+		tracing::info!(
+			target: "alchemy::operations",
+			request_id = %request_id,
+			operation = %request.operation_name.as_deref().unwrap_or_default(),
+			query = %redact_document(&request.query).unwrap_or_default(),
+			variables = %variables,
+			"GraphQL operation"
+		);
+	}
+}
+
+/// Whether the If-None-Match header of the request lists the ETag
+fn matches_etag(req: &ActixRequest, etag: &str) -> bool {
+	req.headers()
+		.get(header::IF_NONE_MATCH)
+		.and_then(|value| value.to_str().ok())
+		.map(|value| {
+			value
+				.split(',')
+				.map(|tag| tag.trim())
+				.any(|tag| tag == "*" || tag.strip_prefix("W/").unwrap_or(tag) == etag)
+		})
+		.unwrap_or(false)
+}
+
+/// Execute a single request or a batch, whose operations run one after another
+/// unless concurrent batches are enabled
+async fn execute_request<'a>(
+	request: &'a GraphQLBatchRequest,
+	schema: &'a Schema,
+	context: &'a Context,
+) -> GraphQLBatchResponse<'a> {
+	match request {
+		GraphQLBatchRequest::Batch(requests) if !CONFIG.graphql_batch_concurrent => {
+			let mut responses = Vec::with_capacity(requests.len());
+
+			for request in requests {
+				responses.push(request.execute(schema, context).await);
+			}
+
+			GraphQLBatchResponse::Batch(responses)
+		}
+		request => request.execute(schema, context).await,
+	}
+}
+
+/// The SDL of the unrestricted schema, for the code generators of the clients, it is
+/// only served to admins when introspection is disabled
+pub async fn sdl_api_route(
+	req: ActixRequest,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	if !CONFIG.introspection_enabled() {
+		require_admin(&req)?;
+	}
+
+	Ok(ActixResponse::Ok()
+		.content_type("application/graphql; charset=utf-8")
+		.body(schemas.sdl()))
+}
+
+pub async fn ide_api_route() -> Result<ActixResponse, ActixError> {
+	ide_handler(&GRAPHQL_PATH, &SUBSCRIPTIONS_PATH).await
+}
+
+/// Serve the configured IDE for a GraphQL endpoint
+pub async fn ide_handler(
+	graphql_path: &str,
+	subscriptions_path: &'static str,
+) -> Result<ActixResponse, ActixError> {
+	match CONFIG.graphql_ide() {
+		GraphQLIde::GraphiQL => graphiql_handler(graphql_path, Some(subscriptions_path)).await,
+		GraphQLIde::Playground => playground_handler(graphql_path, Some(subscriptions_path)).await,
+		GraphQLIde::Disabled => Ok(ActixResponse::NotFound().finish()),
+	}
 }
@@ -0,0 +1,311 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use actix_web::{
+	cookie::{time::Duration as CookieDuration, Cookie, CookieJar, Key, SameSite},
+	dev::{Service, ServiceRequest},
+	web::{self, Json},
+	HttpMessage, HttpResponse as ActixResponse, HttpResponseBuilder,
+};
+use juniper::{Arguments, ExecutionResult, FieldError, ScalarValue, Value};
+use lazy_static::lazy_static;
+use rust_arango::AqlQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+use uuid::Uuid;
+
+use crate::api::auth::Claims;
+use crate::api::cache::record_mutation;
+use crate::api::rate_limit::with_rate_limit;
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::{error_extensions, ErrorCode};
+use crate::lib::database::DATABASE;
+use crate::lib::passwords::verify_password;
+use crate::lib::CONFIG;
+
+pub const LOGIN_FIELD: &str = "login";
+pub const LOGOUT_FIELD: &str = "logout";
+
+/// Header echoing the CSRF cookie, without it the session of a request is ignored
+pub const CSRF_HEADER: &str = "x-csrf-token";
+
+lazy_static! {
+	static ref SESSION_KEY: Option<Key> = CONFIG.session_secret.as_ref().map(|secret| {
+		if secret.len() < 32 {
+			panic!("SESSION_SECRET must be at least 32 bytes long");
+		}
+
+		Key::derive_from(secret.as_bytes())
+	});
+}
+
+/// The content of the encrypted session cookie
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct Session {
+	pub user_id: String,
+	pub roles: Vec<String>,
+	pub csrf: String,
+	pub expires_at: u64,
+}
+
+impl Session {
+	fn into_claims(self) -> Claims {
+		let mut claims = JsonMap::new();
+
+		// Named like the token claims so that row filters apply to sessions as well
+		claims.insert(
+			CONFIG.jwt_user_id_claim.clone(),
+			JsonValue::String(self.user_id.clone()),
+		);
+		claims.insert(CONFIG.jwt_roles_claim.clone(), json!(self.roles));
+
+		Claims {
+			user_id: Some(self.user_id),
+			roles: self.roles,
+			tenant: None,
+			api_key: None,
+			claims,
+		}
+	}
+}
+
+/// A session opened or closed by the login and logout mutations
+pub enum SessionChange {
+	Login(Session),
+	Logout,
+}
+
+fn now() -> u64 {
+	SystemTime::now()
+		.duration_since(UNIX_EPOCH)
+		.map(|now| now.as_secs())
+		.unwrap_or_default()
+}
+
+fn csrf_cookie_name() -> String {
+	format!("{}_csrf", CONFIG.session_cookie_name)
+}
+
+/// Make sure the session key is valid before serving requests
+pub fn check_session_key() {
+	lazy_static::initialize(&SESSION_KEY);
+}
+
+/// The claims of the session cookie, only when the request echoes its CSRF token
+pub fn session_claims(req: &ServiceRequest) -> Option<Claims> {
+	let key = SESSION_KEY.as_ref()?;
+	let cookie = req.cookie(&CONFIG.session_cookie_name)?;
+
+	let mut jar = CookieJar::new();
+	jar.add_original(cookie);
+
+	let session: Session =
+		serde_json::from_str(jar.private(key).get(&CONFIG.session_cookie_name)?.value()).ok()?;
+
+	let csrf = req
+		.headers()
+		.get(CSRF_HEADER)
+		.and_then(|value| value.to_str().ok())?;
+
+	if session.expires_at <= now() || csrf != session.csrf {
+		return None;
+	}
+
+	Some(session.into_claims())
+}
+
+/// Set or remove the session and CSRF cookies on the response
+pub fn apply_session_change(builder: &mut HttpResponseBuilder, change: SessionChange) {
+	let key = match SESSION_KEY.as_ref() {
+		Some(key) => key,
+		None => return,
+	};
+
+	let path = match CONFIG.base_path() {
+		path if path.is_empty() => String::from("/"),
+		path => path,
+	};
+
+	let cookie = |name: String, value: String, http_only: bool| {
+		Cookie::build(name, value)
+			.path(path.clone())
+			.http_only(http_only)
+			.secure(!CONFIG.is_development())
+			.same_site(SameSite::Lax)
+			.max_age(CookieDuration::seconds(CONFIG.session_max_age_secs as i64))
+			.finish()
+	};
+
+	match change {
+		SessionChange::Login(session) => {
+			let mut jar = CookieJar::new();
+
+			jar.private_mut(key).add(cookie(
+				CONFIG.session_cookie_name.clone(),
+				serde_json::to_string(&session).unwrap_or_default(),
+				true,
+			));
+
+			if let Some(encrypted) = jar.get(&CONFIG.session_cookie_name) {
+				builder.cookie(encrypted.clone());
+			}
+
+			// Readable by scripts so that they can send it back in the CSRF header
+			builder.cookie(cookie(csrf_cookie_name(), session.csrf, false));
+		}
+		SessionChange::Logout => {
+			for name in [CONFIG.session_cookie_name.clone(), csrf_cookie_name()] {
+				let mut removal = cookie(name, String::new(), true);
+
+				removal.make_removal();
+				builder.cookie(removal);
+			}
+		}
+	}
+}
+
+#[derive(Deserialize)]
+struct StoredUser {
+	#[serde(rename = "_key")]
+	key: String,
+	password: Option<String>,
+	#[serde(default)]
+	roles: Vec<String>,
+}
+
+/// Open a session for the user whose password matches
+async fn login(username: &str, password: &str) -> Option<Session> {
+	let query = AqlQuery::builder()
+		.query(
+			"FOR user IN @@collection
+				FILTER user[@username_property] == @username
+				LIMIT 1
+				RETURN { _key: user._key, password: user[@password_property], roles: NOT_NULL(user[@roles_property], []) }",
+		)
+		.bind_var("@collection", CONFIG.session_users_collection.as_str())
+		.bind_var(
+			"username_property",
+			CONFIG.session_username_property.as_str(),
+		)
+		.bind_var(
+			"password_property",
+			CONFIG.session_password_property.as_str(),
+		)
+		.bind_var("roles_property", CONFIG.session_roles_property.as_str())
+		.bind_var("username", username)
+		.build();
+
+	let users: Vec<StoredUser> = match DATABASE.get().await.database.aql_query(query).await {
+		Ok(users) => users,
+		Err(e) => {
+			tracing::error!(error = ?e, "Failed to look up the user");
+
+			return None;
+		}
+	};
+
+	let user = users.into_iter().next()?;
+
+	if !verify_password(password, user.password.as_deref()?) {
+		return None;
+	}
+
+	Some(Session {
+		user_id: user.key,
+		roles: user.roles,
+		csrf: Uuid::new_v4().to_string(),
+		expires_at: now() + CONFIG.session_max_age_secs,
+	})
+}
+
+#[derive(Deserialize)]
+pub struct LoginInput {
+	username: String,
+	password: String,
+}
+
+/// `POST {session_path}/login`, returns the CSRF token the requests of the session echo
+async fn login_route(input: Json<LoginInput>) -> ActixResponse {
+	record_mutation();
+
+	match login(&input.username, &input.password).await {
+		Some(session) => {
+			let csrf = session.csrf.clone();
+			let mut response = ActixResponse::Ok();
+
+			apply_session_change(&mut response, SessionChange::Login(session));
+
+			response.json(json!({ "csrf": csrf }))
+		}
+		None => ActixResponse::Unauthorized().json(json!({ "error": "Invalid credentials" })),
+	}
+}
+
+/// `POST {session_path}/logout`
+async fn logout_route() -> ActixResponse {
+	record_mutation();
+
+	let mut response = ActixResponse::Ok();
+
+	apply_session_change(&mut response, SessionChange::Logout);
+
+	response.finish()
+}
+
+/// Register the session routes, outside of the authentication of the API since a client logging
+/// in has no credentials yet, the guessed passwords are charged to the IP of the client
+pub fn configure_sessions(config: &mut web::ServiceConfig) {
+	if !CONFIG.sessions_enabled() {
+		return;
+	}
+
+	config
+		.service(
+			web::resource(format!("{}/login", CONFIG.session_path))
+				.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+				.route(web::post().to(login_route)),
+		)
+		.service(
+			web::resource(format!("{}/logout", CONFIG.session_path))
+				.route(web::post().to(logout_route)),
+		);
+}
+
+/// Resolve the login and logout mutations
+pub async fn resolve_session_field<S>(
+	context: &Context,
+	field_name: &str,
+	arguments: &Arguments<'_, S>,
+) -> ExecutionResult<S>
+where
+	S: ScalarValue + Send + Sync,
+{
+	record_mutation();
+
+	let change = if field_name == LOGIN_FIELD {
+		let username = arguments.get::<String>("username").unwrap_or_default();
+		let password = arguments.get::<String>("password").unwrap_or_default();
+
+		match login(&username, &password).await {
+			Some(session) => SessionChange::Login(session),
+			None => {
+				return Err(FieldError::new(
+					"Invalid credentials",
+					Value::Object(error_extensions(ErrorCode::Unauthorized)),
+				))
+			}
+		}
+	} else {
+		SessionChange::Logout
+	};
+
+	*context.session_change.lock().unwrap() = Some(change);
+
+	Ok(Value::scalar(true))
+}
+
+/// Whether a property holds the password hashes of the users
+pub fn is_password_property(collection: &str, property: &str) -> bool {
+	CONFIG.sessions_enabled()
+		&& collection == CONFIG.session_users_collection
+		&& property == CONFIG.session_password_property
+}
@@ -0,0 +1,301 @@
+use std::cell::RefCell;
+use std::convert::Infallible;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+use actix_web::{rt, web::Bytes, HttpResponse as ActixResponse};
+use futures::channel::{mpsc, oneshot};
+use futures::future::{self, Either};
+use futures::{SinkExt, StreamExt};
+use juniper::http::GraphQLRequest;
+use juniper::DefaultScalarValue;
+use serde_json::json;
+use serde_json::value::RawValue;
+
+use crate::api::auth::Claims;
+use crate::api::rate_limit::record_documents_read;
+use crate::api::request_id::scope_request_id;
+use crate::api::schema::context::Context;
+use crate::api::schema::errors::mask_internal_errors;
+use crate::api::schema::values::DocumentValue;
+use crate::api::schema::Schema;
+use crate::api::sessions::SessionChange;
+use crate::lib::database::backend::{run_query_batches, BackendError, BackendQuery};
+use crate::lib::CONFIG;
+
+/// Batches of documents waiting to be written, the cursor is read no faster than the client
+/// reads the response
+const STREAM_BUFFER: usize = 2;
+
+type Chunk = Result<Bytes, Infallible>;
+
+tokio::task_local! {
+	static LIST_STREAM: RefCell<Option<ListStream>>;
+}
+
+/// The body of a response made of a single list, the resolver of the list writes its documents
+/// to it as the cursor returns them
+pub struct ListStream {
+	/// The response key of the list
+	key: String,
+	claims: Option<Claims>,
+	client: String,
+	sender: mpsc::Sender<Chunk>,
+	/// Whether the start of the body was written, the response is no longer buffered then
+	started: Arc<AtomicBool>,
+}
+
+/// The response of a query selecting a single list
+pub enum ListResponse {
+	Streamed(ActixResponse),
+	/// No resolver wrote the list, e.g. it failed before its first batch, the response is sent
+	/// as usual
+	Buffered {
+		body: Vec<u8>,
+		is_ok: bool,
+		session_change: Option<SessionChange>,
+	},
+}
+
+/// A document of the list as the buffered response writes it, e.g. with the integers clamped to
+/// the range of `Int`
+fn encode_document(document: &RawValue) -> String {
+	serde_json::from_str::<DocumentValue<DefaultScalarValue>>(document.get())
+		.and_then(|DocumentValue(value)| serde_json::to_string(&value))
+		.unwrap_or_else(|_| document.get().to_string())
+}
+
+/// The stream of the request, to the first resolver of a list taking it
+pub fn take_list_stream() -> Option<ListStream> {
+	LIST_STREAM
+		.try_with(|stream| stream.borrow_mut().take())
+		.ok()
+		.flatten()
+}
+
+impl ListStream {
+	/// Write the documents of the query to the body, one chunk per batch of the cursor
+	pub async fn write(mut self, query: BackendQuery) -> Result<(), BackendError> {
+		let (batches, receiver) = mpsc::channel::<Vec<Box<RawValue>>>(STREAM_BUFFER);
+
+		let write = async {
+			// Dropped along with the writer, which stops the query
+			let mut receiver = receiver;
+
+			while let Some(documents) = receiver.next().await {
+				record_documents_read(self.claims.as_ref(), &self.client, &documents);
+
+				let mut chunk = String::new();
+
+				for document in &documents {
+					if self.started.swap(true, Ordering::SeqCst) {
+						chunk.push(',');
+					} else {
+						chunk.push_str(&format!("{{\"data\":{{{}:[", json!(self.key)));
+					}
+
+					chunk.push_str(&encode_document(document));
+				}
+
+				// The client went away
+				if !chunk.is_empty() && self.sender.send(Ok(Bytes::from(chunk))).await.is_err() {
+					return;
+				}
+			}
+		};
+
+		let (result, _) = future::join(run_query_batches(query, batches), write).await;
+
+		result
+	}
+}
+
+/// Execute a query selecting a single list, the documents of the list are written to the body
+/// as they are read. The response is only streamed once the first document is written, until
+/// then it can still turn out to be an error
+pub async fn list_response(
+	request: GraphQLRequest,
+	key: String,
+	schema: Arc<Schema>,
+	context: Context,
+	request_id: String,
+	client: String,
+) -> Result<ListResponse, oneshot::Canceled> {
+	let (sender, receiver) = mpsc::channel::<Chunk>(STREAM_BUFFER);
+	let (buffered_sender, buffered) = oneshot::channel();
+	let started = Arc::new(AtomicBool::new(false));
+
+	let stream = ListStream {
+		key,
+		claims: context.claims.clone(),
+		client,
+		sender: sender.clone(),
+		started: started.clone(),
+	};
+
+	rt::spawn(async move {
+		let mut sender = sender;
+
+		let (is_ok, mut response) = LIST_STREAM
+			.scope(
+				RefCell::new(Some(stream)),
+				scope_request_id(request_id.clone(), async {
+					let response = request.execute(&schema, &context).await;
+
+					(
+						response.is_ok(),
+						serde_json::to_value(&response).unwrap_or_default(),
+					)
+				}),
+			)
+			.await;
+
+		if !CONFIG.error_details() {
+			mask_internal_errors(&mut response, &request_id);
+		}
+
+		context.attach_warnings(&mut response);
+
+		if !started.load(Ordering::SeqCst) {
+			let _ = buffered_sender.send(ListResponse::Buffered {
+				body: serde_json::to_vec(&response).unwrap_or_default(),
+				is_ok,
+				session_change: context.session_change.lock().unwrap().take(),
+			});
+
+			return;
+		}
+
+		// The documents are written already, the errors and the extensions of the list follow them
+		let mut end = String::from("]}");
+
+		for key in ["errors", "extensions"] {
+			if let Some(value) = response.get(key) {
+				end.push_str(&format!(",{}:{}", json!(key), value));
+			}
+		}
+
+		end.push('}');
+
+		let _ = sender.send(Ok(Bytes::from(end))).await;
+	});
+
+	match future::select(buffered, receiver.into_future()).await {
+		Either::Left((buffered, _)) => buffered,
+		Either::Right(((Some(first), receiver), _)) => Ok(ListResponse::Streamed(
+			ActixResponse::Ok()
+				.content_type("application/json")
+				.streaming(futures::stream::once(future::ready(first)).chain(receiver)),
+		)),
+		Either::Right(((None, _), buffered)) => buffered.await,
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use actix_web::body::to_bytes;
+	use juniper::http::GraphQLRequest;
+	use serde_json::{json, Value as JsonValue};
+
+	use super::{list_response, ListResponse};
+	use crate::api::schema::context::Context;
+	use crate::api::testing::TestHarness;
+
+	const QUERY: &str = "{ books: getAllBooks { title pages } }";
+
+	fn books() -> JsonValue {
+		json!({
+			"name": "books",
+			"schema": {
+				"properties": {
+					"title": { "type": "string" },
+					"pages": { "type": "integer" },
+				},
+				"required": ["title"],
+			},
+		})
+	}
+
+	async fn respond(harness: &TestHarness) -> ListResponse {
+		let request: GraphQLRequest = serde_json::from_value(json!({ "query": QUERY })).unwrap();
+
+		list_response(
+			request,
+			String::from("books"),
+			harness.schemas.for_roles(None),
+			Context::new(None),
+			String::from("request"),
+			String::from("client"),
+		)
+		.await
+		.unwrap()
+	}
+
+	#[actix_web::test]
+	async fn lists_are_written_as_they_are_read() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness.backend.respond(
+			"FOR i_1 IN @@collection",
+			vec![json!({ "title": "Dune" }), json!({ "title": "Emma" })],
+		);
+
+		let response = match respond(&harness).await {
+			ListResponse::Streamed(response) => response,
+			ListResponse::Buffered { .. } => panic!("The list was not streamed"),
+		};
+
+		let body = to_bytes(response.into_body()).await.unwrap();
+
+		assert_eq!(
+			serde_json::from_slice::<JsonValue>(&body).unwrap(),
+			json!({ "data": { "books": [{ "title": "Dune" }, { "title": "Emma" }] } })
+		);
+	}
+
+	#[actix_web::test]
+	async fn streamed_lists_match_buffered_lists() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness.backend.respond(
+			"FOR i_1 IN @@collection",
+			vec![
+				json!({ "title": "Dune", "pages": 412 }),
+				json!({ "title": "Emma", "pages": 5_000_000_000u64 }),
+				json!({ "title": "Ulysses", "pages": null }),
+			],
+		);
+
+		let response = match respond(&harness).await {
+			ListResponse::Streamed(response) => response,
+			ListResponse::Buffered { .. } => panic!("The list was not streamed"),
+		};
+
+		let streamed: JsonValue =
+			serde_json::from_slice(&to_bytes(response.into_body()).await.unwrap()).unwrap();
+		let buffered = harness.execute(QUERY, None, None).await;
+
+		assert_eq!(streamed, buffered);
+		assert_eq!(streamed["data"]["books"][1]["pages"], i32::MAX);
+	}
+
+	#[actix_web::test]
+	async fn lists_failing_before_their_first_document_are_buffered() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness
+			.backend
+			.fail("FOR i_1 IN @@collection", 1203, "collection not found");
+
+		match respond(&harness).await {
+			ListResponse::Buffered { body, is_ok, .. } => {
+				let body: JsonValue = serde_json::from_slice(&body).unwrap();
+
+				assert!(is_ok);
+				assert!(body["data"]["books"].is_null());
+				assert!(!body["errors"].as_array().unwrap().is_empty());
+			}
+			ListResponse::Streamed(_) => panic!("The failed list was streamed"),
+		}
+	}
+}
@@ -0,0 +1,501 @@
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use actix_web::{
+	http::header::{HeaderValue, AUTHORIZATION, SEC_WEBSOCKET_PROTOCOL},
+	rt,
+	web::{Data, Payload as ActixPayload},
+	Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use actix_ws::{CloseCode, CloseReason, Message, MessageStream, ProtocolError, Session};
+use futures::future::{self, abortable, AbortHandle};
+use futures::stream::{self, StreamExt};
+use juniper::{GraphQLError, Value, Variables};
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::analysis::check_operations;
+use crate::api::auth::{authorize, AccessError, Claims, Credentials};
+use crate::api::limits::check_document;
+use crate::api::persisted::check_allowed;
+use crate::api::rate_limit::API_KEY_HEADER;
+use crate::api::schema::context::Context;
+use crate::api::schema::{Schema, Schemas};
+use crate::lib::CONFIG;
+
+/// Sub-protocol spoken on the subscriptions endpoint
+pub const GRAPHQL_TRANSPORT_WS: &str = "graphql-transport-ws";
+
+type Subscriptions = Rc<RefCell<HashMap<String, AbortHandle>>>;
+
+/// What the connection loop waits for
+enum ConnectionEvent {
+	Message(Result<Message, ProtocolError>),
+	/// Time to ping the client and to check whether the connection is still used
+	Tick,
+	Closed,
+}
+
+/// Credentials sent with the upgrade request
+struct ConnectionHeaders {
+	authorization: Option<String>,
+	api_key: Option<String>,
+	address: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SubscribePayload {
+	query: String,
+	operation_name: Option<String>,
+	#[serde(default)]
+	variables: Option<Variables>,
+}
+
+#[derive(Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum ClientMessage {
+	ConnectionInit {
+		#[serde(default)]
+		payload: Option<JsonValue>,
+	},
+	Ping {},
+	Pong {},
+	Subscribe {
+		id: String,
+		payload: SubscribePayload,
+	},
+	Complete {
+		id: String,
+	},
+}
+
+/// Upgrade the connection and serve the graphql-transport-ws protocol on it
+pub async fn graphql_subscriptions_route(
+	req: ActixRequest,
+	body: ActixPayload,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	let (mut response, session, messages) = actix_ws::handle(&req, body)?;
+
+	// Browsers cannot set headers on websockets, credentials can also be sent in connection_init
+	let header = |name| {
+		req.headers()
+			.get(name)
+			.and_then(|value| value.to_str().ok())
+			.map(String::from)
+	};
+
+	let headers = ConnectionHeaders {
+		authorization: header(AUTHORIZATION.as_str()),
+		api_key: header(API_KEY_HEADER),
+		address: req.peer_addr().map(|address| address.ip().to_string()),
+	};
+
+	response.headers_mut().insert(
+		SEC_WEBSOCKET_PROTOCOL,
+		HeaderValue::from_static(GRAPHQL_TRANSPORT_WS),
+	);
+
+	rt::spawn(run_connection(
+		session,
+		messages,
+		schemas.into_inner(),
+		headers,
+	));
+
+	Ok(response)
+}
+
+async fn send(session: &mut Session, message: JsonValue) -> bool {
+	session.text(message.to_string()).await.is_ok()
+}
+
+async fn close(session: Session, code: u16, description: &str) {
+	let _ = session
+		.close(Some(CloseReason {
+			code: CloseCode::Other(code),
+			description: Some(description.to_string()),
+		}))
+		.await;
+}
+
+/// The ticks of the connection, at the keep-alive interval, or often enough to notice an idle
+/// connection when the pings are disabled
+fn connection_ticks() -> stream::BoxStream<'static, ConnectionEvent> {
+	let period = match (
+		CONFIG.subscriptions_keep_alive_secs,
+		CONFIG.subscriptions_idle_timeout_secs,
+	) {
+		(0, 0) => return stream::pending().boxed(),
+		(0, idle) => idle,
+		(keep_alive, _) => keep_alive,
+	};
+
+	let start = rt::time::Instant::now() + Duration::from_secs(period);
+
+	stream::unfold(
+		rt::time::interval_at(start, Duration::from_secs(period)),
+		|mut interval| async move {
+			interval.tick().await;
+
+			Some((ConnectionEvent::Tick, interval))
+		},
+	)
+	.boxed()
+}
+
+/// The reason to close a connection that has been silent for so long, if it must be
+fn expired(silent: Duration, subscriptions: &Subscriptions) -> Option<&'static str> {
+	let keep_alive = CONFIG.subscriptions_keep_alive_secs;
+	let idle_timeout = CONFIG.subscriptions_idle_timeout_secs;
+
+	// The clients answer the pings, those that did not answer two of them are gone
+	if keep_alive > 0 && silent > Duration::from_secs(keep_alive * 2) {
+		return Some("Keep-alive timed out");
+	}
+
+	if idle_timeout > 0
+		&& silent > Duration::from_secs(idle_timeout)
+		&& subscriptions.borrow().is_empty()
+	{
+		return Some("Idle connection");
+	}
+
+	None
+}
+
+async fn run_connection(
+	mut session: Session,
+	messages: MessageStream,
+	schemas: Arc<Schemas>,
+	headers: ConnectionHeaders,
+) {
+	let subscriptions: Subscriptions = Rc::new(RefCell::new(HashMap::new()));
+	// Set once the connection is acknowledged
+	let mut context: Option<Arc<Context>> = None;
+	let mut last_activity = Instant::now();
+
+	let mut events = stream::select(
+		messages
+			.map(ConnectionEvent::Message)
+			.chain(stream::once(future::ready(ConnectionEvent::Closed))),
+		connection_ticks(),
+	)
+	.boxed_local();
+
+	while let Some(event) = events.next().await {
+		let message = match event {
+			ConnectionEvent::Message(Ok(message)) => message,
+			ConnectionEvent::Message(Err(_)) | ConnectionEvent::Closed => break,
+			ConnectionEvent::Tick => {
+				if let Some(reason) = expired(last_activity.elapsed(), &subscriptions) {
+					tracing::debug!(reason = %reason, "Closing a subscription connection");
+
+					close(session, 4408, reason).await;
+
+					abort_all(&subscriptions);
+
+					return;
+				}
+
+				if CONFIG.subscriptions_keep_alive_secs > 0 && session.ping(b"").await.is_err() {
+					break;
+				}
+
+				continue;
+			}
+		};
+
+		last_activity = Instant::now();
+
+		let text = match message {
+			Message::Text(text) => text,
+			Message::Ping(bytes) => {
+				let _ = session.pong(&bytes).await;
+
+				continue;
+			}
+			Message::Close(_) => break,
+			_ => continue,
+		};
+
+		let message = match serde_json::from_str::<ClientMessage>(&text) {
+			Ok(message) => message,
+			Err(e) => {
+				close(session, 4400, &e.to_string()).await;
+
+				abort_all(&subscriptions);
+
+				return;
+			}
+		};
+
+		match message {
+			ClientMessage::ConnectionInit { payload } => {
+				if context.is_some() {
+					close(session, 4429, "Too many initialisation requests").await;
+
+					abort_all(&subscriptions);
+
+					return;
+				}
+
+				match authenticate_connection(payload, &headers).await {
+					Ok(claims) => context = Some(Arc::new(Context::new(claims))),
+					Err(AccessError::Unauthenticated(e)) => {
+						tracing::warn!(reason = %e, "Unauthenticated subscription");
+
+						close(session, 4403, "Forbidden").await;
+
+						return;
+					}
+					Err(AccessError::RateLimited(retry_after)) => {
+						tracing::warn!(
+							retry_after_ms = retry_after.as_millis() as u64,
+							"Rate limit exceeded"
+						);
+
+						close(session, 4429, "Too many requests").await;
+
+						return;
+					}
+				}
+
+				send(&mut session, json!({ "type": "connection_ack" })).await;
+			}
+			ClientMessage::Ping {} => {
+				send(&mut session, json!({ "type": "pong" })).await;
+			}
+			ClientMessage::Pong {} => {}
+			ClientMessage::Subscribe { id, payload } => {
+				let context = match &context {
+					Some(context) => context.clone(),
+					None => {
+						close(session, 4401, "Unauthorized").await;
+
+						return;
+					}
+				};
+
+				if subscriptions.borrow().contains_key(&id) {
+					close(
+						session,
+						4409,
+						&format!("Subscriber for {} already exists", id),
+					)
+					.await;
+
+					abort_all(&subscriptions);
+
+					return;
+				}
+
+				// The operation is refused, those already running are kept
+				if subscriptions.borrow().len() >= CONFIG.subscriptions_max_per_connection {
+					let error = json!([{
+						"message": format!(
+							"At most {} operations can run at once on a connection",
+							CONFIG.subscriptions_max_per_connection
+						),
+					}]);
+
+					send(
+						&mut session,
+						json!({ "type": "error", "id": id, "payload": error }),
+					)
+					.await;
+
+					continue;
+				}
+
+				let (task, handle) = abortable(run_operation(
+					id.clone(),
+					payload,
+					schemas.for_roles(context.roles()),
+					context,
+					session.clone(),
+					subscriptions.clone(),
+				));
+
+				subscriptions.borrow_mut().insert(id, handle);
+
+				rt::spawn(task);
+			}
+			ClientMessage::Complete { id } => {
+				if let Some(handle) = subscriptions.borrow_mut().remove(&id) {
+					handle.abort();
+				}
+			}
+		}
+	}
+
+	abort_all(&subscriptions);
+
+	let _ = session.close(None).await;
+}
+
+/// Verify the credentials of the connection_init payload, or of the upgrade request, and charge
+/// the connection to the budgets of its client
+async fn authenticate_connection(
+	payload: Option<JsonValue>,
+	headers: &ConnectionHeaders,
+) -> Result<Option<Claims>, AccessError> {
+	let from_payload = |names: [&str; 2]| {
+		let payload = payload.as_ref()?;
+
+		names
+			.iter()
+			.find_map(|name| payload.get(*name))
+			.and_then(|value| value.as_str())
+	};
+
+	let authorization =
+		from_payload(["Authorization", "authorization"]).or(headers.authorization.as_deref());
+	let api_key = from_payload([API_KEY_HEADER, "apiKey"]).or(headers.api_key.as_deref());
+
+	let forwarded = [("authorization", authorization), (API_KEY_HEADER, api_key)]
+		.into_iter()
+		.filter_map(|(name, value)| Some((name.to_string(), value?.to_string())))
+		.collect();
+
+	// Browsers cannot send the CSRF header on the handshake, so sessions are not accepted here
+	authorize(Credentials {
+		authorization: authorization.map(String::from),
+		api_key: api_key.map(String::from),
+		headers: forwarded,
+		session: None,
+		address: headers.address.clone(),
+		ip_limited: false,
+	})
+	.await
+}
+
+fn abort_all(subscriptions: &Subscriptions) {
+	for (_, handle) in subscriptions.borrow_mut().drain() {
+		handle.abort();
+	}
+}
+
+/// Execute a single operation, streaming every event of a subscription until it ends
+async fn run_operation(
+	id: String,
+	payload: SubscribePayload,
+	schema: Arc<Schema>,
+	context: Arc<Context>,
+	mut session: Session,
+	subscriptions: Subscriptions,
+) {
+	let variables = payload.variables.unwrap_or_default();
+	let operation_name = payload.operation_name.as_deref();
+
+	if let Err(e) = check_document(&payload.query)
+		.and_then(|_| check_allowed(&payload.query))
+		.and_then(|_| check_operations(&payload.query, &variables, &schema))
+	{
+		let error = json!([{ "message": e.to_string() }]);
+
+		send(
+			&mut session,
+			json!({ "type": "error", "id": id, "payload": error }),
+		)
+		.await;
+		subscriptions.borrow_mut().remove(&id);
+
+		return;
+	}
+
+	let result = juniper::resolve_into_stream(
+		&payload.query,
+		operation_name,
+		&*schema,
+		&variables,
+		&*context,
+	)
+	.await;
+
+	match result {
+		Ok((Value::Object(fields), errors)) if errors.is_empty() => {
+			// Every root field is a stream of its own, their events are sent as they come
+			let streams = fields.into_iter().map(|(name, value)| match value {
+				Value::Scalar(values) => values.map(move |result| (name.clone(), result)).boxed(),
+				_ => stream::empty().boxed(),
+			});
+
+			let mut events = stream::select_all(streams);
+
+			while let Some((name, result)) = events.next().await {
+				let payload = match result {
+					Ok(value) => json!({ "data": { name: value } }),
+					Err(error) => json!({ "data": { name: null }, "errors": [error] }),
+				};
+
+				if !send(
+					&mut session,
+					json!({ "type": "next", "id": id, "payload": payload }),
+				)
+				.await
+				{
+					break;
+				}
+			}
+		}
+		Ok((_, errors)) => {
+			send(
+				&mut session,
+				json!({ "type": "error", "id": id, "payload": errors }),
+			)
+			.await;
+			subscriptions.borrow_mut().remove(&id);
+
+			return;
+		}
+		Err(GraphQLError::NotSubscription) => {
+			// Queries and mutations are answered with a single result
+			let payload = match juniper::execute(
+				&payload.query,
+				operation_name,
+				&*schema,
+				&variables,
+				&*context,
+			)
+			.await
+			{
+				Ok((value, errors)) if errors.is_empty() => json!({ "data": value }),
+				Ok((value, errors)) => json!({ "data": value, "errors": errors }),
+				Err(e) => {
+					send(
+						&mut session,
+						json!({ "type": "error", "id": id, "payload": [e] }),
+					)
+					.await;
+					subscriptions.borrow_mut().remove(&id);
+
+					return;
+				}
+			};
+
+			send(
+				&mut session,
+				json!({ "type": "next", "id": id, "payload": payload }),
+			)
+			.await;
+		}
+		Err(e) => {
+			send(
+				&mut session,
+				json!({ "type": "error", "id": id, "payload": [e] }),
+			)
+			.await;
+			subscriptions.borrow_mut().remove(&id);
+
+			return;
+		}
+	}
+
+	send(&mut session, json!({ "type": "complete", "id": id })).await;
+	subscriptions.borrow_mut().remove(&id);
+}
@@ -0,0 +1,279 @@
+use std::sync::{Arc, Mutex, MutexGuard, Once};
+
+use juniper::http::GraphQLRequest;
+use lazy_static::lazy_static;
+use serde_json::{json, Value as JsonValue};
+
+use crate::api::auth::Claims;
+use crate::api::schema::context::Context;
+use crate::api::schema::{schema, Schemas};
+use crate::lib::database::backend::set_backend;
+use crate::lib::database::build_db_map;
+use crate::lib::database::mock::MockBackend;
+
+/// The settings the configuration requires, set when the tests run without them
+const TEST_ENVIRONMENT: [(&str, &str); 5] = [
+	("DB_HOST", "http://localhost:8529"),
+	("DB_USER", "root"),
+	("DB_PASS", ""),
+	("DB_NAME", "alchemy_test"),
+	("APP_PORT", "8080"),
+];
+
+static INIT: Once = Once::new();
+
+lazy_static! {
+	/// The backend is global to the process, the harnesses of parallel tests take turns
+	static ref HARNESS_LOCK: Mutex<()> = Mutex::new(());
+}
+
+/// Runs GraphQL documents in-process against a schema built from fixture metadata, the queries
+/// are answered by a mock backend
+pub struct TestHarness {
+	pub schemas: Schemas,
+	pub backend: Arc<MockBackend>,
+	_lock: MutexGuard<'static, ()>,
+}
+
+impl TestHarness {
+	/// `collections` and `edges` are entries as stored in `alchemy_collections` and
+	/// `alchemy_edges`, the mock becomes the backend of the process until the harness is dropped
+	pub fn new(collections: Vec<JsonValue>, edges: Vec<JsonValue>) -> Self {
+		INIT.call_once(|| {
+			for (key, value) in TEST_ENVIRONMENT {
+				if std::env::var_os(key).is_none() {
+					std::env::set_var(key, value);
+				}
+			}
+
+			pluralizer::initialize();
+		});
+
+		// A test that panicked poisons the lock, the next one still gets a fresh backend
+		let lock = HARNESS_LOCK.lock().unwrap_or_else(|e| e.into_inner());
+		let backend = Arc::new(MockBackend::new());
+
+		set_backend(backend.clone());
+
+		TestHarness {
+			schemas: schema(build_db_map(&collections, &edges), Vec::new()),
+			backend,
+			_lock: lock,
+		}
+	}
+
+	/// Execute a document with the roles, or unrestricted with `None`, and return the response
+	pub async fn execute(
+		&self,
+		document: &str,
+		variables: Option<JsonValue>,
+		roles: Option<Vec<String>>,
+	) -> JsonValue {
+		let request: GraphQLRequest = serde_json::from_value(json!({
+			"query": document,
+			"variables": variables,
+		}))
+		.expect("Invalid GraphQL request");
+
+		let context = Context::new(roles.map(|roles| Claims {
+			roles,
+			..Default::default()
+		}));
+
+		let schema = self.schemas.for_roles(context.roles());
+		let response = request.execute(&schema, &context).await;
+
+		serde_json::to_value(&response).expect("Failed to encode the response")
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::{json, Value as JsonValue};
+
+	use super::TestHarness;
+
+	fn books() -> JsonValue {
+		json!({
+			"name": "books",
+			"schema": {
+				"properties": {
+					"title": { "type": "string" },
+					"published": { "type": "boolean" },
+				},
+				"required": ["title"],
+				"additionalProperties": false,
+			},
+			"permissions": [
+				{ "role": "editor", "operations": ["get", "getAll", "create"] },
+				{ "role": "reader", "operations": ["get", "getAll"] },
+			],
+			"rowFilters": [{ "role": "reader", "filter": "published == true" }],
+		})
+	}
+
+	fn last_query(harness: &TestHarness) -> String {
+		harness
+			.backend
+			.queries()
+			.last()
+			.map(|query| query.query.clone())
+			.unwrap_or_default()
+	}
+
+	#[actix_web::test]
+	async fn get_returns_the_document() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness
+			.backend
+			.respond("FOR i_1 IN @@collection", vec![json!({ "title": "Dune" })]);
+
+		let response = harness
+			.execute(r#"{ getBook(id: "1") { title } }"#, None, None)
+			.await;
+
+		assert_eq!(response["data"]["getBook"]["title"], "Dune");
+
+		let queries = harness.backend.queries();
+
+		assert_eq!(queries.len(), 1);
+		assert_eq!(queries[0].bind_vars["@collection"], "books");
+		assert_eq!(queries[0].bind_vars["arg_1_id"], "1");
+	}
+
+	#[actix_web::test]
+	async fn get_reports_a_missing_document() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		let response = harness
+			.execute(r#"{ getBook(id: "1") { title } }"#, None, None)
+			.await;
+
+		assert!(response["data"]["getBook"].is_null());
+		assert!(!response["errors"].as_array().unwrap().is_empty());
+	}
+
+	#[actix_web::test]
+	async fn get_all_returns_every_document() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness.backend.respond(
+			"FOR i_1 IN @@collection",
+			vec![json!({ "title": "Dune" }), json!({ "title": "Emma" })],
+		);
+
+		let response = harness
+			.execute("{ getAllBooks { title } }", None, None)
+			.await;
+
+		assert_eq!(
+			response["data"]["getAllBooks"],
+			json!([{ "title": "Dune" }, { "title": "Emma" }])
+		);
+	}
+
+	#[actix_web::test]
+	async fn get_all_rejects_a_negative_limit() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		let response = harness
+			.execute("{ getAllBooks(limit: -1) { title } }", None, None)
+			.await;
+
+		assert!(response["data"]["getAllBooks"].is_null());
+		assert!(response["errors"][0]["message"]
+			.as_str()
+			.unwrap()
+			.contains("must not be negative"));
+		assert!(harness.backend.queries().is_empty());
+	}
+
+	#[actix_web::test]
+	async fn create_inserts_the_input() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness.backend.respond(
+			"INSERT",
+			vec![json!({ "key": "1", "document": { "title": "Dune" } })],
+		);
+
+		let response = harness
+			.execute(
+				"mutation($data: BookInput!) { createBook(data: $data) { title } }",
+				Some(json!({ "data": { "title": "Dune" } })),
+				Some(vec![String::from("editor")]),
+			)
+			.await;
+
+		assert_eq!(response["data"]["createBook"]["title"], "Dune");
+
+		let query = harness.backend.queries().pop().unwrap();
+
+		assert!(query.query.contains("INSERT"));
+		assert_eq!(query.bind_vars["arg_1_data"]["title"], "Dune");
+	}
+
+	#[actix_web::test]
+	async fn create_reports_a_failed_insert() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness.backend.fail("INSERT", 1200, "write-write conflict");
+
+		let response = harness
+			.execute(
+				r#"mutation { createBook(data: { title: "Dune" }) { title } }"#,
+				None,
+				Some(vec![String::from("editor")]),
+			)
+			.await;
+
+		assert!(response["data"]["createBook"].is_null());
+		assert!(!response["errors"].as_array().unwrap().is_empty());
+	}
+
+	#[actix_web::test]
+	async fn roles_without_permission_cannot_create() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		let response = harness
+			.execute(
+				r#"mutation { createBook(data: { title: "Dune" }) { title } }"#,
+				None,
+				Some(vec![String::from("reader")]),
+			)
+			.await;
+
+		assert!(!response["errors"].as_array().unwrap().is_empty());
+		assert!(harness
+			.backend
+			.queries()
+			.iter()
+			.all(|query| !query.query.contains("INSERT")));
+	}
+
+	#[actix_web::test]
+	async fn row_filters_restrict_the_documents_of_their_role() {
+		let harness = TestHarness::new(vec![books()], Vec::new());
+
+		harness
+			.execute(
+				"{ getAllBooks { title } }",
+				None,
+				Some(vec![String::from("reader")]),
+			)
+			.await;
+
+		assert!(last_query(&harness).contains("`published` == true"));
+
+		harness
+			.execute(
+				"{ getAllBooks { title } }",
+				None,
+				Some(vec![String::from("editor")]),
+			)
+			.await;
+
+		assert!(!last_query(&harness).contains("`published`"));
+	}
+}
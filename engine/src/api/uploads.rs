@@ -0,0 +1,194 @@
+use std::collections::HashMap;
+use std::future::Future;
+
+use actix_multipart::Multipart;
+use actix_web::{
+	error::{ErrorBadRequest, ErrorInternalServerError, ErrorPayloadTooLarge},
+	web::Payload as ActixPayload,
+	Error as ActixError, HttpRequest as ActixRequest,
+};
+use futures::StreamExt;
+use serde_json::Value as JsonValue;
+
+use crate::lib::uploads::{delete_upload, store_upload, upload_id, UploadedFile};
+use crate::lib::CONFIG;
+
+/// Files stored for a request, keyed by the ID substituted in its variables
+pub type Uploads = HashMap<String, UploadedFile>;
+
+/// A file read from a multipart request, only stored once the request is accepted
+pub struct PendingUpload {
+	id: String,
+	filename: String,
+	mimetype: String,
+	content: Vec<u8>,
+}
+
+tokio::task_local! {
+	static UPLOADS: Uploads;
+}
+
+/// Run a future with the uploads of the request available to the `Upload` scalar
+pub async fn scope_uploads<F: Future>(uploads: Uploads, f: F) -> F::Output {
+	UPLOADS.scope(uploads, f).await
+}
+
+/// The upload sent with the current request under the given ID
+pub fn get_upload(id: &str) -> Option<UploadedFile> {
+	UPLOADS
+		.try_with(|uploads| uploads.get(id).cloned())
+		.ok()
+		.flatten()
+}
+
+/// Replace the value at a dotted path such as `variables.files.0`
+fn set_path(value: &mut JsonValue, path: &str, replacement: JsonValue) -> bool {
+	let mut current = value;
+
+	for segment in path.split('.') {
+		current = match current {
+			JsonValue::Object(map) => match map.get_mut(segment) {
+				Some(next) => next,
+				None => return false,
+			},
+			JsonValue::Array(items) => {
+				match segment.parse::<usize>().ok().and_then(|i| items.get_mut(i)) {
+					Some(next) => next,
+					None => return false,
+				}
+			}
+			_ => return false,
+		};
+	}
+
+	*current = replacement;
+
+	true
+}
+
+async fn read_field(
+	field: &mut actix_multipart::Field,
+	limit: usize,
+) -> Result<Vec<u8>, ActixError> {
+	let mut content = Vec::new();
+
+	while let Some(chunk) = field.next().await {
+		let chunk = chunk?;
+
+		if content.len() + chunk.len() > limit {
+			return Err(ErrorPayloadTooLarge("Upload is too large"));
+		}
+
+		content.extend_from_slice(&chunk);
+	}
+
+	Ok(content)
+}
+
+/// Read a GraphQL multipart request, substituting the IDs of its files in the operations, the
+/// files are kept in memory until the request is validated and authorized
+pub async fn read_multipart_request(
+	req: &ActixRequest,
+	payload: ActixPayload,
+) -> Result<(JsonValue, Vec<PendingUpload>), ActixError> {
+	let mut multipart = Multipart::new(req.headers(), payload);
+
+	let mut operations: Option<JsonValue> = None;
+	let mut map: HashMap<String, Vec<String>> = HashMap::new();
+	let mut uploads = Vec::new();
+	let mut total_size = 0;
+
+	while let Some(field) = multipart.next().await {
+		let mut field = field?;
+
+		let disposition = field.content_disposition().clone();
+		let name = disposition.get_name().unwrap_or_default().to_string();
+
+		match name.as_str() {
+			"operations" => {
+				let content = read_field(&mut field, CONFIG.max_body_size).await?;
+
+				operations = Some(serde_json::from_slice(&content).map_err(ErrorBadRequest)?);
+			}
+			"map" => {
+				let content = read_field(&mut field, CONFIG.max_body_size).await?;
+
+				map = serde_json::from_slice(&content).map_err(ErrorBadRequest)?;
+			}
+			_ => {
+				// The spec requires the operations and the map to come before any file
+				let (operations, paths) = match (operations.as_mut(), map.get(&name)) {
+					(Some(operations), Some(paths)) => (operations, paths),
+					_ => {
+						return Err(ErrorBadRequest(format!(
+							"Unexpected multipart field {}",
+							name
+						)))
+					}
+				};
+
+				let content = read_field(&mut field, CONFIG.max_upload_size - total_size).await?;
+
+				total_size += content.len();
+
+				let filename = disposition.get_filename().unwrap_or(&name).to_string();
+				let mimetype = field.content_type().to_string();
+
+				let id = upload_id();
+
+				for path in paths {
+					if !set_path(operations, path, JsonValue::String(id.clone())) {
+						return Err(ErrorBadRequest(format!("Invalid upload path {}", path)));
+					}
+				}
+
+				uploads.push(PendingUpload {
+					id,
+					filename,
+					mimetype,
+					content,
+				});
+			}
+		}
+	}
+
+	let operations = operations.ok_or_else(|| ErrorBadRequest("Missing the operations field"))?;
+
+	Ok((operations, uploads))
+}
+
+/// Store the files of an accepted request, those already stored are removed when one fails
+pub async fn store_uploads(pending: Vec<PendingUpload>) -> Result<Uploads, ActixError> {
+	let mut uploads = Uploads::new();
+
+	for upload in pending {
+		match store_upload(
+			upload.id,
+			&upload.filename,
+			&upload.mimetype,
+			upload.content,
+		)
+		.await
+		{
+			Ok(file) => {
+				uploads.insert(file.id.clone(), file);
+			}
+			Err(e) => {
+				discard_uploads(&uploads).await;
+
+				return Err(ErrorInternalServerError(e));
+			}
+		}
+	}
+
+	Ok(uploads)
+}
+
+/// Remove the files of a request that failed, nothing refers to them
+pub async fn discard_uploads(uploads: &Uploads) {
+	for file in uploads.values() {
+		if let Err(e) = delete_upload(file).await {
+			tracing::error!(upload = %file.id, error = %e, "Failed to remove the upload of a failed request");
+		}
+	}
+}
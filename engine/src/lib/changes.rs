@@ -0,0 +1,62 @@
+use futures::Stream;
+use lazy_static::lazy_static;
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::{self, error::RecvError};
+
+use crate::lib::database::aql::AQLMutation;
+
+/// Events buffered for each subscriber before the slowest ones start skipping
+const CHANGES_CAPACITY: usize = 1024;
+
+lazy_static! {
+	static ref CHANGES: broadcast::Sender<ChangeEvent> = broadcast::channel(CHANGES_CAPACITY).0;
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, GraphQLEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOperation {
+	Insert,
+	Update,
+	Remove,
+}
+
+impl From<&AQLMutation> for ChangeOperation {
+	fn from(mutation: &AQLMutation) -> Self {
+		match mutation {
+			AQLMutation::Insert => ChangeOperation::Insert,
+			AQLMutation::Update => ChangeOperation::Update,
+			AQLMutation::Remove => ChangeOperation::Remove,
+		}
+	}
+}
+
+/// A document changed by a mutation
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChangeEvent {
+	/// The collection of the document, prefixed with its tenant when collections are prefixed
+	/// per tenant, so that subscribers only see the changes of their own tenant
+	pub collection: String,
+	pub key: String,
+	pub operation: ChangeOperation,
+}
+
+/// Notify the subscribers of a change, it is dropped when nobody listens
+pub fn publish_change(event: ChangeEvent) {
+	let _ = CHANGES.send(event);
+}
+
+/// Stream of the changes published from now on
+pub fn subscribe_changes() -> impl Stream<Item = ChangeEvent> + Send + 'static {
+	futures::stream::unfold(CHANGES.subscribe(), |mut receiver| async move {
+		loop {
+			match receiver.recv().await {
+				Ok(event) => return Some((event, receiver)),
+				Err(RecvError::Lagged(skipped)) => {
+					tracing::warn!(skipped, "Subscriber lagged behind the change events");
+				}
+				Err(RecvError::Closed) => return None,
+			}
+		}
+	})
+}
@@ -0,0 +1,60 @@
+use clap::{Parser, Subcommand};
+
+/// GraphQL API generated from ArangoDB collections
+#[derive(Parser, Debug)]
+#[clap(name = "alchemy", version)]
+pub struct Cli {
+	/// Configuration file, instead of the one named by `ALCHEMY_CONFIG` or the working directory
+	#[clap(long, global = true)]
+	pub config: Option<String>,
+
+	#[clap(subcommand)]
+	pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+	/// Start the GraphQL server, the default command
+	Serve,
+	/// Apply the pending migrations
+	Migrate,
+	/// Revert the last applied migration
+	Rollback,
+	/// Insert the seed documents
+	Seed,
+	/// Generate the metadata of the collections Alchemy does not expose yet
+	Introspect {
+		/// Only list the collections and whether Alchemy exposes them
+		#[clap(long)]
+		dry_run: bool,
+	},
+	/// Print the SDL of the generated GraphQL schema
+	ExportSchema {
+		/// File to write the SDL to, instead of the standard output
+		#[clap(short, long)]
+		output: Option<String>,
+	},
+	/// Write the collections, metadata and documents to an archive
+	Backup {
+		#[clap(default_value = "alchemy-backup.jsonl")]
+		path: String,
+	},
+	/// Restore an archive written by `backup`
+	Restore {
+		#[clap(default_value = "alchemy-backup.jsonl")]
+		path: String,
+	},
+}
+
+impl Cli {
+	/// Parse the arguments, the configuration file is selected before `CONFIG` is first read
+	pub fn init() -> Self {
+		let cli = Cli::parse();
+
+		if let Some(path) = &cli.config {
+			std::env::set_var("ALCHEMY_CONFIG", path);
+		}
+
+		cli
+	}
+}
@@ -1,62 +1,1033 @@
-use std::{fs::File, io::Read};
+use std::{collections::HashSet, fs::File, io::Read, sync::Mutex, time::Duration};
 
-use anyhow::Result;
+use anyhow::{bail, Context, Result};
+use jsonwebtoken::Algorithm;
 use lazy_static::lazy_static;
 use serde::Deserialize;
+use serde_json::Value as JsonValue;
 
-#[derive(Deserialize, Debug)]
+use crate::lib::secrets::{apply_secret_files, redact, redact_url};
+use crate::lib::uploads::UploadStorage;
+
+#[derive(Deserialize, Derivative)]
+#[derivative(Debug)]
 pub struct Config {
 	pub db_host: String,
 	pub db_user: String,
+	/// Secrets can also be read from a file, see `apply_secret_files`
+	#[derivative(Debug(format_with = "redact"))]
 	pub db_pass: String,
 	pub db_name: String,
+	/// Documents fetched per round trip when reading the results of a query through its cursor
+	#[serde(default = "default_cursor_batch_size")]
+	pub cursor_batch_size: u32,
+	/// Queries running longer are logged to the `alchemy::slow_queries` target, in milliseconds
+	pub slow_query_threshold_ms: Option<u64>,
+	/// Log the values of the bind variables of the slow queries rather than their types, those
+	/// of the sensitive fields are redacted
+	#[serde(default)]
+	pub slow_query_log_values: bool,
 
+	#[serde(default)]
 	pub rust_env: String,
+	/// Profile bundling the defaults of an environment, `RUST_ENV` selects it when unset
+	pub alchemy_env: Option<Profile>,
 
+	#[serde(default = "default_app_host")]
+	pub app_host: String,
 	pub app_port: String,
+	/// Prefix of every route, e.g. `/alchemy`
+	#[serde(default)]
+	pub app_base_path: String,
+	/// Number of HTTP workers, defaults to the number of CPU cores
+	pub app_workers: Option<usize>,
+
+	/// Keep-alive of idle connections in seconds, 0 disables it
+	pub keep_alive_secs: Option<u64>,
+	/// Time allowed for a client to send the request headers, in milliseconds
+	pub client_request_timeout_ms: Option<u64>,
+	/// Time allowed for a client to acknowledge the connection shutdown, in milliseconds
+	pub client_disconnect_timeout_ms: Option<u64>,
+	/// Grace period of the in-flight requests on shutdown, in seconds
+	pub shutdown_timeout_secs: Option<u64>,
+
+	/// Listen on this Unix socket instead of TCP
+	pub unix_socket_path: Option<String>,
+	/// Octal permissions of the socket, e.g. `660`
+	pub unix_socket_mode: Option<String>,
+
+	/// PEM certificate chain, HTTPS is served when both TLS paths are set
+	pub tls_cert_path: Option<String>,
+	pub tls_key_path: Option<String>,
+
+	#[derivative(Debug(format_with = "redact"))]
+	pub admin_secret: Option<String>,
+
+	/// Shared secret, or PEM public key with an asymmetric algorithm, verifying the JWTs
+	#[derivative(Debug(format_with = "redact"))]
+	pub jwt_secret: Option<String>,
+	/// JWKS document providing the keys verifying the JWTs
+	pub jwt_jwks_url: Option<String>,
+	/// Defaults to HS256 with a secret and RS256 with a JWKS
+	pub jwt_algorithm: Option<Algorithm>,
+	pub jwt_issuer: Option<String>,
+	pub jwt_audience: Option<String>,
+	#[serde(default = "default_jwt_user_id_claim")]
+	pub jwt_user_id_claim: String,
+	#[serde(default = "default_jwt_roles_claim")]
+	pub jwt_roles_claim: String,
+	#[serde(default = "default_jwt_tenant_claim")]
+	pub jwt_tenant_claim: String,
+	/// Namespace the collections per tenant, e.g. `acme_books`, the requests without a tenant
+	/// claim are rejected, the collections of each tenant are created by the operator
+	#[serde(default)]
+	pub tenant_prefix_enabled: bool,
+	/// The JWKS is fetched again after this long, so that rotated keys are dropped
+	#[serde(default = "default_jwt_jwks_max_age_secs")]
+	pub jwt_jwks_max_age_secs: u64,
+
+	/// OpenID Connect provider, its discovery document gives the issuer and the JWKS
+	pub oidc_issuer_url: Option<String>,
+	/// Expected audience of the ID tokens when no JWT audience is set
+	pub oidc_client_id: Option<String>,
+	/// Roles granted from the claims, written as `claim:value=role`, e.g. `groups:admins=admin`
+	pub oidc_role_mappings: Option<Vec<String>>,
+
+	/// Operations per role, written as `role:operation|operation`, `*` matching every role or
+	/// operation, e.g. a denylist of `*:deleteUser` with an allowlist of `admin:*`
+	pub operation_allowlist: Option<Vec<String>>,
+	pub operation_denylist: Option<Vec<String>>,
+
+	/// Service answering with the claims of a request, whose headers it receives,
+	/// the other ways to authenticate are not used when it is set
+	pub auth_webhook_url: Option<String>,
+	#[serde(default)]
+	pub auth_webhook_method: AuthWebhookMethod,
+	#[serde(default = "default_auth_webhook_timeout_ms")]
+	pub auth_webhook_timeout_ms: u64,
+
+	/// Role of the requests without credentials, they are rejected when unset
+	pub anonymous_role: Option<String>,
+
+	/// Static API keys, written as `key:role|role:expires_at:tenant` with optional roles, expiry
+	/// and tenant
+	#[derivative(Debug(format_with = "redact"))]
+	pub api_keys: Option<Vec<String>>,
+	/// Also accept the API keys stored in the `alchemy_api_keys` collection
+	#[serde(default)]
+	pub api_keys_from_database: bool,
+
+	/// Record every mutation of the generated API in the `alchemy_audit` collection
+	#[serde(default)]
+	pub audit_log_enabled: bool,
+	/// Properties of the mutation arguments whose values are not recorded, at any depth
+	#[serde(default = "default_audit_redacted_arguments")]
+	pub audit_redacted_arguments: Vec<String>,
+
+	/// Retries of the event trigger deliveries, delayed by an exponential backoff
+	#[serde(default = "default_trigger_max_retries")]
+	pub trigger_max_retries: u32,
+	#[serde(default = "default_trigger_retry_base_ms")]
+	pub trigger_retry_base_ms: u64,
+	#[serde(default = "default_trigger_timeout_ms")]
+	pub trigger_timeout_ms: u64,
+
+	/// `kafka` or `nats`, publish an event for every successful mutation, Alchemy must be built
+	/// with the feature of the broker
+	pub event_broker: Option<String>,
+	/// Kafka bootstrap servers or NATS server URL
+	#[derivative(Debug(format_with = "redact_url"))]
+	pub event_broker_url: Option<String>,
+	/// Prepended to the collection name to get the topic or subject of its events
+	#[serde(default = "default_event_topic_prefix")]
+	pub event_topic_prefix: String,
+	/// Write the events in the `alchemy_outbox` collection within the transaction of the mutation,
+	/// a background relay publishes them at least once
+	#[serde(default)]
+	pub event_outbox: bool,
+	#[serde(default = "default_event_outbox_interval_ms")]
+	pub event_outbox_interval_ms: u64,
+	#[serde(default = "default_event_outbox_batch_size")]
+	pub event_outbox_batch_size: u32,
+
+	/// Follow the WAL of the database to publish the changes of every writer to the subscriptions,
+	/// instead of the mutations of this instance only
+	#[serde(default)]
+	pub change_feed_enabled: bool,
+	#[serde(default = "default_change_feed_interval_ms")]
+	pub change_feed_interval_ms: u64,
+
+	/// Seconds between the polls of the due scheduled events, 0 disables the scheduler
+	#[serde(default = "default_scheduler_interval_secs")]
+	pub scheduler_interval_secs: u64,
+	/// Seconds a claimed event stays locked to its instance, others take it over afterwards
+	#[serde(default = "default_scheduler_lock_secs")]
+	pub scheduler_lock_secs: u64,
+
+	/// Key encrypting the session cookies, at least 32 bytes, sessions are disabled when unset
+	#[derivative(Debug(format_with = "redact"))]
+	pub session_secret: Option<String>,
+	#[serde(default = "default_session_cookie_name")]
+	pub session_cookie_name: String,
+	#[serde(default = "default_session_max_age_secs")]
+	pub session_max_age_secs: u64,
+	/// Collection of the users logging in, their passwords are stored as Argon2 hashes
+	#[serde(default = "default_session_users_collection")]
+	pub session_users_collection: String,
+	#[serde(default = "default_session_username_property")]
+	pub session_username_property: String,
+	#[serde(default = "default_session_password_property")]
+	pub session_password_property: String,
+	#[serde(default = "default_session_roles_property")]
+	pub session_roles_property: String,
+	/// Path of the `/login` and `/logout` routes, they open and close sessions for the clients
+	/// without credentials, which the GraphQL API refuses unless there is an anonymous role
+	#[serde(default = "default_session_path")]
+	pub session_path: String,
+
+	/// Maximum request body size in bytes
+	#[serde(default = "default_max_body_size")]
+	pub max_body_size: usize,
+
+	/// Encodings of the compressed responses, `br`, `zstd`, `gzip` or `deflate`, the first one
+	/// the client accepts is used, none disables the compression
+	#[serde(default = "default_compression_algorithms")]
+	pub compression_algorithms: Vec<String>,
+	/// Responses smaller than this are sent uncompressed, in bytes
+	#[serde(default = "default_compression_min_size")]
+	pub compression_min_size: u64,
+	/// Content types sent uncompressed, matched by prefix, e.g. `image/`
+	#[serde(default = "default_compression_excluded_types")]
+	pub compression_excluded_types: Vec<String>,
+	/// Maximum length in characters and lexical tokens of a GraphQL document
+	#[serde(default = "default_graphql_max_document_length")]
+	pub graphql_max_document_length: usize,
+	#[serde(default = "default_graphql_max_tokens")]
+	pub graphql_max_tokens: usize,
+	/// Maximum nesting of selection sets, relationships make deep documents expensive
+	#[serde(default = "default_graphql_max_depth")]
+	pub graphql_max_depth: Option<usize>,
+	/// Budget of the estimated cost of a document, which is not analyzed when unset
+	pub graphql_max_cost: Option<u64>,
+	/// Only log the documents over budget instead of rejecting them
+	#[serde(default)]
+	pub graphql_cost_log_only: bool,
+	/// Costs of fields and arguments, e.g. `getAllUsers=5,User.posts=2,getAllUsers(search)=10`
+	pub graphql_costs: Option<Vec<String>>,
+	/// Templates of the names of the generated operations, e.g. `get={singular},getAll={plural}`,
+	/// the placeholders are `{singular}`, `{plural}` and `{field}`, capitalized for PascalCase
+	pub graphql_operation_names: Option<Vec<String>>,
+	/// Items assumed for the list fields without a limit
+	#[serde(default = "default_graphql_default_list_size")]
+	pub graphql_default_list_size: u64,
+	/// Limit of the list operations called without one, the whole collection is read when unset
+	pub graphql_list_default_limit: Option<i32>,
+	/// Largest limit of the list operations, larger ones are rejected
+	pub graphql_list_max_limit: Option<i32>,
+	/// Lower the larger limits to the maximum with a warning, instead of rejecting them
+	#[serde(default)]
+	pub graphql_list_clamp_limit: bool,
+	/// Return the list operations as pages of `items` with their `pageInfo`, read from the
+	/// `after` cursor, instead of plain lists
+	#[serde(default)]
+	pub graphql_page_info: bool,
+	/// Time allowed to resolve a generated operation, in milliseconds, it is not limited when unset
+	pub graphql_resolver_timeout_ms: Option<u64>,
+	/// Time allowed to execute a whole GraphQL request, in milliseconds, the resolvers still
+	/// pending are dropped along with their queries when it runs out
+	pub graphql_request_timeout_ms: Option<u64>,
+	/// Write the documents of a query selecting a single list to the response as the cursor
+	/// returns them, rather than once they are all read, for the POST requests that are neither
+	/// cached, traced nor limited in time. The response is 200 once written, a query failing
+	/// halfway leaves the documents already sent, followed by its errors
+	#[serde(default)]
+	pub graphql_stream_lists: bool,
+	/// Return the timings of the resolvers and of the AQL queries in `extensions.tracing`, in
+	/// the Apollo tracing format, for debugging only as it exposes the generated queries
+	#[serde(default)]
+	pub graphql_tracing: bool,
+	/// Analyzer normalizing the values compared by `_ieq` and `_icontains`, e.g. a `norm`
+	/// analyzer with `accent: false` to also ignore diacritics, they are only lowercased when unset
+	pub graphql_filter_analyzer: Option<String>,
+	/// Locale of the localized fields when none of the languages of a request is translated,
+	/// any translation is returned otherwise
+	pub default_locale: Option<String>,
+
+	/// Arrays of operations sent in a single request, optionally executed concurrently
+	#[serde(default = "default_true")]
+	pub graphql_batch_enabled: bool,
+	#[serde(default = "default_graphql_max_batch_size")]
+	pub graphql_max_batch_size: usize,
+	#[serde(default)]
+	pub graphql_batch_concurrent: bool,
+
+	/// Send the fragments marked with `@defer` and the lists marked with `@stream` after the
+	/// initial payload, to the clients accepting `multipart/mixed`, the directives are otherwise
+	/// ignored
+	#[serde(default = "default_true")]
+	pub graphql_incremental_enabled: bool,
+
+	/// Cache-Control header of successful GET queries, e.g. `public, max-age=60`
+	pub graphql_get_cache_control: Option<String>,
+
+	/// External GraphQL endpoints whose root fields are merged into the API, written as `name=url`
+	pub remote_schemas: Option<Vec<String>>,
+	/// Headers of a request sent along with the fields proxied to the remote schemas
+	#[serde(default = "default_remote_schema_forward_headers")]
+	pub remote_schema_forward_headers: Vec<String>,
+	#[serde(default = "default_remote_schema_timeout_ms")]
+	pub remote_schema_timeout_ms: u64,
+	/// Timeout of the requests to the action endpoints, unless the action sets its own
+	#[serde(default = "default_action_timeout_ms")]
+	pub action_timeout_ms: u64,
+
+	/// Routes of the API, relative to the base path, e.g. `/api/v1/graphql`
+	#[serde(default = "default_graphql_path")]
+	pub graphql_path: String,
+	#[serde(default = "default_graphql_subscriptions_path")]
+	pub graphql_subscriptions_path: String,
+
+	/// Interval of the pings sent on the subscription connections, in seconds, 0 disables them,
+	/// a connection silent for two intervals is closed
+	#[serde(default = "default_subscriptions_keep_alive_secs")]
+	pub subscriptions_keep_alive_secs: u64,
+	/// Operations running at once on a subscription connection
+	#[serde(default = "default_subscriptions_max_per_connection")]
+	pub subscriptions_max_per_connection: usize,
+	/// Connections running no operation are closed once silent for longer, in seconds, 0
+	/// disables it
+	#[serde(default = "default_subscriptions_idle_timeout_secs")]
+	pub subscriptions_idle_timeout_secs: u64,
+
+	/// In-browser IDE, always disabled in production
+	pub graphql_ide: Option<GraphQLIde>,
+	/// Queries of `__schema` and `__type`, disabled by default in production
+	pub graphql_introspection: Option<bool>,
+	#[serde(default = "default_graphql_ide_path")]
+	pub graphql_ide_path: String,
+	/// Route of the generated SDL, served like introspection
+	#[serde(default = "default_graphql_sdl_path")]
+	pub graphql_sdl_path: String,
+
+	/// REST routes of the generated operations, e.g. `GET /api/books/{key}`
+	#[serde(default)]
+	pub rest_enabled: bool,
+	#[serde(default = "default_rest_path")]
+	pub rest_path: String,
+	/// Route of the OpenAPI document of the REST and admin routes, served like the SDL
+	#[serde(default = "default_openapi_path")]
+	pub openapi_path: String,
+	/// Serve the histograms of the generated operations in the Prometheus format
+	#[serde(default)]
+	pub metrics_enabled: bool,
+	#[serde(default = "default_metrics_path")]
+	pub metrics_path: String,
+	/// Port of the gRPC services generated from the entities, requires the `grpc` feature
+	pub grpc_port: Option<u16>,
+	/// Route of the `.proto` of the gRPC services, served like the SDL
+	#[serde(default = "default_grpc_proto_path")]
+	pub grpc_proto_path: String,
+
+	/// In-process cache of query responses, invalidated by the mutations of their collections
+	#[serde(default)]
+	pub response_cache_enabled: bool,
+	#[serde(default = "default_response_cache_ttl_secs")]
+	pub response_cache_ttl_secs: u64,
+	/// TTL overrides per root field, e.g. `getAllUsers=10,getUser=30`
+	pub response_cache_ttls: Option<Vec<String>>,
+	#[serde(default = "default_response_cache_max_entries")]
+	pub response_cache_max_entries: usize,
+
+	/// Where the results of the operations cached through the metadata are kept, `memory` or
+	/// `redis`, which requires the `redis-cache` feature
+	#[serde(default = "default_query_cache_backend")]
+	pub query_cache_backend: String,
+	#[derivative(Debug(format_with = "redact_url"))]
+	pub query_cache_redis_url: Option<String>,
+	#[serde(default = "default_query_cache_max_entries")]
+	pub query_cache_max_entries: usize,
+
+	/// Automatic persisted queries, documents are cached by their SHA-256 hash
+	#[serde(default = "default_true")]
+	pub apq_enabled: bool,
+	#[serde(default = "default_apq_cache_size")]
+	pub apq_cache_size: usize,
+	/// Also store the documents registered by clients in the persisted queries collection, so
+	/// that every instance finds the hashes learned by the others
+	#[serde(default)]
+	pub apq_shared: bool,
+
+	/// Only execute the documents registered in the persisted queries path or collection
+	#[serde(default)]
+	pub persisted_queries_only: bool,
+	#[serde(default = "default_persisted_queries_path")]
+	pub persisted_queries_path: String,
+
+	/// Where the files of multipart requests are stored, `database`, `directory` or `s3`
+	#[serde(default)]
+	pub upload_storage: UploadStorage,
+	#[serde(default = "default_upload_directory")]
+	pub upload_directory: String,
+	/// Maximum size in bytes of all the files of a request
+	#[serde(default = "default_max_upload_size")]
+	pub max_upload_size: usize,
+	pub s3_bucket: Option<String>,
+	#[serde(default = "default_s3_region")]
+	pub s3_region: String,
+	/// Endpoint of S3 compatible services such as MinIO
+	pub s3_endpoint: Option<String>,
+	#[derivative(Debug(format_with = "redact"))]
+	pub s3_access_key: Option<String>,
+	#[derivative(Debug(format_with = "redact"))]
+	pub s3_secret_key: Option<String>,
+
+	/// Requests allowed per rate limit period on the GraphQL API, unlimited when unset
+	pub rate_limit_per_ip: Option<u32>,
+	/// Requests allowed per verified API key, a refused key is charged to the IP of the client
+	pub rate_limit_per_api_key: Option<u32>,
+	#[serde(default = "default_rate_limit_period_secs")]
+	pub rate_limit_period_secs: u64,
+	/// Budgets of the authenticated clients per role, written as `role:requests:rows_per_day`
+	/// with optional parts, e.g. `anonymous:30:10000`, a role without budget is not limited
+	pub rate_limit_per_role: Option<Vec<String>>,
+
+	/// Comma separated CORS lists, `*` allows any origin
+	pub cors_allowed_origins: Option<Vec<String>>,
+	pub cors_allowed_methods: Option<Vec<String>>,
+	pub cors_allowed_headers: Option<Vec<String>>,
+	/// Defaults to true in development only
+	pub cors_allow_credentials: Option<bool>,
+	#[serde(default = "default_cors_max_age")]
+	pub cors_max_age: usize,
+
+	/// Default log filter, e.g. `info` or `alchemy=debug`, overridden by RUST_LOG
+	#[serde(default = "default_log_level")]
+	pub log_level: String,
+	/// Defaults to pretty logs in development and JSON logs otherwise
+	pub log_format: Option<LogFormat>,
+	/// Log every GraphQL operation to the `alchemy::operations` target, along with its variables
+	#[serde(default)]
+	pub log_operations: bool,
+	/// Fields whose values are redacted from the logs, besides the properties marked sensitive
+	#[serde(default = "default_log_redacted_fields")]
+	pub log_redacted_fields: Vec<String>,
+
+	/// Send the reasons of rejected requests and the messages of internal errors to the clients,
+	/// defaults to true in development
+	pub error_details: Option<bool>,
+
+	/// How often the configuration file is checked for changes, `0` only reloads it on SIGHUP
+	#[serde(default = "default_config_watch_interval_secs")]
+	pub config_watch_interval_secs: u64,
+
+	/// OTLP collector receiving the traces, e.g. `http://localhost:4317`
+	pub otlp_endpoint: Option<String>,
+	#[serde(default = "default_otel_service_name")]
+	pub otel_service_name: String,
+
+	/// YAML file declaring the collections and relationships, reconciled on startup
+	pub schema_path: Option<String>,
+
+	/// Generate the metadata of the untracked collections on startup, like `alchemy introspect`
+	#[serde(default)]
+	pub auto_introspect: bool,
+	/// Documents sampled to infer the properties of a collection without validation schema
+	#[serde(default = "default_introspection_sample_size")]
+	pub introspection_sample_size: usize,
+	/// Compare the metadata with the collections, indexes and validation schemas on startup, the
+	/// drift is logged
+	#[serde(default = "default_true")]
+	pub schema_check: bool,
+	/// Have the database explain the queries of every generated operation on startup and reload,
+	/// the server does not start and the schema is not reloaded when one is invalid
+	#[serde(default = "default_true")]
+	pub preflight_operations: bool,
+
+	#[serde(default)]
+	pub auto_migrate: bool,
+	#[serde(default = "default_migrations_path")]
+	pub migrations_path: String,
+
+	#[serde(default)]
+	pub auto_seed: bool,
+	#[serde(default = "default_seeds_path")]
+	pub seeds_path: String,
+}
+
+/// Environment whose defaults apply to the settings left unset
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum Profile {
+	/// IDE, introspection, pretty logs, detailed errors and any CORS origin
+	Development,
+	/// Like production, with the IDE and introspection left enabled
+	Staging,
+	/// No IDE nor introspection, JSON logs, terse errors and only the configured CORS origins
+	Production,
+}
+
+/// Output format of the logs
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+	Pretty,
+	Json,
+}
+
+/// In-browser IDE served next to the GraphQL endpoints
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum GraphQLIde {
+	GraphiQL,
+	Playground,
+	Disabled,
+}
+
+/// How the headers of a request are sent to the auth webhook
+#[derive(Deserialize, Debug, Clone, Copy, PartialEq, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthWebhookMethod {
+	/// As the headers of a GET request
+	#[default]
+	Get,
+	/// As a `{ "headers": {...} }` JSON body
+	Post,
+}
+
+fn default_auth_webhook_timeout_ms() -> u64 {
+	5000
+}
+
+fn default_trigger_max_retries() -> u32 {
+	5
+}
+
+fn default_trigger_retry_base_ms() -> u64 {
+	1000
+}
+
+fn default_trigger_timeout_ms() -> u64 {
+	10000
+}
+
+fn default_event_topic_prefix() -> String {
+	String::from("alchemy.")
+}
+
+fn default_event_outbox_interval_ms() -> u64 {
+	1000
+}
+
+fn default_event_outbox_batch_size() -> u32 {
+	100
+}
+
+fn default_change_feed_interval_ms() -> u64 {
+	1000
+}
+
+fn default_scheduler_interval_secs() -> u64 {
+	10
+}
+
+fn default_scheduler_lock_secs() -> u64 {
+	300
+}
+
+fn default_remote_schema_forward_headers() -> Vec<String> {
+	vec![String::from("authorization")]
+}
+
+fn default_action_timeout_ms() -> u64 {
+	10000
+}
+
+fn default_remote_schema_timeout_ms() -> u64 {
+	10000
+}
+
+fn default_graphql_path() -> String {
+	String::from("/api/graphql")
+}
+
+fn default_graphql_subscriptions_path() -> String {
+	String::from("/api/graphql_subscriptions")
+}
+
+fn default_subscriptions_keep_alive_secs() -> u64 {
+	30
+}
+
+fn default_subscriptions_max_per_connection() -> usize {
+	100
+}
+
+fn default_subscriptions_idle_timeout_secs() -> u64 {
+	300
+}
+
+fn default_graphql_ide_path() -> String {
+	String::from("/api/playground")
+}
+
+fn default_graphql_sdl_path() -> String {
+	String::from("/schema.graphql")
+}
+
+fn default_rest_path() -> String {
+	String::from("/api")
+}
+
+fn default_openapi_path() -> String {
+	String::from("/openapi.json")
+}
+
+fn default_metrics_path() -> String {
+	String::from("/metrics")
+}
+
+fn default_grpc_proto_path() -> String {
+	String::from("/alchemy.proto")
+}
+
+fn default_jwt_user_id_claim() -> String {
+	String::from("sub")
+}
+
+fn default_jwt_roles_claim() -> String {
+	String::from("roles")
+}
+
+fn default_jwt_tenant_claim() -> String {
+	String::from("tenant")
+}
+
+fn default_jwt_jwks_max_age_secs() -> u64 {
+	3600
+}
+
+fn default_log_level() -> String {
+	String::from("info")
+}
+
+fn default_config_watch_interval_secs() -> u64 {
+	5
+}
+
+fn default_app_host() -> String {
+	String::from("0.0.0.0")
+}
+
+fn default_cursor_batch_size() -> u32 {
+	1000
+}
+
+fn default_max_body_size() -> usize {
+	1024 * 1024
+}
+
+fn default_compression_algorithms() -> Vec<String> {
+	vec![
+		String::from("br"),
+		String::from("zstd"),
+		String::from("gzip"),
+	]
+}
+
+fn default_compression_min_size() -> u64 {
+	1024
+}
+
+/// Already compressed media, and the multipart and event streams whose parts would be held
+/// back by the encoder
+fn default_compression_excluded_types() -> Vec<String> {
+	vec![
+		String::from("image/"),
+		String::from("video/"),
+		String::from("audio/"),
+		String::from("multipart/mixed"),
+		String::from("text/event-stream"),
+	]
+}
+
+fn default_graphql_max_document_length() -> usize {
+	100_000
+}
+
+fn default_graphql_max_tokens() -> usize {
+	10_000
+}
+
+fn default_graphql_max_depth() -> Option<usize> {
+	Some(15)
+}
+
+fn default_graphql_default_list_size() -> u64 {
+	100
+}
+
+fn default_true() -> bool {
+	true
+}
+
+fn default_graphql_max_batch_size() -> usize {
+	10
+}
+
+fn default_response_cache_ttl_secs() -> u64 {
+	60
+}
+
+fn default_query_cache_backend() -> String {
+	String::from("memory")
+}
+
+fn default_query_cache_max_entries() -> usize {
+	10_000
+}
+
+fn default_response_cache_max_entries() -> usize {
+	1000
+}
+
+fn default_apq_cache_size() -> usize {
+	1000
+}
+
+fn default_persisted_queries_path() -> String {
+	String::from("queries")
+}
+
+fn default_upload_directory() -> String {
+	String::from("uploads")
+}
+
+fn default_max_upload_size() -> usize {
+	10 * 1024 * 1024
+}
+
+fn default_s3_region() -> String {
+	String::from("us-east-1")
+}
+
+fn default_rate_limit_period_secs() -> u64 {
+	60
+}
+
+fn default_cors_max_age() -> usize {
+	3600
+}
+
+fn default_otel_service_name() -> String {
+	String::from("alchemy")
+}
+
+fn default_introspection_sample_size() -> usize {
+	100
+}
+
+fn default_migrations_path() -> String {
+	String::from("migrations")
+}
+
+fn default_log_redacted_fields() -> Vec<String> {
+	vec![
+		String::from("password"),
+		String::from("secret"),
+		String::from("token"),
+	]
+}
+
+fn default_audit_redacted_arguments() -> Vec<String> {
+	vec![
+		String::from("password"),
+		String::from("secret"),
+		String::from("token"),
+	]
+}
+
+fn default_session_cookie_name() -> String {
+	String::from("alchemy_session")
+}
+
+fn default_session_max_age_secs() -> u64 {
+	86400
+}
+
+fn default_session_users_collection() -> String {
+	String::from("users")
+}
+
+fn default_session_username_property() -> String {
+	String::from("email")
+}
+
+fn default_session_password_property() -> String {
+	String::from("password")
+}
+
+fn default_session_roles_property() -> String {
+	String::from("roles")
+}
+
+fn default_session_path() -> String {
+	String::from("/auth")
+}
+
+fn default_seeds_path() -> String {
+	String::from("seeds")
 }
 
 impl Config {
+	/// The selected profile, `RUST_ENV` is used when `ALCHEMY_ENV` is unset
+	pub fn profile(&self) -> Profile {
+		self.alchemy_env
+			.unwrap_or_else(|| match self.rust_env.as_str() {
+				"production" => Profile::Production,
+				"staging" => Profile::Staging,
+				_ => Profile::Development,
+			})
+	}
+
 	pub fn is_production(&self) -> bool {
-		self.rust_env == "production"
+		self.profile() == Profile::Production
 	}
 
 	pub fn is_development(&self) -> bool {
-		!self.is_production()
+		self.profile() == Profile::Development
+	}
+
+	/// Requests are authenticated once a way to verify them or an anonymous role is configured,
+	/// the ones without credentials are rejected unless they can use the anonymous role
+	pub fn auth_enabled(&self) -> bool {
+		self.jwt_secret.is_some()
+			|| self.jwt_jwks_url.is_some()
+			|| self.oidc_issuer_url.is_some()
+			|| self.api_keys.is_some()
+			|| self.api_keys_from_database
+			|| self.anonymous_role.is_some()
+			|| self.auth_webhook_url.is_some()
+			|| self.sessions_enabled()
+	}
+
+	/// Whether the login and logout mutations manage cookie sessions
+	pub fn sessions_enabled(&self) -> bool {
+		self.session_secret.is_some()
+	}
+
+	/// The IDE to serve, Playground by default in development
+	pub fn graphql_ide(&self) -> GraphQLIde {
+		if self.is_production() {
+			GraphQLIde::Disabled
+		} else {
+			self.graphql_ide.unwrap_or(GraphQLIde::Playground)
+		}
+	}
+
+	pub fn introspection_enabled(&self) -> bool {
+		self.graphql_introspection
+			.unwrap_or_else(|| !self.is_production())
+	}
+
+	pub fn log_format(&self) -> LogFormat {
+		self.log_format.unwrap_or(if self.is_development() {
+			LogFormat::Pretty
+		} else {
+			LogFormat::Json
+		})
+	}
+
+	pub fn error_details(&self) -> bool {
+		self.error_details.unwrap_or_else(|| self.is_development())
+	}
+
+	pub fn rate_limit_period(&self) -> Duration {
+		Duration::from_secs(self.rate_limit_period_secs.max(1))
+	}
+
+	/// The normalized base path, empty or starting with a slash and without a trailing one
+	pub fn base_path(&self) -> String {
+		let path = self.app_base_path.trim_matches('/');
+
+		if path.is_empty() {
+			String::new()
+		} else {
+			format!("/{}", path)
+		}
+	}
+
+	/// Prefix a route with the base path
+	pub fn path(&self, route: &str) -> String {
+		format!("{}{}", self.base_path(), route)
+	}
+}
+
+/// Sections of the configuration file, they only group the settings
+const CONFIG_FILE_SECTIONS: [&str; 5] = ["server", "database", "auth", "cors", "limits"];
+
+/// Files looked up when `ALCHEMY_CONFIG` does not name one
+const CONFIG_FILE_NAMES: [&str; 3] = ["alchemy.toml", "alchemy.yaml", "alchemy.yml"];
+
+/// Read the TOML or YAML configuration file, whose sections hold settings named like the
+/// environment variables, e.g. `db_host` in `[database]`
+fn read_config_file(path: &str) -> Result<Vec<(String, String)>> {
+	let content = std::fs::read_to_string(path)
+		.with_context(|| format!("Failed to read the configuration file {}", path))?;
+
+	let file: JsonValue = if path.ends_with(".toml") {
+		toml::from_str(&content).with_context(|| format!("Invalid TOML in {}", path))?
+	} else {
+		serde_yaml::from_str(&content).with_context(|| format!("Invalid YAML in {}", path))?
+	};
+
+	let sections = match file {
+		JsonValue::Object(sections) => sections,
+		JsonValue::Null => return Ok(Vec::new()),
+		_ => bail!("{} must contain sections of settings", path),
+	};
+
+	let mut settings = Vec::new();
+
+	for (section, values) in sections {
+		if !CONFIG_FILE_SECTIONS.contains(&section.as_str()) {
+			bail!(
+				"Unknown section {} in {}, expected one of {}",
+				section,
+				path,
+				CONFIG_FILE_SECTIONS.join(", ")
+			);
+		}
+
+		let values = match values {
+			JsonValue::Object(values) => values,
+			_ => bail!("Section {} of {} must contain settings", section, path),
+		};
+
+		for (key, value) in values {
+			let value = match value {
+				JsonValue::String(value) => value,
+				JsonValue::Number(_) | JsonValue::Bool(_) => value.to_string(),
+				// Lists are read from comma separated values
+				JsonValue::Array(items) => items
+					.iter()
+					.map(|item| match item {
+						JsonValue::String(item) => item.clone(),
+						item => item.to_string(),
+					})
+					.collect::<Vec<_>>()
+					.join(","),
+				JsonValue::Null => continue,
+				JsonValue::Object(_) => {
+					bail!(
+						"Setting {}.{} of {} must not be a table",
+						section,
+						key,
+						path
+					)
+				}
+			};
+
+			settings.push((key.to_uppercase(), value));
+		}
+	}
+
+	Ok(settings)
+}
+
+/// The configuration file, named by `ALCHEMY_CONFIG` or found in the working directory
+pub fn config_file_path() -> Option<String> {
+	match std::env::var("ALCHEMY_CONFIG") {
+		Ok(path) => Some(path),
+		Err(_) => CONFIG_FILE_NAMES
+			.iter()
+			.find(|name| std::path::Path::new(name).exists())
+			.map(|name| name.to_string()),
+	}
+}
+
+/// Set the variables of the configuration file which the environment does not set already,
+/// the ones it set before are updated and removed along with the file
+fn apply_config_file() -> Result<()> {
+	let path = match config_file_path() {
+		Some(path) => path,
+		None => return Ok(()),
+	};
+
+	let settings = read_config_file(&path)?;
+	let mut file_keys = CONFIG_FILE_KEYS.lock().unwrap();
+
+	for key in file_keys.iter() {
+		if !settings.iter().any(|(setting, _)| setting == key) {
+			std::env::remove_var(key);
+		}
+	}
+
+	file_keys.retain(|key| settings.iter().any(|(setting, _)| setting == key));
+
+	for (key, value) in settings {
+		if file_keys.contains(&key) || std::env::var_os(&key).is_none() {
+			std::env::set_var(&key, value);
+			file_keys.insert(key);
+		}
 	}
+
+	Ok(())
+}
+
+/// Read the settings again after the configuration file changed
+pub fn reload_config() -> Result<Config> {
+	apply_config_file()?;
+	apply_secret_files()?;
+
+	envy::from_env::<Config>().context("Invalid settings")
 }
 
+/// Settings are read from the environment, then from `.env` when they are incomplete,
+/// over the values of the configuration file
 fn load_config() -> Result<Config> {
+	apply_config_file()?;
+	apply_secret_files()?;
+
 	let env = envy::from_env::<Config>();
 
 	match env {
 		Ok(config) => Ok(config),
-		Err(_) => {
+		Err(e) => {
 			// Load from .env file
-			let mut file = File::open(".env")?;
+			let mut file =
+				File::open(".env").with_context(|| format!("{}, and there is no .env file", e))?;
 			let mut content = String::new();
 
 			file.read_to_string(&mut content)?;
 
-			for line in content.lines() {
+			for (index, line) in content.lines().enumerate() {
 				let pair = line.split('=').collect::<Vec<&str>>();
 
+				// The line is not reported since it may hold a secret
 				let (key, value) = match &pair[..] {
 					&[key, value] => (key, value),
-					_ => panic!("Expected env variable pairs, got {}", content),
+					_ => bail!(
+						"Expected an env variable pair on line {} of .env",
+						index + 1
+					),
 				};
 
-				std::env::set_var(key, value);
+				// Leave empty values unset so that defaults apply
+				if !value.is_empty() {
+					std::env::set_var(key, value);
+				}
 			}
 
-			match envy::from_env::<Config>() {
-				Ok(config) => Ok(config),
-				Err(e) => panic!("Failed to read the config from env: {}", e),
-			}
+			apply_secret_files()?;
+
+			envy::from_env::<Config>().context("Invalid settings")
 		}
 	}
 }
 
 lazy_static! {
-	pub static ref CONFIG: Config = load_config().unwrap();
+	/// Variables set from the configuration file rather than by the environment
+	static ref CONFIG_FILE_KEYS: Mutex<HashSet<String>> = Mutex::new(HashSet::new());
+	pub static ref CONFIG: Config =
+		load_config().unwrap_or_else(|e| panic!("Invalid configuration: {:#}", e));
 }
@@ -0,0 +1,47 @@
+use actix_cors::Cors;
+use actix_web::http::header::{self, HeaderValue};
+
+use crate::lib::reload::runtime;
+use crate::lib::CONFIG;
+
+/// Whether the origin is allowed by the current settings, any origin is allowed in development
+/// unless origins are configured while production only allows the configured ones
+fn is_origin_allowed(origin: &HeaderValue) -> bool {
+	match &runtime().cors_allowed_origins {
+		Some(origins) => origins
+			.iter()
+			.any(|allowed| allowed == "*" || origin.as_bytes() == allowed.as_bytes()),
+		None => CONFIG.is_development(),
+	}
+}
+
+/// Build the CORS policy, the allowed origins are read on every request so that they can be reloaded
+pub fn build_cors() -> Cors {
+	let mut cors = Cors::default()
+		.max_age(CONFIG.cors_max_age)
+		.allowed_origin_fn(|origin, _| is_origin_allowed(origin));
+
+	cors = match &CONFIG.cors_allowed_methods {
+		Some(methods) => cors.allowed_methods(methods.iter().map(|m| m.as_str())),
+		None => cors.allowed_methods(vec!["POST", "GET"]),
+	};
+
+	cors = match &CONFIG.cors_allowed_headers {
+		Some(headers) => cors.allowed_headers(headers.iter().map(|h| h.as_str())),
+		// The CSRF token of the sessions and the API keys are sent by browsers of other origins too
+		None => cors
+			.allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])
+			.allowed_header(header::CONTENT_TYPE)
+			.allowed_header("x-csrf-token")
+			.allowed_header("x-api-key"),
+	};
+
+	if CONFIG
+		.cors_allow_credentials
+		.unwrap_or_else(|| CONFIG.is_development())
+	{
+		cors = cors.supports_credentials();
+	}
+
+	cors
+}
@@ -5,7 +5,14 @@ use std::fmt::Formatter;
 use std::ops::Deref;
 use std::sync::Arc;
 
-use crate::lib::schema::{get_all_collections, get_all_edges};
+use crate::lib::changes::ChangeOperation;
+use crate::lib::schema::{
+	decimal_scale, get_all_collections, get_all_edges, get_search_view_name, parse_row_filter,
+	FieldComparison, RowFilterCondition, SchemaCacheOptions, SchemaCheck, SchemaDirectives,
+	SchemaExpiryOptions, SchemaFieldPermission, SchemaOperation, SchemaOperationDirective,
+	SchemaPermission, SchemaReference, SchemaRowFilter, SchemaSearchOptions, SchemaTimeLimit,
+	SchemaTrigger, SchemaUniqueConstraint, SchemaValidation, TIMESTAMP_PROPERTIES,
+};
 
 const ERR_CHILD_NOT_DEFINED: &str = "ERROR: Child type not defined";
 const ERR_UNDEFINED_TYPE: &str = "ERROR: Undefined associated SDL type";
@@ -119,6 +126,8 @@ pub struct DbRelationship {
 	pub to: Arc<DbEntity>,
 	pub relationship_type: DbRelationshipType,
 	pub direction: DbRelationshipDirection,
+	/// The SmartGraph of the edges, traversed through the graph rather than the edge collection
+	pub graph: Option<String>,
 }
 
 #[derive(Clone, PartialEq, Debug)]
@@ -126,6 +135,148 @@ pub struct DbEntity {
 	pub name: String,
 	pub collection_name: String,
 	pub properties: Vec<DbProperty>,
+	pub search_view: Option<DbSearchView>,
+	/// Mutations are recorded in the history collection
+	pub audit: bool,
+	/// Updates and removals keep the previous state of the document in the versions collection
+	pub versioning: bool,
+	/// `createdAt` and `updatedAt` are set on mutations
+	pub timestamps: bool,
+	/// Only the properties can be written, the other attributes are rejected
+	pub strict: bool,
+	/// An edge collection, its documents link two documents through `_from` and `_to`
+	pub edge: bool,
+	pub expiry: Option<DbExpiry>,
+	/// TTLs of the cached results of the read operations
+	pub cache: Vec<SchemaCacheOptions>,
+	/// Runtimes after which the queries of the operations are killed
+	pub time_limits: Vec<SchemaTimeLimit>,
+	/// Operations granted per role, every operation is allowed when unset
+	pub permissions: Option<Vec<SchemaPermission>>,
+	/// Properties readable per role, a role without entry reads every property
+	pub field_permissions: Option<Vec<SchemaFieldPermission>>,
+	/// Rows visible per role, a role without filter sees every row
+	pub row_filters: Option<Vec<DbRowFilter>>,
+	/// Names of the generated operations replacing the derived ones
+	pub operation_names: Vec<SchemaOperationDirective>,
+	/// Names of one and several documents in the generated operations, derived from the entity
+	/// name when unset
+	pub singular_name: Option<String>,
+	pub plural_name: Option<String>,
+	/// Operations generated for the entity, all of them when unset
+	pub operations: Option<Vec<SchemaOperation>>,
+	/// Webhooks receiving the changes of the documents
+	pub triggers: Vec<SchemaTrigger>,
+	/// Comparisons of properties the written documents must satisfy
+	pub validations: Vec<FieldComparison>,
+	/// Combinations of properties backed by unique indexes
+	pub unique: Vec<SchemaUniqueConstraint>,
+	/// Properties holding the key of a document of another collection
+	pub references: Vec<SchemaReference>,
+	/// AQL expressions the written documents must satisfy
+	pub checks: Vec<SchemaCheck>,
+}
+
+/// The parsed row filter of a role
+#[derive(Clone, PartialEq, Debug)]
+pub struct DbRowFilter {
+	pub role: String,
+	/// `None` when the stored template is invalid, no row is visible then
+	pub conditions: Option<Vec<RowFilterCondition>>,
+}
+
+impl DbEntity {
+	/// Whether one of the roles is granted the operation
+	pub fn is_allowed(&self, roles: &[String], operation: SchemaOperation) -> bool {
+		match &self.permissions {
+			Some(permissions) => permissions.iter().any(|permission| {
+				roles.contains(&permission.role) && permission.operations.contains(&operation)
+			}),
+			None => true,
+		}
+	}
+
+	/// The properties the roles can read, `None` when one of them is not restricted
+	pub fn allowed_fields(&self, roles: &[String]) -> Option<Vec<&str>> {
+		let permissions = self.field_permissions.as_ref()?;
+		let mut fields = Vec::new();
+
+		for role in roles {
+			let permission = permissions
+				.iter()
+				.find(|permission| &permission.role == role)?;
+
+			fields.extend(permission.fields.iter().map(String::as_str));
+		}
+
+		Some(fields)
+	}
+
+	/// How long the results of the operation are cached, `None` when they are not
+	pub fn cache_ttl(&self, operation: SchemaOperation) -> Option<std::time::Duration> {
+		self.cache
+			.iter()
+			.find(|cache| cache.operation == operation && cache.ttl_secs > 0)
+			.map(|cache| std::time::Duration::from_secs(cache.ttl_secs as u64))
+	}
+
+	/// How long the queries of the operation may run, `None` when they are not limited
+	pub fn max_runtime(&self, operation: SchemaOperation) -> Option<std::time::Duration> {
+		self.time_limits
+			.iter()
+			.find(|limit| limit.operation == operation && limit.max_runtime_ms > 0)
+			.map(|limit| std::time::Duration::from_millis(limit.max_runtime_ms as u64))
+	}
+
+	/// Whether the operation is generated, whatever the roles
+	pub fn is_enabled(&self, operation: SchemaOperation) -> bool {
+		self.operations
+			.as_ref()
+			.map(|operations| operations.contains(&operation))
+			.unwrap_or(true)
+	}
+
+	/// The triggers the change is delivered to
+	pub fn triggers_for(&self, operation: ChangeOperation) -> Vec<SchemaTrigger> {
+		self.triggers
+			.iter()
+			.filter(|trigger| trigger.operations.contains(&operation))
+			.cloned()
+			.collect()
+	}
+
+	/// The renamed operation, `None` when it keeps its derived name
+	pub fn operation_name(&self, operation: SchemaOperation) -> Option<&str> {
+		self.operation_names
+			.iter()
+			.find(|directive| directive.operation == operation)
+			.map(|directive| directive.rename.as_str())
+	}
+
+	/// The property exposed as a GraphQL field
+	pub fn property_by_field(&self, field_name: &str) -> Option<&DbProperty> {
+		self.properties
+			.iter()
+			.find(|property| property.field_name() == field_name)
+	}
+}
+
+/// The TTL configuration of an entity
+#[derive(Clone, PartialEq, Debug)]
+pub struct DbExpiry {
+	pub property: String,
+	/// Seconds after the property value when the document expires
+	pub expire_after: u32,
+	/// Filter out expired documents the TTL index has not purged yet
+	pub hide_expired: bool,
+}
+
+/// The ArangoSearch view linked to an entity
+#[derive(Clone, PartialEq, Debug)]
+pub struct DbSearchView {
+	pub name: String,
+	pub fields: Vec<String>,
+	pub analyzers: Vec<String>,
 }
 
 #[derive(Default, Clone, PartialEq, Debug)]
@@ -134,6 +285,69 @@ pub struct DbProperty {
 	pub associated_type: Option<String>,
 	pub scalar_type: DbScalarType,
 	pub required: bool,
+	/// Set by the server, never part of mutation inputs
+	pub managed: bool,
+	/// Name of the GraphQL field when it differs from the property
+	pub rename: Option<String>,
+	/// Readable but never part of mutation inputs
+	pub readonly: bool,
+	/// Its values are redacted from the logs
+	pub sensitive: bool,
+	/// The rules of the stored schema, also checked by the engine before writing
+	pub rules: DbPropertyRules,
+}
+
+/// The validation rules of a property, as sent to ArangoDB, enums are checked by GraphQL
+#[derive(Default, Clone, PartialEq, Debug)]
+pub struct DbPropertyRules {
+	pub min_length: Option<i32>,
+	pub max_length: Option<i32>,
+	pub pattern: Option<String>,
+	pub minimum: Option<f64>,
+	pub maximum: Option<f64>,
+	/// The bounds themselves are not allowed
+	pub exclusive_minimum: bool,
+	pub exclusive_maximum: bool,
+	pub multiple_of: Option<f64>,
+	/// Largest numeric item of an array
+	pub item_maximum: Option<f64>,
+	/// Regular expression the string items of an array must match
+	pub item_pattern: Option<String>,
+	/// Significant digits of the decimals, and those of them after the point
+	pub precision: Option<i32>,
+	pub scale: Option<i32>,
+}
+
+impl DbPropertyRules {
+	fn from_rule(rule: &Value) -> Self {
+		let length = |key: &str| rule[key].as_i64().map(|length| length as i32);
+
+		DbPropertyRules {
+			min_length: length("minLength"),
+			max_length: length("maxLength"),
+			pattern: rule["pattern"].as_str().map(String::from),
+			minimum: rule["minimum"].as_f64(),
+			maximum: rule["maximum"].as_f64(),
+			exclusive_minimum: rule["exclusiveMinimum"].as_bool().unwrap_or(false),
+			exclusive_maximum: rule["exclusiveMaximum"].as_bool().unwrap_or(false),
+			multiple_of: rule["multipleOf"].as_f64(),
+			item_maximum: rule["items"]["maximum"].as_f64(),
+			item_pattern: rule["items"]["pattern"].as_str().map(String::from),
+			precision: length("precision"),
+			scale: length("scale"),
+		}
+	}
+
+	/// The digits after the point of the stored decimals
+	pub fn decimal_scale(&self) -> Option<usize> {
+		decimal_scale(self.precision, self.scale)
+	}
+}
+
+impl DbProperty {
+	pub fn field_name(&self) -> &str {
+		self.rename.as_deref().unwrap_or(&self.name)
+	}
 }
 
 #[derive(PartialEq, Default, Clone, Debug)]
@@ -146,6 +360,23 @@ pub enum DbScalarType {
 	Float,
 	Int,
 	Boolean,
+	DateTime,
+	File,
+	GeoPoint,
+	GeoPolygon,
+	Decimal,
+	Localized,
+}
+
+impl DbScalarType {
+	/// The GeoJSON type of the geometries stored by the property, if it is a geo property
+	pub fn geometry_type(&self) -> Option<&'static str> {
+		match self {
+			DbScalarType::GeoPoint => Some("Point"),
+			DbScalarType::GeoPolygon => Some("Polygon"),
+			_ => None,
+		}
+	}
 }
 
 impl From<JsonType> for DbScalarType {
@@ -158,6 +389,12 @@ impl From<JsonType> for DbScalarType {
 			JsonType::Number => DbScalarType::Float,
 			JsonType::Object => DbScalarType::Object,
 			JsonType::String => DbScalarType::String,
+			JsonType::DateTime => DbScalarType::DateTime,
+			JsonType::File => DbScalarType::File,
+			JsonType::GeoPoint => DbScalarType::GeoPoint,
+			JsonType::GeoPolygon => DbScalarType::GeoPolygon,
+			JsonType::Decimal => DbScalarType::Decimal,
+			JsonType::Localized => DbScalarType::Localized,
 		}
 	}
 }
@@ -171,20 +408,27 @@ pub enum JsonType {
 	Number,
 	Object,
 	String,
+	DateTime,
+	File,
+	GeoPoint,
+	GeoPolygon,
+	Decimal,
+	Localized,
 }
 
 pub async fn generate_sdl() -> DbMap {
-	let collections = get_all_collections().await;
-	let edges = get_all_edges().await;
+	build_db_map(&get_all_collections().await, &get_all_edges().await)
+}
 
+/// Build the entities and relationships from the entries of `alchemy_collections` and
+/// `alchemy_edges`, without reading the database
+pub fn build_db_map(collections: &[Value], edges: &[Value]) -> DbMap {
 	let mut sdl: DbMap = DbMap::new();
 	let mut collections_by_keys: HashMap<String, Arc<DbEntity>> = HashMap::new();
 
-	println!("----- SDL GENERATION -----");
-
 	let time = std::time::Instant::now();
 
-	for entry in collections.clone().iter() {
+	for entry in collections.iter() {
 		let collection_name = entry["name"].as_str().unwrap().to_string();
 
 		let type_name = pluralizer::pluralize(
@@ -202,10 +446,24 @@ pub async fn generate_sdl() -> DbMap {
 			.map(|v| v.as_str().unwrap().to_string())
 			.collect();
 
+		let timestamps = entry["timestamps"].as_bool().unwrap_or(false);
+
+		let directives = entry
+			.get("directives")
+			.and_then(|directives| {
+				serde_json::from_value::<SchemaDirectives>(directives.clone()).ok()
+			})
+			.unwrap_or_default();
+
 		let mut props: Vec<DbProperty> = Vec::new();
 
 		for prop in entry_properties.as_object().unwrap().iter() {
 			let prop_name = prop.0.clone();
+			let directive = directives.field(&prop_name);
+
+			if directive.and_then(|d| d.hidden).unwrap_or(false) {
+				continue;
+			}
 
 			let json_type = build_json_type(prop.1);
 			let scalar_type: DbScalarType = json_type.clone().into();
@@ -237,14 +495,141 @@ pub async fn generate_sdl() -> DbMap {
 				associated_type,
 				scalar_type,
 				required: entry_required_properties.contains(&prop_name),
+				managed: timestamps && TIMESTAMP_PROPERTIES.contains(&prop_name.as_str()),
+				rename: directive.and_then(|d| d.rename.clone()),
+				readonly: directive.and_then(|d| d.readonly).unwrap_or(false),
+				sensitive: directive.and_then(|d| d.sensitive).unwrap_or(false),
+				rules: DbPropertyRules::from_rule(prop.1),
 				..Default::default()
 			});
 		}
 
+		let search_view = entry
+			.get("search")
+			.and_then(|search| serde_json::from_value::<SchemaSearchOptions>(search.clone()).ok())
+			.map(|search| DbSearchView {
+				name: get_search_view_name(&collection_name),
+				analyzers: search.get_analyzers(),
+				fields: search.fields,
+			});
+
+		let expiry = entry
+			.get("expiry")
+			.and_then(|expiry| serde_json::from_value::<SchemaExpiryOptions>(expiry.clone()).ok())
+			.map(|expiry| DbExpiry {
+				expire_after: expiry.get_expire_after() as u32,
+				hide_expired: expiry.hide_expired.unwrap_or(false),
+				property: expiry.property,
+			});
+
+		let cache = entry
+			.get("cache")
+			.and_then(|cache| serde_json::from_value::<Vec<SchemaCacheOptions>>(cache.clone()).ok())
+			.unwrap_or_default();
+
+		let time_limits = entry
+			.get("timeLimits")
+			.and_then(|limits| serde_json::from_value::<Vec<SchemaTimeLimit>>(limits.clone()).ok())
+			.unwrap_or_default();
+
+		let permissions = entry.get("permissions").and_then(|permissions| {
+			serde_json::from_value::<Vec<SchemaPermission>>(permissions.clone()).ok()
+		});
+
+		let field_permissions = entry.get("fieldPermissions").and_then(|permissions| {
+			serde_json::from_value::<Vec<SchemaFieldPermission>>(permissions.clone()).ok()
+		});
+
+		let operations = entry.get("operations").and_then(|operations| {
+			serde_json::from_value::<Vec<SchemaOperation>>(operations.clone()).ok()
+		});
+
+		let triggers = entry
+			.get("triggers")
+			.and_then(|triggers| {
+				serde_json::from_value::<Vec<SchemaTrigger>>(triggers.clone()).ok()
+			})
+			.unwrap_or_default();
+
+		let validations = entry
+			.get("validations")
+			.and_then(|validations| {
+				serde_json::from_value::<Vec<SchemaValidation>>(validations.clone()).ok()
+			})
+			.unwrap_or_default()
+			.iter()
+			.filter_map(|validation| {
+				FieldComparison::parse(validation)
+					.map_err(
+						|e| tracing::error!(collection = %collection_name, error = %e, "Invalid validation rule"),
+					)
+					.ok()
+			})
+			.collect();
+
+		let unique = entry
+			.get("unique")
+			.and_then(|unique| {
+				serde_json::from_value::<Vec<SchemaUniqueConstraint>>(unique.clone()).ok()
+			})
+			.unwrap_or_default();
+
+		let references = entry
+			.get("references")
+			.and_then(|references| {
+				serde_json::from_value::<Vec<SchemaReference>>(references.clone()).ok()
+			})
+			.unwrap_or_default();
+
+		let checks = entry
+			.get("checks")
+			.and_then(|checks| serde_json::from_value::<Vec<SchemaCheck>>(checks.clone()).ok())
+			.unwrap_or_default();
+
+		let row_filters = entry
+			.get("rowFilters")
+			.and_then(|filters| {
+				serde_json::from_value::<Vec<SchemaRowFilter>>(filters.clone()).ok()
+			})
+			.map(|filters| {
+				filters
+					.into_iter()
+					.map(|filter| DbRowFilter {
+						conditions: parse_row_filter(&filter.filter)
+							.map_err(|e| {
+								tracing::error!(collection = %collection_name, role = %filter.role, error = %e, "Invalid row filter")
+							})
+							.ok(),
+						role: filter.role,
+					})
+					.collect()
+			});
+
 		let entity = Arc::new(DbEntity {
 			name: type_name,
 			collection_name: collection_name.clone(),
 			properties: props,
+			search_view,
+			audit: entry["audit"].as_bool().unwrap_or(false),
+			versioning: entry["versioning"].as_bool().unwrap_or(false),
+			timestamps,
+			strict: entry["schema"]["additionalProperties"] == false,
+			edge: entry["options"]["edge"].as_bool().unwrap_or(false),
+			expiry,
+			cache,
+			time_limits,
+			permissions,
+			field_permissions,
+			row_filters,
+			operation_names: directives.operations.unwrap_or_default(),
+			singular_name: directives.singular,
+			plural_name: directives.plural,
+			operations,
+			triggers,
+			validations,
+			unique,
+			references,
+			checks,
 		});
 
 		// We insert it on this hash map for future use of relationships
@@ -253,7 +638,7 @@ pub async fn generate_sdl() -> DbMap {
 		sdl.primitives.push(DbPrimitive::Entity(entity.clone()))
 	}
 
-	for entry in edges.clone().iter() {
+	for entry in edges.iter() {
 		let prop_name = entry["name"].as_str().unwrap();
 		let edge = entry["edge"].as_str().unwrap();
 		let from = entry["from"].as_str().unwrap();
@@ -272,12 +657,16 @@ pub async fn generate_sdl() -> DbMap {
 				to: to_entity.clone(),
 				relationship_type,
 				direction: relationship_direction,
+				graph: entry["graph"].as_str().map(String::from),
 			})
 		}
 	}
 
-	println!("----- SDL GENERATED in {:?} -----", time.elapsed());
-	println!("{}", sdl);
+	tracing::info!(
+		duration_ms = time.elapsed().as_millis() as u64,
+		"SDL generated"
+	);
+	tracing::debug!(sdl = %sdl, "Generated SDL");
 
 	sdl
 }
@@ -299,7 +688,13 @@ fn build_json_type(json_data: &Value) -> JsonType {
 		"boolean" => JsonType::Boolean,
 		"integer" => JsonType::Integer,
 		"number" => JsonType::Number,
+		"object" if json_data["format"].as_str() == Some("file") => JsonType::File,
+		"object" if json_data["format"].as_str() == Some("geo-point") => JsonType::GeoPoint,
+		"object" if json_data["format"].as_str() == Some("geo-polygon") => JsonType::GeoPolygon,
+		"object" if json_data["format"].as_str() == Some("localized") => JsonType::Localized,
 		"object" => JsonType::Object,
+		"string" if json_data["format"].as_str() == Some("date-time") => JsonType::DateTime,
+		"string" if json_data["format"].as_str() == Some("decimal") => JsonType::Decimal,
 		"string" => JsonType::String,
 		_ => JsonType::String, // This is an unreachable condition
 	}
@@ -312,7 +707,7 @@ fn get_type_body(props: &Vec<DbProperty>) -> String {
 		body.push_str(
 			format!(
 				"\t{}: {}\n",
-				prop.name,
+				prop.field_name(),
 				parse_graphql_prop_type(&prop.scalar_type, !prop.required, &prop.associated_type)
 			)
 			.as_str(),
@@ -337,6 +732,12 @@ fn parse_graphql_prop_type(
 		DbScalarType::Float => with_nullablity("Float", nullable),
 		DbScalarType::Int => with_nullablity("Int", nullable),
 		DbScalarType::Boolean => with_nullablity("Boolean", nullable),
+		DbScalarType::DateTime => with_nullablity("DateTime", nullable),
+		DbScalarType::File => with_nullablity("String", nullable),
+		DbScalarType::GeoPoint => with_nullablity("GeoPoint", nullable),
+		DbScalarType::GeoPolygon => with_nullablity("GeoPolygon", nullable),
+		DbScalarType::Decimal => with_nullablity("Decimal", nullable),
+		DbScalarType::Localized => with_nullablity("String", nullable),
 		DbScalarType::Array(value) => {
 			let mut str_type = String::new();
 
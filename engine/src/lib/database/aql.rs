@@ -2,20 +2,87 @@ use serde_json::Value;
 use std::collections::HashMap;
 
 use crate::lib::database::api::DbRelationshipDirection;
+use crate::lib::schema::{RowFilterOperator, SchemaCheck, SchemaReference};
+use crate::lib::tenants::tenant_collection;
 
 pub struct AQLQueryRelationship {
 	pub edge: String,
 	pub direction: DbRelationshipDirection,
 	pub variable_name: String,
+	/// The SmartGraph the edge collection is part of
+	pub graph: Option<String>,
+}
+
+impl AQLQueryRelationship {
+	/// The edges traversed, the graph is restricted to the edge collection so that the other
+	/// relationships of the graph are not followed
+	fn describe_edges(&self) -> String {
+		match &self.graph {
+			Some(graph) => format!(
+				"GRAPH {} OPTIONS {{ edgeCollections: [{}] }}",
+				Value::from(graph.as_str()),
+				Value::from(self.edge.as_str())
+			),
+			None => self.edge.clone(),
+		}
+	}
+}
+
+/// The data modification performed by a mutation query
+pub enum AQLMutation {
+	Insert,
+	Update,
+	Remove,
+}
+
+pub struct AQLSort {
+	pub property: String,
+	/// Sort the numbers stored as strings, e.g. decimals, by their value
+	pub numeric: bool,
+	pub descending: bool,
+	/// Put the documents without a value first or last, wherever AQL sorts them when unset
+	pub nulls_first: Option<bool>,
 }
 
 pub struct AQLQuery<'a> {
 	pub properties: Vec<AQLProperty>,
 	pub filter: Option<Box<dyn AQLNode>>,
+	/// Values bound to the query that do not come from the GraphQL arguments
 	pub parameters: HashMap<&'a str, Value>,
+	/// Relationships are traversed in subqueries of the parent query, the related documents of
+	/// every parent come back with it in a single round trip, so lists do not need a loader
 	pub relations: HashMap<String, AQLQuery<'a>>,
 	pub limit: Option<i32>,
+	/// Documents skipped before the limit
+	pub offset: Option<u64>,
+	pub sort: Vec<AQLSort>,
+	/// Sort by the distance to a point, before the other sorts
+	pub near: Option<AQLGeoNear>,
+	/// Sort in a random order, instead of the other sorts
+	pub random: bool,
+	/// Return the number of matching documents instead of the documents
+	pub count: bool,
 	pub relationship: Option<AQLQueryRelationship>,
+	pub mutation: Option<AQLMutation>,
+	/// Record every mutation in the `@@history` collection
+	pub history: bool,
+	/// Keep the state replaced by an update or a removal in the `@@versions` collection
+	pub versions: bool,
+	/// Read the state of the document `id` at the time `at`, from the `@@versions` collection
+	/// or the document itself, instead of the documents of the collection
+	pub as_of: bool,
+	/// Set `createdAt` and `updatedAt` on mutations
+	pub timestamps: bool,
+	/// Return the document before and after a mutation as `old` and `new`
+	pub changes: bool,
+	/// Write the event of a mutation in the `@@outbox` collection, in the same transaction
+	pub outbox: bool,
+	/// The tenant whose collections are queried, when collections are prefixed per tenant
+	pub tenant: Option<String>,
+	/// Properties of the written document whose referenced document must exist
+	pub references: Vec<SchemaReference>,
+	/// Expressions the written document, bound to `doc`, must satisfy
+	pub checks: Vec<SchemaCheck>,
 
 	pub id: u32,
 }
@@ -28,44 +95,245 @@ impl<'a> AQLQuery<'a> {
 			parameters: HashMap::new(),
 			relations: HashMap::new(),
 			limit: None,
+			offset: None,
+			sort: Vec::new(),
+			near: None,
+			random: false,
+			count: false,
 			relationship: None,
+			mutation: None,
+			history: false,
+			versions: false,
+			as_of: false,
+			timestamps: false,
+			changes: false,
+			outbox: false,
+			tenant: None,
+			references: Vec::new(),
+			checks: Vec::new(),
 			id,
 		}
 	}
 
 	pub fn to_aql(&self) -> String {
-		if let Some(ref r) = self.relationship {
+		if let Some(ref m) = self.mutation {
+			self.describe_mutation(m)
+		} else if let Some(ref r) = self.relationship {
 			format!(
-				"FOR {} IN {} {} {} {} {} RETURN {}",
+				"FOR {} IN {} {} {} {} {} {} RETURN {}",
 				self.get_variable_name(),
 				r.direction.to_string(),
 				r.variable_name,
-				r.edge,
+				r.describe_edges(),
+				self.describe_filter(),
+				self.describe_sort(),
+				self.describe_limit(),
+				self.describe_parameters()
+			)
+		} else if self.as_of {
+			format!(
+				"{} FOR {} IN {} {} {} RETURN {}",
+				self.describe_as_of(),
+				self.get_variable_name(),
+				self.describe_as_of_state(),
 				self.describe_filter(),
 				self.describe_limit(),
 				self.describe_parameters()
 			)
+		} else if self.count {
+			format!(
+				"FOR {} IN @@collection {} COLLECT WITH COUNT INTO count RETURN count",
+				self.get_variable_name(),
+				self.describe_filter()
+			)
 		} else {
 			format!(
-				"FOR {} IN @@collection {} {} RETURN {}",
+				"FOR {} IN @@collection {} {} {} RETURN {}",
 				self.get_variable_name(),
 				self.describe_filter(),
+				self.describe_sort(),
 				self.describe_limit(),
 				self.describe_parameters()
 			)
 		}
 	}
 
+	fn describe_mutation(&self, mutation: &AQLMutation) -> String {
+		let id = AQLQueryBind("id").describe(self.id);
+		let mut data = AQLQueryBind("data").describe(self.id);
+
+		// The referenced documents are checked in the query, so that they cannot be removed
+		// between the check and the write
+		let references = self
+			.references
+			.iter()
+			.map(|reference| {
+				format!(
+					"LET {variable}_{property} = ASSERT({data}.`{property}` == null || DOCUMENT({collection}, {data}.`{property}`) != null, {message})",
+					variable = self.get_variable_name(),
+					data = data,
+					property = reference.property,
+					collection = Value::from(tenant_collection(self.tenant.as_deref(), &reference.collection)),
+					message = Value::from(reference.assertion())
+				)
+			})
+			.collect::<Vec<String>>()
+			.join(" ");
+
+		// The checks see the document as it will be stored
+		let checks = if self.checks.is_empty() {
+			"".to_string()
+		} else {
+			let document = match mutation {
+				AQLMutation::Update => format!(
+					"MERGE(FIRST(FOR d IN @@collection FILTER d._key == {} RETURN d), {})",
+					id, data
+				),
+				_ => data.clone(),
+			};
+
+			format!(
+				"LET doc = {} {}",
+				document,
+				self.checks
+					.iter()
+					.map(|check| format!(
+						"LET {}_{} = ASSERT({}, {})",
+						self.get_variable_name(),
+						check.name,
+						check.expression,
+						Value::from(check.assertion())
+					))
+					.collect::<Vec<String>>()
+					.join(" ")
+			)
+		};
+
+		if self.timestamps {
+			data = match mutation {
+				AQLMutation::Insert => format!(
+					"MERGE({}, {{ createdAt: DATE_ISO8601(DATE_NOW()), updatedAt: DATE_ISO8601(DATE_NOW()) }})",
+					data
+				),
+				_ => format!("MERGE({}, {{ updatedAt: DATE_ISO8601(DATE_NOW()) }})", data),
+			};
+		}
+
+		let (operation, change, diff) = match mutation {
+			AQLMutation::Insert => (
+				format!("INSERT {} INTO @@collection", data),
+				"NEW",
+				"{ new: UNSET(NEW, '_id', '_rev') }".to_string(),
+			),
+			AQLMutation::Update => (
+				format!("UPDATE {} WITH {} IN @@collection", id, data),
+				"NEW",
+				format!(
+					"{{ old: KEEP(OLD, ATTRIBUTES({data})), new: KEEP(NEW, ATTRIBUTES({data})) }}",
+					data = data
+				),
+			),
+			AQLMutation::Remove => (
+				format!("REMOVE {} IN @@collection", id),
+				"OLD",
+				"{ old: UNSET(OLD, '_id', '_rev') }".to_string(),
+			),
+		};
+
+		let history = if self.history {
+			format!(
+				"INSERT {{ documentKey: {variable}._key, operation: \"{operation}\", actor: null, timestamp: DATE_ISO8601(DATE_NOW()), diff: {diff} }} INTO @@history",
+				variable = self.get_variable_name(),
+				operation = mutation.to_string(),
+				diff = diff
+			)
+		} else {
+			"".to_string()
+		};
+
+		// The last version tells the number of the next one and when the replaced state began,
+		// the first state began when the document was created
+		let (previous, versions) = match mutation {
+			AQLMutation::Update | AQLMutation::Remove if self.versions => (
+				format!(
+					"LET {variable}_previous = FIRST(FOR v IN @@versions FILTER v.documentKey == {id} SORT v.version DESC LIMIT 1 RETURN v)",
+					variable = self.get_variable_name(),
+					id = id
+				),
+				format!(
+					"INSERT {{ documentKey: OLD._key, version: NOT_NULL({variable}_previous.version, 0) + 1, operation: \"{operation}\", validFrom: {variable}_previous.operation == \"update\" ? {variable}_previous.validTo : OLD.createdAt, validTo: DATE_ISO8601(DATE_NOW()), document: UNSET(OLD, '_rev') }} INTO @@versions",
+					variable = self.get_variable_name(),
+					operation = mutation.to_string()
+				),
+			),
+			_ => ("".to_string(), "".to_string()),
+		};
+
+		let outbox = if self.outbox {
+			let (operation, old, new) = match mutation {
+				AQLMutation::Insert => ("insert", "null", "NEW"),
+				AQLMutation::Update => ("update", "OLD", "NEW"),
+				AQLMutation::Remove => ("remove", "OLD", "null"),
+			};
+
+			format!(
+				"INSERT {{ collection: {collection}, tenant: {tenant}, key: {variable}._key, operation: \"{operation}\", old: {old}, new: {new}, actor: {actor}, status: \"pending\", attempts: 0, createdAt: DATE_ISO8601(DATE_NOW()) }} INTO @@outbox",
+				collection = AQLQueryBind("collection").describe(self.id),
+				tenant = AQLQueryBind("tenant").describe(self.id),
+				variable = self.get_variable_name(),
+				operation = operation,
+				old = old,
+				new = new,
+				actor = AQLQueryBind("actor").describe(self.id)
+			)
+		} else {
+			"".to_string()
+		};
+
+		let changes = if self.changes {
+			match mutation {
+				AQLMutation::Insert => ", old: null, new: NEW",
+				AQLMutation::Update => ", old: OLD, new: NEW",
+				AQLMutation::Remove => ", old: OLD, new: null",
+			}
+		} else {
+			""
+		};
+
+		// The key is returned next to the document so that the change can be published
+		format!(
+			"{} {} {} {} LET {} = {} {} {} {} RETURN {{ key: {}._key, document: {}{} }}",
+			previous,
+			references,
+			checks,
+			operation,
+			self.get_variable_name(),
+			change,
+			history,
+			versions,
+			outbox,
+			self.get_variable_name(),
+			self.describe_parameters(),
+			changes
+		)
+	}
+
 	pub fn describe_parameters(&self) -> String {
 		format!(
 			"{{{}}}",
 			self.properties
 				.iter()
-				.map(|p| format!(
-					"\"{name}\": {}.`{name}`",
-					self.get_variable_name(),
-					name = p.name
-				))
+				.map(|p| {
+					let value = format!("{}.`{}`", self.get_variable_name(), p.attribute);
+
+					match &p.locales {
+						_ if p.masked => format!("\"{}\": null", p.name),
+						Some(locales) => {
+							format!("\"{}\": {}", p.name, localized_value(&value, locales))
+						}
+						None => format!("\"{}\": {}", p.name, value),
+					}
+				})
 				.chain(self.relations.iter().map(|(key, query)| format!(
 					"\"{}\": ({})",
 					key,
@@ -77,13 +345,86 @@ impl<'a> AQLQuery<'a> {
 	}
 
 	fn describe_limit(&self) -> String {
-		if let Some(limit) = self.limit {
-			format!("LIMIT {}", limit)
-		} else {
+		match (self.offset, self.limit) {
+			(Some(offset), Some(limit)) => format!("LIMIT {}, {}", offset, limit),
+			// AQL has no offset without a count, the largest safe integer reads the rest
+			(Some(offset), None) => format!("LIMIT {}, 9007199254740991", offset),
+			(None, Some(limit)) => format!("LIMIT {}", limit),
+			(None, None) => "".to_string(),
+		}
+	}
+
+	fn describe_sort(&self) -> String {
+		if self.random {
+			return "SORT RAND()".to_string();
+		}
+
+		let sorts: Vec<String> = self
+			.near
+			.iter()
+			.map(|near| format!("{} ASC", near.distance(self.id)))
+			.chain(self.sort.iter().flat_map(|s| {
+				let mut value = format!("{}.`{}`", self.get_variable_name(), s.property);
+
+				if s.numeric {
+					value = format!("TO_NUMBER({})", value);
+				}
+
+				// Null is lower than any value, sorting on the null check first moves them
+				let nulls = s.nulls_first.map(|nulls_first| {
+					format!(
+						"{} == null {}",
+						value,
+						if nulls_first { "DESC" } else { "ASC" }
+					)
+				});
+
+				nulls.into_iter().chain(std::iter::once(format!(
+					"{} {}",
+					value,
+					if s.descending { "DESC" } else { "ASC" }
+				)))
+			}))
+			.collect();
+
+		if sorts.is_empty() {
 			"".to_string()
+		} else {
+			format!("SORT {}", sorts.join(", "))
 		}
 	}
 
+	/// The `at` time, and the first version replaced after it
+	fn describe_as_of(&self) -> String {
+		format!(
+			"LET {variable}_at = {at} LET {variable}_version = FIRST(FOR v IN @@versions FILTER v.documentKey == {id} AND v.validTo > {variable}_at SORT v.version ASC LIMIT 1 RETURN v)",
+			variable = self.get_variable_name(),
+			at = AQLQueryBind("at").describe(self.id),
+			id = AQLQueryBind("id").describe(self.id)
+		)
+	}
+
+	/// The state of the document at the `at` time, none if it was not created yet or removed
+	fn describe_as_of_state(&self) -> String {
+		format!(
+			"({variable}_version != null ? ({variable}_version.validFrom == null || {variable}_version.validFrom <= {variable}_at ? [{variable}_version.document] : []) : (FOR d IN @@collection FILTER d._key == {id} AND (d.createdAt == null || d.createdAt <= {variable}_at) RETURN d))",
+			variable = self.get_variable_name(),
+			id = AQLQueryBind("id").describe(self.id)
+		)
+	}
+
+	/// Combine a filter with the existing one, if any
+	pub fn add_filter(&mut self, node: Box<dyn AQLNode>) {
+		self.filter = Some(match self.filter.take() {
+			Some(existing) => Box::new(AQLFilter {
+				left_node: existing,
+				operation: AQLOperation::AND,
+				right_node: node,
+			}),
+			None => node,
+		});
+	}
+
 	fn describe_filter(&self) -> String {
 		if let Some(f) = &self.filter {
 			format!("FILTER {}", f.describe(self.id))
@@ -101,11 +442,32 @@ impl<'a> AQLQuery<'a> {
 	}
 }
 
-unsafe impl<'a> Send for AQLQuery<'a> {}
-
 #[derive(Debug)]
 pub struct AQLProperty {
+	/// Key of the projected value, the alias or name of the GraphQL field
 	pub name: String,
+	/// The stored property
+	pub attribute: String,
+	/// Projected as null, the property cannot be read by the request
+	pub masked: bool,
+	/// The locales tried in turn to project a localized property, before any of its translations
+	pub locales: Option<Vec<String>>,
+}
+
+/// The first translation of the locales stored in the object, values that are not objects are
+/// projected as they are
+fn localized_value(value: &str, locales: &[String]) -> String {
+	// The locales are checked language tags, their JSON array is a valid AQL array
+	let locales = serde_json::to_string(locales).unwrap_or_else(|_| String::from("[]"));
+
+	format!(
+		"(IS_OBJECT({value}) ? NOT_NULL(\
+			FIRST({locales}[* FILTER HAS({value}, CURRENT) LIMIT 1 RETURN {value}[CURRENT]]), \
+			FIRST(VALUES({value}))\
+		) : {value})",
+		value = value,
+		locales = locales
+	)
 }
 
 pub struct AQLFilter {
@@ -116,12 +478,24 @@ pub struct AQLFilter {
 
 pub enum AQLOperation {
 	EQUAL,
+	AND,
 }
 
 impl ToString for AQLOperation {
 	fn to_string(&self) -> String {
 		return match self {
 			AQLOperation::EQUAL => "==".to_string(),
+			AQLOperation::AND => "AND".to_string(),
+		};
+	}
+}
+
+impl ToString for AQLMutation {
+	fn to_string(&self) -> String {
+		return match self {
+			AQLMutation::Insert => "create".to_string(),
+			AQLMutation::Update => "update".to_string(),
+			AQLMutation::Remove => "delete".to_string(),
 		};
 	}
 }
@@ -129,7 +503,92 @@ impl ToString for AQLOperation {
 pub struct AQLQueryBind<'a>(pub &'a str);
 pub struct AQLQueryParameter(pub String);
 
-pub trait AQLNode {
+/// Excludes documents whose TTL has passed but that were not purged yet
+pub struct AQLExpiryFilter {
+	pub property: String,
+	pub expire_after: u32,
+}
+
+/// A row filter condition whose claim was replaced by its value
+pub struct AQLRowCondition {
+	pub property: Vec<String>,
+	pub operator: RowFilterOperator,
+	/// `None` when the claim is missing, the condition never holds then
+	pub value: Option<Value>,
+}
+
+/// Row-level security, a document matches when every condition of one alternative holds
+pub struct AQLRowFilter {
+	pub alternatives: Vec<Vec<AQLRowCondition>>,
+}
+
+/// A comparison of the `where` argument of a list operation
+pub enum AQLCondition {
+	Compare {
+		property: String,
+		operator: RowFilterOperator,
+		value: Value,
+	},
+	Contains {
+		property: String,
+		value: Value,
+		insensitive: bool,
+	},
+	EqualInsensitive {
+		property: String,
+		value: Value,
+	},
+	/// Compares the numbers stored as strings, e.g. decimals, by their value
+	CompareNumber {
+		property: String,
+		operator: RowFilterOperator,
+		value: Value,
+	},
+}
+
+/// Documents matching every condition, the insensitive ones compare normalized values
+pub struct AQLWhere {
+	pub conditions: Vec<AQLCondition>,
+	/// Analyzer normalizing the values, they are only lowercased without one
+	pub analyzer: Option<String>,
+}
+
+impl AQLWhere {
+	fn normalize(&self, value: &str) -> String {
+		match self.analyzer {
+			Some(ref analyzer) => {
+				format!("TOKENS({}, {})[0]", value, Value::from(analyzer.as_str()))
+			}
+			None => format!("LOWER({})", value),
+		}
+	}
+}
+
+/// Documents whose geometry is inside a polygon, given as GeoJSON
+pub struct AQLGeoWithin {
+	pub property: String,
+	pub polygon: Value,
+}
+
+/// Documents up to a distance in meters of a point
+#[derive(Clone)]
+pub struct AQLGeoNear {
+	pub property: String,
+	pub longitude: f64,
+	pub latitude: f64,
+	pub max_distance: Option<f64>,
+}
+
+impl AQLGeoNear {
+	fn distance(&self, id: u32) -> String {
+		format!(
+			"GEO_DISTANCE([{}, {}], i_{}.`{}`)",
+			self.longitude, self.latitude, id, self.property
+		)
+	}
+}
+
+pub trait AQLNode: Send + Sync {
 	fn describe(&self, id: u32) -> String;
 }
 
@@ -155,3 +614,118 @@ impl AQLNode for AQLQueryParameter {
 		format!("i_{}.`{}`", id, self.0)
 	}
 }
+
+impl AQLNode for AQLExpiryFilter {
+	fn describe(&self, id: u32) -> String {
+		// TTL indexes accept both unix timestamps in seconds and ISO 8601 dates
+		format!(
+			"(i_{id}.`{property}` == null || (IS_NUMBER(i_{id}.`{property}`) ? i_{id}.`{property}` * 1000 : DATE_TIMESTAMP(i_{id}.`{property}`)) + {expire_after} > DATE_NOW())",
+			id = id,
+			property = self.property,
+			expire_after = self.expire_after as u64 * 1000
+		)
+	}
+}
+
+impl AQLNode for AQLWhere {
+	fn describe(&self, id: u32) -> String {
+		if self.conditions.is_empty() {
+			return "true".to_string();
+		}
+
+		// Values are inlined as JSON, which is valid AQL
+		let conditions: Vec<String> = self
+			.conditions
+			.iter()
+			.map(|condition| match condition {
+				AQLCondition::Compare {
+					property,
+					operator,
+					value,
+				} => format!("i_{}.`{}` {} {}", id, property, operator.as_aql(), value),
+				AQLCondition::Contains {
+					property,
+					value,
+					insensitive: false,
+				} => format!("CONTAINS(i_{}.`{}`, {})", id, property, value),
+				AQLCondition::Contains {
+					property, value, ..
+				} => format!(
+					"CONTAINS({}, {})",
+					self.normalize(&format!("i_{}.`{}`", id, property)),
+					self.normalize(&value.to_string())
+				),
+				AQLCondition::EqualInsensitive { property, value } => format!(
+					"{} == {}",
+					self.normalize(&format!("i_{}.`{}`", id, property)),
+					self.normalize(&value.to_string())
+				),
+				AQLCondition::CompareNumber {
+					property,
+					operator,
+					value,
+				} => format!(
+					"TO_NUMBER(i_{}.`{}`) {} TO_NUMBER({})",
+					id,
+					property,
+					operator.as_aql(),
+					value
+				),
+			})
+			.collect();
+
+		format!("({})", conditions.join(" && "))
+	}
+}
+
+impl AQLNode for AQLGeoWithin {
+	fn describe(&self, id: u32) -> String {
+		// The polygon comes first so that the geo index is used
+		format!(
+			"GEO_CONTAINS({}, i_{}.`{}`)",
+			self.polygon, id, self.property
+		)
+	}
+}
+
+impl AQLNode for AQLGeoNear {
+	fn describe(&self, id: u32) -> String {
+		match self.max_distance {
+			Some(max_distance) => format!("{} <= {}", self.distance(id), max_distance),
+			None => "true".to_string(),
+		}
+	}
+}
+
+impl AQLNode for AQLRowFilter {
+	fn describe(&self, id: u32) -> String {
+		if self.alternatives.is_empty() {
+			return "false".to_string();
+		}
+
+		let alternatives: Vec<String> = self
+			.alternatives
+			.iter()
+			.map(|conditions| {
+				let conditions: Vec<String> = conditions
+					.iter()
+					.map(|condition| match &condition.value {
+						// Values are inlined as JSON, which is valid AQL
+						Some(value) => format!(
+							"i_{}.`{}` {} {}",
+							id,
+							condition.property.join("`.`"),
+							condition.operator.as_aql(),
+							value
+						),
+						None => "false".to_string(),
+					})
+					.collect();
+
+				format!("({})", conditions.join(" && "))
+			})
+			.collect();
+
+		format!("({})", alternatives.join(" || "))
+	}
+}
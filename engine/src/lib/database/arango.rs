@@ -1,30 +1,57 @@
-use rust_arango::collection::options::{CreateOptions as CollectionOptions, CreateParameters};
+use rust_arango::collection::options::{
+	CreateOptions as CollectionOptions, CreateParameters, PropertiesOptions,
+};
+use rust_arango::index::{Index, IndexSettings};
+use rust_arango::view::{ArangoSearchViewLink, ArangoSearchViewPropertiesOptions, ViewOptions};
+use std::collections::HashMap;
 
-use anyhow::Error;
+use anyhow::{anyhow, Error};
+use reqwest::Method;
+use rust_arango::AqlQuery;
 
 use serde_json::{to_value as toJsonValue, value::Value as JsonValue};
 
-use crate::lib::database::schema::{DatabaseSchema, Rule, SchemaProperty};
+use crate::lib::database::graphs::{add_edge_definition, add_vertex_collection};
+use crate::lib::database::http::{api_error, send_api_request};
+use crate::lib::database::schema::{DatabaseSchema, Rule, SchemaProperty, SchemaPropertyType};
 use crate::lib::database::DATABASE;
-use crate::lib::schema::{create_entry, delete_entry, SchemaDocumentProperty};
+use crate::lib::schema::versions::metadata_changed;
+use crate::lib::schema::{
+	create_entry, delete_entry, get_collection_entry, get_history_collection_name,
+	get_search_view_name, get_versions_collection_name, parse_row_filter, update_entry,
+	update_entry_schema, update_entry_search, validate_checks, validate_property_rules,
+	validate_triggers, validate_validations, SchemaCacheOptions, SchemaCheck,
+	SchemaCollectionOptions, SchemaCollectionUpdate, SchemaDirectives, SchemaDocumentProperty,
+	SchemaDocumentPropertyValues, SchemaFieldPermission, SchemaOperation, SchemaPermission,
+	SchemaReference, SchemaRowFilter, SchemaSearchOptions, SchemaTimeLimit, SchemaTrigger,
+	SchemaUniqueConstraint, SchemaValidation, TIMESTAMP_PROPERTIES, UNIQUE_INDEX_PREFIX,
+};
+use crate::lib::triggers::TRIGGER_DELIVERIES_COLLECTION;
 
 pub async fn create_collection(
 	name: String,
 	properties: Vec<SchemaDocumentProperty>,
+	options: SchemaCollectionOptions,
 ) -> Result<(), Error> {
-	let db = DATABASE.get().await.database.clone();
-
 	// Create a schema struct to be populated with an empty JSON Map for properties
-	let mut schema = DatabaseSchema {
-		message: String::from("Schema validation failed"),
-		level: String::from("strict"),
-		rule: Rule {
-			r#type: String::from("object"),
-			properties: JsonValue::Object(serde_json::Map::new()),
-			required: Vec::new(),
-			additional_properties: false,
-		},
-	};
+	let mut schema = DatabaseSchema::from_rule(Rule {
+		r#type: String::from("object"),
+		properties: JsonValue::Object(serde_json::Map::new()),
+		required: Vec::new(),
+		additional_properties: false,
+	});
+
+	for property in &properties {
+		validate_property_rules(property)?;
+	}
+
+	options.validate()?;
+
+	let geo_properties: Vec<String> = properties
+		.iter()
+		.filter(|property| property.values.r#type == SchemaPropertyType::Geo)
+		.map(|property| property.name.clone())
+		.collect();
 
 	// Iterate over the properties and add them to the schema rules
 	for property in properties {
@@ -40,16 +67,341 @@ pub async fn create_collection(
 
 	// println!("{:?}", toJsonValue(schema.clone()).unwrap().to_string());
 
-	// Create the collection with the schema
+	create_collection_with_schema(&name, &schema, &options).await?;
+
+	for property in geo_properties {
+		create_geo_index(&name, property).await?;
+	}
+
+	create_entry(name.clone(), schema.rule).await;
+
+	if options != SchemaCollectionOptions::default() {
+		update_entry(name, serde_json::json!({ "options": options })).await;
+	}
+
+	Ok(())
+}
+
+/// Create the Arango collection with its validation schema, without any alchemy metadata
+pub async fn create_collection_with_schema(
+	name: &str,
+	schema: &DatabaseSchema,
+	options: &SchemaCollectionOptions,
+) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	// The vertex collections of a SmartGraph are created through the graph, sharded by its
+	// attribute, the schema is set once they exist
+	if let Some(ref graph) = options.smart_graph {
+		add_vertex_collection(graph, name).await?;
+
+		db.collection(name)
+			.await?
+			.change_properties(
+				PropertiesOptions::builder()
+					.schema(toJsonValue(schema)?)
+					.build(),
+			)
+			.await?;
+
+		return Ok(());
+	}
+
+	if options.is_satellite() {
+		return create_satellite_collection(name, schema, options).await;
+	}
+
 	let collection_options = CollectionOptions::builder()
-		.name(name.as_str())
-		.schema(toJsonValue(&schema).unwrap())
+		.name(name)
+		.schema(toJsonValue(schema).unwrap())
+		.collection_type(options.get_collection_type())
+		.number_of_shards(options.get_number_of_shards())
+		.replication_factor(options.get_replication_factor())
+		.shard_keys(options.get_shard_keys())
+		.wait_for_sync(options.get_wait_for_sync())
+		.key_options(options.get_key_options())
 		.build();
 
 	db.create_collection_with_options(collection_options, CreateParameters::default())
 		.await?;
 
-	create_entry(name, schema.rule).await;
+	Ok(())
+}
+
+/// Create a satellite collection with the collection API, the driver only takes a number of
+/// replicas
+async fn create_satellite_collection(
+	name: &str,
+	schema: &DatabaseSchema,
+	options: &SchemaCollectionOptions,
+) -> Result<(), Error> {
+	let response = send_api_request(
+		Method::POST,
+		"collection",
+		serde_json::json!({
+			"name": name,
+			"type": if options.is_edge() { 3 } else { 2 },
+			"schema": toJsonValue(schema)?,
+			"replicationFactor": "satellite",
+			"waitForSync": options.get_wait_for_sync(),
+			"keyOptions": toJsonValue(options.get_key_options())?,
+		}),
+	)
+	.await?;
+
+	if !response.status().is_success() {
+		return Err(api_error(response).await);
+	}
+
+	Ok(())
+}
+
+/// Create a plain document collection if it does not exist yet
+pub async fn ensure_collection(name: &str) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let collections = db.accessible_collections().await?;
+
+	if !collections.iter().any(|c| c.name == name) {
+		db.create_collection(name).await?;
+	}
+
+	Ok(())
+}
+
+/// Add a property to the schema of an existing collection
+pub async fn add_collection_property(
+	name: String,
+	property: SchemaDocumentProperty,
+) -> Result<(), Error> {
+	validate_property_rules(&property)?;
+
+	let db = DATABASE.get().await.database.clone();
+	let collection = db.collection(name.as_str()).await?;
+
+	let geo_property = match property.values.r#type {
+		SchemaPropertyType::Geo => Some(property.name.clone()),
+		_ => None,
+	};
+
+	let mut schema: DatabaseSchema = match collection.properties().await?.info.schema {
+		Some(schema) => serde_json::from_value(schema)?,
+		None => return Err(anyhow!("Collection {} has no schema", name)),
+	};
+
+	schema.rule.properties.as_object_mut().unwrap().insert(
+		property.name.clone(),
+		toJsonValue(SchemaProperty::from(property.values))?,
+	);
+
+	if property.required && !schema.rule.required.contains(&property.name) {
+		schema.rule.required.push(property.name);
+	}
+
+	collection
+		.change_properties(
+			PropertiesOptions::builder()
+				.schema(toJsonValue(&schema)?)
+				.build(),
+		)
+		.await?;
+
+	if let Some(property) = geo_property {
+		create_geo_index(&name, property).await?;
+	}
+
+	update_entry_schema(name, schema.rule).await;
+
+	Ok(())
+}
+
+/// Patch the schema of an existing collection: add or replace properties, replace the required
+/// properties and change the validation level, then refresh the metadata
+pub async fn update_collection(name: String, update: SchemaCollectionUpdate) -> Result<(), Error> {
+	for property in &update.properties {
+		validate_property_rules(property)?;
+	}
+
+	let db = DATABASE.get().await.database.clone();
+	let collection = db.collection(name.as_str()).await?;
+
+	let mut schema: DatabaseSchema = match collection.properties().await?.info.schema {
+		Some(schema) => serde_json::from_value(schema)?,
+		None => return Err(anyhow!("Collection {} has no schema", name)),
+	};
+
+	if let Some(required) = update.required {
+		if let Some(property) = required.iter().find(|required| {
+			schema.rule.properties.get(required.as_str()).is_none()
+				&& !update.properties.iter().any(|p| p.name == **required)
+		}) {
+			return Err(anyhow!("Collection {} has no property {}", name, property));
+		}
+
+		schema.rule.required = required;
+	}
+
+	// Creating the index of a property that was already a geo property returns the existing one
+	let geo_properties: Vec<String> = update
+		.properties
+		.iter()
+		.filter(|property| property.values.r#type == SchemaPropertyType::Geo)
+		.map(|property| property.name.clone())
+		.collect();
+
+	for property in update.properties {
+		if property.required && !schema.rule.required.contains(&property.name) {
+			schema.rule.required.push(property.name.clone());
+		}
+
+		schema.rule.properties.as_object_mut().unwrap().insert(
+			property.name,
+			toJsonValue(SchemaProperty::from(property.values))?,
+		);
+	}
+
+	if let Some(level) = update.level {
+		schema.level = String::from(level.as_str());
+	}
+
+	collection
+		.change_properties(
+			PropertiesOptions::builder()
+				.schema(toJsonValue(&schema)?)
+				.build(),
+		)
+		.await?;
+
+	for property in geo_properties {
+		create_geo_index(&name, property).await?;
+	}
+
+	update_entry_schema(name, schema.rule).await;
+
+	Ok(())
+}
+
+/// Apply a change to the schema of a collection, then refresh the metadata
+pub async fn change_collection_schema<F>(name: &str, change: F) -> Result<(), Error>
+where
+	F: FnOnce(&mut DatabaseSchema) -> Result<(), Error>,
+{
+	let db = DATABASE.get().await.database.clone();
+	let collection = db.collection(name).await?;
+
+	let mut schema: DatabaseSchema = match collection.properties().await?.info.schema {
+		Some(schema) => serde_json::from_value(schema)?,
+		None => return Err(anyhow!("Collection {} has no schema", name)),
+	};
+
+	change(&mut schema)?;
+
+	collection
+		.change_properties(
+			PropertiesOptions::builder()
+				.schema(toJsonValue(&schema)?)
+				.build(),
+		)
+		.await?;
+
+	update_entry_schema(name.to_string(), schema.rule).await;
+
+	Ok(())
+}
+
+/// Remove a property from the schema of a collection, the stored values are kept
+pub async fn remove_collection_property(name: String, property: String) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+	let collection = db.collection(name.as_str()).await?;
+
+	let mut schema: DatabaseSchema = match collection.properties().await?.info.schema {
+		Some(schema) => serde_json::from_value(schema)?,
+		None => return Err(anyhow!("Collection {} has no schema", name)),
+	};
+
+	if schema
+		.rule
+		.properties
+		.as_object_mut()
+		.unwrap()
+		.remove(&property)
+		.is_none()
+	{
+		return Err(anyhow!("Collection {} has no property {}", name, property));
+	}
+
+	schema
+		.rule
+		.required
+		.retain(|required| required != &property);
+
+	collection
+		.change_properties(
+			PropertiesOptions::builder()
+				.schema(toJsonValue(&schema)?)
+				.build(),
+		)
+		.await?;
+
+	update_entry_schema(name, schema.rule).await;
+
+	Ok(())
+}
+
+/// Reject the attributes that are not properties of a collection, or allow them, in the
+/// schema of the collection and in the mutation inputs
+pub async fn set_collection_strict(name: String, strict: bool) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+	let collection = db.collection(name.as_str()).await?;
+
+	let mut schema: DatabaseSchema = match collection.properties().await?.info.schema {
+		Some(schema) => serde_json::from_value(schema)?,
+		None => return Err(anyhow!("Collection {} has no schema", name)),
+	};
+
+	schema.rule.additional_properties = !strict;
+
+	collection
+		.change_properties(
+			PropertiesOptions::builder()
+				.schema(toJsonValue(&schema)?)
+				.build(),
+		)
+		.await?;
+
+	update_entry_schema(name, schema.rule).await;
+
+	Ok(())
+}
+
+/// Create a persistent index on the given properties of a collection
+pub async fn create_index(name: String, fields: Vec<String>, unique: bool) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let index = Index::builder()
+		.fields(fields)
+		.settings(IndexSettings::Persistent {
+			unique,
+			sparse: false,
+			deduplicate: false,
+		})
+		.build();
+
+	db.create_index(name.as_str(), &index).await?;
+
+	Ok(())
+}
+
+/// Create a geo index on a GeoJSON property, used by the `_within` and `_near` filters
+pub async fn create_geo_index(name: &str, property: String) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let index = Index::builder()
+		.fields(vec![property])
+		.settings(IndexSettings::Geo { geo_json: true })
+		.build();
+
+	db.create_index(name, &index).await?;
 
 	Ok(())
 }
@@ -63,3 +415,518 @@ pub async fn delete_collection(name: String) -> Result<(), Error> {
 
 	Ok(())
 }
+
+pub async fn create_search_view(name: String, options: SchemaSearchOptions) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let analyzers = options.get_analyzers();
+
+	// Link every requested property with the analyzers, or the whole document if none were given
+	let mut link = ArangoSearchViewLink::builder()
+		.analyzers(analyzers.clone())
+		.include_all_fields(options.fields.is_empty());
+
+	if !options.fields.is_empty() {
+		let fields: HashMap<String, ArangoSearchViewLink> = options
+			.fields
+			.iter()
+			.map(|field| {
+				(
+					field.clone(),
+					ArangoSearchViewLink::builder()
+						.analyzers(analyzers.clone())
+						.build(),
+				)
+			})
+			.collect();
+
+		link = link.fields(fields);
+	}
+
+	let mut links = HashMap::new();
+	links.insert(name.clone(), link.build());
+
+	let view_options = ViewOptions::builder()
+		.name(get_search_view_name(&name))
+		.properties(
+			ArangoSearchViewPropertiesOptions::builder()
+				.links(links)
+				.build()
+				.into(),
+		)
+		.build();
+
+	db.create_view(view_options).await?;
+
+	update_entry_search(name, Some(toJsonValue(&options)?)).await;
+
+	Ok(())
+}
+
+pub async fn delete_search_view(name: String) -> Result<(), Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	db.drop_view(get_search_view_name(&name).as_str()).await?;
+
+	update_entry_search(name, None).await;
+
+	Ok(())
+}
+
+/// Enable or disable the change history of a collection, the history itself is kept
+pub async fn set_collection_audit(name: String, enabled: bool) -> Result<(), Error> {
+	if enabled {
+		ensure_collection(&get_history_collection_name(&name)).await?;
+	}
+
+	update_entry(name, serde_json::json!({ "audit": enabled })).await;
+
+	Ok(())
+}
+
+/// Enable or disable the versions of a collection, the versions already written are kept
+pub async fn set_collection_versioning(name: String, enabled: bool) -> Result<(), Error> {
+	if enabled {
+		let versions = get_versions_collection_name(&name);
+
+		ensure_collection(&versions).await?;
+
+		// The versions of a document are read in order, the index is left as is if it exists
+		create_index(
+			versions,
+			vec![String::from("documentKey"), String::from("version")],
+			true,
+		)
+		.await?;
+	}
+
+	update_entry(name, serde_json::json!({ "versioning": enabled })).await;
+
+	Ok(())
+}
+
+/// Enable or disable the server managed `createdAt` and `updatedAt` properties of a collection
+pub async fn set_collection_timestamps(name: String, enabled: bool) -> Result<(), Error> {
+	if enabled {
+		for property in TIMESTAMP_PROPERTIES {
+			add_collection_property(
+				name.clone(),
+				SchemaDocumentProperty {
+					name: property.to_string(),
+					values: SchemaDocumentPropertyValues {
+						r#type: SchemaPropertyType::DateTime,
+						..Default::default()
+					},
+					required: false,
+				},
+			)
+			.await?;
+		}
+	}
+
+	update_entry(name, serde_json::json!({ "timestamps": enabled })).await;
+
+	Ok(())
+}
+
+/// Set the TTL index of a collection, replacing any previous one, or remove it with `None`
+pub async fn set_collection_expiry(
+	name: String,
+	expiry: Option<SchemaExpiryOptions>,
+) -> Result<(), Error> {
+	if expiry.iter().any(|expiry| expiry.get_expire_after() < 0) {
+		return Err(anyhow!("expireAfter must be a positive number of seconds"));
+	}
+
+	let db = DATABASE.get().await.database.clone();
+
+	// A collection can only have a single TTL index
+	for index in db.indexes(name.as_str()).await?.indexes {
+		if let IndexSettings::Ttl { .. } = index.settings {
+			db.delete_index(index.id.as_str()).await?;
+		}
+	}
+
+	if let Some(ref expiry) = expiry {
+		let index = Index::builder()
+			.fields(vec![expiry.property.clone()])
+			.settings(IndexSettings::Ttl {
+				expire_after: expiry.get_expire_after() as u32,
+			})
+			.build();
+
+		db.create_index(name.as_str(), &index).await?;
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "expiry": expiry.map(|e| toJsonValue(e).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Replace the unique constraints of a collection, each one backed by a unique persistent index,
+/// or remove them with `None`
+pub async fn set_collection_unique(
+	name: String,
+	unique: Option<Vec<SchemaUniqueConstraint>>,
+) -> Result<(), Error> {
+	if unique
+		.iter()
+		.flatten()
+		.any(|constraint| constraint.properties.is_empty())
+	{
+		return Err(anyhow!("A unique constraint needs at least one property"));
+	}
+
+	let db = DATABASE.get().await.database.clone();
+
+	for index in db.indexes(name.as_str()).await?.indexes {
+		if index.name.starts_with(UNIQUE_INDEX_PREFIX) {
+			db.delete_index(index.id.as_str()).await?;
+		}
+	}
+
+	// Creating the index fails when the stored documents already have duplicates
+	for constraint in unique.iter().flatten() {
+		let index = Index::builder()
+			.name(constraint.index_name())
+			.fields(constraint.properties.clone())
+			.settings(IndexSettings::Persistent {
+				unique: true,
+				sparse: false,
+				deduplicate: false,
+			})
+			.build();
+
+		db.create_index(name.as_str(), &index).await?;
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "unique": unique.map(|u| toJsonValue(u).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Check that the properties referencing other collections hold the key of an existing document
+/// on every write, or stop checking them with `None`
+pub async fn set_collection_references(
+	name: String,
+	references: Option<Vec<SchemaReference>>,
+) -> Result<(), Error> {
+	for reference in references.iter().flatten() {
+		if get_collection_entry(&reference.collection).await.is_none() {
+			return Err(anyhow!(
+				"The property {} references the unknown collection {}",
+				reference.property,
+				reference.collection
+			));
+		}
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "references": references.map(|r| toJsonValue(r).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Set the AQL expressions the written documents must satisfy, or remove them with `None`
+pub async fn set_collection_checks(
+	name: String,
+	checks: Option<Vec<SchemaCheck>>,
+) -> Result<(), Error> {
+	if let Some(ref checks) = checks {
+		validate_checks(checks)?;
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "checks": checks.map(|c| toJsonValue(c).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Cache the results of the read operations of a collection, or stop caching them with `None`
+pub async fn set_collection_cache(
+	name: String,
+	cache: Option<Vec<SchemaCacheOptions>>,
+) -> Result<(), Error> {
+	for options in cache.iter().flatten() {
+		if !options.is_cacheable() {
+			return Err(anyhow!("Only the results of get and getAll can be cached"));
+		}
+
+		if options.ttl_secs < 0 {
+			return Err(anyhow!("ttlSecs must be a positive number of seconds"));
+		}
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "cache": cache.map(|c| toJsonValue(c).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Kill the queries of the operations of a collection running longer than their limit, or stop
+/// limiting them with `None`
+pub async fn set_collection_time_limits(
+	name: String,
+	time_limits: Option<Vec<SchemaTimeLimit>>,
+) -> Result<(), Error> {
+	for limit in time_limits.iter().flatten() {
+		if limit.max_runtime_ms <= 0 {
+			return Err(anyhow!(
+				"maxRuntimeMs must be a positive number of milliseconds"
+			));
+		}
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "timeLimits": time_limits.map(|l| toJsonValue(l).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Restrict the generated operations of a collection to the granted roles, or lift the restriction
+pub async fn set_collection_permissions(
+	name: String,
+	permissions: Option<Vec<SchemaPermission>>,
+) -> Result<(), Error> {
+	update_entry(
+		name,
+		serde_json::json!({ "permissions": permissions.map(|p| toJsonValue(p).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Restrict the rows of a collection each role can see, update and remove
+pub async fn set_collection_row_filters(
+	name: String,
+	row_filters: Option<Vec<SchemaRowFilter>>,
+) -> Result<(), Error> {
+	for row_filter in row_filters.iter().flatten() {
+		parse_row_filter(&row_filter.filter)
+			.map_err(|e| anyhow!("Invalid row filter of {}: {}", row_filter.role, e))?;
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "rowFilters": row_filters.map(|f| toJsonValue(f).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Restrict the properties of a collection each role can read
+pub async fn set_collection_field_permissions(
+	name: String,
+	field_permissions: Option<Vec<SchemaFieldPermission>>,
+) -> Result<(), Error> {
+	update_entry(
+		name,
+		serde_json::json!({ "fieldPermissions": field_permissions.map(|p| toJsonValue(p).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Only generate the given operations of a collection, e.g. no mutations for reference data,
+/// or every operation with `None`
+pub async fn set_collection_operations(
+	name: String,
+	operations: Option<Vec<SchemaOperation>>,
+) -> Result<(), Error> {
+	update_entry(
+		name,
+		serde_json::json!({ "operations": operations.map(|o| toJsonValue(o).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Deliver the changes of a collection to webhooks, or stop delivering them with `None`
+pub async fn set_collection_triggers(
+	name: String,
+	triggers: Option<Vec<SchemaTrigger>>,
+) -> Result<(), Error> {
+	if let Some(ref triggers) = triggers {
+		validate_triggers(triggers)?;
+
+		ensure_collection(TRIGGER_DELIVERIES_COLLECTION).await?;
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "triggers": triggers.map(|t| toJsonValue(t).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Set the cross-field rules the written documents must satisfy, or remove them with `None`
+pub async fn set_collection_validations(
+	name: String,
+	validations: Option<Vec<SchemaValidation>>,
+) -> Result<(), Error> {
+	if let Some(ref validations) = validations {
+		validate_validations(validations)?;
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "validations": validations.map(|v| toJsonValue(v).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+/// Rename the generated operations and fields of a collection, hide properties or make them
+/// read-only, or remove these customizations with `None`
+pub async fn set_collection_directives(
+	name: String,
+	directives: Option<SchemaDirectives>,
+) -> Result<(), Error> {
+	if let Some(ref directives) = directives {
+		let properties: Vec<String> = get_collection_entry(&name)
+			.await
+			.and_then(|entry| {
+				entry["schema"]["properties"]
+					.as_object()
+					.map(|properties| properties.keys().cloned().collect())
+			})
+			.unwrap_or_default();
+
+		directives.validate(&properties)?;
+	}
+
+	update_entry(
+		name,
+		serde_json::json!({ "directives": directives.map(|d| toJsonValue(d).unwrap()) }),
+	)
+	.await;
+
+	Ok(())
+}
+
+const RELATIONSHIP_TYPES: [&str; 3] = ["one_to_one", "one_to_many", "many_to_many"];
+const RELATIONSHIP_DIRECTIONS: [&str; 3] = ["outbound", "inbound", "any"];
+
+/// Create the edge collection of a relationship and register it, or update its entry
+pub async fn create_relationship(
+	name: String,
+	edge: String,
+	from: String,
+	to: String,
+	r#type: String,
+	direction: String,
+	graph: Option<String>,
+) -> Result<(), Error> {
+	if !RELATIONSHIP_TYPES.contains(&r#type.as_str()) {
+		return Err(anyhow!(
+			"Invalid type {} of the relationship {}, expected one of {}",
+			r#type,
+			name,
+			RELATIONSHIP_TYPES.join(", ")
+		));
+	}
+
+	if !RELATIONSHIP_DIRECTIONS.contains(&direction.as_str()) {
+		return Err(anyhow!(
+			"Invalid direction {} of the relationship {}, expected one of {}",
+			direction,
+			name,
+			RELATIONSHIP_DIRECTIONS.join(", ")
+		));
+	}
+
+	let db = DATABASE.get().await.database.clone();
+
+	ensure_collection("alchemy_edges").await?;
+
+	// The edges of a SmartGraph are sharded along their vertices, the graph creates the edge
+	// collection, or extends its definition
+	if let Some(ref graph) = graph {
+		add_edge_definition(graph, &edge, &from, &to).await?;
+	} else if !db
+		.accessible_collections()
+		.await?
+		.iter()
+		.any(|c| c.name == edge)
+	{
+		db.create_edge_collection(edge.as_str()).await?;
+	}
+
+	let entry_query = AqlQuery::builder()
+		.query(
+			"UPSERT { name: @name }
+				INSERT @entry
+				UPDATE @entry
+				IN alchemy_edges",
+		)
+		.bind_var("name", name.clone())
+		.bind_var(
+			"entry",
+			serde_json::json!({
+				"name": name,
+				"edge": edge,
+				"from": from,
+				"to": to,
+				"type": r#type,
+				"direction": direction,
+				"graph": graph,
+			}),
+		)
+		.build();
+
+	let _entries: Vec<JsonValue> = db.aql_query(entry_query).await?;
+
+	metadata_changed().await;
+
+	Ok(())
+}
+
+/// Remove a relationship from the API, its edge collection and documents are kept
+pub async fn delete_relationship(name: String) -> Result<(), Error> {
+	let entry_query = AqlQuery::builder()
+		.query(
+			"FOR e IN alchemy_edges
+				FILTER e.name == @name
+				REMOVE e IN alchemy_edges
+				RETURN OLD",
+		)
+		.bind_var("name", name.clone())
+		.build();
+
+	let removed: Vec<JsonValue> = DATABASE.get().await.database.aql_query(entry_query).await?;
+
+	if removed.is_empty() {
+		return Err(anyhow!("Relationship {} does not exist", name));
+	}
+
+	metadata_changed().await;
+
+	Ok(())
+}
@@ -0,0 +1,554 @@
+use std::cell::RefCell;
+use std::collections::VecDeque;
+use std::fmt;
+use std::future::Future;
+use std::sync::{Arc, Mutex, RwLock};
+use std::time::{Duration, Instant};
+
+use chrono::Utc;
+use futures::channel::mpsc::Sender;
+use futures::future::BoxFuture;
+use futures::SinkExt;
+use lazy_static::lazy_static;
+use reqwest::Method;
+use rust_arango::{AqlOptions, AqlQuery, ClientError};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::value::RawValue;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::lib::database::cancel::QueryGuard;
+use crate::lib::database::http::send_api_request;
+use crate::lib::database::DATABASE;
+use crate::lib::redaction::{redact_aql, redact_bind_vars};
+use crate::lib::CONFIG;
+
+/// A query of the generated operations with its bind variables, independent of the driver
+#[derive(Clone, Debug)]
+pub struct BackendQuery {
+	pub query: String,
+	pub bind_vars: JsonMap<String, JsonValue>,
+	/// Number of documents read per round trip, the driver default when unset
+	pub batch_size: Option<u32>,
+	/// What the query runs for, embedded in a comment so that it shows in the query monitor
+	pub tag: Option<String>,
+	/// Runtime after which the database kills the query
+	pub max_runtime: Option<Duration>,
+}
+
+impl BackendQuery {
+	pub fn new(query: impl Into<String>) -> Self {
+		BackendQuery {
+			query: query.into(),
+			bind_vars: JsonMap::new(),
+			batch_size: None,
+			tag: None,
+			max_runtime: None,
+		}
+	}
+
+	pub fn bind_var(mut self, key: impl Into<String>, value: impl Into<JsonValue>) -> Self {
+		self.bind_vars.insert(key.into(), value.into());
+		self
+	}
+
+	pub fn batch_size(mut self, batch_size: u32) -> Self {
+		self.batch_size = Some(batch_size);
+		self
+	}
+
+	pub fn tag(mut self, tag: impl Into<String>) -> Self {
+		self.tag = Some(tag.into());
+		self
+	}
+
+	pub fn max_runtime(mut self, max_runtime: Duration) -> Self {
+		self.max_runtime = Some(max_runtime);
+		self
+	}
+
+	/// The AQL sent to the database, prefixed with the comment of its tag
+	pub fn tagged_query(&self) -> String {
+		match self.tag {
+			// The tag cannot end the comment early, it may come from a request header
+			Some(ref tag) => format!(
+				"/* {} */ {}",
+				tag.chars()
+					.filter(|c| !matches!(c, '*' | '/'))
+					.collect::<String>(),
+				self.query
+			),
+			None => self.query.clone(),
+		}
+	}
+}
+
+/// A failed query, as reported by the backend
+#[derive(Debug)]
+pub enum BackendError {
+	/// Rejected by the database, with the HTTP status and the error number of ArangoDB
+	Database {
+		code: u16,
+		error_num: u16,
+		message: String,
+	},
+	/// The database could not be reached
+	Unavailable(String),
+	Other(String),
+}
+
+impl BackendError {
+	/// The error number of ArangoDB, if the database rejected the query
+	pub fn error_num(&self) -> Option<u16> {
+		match self {
+			BackendError::Database { error_num, .. } => Some(*error_num),
+			_ => None,
+		}
+	}
+
+	pub fn message(&self) -> &str {
+		match self {
+			BackendError::Database { message, .. }
+			| BackendError::Unavailable(message)
+			| BackendError::Other(message) => message,
+		}
+	}
+}
+
+impl fmt::Display for BackendError {
+	fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+		match self {
+			BackendError::Database {
+				error_num, message, ..
+			} => write!(f, "{} ({})", message, error_num),
+			BackendError::Unavailable(message) => write!(f, "Database unavailable: {}", message),
+			BackendError::Other(message) => write!(f, "{}", message),
+		}
+	}
+}
+
+impl From<ClientError> for BackendError {
+	fn from(error: ClientError) -> Self {
+		match error {
+			ClientError::Arango(error) => BackendError::Database {
+				code: error.code(),
+				error_num: error.error_num(),
+				message: error.message().to_string(),
+			},
+			ClientError::HttpClient(message) | ClientError::InvalidServer(message) => {
+				BackendError::Unavailable(message)
+			}
+			error => BackendError::Other(error.to_string()),
+		}
+	}
+}
+
+/// The database the generated operations run their queries against, the queries are AQL
+pub trait QueryBackend: Send + Sync {
+	/// Run a query and return every result, still encoded so that the caller decodes them into
+	/// the type it needs
+	fn query<'a>(
+		&'a self,
+		query: BackendQuery,
+	) -> BoxFuture<'a, Result<Vec<Box<RawValue>>, BackendError>>;
+
+	/// Run a query and send its results one batch at a time, as they are read, the query stops
+	/// once the receiver is dropped. The backends without a cursor send them all at once
+	fn query_batches<'a>(
+		&'a self,
+		query: BackendQuery,
+		mut batches: Sender<Vec<Box<RawValue>>>,
+	) -> BoxFuture<'a, Result<(), BackendError>> {
+		Box::pin(async move {
+			let results = self.query(query).await?;
+			let _ = batches.send(results).await;
+
+			Ok(())
+		})
+	}
+
+	/// Check a query without running it, the backends without a query planner accept them all
+	fn explain<'a>(&'a self, _query: BackendQuery) -> BoxFuture<'a, Result<(), BackendError>> {
+		Box::pin(async { Ok(()) })
+	}
+}
+
+/// The backend of the ArangoDB connection of the engine
+pub struct ArangoBackend;
+
+impl ArangoBackend {
+	/// Read the results through the cursor, they are sent one batch at a time when there is a
+	/// sender and returned all together otherwise
+	async fn read(
+		query: BackendQuery,
+		mut batches: Option<Sender<Vec<Box<RawValue>>>>,
+	) -> Result<Vec<Box<RawValue>>, BackendError> {
+		let database = DATABASE.get().await.database.clone();
+
+		// The query is cancelled if this future is dropped before all the results are read
+		let mut guard = QueryGuard::new();
+
+		let aql = format!("{} {}", guard.marker(), query.tagged_query());
+		let options = AqlOptions::builder()
+			.max_runtime(query.max_runtime.map(|runtime| runtime.as_secs_f64()))
+			.build();
+		let mut builder = AqlQuery::builder().query(&aql).options(options);
+
+		for (key, value) in query.bind_vars {
+			builder = builder.bind_var(key, value);
+		}
+
+		// The receiver went away before every result was sent, the rest of the query is cancelled
+		let mut abandoned = false;
+
+		let results = async {
+			let mut output = Vec::new();
+
+			let mut cursor = match query.batch_size {
+				Some(batch_size) => {
+					database
+						.aql_query_batch::<Box<RawValue>>(builder.batch_size(batch_size).build())
+						.await?
+				}
+				None => {
+					let results = database.aql_query(builder.build()).await?;
+
+					return match batches.as_mut() {
+						Some(batches) => {
+							let _ = batches.send(results).await;
+
+							Ok(output)
+						}
+						None => Ok(results),
+					};
+				}
+			};
+
+			// The results are read through the cursor one batch at a time
+			loop {
+				match batches.as_mut() {
+					Some(batches) => {
+						if batches.send(cursor.result).await.is_err() {
+							abandoned = true;
+
+							return Ok(output);
+						}
+					}
+					None => output.extend(cursor.result),
+				}
+
+				cursor = match (cursor.more, cursor.id) {
+					(true, Some(id)) => {
+						guard.set_cursor(Some(id.clone()));
+
+						database.aql_next_batch(id.as_str()).await?
+					}
+					_ => return Ok::<_, BackendError>(output),
+				};
+			}
+		}
+		.await;
+
+		if !abandoned {
+			guard.finish();
+		}
+
+		results
+	}
+}
+
+impl QueryBackend for ArangoBackend {
+	fn query<'a>(
+		&'a self,
+		query: BackendQuery,
+	) -> BoxFuture<'a, Result<Vec<Box<RawValue>>, BackendError>> {
+		Box::pin(ArangoBackend::read(query, None))
+	}
+
+	fn query_batches<'a>(
+		&'a self,
+		query: BackendQuery,
+		batches: Sender<Vec<Box<RawValue>>>,
+	) -> BoxFuture<'a, Result<(), BackendError>> {
+		Box::pin(async move {
+			ArangoBackend::read(query, Some(batches)).await?;
+
+			Ok(())
+		})
+	}
+
+	/// The query is parsed and planned by the database, the driver does not expose the endpoint
+	fn explain<'a>(&'a self, query: BackendQuery) -> BoxFuture<'a, Result<(), BackendError>> {
+		Box::pin(async move {
+			let response = send_api_request(
+				Method::POST,
+				"explain",
+				json!({ "query": query.query, "bindVars": query.bind_vars }),
+			)
+			.await
+			.map_err(|e| BackendError::Unavailable(e.to_string()))?;
+
+			if response.status().is_success() {
+				return Ok(());
+			}
+
+			let code = response.status().as_u16();
+			let error: JsonValue = response.json().await.unwrap_or_default();
+
+			Err(BackendError::Database {
+				code,
+				error_num: error["errorNum"].as_u64().unwrap_or_default() as u16,
+				message: error["errorMessage"]
+					.as_str()
+					.unwrap_or("no message")
+					.to_string(),
+			})
+		})
+	}
+}
+
+/// Options of the queries run for a GraphQL operation
+#[derive(Clone, Default)]
+pub struct QueryScope {
+	pub tag: Option<String>,
+	pub max_runtime: Option<Duration>,
+}
+
+/// The timing of a query run while the queries are recorded
+#[derive(Clone, Debug)]
+pub struct QueryTiming {
+	pub tag: Option<String>,
+	pub started: Instant,
+	/// Time spent in the database
+	pub query_time: Duration,
+	/// Time spent decoding the results
+	pub decode_time: Duration,
+}
+
+/// A query explained by the database instead of being run
+#[derive(Debug)]
+pub struct ExplainedQuery {
+	pub query: BackendQuery,
+	/// Why the database rejected the query, if it did
+	pub error: Option<BackendError>,
+}
+
+tokio::task_local! {
+	static QUERY_SCOPE: QueryScope;
+	static QUERY_TIMINGS: RefCell<Vec<QueryTiming>>;
+	static EXPLAINED_QUERIES: RefCell<Vec<ExplainedQuery>>;
+}
+
+lazy_static! {
+	static ref BACKEND: RwLock<Arc<dyn QueryBackend>> = RwLock::new(Arc::new(ArangoBackend));
+	static ref SLOW_QUERIES: Mutex<VecDeque<SlowQuery>> = Mutex::new(VecDeque::new());
+}
+
+/// Slow queries kept for the admin dashboard, the oldest are dropped first
+const RECENT_SLOW_QUERIES: usize = 50;
+
+/// A query that exceeded the slow query threshold, its sensitive values redacted
+#[derive(Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SlowQuery {
+	pub operation: Option<String>,
+	pub query: String,
+	pub duration_ms: u64,
+	pub results: usize,
+	/// When the query finished, in RFC 3339
+	pub finished_at: String,
+}
+
+/// The latest slow queries, the most recent first
+pub fn recent_slow_queries() -> Vec<SlowQuery> {
+	SLOW_QUERIES.lock().unwrap().iter().rev().cloned().collect()
+}
+
+/// Replace the backend of the generated operations, e.g. by an in-memory one
+pub fn set_backend(backend: Arc<dyn QueryBackend>) {
+	*BACKEND.write().unwrap() = backend;
+}
+
+pub fn backend() -> Arc<dyn QueryBackend> {
+	BACKEND.read().unwrap().clone()
+}
+
+/// Run a future with the options of the scope applied to its queries, unless they set them
+pub async fn with_query_scope<F: Future>(scope: QueryScope, f: F) -> F::Output {
+	QUERY_SCOPE.scope(scope, f).await
+}
+
+/// Run a future and return the timings of the queries it ran along with its output, they are
+/// still recorded by the enclosing recording
+pub async fn record_query_timings<F: Future>(f: F) -> (F::Output, Vec<QueryTiming>) {
+	let (output, timings) = QUERY_TIMINGS
+		.scope(RefCell::new(Vec::new()), async {
+			let output = f.await;
+			let timings = QUERY_TIMINGS.with(|timings| timings.take());
+
+			(output, timings)
+		})
+		.await;
+
+	let _ = QUERY_TIMINGS.try_with(|outer| outer.borrow_mut().extend(timings.iter().cloned()));
+
+	(output, timings)
+}
+
+/// Run a future with its queries explained by the backend instead of being run, they find
+/// nothing, and return the explained queries
+pub async fn explain_queries<F: Future>(f: F) -> Vec<ExplainedQuery> {
+	EXPLAINED_QUERIES
+		.scope(RefCell::new(Vec::new()), async {
+			f.await;
+
+			EXPLAINED_QUERIES.with(|queries| queries.take())
+		})
+		.await
+}
+
+/// Whether the queries are explained rather than run, their empty results must not be cached
+pub fn is_explaining() -> bool {
+	EXPLAINED_QUERIES.try_with(|_| ()).is_ok()
+}
+
+/// The options of the operation the query runs for, unless the query sets its own
+fn apply_scope(query: &mut BackendQuery) {
+	if let Ok(scope) = QUERY_SCOPE.try_with(QueryScope::clone) {
+		query.tag = query.tag.take().or(scope.tag);
+		query.max_runtime = query.max_runtime.or(scope.max_runtime);
+	}
+}
+
+/// Run a query on the current backend and decode its results
+pub async fn run_query<T: DeserializeOwned>(
+	mut query: BackendQuery,
+) -> Result<Vec<T>, BackendError> {
+	apply_scope(&mut query);
+
+	if is_explaining() {
+		let error = backend().explain(query.clone()).await.err();
+
+		let _ = EXPLAINED_QUERIES
+			.try_with(|queries| queries.borrow_mut().push(ExplainedQuery { query, error }));
+
+		return Ok(Vec::new());
+	}
+
+	// The query is only kept for the log when it may turn out slow
+	let slow_query = CONFIG
+		.slow_query_threshold_ms
+		.map(|threshold| (Duration::from_millis(threshold), query.clone()));
+
+	let tag = query.tag.clone();
+	let started = Instant::now();
+	let results = backend().query(query).await?;
+	let queried = Instant::now();
+
+	let output = results
+		.iter()
+		.map(|result| {
+			serde_json::from_str(result.get()).map_err(|e| BackendError::Other(e.to_string()))
+		})
+		.collect();
+
+	let _ = QUERY_TIMINGS.try_with(|timings| {
+		timings.borrow_mut().push(QueryTiming {
+			tag: tag.clone(),
+			started,
+			query_time: queried - started,
+			decode_time: queried.elapsed(),
+		})
+	});
+
+	if let Some((threshold, query)) = slow_query {
+		if started.elapsed() > threshold {
+			log_slow_query(&query, queried - started, queried.elapsed(), results.len());
+		}
+	}
+
+	output
+}
+
+/// Run a query on the current backend and send its results one batch at a time, still encoded,
+/// as they are read
+pub async fn run_query_batches(
+	mut query: BackendQuery,
+	batches: Sender<Vec<Box<RawValue>>>,
+) -> Result<(), BackendError> {
+	apply_scope(&mut query);
+
+	let tag = query.tag.clone();
+	let started = Instant::now();
+	let result = backend().query_batches(query, batches).await;
+
+	// The results are decoded by the receiver, while the next batch is read
+	let _ = QUERY_TIMINGS.try_with(|timings| {
+		timings.borrow_mut().push(QueryTiming {
+			tag,
+			started,
+			query_time: started.elapsed(),
+			decode_time: Duration::ZERO,
+		})
+	});
+
+	result
+}
+
+/// Log a query that exceeded the threshold, the bind variables are logged without their values
+/// unless they are enabled, the values of the sensitive fields are always redacted
+fn log_slow_query(query: &BackendQuery, query_time: Duration, decode_time: Duration, count: usize) {
+	let bind_vars = if CONFIG.slow_query_log_values {
+		redact_bind_vars(&query.bind_vars)
+	} else {
+		query
+			.bind_vars
+			.iter()
+			.map(|(key, value)| {
+				// Collections are bound by name, the other values may hold personal data
+				let shape = match value {
+					JsonValue::String(name) if key.starts_with('@') => name.as_str(),
+					JsonValue::Null => "null",
+					JsonValue::Bool(_) => "boolean",
+					JsonValue::Number(_) => "number",
+					JsonValue::String(_) => "string",
+					JsonValue::Array(_) => "array",
+					JsonValue::Object(_) => "object",
+				};
+
+				(key.clone(), JsonValue::from(shape))
+			})
+			.collect::<JsonMap<String, JsonValue>>()
+			.into()
+	};
+
+	let statement = redact_aql(&query.query);
+
+	{
+		let mut queries = SLOW_QUERIES.lock().unwrap();
+
+		if queries.len() == RECENT_SLOW_QUERIES {
+			queries.pop_front();
+		}
+
+		queries.push_back(SlowQuery {
+			operation: query.tag.clone(),
+			query: statement.clone(),
+			duration_ms: (query_time + decode_time).as_millis() as u64,
+			results: count,
+			finished_at: Utc::now().to_rfc3339(),
+		});
+	}
+
+	tracing::warn!(
+		target: "alchemy::slow_queries",
+		operation = %query.tag.as_deref().unwrap_or_default(),
+		query = %statement,
+		bind_vars = %bind_vars,
+		duration_ms = (query_time + decode_time).as_millis() as u64,
+		query_ms = query_time.as_millis() as u64,
+		decode_ms = decode_time.as_millis() as u64,
+		results = count,
+		"Slow query"
+	);
+}
@@ -0,0 +1,121 @@
+use std::time::Duration;
+
+use lazy_static::lazy_static;
+use reqwest::{Client, RequestBuilder};
+use serde::Deserialize;
+use uuid::Uuid;
+
+use crate::lib::database::database::database_name;
+use crate::lib::CONFIG;
+
+lazy_static! {
+	static ref CLIENT: Client = Client::builder()
+		.timeout(Duration::from_secs(5))
+		.build()
+		.expect("Failed to build the query cancellation client");
+}
+
+/// A query of the backend, cancelled in ArangoDB when it is dropped before all of its results
+/// are read, e.g. when the client disconnected and the resolver was dropped with it
+pub struct QueryGuard {
+	marker: String,
+	cursor: Option<String>,
+	done: bool,
+}
+
+impl QueryGuard {
+	pub fn new() -> Self {
+		QueryGuard {
+			marker: Uuid::new_v4().to_string(),
+			cursor: None,
+			done: false,
+		}
+	}
+
+	/// Comment of the AQL telling the query apart from the other running ones
+	pub fn marker(&self) -> String {
+		format!("/* query {} */", self.marker)
+	}
+
+	/// The cursor still holding results of the query
+	pub fn set_cursor(&mut self, cursor: Option<String>) {
+		self.cursor = cursor;
+	}
+
+	/// Every result was read, there is nothing to cancel
+	pub fn finish(mut self) {
+		self.done = true;
+	}
+}
+
+impl Drop for QueryGuard {
+	fn drop(&mut self) {
+		if self.done {
+			return;
+		}
+
+		let marker = self.marker();
+		let cursor = self.cursor.take();
+
+		// Nothing can be cancelled once the runtime is gone
+		if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+			runtime.spawn(async move {
+				if let Err(e) = cancel_query(&marker, cursor.as_deref()).await {
+					tracing::warn!(error = %e, "Failed to cancel an abandoned query");
+				}
+			});
+		}
+	}
+}
+
+/// A query listed by `/_api/query/current`
+#[derive(Deserialize)]
+struct RunningQuery {
+	id: String,
+	query: String,
+}
+
+fn request(method: reqwest::Method, path: &str) -> RequestBuilder {
+	let url = format!(
+		"{}/_db/{}/_api/{}",
+		CONFIG.db_host.trim_end_matches('/'),
+		database_name(),
+		path
+	);
+
+	CLIENT
+		.request(method, url)
+		.basic_auth(&CONFIG.db_user, Some(&CONFIG.db_pass))
+}
+
+/// Delete the cursor of a query that has run, or kill the query still running
+async fn cancel_query(marker: &str, cursor: Option<&str>) -> Result<(), reqwest::Error> {
+	if let Some(cursor) = cursor {
+		request(reqwest::Method::DELETE, &format!("cursor/{}", cursor))
+			.send()
+			.await?
+			.error_for_status()?;
+
+		tracing::debug!(cursor = %cursor, "Cursor of an abandoned query deleted");
+
+		return Ok(());
+	}
+
+	let running: Vec<RunningQuery> = request(reqwest::Method::GET, "query/current")
+		.send()
+		.await?
+		.error_for_status()?
+		.json()
+		.await?;
+
+	for query in running.iter().filter(|query| query.query.contains(marker)) {
+		request(reqwest::Method::DELETE, &format!("query/{}", query.id))
+			.send()
+			.await?
+			.error_for_status()?;
+
+		tracing::info!(query_id = %query.id, "Abandoned query killed");
+	}
+
+	Ok(())
+}
@@ -6,12 +6,18 @@ use crate::lib::CONFIG;
 use rust_arango::{Connection as ArangoConnection, Database as ArangoDatabase};
 
 pub struct ArangoDB {
+	pub name: String,
 	pub connection: ArangoConnection,
 	pub database: ArangoDatabase,
 }
 
 impl ArangoDB {
 	pub async fn new() -> ArangoDB {
+		ArangoDB::open(&CONFIG.db_name).await
+	}
+
+	/// Connect to a database of the configured server
+	pub async fn open(name: &str) -> ArangoDB {
 		let connection = ArangoConnection::establish_basic_auth(
 			&CONFIG.db_host.as_str(),
 			&CONFIG.db_user.as_str(),
@@ -20,9 +26,10 @@ impl ArangoDB {
 		.await
 		.unwrap();
 
-		let database = connection.db(&CONFIG.db_name.as_str()).await.unwrap();
+		let database = connection.db(name).await.unwrap();
 
 		ArangoDB {
+			name: name.to_string(),
 			connection,
 			database,
 		}
@@ -47,8 +54,47 @@ impl ArangoDB {
 	// }
 }
 
+#[cfg(test)]
+tokio::task_local! {
+	/// The database of the test running on the task, see `scope_database`
+	static SCOPED_DATABASE: &'static Arc<ArangoDB>;
+}
+
+/// The name of the database the engine connects to
+pub fn database_name() -> String {
+	#[cfg(test)]
+	if let Ok(name) = SCOPED_DATABASE.try_with(|database| database.name.clone()) {
+		return name;
+	}
+
+	CONFIG.db_name.clone()
+}
+
+/// Run a future against another database than `DB_NAME`, the tasks it spawns still use `DB_NAME`
+#[cfg(test)]
+pub async fn scope_database<F: std::future::Future>(
+	database: &'static Arc<ArangoDB>,
+	f: F,
+) -> F::Output {
+	SCOPED_DATABASE.scope(database, f).await
+}
+
+/// The connection of the engine, opened on first use
+pub struct Database(AsyncOnce<Arc<ArangoDB>>);
+
+impl Database {
+	pub async fn get(&'static self) -> &'static Arc<ArangoDB> {
+		#[cfg(test)]
+		if let Ok(database) = SCOPED_DATABASE.try_with(|database| *database) {
+			return database;
+		}
+
+		self.0.get().await
+	}
+}
+
 lazy_static::lazy_static! {
-	pub static ref DATABASE: AsyncOnce<Arc<ArangoDB>> = AsyncOnce::new(async {
+	pub static ref DATABASE: Database = Database(AsyncOnce::new(async {
 		   Arc::new(ArangoDB::new().await)
-	   });
+	   }));
 }
@@ -0,0 +1,169 @@
+use std::future::Future;
+use std::panic::{resume_unwind, AssertUnwindSafe};
+use std::sync::Arc;
+
+use anyhow::Error;
+use futures::FutureExt;
+use rust_arango::Connection as ArangoConnection;
+
+use crate::lib::database::database::scope_database;
+use crate::lib::database::ArangoDB;
+use crate::lib::CONFIG;
+
+/// A uniquely named database created for a test run, so that runs in parallel against the same
+/// ArangoDB instance do not see each other's documents
+pub struct EphemeralDatabase {
+	pub name: String,
+	connection: ArangoConnection,
+}
+
+impl EphemeralDatabase {
+	/// Create the database, `run` points the queries of a test to it
+	pub async fn create() -> Result<Self, Error> {
+		let connection = ArangoConnection::establish_basic_auth(
+			&CONFIG.db_host,
+			&CONFIG.db_user,
+			&CONFIG.db_pass,
+		)
+		.await?;
+
+		let name = format!("alchemy_test_{}", uuid::Uuid::new_v4().to_simple());
+
+		connection.create_database(&name).await?;
+
+		tracing::info!(database = %name, "Ephemeral database created");
+
+		Ok(EphemeralDatabase { name, connection })
+	}
+
+	/// Drop the database, at the end of the run
+	pub async fn teardown(self) -> Result<(), Error> {
+		self.connection.drop_database(&self.name).await?;
+
+		tracing::info!(database = %self.name, "Ephemeral database dropped");
+
+		Ok(())
+	}
+
+	/// Run a test against a new database, which is dropped afterwards even when the test panics.
+	/// The queries of the engine made on the task of the test use the database, so tests can run
+	/// one after the other or in parallel in a process, the tasks they spawn use `DB_NAME`
+	pub async fn run<F, Fut>(test: F) -> Result<(), Error>
+	where
+		F: FnOnce(String) -> Fut,
+		Fut: Future<Output = ()>,
+	{
+		let database = EphemeralDatabase::create().await?;
+
+		// The engine hands out its connection for the whole process, the one of a test is leaked
+		let connection: &'static Arc<ArangoDB> =
+			Box::leak(Box::new(Arc::new(ArangoDB::open(&database.name).await)));
+
+		let outcome = AssertUnwindSafe(scope_database(connection, test(database.name.clone())))
+			.catch_unwind()
+			.await;
+
+		database.teardown().await?;
+
+		if let Err(panic) = outcome {
+			resume_unwind(panic);
+		}
+
+		Ok(())
+	}
+}
+
+// Both tests need an ArangoDB instance, configured by `DB_HOST`, `DB_USER` and `DB_PASS`, run
+// them with `cargo test -- --ignored`
+#[cfg(test)]
+mod tests {
+	use std::panic::AssertUnwindSafe;
+	use std::sync::Mutex;
+
+	use futures::FutureExt;
+	use rust_arango::{AqlQuery, Connection as ArangoConnection};
+	use serde_json::{json, Value as JsonValue};
+
+	use super::EphemeralDatabase;
+	use crate::lib::database::DATABASE;
+	use crate::lib::seeds::Factory;
+	use crate::lib::CONFIG;
+
+	#[actix_web::test]
+	#[ignore = "requires an ArangoDB instance"]
+	async fn run_drops_the_database_after_a_panic() {
+		let name = Mutex::new(None);
+		let slot = &name;
+
+		let outcome = AssertUnwindSafe(EphemeralDatabase::run(|database| async move {
+			*slot.lock().unwrap() = Some(database);
+
+			panic!("The test failed");
+		}))
+		.catch_unwind()
+		.await;
+
+		assert!(outcome.is_err());
+
+		let name = name.into_inner().unwrap().unwrap();
+		let connection = ArangoConnection::establish_basic_auth(
+			&CONFIG.db_host,
+			&CONFIG.db_user,
+			&CONFIG.db_pass,
+		)
+		.await
+		.unwrap();
+
+		assert!(connection.db(&name).await.is_err());
+	}
+
+	#[actix_web::test]
+	#[ignore = "requires an ArangoDB instance"]
+	async fn each_run_queries_its_own_database() {
+		for _ in 0..2 {
+			EphemeralDatabase::run(|database| async move {
+				let connection = DATABASE.get().await;
+
+				assert_eq!(connection.name, database);
+				assert!(connection.database.accessible_collections().await.is_ok());
+			})
+			.await
+			.unwrap();
+		}
+	}
+
+	#[actix_web::test]
+	#[ignore = "requires an ArangoDB instance"]
+	async fn factories_insert_into_the_database() {
+		EphemeralDatabase::run(|_| async {
+			let database = &DATABASE.get().await.database;
+
+			database.create_collection("users").await.unwrap();
+
+			let factory = Factory::new("users", json!({ "email": "user{n}@example.com" }));
+			let users = factory
+				.insert_many(vec![json!({}), json!({ "admin": true })])
+				.await
+				.unwrap();
+			let user = factory.insert(json!({})).await.unwrap();
+
+			assert_eq!(users[0]["email"], "user1@example.com");
+			assert_eq!(users[1]["admin"], true);
+			assert_eq!(user["email"], "user3@example.com");
+
+			factory.cleanup().await.unwrap();
+
+			let count = AqlQuery::builder()
+				.query("RETURN LENGTH(@@collection)")
+				.bind_var("@collection", "users")
+				.build();
+
+			assert_eq!(
+				database.aql_query::<JsonValue>(count).await.unwrap(),
+				vec![json!(0)]
+			);
+		})
+		.await
+		.unwrap();
+	}
+}
@@ -0,0 +1,49 @@
+use anyhow::Error;
+use reqwest::{Method, StatusCode};
+use serde_json::{json, Value as JsonValue};
+
+use crate::lib::database::http::{api_error, send_api_request};
+use crate::lib::schema::SchemaSmartGraph;
+
+/// Call the graph API, a conflict means the change is already applied, e.g. the collection is
+/// already part of the graph
+async fn gharial(method: Method, path: &str, body: JsonValue) -> Result<(), Error> {
+	let response = send_api_request(method, &format!("gharial{}", path), body).await?;
+	let status = response.status();
+
+	if status.is_success() || status == StatusCode::CONFLICT {
+		return Ok(());
+	}
+
+	Err(api_error(response).await)
+}
+
+/// Create a SmartGraph without collections, only available on ArangoDB Enterprise
+pub async fn create_smart_graph(graph: &SchemaSmartGraph) -> Result<(), Error> {
+	gharial(Method::POST, "", graph.definition()).await
+}
+
+/// Create a document collection as a vertex collection of a SmartGraph, sharded by its attribute
+pub async fn add_vertex_collection(graph: &str, collection: &str) -> Result<(), Error> {
+	gharial(
+		Method::POST,
+		&format!("/{}/vertex", graph),
+		json!({ "collection": collection }),
+	)
+	.await
+}
+
+/// Create an edge collection linking two vertex collections of a SmartGraph
+pub async fn add_edge_definition(
+	graph: &str,
+	edge: &str,
+	from: &str,
+	to: &str,
+) -> Result<(), Error> {
+	gharial(
+		Method::POST,
+		&format!("/{}/edge", graph),
+		json!({ "collection": edge, "from": [from], "to": [to] }),
+	)
+	.await
+}
@@ -0,0 +1,50 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Error};
+use lazy_static::lazy_static;
+use reqwest::{Client, Method, Response};
+use serde_json::Value as JsonValue;
+
+use crate::lib::database::database::database_name;
+use crate::lib::CONFIG;
+
+lazy_static! {
+	static ref CLIENT: Client = Client::builder()
+		.timeout(Duration::from_secs(60))
+		.build()
+		.expect("Failed to build the ArangoDB HTTP client");
+}
+
+/// Call an endpoint of the HTTP API of the database that the driver does not expose, e.g.
+/// `gharial` for the graphs
+pub async fn send_api_request(
+	method: Method,
+	path: &str,
+	body: JsonValue,
+) -> Result<Response, Error> {
+	let url = format!(
+		"{}/_db/{}/_api/{}",
+		CONFIG.db_host.trim_end_matches('/'),
+		database_name(),
+		path
+	);
+
+	Ok(CLIENT
+		.request(method, url)
+		.basic_auth(&CONFIG.db_user, Some(&CONFIG.db_pass))
+		.json(&body)
+		.send()
+		.await?)
+}
+
+/// The error of a failed call, with the message of the database
+pub async fn api_error(response: Response) -> Error {
+	let status = response.status();
+	let error: JsonValue = response.json().await.unwrap_or_default();
+
+	anyhow!(
+		"The database answered {}: {}",
+		status,
+		error["errorMessage"].as_str().unwrap_or("no message")
+	)
+}
@@ -0,0 +1,317 @@
+use std::collections::HashMap;
+
+use anyhow::Error;
+use rust_arango::collection::CollectionType;
+use rust_arango::AqlQuery;
+use serde::Serialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::lib::database::arango::create_relationship;
+use crate::lib::database::schema::{DatabaseSchema, Rule};
+use crate::lib::database::DATABASE;
+use crate::lib::schema::{create_entry, get_all_collections, get_all_edges};
+use crate::lib::CONFIG;
+
+/// A collection of the database, along with how Alchemy uses it
+#[derive(Serialize, Debug)]
+pub struct IntrospectedCollection {
+	pub name: String,
+	pub edge: bool,
+	/// Exposed in the GraphQL API through an entry of `alchemy_collections` or `alchemy_edges`
+	pub tracked: bool,
+}
+
+/// The collections of the database, excluding the system and the Alchemy ones
+pub async fn introspect_collections() -> Result<Vec<IntrospectedCollection>, Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let tracked: Vec<String> = get_all_collections()
+		.await
+		.iter()
+		.map(|entry| &entry["name"])
+		.chain(get_all_edges().await.iter().map(|entry| &entry["edge"]))
+		.filter_map(|name| name.as_str().map(String::from))
+		.collect();
+
+	let mut collections: Vec<IntrospectedCollection> = db
+		.accessible_collections()
+		.await?
+		.into_iter()
+		.filter(|collection| !collection.is_system && !collection.name.starts_with("alchemy_"))
+		.map(|collection| IntrospectedCollection {
+			tracked: tracked.contains(&collection.name),
+			edge: collection.collection_type == CollectionType::Edge,
+			name: collection.name,
+		})
+		.collect();
+
+	collections.sort_by(|a, b| a.name.cmp(&b.name));
+
+	Ok(collections)
+}
+
+/// Metadata generated for the untracked collections
+#[derive(Debug, Default)]
+pub struct IntrospectionSummary {
+	pub collections: Vec<String>,
+	pub relationships: Vec<String>,
+}
+
+/// Strings written as ISO 8601 dates, e.g. `2022-01-31T12:00:00Z`
+fn looks_like_date_time(value: &str) -> bool {
+	let bytes = value.as_bytes();
+
+	bytes.len() >= 19
+		&& bytes[..19].iter().enumerate().all(|(i, b)| match i {
+			4 | 7 => *b == b'-',
+			10 => *b == b'T' || *b == b' ',
+			13 | 16 => *b == b':',
+			_ => b.is_ascii_digit(),
+		})
+}
+
+/// The JSON schema rule of a value, `None` for null
+fn infer_rule(value: &JsonValue) -> Option<JsonValue> {
+	let rule = match value {
+		JsonValue::Null => return None,
+		JsonValue::Bool(_) => json!({ "type": "boolean" }),
+		JsonValue::Number(number) if number.is_i64() || number.is_u64() => {
+			json!({ "type": "integer" })
+		}
+		JsonValue::Number(_) => json!({ "type": "number" }),
+		JsonValue::String(value) if looks_like_date_time(value) => {
+			json!({ "type": "string", "format": "date-time" })
+		}
+		JsonValue::String(_) => json!({ "type": "string" }),
+		JsonValue::Array(items) => {
+			let item_rule = items
+				.iter()
+				.filter_map(infer_rule)
+				.reduce(merge_rules)
+				.unwrap_or_else(|| json!({ "type": "string" }));
+
+			json!({ "type": "array", "items": item_rule })
+		}
+		JsonValue::Object(_) => json!({ "type": "object" }),
+	};
+
+	Some(rule)
+}
+
+/// The rule accepting the values of both rules, exposed as JSON when they have nothing in common
+fn merge_rules(a: JsonValue, b: JsonValue) -> JsonValue {
+	if a == b {
+		return a;
+	}
+
+	match (a["type"].as_str(), b["type"].as_str()) {
+		(Some("integer"), Some("number")) | (Some("number"), Some("integer")) => {
+			json!({ "type": "number" })
+		}
+		// Only some of the strings are dates
+		(Some("string"), Some("string")) => json!({ "type": "string" }),
+		(Some("array"), Some("array")) => json!({
+			"type": "array",
+			"items": merge_rules(a["items"].clone(), b["items"].clone())
+		}),
+		_ => json!({ "type": "object" }),
+	}
+}
+
+/// The rule of the documents, a property is required when every sampled document has it
+fn infer_collection_rule(documents: &[JsonValue]) -> Rule {
+	let mut properties: JsonMap<String, JsonValue> = JsonMap::new();
+	let mut counts: HashMap<String, usize> = HashMap::new();
+
+	for document in documents {
+		for (name, value) in document.as_object().into_iter().flatten() {
+			if name.starts_with('_') {
+				continue;
+			}
+
+			*counts.entry(name.clone()).or_default() += 1;
+
+			if let Some(rule) = infer_rule(value) {
+				let rule = match properties.remove(name) {
+					Some(previous) => merge_rules(previous, rule),
+					None => rule,
+				};
+
+				properties.insert(name.clone(), rule);
+			}
+		}
+	}
+
+	// Properties that were always null are still exposed
+	for name in counts.keys() {
+		if !properties.contains_key(name) {
+			properties.insert(name.clone(), json!({ "type": "string" }));
+		}
+	}
+
+	let mut required: Vec<String> = counts
+		.into_iter()
+		.filter(|(name, count)| {
+			*count == documents.len()
+				&& documents
+					.iter()
+					.all(|document| !document[name.as_str()].is_null())
+		})
+		.map(|(name, _)| name)
+		.collect();
+
+	required.sort();
+
+	Rule {
+		r#type: String::from("object"),
+		properties: JsonValue::Object(properties),
+		required,
+		additional_properties: true,
+	}
+}
+
+/// The validation rule of the collection when Alchemy understands every one of its properties
+async fn validation_rule(collection: &str) -> Result<Option<Rule>, Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let schema = match db
+		.collection(collection)
+		.await?
+		.properties()
+		.await?
+		.info
+		.schema
+	{
+		Some(schema) => schema,
+		None => return Ok(None),
+	};
+
+	let schema: DatabaseSchema = match serde_json::from_value(schema) {
+		Ok(schema) => schema,
+		Err(_) => return Ok(None),
+	};
+
+	let supported = schema
+		.rule
+		.properties
+		.as_object()
+		.map(|properties| {
+			!properties.is_empty()
+				&& properties
+					.values()
+					.all(|rule| rule["type"].is_string() || rule["enum"].is_array())
+		})
+		.unwrap_or(false);
+
+	Ok(if supported { Some(schema.rule) } else { None })
+}
+
+async fn sample_documents(collection: &str) -> Result<Vec<JsonValue>, Error> {
+	let query = AqlQuery::builder()
+		.query(
+			"FOR d IN @@collection
+				LIMIT @limit
+				RETURN d",
+		)
+		.bind_var("@collection", collection)
+		.bind_var("limit", CONFIG.introspection_sample_size)
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
+
+/// The relationships of the sampled edges, one per pair of linked collections
+fn infer_relationships(edge: &str, edges: &[JsonValue]) -> Vec<(String, String, String, String)> {
+	let mut pairs: HashMap<(String, String), (HashMap<String, usize>, HashMap<String, usize>)> =
+		HashMap::new();
+
+	for document in edges {
+		let (from, to) = match (document["_from"].as_str(), document["_to"].as_str()) {
+			(Some(from), Some(to)) => (from, to),
+			_ => continue,
+		};
+
+		let (from_collection, to_collection) = match (from.split_once('/'), to.split_once('/')) {
+			(Some((from_collection, _)), Some((to_collection, _))) => {
+				(from_collection.to_string(), to_collection.to_string())
+			}
+			_ => continue,
+		};
+
+		let (outgoing, incoming) = pairs.entry((from_collection, to_collection)).or_default();
+
+		*outgoing.entry(from.to_string()).or_default() += 1;
+		*incoming.entry(to.to_string()).or_default() += 1;
+	}
+
+	let single_pair = pairs.len() == 1;
+
+	let mut relationships: Vec<(String, String, String, String)> = pairs
+		.into_iter()
+		.map(|((from, to), (outgoing, incoming))| {
+			let max_outgoing = outgoing.values().max().copied().unwrap_or(0);
+			let max_incoming = incoming.values().max().copied().unwrap_or(0);
+
+			let relationship_type = match (max_outgoing > 1, max_incoming > 1) {
+				(false, false) => "one_to_one",
+				(true, false) => "one_to_many",
+				_ => "many_to_many",
+			};
+
+			let name = if single_pair {
+				edge.to_string()
+			} else {
+				format!("{}_{}", edge, to)
+			};
+
+			(name, from, to, relationship_type.to_string())
+		})
+		.collect();
+
+	relationships.sort();
+
+	relationships
+}
+
+/// Generate the metadata of the untracked collections, from their validation schema or from
+/// samples of their documents, and of the relationships of the untracked edge collections
+pub async fn introspect_database() -> Result<IntrospectionSummary, Error> {
+	let mut summary = IntrospectionSummary::default();
+
+	let collections = introspect_collections().await?;
+
+	for collection in collections.iter().filter(|c| !c.tracked && !c.edge) {
+		let rule = match validation_rule(&collection.name).await? {
+			Some(rule) => rule,
+			None => infer_collection_rule(&sample_documents(&collection.name).await?),
+		};
+
+		tracing::info!(collection = %collection.name, "Tracking the introspected collection");
+
+		create_entry(collection.name.clone(), rule).await;
+		summary.collections.push(collection.name.clone());
+	}
+
+	for collection in collections.iter().filter(|c| !c.tracked && c.edge) {
+		let edges = sample_documents(&collection.name).await?;
+
+		for (name, from, to, relationship_type) in infer_relationships(&collection.name, &edges) {
+			tracing::info!(edge = %collection.name, relationship = %name, %from, %to, "Tracking the introspected relationship");
+
+			create_relationship(
+				name.clone(),
+				collection.name.clone(),
+				from,
+				to,
+				relationship_type,
+				String::from("outbound"),
+				None,
+			)
+			.await?;
+
+			summary.relationships.push(name);
+		}
+	}
+
+	Ok(summary)
+}
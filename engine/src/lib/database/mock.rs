@@ -0,0 +1,85 @@
+use std::sync::Mutex;
+
+use futures::future::BoxFuture;
+use serde_json::value::{to_raw_value, RawValue};
+use serde_json::Value as JsonValue;
+
+use crate::lib::database::backend::{BackendError, BackendQuery, QueryBackend};
+
+/// The results, or the error, of the queries containing a fragment of AQL
+struct MockResponse {
+	fragment: String,
+	response: Result<Vec<JsonValue>, (u16, String)>,
+}
+
+/// An in-memory backend answering the queries with canned results, so that the generated
+/// operations run without ArangoDB
+#[derive(Default)]
+pub struct MockBackend {
+	responses: Mutex<Vec<MockResponse>>,
+	queries: Mutex<Vec<BackendQuery>>,
+}
+
+impl MockBackend {
+	pub fn new() -> Self {
+		Self::default()
+	}
+
+	/// Answer the queries containing the fragment, e.g. `INSERT`, with the results
+	pub fn respond(&self, fragment: &str, results: Vec<JsonValue>) -> &Self {
+		self.responses.lock().unwrap().push(MockResponse {
+			fragment: fragment.to_string(),
+			response: Ok(results),
+		});
+		self
+	}
+
+	/// Fail the queries containing the fragment with an ArangoDB error number
+	pub fn fail(&self, fragment: &str, error_num: u16, message: &str) -> &Self {
+		self.responses.lock().unwrap().push(MockResponse {
+			fragment: fragment.to_string(),
+			response: Err((error_num, message.to_string())),
+		});
+		self
+	}
+
+	/// The queries run so far, in order
+	pub fn queries(&self) -> Vec<BackendQuery> {
+		self.queries.lock().unwrap().clone()
+	}
+}
+
+impl QueryBackend for MockBackend {
+	fn query<'a>(
+		&'a self,
+		query: BackendQuery,
+	) -> BoxFuture<'a, Result<Vec<Box<RawValue>>, BackendError>> {
+		Box::pin(async move {
+			let response = self
+				.responses
+				.lock()
+				.unwrap()
+				.iter()
+				.find(|response| query.query.contains(&response.fragment))
+				.map(|response| response.response.clone());
+
+			self.queries.lock().unwrap().push(query);
+
+			// The queries without a response find nothing
+			match response {
+				Some(Ok(results)) => results
+					.iter()
+					.map(|result| {
+						to_raw_value(result).map_err(|e| BackendError::Other(e.to_string()))
+					})
+					.collect(),
+				Some(Err((error_num, message))) => Err(BackendError::Database {
+					code: 400,
+					error_num,
+					message,
+				}),
+				None => Ok(Vec::new()),
+			}
+		})
+	}
+}
@@ -4,10 +4,19 @@ pub mod schema;
 // pub mod collection;
 
 pub mod api;
-pub use api::generate_sdl;
+pub use api::{build_db_map, generate_sdl};
 
 pub mod aql;
+pub mod backend;
+pub mod cancel;
 pub mod database;
+#[cfg(test)]
+pub mod ephemeral;
+pub mod graphs;
+pub mod http;
+pub mod introspection;
+#[cfg(test)]
+pub mod mock;
 
 pub use database::ArangoDB;
 pub use database::DATABASE;
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+/// The GeoJSON geometry stored by a `Geo` property
+#[derive(Serialize, Deserialize, PartialEq, Default, Clone, Copy, Debug, GraphQLEnum)]
+pub enum SchemaGeoType {
+	#[default]
+	Point,
+	Polygon,
+}
+
+impl SchemaGeoType {
+	/// The format of the property in the collection schema
+	pub fn format(&self) -> String {
+		match self {
+			SchemaGeoType::Point => String::from("geo-point"),
+			SchemaGeoType::Polygon => String::from("geo-polygon"),
+		}
+	}
+}
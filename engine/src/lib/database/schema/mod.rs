@@ -13,5 +13,8 @@ pub use property::SchemaProperty;
 pub mod property_type;
 pub use property_type::SchemaPropertyType;
 
+pub mod geo_type;
+pub use geo_type::SchemaGeoType;
+
 pub mod native_type_array;
 pub use native_type_array::SchemaNativeTypeArray;
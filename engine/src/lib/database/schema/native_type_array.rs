@@ -5,4 +5,7 @@ use serde::{Deserialize, Serialize};
 pub struct SchemaNativeTypeArray {
 	pub r#type: String,
 	pub maximum: Option<i32>,
+	/// Regular expression the string items must match
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pattern: Option<String>,
 }
@@ -14,6 +14,27 @@ pub struct SchemaProperty {
 	pub max_length: Option<i32>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub r#enum: Option<Vec<String>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub format: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pattern: Option<String>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub minimum: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub maximum: Option<f64>,
+	/// JSON Schema draft 4 flags, as validated by ArangoDB
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub exclusive_minimum: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub exclusive_maximum: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub multiple_of: Option<f64>,
+	/// Digits of the decimals, and those of them after the point, not validated by ArangoDB
+	/// which checks the pattern derived from them
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub precision: Option<i32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub scale: Option<i32>,
 	/// Only set if the type is an Array
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub items: Option<SchemaNativeTypeArray>,
@@ -11,6 +11,16 @@ pub enum SchemaPropertyType {
 	Array,
 	Boolean,
 	Enum,
+	/// An ISO 8601 string
+	DateTime,
+	/// A file sent with the `Upload` scalar, stored as a reference object
+	File,
+	/// A GeoJSON point or polygon, backed by a geo index
+	Geo,
+	/// An exact decimal number, stored as a string
+	Decimal,
+	/// A text translated in several languages, stored as an object keyed by locale
+	Localized,
 }
 
 impl SchemaPropertyType {
@@ -21,6 +31,11 @@ impl SchemaPropertyType {
 			SchemaPropertyType::Array => String::from("array"),
 			SchemaPropertyType::Boolean => String::from("boolean"),
 			SchemaPropertyType::Enum => String::from("enum"),
+			SchemaPropertyType::DateTime => String::from("string"),
+			SchemaPropertyType::File => String::from("object"),
+			SchemaPropertyType::Geo => String::from("object"),
+			SchemaPropertyType::Decimal => String::from("string"),
+			SchemaPropertyType::Localized => String::from("object"),
 		}
 	}
 }
@@ -33,6 +48,11 @@ impl From<SchemaPropertyType> for SchemaNativeType {
 			SchemaPropertyType::Array => SchemaNativeType::String,
 			SchemaPropertyType::Boolean => SchemaNativeType::Boolean,
 			SchemaPropertyType::Enum => SchemaNativeType::String,
+			SchemaPropertyType::DateTime => SchemaNativeType::String,
+			SchemaPropertyType::File => SchemaNativeType::String,
+			SchemaPropertyType::Geo => SchemaNativeType::String,
+			SchemaPropertyType::Decimal => SchemaNativeType::String,
+			SchemaPropertyType::Localized => SchemaNativeType::String,
 		}
 	}
 }
@@ -7,3 +7,14 @@ pub struct DatabaseSchema {
 	pub level: String,
 	pub rule: Rule,
 }
+
+impl DatabaseSchema {
+	/// Wrap a rule with the strict validation level used by every Alchemy collection
+	pub fn from_rule(rule: Rule) -> Self {
+		DatabaseSchema {
+			message: String::from("Schema validation failed"),
+			level: String::from("strict"),
+			rule,
+		}
+	}
+}
@@ -0,0 +1,274 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use anyhow::{anyhow, Error};
+use async_once::AsyncOnce;
+use lazy_static::lazy_static;
+use rust_arango::AqlQuery;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+use uuid::Uuid;
+
+use crate::lib::changes::ChangeOperation;
+use crate::lib::database::DATABASE;
+use crate::lib::triggers::TriggerEvent;
+use crate::lib::CONFIG;
+
+/// Collection holding the mutation events until the relay publishes them
+pub const OUTBOX_COLLECTION: &str = "alchemy_outbox";
+
+/// Seconds a batch of events stays locked to the relay of an instance
+const OUTBOX_LOCK_SECS: u64 = 60;
+
+/// A message broker receiving the events of the successful mutations
+pub enum EventBroker {
+	#[cfg(feature = "kafka")]
+	Kafka(rdkafka::producer::FutureProducer),
+	#[cfg(feature = "nats")]
+	Nats(async_nats::Client),
+}
+
+lazy_static! {
+	/// Identifies the relay holding the lock of an event
+	static ref RELAY_ID: String = Uuid::new_v4().to_string();
+	static ref BROKER: AsyncOnce<Option<EventBroker>> = AsyncOnce::new(async {
+		match connect_broker().await {
+			Ok(broker) => broker,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to connect to the event broker");
+
+				None
+			}
+		}
+	});
+}
+
+#[allow(unused_variables)]
+async fn connect_broker() -> Result<Option<EventBroker>, Error> {
+	let broker = match CONFIG.event_broker.as_deref() {
+		Some(broker) => broker,
+		None => return Ok(None),
+	};
+
+	let url = CONFIG
+		.event_broker_url
+		.as_deref()
+		.ok_or_else(|| anyhow!("EVENT_BROKER_URL must be set with EVENT_BROKER"))?;
+
+	match broker {
+		#[cfg(feature = "kafka")]
+		"kafka" => {
+			let producer = rdkafka::ClientConfig::new()
+				.set("bootstrap.servers", url)
+				.set("message.timeout.ms", CONFIG.trigger_timeout_ms.to_string())
+				.create()?;
+
+			tracing::info!(broker, url, "Publishing the mutation events");
+
+			Ok(Some(EventBroker::Kafka(producer)))
+		}
+		#[cfg(feature = "nats")]
+		"nats" => {
+			let client = async_nats::connect(url).await?;
+
+			tracing::info!(broker, url, "Publishing the mutation events");
+
+			Ok(Some(EventBroker::Nats(client)))
+		}
+		_ => Err(anyhow!(
+			"Unsupported event broker {}, Alchemy may have been built without its feature",
+			broker
+		)),
+	}
+}
+
+/// Whether the mutations publish their events
+pub fn events_enabled() -> bool {
+	CONFIG.event_broker.is_some()
+}
+
+/// Whether the events are written in the outbox by the mutations, then published by the relay
+pub fn outbox_enabled() -> bool {
+	events_enabled() && CONFIG.event_outbox
+}
+
+/// The topic of a collection, or the NATS subject
+pub fn event_topic(collection: &str) -> String {
+	format!("{}{}", CONFIG.event_topic_prefix, collection)
+}
+
+/// Publish a message, the key orders the messages of a document on Kafka
+#[allow(unused_variables)]
+pub async fn publish_message(topic: &str, key: &str, payload: &[u8]) -> Result<(), Error> {
+	match BROKER.get().await {
+		#[cfg(feature = "kafka")]
+		Some(EventBroker::Kafka(producer)) => {
+			producer
+				.send(
+					rdkafka::producer::FutureRecord::to(topic)
+						.key(key)
+						.payload(payload),
+					std::time::Duration::from_millis(CONFIG.trigger_timeout_ms),
+				)
+				.await
+				.map_err(|(e, _)| e)?;
+
+			Ok(())
+		}
+		#[cfg(feature = "nats")]
+		Some(EventBroker::Nats(client)) => {
+			client
+				.publish(topic.to_string(), payload.to_vec().into())
+				.await?;
+
+			Ok(())
+		}
+		#[allow(unreachable_patterns)]
+		_ => Err(anyhow!("No event broker is connected")),
+	}
+}
+
+/// The JSON message of a change, with the state of the document before and after it
+pub fn event_payload(event: &TriggerEvent) -> serde_json::Value {
+	json!({
+		"collection": event.collection,
+		"tenant": event.tenant,
+		"operation": event.operation,
+		"old": event.old,
+		"new": event.new,
+		"actor": event.actor,
+	})
+}
+
+/// The key of the changed document
+pub fn event_key(event: &TriggerEvent) -> String {
+	event.new["_key"]
+		.as_str()
+		.or_else(|| event.old["_key"].as_str())
+		.unwrap_or_default()
+		.to_string()
+}
+
+/// Publish the event of a mutation in the background, a failure is logged and the event dropped,
+/// unless the outbox already holds it
+pub fn publish_event(event: TriggerEvent) {
+	if !events_enabled() || outbox_enabled() {
+		return;
+	}
+
+	rt::spawn(async move {
+		let topic = event_topic(&event.collection);
+		let payload = event_payload(&event).to_string();
+
+		if let Err(e) = publish_message(&topic, &event_key(&event), payload.as_bytes()).await {
+			tracing::error!(topic = %topic, error = %e, "Failed to publish the mutation event");
+		}
+	});
+}
+
+/// An event written by a mutation, kept until it is published
+#[derive(Deserialize)]
+struct OutboxEvent {
+	#[serde(rename = "_key")]
+	id: String,
+	collection: String,
+	#[serde(default)]
+	tenant: Option<String>,
+	key: String,
+	operation: ChangeOperation,
+	old: JsonValue,
+	new: JsonValue,
+	actor: Option<String>,
+}
+
+/// Lock the oldest pending events for this relay, the revision check of the update
+/// lets a single relay claim an event when several instances run
+async fn claim_outbox_events() -> Result<Vec<OutboxEvent>, Error> {
+	let query = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				FILTER e.status == 'pending'
+				FILTER e.lockedUntil == null || e.lockedUntil < DATE_NOW()
+				SORT e.createdAt
+				LIMIT @limit
+				UPDATE e WITH { lockedBy: @relay, lockedUntil: DATE_NOW() + @lockMs } IN @@collection
+				OPTIONS { ignoreRevs: false, ignoreErrors: true }
+				RETURN NEW",
+		)
+		.bind_var("@collection", OUTBOX_COLLECTION)
+		.bind_var("limit", CONFIG.event_outbox_batch_size)
+		.bind_var("relay", RELAY_ID.as_str())
+		.bind_var("lockMs", OUTBOX_LOCK_SECS * 1000)
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
+
+async fn release_outbox_event(key: &str, error: Option<String>) -> Result<(), Error> {
+	let query = AqlQuery::builder()
+		.query(
+			"LET e = DOCUMENT(@@collection, @key)
+				UPDATE e WITH MERGE(
+					{ attempts: e.attempts + 1, error: @error, lockedBy: null, lockedUntil: null },
+					@error == null ? { status: 'published', publishedAt: DATE_ISO8601(DATE_NOW()) } : {}
+				) IN @@collection",
+		)
+		.bind_var("@collection", OUTBOX_COLLECTION)
+		.bind_var("key", key)
+		.bind_var("error", json!(error))
+		.build();
+
+	let _: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
+
+/// Publish the claimed events in order, an event is only marked once the broker acknowledged it
+/// so a crash in between publishes it again
+async fn relay_outbox() -> Result<(), Error> {
+	for event in claim_outbox_events().await? {
+		let topic = event_topic(&event.collection);
+		let payload = event_payload(&TriggerEvent {
+			collection: event.collection,
+			tenant: event.tenant,
+			operation: event.operation,
+			old: event.old,
+			new: event.new,
+			actor: event.actor,
+		})
+		.to_string();
+
+		let error = match publish_message(&topic, &event.key, payload.as_bytes()).await {
+			Ok(_) => None,
+			Err(e) => {
+				tracing::warn!(topic = %topic, error = %e, "Failed to publish the outbox event");
+
+				Some(e.to_string())
+			}
+		};
+
+		release_outbox_event(&event.id, error).await?;
+	}
+
+	Ok(())
+}
+
+/// Publish the outbox events from the background until the server stops
+pub fn start_outbox_relay() {
+	if !outbox_enabled() {
+		return;
+	}
+
+	rt::spawn(async {
+		let mut interval =
+			rt::time::interval(Duration::from_millis(CONFIG.event_outbox_interval_ms));
+
+		loop {
+			interval.tick().await;
+
+			if let Err(e) = relay_outbox().await {
+				tracing::error!(error = %e, "Failed to relay the outbox events");
+			}
+		}
+	});
+}
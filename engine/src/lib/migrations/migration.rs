@@ -0,0 +1,106 @@
+use anyhow::Error;
+use rust_arango::AqlQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::value::Value as JsonValue;
+
+use crate::lib::database::arango::{
+	add_collection_property, create_collection, create_index, delete_collection, update_collection,
+};
+use crate::lib::database::DATABASE;
+use crate::lib::migrations::{drop_property, rename_property};
+use crate::lib::schema::{SchemaCollectionOptions, SchemaCollectionUpdate, SchemaDocumentProperty};
+
+/// A versioned migration loaded from a migration file
+#[derive(Serialize, Deserialize)]
+pub struct Migration {
+	pub version: u32,
+	pub name: String,
+	pub up: Vec<MigrationStep>,
+	#[serde(default)]
+	pub down: Vec<MigrationStep>,
+}
+
+/// A single step of a migration
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum MigrationStep {
+	CreateCollection {
+		name: String,
+		properties: Vec<SchemaDocumentProperty>,
+		#[serde(default)]
+		options: SchemaCollectionOptions,
+	},
+	DropCollection {
+		name: String,
+	},
+	AddProperty {
+		collection: String,
+		property: SchemaDocumentProperty,
+	},
+	/// Rename an attribute in the schema and in every document
+	RenameProperty {
+		collection: String,
+		from: String,
+		to: String,
+	},
+	/// Remove an attribute from the schema and from every document
+	DropProperty {
+		collection: String,
+		property: String,
+	},
+	UpdateCollection {
+		collection: String,
+		update: SchemaCollectionUpdate,
+	},
+	AddIndex {
+		collection: String,
+		fields: Vec<String>,
+		#[serde(default)]
+		unique: bool,
+	},
+	/// Raw AQL, used to backfill or transform existing documents
+	Aql {
+		query: String,
+	},
+}
+
+impl MigrationStep {
+	pub async fn apply(self) -> Result<(), Error> {
+		match self {
+			MigrationStep::CreateCollection {
+				name,
+				properties,
+				options,
+			} => create_collection(name, properties, options).await,
+			MigrationStep::DropCollection { name } => delete_collection(name).await,
+			MigrationStep::AddProperty {
+				collection,
+				property,
+			} => add_collection_property(collection, property).await,
+			MigrationStep::RenameProperty {
+				collection,
+				from,
+				to,
+			} => rename_property(collection, from, to).await,
+			MigrationStep::DropProperty {
+				collection,
+				property,
+			} => drop_property(collection, property).await,
+			MigrationStep::UpdateCollection { collection, update } => {
+				update_collection(collection, update).await
+			}
+			MigrationStep::AddIndex {
+				collection,
+				fields,
+				unique,
+			} => create_index(collection, fields, unique).await,
+			MigrationStep::Aql { query } => {
+				let aql = AqlQuery::builder().query(query.as_str()).build();
+
+				let _result: Vec<JsonValue> = DATABASE.get().await.database.aql_query(aql).await?;
+
+				Ok(())
+			}
+		}
+	}
+}
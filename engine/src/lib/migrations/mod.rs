@@ -0,0 +1,13 @@
+pub mod migration;
+pub use migration::{Migration, MigrationStep};
+
+pub mod properties;
+pub use properties::{drop_property, rename_property};
+
+pub mod records;
+pub use records::{
+	delete_record, get_applied_versions, get_completed_steps, insert_record, record_progress,
+};
+
+pub mod runner;
+pub use runner::{load_migrations, migrate, migration_status, rollback, MigrationStatus};
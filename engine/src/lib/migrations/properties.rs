@@ -0,0 +1,161 @@
+use anyhow::{anyhow, Error};
+use rust_arango::AqlQuery;
+use serde_json::value::Value as JsonValue;
+
+use crate::lib::database::arango::change_collection_schema;
+use crate::lib::database::DATABASE;
+
+/// Documents updated by each query of a rename or a drop
+const PROPERTY_BATCH_SIZE: u32 = 1000;
+
+/// Rename an attribute in every document of a collection and in its schema
+pub async fn rename_property(collection: String, from: String, to: String) -> Result<(), Error> {
+	// Both properties are valid while the documents are moved, neither of them is required
+	let mut required = false;
+
+	change_collection_schema(&collection, |schema| {
+		let properties = schema.rule.properties.as_object_mut().unwrap();
+
+		if properties.contains_key(&to) {
+			return Err(anyhow!(
+				"Collection {} already has a property {}",
+				collection,
+				to
+			));
+		}
+
+		let property = properties
+			.get(&from)
+			.cloned()
+			.ok_or_else(|| anyhow!("Collection {} has no property {}", collection, from))?;
+
+		properties.insert(to.clone(), property);
+
+		required = schema.rule.required.contains(&from);
+		schema.rule.required.retain(|name| name != &from);
+
+		Ok(())
+	})
+	.await?;
+
+	update_documents(
+		&collection,
+		&from,
+		"UPDATE d WITH { [@to]: d[@attribute], [@attribute]: null } IN @@collection",
+		Some(&to),
+	)
+	.await?;
+
+	change_collection_schema(&collection, |schema| {
+		schema
+			.rule
+			.properties
+			.as_object_mut()
+			.unwrap()
+			.remove(&from);
+
+		if required {
+			schema.rule.required.push(to.clone());
+		}
+
+		Ok(())
+	})
+	.await
+}
+
+/// Remove a property from the schema of a collection and its attribute from every document
+pub async fn drop_property(collection: String, property: String) -> Result<(), Error> {
+	// The schema changes first, the documents still holding the attribute validate once updated
+	change_collection_schema(&collection, |schema| {
+		if schema
+			.rule
+			.properties
+			.as_object_mut()
+			.unwrap()
+			.remove(&property)
+			.is_none()
+		{
+			return Err(anyhow!(
+				"Collection {} has no property {}",
+				collection,
+				property
+			));
+		}
+
+		schema.rule.required.retain(|name| name != &property);
+
+		Ok(())
+	})
+	.await?;
+
+	update_documents(
+		&collection,
+		&property,
+		"UPDATE d WITH { [@attribute]: null } IN @@collection",
+		None,
+	)
+	.await
+}
+
+/// Run an update on the documents holding an attribute, a batch at a time until none is left
+async fn update_documents(
+	collection: &str,
+	attribute: &str,
+	update: &str,
+	to: Option<&str>,
+) -> Result<(), Error> {
+	let database = DATABASE.get().await.database.clone();
+
+	let count_query = AqlQuery::builder()
+		.query("RETURN COUNT(FOR d IN @@collection FILTER HAS(d, @attribute) RETURN 1)")
+		.bind_var("@collection", collection)
+		.bind_var("attribute", attribute)
+		.build();
+	let total = database
+		.aql_query::<u64>(count_query)
+		.await?
+		.first()
+		.copied()
+		.unwrap_or(0);
+
+	// keepNull removes the attributes set to null
+	let query = format!(
+		"FOR d IN @@collection
+			FILTER HAS(d, @attribute)
+			LIMIT @limit
+			{} OPTIONS {{ keepNull: false }}
+			RETURN 1",
+		update
+	);
+	let mut updated = 0;
+
+	while updated < total {
+		let mut builder = AqlQuery::builder()
+			.query(query.as_str())
+			.bind_var("@collection", collection)
+			.bind_var("attribute", attribute)
+			.bind_var("limit", PROPERTY_BATCH_SIZE);
+
+		if let Some(to) = to {
+			builder = builder.bind_var("to", to);
+		}
+
+		let batch: Vec<JsonValue> = database.aql_query(builder.build()).await?;
+
+		if batch.is_empty() {
+			break;
+		}
+
+		updated += batch.len() as u64;
+
+		tracing::info!(
+			collection,
+			attribute,
+			updated,
+			total,
+			"Migrating the documents"
+		);
+	}
+
+	Ok(())
+}
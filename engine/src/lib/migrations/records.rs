@@ -0,0 +1,99 @@
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use anyhow::Error;
+use rust_arango::AqlQuery;
+use serde_json::value::Value as JsonValue;
+
+use crate::lib::database::arango::ensure_collection;
+use crate::lib::database::DATABASE;
+
+pub const MIGRATIONS_COLLECTION: &str = "alchemy_migrations";
+
+/// Get the versions of all of the applied migrations, in ascending order, a migration that
+/// failed halfway is not applied
+pub async fn get_applied_versions() -> Result<Vec<u32>, Error> {
+	ensure_collection(MIGRATIONS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR m IN @@collection
+				FILTER m.pending != true
+				SORT m.version ASC
+				RETURN m.version",
+		)
+		.bind_var("@collection", MIGRATIONS_COLLECTION)
+		.build();
+
+	let versions: Vec<u32> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(versions)
+}
+
+/// The number of steps already applied of a migration that failed halfway
+pub async fn get_completed_steps(version: u32) -> Result<usize, Error> {
+	let query = AqlQuery::builder()
+		.query(
+			"FOR m IN @@collection
+				FILTER m._key == @key && m.pending == true
+				RETURN m.steps",
+		)
+		.bind_var("@collection", MIGRATIONS_COLLECTION)
+		.bind_var("key", version.to_string())
+		.build();
+
+	let steps: Vec<usize> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(steps.into_iter().next().unwrap_or(0))
+}
+
+/// Remember the steps applied so far, so that a failed migration resumes after them
+pub async fn record_progress(version: u32, name: &str, steps: usize) -> Result<(), Error> {
+	upsert_record(serde_json::json!({
+		"_key": version.to_string(),
+		"version": version,
+		"name": name,
+		"pending": true,
+		"steps": steps,
+	}))
+	.await
+}
+
+pub async fn insert_record(version: u32, name: String) -> Result<(), Error> {
+	let applied_at = SystemTime::now().duration_since(UNIX_EPOCH)?.as_secs();
+
+	upsert_record(serde_json::json!({
+		"_key": version.to_string(),
+		"version": version,
+		"name": name,
+		"appliedAt": applied_at,
+	}))
+	.await
+}
+
+async fn upsert_record(document: JsonValue) -> Result<(), Error> {
+	let query = AqlQuery::builder()
+		.query(
+			"UPSERT { _key: @document._key }
+				INSERT @document
+				REPLACE @document IN @@collection",
+		)
+		.bind_var("@collection", MIGRATIONS_COLLECTION)
+		.bind_var("document", document)
+		.build();
+
+	let _result: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
+
+pub async fn delete_record(version: u32) -> Result<(), Error> {
+	let query = AqlQuery::builder()
+		.query("REMOVE @key IN @@collection")
+		.bind_var("@collection", MIGRATIONS_COLLECTION)
+		.bind_var("key", version.to_string())
+		.build();
+
+	let _result: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
@@ -0,0 +1,137 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+
+use super::{
+	delete_record, get_applied_versions, get_completed_steps, insert_record, record_progress,
+	Migration,
+};
+
+/// Load every `.yaml`/`.yml` migration file of a directory, sorted by version
+pub fn load_migrations(path: &str) -> Result<Vec<Migration>, Error> {
+	let mut migrations = Vec::new();
+
+	if !Path::new(path).is_dir() {
+		return Ok(migrations);
+	}
+
+	for entry in fs::read_dir(path)? {
+		let path = entry?.path();
+
+		let is_yaml = path
+			.extension()
+			.map(|ext| ext == "yaml" || ext == "yml")
+			.unwrap_or(false);
+
+		if !is_yaml {
+			continue;
+		}
+
+		let content = fs::read_to_string(&path)?;
+		let migration: Migration = serde_yaml::from_str(&content)
+			.map_err(|e| anyhow!("Invalid migration {}: {}", path.display(), e))?;
+
+		migrations.push(migration);
+	}
+
+	migrations.sort_by_key(|m| m.version);
+
+	for pair in migrations.windows(2) {
+		if pair[0].version == pair[1].version {
+			return Err(anyhow!("Duplicate migration version {}", pair[0].version));
+		}
+	}
+
+	Ok(migrations)
+}
+
+/// Apply every pending migration, returning the applied versions, the progress is recorded after
+/// each step so that a failed migration resumes after its completed steps
+pub async fn migrate(path: &str) -> Result<Vec<u32>, Error> {
+	let applied = get_applied_versions().await?;
+	let mut versions = Vec::new();
+
+	for migration in load_migrations(path)? {
+		if applied.contains(&migration.version) {
+			continue;
+		}
+
+		let completed = get_completed_steps(migration.version).await?;
+
+		tracing::info!(
+			version = migration.version,
+			name = %migration.name,
+			completed_steps = completed,
+			"Applying migration"
+		);
+
+		for (index, step) in migration.up.into_iter().enumerate().skip(completed) {
+			step.apply().await.map_err(|e| {
+				anyhow!(
+					"Migration {} ({}) failed at step {}: {}",
+					migration.version,
+					migration.name,
+					index + 1,
+					e
+				)
+			})?;
+
+			record_progress(migration.version, &migration.name, index + 1).await?;
+		}
+
+		insert_record(migration.version, migration.name).await?;
+		versions.push(migration.version);
+	}
+
+	Ok(versions)
+}
+
+/// Revert the last applied migration, returning its version
+pub async fn rollback(path: &str) -> Result<Option<u32>, Error> {
+	let last = match get_applied_versions().await?.last() {
+		Some(version) => *version,
+		None => return Ok(None),
+	};
+
+	let migration = load_migrations(path)?
+		.into_iter()
+		.find(|m| m.version == last)
+		.ok_or_else(|| anyhow!("Migration file for version {} not found", last))?;
+
+	tracing::info!(
+		version = migration.version,
+		name = %migration.name,
+		"Reverting migration"
+	);
+
+	for step in migration.down {
+		step.apply().await?;
+	}
+
+	delete_record(last).await?;
+
+	Ok(Some(last))
+}
+
+/// A migration file along with whether it was applied
+#[derive(GraphQLObject)]
+pub struct MigrationStatus {
+	pub version: i32,
+	pub name: String,
+	pub applied: bool,
+}
+
+/// The migrations of a directory, in ascending order
+pub async fn migration_status(path: &str) -> Result<Vec<MigrationStatus>, Error> {
+	let applied = get_applied_versions().await?;
+
+	Ok(load_migrations(path)?
+		.into_iter()
+		.map(|migration| MigrationStatus {
+			applied: applied.contains(&migration.version),
+			version: migration.version as i32,
+			name: migration.name,
+		})
+		.collect())
+}
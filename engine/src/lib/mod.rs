@@ -1,5 +1,24 @@
+pub mod changes;
+pub mod cli;
+pub mod cors;
 pub mod database;
+pub mod events;
+pub mod migrations;
+pub mod passwords;
+pub mod redaction;
+pub mod reload;
+pub mod scheduler;
 pub mod schema;
+pub mod secrets;
+pub mod seeds;
+#[cfg(unix)]
+pub mod socket;
+pub mod telemetry;
+pub mod tenants;
+pub mod tls;
+pub mod transfer;
+pub mod triggers;
+pub mod uploads;
 
 pub mod config;
 pub use config::CONFIG;
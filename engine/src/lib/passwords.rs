@@ -0,0 +1,28 @@
+use argon2::password_hash::{
+	rand_core::OsRng, Error, PasswordHash, PasswordHasher, PasswordVerifier, SaltString,
+};
+use argon2::Argon2;
+
+/// Hash a password with Argon2id into a PHC string
+pub fn hash_password(password: &str) -> Result<String, Error> {
+	let salt = SaltString::generate(&mut OsRng);
+
+	Ok(Argon2::default()
+		.hash_password(password.as_bytes(), &salt)?
+		.to_string())
+}
+
+/// Whether the password matches a PHC string, hashes that cannot be parsed never match
+pub fn verify_password(password: &str, hash: &str) -> bool {
+	match PasswordHash::new(hash) {
+		Ok(hash) => Argon2::default()
+			.verify_password(password.as_bytes(), &hash)
+			.is_ok(),
+		Err(_) => false,
+	}
+}
+
+/// Whether a value is already a PHC string, so that it is not hashed twice
+pub fn is_password_hash(value: &str) -> bool {
+	PasswordHash::new(value).is_ok()
+}
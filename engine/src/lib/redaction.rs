@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use graphql_parser::query::{Definition, OperationDefinition, Selection, SelectionSet, Value};
+use lazy_static::lazy_static;
+use regex::{Captures, Regex};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::lib::CONFIG;
+
+/// Replaces the values of the sensitive fields in the logs
+pub const REDACTED: &str = "[REDACTED]";
+
+lazy_static! {
+	static ref SENSITIVE: RwLock<SensitiveFields> = RwLock::new(SensitiveFields::default());
+}
+
+#[derive(Default)]
+struct SensitiveFields {
+	/// The names of the properties marked sensitive and of their fields, lowercased
+	names: HashSet<String>,
+	/// The string literals compared with the sensitive properties in the AQL queries
+	literals: Option<Regex>,
+}
+
+/// Replace the sensitive fields, with those of the metadata the schema is generated from
+pub fn set_sensitive_fields(names: HashSet<String>) {
+	let names: HashSet<String> = names
+		.iter()
+		.chain(CONFIG.log_redacted_fields.iter())
+		.map(|name| name.to_ascii_lowercase())
+		.collect();
+
+	// The filters inline their values in the queries, e.g. i_0.`email` == "..."
+	let literals = if names.is_empty() {
+		None
+	} else {
+		let alternatives: Vec<String> = names.iter().map(|name| regex::escape(name)).collect();
+
+		Regex::new(&format!(
+			r#"(?i)(i_\d+\.`(?:{})`[^"]{{0,40}}?)"(?:[^"\\]|\\.)*""#,
+			alternatives.join("|")
+		))
+		.ok()
+	};
+
+	*SENSITIVE.write().unwrap() = SensitiveFields { names, literals };
+}
+
+/// Whether the values of a field or property are kept out of the logs
+pub fn is_sensitive(name: &str) -> bool {
+	let name = name.to_ascii_lowercase();
+
+	// The configured fields are also checked before the first schema is generated
+	SENSITIVE.read().unwrap().names.contains(&name)
+		|| CONFIG
+			.log_redacted_fields
+			.iter()
+			.any(|field| field.eq_ignore_ascii_case(&name))
+}
+
+/// Replace the values of the sensitive fields, at any depth
+pub fn redact_json(value: &mut JsonValue) {
+	match value {
+		JsonValue::Object(fields) => {
+			for (name, value) in fields.iter_mut() {
+				if is_sensitive(name) {
+					*value = JsonValue::String(String::from(REDACTED));
+				} else {
+					redact_json(value);
+				}
+			}
+		}
+		JsonValue::Array(items) => items.iter_mut().for_each(redact_json),
+		_ => {}
+	}
+}
+
+/// The bind variables of a query as logged, the arguments named after a sensitive field and the
+/// sensitive fields of the bound documents redacted, the collections are bound by name
+pub fn redact_bind_vars(bind_vars: &JsonMap<String, JsonValue>) -> JsonValue {
+	let mut redacted = JsonMap::new();
+
+	for (key, value) in bind_vars {
+		let mut value = value.clone();
+
+		// The arguments are bound as `arg_{id}_{name}`
+		let name = key
+			.strip_prefix("arg_")
+			.and_then(|argument| argument.split_once('_'))
+			.map_or(key.as_str(), |(_, name)| name);
+
+		if !key.starts_with('@') && is_sensitive(name) {
+			value = JsonValue::String(String::from(REDACTED));
+		} else {
+			redact_json(&mut value);
+		}
+
+		redacted.insert(key.clone(), value);
+	}
+
+	JsonValue::Object(redacted)
+}
+
+/// An AQL query as logged, the strings compared with the sensitive properties redacted
+pub fn redact_aql(query: &str) -> String {
+	match &SENSITIVE.read().unwrap().literals {
+		Some(literals) => literals
+			.replace_all(query, |captures: &Captures| {
+				format!("{}\"{}\"", &captures[1], REDACTED)
+			})
+			.into_owned(),
+		None => query.to_string(),
+	}
+}
+
+/// A GraphQL document as logged, the literal values of the sensitive arguments and input fields
+/// redacted, `None` when it does not parse
+pub fn redact_document(document: &str) -> Option<String> {
+	let mut document = graphql_parser::parse_query::<String>(document).ok()?;
+
+	for definition in &mut document.definitions {
+		match definition {
+			Definition::Operation(operation) => {
+				let selection_set = match operation {
+					OperationDefinition::SelectionSet(selection_set) => selection_set,
+					OperationDefinition::Query(query) => {
+						for variable in &mut query.variable_definitions {
+							redact_default(&variable.name, &mut variable.default_value);
+						}
+
+						&mut query.selection_set
+					}
+					OperationDefinition::Mutation(mutation) => {
+						for variable in &mut mutation.variable_definitions {
+							redact_default(&variable.name, &mut variable.default_value);
+						}
+
+						&mut mutation.selection_set
+					}
+					OperationDefinition::Subscription(subscription) => {
+						for variable in &mut subscription.variable_definitions {
+							redact_default(&variable.name, &mut variable.default_value);
+						}
+
+						&mut subscription.selection_set
+					}
+				};
+
+				redact_selection_set(selection_set);
+			}
+			Definition::Fragment(fragment) => redact_selection_set(&mut fragment.selection_set),
+		}
+	}
+
+	Some(document.to_string())
+}
+
+fn redact_default(name: &str, default_value: &mut Option<Value<String>>) {
+	match default_value {
+		Some(value) if is_sensitive(name) => *value = Value::String(String::from(REDACTED)),
+		Some(value) => redact_value(value),
+		None => {}
+	}
+}
+
+fn redact_selection_set(selection_set: &mut SelectionSet<String>) {
+	for selection in &mut selection_set.items {
+		match selection {
+			Selection::Field(field) => {
+				redact_arguments(&mut field.arguments);
+				redact_selection_set(&mut field.selection_set);
+			}
+			Selection::InlineFragment(fragment) => {
+				redact_selection_set(&mut fragment.selection_set)
+			}
+			Selection::FragmentSpread(_) => {}
+		}
+	}
+}
+
+fn redact_arguments(arguments: &mut [(String, Value<String>)]) {
+	for (name, value) in arguments.iter_mut() {
+		if is_sensitive(name) && !matches!(value, Value::Variable(_)) {
+			*value = Value::String(String::from(REDACTED));
+		} else {
+			redact_value(value);
+		}
+	}
+}
+
+/// The variables are kept, their values are redacted with the other variables
+fn redact_value(value: &mut Value<String>) {
+	match value {
+		Value::Object(fields) => {
+			for (name, value) in fields.iter_mut() {
+				if is_sensitive(name) && !matches!(value, Value::Variable(_)) {
+					*value = Value::String(String::from(REDACTED));
+				} else {
+					redact_value(value);
+				}
+			}
+		}
+		Value::List(items) => items.iter_mut().for_each(redact_value),
+		_ => {}
+	}
+}
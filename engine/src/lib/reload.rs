@@ -0,0 +1,133 @@
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
+
+use actix_web::rt;
+use lazy_static::lazy_static;
+
+use crate::lib::config::{config_file_path, reload_config, Config};
+use crate::lib::telemetry::set_log_level;
+use crate::lib::CONFIG;
+
+lazy_static! {
+	static ref RUNTIME: RwLock<Arc<RuntimeSettings>> =
+		RwLock::new(Arc::new(RuntimeSettings::from_config(&CONFIG)));
+}
+
+/// The settings applied without restarting the server when the configuration file changes
+#[derive(Debug)]
+pub struct RuntimeSettings {
+	pub log_level: String,
+	pub rate_limit_per_ip: Option<u32>,
+	pub rate_limit_per_api_key: Option<u32>,
+	pub rate_limit_per_role: Vec<String>,
+	pub rate_limit_period: Duration,
+	pub cors_allowed_origins: Option<Vec<String>>,
+	pub response_cache_ttl: Duration,
+	/// TTL overrides of the root fields, configured as `field=seconds`
+	pub response_cache_ttls: HashMap<String, Duration>,
+}
+
+impl RuntimeSettings {
+	fn from_config(config: &Config) -> Self {
+		RuntimeSettings {
+			log_level: config.log_level.clone(),
+			rate_limit_per_ip: config.rate_limit_per_ip,
+			rate_limit_per_api_key: config.rate_limit_per_api_key,
+			rate_limit_per_role: config.rate_limit_per_role.clone().unwrap_or_default(),
+			rate_limit_period: config.rate_limit_period(),
+			cors_allowed_origins: config.cors_allowed_origins.clone(),
+			response_cache_ttl: Duration::from_secs(config.response_cache_ttl_secs),
+			response_cache_ttls: config
+				.response_cache_ttls
+				.iter()
+				.flatten()
+				.filter_map(|entry| {
+					let (field, seconds) = entry.split_once('=')?;
+
+					Some((
+						field.trim().to_string(),
+						Duration::from_secs(seconds.trim().parse().ok()?),
+					))
+				})
+				.collect(),
+		}
+	}
+}
+
+/// The current runtime settings, compare them with `Arc::ptr_eq` to detect a reload
+pub fn runtime() -> Arc<RuntimeSettings> {
+	RUNTIME.read().unwrap().clone()
+}
+
+/// Read the configuration again and apply its runtime settings, the other ones
+/// still need a restart, invalid configurations are ignored
+fn reload() {
+	let config = match reload_config() {
+		Ok(config) => config,
+		Err(e) => {
+			tracing::error!(error = %format!("{:#}", e), "Ignoring the invalid configuration");
+
+			return;
+		}
+	};
+
+	let settings = RuntimeSettings::from_config(&config);
+
+	if settings.log_level != runtime().log_level {
+		set_log_level(&settings.log_level);
+	}
+
+	*RUNTIME.write().unwrap() = Arc::new(settings);
+
+	tracing::info!("Configuration reloaded");
+}
+
+fn modified_at(path: &str) -> Option<SystemTime> {
+	std::fs::metadata(path)
+		.and_then(|metadata| metadata.modified())
+		.ok()
+}
+
+/// Reload the configuration on SIGHUP, and when the configuration file is modified
+pub fn watch_config() {
+	#[cfg(unix)]
+	rt::spawn(async {
+		use rt::signal::unix::{signal, SignalKind};
+
+		let mut hangups = match signal(SignalKind::hangup()) {
+			Ok(hangups) => hangups,
+			Err(e) => {
+				tracing::warn!(error = %e, "Failed to listen to SIGHUP");
+
+				return;
+			}
+		};
+
+		while hangups.recv().await.is_some() {
+			reload();
+		}
+	});
+
+	let path = match config_file_path() {
+		Some(path) if CONFIG.config_watch_interval_secs > 0 => path,
+		_ => return,
+	};
+
+	rt::spawn(async move {
+		let mut interval =
+			rt::time::interval(Duration::from_secs(CONFIG.config_watch_interval_secs));
+		let mut last_modified = modified_at(&path);
+
+		loop {
+			interval.tick().await;
+
+			let modified = modified_at(&path);
+
+			if modified != last_modified {
+				last_modified = modified;
+				reload();
+			}
+		}
+	});
+}
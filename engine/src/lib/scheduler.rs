@@ -0,0 +1,332 @@
+use std::str::FromStr;
+use std::time::Duration;
+
+use actix_web::rt;
+use anyhow::{anyhow, Error};
+use chrono::{DateTime, SecondsFormat, Utc};
+use cron::Schedule;
+use lazy_static::lazy_static;
+use reqwest::Client;
+use rust_arango::AqlQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+use uuid::Uuid;
+
+use crate::lib::database::arango::ensure_collection;
+use crate::lib::database::DATABASE;
+use crate::lib::CONFIG;
+
+/// Collection holding the one-off and cron events
+pub const SCHEDULED_EVENTS_COLLECTION: &str = "alchemy_scheduled_events";
+
+/// Events claimed by an instance on each tick
+const CLAIM_BATCH_SIZE: i32 = 20;
+
+lazy_static! {
+	/// Identifies the instance holding the lock of an event
+	static ref INSTANCE_ID: String = Uuid::new_v4().to_string();
+	static ref CLIENT: Client = Client::builder()
+		.timeout(Duration::from_millis(CONFIG.trigger_timeout_ms))
+		.build()
+		.expect("Failed to build the scheduler client");
+}
+
+/// An event firing a webhook or an AQL query, once at `runAt` or on a cron schedule
+#[derive(Serialize, Deserialize, GraphQLObject)]
+#[serde(rename_all = "camelCase")]
+pub struct ScheduledEvent {
+	pub name: String,
+	/// Cron expression, with an optional leading seconds field
+	pub cron: Option<String>,
+	pub run_at: Option<String>,
+	pub webhook: Option<String>,
+	pub aql: Option<String>,
+	/// JSON encoded payload sent to the webhook
+	pub payload: Option<String>,
+	/// `scheduled`, `done` or `failed`
+	pub status: String,
+	pub next_run_at: Option<String>,
+	pub last_run_at: Option<String>,
+	pub last_error: Option<String>,
+	#[serde(default)]
+	pub attempts: i32,
+}
+
+#[derive(GraphQLInputObject)]
+pub struct ScheduledEventInput {
+	pub name: String,
+	/// Either a cron expression, e.g. `0 3 * * *`, or the ISO 8601 date of a one-off event
+	pub cron: Option<String>,
+	pub run_at: Option<String>,
+	/// Either the URL receiving the event, or an AQL query to run
+	pub webhook: Option<String>,
+	pub aql: Option<String>,
+	pub payload: Option<String>,
+}
+
+fn format_date(date: DateTime<Utc>) -> String {
+	date.to_rfc3339_opts(SecondsFormat::Millis, true)
+}
+
+/// Parse a cron expression, the 5 fields expressions run at second 0
+fn parse_cron(expression: &str) -> Result<Schedule, Error> {
+	let expression = if expression.split_whitespace().count() == 5 {
+		format!("0 {}", expression)
+	} else {
+		expression.to_string()
+	};
+
+	Schedule::from_str(&expression).map_err(|e| anyhow!("Invalid cron expression: {}", e))
+}
+
+fn next_cron_run(expression: &str) -> Option<String> {
+	parse_cron(expression)
+		.ok()?
+		.after(&Utc::now())
+		.next()
+		.map(format_date)
+}
+
+/// Store an event, replacing the one with the same name
+pub async fn create_scheduled_event(input: ScheduledEventInput) -> Result<(), Error> {
+	let next_run_at = match (&input.cron, &input.run_at) {
+		(Some(cron), None) => {
+			parse_cron(cron)?;
+
+			next_cron_run(cron)
+		}
+		(None, Some(run_at)) => Some(format_date(
+			DateTime::parse_from_rfc3339(run_at)
+				.map_err(|e| anyhow!("Invalid date {}: {}", run_at, e))?
+				.with_timezone(&Utc),
+		)),
+		_ => return Err(anyhow!("Either cron or runAt must be set")),
+	};
+
+	if input.webhook.is_some() == input.aql.is_some() {
+		return Err(anyhow!("Either webhook or aql must be set"));
+	}
+
+	if let Some(payload) = &input.payload {
+		serde_json::from_str::<JsonValue>(payload)
+			.map_err(|e| anyhow!("Invalid JSON payload: {}", e))?;
+	}
+
+	ensure_collection(SCHEDULED_EVENTS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"UPSERT { name: @event.name }
+				INSERT @event
+				REPLACE @event
+				IN @@collection",
+		)
+		.bind_var("@collection", SCHEDULED_EVENTS_COLLECTION)
+		.bind_var(
+			"event",
+			json!({
+				"name": input.name,
+				"cron": input.cron,
+				"runAt": input.run_at,
+				"webhook": input.webhook,
+				"aql": input.aql,
+				"payload": input.payload,
+				"status": "scheduled",
+				"nextRunAt": next_run_at,
+				"attempts": 0,
+			}),
+		)
+		.build();
+
+	let _: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
+
+pub async fn delete_scheduled_event(name: String) -> Result<(), Error> {
+	ensure_collection(SCHEDULED_EVENTS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				FILTER e.name == @name
+				REMOVE e IN @@collection",
+		)
+		.bind_var("@collection", SCHEDULED_EVENTS_COLLECTION)
+		.bind_var("name", name)
+		.build();
+
+	let _: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
+
+pub async fn get_scheduled_events() -> Result<Vec<ScheduledEvent>, Error> {
+	ensure_collection(SCHEDULED_EVENTS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				SORT e.name
+				RETURN e",
+		)
+		.bind_var("@collection", SCHEDULED_EVENTS_COLLECTION)
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
+
+/// Lock the due events for this instance, the revision check of the update
+/// lets a single instance claim an event when several of them poll at once
+async fn claim_due_events() -> Result<Vec<ScheduledEvent>, Error> {
+	let now = Utc::now();
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				FILTER e.status == 'scheduled' && e.nextRunAt != null && e.nextRunAt <= @now
+				FILTER e.lockedUntil == null || e.lockedUntil < @now
+				LIMIT @limit
+				UPDATE e WITH { lockedBy: @instance, lockedUntil: @lockedUntil } IN @@collection
+				OPTIONS { ignoreRevs: false, ignoreErrors: true }
+				RETURN NEW",
+		)
+		.bind_var("@collection", SCHEDULED_EVENTS_COLLECTION)
+		.bind_var("now", format_date(now))
+		.bind_var(
+			"lockedUntil",
+			format_date(now + chrono::Duration::seconds(CONFIG.scheduler_lock_secs as i64)),
+		)
+		.bind_var("instance", INSTANCE_ID.as_str())
+		.bind_var("limit", CLAIM_BATCH_SIZE)
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
+
+async fn run_event(event: &ScheduledEvent) -> Result<(), Error> {
+	if let Some(url) = &event.webhook {
+		let payload = match &event.payload {
+			Some(payload) => serde_json::from_str(payload)?,
+			None => JsonValue::Null,
+		};
+
+		let response = CLIENT
+			.post(url)
+			.json(&json!({
+				"name": event.name,
+				"scheduledAt": event.next_run_at,
+				"payload": payload,
+			}))
+			.send()
+			.await?;
+
+		if !response.status().is_success() {
+			return Err(anyhow!("Webhook answered with {}", response.status()));
+		}
+	}
+
+	if let Some(aql) = &event.aql {
+		let _: Vec<JsonValue> = DATABASE.get().await.database.aql_str(aql.as_str()).await?;
+	}
+
+	Ok(())
+}
+
+/// Run a claimed event, then schedule its next run and release its lock, failed one-off
+/// events are retried with an exponential backoff
+async fn fire(event: ScheduledEvent) {
+	let result = run_event(&event).await;
+	let attempts = event.attempts + 1;
+
+	let (status, next_run_at) = match (&result, &event.cron) {
+		(_, Some(cron)) => ("scheduled", next_cron_run(cron)),
+		(Ok(_), None) => ("done", None),
+		(Err(_), None) if attempts as u32 > CONFIG.trigger_max_retries => ("failed", None),
+		(Err(_), None) => {
+			let backoff = CONFIG
+				.trigger_retry_base_ms
+				.saturating_mul(1u64 << (attempts as u32 - 1).min(16));
+
+			(
+				"scheduled",
+				Some(format_date(
+					Utc::now() + chrono::Duration::milliseconds(backoff as i64),
+				)),
+			)
+		}
+	};
+
+	let last_error = result.as_ref().err().map(|e| e.to_string());
+
+	match &result {
+		Ok(_) => tracing::info!(event = %event.name, "Scheduled event fired"),
+		Err(e) => tracing::error!(event = %event.name, error = %e, "Scheduled event failed"),
+	}
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				FILTER e.name == @name && e.lockedBy == @instance
+				UPDATE e WITH {
+					status: @status,
+					nextRunAt: @nextRunAt,
+					lastRunAt: DATE_ISO8601(DATE_NOW()),
+					lastError: @lastError,
+					attempts: @attempts,
+					lockedBy: null,
+					lockedUntil: null
+				} IN @@collection",
+		)
+		.bind_var("@collection", SCHEDULED_EVENTS_COLLECTION)
+		.bind_var("name", event.name.as_str())
+		.bind_var("instance", INSTANCE_ID.as_str())
+		.bind_var("status", status)
+		.bind_var("nextRunAt", json!(next_run_at))
+		.bind_var("lastError", json!(last_error))
+		.bind_var(
+			"attempts",
+			if result.is_ok() || event.cron.is_some() {
+				0
+			} else {
+				attempts
+			},
+		)
+		.build();
+
+	let updated: Result<Vec<JsonValue>, _> = DATABASE.get().await.database.aql_query(query).await;
+
+	if let Err(e) = updated {
+		tracing::error!(event = %event.name, error = ?e, "Failed to release the scheduled event");
+	}
+}
+
+/// Poll the due events, a disabled interval of 0 leaves them to the other instances
+pub fn start_scheduler() {
+	if CONFIG.scheduler_interval_secs == 0 {
+		return;
+	}
+
+	rt::spawn(async {
+		if let Err(e) = ensure_collection(SCHEDULED_EVENTS_COLLECTION).await {
+			tracing::error!(error = %e, "Failed to create the scheduled events collection");
+
+			return;
+		}
+
+		let mut interval = rt::time::interval(Duration::from_secs(CONFIG.scheduler_interval_secs));
+
+		loop {
+			interval.tick().await;
+
+			match claim_due_events().await {
+				Ok(events) => {
+					for event in events {
+						rt::spawn(fire(event));
+					}
+				}
+				Err(e) => tracing::error!(error = %e, "Failed to claim the scheduled events"),
+			}
+		}
+	});
+}
@@ -0,0 +1,4 @@
+/// Name of the shadow collection holding the change records of an audited collection
+pub fn get_history_collection_name(collection: &str) -> String {
+	format!("{}_history", collection)
+}
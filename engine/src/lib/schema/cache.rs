@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lib::schema::SchemaOperation;
+
+/// How long the results of a read operation of a collection are cached
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaCacheOptions {
+	/// `get` or `getAll`
+	pub operation: SchemaOperation,
+	pub ttl_secs: i32,
+}
+
+impl SchemaCacheOptions {
+	pub fn is_cacheable(&self) -> bool {
+		matches!(
+			self.operation,
+			SchemaOperation::Get | SchemaOperation::GetAll | SchemaOperation::Count
+		)
+	}
+}
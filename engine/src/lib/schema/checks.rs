@@ -0,0 +1,47 @@
+use anyhow::{anyhow, Error};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// A boolean AQL expression every written document must satisfy, the document is bound to `doc`
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaCheck {
+	pub name: String,
+	/// e.g. `doc.discount <= doc.price`
+	pub expression: String,
+	/// Error returned when the expression does not hold, a default one is derived from the name
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub message: Option<String>,
+}
+
+impl SchemaCheck {
+	/// Message of the AQL assertion failing when the expression does not hold
+	pub fn assertion(&self) -> String {
+		format!("failed check {}", self.name)
+	}
+
+	pub fn get_message(&self) -> String {
+		self.message
+			.clone()
+			.unwrap_or_else(|| format!("The check {} failed", self.name))
+	}
+}
+
+/// The expressions are embedded in the mutation queries, they may only read
+pub fn validate_checks(checks: &[SchemaCheck]) -> Result<(), Error> {
+	let operations = Regex::new(r"(?i)\b(INSERT|UPDATE|REPLACE|REMOVE|UPSERT|INTO)\b").unwrap();
+
+	for check in checks {
+		if check.name.is_empty() || !check.name.chars().all(|c| c.is_alphanumeric() || c == '_') {
+			return Err(anyhow!("Invalid check name {}", check.name));
+		}
+
+		if operations.is_match(&check.expression) {
+			return Err(anyhow!(
+				"The expression of the check {} must not modify data",
+				check.name
+			));
+		}
+	}
+
+	Ok(())
+}
@@ -0,0 +1,195 @@
+use anyhow::{anyhow, Error};
+use rust_arango::collection::options::KeyOptions;
+use rust_arango::collection::CollectionType;
+use serde::{Deserialize, Serialize};
+
+/// How ArangoDB generates the keys of the documents created without one
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, GraphQLEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum SchemaKeyGenerator {
+	/// Ascending numeric strings, not contiguous
+	Traditional,
+	/// Contiguous numbers from an offset, only on single server or single shard collections
+	Autoincrement,
+	Uuid,
+	/// Ascending hexadecimal strings of the same length, sorted like the numbers they encode
+	Padded,
+}
+
+impl SchemaKeyGenerator {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SchemaKeyGenerator::Traditional => "traditional",
+			SchemaKeyGenerator::Autoincrement => "autoincrement",
+			SchemaKeyGenerator::Uuid => "uuid",
+			SchemaKeyGenerator::Padded => "padded",
+		}
+	}
+}
+
+/// The generation of the document keys of a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaKeyOptions {
+	pub r#type: SchemaKeyGenerator,
+	/// Clients may create documents with their own keys, true by default
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub allow_user_keys: Option<bool>,
+	/// Step between two `autoincrement` keys
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub increment: Option<i32>,
+	/// First `autoincrement` key
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub offset: Option<i32>,
+}
+
+impl SchemaKeyOptions {
+	fn to_key_options(&self) -> KeyOptions {
+		KeyOptions {
+			key_type: Some(String::from(self.r#type.as_str())),
+			allow_user_keys: Some(self.allow_user_keys.unwrap_or(true)),
+			increment: self.increment.map(|increment| increment as u32),
+			offset: self.offset.map(|offset| offset as u32),
+			last_value: None,
+		}
+	}
+}
+
+/// How a collection is created on a cluster, the options left out use the ArangoDB defaults
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default, GraphQLInputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaCollectionOptions {
+	/// An edge collection, its documents link two documents through `_from` and `_to` which
+	/// the schema does not validate
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub edge: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub number_of_shards: Option<i32>,
+	/// Copies of each shard, including the leader
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub replication_factor: Option<i32>,
+	/// Attributes the documents are distributed by, `_key` by default
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub shard_keys: Option<Vec<String>>,
+	/// Writes return once they are synced to disk
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub wait_for_sync: Option<bool>,
+	/// The key generator, `traditional` by default
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub keys: Option<SchemaKeyOptions>,
+	/// The SmartGraph the collection is a vertex collection of, it is sharded like the graph
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub smart_graph: Option<String>,
+	/// Replicate the single shard of the collection on every server of an Enterprise cluster,
+	/// for small collections joined by the others, e.g. countries, so that the joins stay local
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub satellite: Option<bool>,
+}
+
+impl SchemaCollectionOptions {
+	pub fn is_edge(&self) -> bool {
+		self.edge.unwrap_or(false)
+	}
+
+	pub fn is_satellite(&self) -> bool {
+		self.satellite.unwrap_or(false)
+	}
+
+	pub fn get_collection_type(&self) -> CollectionType {
+		match self.is_edge() {
+			true => CollectionType::Edge,
+			false => CollectionType::Document,
+		}
+	}
+
+	pub fn get_number_of_shards(&self) -> u32 {
+		self.number_of_shards.unwrap_or(1).max(1) as u32
+	}
+
+	pub fn get_replication_factor(&self) -> u32 {
+		self.replication_factor.unwrap_or(1).max(1) as u32
+	}
+
+	pub fn get_shard_keys(&self) -> Vec<String> {
+		self.shard_keys
+			.clone()
+			.unwrap_or_else(|| vec![String::from("_key")])
+	}
+
+	pub fn get_wait_for_sync(&self) -> bool {
+		self.wait_for_sync.unwrap_or(false)
+	}
+
+	pub fn get_key_options(&self) -> KeyOptions {
+		match self.keys {
+			Some(ref keys) => keys.to_key_options(),
+			None => SchemaKeyOptions {
+				r#type: SchemaKeyGenerator::Traditional,
+				allow_user_keys: None,
+				increment: None,
+				offset: None,
+			}
+			.to_key_options(),
+		}
+	}
+
+	/// Reject the options ArangoDB would refuse when creating the collection
+	pub fn validate(&self) -> Result<(), Error> {
+		if self.is_satellite() {
+			if self.smart_graph.is_some() {
+				return Err(anyhow!(
+					"A satellite collection cannot be part of a SmartGraph"
+				));
+			}
+
+			if self.number_of_shards.is_some()
+				|| self.replication_factor.is_some()
+				|| self.shard_keys.is_some()
+			{
+				return Err(anyhow!(
+					"A satellite collection has a single shard replicated on every server"
+				));
+			}
+		}
+
+		if self.smart_graph.is_some() {
+			if self.is_edge() {
+				return Err(anyhow!(
+					"Edge collections join a SmartGraph through their relationships"
+				));
+			}
+
+			if self.number_of_shards.is_some() || self.shard_keys.is_some() {
+				return Err(anyhow!(
+					"The collections of a SmartGraph are sharded like the graph"
+				));
+			}
+		}
+
+		if let Some(ref keys) = self.keys {
+			let autoincrement = keys.r#type == SchemaKeyGenerator::Autoincrement;
+
+			if !autoincrement && (keys.increment.is_some() || keys.offset.is_some()) {
+				return Err(anyhow!(
+					"The increment and offset of the keys only apply to the autoincrement generator"
+				));
+			}
+
+			if autoincrement && self.get_number_of_shards() > 1 {
+				return Err(anyhow!(
+					"The autoincrement key generator cannot be used with several shards"
+				));
+			}
+
+			if keys.increment.map_or(false, |increment| increment < 1)
+				|| keys.offset.map_or(false, |offset| offset < 0)
+			{
+				return Err(anyhow!(
+					"The increment of the keys must be positive and their offset not negative"
+				));
+			}
+		}
+
+		Ok(())
+	}
+}
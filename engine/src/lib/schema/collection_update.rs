@@ -0,0 +1,43 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lib::schema::SchemaDocumentProperty;
+
+/// When ArangoDB applies the schema of a collection to the documents
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, GraphQLEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum SchemaValidationLevel {
+	/// The schema is not applied
+	None,
+	/// Only the new documents are validated
+	New,
+	/// The documents are validated unless they were invalid before the change
+	Moderate,
+	/// Every inserted or modified document is validated
+	Strict,
+}
+
+impl SchemaValidationLevel {
+	pub fn as_str(&self) -> &'static str {
+		match self {
+			SchemaValidationLevel::None => "none",
+			SchemaValidationLevel::New => "new",
+			SchemaValidationLevel::Moderate => "moderate",
+			SchemaValidationLevel::Strict => "strict",
+		}
+	}
+}
+
+/// The changes to the schema of an existing collection, the omitted parts are kept
+#[derive(Serialize, Deserialize, PartialEq, GraphQLInputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaCollectionUpdate {
+	/// Properties added to the collection, or replacing the rules of the property of the same name
+	#[serde(default)]
+	#[graphql(default)]
+	pub properties: Vec<SchemaDocumentProperty>,
+	/// Replaces the required properties, including those of the given properties
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub required: Option<Vec<String>>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub level: Option<SchemaValidationLevel>,
+}
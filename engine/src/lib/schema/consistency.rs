@@ -0,0 +1,393 @@
+use anyhow::Error;
+use rust_arango::collection::CollectionType;
+use rust_arango::index::{Index, IndexSettings};
+use serde_json::Value as JsonValue;
+
+use crate::lib::database::DATABASE;
+use crate::lib::schema::{
+	get_all_collections, get_all_edges, get_history_collection_name, get_search_view_name,
+	get_versions_collection_name, SchemaUniqueConstraint, UNIQUE_INDEX_PREFIX,
+};
+
+/// A difference between the metadata and the database
+#[derive(GraphQLObject)]
+pub struct SchemaDrift {
+	/// `collection`, `schema`, `index`, `searchView`, `relationship` or `untracked`
+	pub kind: String,
+	/// The collection or relationship that drifted
+	pub name: String,
+	pub message: String,
+	/// What reconciles the database with the metadata, e.g. `create collection users`
+	pub action: String,
+}
+
+/// How the database matches the metadata it is exposed with
+#[derive(GraphQLObject)]
+pub struct SchemaStatus {
+	/// The database has every collection, index and validation schema the metadata describes
+	pub consistent: bool,
+	pub drifts: Vec<SchemaDrift>,
+}
+
+fn drift(kind: &str, name: &str, message: String, action: String) -> SchemaDrift {
+	SchemaDrift {
+		kind: kind.to_string(),
+		name: name.to_string(),
+		message,
+		action,
+	}
+}
+
+/// The names of the properties whose rules differ between the metadata and the collection schema
+fn schema_differences(expected: &JsonValue, actual: &JsonValue) -> Vec<String> {
+	let mut names: Vec<String> = expected["properties"]
+		.as_object()
+		.into_iter()
+		.flatten()
+		.chain(actual["properties"].as_object().into_iter().flatten())
+		.map(|(name, _)| name)
+		.filter(|name| expected["properties"][name.as_str()] != actual["properties"][name.as_str()])
+		.cloned()
+		.collect();
+
+	// The order of the required properties does not matter
+	let required = |rule: &JsonValue| {
+		let mut required: Vec<String> = rule["required"]
+			.as_array()
+			.into_iter()
+			.flatten()
+			.filter_map(|name| name.as_str().map(String::from))
+			.collect();
+
+		required.sort();
+		required
+	};
+
+	if required(expected) != required(actual) {
+		names.push(String::from("required"));
+	}
+
+	if expected["additionalProperties"] != actual["additionalProperties"] {
+		names.push(String::from("additionalProperties"));
+	}
+
+	names.sort();
+	names.dedup();
+
+	names
+}
+
+/// The indexes the options of a collection entry need but the collection lacks, and the unique
+/// indexes of constraints the entry no longer has
+fn index_drifts(name: &str, entry: &JsonValue, indexes: &[Index]) -> Vec<SchemaDrift> {
+	let mut drifts = Vec::new();
+
+	let geo_properties = entry["schema"]["properties"]
+		.as_object()
+		.into_iter()
+		.flatten()
+		.filter(|(_, rule)| {
+			rule["format"]
+				.as_str()
+				.map_or(false, |format| format.starts_with("geo-"))
+		})
+		.map(|(property, _)| property);
+
+	for property in geo_properties {
+		let indexed = indexes.iter().any(|index| {
+			matches!(index.settings, IndexSettings::Geo { .. })
+				&& index.fields == [property.clone()]
+		});
+
+		if !indexed {
+			drifts.push(drift(
+				"index",
+				name,
+				format!("The geo property {} of {} is not indexed", property, name),
+				format!("create geo index of {}.{}", name, property),
+			));
+		}
+	}
+
+	let ttl_index = indexes
+		.iter()
+		.find(|index| matches!(index.settings, IndexSettings::Ttl { .. }));
+
+	match (entry["expiry"]["property"].as_str(), ttl_index) {
+		(Some(property), Some(index)) if index.fields != [property.to_string()] => {
+			drifts.push(drift(
+				"index",
+				name,
+				format!(
+					"The TTL index of {} is on {} instead of {}",
+					name,
+					index.fields.join(", "),
+					property
+				),
+				format!("replace the TTL index of {}", name),
+			))
+		}
+		(Some(_), None) => drifts.push(drift(
+			"index",
+			name,
+			format!("{} expires its documents but has no TTL index", name),
+			format!("create TTL index of {}", name),
+		)),
+		(None, Some(_)) => drifts.push(drift(
+			"index",
+			name,
+			format!("{} has a TTL index but no expiry", name),
+			format!("drop TTL index of {}", name),
+		)),
+		_ => {}
+	}
+
+	let constraints: Vec<SchemaUniqueConstraint> =
+		serde_json::from_value(entry["unique"].clone()).unwrap_or_default();
+
+	for constraint in &constraints {
+		let index_name = constraint.index_name();
+
+		if !indexes.iter().any(|index| index.name == index_name) {
+			drifts.push(drift(
+				"index",
+				name,
+				format!(
+					"The unique constraint on {} of {} has no index",
+					constraint.properties.join(", "),
+					name
+				),
+				format!("create unique index {} of {}", index_name, name),
+			));
+		}
+	}
+
+	for index in indexes {
+		let stale = index.name.starts_with(UNIQUE_INDEX_PREFIX)
+			&& !constraints
+				.iter()
+				.any(|constraint| constraint.index_name() == index.name);
+
+		if stale {
+			drifts.push(drift(
+				"index",
+				name,
+				format!(
+					"The index {} of {} backs no unique constraint",
+					index.name, name
+				),
+				format!("drop index {} of {}", index.name, name),
+			));
+		}
+	}
+
+	drifts
+}
+
+/// Compare the collections, relationships, indexes and validation schemas of the metadata with
+/// those of the database
+pub async fn check_schema_status() -> Result<SchemaStatus, Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let collections = db.accessible_collections().await?;
+
+	let views: Vec<String> = db
+		.list_views()
+		.await?
+		.into_iter()
+		.map(|view| view.name)
+		.collect();
+
+	let entries = get_all_collections().await;
+	let relationships = get_all_edges().await;
+
+	let mut drifts = Vec::new();
+
+	for entry in &entries {
+		let name = match entry["name"].as_str() {
+			Some(name) => name,
+			None => continue,
+		};
+
+		let collection = match collections.iter().find(|c| c.name == name) {
+			Some(collection) => collection,
+			None => {
+				drifts.push(drift(
+					"collection",
+					name,
+					format!("The collection {} does not exist", name),
+					format!("create collection {}", name),
+				));
+
+				continue;
+			}
+		};
+
+		let edge = entry["options"]["edge"].as_bool().unwrap_or(false);
+
+		if edge != (collection.collection_type == CollectionType::Edge) {
+			let (expected, actual) = if edge {
+				("an edge", "a document")
+			} else {
+				("a document", "an edge")
+			};
+
+			drifts.push(drift(
+				"collection",
+				name,
+				format!(
+					"{} is {} collection, the metadata describes {} collection",
+					name, actual, expected
+				),
+				format!("recreate {} as {} collection", name, expected),
+			));
+		}
+
+		let schema = db.collection(name).await?.properties().await?.info.schema;
+
+		match schema {
+			None => drifts.push(drift(
+				"schema",
+				name,
+				format!("{} has no validation schema", name),
+				format!("set the validation schema of {} from the metadata", name),
+			)),
+			Some(schema) => {
+				let differences = schema_differences(&entry["schema"], &schema["rule"]);
+
+				if !differences.is_empty() {
+					drifts.push(drift(
+						"schema",
+						name,
+						format!(
+							"The validation schema of {} differs from the metadata on {}",
+							name,
+							differences.join(", ")
+						),
+						format!("set the validation schema of {} from the metadata", name),
+					));
+				}
+			}
+		}
+
+		let indexes = db.indexes(name).await?.indexes;
+
+		drifts.extend(index_drifts(name, entry, &indexes));
+
+		if !entry["search"].is_null() && !views.contains(&get_search_view_name(name)) {
+			drifts.push(drift(
+				"searchView",
+				name,
+				format!("The search view of {} does not exist", name),
+				format!("create search view of {}", name),
+			));
+		}
+	}
+
+	let tracked: Vec<&str> = entries
+		.iter()
+		.filter_map(|entry| entry["name"].as_str())
+		.collect();
+
+	for relationship in &relationships {
+		let (name, edge) = match (relationship["name"].as_str(), relationship["edge"].as_str()) {
+			(Some(name), Some(edge)) => (name, edge),
+			_ => continue,
+		};
+
+		match collections.iter().find(|c| c.name == edge) {
+			None => drifts.push(drift(
+				"relationship",
+				name,
+				format!("The edge collection {} of {} does not exist", edge, name),
+				format!("create edge collection {} of {}", edge, name),
+			)),
+			Some(collection) if collection.collection_type != CollectionType::Edge => {
+				drifts.push(drift(
+					"relationship",
+					name,
+					format!(
+						"The collection {} of {} is not an edge collection",
+						edge, name
+					),
+					format!("recreate {} as an edge collection", edge),
+				))
+			}
+			Some(_) => {}
+		}
+
+		for end in ["from", "to"] {
+			if let Some(collection) = relationship[end].as_str() {
+				if !tracked.contains(&collection) {
+					drifts.push(drift(
+						"relationship",
+						name,
+						format!("{} links the unknown collection {}", name, collection),
+						format!(
+							"create collection {} or delete relationship {}",
+							collection, name
+						),
+					));
+				}
+			}
+		}
+	}
+
+	// The history and versions of the tracked collections are managed by Alchemy
+	let managed: Vec<String> = tracked
+		.iter()
+		.flat_map(|name| {
+			[
+				get_history_collection_name(name),
+				get_versions_collection_name(name),
+			]
+		})
+		.chain(
+			relationships
+				.iter()
+				.filter_map(|relationship| relationship["edge"].as_str().map(String::from)),
+		)
+		.collect();
+
+	for collection in &collections {
+		let untracked = !collection.is_system
+			&& !collection.name.starts_with("alchemy_")
+			&& !tracked.contains(&collection.name.as_str())
+			&& !managed.contains(&collection.name);
+
+		if untracked {
+			drifts.push(drift(
+				"untracked",
+				&collection.name,
+				format!("The collection {} has no metadata", collection.name),
+				String::from("run alchemy introspect"),
+			));
+		}
+	}
+
+	Ok(SchemaStatus {
+		consistent: drifts.is_empty(),
+		drifts,
+	})
+}
+
+/// Log how the database drifted from the metadata, a failed check does not stop the startup
+pub async fn log_schema_status() {
+	match check_schema_status().await {
+		Ok(status) if status.consistent => {
+			tracing::info!("The database matches the metadata");
+		}
+		Ok(status) => {
+			for drift in &status.drifts {
+				tracing::warn!(
+					kind = %drift.kind,
+					name = %drift.name,
+					action = %drift.action,
+					"{}",
+					drift.message
+				);
+			}
+		}
+		Err(e) => tracing::error!(error = %e, "Failed to compare the database with the metadata"),
+	}
+}
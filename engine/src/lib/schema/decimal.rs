@@ -0,0 +1,96 @@
+/// A decimal number written as a string, e.g. `-12.50`, parsed without losing digits
+pub struct DecimalValue<'a> {
+	pub negative: bool,
+	/// Digits before the point, without the leading zeros
+	pub integer: &'a str,
+	/// Digits after the point, without the trailing zeros
+	pub fraction: &'a str,
+}
+
+impl<'a> DecimalValue<'a> {
+	/// `None` unless the value is an optional minus sign, digits, and an optional point followed
+	/// by digits
+	pub fn parse(value: &'a str) -> Option<Self> {
+		let (negative, unsigned) = match value.strip_prefix('-') {
+			Some(unsigned) => (true, unsigned),
+			None => (false, value),
+		};
+
+		let (integer, fraction) = match unsigned.split_once('.') {
+			Some((_, "")) => return None,
+			Some((integer, fraction)) => (integer, fraction),
+			None => (unsigned, ""),
+		};
+
+		let is_digits = |part: &str| part.bytes().all(|b| b.is_ascii_digit());
+
+		if integer.is_empty() || !is_digits(integer) || !is_digits(fraction) {
+			return None;
+		}
+
+		Some(DecimalValue {
+			negative,
+			integer: integer.trim_start_matches('0'),
+			fraction: fraction.trim_end_matches('0'),
+		})
+	}
+
+	/// The digits after the point that are not trailing zeros
+	pub fn scale(&self) -> usize {
+		self.fraction.len()
+	}
+
+	/// The value as it is stored, with exactly `scale` digits after the point when it is set, so
+	/// that equal values are equal strings
+	pub fn to_canonical(&self, scale: Option<usize>) -> String {
+		let fraction = match scale {
+			Some(scale) => format!("{:0<width$}", self.fraction, width = scale),
+			None => self.fraction.to_string(),
+		};
+
+		let integer = if self.integer.is_empty() {
+			"0"
+		} else {
+			self.integer
+		};
+		let zero = self.integer.is_empty() && self.fraction.is_empty();
+		let sign = if self.negative && !zero { "-" } else { "" };
+
+		if fraction.is_empty() {
+			format!("{}{}", sign, integer)
+		} else {
+			format!("{}{}.{}", sign, integer, fraction)
+		}
+	}
+}
+
+/// The digits after the point of the stored decimals, none without precision nor scale
+pub fn decimal_scale(precision: Option<i32>, scale: Option<i32>) -> Option<usize> {
+	scale
+		.or(precision.map(|_| 0))
+		.map(|scale| scale.max(0) as usize)
+}
+
+/// The pattern ArangoDB checks the stored decimals against, they are canonical and have at most
+/// `precision` digits, `scale` of them after the point
+pub fn decimal_pattern(precision: Option<i32>, scale: Option<i32>) -> String {
+	let fraction_digits = decimal_scale(precision, scale);
+
+	let integer = match precision {
+		Some(precision) => {
+			match (precision.max(1) as usize).saturating_sub(fraction_digits.unwrap_or(0)) {
+				0 => String::from("0"),
+				digits => format!("(0|[1-9][0-9]{{0,{}}})", digits - 1),
+			}
+		}
+		None => String::from("(0|[1-9][0-9]*)"),
+	};
+
+	let fraction = match fraction_digits {
+		Some(0) => String::new(),
+		Some(digits) => format!(r"\.[0-9]{{{}}}", digits),
+		None => String::from(r"(\.[0-9]*[1-9])?"),
+	};
+
+	format!("^-?{}{}$", integer, fraction)
+}
@@ -0,0 +1,111 @@
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::lib::schema::SchemaOperation;
+
+/// `@rename` of a generated operation, e.g. `listAuthors` instead of `getAllAuthors`
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaOperationDirective {
+	pub operation: SchemaOperation,
+	pub rename: String,
+}
+
+/// How a property is exposed by the generated API
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaFieldDirective {
+	pub property: String,
+	/// `@rename`, the name of the GraphQL field, the stored property keeps its name
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub rename: Option<String>,
+	/// `@hidden`, left out of the types and inputs
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub hidden: Option<bool>,
+	/// `@readonly`, left out of the mutation inputs
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub readonly: Option<bool>,
+	/// `@sensitive`, its values are redacted from the logged operations and queries
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub sensitive: Option<bool>,
+}
+
+/// Customizations of the schema generated from a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, Default, GraphQLInputObject)]
+pub struct SchemaDirectives {
+	pub operations: Option<Vec<SchemaOperationDirective>>,
+	pub fields: Option<Vec<SchemaFieldDirective>>,
+	/// How one document is named in the generated operations, e.g. `pandey` for `getPandey`
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub singular: Option<String>,
+	/// How several documents are named in the generated operations, e.g. `pandeys` for
+	/// `getAllPandeys`
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub plural: Option<String>,
+}
+
+/// Whether a name can be given to a GraphQL field or operation
+pub fn is_graphql_name(name: &str) -> bool {
+	let mut chars = name.chars();
+
+	matches!(chars.next(), Some(c) if c == '_' || c.is_ascii_alphabetic())
+		&& chars.all(|c| c == '_' || c.is_ascii_alphanumeric())
+		&& !name.starts_with("__")
+}
+
+impl SchemaDirectives {
+	pub fn field(&self, property: &str) -> Option<&SchemaFieldDirective> {
+		self.fields
+			.iter()
+			.flatten()
+			.find(|directive| directive.property == property)
+	}
+
+	/// Check the new names, a renamed field cannot take the name of another field
+	/// of the collection, whose properties are given
+	pub fn validate(&self, properties: &[String]) -> Result<(), Error> {
+		for name in self.singular.iter().chain(self.plural.iter()) {
+			if !is_graphql_name(name) {
+				return Err(anyhow!("Invalid entity name {}", name));
+			}
+		}
+
+		let mut operations: Vec<&str> = Vec::new();
+
+		for directive in self.operations.iter().flatten() {
+			if !is_graphql_name(&directive.rename) {
+				return Err(anyhow!("Invalid operation name {}", directive.rename));
+			}
+
+			if operations.contains(&directive.rename.as_str()) {
+				return Err(anyhow!("Duplicate operation name {}", directive.rename));
+			}
+
+			operations.push(&directive.rename);
+		}
+
+		let mut fields: Vec<&str> = Vec::new();
+
+		for property in properties {
+			let directive = self.field(property);
+
+			if directive.and_then(|d| d.hidden).unwrap_or(false) {
+				continue;
+			}
+
+			let name = match directive.and_then(|d| d.rename.as_deref()) {
+				Some(rename) if !is_graphql_name(rename) => {
+					return Err(anyhow!("Invalid field name {} of {}", rename, property));
+				}
+				Some(rename) => rename,
+				None => property,
+			};
+
+			if fields.contains(&name) {
+				return Err(anyhow!("Duplicate field name {}", name));
+			}
+
+			fields.push(name);
+		}
+
+		Ok(())
+	}
+}
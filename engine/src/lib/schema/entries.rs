@@ -6,6 +6,7 @@ use serde_json::{to_value as toJsonValue, value::Value as JsonValue};
 use rust_arango::AqlQuery;
 
 use crate::lib::database::schema::rule::Rule;
+use crate::lib::schema::versions::metadata_changed;
 
 #[derive(Serialize, Deserialize, Default, Derivative)]
 pub struct AlchemyCollectionEntry {
@@ -77,6 +78,8 @@ pub async fn create_entry(name: String, schema_rule: Rule) {
 		.aql_query(alchemy_entry)
 		.await
 		.unwrap();
+
+	metadata_changed().await;
 }
 
 pub async fn delete_entry(name: String) {
@@ -99,4 +102,106 @@ pub async fn delete_entry(name: String) {
 		.aql_query(alchemy_entry)
 		.await
 		.unwrap();
+
+	metadata_changed().await;
+}
+
+/// Set (or clear with `None`) the search view metadata of a collection entry
+pub async fn update_entry_search(name: String, search: Option<JsonValue>) {
+	let alchemy_entry = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				FILTER e.name == @name
+				UPDATE e WITH { search: @search } IN @@collection
+				OPTIONS { keepNull: false }
+		",
+		)
+		.bind_var("@collection", "alchemy_collections")
+		.bind_var("name", name)
+		.bind_var("search", search.unwrap_or(JsonValue::Null))
+		.build();
+
+	let _alchemy_entry_document: Vec<JsonValue> = DATABASE
+		.get()
+		.await
+		.database
+		.aql_query(alchemy_entry)
+		.await
+		.unwrap();
+
+	metadata_changed().await;
+}
+
+/// Replace the stored schema rule of a collection entry
+pub async fn update_entry_schema(name: String, schema_rule: Rule) {
+	let alchemy_entry = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				FILTER e.name == @name
+				UPDATE e WITH { schema: @schema } IN @@collection
+				OPTIONS { mergeObjects: false }
+		",
+		)
+		.bind_var("@collection", "alchemy_collections")
+		.bind_var("name", name)
+		.bind_var("schema", toJsonValue(&schema_rule).unwrap())
+		.build();
+
+	let _alchemy_entry_document: Vec<JsonValue> = DATABASE
+		.get()
+		.await
+		.database
+		.aql_query(alchemy_entry)
+		.await
+		.unwrap();
+
+	metadata_changed().await;
+}
+
+/// Get the entry of a single collection
+pub async fn get_collection_entry(name: &str) -> Option<JsonValue> {
+	let entry_query = AqlQuery::builder()
+		.query(
+			"FOR entry IN alchemy_collections
+				FILTER entry.name == @name
+				LIMIT 1
+				RETURN entry",
+		)
+		.bind_var("name", name)
+		.build();
+
+	let entries: Vec<JsonValue> = DATABASE
+		.get()
+		.await
+		.database
+		.aql_query(entry_query)
+		.await
+		.unwrap();
+
+	entries.into_iter().next()
+}
+
+/// Merge a patch of options into a collection entry
+pub async fn update_entry(name: String, patch: JsonValue) {
+	let alchemy_entry = AqlQuery::builder()
+		.query(
+			"FOR e IN @@collection
+				FILTER e.name == @name
+				UPDATE e WITH @patch IN @@collection
+		",
+		)
+		.bind_var("@collection", "alchemy_collections")
+		.bind_var("name", name)
+		.bind_var("patch", patch)
+		.build();
+
+	let _alchemy_entry_document: Vec<JsonValue> = DATABASE
+		.get()
+		.await
+		.database
+		.aql_query(alchemy_entry)
+		.await
+		.unwrap();
+
+	metadata_changed().await;
 }
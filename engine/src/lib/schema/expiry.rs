@@ -0,0 +1,21 @@
+use serde::{Deserialize, Serialize};
+
+/// The TTL options for a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, GraphQLInputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaExpiryOptions {
+	/// Property holding a unix timestamp in seconds or an ISO 8601 date
+	pub property: String,
+	/// Seconds after the property value when the document expires, defaults to 0
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub expire_after: Option<i32>,
+	/// Hide expired documents that have not been purged yet by ArangoDB
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub hide_expired: Option<bool>,
+}
+
+impl SchemaExpiryOptions {
+	pub fn get_expire_after(&self) -> i32 {
+		self.expire_after.unwrap_or(0)
+	}
+}
@@ -0,0 +1,235 @@
+use std::fs;
+
+use anyhow::{anyhow, Error};
+use serde::Deserialize;
+use serde_json::to_value as toJsonValue;
+
+use crate::lib::database::arango::{
+	add_collection_property, create_collection, create_relationship, create_search_view,
+	delete_search_view, set_collection_audit, set_collection_cache, set_collection_checks,
+	set_collection_directives, set_collection_expiry, set_collection_field_permissions,
+	set_collection_operations, set_collection_permissions, set_collection_references,
+	set_collection_row_filters, set_collection_strict, set_collection_time_limits,
+	set_collection_timestamps, set_collection_triggers, set_collection_unique,
+	set_collection_validations, set_collection_versioning,
+};
+use crate::lib::database::graphs::create_smart_graph;
+use crate::lib::schema::{
+	get_collection_entry, SchemaCacheOptions, SchemaCheck, SchemaCollectionOptions,
+	SchemaDirectives, SchemaDocumentProperty, SchemaExpiryOptions, SchemaFieldPermission,
+	SchemaOperation, SchemaPermission, SchemaReference, SchemaRowFilter, SchemaSearchOptions,
+	SchemaSmartGraph, SchemaTimeLimit, SchemaTrigger, SchemaUniqueConstraint, SchemaValidation,
+};
+
+/// Collections and relationships declared in a YAML file, reconciled against the database
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaFile {
+	/// Created before the collections, which may be part of them
+	#[serde(default)]
+	pub smart_graphs: Vec<SchemaSmartGraph>,
+	#[serde(default)]
+	pub collections: Vec<SchemaFileCollection>,
+	#[serde(default)]
+	pub relationships: Vec<SchemaFileRelationship>,
+}
+
+/// A declared collection, the options left out are not changed
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaFileCollection {
+	pub name: String,
+	#[serde(default)]
+	pub properties: Vec<SchemaDocumentProperty>,
+	/// Only applied when the collection is created
+	pub options: Option<SchemaCollectionOptions>,
+	pub search: Option<SchemaSearchOptions>,
+	pub audit: Option<bool>,
+	pub versioning: Option<bool>,
+	pub timestamps: Option<bool>,
+	/// Reject the attributes that are not properties, collections are created strict
+	pub strict: Option<bool>,
+	pub expiry: Option<SchemaExpiryOptions>,
+	pub cache: Option<Vec<SchemaCacheOptions>>,
+	pub time_limits: Option<Vec<SchemaTimeLimit>>,
+	pub permissions: Option<Vec<SchemaPermission>>,
+	pub field_permissions: Option<Vec<SchemaFieldPermission>>,
+	pub row_filters: Option<Vec<SchemaRowFilter>>,
+	pub directives: Option<SchemaDirectives>,
+	/// The generated operations, all of them when left out
+	pub operations: Option<Vec<SchemaOperation>>,
+	pub triggers: Option<Vec<SchemaTrigger>>,
+	pub validations: Option<Vec<SchemaValidation>>,
+	pub unique: Option<Vec<SchemaUniqueConstraint>>,
+	pub references: Option<Vec<SchemaReference>>,
+	pub checks: Option<Vec<SchemaCheck>>,
+}
+
+/// A declared relationship, stored through an edge collection
+#[derive(Deserialize)]
+pub struct SchemaFileRelationship {
+	pub name: String,
+	pub edge: String,
+	pub from: String,
+	pub to: String,
+	/// `one_to_one`, `one_to_many` or `many_to_many`
+	pub r#type: String,
+	/// `outbound`, `inbound` or `any`
+	pub direction: String,
+	/// The SmartGraph the edges are part of
+	pub graph: Option<String>,
+}
+
+pub fn load_schema_file(path: &str) -> Result<SchemaFile, Error> {
+	let content = fs::read_to_string(path)
+		.map_err(|e| anyhow!("Failed to read the schema file {}: {}", path, e))?;
+
+	serde_yaml::from_str(&content).map_err(|e| anyhow!("Invalid schema file {}: {}", path, e))
+}
+
+/// Create the missing collections and properties, then apply the declared options,
+/// properties missing from the file are kept so that no data is lost
+async fn reconcile_collection(collection: SchemaFileCollection) -> Result<(), Error> {
+	let name = collection.name;
+
+	match get_collection_entry(&name).await {
+		None => {
+			tracing::info!(collection = %name, "Creating the declared collection");
+
+			create_collection(
+				name.clone(),
+				collection.properties,
+				collection.options.unwrap_or_default(),
+			)
+			.await?;
+		}
+		Some(entry) => {
+			let existing = entry["schema"]["properties"].as_object().cloned();
+
+			for property in collection.properties {
+				let exists = existing
+					.as_ref()
+					.map(|properties| properties.contains_key(&property.name))
+					.unwrap_or(false);
+
+				if !exists {
+					tracing::info!(collection = %name, property = %property.name, "Adding the declared property");
+
+					add_collection_property(name.clone(), property).await?;
+				}
+			}
+
+			if let Some(search) = &collection.search {
+				if !entry["search"].is_null() && entry["search"] != toJsonValue(search)? {
+					delete_search_view(name.clone()).await?;
+				}
+			}
+		}
+	}
+
+	if let Some(search) = collection.search {
+		let entry = get_collection_entry(&name).await;
+
+		if entry.map(|entry| entry["search"].is_null()).unwrap_or(true) {
+			create_search_view(name.clone(), search).await?;
+		}
+	}
+
+	if let Some(audit) = collection.audit {
+		set_collection_audit(name.clone(), audit).await?;
+	}
+
+	if let Some(versioning) = collection.versioning {
+		set_collection_versioning(name.clone(), versioning).await?;
+	}
+
+	if let Some(timestamps) = collection.timestamps {
+		set_collection_timestamps(name.clone(), timestamps).await?;
+	}
+
+	if let Some(strict) = collection.strict {
+		set_collection_strict(name.clone(), strict).await?;
+	}
+
+	if collection.expiry.is_some() {
+		set_collection_expiry(name.clone(), collection.expiry).await?;
+	}
+
+	if collection.cache.is_some() {
+		set_collection_cache(name.clone(), collection.cache).await?;
+	}
+
+	if collection.time_limits.is_some() {
+		set_collection_time_limits(name.clone(), collection.time_limits).await?;
+	}
+
+	if collection.permissions.is_some() {
+		set_collection_permissions(name.clone(), collection.permissions).await?;
+	}
+
+	if collection.field_permissions.is_some() {
+		set_collection_field_permissions(name.clone(), collection.field_permissions).await?;
+	}
+
+	if collection.row_filters.is_some() {
+		set_collection_row_filters(name.clone(), collection.row_filters).await?;
+	}
+
+	if collection.directives.is_some() {
+		set_collection_directives(name.clone(), collection.directives).await?;
+	}
+
+	if collection.operations.is_some() {
+		set_collection_operations(name.clone(), collection.operations).await?;
+	}
+
+	if collection.triggers.is_some() {
+		set_collection_triggers(name.clone(), collection.triggers).await?;
+	}
+
+	if collection.validations.is_some() {
+		set_collection_validations(name.clone(), collection.validations).await?;
+	}
+
+	if collection.unique.is_some() {
+		set_collection_unique(name.clone(), collection.unique).await?;
+	}
+
+	if collection.references.is_some() {
+		set_collection_references(name.clone(), collection.references).await?;
+	}
+
+	if collection.checks.is_some() {
+		set_collection_checks(name, collection.checks).await?;
+	}
+
+	Ok(())
+}
+
+/// Reconcile the database with the declared collections and relationships
+pub async fn apply_schema_file(path: &str) -> Result<(), Error> {
+	let file = load_schema_file(path)?;
+
+	for graph in &file.smart_graphs {
+		create_smart_graph(graph).await?;
+	}
+
+	for collection in file.collections {
+		reconcile_collection(collection).await?;
+	}
+
+	for relationship in file.relationships {
+		create_relationship(
+			relationship.name,
+			relationship.edge,
+			relationship.from,
+			relationship.to,
+			relationship.r#type,
+			relationship.direction,
+			relationship.graph,
+		)
+		.await?;
+	}
+
+	Ok(())
+}
@@ -0,0 +1,141 @@
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::lib::schema::{get_all_collections, get_all_edges, get_collection_entry};
+
+/// A property of a collection as stored in its validation rule
+#[derive(GraphQLObject)]
+pub struct PropertyMetadata {
+	pub name: String,
+	pub required: bool,
+	/// JSON encoded rule of the property, e.g. `{"type":"string","maxLength":20}`
+	pub rule: String,
+}
+
+/// The metadata of a collection stored in `alchemy_collections`
+#[derive(GraphQLObject)]
+pub struct CollectionMetadata {
+	pub name: String,
+	pub properties: Vec<PropertyMetadata>,
+	pub audit: bool,
+	pub versioning: bool,
+	pub timestamps: bool,
+	/// The attributes that are not properties are rejected
+	pub strict: bool,
+	/// The documents link two documents through `_from` and `_to`
+	pub edge: bool,
+	/// The options below are JSON encoded, and null when unset
+	pub search: Option<String>,
+	pub expiry: Option<String>,
+	pub cache: Option<String>,
+	pub time_limits: Option<String>,
+	pub options: Option<String>,
+	pub permissions: Option<String>,
+	pub field_permissions: Option<String>,
+	pub row_filters: Option<String>,
+	pub directives: Option<String>,
+	pub operations: Option<String>,
+	pub triggers: Option<String>,
+	pub validations: Option<String>,
+	pub unique: Option<String>,
+	pub references: Option<String>,
+	pub checks: Option<String>,
+}
+
+/// A relationship stored in `alchemy_edges`
+#[derive(Deserialize, GraphQLObject)]
+pub struct RelationshipMetadata {
+	pub name: String,
+	pub edge: String,
+	pub from: String,
+	pub to: String,
+	#[serde(rename = "type")]
+	#[graphql(name = "type")]
+	pub relationship_type: String,
+	pub direction: String,
+	/// The SmartGraph the edges are traversed through
+	pub graph: Option<String>,
+}
+
+fn encoded_option(entry: &JsonValue, key: &str) -> Option<String> {
+	match &entry[key] {
+		JsonValue::Null => None,
+		value => Some(value.to_string()),
+	}
+}
+
+impl CollectionMetadata {
+	fn from_entry(entry: &JsonValue) -> Self {
+		let required = entry["schema"]["required"].as_array();
+
+		let properties = entry["schema"]["properties"]
+			.as_object()
+			.map(|properties| {
+				properties
+					.iter()
+					.map(|(name, rule)| PropertyMetadata {
+						name: name.clone(),
+						required: required
+							.map(|required| required.iter().any(|r| r == name.as_str()))
+							.unwrap_or(false),
+						rule: rule.to_string(),
+					})
+					.collect()
+			})
+			.unwrap_or_default();
+
+		CollectionMetadata {
+			name: entry["name"].as_str().unwrap_or_default().to_string(),
+			properties,
+			audit: entry["audit"].as_bool().unwrap_or(false),
+			versioning: entry["versioning"].as_bool().unwrap_or(false),
+			timestamps: entry["timestamps"].as_bool().unwrap_or(false),
+			strict: entry["schema"]["additionalProperties"] == false,
+			edge: entry["options"]["edge"].as_bool().unwrap_or(false),
+			search: encoded_option(entry, "search"),
+			expiry: encoded_option(entry, "expiry"),
+			cache: encoded_option(entry, "cache"),
+			time_limits: encoded_option(entry, "timeLimits"),
+			options: encoded_option(entry, "options"),
+			permissions: encoded_option(entry, "permissions"),
+			field_permissions: encoded_option(entry, "fieldPermissions"),
+			row_filters: encoded_option(entry, "rowFilters"),
+			directives: encoded_option(entry, "directives"),
+			operations: encoded_option(entry, "operations"),
+			triggers: encoded_option(entry, "triggers"),
+			validations: encoded_option(entry, "validations"),
+			unique: encoded_option(entry, "unique"),
+			references: encoded_option(entry, "references"),
+			checks: encoded_option(entry, "checks"),
+		}
+	}
+}
+
+/// The metadata of every collection, sorted by name
+pub async fn get_collections_metadata() -> Vec<CollectionMetadata> {
+	let mut collections: Vec<CollectionMetadata> = get_all_collections()
+		.await
+		.iter()
+		.map(CollectionMetadata::from_entry)
+		.collect();
+
+	collections.sort_by(|a, b| a.name.cmp(&b.name));
+
+	collections
+}
+
+pub async fn get_collection_metadata(name: &str) -> Option<CollectionMetadata> {
+	get_collection_entry(name)
+		.await
+		.as_ref()
+		.map(CollectionMetadata::from_entry)
+}
+
+/// The relationships, the invalid entries are skipped
+pub async fn get_relationships_metadata() -> Vec<RelationshipMetadata> {
+	get_all_edges()
+		.await
+		.into_iter()
+		.filter_map(|entry| serde_json::from_value(entry).ok())
+		.collect()
+}
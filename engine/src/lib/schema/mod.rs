@@ -1,6 +1,90 @@
 pub mod properties;
-pub use properties::{SchemaDocumentProperty, SchemaDocumentPropertyValues};
+pub use properties::{
+	validate_property_rules, SchemaDocumentProperty, SchemaDocumentPropertyValues,
+};
+
+pub mod audit;
+pub use audit::get_history_collection_name;
+
+pub mod versioning;
+pub use versioning::get_versions_collection_name;
+
+pub mod decimal;
+pub use decimal::{decimal_pattern, decimal_scale, DecimalValue};
+
+pub mod expiry;
+pub use expiry::SchemaExpiryOptions;
+
+pub mod cache;
+pub use cache::SchemaCacheOptions;
+
+pub mod collection_options;
+pub use collection_options::{SchemaCollectionOptions, SchemaKeyGenerator, SchemaKeyOptions};
+
+pub mod smart_graphs;
+pub use smart_graphs::SchemaSmartGraph;
+
+pub mod collection_update;
+pub use collection_update::{SchemaCollectionUpdate, SchemaValidationLevel};
+
+pub mod time_limits;
+pub use time_limits::SchemaTimeLimit;
+
+pub mod checks;
+pub use checks::{validate_checks, SchemaCheck};
+
+pub mod directives;
+pub use directives::{
+	is_graphql_name, SchemaDirectives, SchemaFieldDirective, SchemaOperationDirective,
+};
+
+pub mod permissions;
+pub use permissions::{SchemaFieldPermission, SchemaOperation, SchemaPermission};
+
+pub mod row_filters;
+pub use row_filters::{
+	parse_row_filter, RowFilterCondition, RowFilterOperator, RowFilterValue, SchemaRowFilter,
+};
+
+pub mod references;
+pub use references::SchemaReference;
+
+pub mod search;
+pub use search::{get_search_view_name, SchemaSearchOptions};
+
+pub mod triggers;
+pub use triggers::{validate_triggers, SchemaTrigger};
+
+pub mod unique;
+pub use unique::{SchemaUniqueConstraint, UNIQUE_INDEX_PREFIX};
+
+pub mod validations;
+pub use validations::{validate_validations, FieldComparison, SchemaValidation};
+
+pub mod timestamps;
+pub use timestamps::{CREATED_AT, TIMESTAMP_PROPERTIES, UPDATED_AT};
+
+pub mod versions;
+pub use versions::{
+	diff_metadata, get_metadata_versions, MetadataDiff, MetadataVersion,
+	METADATA_VERSIONS_COLLECTION,
+};
+
+pub mod consistency;
+pub use consistency::{check_schema_status, log_schema_status, SchemaDrift, SchemaStatus};
+
+pub mod metadata;
+pub use metadata::{
+	get_collection_metadata, get_collections_metadata, get_relationships_metadata,
+	CollectionMetadata, RelationshipMetadata,
+};
+
+pub mod file;
+pub use file::apply_schema_file;
 
 // pub mod generation;
 pub mod entries;
-pub use entries::{create_entry, delete_entry, get_all_collections, get_all_edges};
+pub use entries::{
+	create_entry, delete_entry, get_all_collections, get_all_edges, get_collection_entry,
+	update_entry, update_entry_schema, update_entry_search,
+};
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// The generated operations a role can be granted on a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Copy, Debug, GraphQLEnum)]
+#[serde(rename_all = "camelCase")]
+pub enum SchemaOperation {
+	Get,
+	GetAll,
+	Create,
+	Update,
+	Delete,
+	History,
+	Changed,
+	/// Documents picked at random
+	Sample,
+	/// The number of documents matching a filter
+	Count,
+	/// The previous states of a document of a versioned collection
+	Versions,
+	/// The state of a document of a versioned collection at a given time
+	AsOf,
+}
+
+/// The operations a role is allowed to run on a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaPermission {
+	pub role: String,
+	pub operations: Vec<SchemaOperation>,
+}
+
+/// The properties a role is allowed to read on a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaFieldPermission {
+	pub role: String,
+	pub fields: Vec<String>,
+}
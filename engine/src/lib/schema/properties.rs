@@ -1,8 +1,11 @@
+use anyhow::{anyhow, Error};
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 
 use crate::lib::database::schema::{
-	SchemaNativeType, SchemaNativeTypeArray, SchemaProperty, SchemaPropertyType,
+	SchemaGeoType, SchemaNativeType, SchemaNativeTypeArray, SchemaProperty, SchemaPropertyType,
 };
+use crate::lib::schema::decimal_pattern;
 
 /// The property for the collection property
 #[derive(Serialize, Deserialize, PartialEq, GraphQLInputObject)]
@@ -25,6 +28,31 @@ pub struct SchemaDocumentPropertyValues {
 	pub r#enum: Option<Vec<String>>,
 	#[serde(skip_serializing_if = "Option::is_none")]
 	pub array_type: Option<SchemaNativeType>,
+	/// Regular expression the strings, or the string items of an array, must match
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub pattern: Option<String>,
+	/// Bounds of the numbers
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub minimum: Option<f64>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub maximum: Option<f64>,
+	/// Exclude the bounds themselves from the allowed numbers
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub exclusive_minimum: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub exclusive_maximum: Option<bool>,
+	/// Positive number the numbers must be a multiple of
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub multiple_of: Option<f64>,
+	/// The geometry of `Geo` properties, a point by default
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub geo_type: Option<SchemaGeoType>,
+	/// Significant digits of `Decimal` properties, unlimited by default
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub precision: Option<i32>,
+	/// Digits after the point of `Decimal` properties, 0 by default when the precision is set
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub scale: Option<i32>,
 }
 
 // Convert `SchemaDocumentProperty` to `SchemaProperty` ready for Arango
@@ -40,11 +68,13 @@ impl From<SchemaDocumentPropertyValues> for SchemaProperty {
 		// Match through the types based on Array, Enum or default scalar values
 		match values.r#type {
 			SchemaPropertyType::Array => {
+				let array_type = values.array_type.unwrap_or(SchemaNativeType::String);
+
 				property.items = Some(SchemaNativeTypeArray {
-					r#type: values
-						.array_type
-						.unwrap_or(SchemaNativeType::String)
-						.as_str(),
+					pattern: values
+						.pattern
+						.filter(|_| array_type == SchemaNativeType::String),
+					r#type: array_type.as_str(),
 					maximum: values.max_length,
 				});
 			}
@@ -52,13 +82,102 @@ impl From<SchemaDocumentPropertyValues> for SchemaProperty {
 				property.r#type = None;
 				property.r#enum = values.r#enum;
 			}
+			SchemaPropertyType::DateTime => {
+				property.format = Some(String::from("date-time"));
+			}
+			SchemaPropertyType::File => {
+				property.format = Some(String::from("file"));
+			}
+			SchemaPropertyType::Geo => {
+				property.format = Some(values.geo_type.unwrap_or_default().format());
+			}
+			SchemaPropertyType::Decimal => {
+				property.format = Some(String::from("decimal"));
+				property.pattern = Some(decimal_pattern(values.precision, values.scale));
+				property.precision = values.precision;
+				property.scale = values.scale;
+			}
+			SchemaPropertyType::Localized => {
+				property.format = Some(String::from("localized"));
+			}
 			// Default for string, integer and boolean
 			_ => {
 				property.min_length = values.min_length;
 				property.max_length = values.max_length;
+				property.pattern = values.pattern;
+				property.minimum = values.minimum;
+				property.maximum = values.maximum;
+				property.exclusive_minimum = values
+					.exclusive_minimum
+					.filter(|_| values.minimum.is_some());
+				property.exclusive_maximum = values
+					.exclusive_maximum
+					.filter(|_| values.maximum.is_some());
+				property.multiple_of = values.multiple_of;
 			}
 		}
 
 		return property;
 	}
 }
+
+/// Reject the rules the validation of the inputs could not apply
+pub fn validate_property_rules(property: &SchemaDocumentProperty) -> Result<(), Error> {
+	if let Some(ref pattern) = property.values.pattern {
+		Regex::new(pattern)
+			.map_err(|e| anyhow!("Invalid pattern of the property {}: {}", property.name, e))?;
+	}
+
+	if let (Some(minimum), Some(maximum)) = (property.values.minimum, property.values.maximum) {
+		if minimum > maximum {
+			return Err(anyhow!(
+				"The minimum of the property {} is greater than its maximum",
+				property.name
+			));
+		}
+
+		let exclusive = property.values.exclusive_minimum.unwrap_or(false)
+			|| property.values.exclusive_maximum.unwrap_or(false);
+
+		if minimum == maximum && exclusive {
+			return Err(anyhow!(
+				"The exclusive bounds of the property {} allow no number",
+				property.name
+			));
+		}
+	}
+
+	if let Some(precision) = property.values.precision {
+		if precision < 1 {
+			return Err(anyhow!(
+				"The precision of the property {} must be positive",
+				property.name
+			));
+		}
+	}
+
+	if let Some(scale) = property.values.scale {
+		if scale < 0
+			|| property
+				.values
+				.precision
+				.map_or(false, |precision| scale > precision)
+		{
+			return Err(anyhow!(
+				"The scale of the property {} must be between 0 and its precision",
+				property.name
+			));
+		}
+	}
+
+	if let Some(multiple_of) = property.values.multiple_of {
+		if !(multiple_of > 0.0) {
+			return Err(anyhow!(
+				"multipleOf of the property {} must be a positive number",
+				property.name
+			));
+		}
+	}
+
+	Ok(())
+}
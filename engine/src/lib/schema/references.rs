@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// A property holding the key of a document of another collection, checked on every write since
+/// ArangoDB does not enforce it
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaReference {
+	pub property: String,
+	pub collection: String,
+}
+
+impl SchemaReference {
+	/// Message of the AQL assertion failing when the referenced document does not exist
+	pub fn assertion(&self) -> String {
+		format!("missing reference {}", self.property)
+	}
+}
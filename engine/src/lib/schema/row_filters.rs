@@ -0,0 +1,149 @@
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+/// Prefix of the operands read from the claims of the request
+const CLAIMS_PREFIX: &str = "$claims.";
+
+/// The rows of a collection a role can see, updated and delete
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaRowFilter {
+	pub role: String,
+	/// Conditions joined with `&&`, e.g. `ownerId == $claims.sub && archived == false`
+	pub filter: String,
+}
+
+#[derive(PartialEq, Clone, Copy, Debug)]
+pub enum RowFilterOperator {
+	Equal,
+	NotEqual,
+	Less,
+	LessOrEqual,
+	Greater,
+	GreaterOrEqual,
+	In,
+}
+
+impl RowFilterOperator {
+	pub fn parse(operator: &str) -> Option<Self> {
+		Some(match operator {
+			"==" => RowFilterOperator::Equal,
+			"!=" => RowFilterOperator::NotEqual,
+			"<" => RowFilterOperator::Less,
+			"<=" => RowFilterOperator::LessOrEqual,
+			">" => RowFilterOperator::Greater,
+			">=" => RowFilterOperator::GreaterOrEqual,
+			"IN" | "in" => RowFilterOperator::In,
+			_ => return None,
+		})
+	}
+
+	pub fn as_aql(&self) -> &'static str {
+		match self {
+			RowFilterOperator::Equal => "==",
+			RowFilterOperator::NotEqual => "!=",
+			RowFilterOperator::Less => "<",
+			RowFilterOperator::LessOrEqual => "<=",
+			RowFilterOperator::Greater => ">",
+			RowFilterOperator::GreaterOrEqual => ">=",
+			RowFilterOperator::In => "IN",
+		}
+	}
+}
+
+#[derive(PartialEq, Clone, Debug)]
+pub enum RowFilterValue {
+	/// Path of a claim, e.g. `sub` or `org.id`
+	Claim(Vec<String>),
+	Literal(JsonValue),
+}
+
+/// A single `property operator value` comparison
+#[derive(PartialEq, Clone, Debug)]
+pub struct RowFilterCondition {
+	pub property: Vec<String>,
+	pub operator: RowFilterOperator,
+	pub value: RowFilterValue,
+}
+
+fn parse_path(path: &str) -> Option<Vec<String>> {
+	let segments: Vec<String> = path.split('.').map(String::from).collect();
+
+	let valid = segments.iter().all(|segment| {
+		let mut chars = segment.chars();
+
+		matches!(chars.next(), Some(c) if c.is_ascii_alphabetic() || c == '_')
+			&& chars.all(|c| c.is_ascii_alphanumeric() || c == '_')
+	});
+
+	if valid {
+		Some(segments)
+	} else {
+		None
+	}
+}
+
+/// Split on the `&&` that are not part of a string literal
+fn split_conditions(filter: &str) -> Vec<&str> {
+	let mut conditions = Vec::new();
+	let mut in_string = false;
+	let mut escaped = false;
+	let mut start = 0;
+	let bytes = filter.as_bytes();
+
+	for (i, &byte) in bytes.iter().enumerate() {
+		match byte {
+			_ if escaped => escaped = false,
+			b'\\' if in_string => escaped = true,
+			b'"' => in_string = !in_string,
+			b'&' if !in_string && i > start && bytes[i - 1] == b'&' => {
+				conditions.push(&filter[start..i - 1]);
+				start = i + 1;
+			}
+			_ => {}
+		}
+	}
+
+	conditions.push(&filter[start..]);
+	conditions
+}
+
+/// Parse a filter template, only properties, claims and JSON literals are accepted
+pub fn parse_row_filter(filter: &str) -> Result<Vec<RowFilterCondition>, String> {
+	split_conditions(filter)
+		.into_iter()
+		.map(|condition| {
+			let mut parts = condition.trim().splitn(3, char::is_whitespace);
+
+			let (property, operator, value) = match (parts.next(), parts.next(), parts.next()) {
+				(Some(property), Some(operator), Some(value)) => (property, operator, value.trim()),
+				_ => {
+					return Err(format!(
+						"Expected `property operator value` in {}",
+						condition
+					))
+				}
+			};
+
+			let property =
+				parse_path(property).ok_or_else(|| format!("Invalid property {}", property))?;
+
+			let operator = RowFilterOperator::parse(operator)
+				.ok_or_else(|| format!("Unknown operator {}", operator))?;
+
+			let value = match value.strip_prefix(CLAIMS_PREFIX) {
+				Some(claim) => RowFilterValue::Claim(
+					parse_path(claim).ok_or_else(|| format!("Invalid claim {}", claim))?,
+				),
+				None => RowFilterValue::Literal(
+					serde_json::from_str(value).map_err(|_| format!("Invalid value {}", value))?,
+				),
+			};
+
+			Ok(RowFilterCondition {
+				property,
+				operator,
+				value,
+			})
+		})
+		.collect()
+}
@@ -0,0 +1,24 @@
+use serde::{Deserialize, Serialize};
+
+/// The ArangoSearch options for a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Default, GraphQLInputObject)]
+pub struct SchemaSearchOptions {
+	/// Properties to index, every property is indexed when empty
+	pub fields: Vec<String>,
+	/// Analyzers applied to the indexed properties, defaults to `identity`
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub analyzers: Option<Vec<String>>,
+}
+
+impl SchemaSearchOptions {
+	pub fn get_analyzers(&self) -> Vec<String> {
+		self.analyzers
+			.clone()
+			.unwrap_or_else(|| vec![String::from("identity")])
+	}
+}
+
+/// Name of the ArangoSearch view linked to a collection
+pub fn get_search_view_name(collection: &str) -> String {
+	format!("{}_search", collection)
+}
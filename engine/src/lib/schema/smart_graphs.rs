@@ -0,0 +1,42 @@
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value as JsonValue};
+
+/// A SmartGraph of an ArangoDB Enterprise cluster, its vertices are sharded by the value of an
+/// attribute so that the traversals between vertices of a same value stay on one server
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaSmartGraph {
+	pub name: String,
+	/// Attribute every vertex must have, it prefixes their keys, e.g. `region:1234`
+	pub smart_graph_attribute: String,
+	/// The edges only link vertices of a same value of the attribute
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub disjoint: Option<bool>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub number_of_shards: Option<i32>,
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub replication_factor: Option<i32>,
+}
+
+impl SchemaSmartGraph {
+	/// The body creating the graph with the graph API, the collections are added later
+	pub fn definition(&self) -> JsonValue {
+		let mut options = json!({ "smartGraphAttribute": self.smart_graph_attribute });
+
+		if let Some(number_of_shards) = self.number_of_shards {
+			options["numberOfShards"] = json!(number_of_shards.max(1));
+		}
+
+		if let Some(replication_factor) = self.replication_factor {
+			options["replicationFactor"] = json!(replication_factor.max(1));
+		}
+
+		json!({
+			"name": self.name,
+			"edgeDefinitions": [],
+			"isSmart": true,
+			"isDisjoint": self.disjoint.unwrap_or(false),
+			"options": options,
+		})
+	}
+}
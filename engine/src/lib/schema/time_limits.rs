@@ -0,0 +1,11 @@
+use serde::{Deserialize, Serialize};
+
+use crate::lib::schema::SchemaOperation;
+
+/// How long the queries of an operation of a collection may run before ArangoDB kills them
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+#[serde(rename_all = "camelCase")]
+pub struct SchemaTimeLimit {
+	pub operation: SchemaOperation,
+	pub max_runtime_ms: i32,
+}
@@ -0,0 +1,5 @@
+pub const CREATED_AT: &str = "createdAt";
+pub const UPDATED_AT: &str = "updatedAt";
+
+/// The properties managed by the server when timestamps are enabled
+pub const TIMESTAMP_PROPERTIES: [&str; 2] = [CREATED_AT, UPDATED_AT];
@@ -0,0 +1,36 @@
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+
+use crate::lib::changes::ChangeOperation;
+
+/// A webhook receiving the changes of a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaTrigger {
+	pub name: String,
+	pub url: String,
+	/// The changes delivered to the webhook
+	pub operations: Vec<ChangeOperation>,
+}
+
+/// Check the triggers of a collection, their names identify the deliveries
+pub fn validate_triggers(triggers: &[SchemaTrigger]) -> Result<(), Error> {
+	for (index, trigger) in triggers.iter().enumerate() {
+		if trigger.name.is_empty() {
+			return Err(anyhow!("Triggers must be named"));
+		}
+
+		if triggers[..index].iter().any(|t| t.name == trigger.name) {
+			return Err(anyhow!("Duplicate trigger {}", trigger.name));
+		}
+
+		if !trigger.url.starts_with("http://") && !trigger.url.starts_with("https://") {
+			return Err(anyhow!(
+				"Invalid URL {} of the trigger {}",
+				trigger.url,
+				trigger.name
+			));
+		}
+	}
+
+	Ok(())
+}
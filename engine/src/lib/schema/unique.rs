@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Prefix of the names of the indexes backing the unique constraints
+pub const UNIQUE_INDEX_PREFIX: &str = "alchemy_unique_";
+
+/// Properties whose combination of values can only be stored once in a collection
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaUniqueConstraint {
+	pub properties: Vec<String>,
+}
+
+impl SchemaUniqueConstraint {
+	/// Name of the unique persistent index, found in the errors of ArangoDB
+	pub fn index_name(&self) -> String {
+		format!("{}{}", UNIQUE_INDEX_PREFIX, self.properties.join("_"))
+	}
+}
@@ -0,0 +1,98 @@
+use anyhow::{anyhow, Error};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use crate::lib::schema::RowFilterOperator;
+
+/// A comparison of two properties every written document must satisfy
+#[derive(Serialize, Deserialize, PartialEq, Clone, Debug, GraphQLInputObject)]
+pub struct SchemaValidation {
+	/// e.g. `endDate >= startDate`
+	pub rule: String,
+	/// Error returned when the rule does not hold, a default one is derived from the rule
+	#[serde(skip_serializing_if = "Option::is_none")]
+	pub message: Option<String>,
+}
+
+/// A parsed cross-field rule
+#[derive(Clone, PartialEq, Debug)]
+pub struct FieldComparison {
+	pub left: String,
+	pub operator: RowFilterOperator,
+	pub right: String,
+	pub message: String,
+}
+
+impl FieldComparison {
+	pub fn parse(validation: &SchemaValidation) -> Result<Self, Error> {
+		let parts: Vec<&str> = validation.rule.split_whitespace().collect();
+
+		let (left, operator, right) = match parts.as_slice() {
+			[left, operator, right] => (left, operator, right),
+			_ => {
+				return Err(anyhow!(
+					"Invalid rule {}, expected `property operator property`",
+					validation.rule
+				))
+			}
+		};
+
+		let operator = RowFilterOperator::parse(operator)
+			.filter(|operator| *operator != RowFilterOperator::In)
+			.ok_or_else(|| {
+				anyhow!(
+					"Invalid operator {} in the rule {}",
+					operator,
+					validation.rule
+				)
+			})?;
+
+		Ok(FieldComparison {
+			left: left.to_string(),
+			operator,
+			right: right.to_string(),
+			message: validation
+				.message
+				.clone()
+				.unwrap_or_else(|| format!("{} must be {} {}", left, operator.as_aql(), right)),
+		})
+	}
+
+	/// Whether the document satisfies the rule, `None` when one of the properties is missing
+	pub fn holds(&self, document: &JsonMap<String, JsonValue>) -> Option<bool> {
+		let ordering = match (document.get(&self.left)?, document.get(&self.right)?) {
+			(JsonValue::Number(left), JsonValue::Number(right)) => {
+				left.as_f64()?.partial_cmp(&right.as_f64()?)?
+			}
+			// ISO 8601 dates are ordered like strings
+			(JsonValue::String(left), JsonValue::String(right)) => left.cmp(right),
+			(left, right) if left.is_null() || right.is_null() => return None,
+			// Other values can only be compared for equality
+			(left, right) => {
+				return match self.operator {
+					RowFilterOperator::Equal => Some(left == right),
+					RowFilterOperator::NotEqual => Some(left != right),
+					_ => None,
+				}
+			}
+		};
+
+		Some(match self.operator {
+			RowFilterOperator::Equal => ordering.is_eq(),
+			RowFilterOperator::NotEqual => ordering.is_ne(),
+			RowFilterOperator::Less => ordering.is_lt(),
+			RowFilterOperator::LessOrEqual => ordering.is_le(),
+			RowFilterOperator::Greater => ordering.is_gt(),
+			RowFilterOperator::GreaterOrEqual => ordering.is_ge(),
+			RowFilterOperator::In => true,
+		})
+	}
+}
+
+pub fn validate_validations(validations: &[SchemaValidation]) -> Result<(), Error> {
+	for validation in validations {
+		FieldComparison::parse(validation)?;
+	}
+
+	Ok(())
+}
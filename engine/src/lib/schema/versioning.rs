@@ -0,0 +1,5 @@
+/// Name of the shadow collection holding the previous states of the documents of a versioned
+/// collection
+pub fn get_versions_collection_name(collection: &str) -> String {
+	format!("{}_versions", collection)
+}
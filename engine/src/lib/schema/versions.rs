@@ -0,0 +1,298 @@
+use anyhow::Error;
+use rust_arango::index::IndexSettings;
+use rust_arango::AqlQuery;
+use serde::Deserialize;
+use serde_json::{json, Map as JsonMap, Value as JsonValue};
+
+use crate::lib::database::arango::ensure_collection;
+use crate::lib::database::DATABASE;
+use crate::lib::schema::{get_all_collections, get_all_edges, get_search_view_name};
+
+pub const METADATA_VERSIONS_COLLECTION: &str = "alchemy_metadata_versions";
+
+/// A snapshot of the collections and relationships, taken after each metadata change
+#[derive(Deserialize, GraphQLObject)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataVersion {
+	pub version: i32,
+	pub created_at: String,
+}
+
+/// A collection or relationship which differs between two versions
+#[derive(GraphQLObject)]
+pub struct MetadataChange {
+	/// `added`, `removed` or `changed`
+	pub kind: String,
+	/// `collection` or `relationship`
+	pub target: String,
+	pub name: String,
+	/// The options that changed, e.g. `schema` or `permissions`
+	pub options: Vec<String>,
+}
+
+#[derive(GraphQLObject)]
+pub struct MetadataDiff {
+	pub from: i32,
+	pub to: i32,
+	pub changes: Vec<MetadataChange>,
+	/// What the database lacks to match the latest metadata, e.g. `create collection users`
+	pub pending_actions: Vec<String>,
+}
+
+/// The entries without the attributes managed by ArangoDB, sorted by name
+fn strip_entries(entries: Vec<JsonValue>) -> Vec<JsonValue> {
+	let mut entries: Vec<JsonValue> = entries
+		.into_iter()
+		.map(|entry| match entry {
+			JsonValue::Object(entry) => JsonValue::Object(
+				entry
+					.into_iter()
+					.filter(|(key, _)| !key.starts_with('_') && key != "count")
+					.collect(),
+			),
+			entry => entry,
+		})
+		.collect();
+
+	entries.sort_by(|a, b| a["name"].as_str().cmp(&b["name"].as_str()));
+
+	entries
+}
+
+async fn current_metadata() -> JsonValue {
+	json!({
+		"collections": strip_entries(get_all_collections().await),
+		"relationships": strip_entries(get_all_edges().await),
+	})
+}
+
+/// Store the current metadata as a new version, unless it did not change since the latest one
+async fn record_metadata_version() -> Result<(), Error> {
+	ensure_collection(METADATA_VERSIONS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"LET latest = FIRST(
+					FOR v IN @@collection
+						SORT v.version DESC
+						LIMIT 1
+						RETURN v
+				)
+				FILTER latest == null || latest.metadata != @metadata
+				INSERT {
+					version: latest == null ? 1 : latest.version + 1,
+					createdAt: DATE_ISO8601(DATE_NOW()),
+					metadata: @metadata
+				} INTO @@collection
+				RETURN NEW.version",
+		)
+		.bind_var("@collection", METADATA_VERSIONS_COLLECTION)
+		.bind_var("metadata", current_metadata().await)
+		.build();
+
+	let versions: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	if let Some(version) = versions.first() {
+		tracing::debug!(version = %version, "Metadata version recorded");
+	}
+
+	Ok(())
+}
+
+/// Record a version after the metadata was written, a failure does not undo the change
+pub async fn metadata_changed() {
+	if let Err(e) = record_metadata_version().await {
+		tracing::error!(error = %e, "Failed to record the metadata version");
+	}
+}
+
+/// The recorded versions, most recent first
+pub async fn get_metadata_versions(limit: i32) -> Result<Vec<MetadataVersion>, Error> {
+	ensure_collection(METADATA_VERSIONS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR v IN @@collection
+				SORT v.version DESC
+				LIMIT @limit
+				RETURN { version: v.version, createdAt: v.createdAt }",
+		)
+		.bind_var("@collection", METADATA_VERSIONS_COLLECTION)
+		.bind_var("limit", limit.max(0))
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
+
+/// The version and metadata of a version, or of the latest one with `None`
+async fn get_metadata(version: Option<i32>) -> Result<Option<(i32, JsonValue)>, Error> {
+	ensure_collection(METADATA_VERSIONS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR v IN @@collection
+				FILTER @version == null || v.version == @version
+				SORT v.version DESC
+				LIMIT 1
+				RETURN [v.version, v.metadata]",
+		)
+		.bind_var("@collection", METADATA_VERSIONS_COLLECTION)
+		.bind_var("version", json!(version))
+		.build();
+
+	let versions: Vec<(i32, JsonValue)> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(versions.into_iter().next())
+}
+
+fn entries_by_name(metadata: &JsonValue, key: &str) -> JsonMap<String, JsonValue> {
+	metadata[key]
+		.as_array()
+		.into_iter()
+		.flatten()
+		.filter_map(|entry| Some((entry["name"].as_str()?.to_string(), entry.clone())))
+		.collect()
+}
+
+fn diff_entries(target: &str, from: &JsonValue, to: &JsonValue, key: &str) -> Vec<MetadataChange> {
+	let from = entries_by_name(from, key);
+	let to = entries_by_name(to, key);
+
+	let mut changes = Vec::new();
+
+	for (name, entry) in &to {
+		match from.get(name) {
+			None => changes.push(MetadataChange {
+				kind: String::from("added"),
+				target: target.to_string(),
+				name: name.clone(),
+				options: Vec::new(),
+			}),
+			Some(previous) if previous != entry => {
+				let mut options: Vec<String> = entry
+					.as_object()
+					.into_iter()
+					.flatten()
+					.map(|(option, _)| option)
+					.chain(
+						previous
+							.as_object()
+							.into_iter()
+							.flatten()
+							.map(|(option, _)| option),
+					)
+					.filter(|option| previous[option.as_str()] != entry[option.as_str()])
+					.cloned()
+					.collect();
+
+				options.sort();
+				options.dedup();
+
+				changes.push(MetadataChange {
+					kind: String::from("changed"),
+					target: target.to_string(),
+					name: name.clone(),
+					options,
+				})
+			}
+			Some(_) => {}
+		}
+	}
+
+	for name in from.keys().filter(|name| !to.contains_key(*name)) {
+		changes.push(MetadataChange {
+			kind: String::from("removed"),
+			target: target.to_string(),
+			name: name.clone(),
+			options: Vec::new(),
+		});
+	}
+
+	changes
+}
+
+/// The collections, edge collections, search views and TTL indexes the metadata needs
+/// but the database does not have
+async fn pending_actions(metadata: &JsonValue) -> Result<Vec<String>, Error> {
+	let db = DATABASE.get().await.database.clone();
+
+	let collections: Vec<String> = db
+		.accessible_collections()
+		.await?
+		.into_iter()
+		.map(|collection| collection.name)
+		.collect();
+
+	let views: Vec<String> = db
+		.list_views()
+		.await?
+		.into_iter()
+		.map(|view| view.name)
+		.collect();
+
+	let mut actions = Vec::new();
+
+	for (name, entry) in entries_by_name(metadata, "collections") {
+		if !collections.contains(&name) {
+			actions.push(format!("create collection {}", name));
+
+			continue;
+		}
+
+		if !entry["search"].is_null() && !views.contains(&get_search_view_name(&name)) {
+			actions.push(format!("create search view of {}", name));
+		}
+
+		if !entry["expiry"].is_null() {
+			let has_ttl_index = db
+				.indexes(name.as_str())
+				.await?
+				.indexes
+				.iter()
+				.any(|index| matches!(index.settings, IndexSettings::Ttl { .. }));
+
+			if !has_ttl_index {
+				actions.push(format!("create TTL index of {}", name));
+			}
+		}
+	}
+
+	for (name, entry) in entries_by_name(metadata, "relationships") {
+		if let Some(edge) = entry["edge"].as_str() {
+			if !collections.iter().any(|collection| collection == edge) {
+				actions.push(format!("create edge collection {} of {}", edge, name));
+			}
+		}
+	}
+
+	Ok(actions)
+}
+
+/// The changes between two versions, the latest one by default, and what is
+/// pending to reconcile the database with the latest metadata
+pub async fn diff_metadata(from: i32, to: Option<i32>) -> Result<Option<MetadataDiff>, Error> {
+	let (from_version, from_metadata) = match get_metadata(Some(from)).await? {
+		Some(version) => version,
+		None => return Ok(None),
+	};
+
+	let (to_version, to_metadata) = match get_metadata(to).await? {
+		Some(version) => version,
+		None => return Ok(None),
+	};
+
+	let mut changes = diff_entries("collection", &from_metadata, &to_metadata, "collections");
+	changes.extend(diff_entries(
+		"relationship",
+		&from_metadata,
+		&to_metadata,
+		"relationships",
+	));
+
+	Ok(Some(MetadataDiff {
+		from: from_version,
+		to: to_version,
+		changes,
+		pending_actions: pending_actions(&current_metadata().await).await?,
+	}))
+}
@@ -0,0 +1,146 @@
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use anyhow::{anyhow, Error};
+use rust_arango::AqlQuery;
+use serde_json::value::Value as JsonValue;
+
+use crate::lib::database::DATABASE;
+
+/// Placeholder of the string defaults replaced by the sequence number of the document, so that
+/// unique properties differ, e.g. `user{n}@example.com`
+const SEQUENCE_PLACEHOLDER: &str = "{n}";
+
+/// Builds the documents of a collection from defaults and inserts them, remembering their keys so
+/// that a test can remove them afterwards
+pub struct Factory {
+	pub collection: String,
+	defaults: JsonValue,
+	sequence: AtomicUsize,
+	inserted: Mutex<Vec<String>>,
+}
+
+impl Factory {
+	pub fn new(collection: &str, defaults: JsonValue) -> Self {
+		Factory {
+			collection: collection.to_string(),
+			defaults,
+			sequence: AtomicUsize::new(0),
+			inserted: Mutex::new(Vec::new()),
+		}
+	}
+
+	/// The defaults merged with the overrides, without inserting it
+	pub fn build(&self, overrides: JsonValue) -> JsonValue {
+		let n = self.sequence.fetch_add(1, Ordering::SeqCst) + 1;
+		let mut document = self.defaults.clone();
+
+		if let Some(properties) = document.as_object_mut() {
+			for value in properties.values_mut() {
+				if let JsonValue::String(s) = value {
+					*s = s.replace(SEQUENCE_PLACEHOLDER, &n.to_string());
+				}
+			}
+
+			if let JsonValue::Object(overrides) = overrides {
+				properties.extend(overrides);
+			}
+		}
+
+		document
+	}
+
+	/// Insert a document built from the overrides and return it as stored
+	pub async fn insert(&self, overrides: JsonValue) -> Result<JsonValue, Error> {
+		let mut documents = self.insert_many(vec![overrides]).await?;
+
+		documents
+			.pop()
+			.ok_or_else(|| anyhow!("Nothing was inserted into {}", self.collection))
+	}
+
+	/// Insert a document for each of the overrides
+	pub async fn insert_many(&self, overrides: Vec<JsonValue>) -> Result<Vec<JsonValue>, Error> {
+		let documents: Vec<JsonValue> = overrides.into_iter().map(|o| self.build(o)).collect();
+
+		let query = AqlQuery::builder()
+			.query("FOR doc IN @documents INSERT doc INTO @@collection RETURN NEW")
+			.bind_var("@collection", self.collection.clone())
+			.bind_var("documents", documents)
+			.build();
+
+		let inserted: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+		self.inserted.lock().unwrap().extend(
+			inserted
+				.iter()
+				.filter_map(|document| document["_key"].as_str().map(String::from)),
+		);
+
+		Ok(inserted)
+	}
+
+	/// Remove the documents inserted by the factory
+	pub async fn cleanup(&self) -> Result<(), Error> {
+		let keys: Vec<String> = self.inserted.lock().unwrap().drain(..).collect();
+
+		let query = AqlQuery::builder()
+			.query("FOR key IN @keys REMOVE key IN @@collection OPTIONS { ignoreErrors: true }")
+			.bind_var("@collection", self.collection.clone())
+			.bind_var("keys", keys)
+			.build();
+
+		DATABASE
+			.get()
+			.await
+			.database
+			.aql_query::<JsonValue>(query)
+			.await?;
+
+		Ok(())
+	}
+}
+
+#[cfg(test)]
+mod tests {
+	use serde_json::json;
+
+	use super::Factory;
+
+	#[test]
+	fn build_numbers_the_placeholders() {
+		let factory = Factory::new(
+			"users",
+			json!({ "email": "user{n}@example.com", "name": "User {n}", "age": 30 }),
+		);
+
+		assert_eq!(
+			factory.build(json!({})),
+			json!({ "email": "user1@example.com", "name": "User 1", "age": 30 })
+		);
+		assert_eq!(factory.build(json!({}))["email"], "user2@example.com");
+	}
+
+	#[test]
+	fn build_merges_the_overrides() {
+		let factory = Factory::new(
+			"users",
+			json!({ "email": "user{n}@example.com", "age": 30 }),
+		);
+
+		assert_eq!(
+			factory.build(json!({ "age": 40, "admin": true })),
+			json!({ "email": "user1@example.com", "age": 40, "admin": true })
+		);
+	}
+
+	#[test]
+	fn build_keeps_the_placeholders_of_overrides() {
+		let factory = Factory::new("users", json!({ "email": "user{n}@example.com" }));
+
+		assert_eq!(
+			factory.build(json!({ "email": "{n}@example.com" }))["email"],
+			"{n}@example.com"
+		);
+	}
+}
@@ -0,0 +1,43 @@
+use anyhow::Error;
+use rust_arango::AqlQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::value::Value as JsonValue;
+
+use crate::lib::database::DATABASE;
+
+/// A set of documents to seed into a collection
+#[derive(Serialize, Deserialize)]
+pub struct Fixture {
+	pub collection: String,
+	/// The property used to detect already seeded documents
+	#[serde(default = "default_key")]
+	pub key: String,
+	pub documents: Vec<JsonValue>,
+}
+
+fn default_key() -> String {
+	String::from("_key")
+}
+
+impl Fixture {
+	/// Upsert the documents on their key so that seeding can be repeated safely
+	pub async fn insert(&self) -> Result<usize, Error> {
+		let query = AqlQuery::builder()
+			.query(
+				"FOR doc IN @documents
+					UPSERT { [@key]: doc[@key] }
+					INSERT doc
+					UPDATE {}
+					IN @@collection
+					RETURN OLD ? 0 : 1",
+			)
+			.bind_var("@collection", self.collection.clone())
+			.bind_var("key", self.key.clone())
+			.bind_var("documents", self.documents.clone())
+			.build();
+
+		let inserted: Vec<usize> = DATABASE.get().await.database.aql_query(query).await?;
+
+		Ok(inserted.iter().sum())
+	}
+}
@@ -0,0 +1,53 @@
+use std::fs;
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+
+use super::Fixture;
+
+/// Load every JSON or YAML fixture file of a directory, sorted by file name
+pub fn load_fixtures(path: &str) -> Result<Vec<Fixture>, Error> {
+	let mut fixtures = Vec::new();
+
+	if !Path::new(path).is_dir() {
+		return Ok(fixtures);
+	}
+
+	let mut paths = fs::read_dir(path)?
+		.map(|entry| entry.map(|e| e.path()))
+		.collect::<Result<Vec<_>, _>>()?;
+
+	paths.sort();
+
+	for path in paths {
+		let extension = path.extension().and_then(|ext| ext.to_str()).unwrap_or("");
+		let content = || fs::read_to_string(&path);
+
+		let fixture: Fixture = match extension {
+			"json" => serde_json::from_str(&content()?)
+				.map_err(|e| anyhow!("Invalid fixture {}: {}", path.display(), e))?,
+			"yaml" | "yml" => serde_yaml::from_str(&content()?)
+				.map_err(|e| anyhow!("Invalid fixture {}: {}", path.display(), e))?,
+			_ => continue,
+		};
+
+		fixtures.push(fixture);
+	}
+
+	Ok(fixtures)
+}
+
+/// Seed every fixture of a directory, returning the number of inserted documents
+pub async fn seed(path: &str) -> Result<usize, Error> {
+	let mut inserted = 0;
+
+	for fixture in load_fixtures(path)? {
+		let count = fixture.insert().await?;
+
+		tracing::info!(collection = %fixture.collection, count, "Seeded documents");
+
+		inserted += count;
+	}
+
+	Ok(inserted)
+}
@@ -0,0 +1,10 @@
+pub mod fixture;
+pub use fixture::Fixture;
+
+#[cfg(test)]
+pub mod factory;
+#[cfg(test)]
+pub use factory::Factory;
+
+pub mod loader;
+pub use loader::{load_fixtures, seed};
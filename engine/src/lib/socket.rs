@@ -0,0 +1,24 @@
+use std::fs;
+use std::io::{Error, ErrorKind, Result};
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+
+/// Remove the socket left behind by a previous run, binding fails otherwise
+pub fn remove_stale_socket(path: &str) -> Result<()> {
+	match fs::remove_file(path) {
+		Err(e) if e.kind() != ErrorKind::NotFound => Err(e),
+		_ => Ok(()),
+	}
+}
+
+/// Apply octal permissions such as `660` to the bound socket
+pub fn set_socket_permissions(path: &str, mode: &str) -> Result<()> {
+	let mode = u32::from_str_radix(mode.trim_start_matches("0o"), 8).map_err(|_| {
+		Error::new(
+			ErrorKind::InvalidInput,
+			format!("Invalid socket permissions {}", mode),
+		)
+	})?;
+
+	fs::set_permissions(Path::new(path), fs::Permissions::from_mode(mode))
+}
@@ -0,0 +1,89 @@
+use opentelemetry::sdk::{propagation::TraceContextPropagator, trace, Resource};
+use opentelemetry::{global, KeyValue};
+use opentelemetry_otlp::WithExportConfig;
+use std::sync::Mutex;
+
+use lazy_static::lazy_static;
+use tracing::Span;
+use tracing_subscriber::{
+	layer::SubscriberExt, reload, util::SubscriberInitExt, EnvFilter, Registry,
+};
+
+use crate::lib::config::LogFormat;
+use crate::lib::redaction::redact_aql;
+use crate::lib::CONFIG;
+
+lazy_static! {
+	/// Replaces the log filter on reload, unset when RUST_LOG sets the filter
+	static ref FILTER_HANDLE: Mutex<Option<reload::Handle<EnvFilter, Registry>>> = Mutex::new(None);
+}
+
+/// Install the tracing subscriber in the configured format, spans are exported over OTLP when an endpoint is configured
+pub fn init_telemetry() {
+	global::set_text_map_propagator(TraceContextPropagator::new());
+
+	let otel_layer = CONFIG.otlp_endpoint.as_ref().map(|endpoint| {
+		let tracer =
+			opentelemetry_otlp::new_pipeline()
+				.tracing()
+				.with_exporter(
+					opentelemetry_otlp::new_exporter()
+						.tonic()
+						.with_endpoint(endpoint),
+				)
+				.with_trace_config(trace::config().with_resource(Resource::new(vec![
+					KeyValue::new("service.name", CONFIG.otel_service_name.clone()),
+				])))
+				.install_batch(opentelemetry::runtime::TokioCurrentThread)
+				.expect("Failed to install the OTLP exporter");
+
+		tracing_opentelemetry::layer().with_tracer(tracer)
+	});
+
+	// RUST_LOG takes precedence over the configured level
+	let (filter, handle) = reload::Layer::new(
+		EnvFilter::try_from_default_env()
+			.unwrap_or_else(|_| EnvFilter::new(CONFIG.log_level.as_str())),
+	);
+
+	if std::env::var_os(EnvFilter::DEFAULT_ENV).is_none() {
+		*FILTER_HANDLE.lock().unwrap() = Some(handle);
+	}
+
+	let (pretty_layer, json_layer) = match CONFIG.log_format() {
+		LogFormat::Pretty => (Some(tracing_subscriber::fmt::layer().pretty()), None),
+		LogFormat::Json => (None, Some(tracing_subscriber::fmt::layer().json())),
+	};
+
+	tracing_subscriber::registry()
+		.with(filter)
+		.with(pretty_layer)
+		.with(json_layer)
+		.with(otel_layer)
+		.init();
+}
+
+/// Replace the configured log filter, RUST_LOG still takes precedence
+pub fn set_log_level(level: &str) {
+	if let Some(handle) = FILTER_HANDLE.lock().unwrap().as_ref() {
+		match handle.reload(EnvFilter::new(level)) {
+			Ok(()) => tracing::info!(level = %level, "Log level changed"),
+			Err(e) => tracing::warn!(error = %e, "Failed to change the log level"),
+		}
+	}
+}
+
+/// Flush the spans that are still buffered by the exporter
+pub fn shutdown_telemetry() {
+	global::shutdown_tracer_provider();
+}
+
+/// Span wrapping the execution of a single AQL query
+pub fn aql_span(collection: &str, query: &str) -> Span {
+	tracing::info_span!(
+		"aql",
+		db.system = "arangodb",
+		db.collection = collection,
+		db.statement = %redact_aql(query),
+	)
+}
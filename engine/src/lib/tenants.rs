@@ -0,0 +1,93 @@
+use std::collections::HashSet;
+use std::sync::RwLock;
+
+use lazy_static::lazy_static;
+
+use crate::lib::database::backend::BackendQuery;
+use crate::lib::CONFIG;
+
+/// The bind variables of the generated queries naming collections that hold the documents of
+/// a tenant, the outbox is shared
+const TENANT_COLLECTION_BINDS: [&str; 3] = ["@collection", "@history", "@versions"];
+
+lazy_static! {
+	/// The collections of the schema, unprefixed, the caches refer to them by these names
+	static ref COLLECTIONS: RwLock<HashSet<String>> = RwLock::new(HashSet::new());
+}
+
+/// Whether a tenant can be used in a collection name, it ends up in the AQL of traversals
+fn is_valid_tenant(tenant: &str) -> bool {
+	!tenant.is_empty()
+		&& tenant
+			.chars()
+			.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// The tenant of a request whose collections are used, `None` when collections are not
+/// prefixed or the claim cannot be part of a collection name
+pub fn request_tenant(tenant: Option<&str>) -> Option<String> {
+	if !CONFIG.tenant_prefix_enabled {
+		return None;
+	}
+
+	tenant
+		.filter(|tenant| is_valid_tenant(tenant))
+		.map(String::from)
+}
+
+/// The collection of a tenant, e.g. `acme_books`
+pub fn tenant_collection(tenant: Option<&str>, collection: &str) -> String {
+	match tenant {
+		Some(tenant) => format!("{}_{}", tenant, collection),
+		None => collection.to_string(),
+	}
+}
+
+/// Replace the collections of the schema, with those of the metadata it is generated from
+pub fn set_schema_collections(collections: HashSet<String>) {
+	*COLLECTIONS.write().unwrap() = collections;
+}
+
+/// The collections of the schema a collection of the database may belong to, e.g. `books` for
+/// `acme_books`, several when tenants and collections both contain underscores
+pub fn schema_collections(collection: &str) -> Vec<String> {
+	let collections = COLLECTIONS.read().unwrap();
+
+	if !CONFIG.tenant_prefix_enabled || collections.contains(collection) {
+		return vec![collection.to_string()];
+	}
+
+	let matches: Vec<String> = collections
+		.iter()
+		.filter(|name| {
+			collection
+				.strip_suffix(name.as_str())
+				.and_then(|prefix| prefix.strip_suffix('_'))
+				.map_or(false, is_valid_tenant)
+		})
+		.cloned()
+		.collect();
+
+	if matches.is_empty() {
+		vec![collection.to_string()]
+	} else {
+		matches
+	}
+}
+
+/// Point the collections bound to a generated query to the ones of the tenant
+pub fn with_tenant(mut query: BackendQuery, tenant: Option<&str>) -> BackendQuery {
+	if tenant.is_none() {
+		return query;
+	}
+
+	for bind in TENANT_COLLECTION_BINDS {
+		if let Some(collection) = query.bind_vars.get_mut(bind) {
+			if let Some(name) = collection.as_str() {
+				*collection = tenant_collection(tenant, name).into();
+			}
+		}
+	}
+
+	query
+}
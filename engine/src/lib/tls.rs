@@ -0,0 +1,48 @@
+use std::fs::File;
+use std::io::{BufReader, Error, ErrorKind, Result};
+
+use rustls::{Certificate, PrivateKey, ServerConfig};
+
+use crate::lib::secrets::check_secret_permissions;
+
+/// Build the rustls configuration from PEM encoded certificate chain and private key files
+pub fn load_rustls_config(cert_path: &str, key_path: &str) -> Result<ServerConfig> {
+	let cert_chain: Vec<Certificate> =
+		rustls_pemfile::certs(&mut BufReader::new(File::open(cert_path)?))?
+			.into_iter()
+			.map(Certificate)
+			.collect();
+
+	if cert_chain.is_empty() {
+		return Err(Error::new(
+			ErrorKind::InvalidInput,
+			format!("No certificate found in {}", cert_path),
+		));
+	}
+
+	check_secret_permissions(key_path)
+		.map_err(|e| Error::new(ErrorKind::PermissionDenied, format!("{:#}", e)))?;
+
+	// Accept both PKCS#8 and PKCS#1 (RSA) private keys
+	let mut keys = rustls_pemfile::pkcs8_private_keys(&mut BufReader::new(File::open(key_path)?))?;
+
+	if keys.is_empty() {
+		keys = rustls_pemfile::rsa_private_keys(&mut BufReader::new(File::open(key_path)?))?;
+	}
+
+	let key = match keys.into_iter().next() {
+		Some(key) => PrivateKey(key),
+		None => {
+			return Err(Error::new(
+				ErrorKind::InvalidInput,
+				format!("No private key found in {}", key_path),
+			))
+		}
+	};
+
+	ServerConfig::builder()
+		.with_safe_defaults()
+		.with_no_client_auth()
+		.with_single_cert(cert_chain, key)
+		.map_err(|e| Error::new(ErrorKind::InvalidInput, e))
+}
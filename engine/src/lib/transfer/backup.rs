@@ -0,0 +1,235 @@
+use std::fs::File;
+use std::io::{BufRead, BufReader, BufWriter, Write};
+
+use anyhow::{anyhow, Error};
+use rust_arango::AqlQuery;
+use serde::{Deserialize, Serialize};
+use serde_json::Value as JsonValue;
+
+use crate::lib::database::arango::{
+	create_collection_with_schema, create_search_view, ensure_collection,
+};
+use crate::lib::database::schema::{DatabaseSchema, Rule};
+use crate::lib::database::DATABASE;
+use crate::lib::schema::{
+	get_all_collections, get_all_edges, SchemaCollectionOptions, SchemaSearchOptions,
+};
+
+const BACKUP_BATCH_SIZE: usize = 1000;
+
+/// A single line of a backup archive
+#[derive(Serialize, Deserialize)]
+#[serde(tag = "kind", rename_all = "camelCase")]
+enum BackupRecord {
+	Collection {
+		entry: JsonValue,
+	},
+	Edge {
+		entry: JsonValue,
+	},
+	Document {
+		collection: String,
+		document: JsonValue,
+	},
+}
+
+/// What has been written to or read from an archive
+#[derive(Default, Debug)]
+pub struct BackupSummary {
+	pub collections: usize,
+	pub edges: usize,
+	pub documents: usize,
+}
+
+/// Dump the alchemy metadata and every managed collection into a JSONL archive
+pub async fn backup(path: &str) -> Result<BackupSummary, Error> {
+	let mut writer = BufWriter::new(File::create(path)?);
+	let mut summary = BackupSummary::default();
+
+	let collections = get_all_collections().await;
+	let edges = get_all_edges().await;
+
+	let mut names: Vec<String> = Vec::new();
+
+	for entry in collections {
+		names.push(entry_field(&entry, "name")?);
+		write_record(&mut writer, &BackupRecord::Collection { entry })?;
+		summary.collections += 1;
+	}
+
+	for entry in edges {
+		let edge = entry_field(&entry, "edge")?;
+
+		if !names.contains(&edge) {
+			names.push(edge);
+		}
+
+		write_record(&mut writer, &BackupRecord::Edge { entry })?;
+		summary.edges += 1;
+	}
+
+	let database = DATABASE.get().await.database.clone();
+
+	for name in names {
+		let query = AqlQuery::builder()
+			.query("FOR doc IN @@collection RETURN UNSET(doc, '_id', '_rev')")
+			.bind_var("@collection", name.clone())
+			.batch_size(BACKUP_BATCH_SIZE as u32)
+			.build();
+
+		let mut cursor = database.aql_query_batch::<JsonValue>(query).await?;
+
+		loop {
+			for document in cursor.result {
+				write_record(
+					&mut writer,
+					&BackupRecord::Document {
+						collection: name.clone(),
+						document,
+					},
+				)?;
+				summary.documents += 1;
+			}
+
+			match cursor.id {
+				Some(ref id) if cursor.more => {
+					cursor = database.aql_next_batch::<JsonValue>(id.as_str()).await?;
+				}
+				_ => break,
+			}
+		}
+	}
+
+	writer.flush()?;
+
+	Ok(summary)
+}
+
+/// Recreate the collections, metadata and documents of an archive in the current database
+pub async fn restore(path: &str) -> Result<BackupSummary, Error> {
+	let reader = BufReader::new(File::open(path)?);
+	let mut summary = BackupSummary::default();
+
+	ensure_collection("alchemy_collections").await?;
+	ensure_collection("alchemy_edges").await?;
+
+	let mut batch_collection = String::new();
+	let mut batch: Vec<JsonValue> = Vec::new();
+
+	for (index, line) in reader.lines().enumerate() {
+		let line = line?;
+
+		if line.trim().is_empty() {
+			continue;
+		}
+
+		let record: BackupRecord = serde_json::from_str(&line)
+			.map_err(|e| anyhow!("Invalid backup record on line {}: {}", index + 1, e))?;
+
+		match record {
+			BackupRecord::Collection { entry } => {
+				restore_collection(entry)
+					.await
+					.map_err(|e| anyhow!("Failed to restore line {}: {}", index + 1, e))?;
+				summary.collections += 1;
+			}
+			BackupRecord::Edge { entry } => {
+				restore_edge(entry)
+					.await
+					.map_err(|e| anyhow!("Failed to restore line {}: {}", index + 1, e))?;
+				summary.edges += 1;
+			}
+			BackupRecord::Document {
+				collection,
+				document,
+			} => {
+				if collection != batch_collection || batch.len() >= BACKUP_BATCH_SIZE {
+					insert_documents(&batch_collection, std::mem::take(&mut batch)).await?;
+					batch_collection = collection;
+				}
+
+				batch.push(document);
+				summary.documents += 1;
+			}
+		}
+	}
+
+	insert_documents(&batch_collection, batch).await?;
+
+	Ok(summary)
+}
+
+fn write_record(writer: &mut impl Write, record: &BackupRecord) -> Result<(), Error> {
+	serde_json::to_writer(&mut *writer, record)?;
+	writer.write_all(b"\n")?;
+
+	Ok(())
+}
+
+/// A string field of a metadata entry, e.g. the `name` of a collection
+fn entry_field(entry: &JsonValue, field: &str) -> Result<String, Error> {
+	entry[field]
+		.as_str()
+		.map(String::from)
+		.ok_or_else(|| anyhow!("The metadata entry {} has no {}", entry, field))
+}
+
+async fn restore_collection(entry: JsonValue) -> Result<(), Error> {
+	let name = entry_field(&entry, "name")?;
+	let rule: Rule = serde_json::from_value(entry["schema"].clone())?;
+
+	let options: SchemaCollectionOptions = entry
+		.get("options")
+		.and_then(|options| serde_json::from_value(options.clone()).ok())
+		.unwrap_or_default();
+
+	create_collection_with_schema(&name, &DatabaseSchema::from_rule(rule), &options).await?;
+
+	let search = entry
+		.get("search")
+		.and_then(|search| serde_json::from_value::<SchemaSearchOptions>(search.clone()).ok());
+
+	insert_documents("alchemy_collections", vec![entry]).await?;
+
+	// The view has to be created again since it is not part of the collection itself
+	if let Some(search) = search {
+		create_search_view(name, search).await?;
+	}
+
+	Ok(())
+}
+
+async fn restore_edge(entry: JsonValue) -> Result<(), Error> {
+	let edge = entry_field(&entry, "edge")?;
+	let db = DATABASE.get().await.database.clone();
+
+	if !db
+		.accessible_collections()
+		.await?
+		.iter()
+		.any(|c| c.name == edge)
+	{
+		db.create_edge_collection(&edge).await?;
+	}
+
+	insert_documents("alchemy_edges", vec![entry]).await
+}
+
+async fn insert_documents(collection: &str, documents: Vec<JsonValue>) -> Result<(), Error> {
+	if documents.is_empty() {
+		return Ok(());
+	}
+
+	let query = AqlQuery::builder()
+		.query(
+			"FOR doc IN @documents
+				INSERT UNSET(doc, '_id', '_rev') INTO @@collection",
+		)
+		.bind_var("@collection", collection)
+		.bind_var("documents", documents)
+		.build();
+
+	let _result: Vec<JsonValue> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(())
+}
@@ -0,0 +1,116 @@
+use anyhow::{anyhow, Error};
+use rust_arango::{AqlQuery, Database as ArangoDatabase};
+use serde_json::Value as JsonValue;
+
+use super::TransferFormat;
+use crate::lib::database::DATABASE;
+use crate::lib::schema::get_collection_entry;
+
+const EXPORT_BATCH_SIZE: u32 = 1000;
+
+/// Reads a collection through a server-side cursor, one batch at a time
+pub struct Exporter {
+	database: ArangoDatabase,
+	format: TransferFormat,
+	columns: Vec<String>,
+	cursor_id: Option<String>,
+	pending: Option<Vec<JsonValue>>,
+	header_written: bool,
+}
+
+impl Exporter {
+	pub async fn new(
+		collection: String,
+		format: TransferFormat,
+		filter: Option<JsonValue>,
+	) -> Result<Exporter, Error> {
+		let entry = get_collection_entry(&collection)
+			.await
+			.ok_or_else(|| anyhow!("Collection {} not found", collection))?;
+
+		let mut columns = vec![String::from("_key")];
+
+		if let Some(properties) = entry["schema"]["properties"].as_object() {
+			columns.extend(properties.keys().cloned());
+		}
+
+		let database = DATABASE.get().await.database.clone();
+
+		let query = AqlQuery::builder()
+			.query(
+				"FOR doc IN @@collection
+					FILTER @filter == null || MATCHES(doc, @filter)
+					RETURN doc",
+			)
+			.bind_var("@collection", collection)
+			.bind_var("filter", filter.unwrap_or(JsonValue::Null))
+			.batch_size(EXPORT_BATCH_SIZE)
+			.build();
+
+		let cursor = database.aql_query_batch::<JsonValue>(query).await?;
+
+		Ok(Exporter {
+			database,
+			format,
+			columns,
+			cursor_id: if cursor.more { cursor.id } else { None },
+			pending: Some(cursor.result),
+			header_written: false,
+		})
+	}
+
+	/// Serialize the next batch of documents, `None` once the cursor is exhausted
+	pub async fn next_chunk(&mut self) -> Result<Option<Vec<u8>>, Error> {
+		let documents = match self.pending.take() {
+			Some(documents) => documents,
+			None => match self.cursor_id.take() {
+				Some(id) => {
+					let cursor = self
+						.database
+						.aql_next_batch::<JsonValue>(id.as_str())
+						.await?;
+
+					if cursor.more {
+						self.cursor_id = cursor.id;
+					}
+
+					cursor.result
+				}
+				None => return Ok(None),
+			},
+		};
+
+		match self.format {
+			TransferFormat::Jsonl => {
+				let mut chunk = Vec::new();
+
+				for document in documents {
+					serde_json::to_writer(&mut chunk, &document)?;
+					chunk.push(b'\n');
+				}
+
+				Ok(Some(chunk))
+			}
+			TransferFormat::Csv => {
+				let mut writer = csv::Writer::from_writer(Vec::new());
+
+				if !self.header_written {
+					writer.write_record(&self.columns)?;
+					self.header_written = true;
+				}
+
+				for document in documents {
+					writer.write_record(self.columns.iter().map(|column| {
+						match &document[column] {
+							JsonValue::Null => String::new(),
+							JsonValue::String(s) => s.clone(),
+							value => value.to_string(),
+						}
+					}))?;
+				}
+
+				Ok(Some(writer.into_inner().map_err(|e| anyhow!("{}", e))?))
+			}
+		}
+	}
+}
@@ -0,0 +1,24 @@
+use serde::Deserialize;
+
+/// The line based formats supported by the import and export endpoints
+#[derive(Deserialize, PartialEq, Clone, Copy, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum TransferFormat {
+	Jsonl,
+	Csv,
+}
+
+impl Default for TransferFormat {
+	fn default() -> Self {
+		TransferFormat::Jsonl
+	}
+}
+
+impl TransferFormat {
+	pub fn content_type(&self) -> &'static str {
+		match self {
+			TransferFormat::Jsonl => "application/x-ndjson",
+			TransferFormat::Csv => "text/csv",
+		}
+	}
+}
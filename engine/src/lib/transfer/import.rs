@@ -0,0 +1,253 @@
+use std::collections::HashMap;
+
+use anyhow::{anyhow, Error};
+use jsonschema::JSONSchema;
+use reqwest::Method;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map as JsonMap, Value as JsonValue};
+
+use super::TransferFormat;
+use crate::lib::database::http::{api_error, send_api_request};
+use crate::lib::schema::get_collection_entry;
+
+const IMPORT_BATCH_SIZE: usize = 1000;
+
+/// The errors of a single rejected row
+#[derive(Serialize)]
+pub struct ImportRowError {
+	pub line: usize,
+	pub errors: Vec<String>,
+}
+
+/// The summary returned once an import is finished
+#[derive(Serialize, Default)]
+pub struct ImportReport {
+	pub created: usize,
+	pub rejected: usize,
+	pub errors: Vec<ImportRowError>,
+}
+
+/// The answer of the import API of the database
+#[derive(Deserialize)]
+struct ImportResult {
+	created: usize,
+	#[serde(default)]
+	details: Vec<String>,
+}
+
+/// Validates rows against the collection schema and inserts them in batches
+pub struct Importer {
+	collection: String,
+	format: TransferFormat,
+	schema: JSONSchema,
+	property_types: HashMap<String, String>,
+	headers: Option<Vec<String>>,
+	/// The documents to insert, along with the line they start on
+	batch: Vec<(usize, JsonValue)>,
+	/// A CSV record whose quoted cell continues on the next line, with the line it starts on
+	pending: Option<(usize, String)>,
+	line: usize,
+	report: ImportReport,
+}
+
+impl Importer {
+	pub async fn new(collection: String, format: TransferFormat) -> Result<Importer, Error> {
+		let entry = get_collection_entry(&collection)
+			.await
+			.ok_or_else(|| anyhow!("Collection {} not found", collection))?;
+
+		let schema = JSONSchema::compile(&entry["schema"])
+			.map_err(|e| anyhow!("Invalid schema for {}: {}", collection, e))?;
+
+		let property_types = entry["schema"]["properties"]
+			.as_object()
+			.map(|properties| {
+				properties
+					.iter()
+					.map(|(name, p)| {
+						let r#type = p["type"].as_str().unwrap_or("string").to_string();
+
+						(name.clone(), r#type)
+					})
+					.collect()
+			})
+			.unwrap_or_default();
+
+		Ok(Importer {
+			collection,
+			format,
+			schema,
+			property_types,
+			headers: None,
+			batch: Vec::with_capacity(IMPORT_BATCH_SIZE),
+			pending: None,
+			line: 0,
+			report: ImportReport::default(),
+		})
+	}
+
+	/// Parse, validate and queue a single line of the payload
+	pub async fn push_line(&mut self, line: &str) -> Result<(), Error> {
+		self.line += 1;
+
+		let (start, record) = match self.pending.take() {
+			Some((start, record)) => (start, format!("{}\n{}", record, line)),
+			None if line.trim().is_empty() => return Ok(()),
+			None => (self.line, line.to_string()),
+		};
+
+		let document = match self.format {
+			TransferFormat::Jsonl => {
+				serde_json::from_str::<JsonValue>(&record).map_err(|e| e.to_string())
+			}
+			// An odd number of quotes leaves a quoted cell open, escaped quotes come in pairs
+			TransferFormat::Csv if record.matches('"').count() % 2 == 1 => {
+				self.pending = Some((start, record));
+
+				return Ok(());
+			}
+			TransferFormat::Csv => match self.headers {
+				None => {
+					self.headers = Some(
+						parse_csv_record(&record)
+							.map_err(|e| anyhow!("Invalid CSV header: {}", e))?,
+					);
+
+					return Ok(());
+				}
+				Some(ref headers) => parse_csv_record(&record)
+					.map_err(|e| e.to_string())
+					.and_then(|cells| self.csv_to_document(headers, cells)),
+			},
+		};
+
+		self.queue(start, document).await
+	}
+
+	async fn queue(
+		&mut self,
+		line: usize,
+		document: Result<JsonValue, String>,
+	) -> Result<(), Error> {
+		let errors = match document {
+			Ok(ref document) => match self.schema.validate(document) {
+				Ok(_) => Vec::new(),
+				Err(errors) => errors.map(|e| e.to_string()).collect(),
+			},
+			Err(e) => vec![e],
+		};
+
+		if errors.is_empty() {
+			self.batch.push((line, document.unwrap()));
+		} else {
+			self.reject(line, errors);
+		}
+
+		if self.batch.len() >= IMPORT_BATCH_SIZE {
+			self.flush().await?;
+		}
+
+		Ok(())
+	}
+
+	fn reject(&mut self, line: usize, errors: Vec<String>) {
+		self.report.rejected += 1;
+		self.report.errors.push(ImportRowError { line, errors });
+	}
+
+	/// Insert the remaining rows and return the report
+	pub async fn finish(mut self) -> Result<ImportReport, Error> {
+		if let Some((start, _)) = self.pending.take() {
+			self.reject(start, vec![String::from("Unterminated quoted cell")]);
+		}
+
+		self.flush().await?;
+
+		Ok(self.report)
+	}
+
+	/// Insert the queued rows, the rows the database refuses, e.g. for a unique constraint, are
+	/// reported without failing the others
+	async fn flush(&mut self) -> Result<(), Error> {
+		if self.batch.is_empty() {
+			return Ok(());
+		}
+
+		let (lines, documents): (Vec<usize>, Vec<JsonValue>) =
+			std::mem::take(&mut self.batch).into_iter().unzip();
+
+		let response = send_api_request(
+			Method::POST,
+			&format!(
+				"import?collection={}&type=list&details=true",
+				self.collection
+			),
+			JsonValue::Array(documents),
+		)
+		.await?;
+
+		if !response.status().is_success() {
+			return Err(api_error(response).await);
+		}
+
+		let result: ImportResult = response.json().await?;
+
+		self.report.created += result.created;
+
+		// e.g. `at position 3: creating document failed with error 'unique constraint violated'`
+		for detail in result.details {
+			let line = detail
+				.strip_prefix("at position ")
+				.and_then(|detail| detail.split_once(':'))
+				.and_then(|(position, _)| position.parse::<usize>().ok())
+				.and_then(|position| lines.get(position).copied())
+				.unwrap_or_default();
+
+			self.reject(line, vec![detail]);
+		}
+
+		Ok(())
+	}
+
+	/// Convert CSV cells into typed values based on the collection schema
+	fn csv_to_document(&self, headers: &[String], cells: Vec<String>) -> Result<JsonValue, String> {
+		if cells.len() != headers.len() {
+			return Err(format!(
+				"Expected {} cells, found {}",
+				headers.len(),
+				cells.len()
+			));
+		}
+
+		let mut document = JsonMap::new();
+
+		for (header, cell) in headers.iter().zip(cells) {
+			if cell.is_empty() {
+				continue;
+			}
+
+			let value = match self.property_types.get(header).map(|t| t.as_str()) {
+				Some("integer") => cell.parse::<i64>().map(JsonValue::from).ok(),
+				Some("number") => cell.parse::<f64>().map(JsonValue::from).ok(),
+				Some("boolean") => cell.parse::<bool>().map(JsonValue::from).ok(),
+				Some("array") | Some("object") => serde_json::from_str(&cell).ok(),
+				_ => None,
+			};
+
+			document.insert(header.clone(), value.unwrap_or(JsonValue::String(cell)));
+		}
+
+		Ok(JsonValue::Object(document))
+	}
+}
+
+fn parse_csv_record(record: &str) -> Result<Vec<String>, Error> {
+	let mut reader = csv::ReaderBuilder::new()
+		.has_headers(false)
+		.from_reader(record.as_bytes());
+
+	match reader.records().next() {
+		Some(record) => Ok(record?.iter().map(|cell| cell.to_string()).collect()),
+		None => Ok(Vec::new()),
+	}
+}
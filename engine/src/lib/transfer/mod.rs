@@ -0,0 +1,11 @@
+pub mod format;
+pub use format::TransferFormat;
+
+pub mod backup;
+pub use backup::{backup, restore, BackupSummary};
+
+pub mod export;
+pub use export::Exporter;
+
+pub mod import;
+pub use import::{ImportReport, Importer};
@@ -0,0 +1,212 @@
+use std::time::Duration;
+
+use actix_web::rt;
+use anyhow::Error;
+use lazy_static::lazy_static;
+use reqwest::Client;
+use rust_arango::AqlQuery;
+use serde::Deserialize;
+use serde_json::{json, Value as JsonValue};
+
+use crate::lib::changes::ChangeOperation;
+use crate::lib::database::DATABASE;
+use crate::lib::schema::SchemaTrigger;
+use crate::lib::CONFIG;
+
+/// Collection logging every delivery of the event triggers
+pub const TRIGGER_DELIVERIES_COLLECTION: &str = "alchemy_trigger_deliveries";
+
+lazy_static! {
+	static ref CLIENT: Client = Client::builder()
+		.timeout(Duration::from_millis(CONFIG.trigger_timeout_ms))
+		.build()
+		.expect("Failed to build the event trigger client");
+}
+
+/// A delivery of an event to a trigger webhook
+#[derive(Deserialize, GraphQLObject)]
+#[serde(rename_all = "camelCase")]
+pub struct TriggerDelivery {
+	#[serde(rename = "_key")]
+	pub id: String,
+	pub trigger: String,
+	pub collection: String,
+	pub tenant: Option<String>,
+	pub operation: String,
+	/// `pending`, `delivered` or `failed`
+	pub status: String,
+	pub attempts: i32,
+	pub response_status: Option<i32>,
+	pub error: Option<String>,
+	pub created_at: String,
+	pub delivered_at: Option<String>,
+}
+
+/// A change of a document, delivered to the triggers of its collection
+#[derive(Clone)]
+pub struct TriggerEvent {
+	pub collection: String,
+	/// The tenant whose collection changed, when collections are prefixed per tenant
+	pub tenant: Option<String>,
+	pub operation: ChangeOperation,
+	pub old: JsonValue,
+	pub new: JsonValue,
+	pub actor: Option<String>,
+}
+
+async fn log_delivery(trigger: &SchemaTrigger, event: &TriggerEvent) -> Result<String, Error> {
+	let query = AqlQuery::builder()
+		.query(
+			"INSERT MERGE(@delivery, { createdAt: DATE_ISO8601(DATE_NOW()) }) INTO @@collection
+				RETURN NEW._key",
+		)
+		.bind_var("@collection", TRIGGER_DELIVERIES_COLLECTION)
+		.bind_var(
+			"delivery",
+			json!({
+				"trigger": trigger.name,
+				"collection": event.collection,
+				"tenant": event.tenant,
+				"operation": event.operation,
+				"status": "pending",
+				"attempts": 0,
+			}),
+		)
+		.build();
+
+	let keys: Vec<String> = DATABASE.get().await.database.aql_query(query).await?;
+
+	Ok(keys.into_iter().next().unwrap_or_default())
+}
+
+async fn update_delivery(key: &str, patch: JsonValue, delivered: bool) {
+	let query = AqlQuery::builder()
+		.query(
+			"UPDATE @key
+				WITH MERGE(@patch, @delivered ? { deliveredAt: DATE_ISO8601(DATE_NOW()) } : {})
+				IN @@collection",
+		)
+		.bind_var("@collection", TRIGGER_DELIVERIES_COLLECTION)
+		.bind_var("key", key)
+		.bind_var("patch", patch)
+		.bind_var("delivered", delivered)
+		.build();
+
+	let result: Result<Vec<JsonValue>, _> = DATABASE.get().await.database.aql_query(query).await;
+
+	if let Err(e) = result {
+		tracing::error!(delivery = %key, error = ?e, "Failed to update the trigger delivery");
+	}
+}
+
+/// POST the payload, retrying with an exponential backoff until the webhook answers
+/// with a success or the retries run out
+async fn deliver(trigger: SchemaTrigger, event: &TriggerEvent) {
+	let key = match log_delivery(&trigger, event).await {
+		Ok(key) => key,
+		Err(e) => {
+			tracing::error!(trigger = %trigger.name, error = %e, "Failed to log the trigger delivery");
+
+			return;
+		}
+	};
+
+	let payload = json!({
+		"id": key,
+		"trigger": { "name": trigger.name },
+		"collection": event.collection,
+		"tenant": event.tenant,
+		"operation": event.operation,
+		"data": { "old": event.old, "new": event.new },
+		"actor": event.actor,
+	});
+
+	for attempt in 1..=CONFIG.trigger_max_retries + 1 {
+		let (response_status, error) = match CLIENT.post(&trigger.url).json(&payload).send().await {
+			Ok(response) if response.status().is_success() => {
+				update_delivery(
+					&key,
+					json!({
+						"status": "delivered",
+						"attempts": attempt,
+						"responseStatus": response.status().as_u16(),
+						"error": null,
+					}),
+					true,
+				)
+				.await;
+
+				tracing::debug!(trigger = %trigger.name, delivery = %key, attempt, "Event delivered");
+
+				return;
+			}
+			Ok(response) => (
+				Some(response.status().as_u16()),
+				format!("Webhook answered with {}", response.status()),
+			),
+			Err(e) => (None, e.to_string()),
+		};
+
+		let last = attempt > CONFIG.trigger_max_retries;
+
+		update_delivery(
+			&key,
+			json!({
+				"status": if last { "failed" } else { "pending" },
+				"attempts": attempt,
+				"responseStatus": response_status,
+				"error": error,
+			}),
+			false,
+		)
+		.await;
+
+		if last {
+			tracing::error!(trigger = %trigger.name, delivery = %key, error = %error, "Event delivery failed");
+
+			return;
+		}
+
+		let backoff = CONFIG
+			.trigger_retry_base_ms
+			.saturating_mul(1u64 << (attempt - 1).min(16));
+
+		rt::time::sleep(Duration::from_millis(backoff)).await;
+	}
+}
+
+/// Deliver a change to the triggers in the background, the mutation does not wait for them
+pub fn dispatch_triggers(triggers: Vec<SchemaTrigger>, event: TriggerEvent) {
+	if triggers.is_empty() {
+		return;
+	}
+
+	rt::spawn(async move {
+		futures::future::join_all(triggers.into_iter().map(|trigger| deliver(trigger, &event)))
+			.await;
+	});
+}
+
+/// The deliveries, most recent first
+pub async fn get_trigger_deliveries(
+	trigger: Option<String>,
+	status: Option<String>,
+	limit: i32,
+) -> Result<Vec<TriggerDelivery>, Error> {
+	let query = AqlQuery::builder()
+		.query(
+			"FOR delivery IN @@collection
+				FILTER @trigger == null OR delivery.trigger == @trigger
+				FILTER @status == null OR delivery.status == @status
+				SORT delivery.createdAt DESC
+				LIMIT @limit
+				RETURN delivery",
+		)
+		.bind_var("@collection", TRIGGER_DELIVERIES_COLLECTION)
+		.bind_var("trigger", json!(trigger))
+		.bind_var("status", json!(status))
+		.bind_var("limit", limit.max(0))
+		.build();
+
+	Ok(DATABASE.get().await.database.aql_query(query).await?)
+}
@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+use super::UploadStorage;
+
+/// Reference to a stored upload, saved as the value of `File` properties
+#[derive(Serialize, Deserialize, Clone, PartialEq, Debug)]
+pub struct UploadedFile {
+	pub id: String,
+	pub filename: String,
+	pub mimetype: String,
+	pub size: usize,
+	pub storage: UploadStorage,
+	/// Document key, file path or object key depending on the storage
+	pub location: String,
+}
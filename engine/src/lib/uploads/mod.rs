@@ -0,0 +1,5 @@
+pub mod file;
+pub use file::UploadedFile;
+
+pub mod storage;
+pub use storage::{delete_upload, store_upload, upload_id, UploadStorage};
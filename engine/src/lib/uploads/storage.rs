@@ -0,0 +1,191 @@
+use std::path::Path;
+
+use anyhow::{anyhow, Error};
+use rust_arango::AqlQuery;
+use s3::{creds::Credentials, Bucket, Region};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use uuid::Uuid;
+
+use super::UploadedFile;
+use crate::lib::database::arango::ensure_collection;
+use crate::lib::database::DATABASE;
+use crate::lib::CONFIG;
+
+/// Collection holding the uploads kept in the database
+pub const UPLOADS_COLLECTION: &str = "alchemy_uploads";
+
+/// Where the uploaded files are stored
+#[derive(Serialize, Deserialize, Clone, Copy, PartialEq, Debug, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum UploadStorage {
+	/// Base64 encoded in the uploads collection
+	#[default]
+	Database,
+	/// In the configured upload directory
+	Directory,
+	/// In an S3 compatible bucket
+	S3,
+}
+
+/// Keep the file name safe to use as a path or an object key
+fn sanitize_filename(filename: &str) -> String {
+	filename
+		.chars()
+		.map(|c| {
+			if c.is_ascii_alphanumeric() || matches!(c, '.' | '-' | '_') {
+				c
+			} else {
+				'_'
+			}
+		})
+		.collect()
+}
+
+/// The ID of a file about to be stored, substituted in the variables of its request
+pub fn upload_id() -> String {
+	Uuid::new_v4().to_string()
+}
+
+/// Store an uploaded file with the configured storage
+pub async fn store_upload(
+	id: String,
+	filename: &str,
+	mimetype: &str,
+	content: Vec<u8>,
+) -> Result<UploadedFile, Error> {
+	let name = format!("{}-{}", id, sanitize_filename(filename));
+	let size = content.len();
+
+	let location = match CONFIG.upload_storage {
+		UploadStorage::Database => store_in_database(&id, filename, mimetype, &content).await?,
+		UploadStorage::Directory => {
+			let path = Path::new(&CONFIG.upload_directory).join(&name);
+
+			tokio::fs::create_dir_all(&CONFIG.upload_directory).await?;
+			tokio::fs::write(&path, &content).await?;
+
+			path.to_string_lossy().to_string()
+		}
+		UploadStorage::S3 => {
+			let bucket = get_bucket()?;
+
+			let (_, status) = bucket
+				.put_object_with_content_type(&name, &content, mimetype)
+				.await?;
+
+			if status >= 300 {
+				return Err(anyhow!(
+					"The bucket rejected the upload with status {}",
+					status
+				));
+			}
+
+			name
+		}
+	};
+
+	Ok(UploadedFile {
+		id,
+		filename: filename.to_string(),
+		mimetype: mimetype.to_string(),
+		size,
+		storage: CONFIG.upload_storage,
+		location,
+	})
+}
+
+/// Remove a stored file, e.g. once the request it was sent with failed
+pub async fn delete_upload(file: &UploadedFile) -> Result<(), Error> {
+	match file.storage {
+		UploadStorage::Database => {
+			let query = AqlQuery::builder()
+				.query("REMOVE @key IN @@collection OPTIONS { ignoreErrors: true }")
+				.bind_var("@collection", UPLOADS_COLLECTION)
+				.bind_var("key", file.location.as_str())
+				.build();
+
+			DATABASE
+				.get()
+				.await
+				.database
+				.aql_query::<serde_json::Value>(query)
+				.await?;
+		}
+		UploadStorage::Directory => tokio::fs::remove_file(&file.location).await?,
+		UploadStorage::S3 => {
+			let (_, status) = get_bucket()?.delete_object(&file.location).await?;
+
+			if status >= 300 {
+				return Err(anyhow!(
+					"The bucket rejected the removal with status {}",
+					status
+				));
+			}
+		}
+	}
+
+	Ok(())
+}
+
+async fn store_in_database(
+	id: &str,
+	filename: &str,
+	mimetype: &str,
+	content: &[u8],
+) -> Result<String, Error> {
+	ensure_collection(UPLOADS_COLLECTION).await?;
+
+	let query = AqlQuery::builder()
+		.query("INSERT @document INTO @@collection RETURN NEW._key")
+		.bind_var("@collection", UPLOADS_COLLECTION)
+		.bind_var(
+			"document",
+			json!({
+				"_key": id,
+				"filename": filename,
+				"mimetype": mimetype,
+				"size": content.len(),
+				"data": base64::encode(content),
+			}),
+		)
+		.build();
+
+	let keys: Vec<String> = DATABASE.get().await.database.aql_query(query).await?;
+
+	keys.into_iter()
+		.next()
+		.ok_or_else(|| anyhow!("The upload was not stored"))
+}
+
+fn get_bucket() -> Result<Bucket, Error> {
+	let name = CONFIG
+		.s3_bucket
+		.as_ref()
+		.ok_or_else(|| anyhow!("S3_BUCKET is required to store uploads in S3"))?;
+
+	let region = match &CONFIG.s3_endpoint {
+		Some(endpoint) => Region::Custom {
+			region: CONFIG.s3_region.clone(),
+			endpoint: endpoint.clone(),
+		},
+		None => CONFIG.s3_region.parse()?,
+	};
+
+	let credentials = Credentials::new(
+		CONFIG.s3_access_key.as_deref(),
+		CONFIG.s3_secret_key.as_deref(),
+		None,
+		None,
+		None,
+	)?;
+
+	// Most self-hosted S3 implementations only support path style requests
+	let bucket = if CONFIG.s3_endpoint.is_some() {
+		Bucket::new_with_path_style(name, region, credentials)?
+	} else {
+		Bucket::new(name, region, credentials)?
+	};
+
+	Ok(bucket)
+}
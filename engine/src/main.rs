@@ -8,70 +8,377 @@ extern crate derivative;
 #[macro_use]
 extern crate juniper_codegen;
 
-use actix_cors::Cors;
 use actix_web::{
-	http::header,
+	dev::Service,
+	http::KeepAlive,
 	middleware,
 	web::{self, Data},
 	App, HttpServer,
 };
+use std::time::Duration;
+use tracing_actix_web::TracingLogger;
 
 mod api;
 mod lib;
 mod meta;
 
+use api::audit::AUDIT_COLLECTION;
+use api::auth::with_authentication;
+use api::change_feed::start_change_feed;
+use api::compression::{with_compression_rules, with_preferred_encoding};
+use api::metrics::configure_metrics;
+use api::persisted::{load_persisted_queries, PERSISTED_QUERIES_COLLECTION};
+use api::rate_limit::with_rate_limit;
+use api::remote::load_remote_schemas;
+use api::request_id::{with_request_id, RequestIdRootSpanBuilder};
+use api::rest::configure_rest;
+use api::sessions::{check_session_key, configure_sessions};
+use lib::cli::{Cli, Command};
+use lib::cors::build_cors;
+use lib::database::arango::ensure_collection;
 use lib::database::generate_sdl;
+use lib::database::introspection::{introspect_collections, introspect_database};
+use lib::events::{outbox_enabled, start_outbox_relay, OUTBOX_COLLECTION};
+use lib::migrations::{migrate, rollback};
+use lib::reload::watch_config;
+use lib::scheduler::start_scheduler;
+use lib::schema::{apply_schema_file, log_schema_status};
+use lib::seeds::seed;
+use lib::telemetry::{init_telemetry, shutdown_telemetry};
+use lib::tls::load_rustls_config;
+use lib::transfer::{backup, restore};
 use lib::CONFIG;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
+	let cli = Cli::init();
+
 	pluralizer::initialize();
+	init_telemetry();
+
+	match cli.command.unwrap_or(Command::Serve) {
+		Command::Serve => return serve().await,
+		Command::Migrate => {
+			let versions = migrate(&CONFIG.migrations_path).await.unwrap();
+
+			tracing::info!(count = versions.len(), "Applied migrations");
+		}
+		Command::Rollback => match rollback(&CONFIG.migrations_path).await.unwrap() {
+			Some(version) => tracing::info!(version, "Reverted migration"),
+			None => tracing::info!("No migration to revert"),
+		},
+		Command::Seed => {
+			let count = seed(&CONFIG.seeds_path).await.unwrap();
+
+			tracing::info!(count, "Seeded documents");
+		}
+		Command::Introspect { dry_run: false } => {
+			let summary = introspect_database().await.unwrap();
+
+			tracing::info!(
+				collections = summary.collections.len(),
+				relationships = summary.relationships.len(),
+				"Introspected the database"
+			);
+		}
+		Command::Introspect { dry_run: true } => {
+			for collection in introspect_collections().await.unwrap() {
+				println!(
+					"{}\t{}\t{}",
+					collection.name,
+					if collection.edge { "edge" } else { "document" },
+					if collection.tracked {
+						"tracked"
+					} else {
+						"untracked"
+					}
+				);
+			}
+		}
+		Command::ExportSchema { output } => {
+			let sdl = api::schema::schema(generate_sdl().await, load_remote_schemas().await).sdl();
+
+			match output {
+				Some(path) => {
+					std::fs::write(&path, sdl)?;
+
+					tracing::info!(path = %path, "Schema exported");
+				}
+				None => print!("{}", sdl),
+			}
+		}
+		Command::Backup { path } => {
+			let summary = backup(&path).await.unwrap();
+
+			tracing::info!(summary = ?summary, path = %path, "Backup written");
+		}
+		Command::Restore { path } => {
+			let summary = restore(&path).await.unwrap();
+
+			tracing::info!(summary = ?summary, path = %path, "Backup restored");
+		}
+	}
+
+	shutdown_telemetry();
+
+	Ok(())
+}
+
+/// Refuse a listener that cannot be served as configured, rather than falling back to plain
+/// HTTP on the port
+fn check_listener() -> std::io::Result<()> {
+	let invalid = |message: &str| std::io::Error::new(std::io::ErrorKind::InvalidInput, message);
+
+	if CONFIG.tls_cert_path.is_some() != CONFIG.tls_key_path.is_some() {
+		return Err(invalid(
+			"TLS_CERT_PATH and TLS_KEY_PATH must be set together",
+		));
+	}
+
+	if cfg!(not(unix)) && CONFIG.unix_socket_path.is_some() {
+		return Err(invalid("UNIX_SOCKET_PATH is only supported on Unix"));
+	}
+
+	Ok(())
+}
+
+/// Prepare the database and run the GraphQL server until it is stopped
+async fn serve() -> std::io::Result<()> {
+	check_listener()?;
+
+	if let Some(path) = &CONFIG.schema_path {
+		apply_schema_file(path).await.unwrap();
+	}
+
+	if CONFIG.auto_introspect {
+		introspect_database().await.unwrap();
+	}
+
+	if CONFIG.auto_migrate {
+		migrate(&CONFIG.migrations_path).await.unwrap();
+	}
+
+	if CONFIG.schema_check {
+		log_schema_status().await;
+	}
+
+	if CONFIG.audit_log_enabled {
+		ensure_collection(AUDIT_COLLECTION).await.unwrap();
+	}
+
+	if outbox_enabled() {
+		ensure_collection(OUTBOX_COLLECTION).await.unwrap();
+	}
+
+	if CONFIG.apq_shared {
+		ensure_collection(PERSISTED_QUERIES_COLLECTION)
+			.await
+			.unwrap();
+	}
+
+	if CONFIG.auto_seed {
+		seed(&CONFIG.seeds_path).await.unwrap();
+	}
+
+	let persisted_queries = load_persisted_queries(&CONFIG.persisted_queries_path)
+		.await
+		.unwrap();
+
+	if CONFIG.persisted_queries_only {
+		tracing::info!(
+			count = persisted_queries,
+			"Only the persisted queries are allowed"
+		);
+	}
+
+	check_session_key();
+	watch_config();
+	start_scheduler();
+	start_outbox_relay();
+	start_change_feed();
 
 	let app_port = CONFIG.app_port.parse::<u16>().unwrap_or(8080);
 
-	println!("Starting Alchemy on port {:?}", app_port);
+	tracing::info!(
+		host = %CONFIG.app_host,
+		port = app_port,
+		base_path = %CONFIG.base_path(),
+		profile = ?CONFIG.profile(),
+		"Starting Alchemy"
+	);
 
 	let map = generate_sdl().await;
-	let api_schema = Data::new(api::schema::schema(map.clone()));
+	let api_schemas = Data::new(api::schema::schema(
+		map.clone(),
+		load_remote_schemas().await,
+	));
+
+	if CONFIG.preflight_operations && !api_schemas.preflight().await.is_empty() {
+		return Err(std::io::Error::new(
+			std::io::ErrorKind::Other,
+			"Some generated operations are invalid, see the preflight report",
+		));
+	}
 
 	let meta_schema = Data::new(meta::graphql::schema());
 
+	#[cfg(feature = "grpc")]
+	api::grpc::start_grpc_server(api_schemas.clone());
+
 	// Actix server
-	HttpServer::new(move || {
+	let server = HttpServer::new(move || {
 		App::new()
 			.app_data(meta_schema.clone())
-			.app_data(api_schema.clone())
-			.wrap(
-				Cors::default()
-					.allow_any_origin()
-					.allowed_methods(vec!["POST", "GET"])
-					.allowed_headers(vec![header::AUTHORIZATION, header::ACCEPT])
-					.allowed_header(header::CONTENT_TYPE)
-					.supports_credentials()
-					.max_age(3600),
-			)
+			.app_data(api_schemas.clone())
+			.app_data(web::PayloadConfig::new(CONFIG.max_body_size))
+			.wrap(build_cors())
+			.wrap_fn(|req, srv| with_compression_rules(req, |req| srv.call(req)))
 			.wrap(middleware::Compress::default())
-			.wrap(middleware::Logger::default())
-			.service(
-				web::resource("/api/graphql")
-					.route(web::post().to(api::server::graphql_api_route))
-					.route(web::get().to(api::server::graphql_api_route)),
-			)
+			.wrap_fn(|req, srv| with_preferred_encoding(req, |req| srv.call(req)))
+			.wrap(TracingLogger::<RequestIdRootSpanBuilder>::new())
+			.wrap_fn(|req, srv| with_request_id(req, |req| srv.call(req)))
 			.service(
-				web::resource("/api/playground")
-					.route(web::get().to(api::server::playground_api_route)),
+				web::scope(&CONFIG.base_path())
+					.service(
+						web::resource("/healthz").route(web::get().to(api::health::healthz_route)),
+					)
+					.service(
+						web::resource("/readyz").route(web::get().to(api::health::readyz_route)),
+					)
+					.service(
+						web::resource(&CONFIG.graphql_path)
+							.wrap_fn(|req, srv| with_authentication(req, |req| srv.call(req)))
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::post().to(api::server::graphql_api_route))
+							.route(web::get().to(api::server::graphql_api_route)),
+					)
+					.service(
+						web::resource(&CONFIG.graphql_subscriptions_path)
+							.route(web::get().to(api::subscriptions::graphql_subscriptions_route)),
+					)
+					.service(
+						web::resource(&CONFIG.graphql_sdl_path)
+							.route(web::get().to(api::server::sdl_api_route)),
+					)
+					.service(
+						web::resource(&CONFIG.openapi_path)
+							.route(web::get().to(api::openapi::openapi_route)),
+					)
+					.configure(|_config| {
+						#[cfg(feature = "grpc")]
+						api::grpc::configure_grpc(_config);
+					})
+					.service(
+						web::resource(&CONFIG.graphql_ide_path)
+							.route(web::get().to(api::server::ide_api_route)),
+					)
+					.configure(configure_rest)
+					.configure(configure_sessions)
+					.configure(configure_metrics)
+					.service(
+						web::resource("/admin/graphql")
+							// The admin secret is guessed no faster than the API is called
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::post().to(meta::graphql::server::graphql_meta_route))
+							.route(web::get().to(meta::graphql::server::graphql_meta_route)),
+					)
+					.service(
+						web::resource("/admin/playground")
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::get().to(meta::graphql::server::ide_meta_route)),
+					)
+					.service(
+						web::resource("/admin/dashboard")
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::get().to(meta::dashboard::dashboard_route)),
+					)
+					.service(
+						web::resource("/admin/dashboard/data")
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::get().to(meta::dashboard::dashboard_data_route)),
+					)
+					.service(
+						web::resource("/meta/graphql")
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::post().to(meta::graphql::server::graphql_meta_route))
+							.route(web::get().to(meta::graphql::server::graphql_meta_route)),
+					)
+					.service(
+						web::resource("/meta/playground")
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::get().to(meta::graphql::server::ide_meta_route)),
+					)
+					.service(
+						web::resource("/import/{collection}")
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::post().to(meta::import::import_route)),
+					)
+					.service(
+						web::resource("/export/{collection}")
+							.wrap_fn(|req, srv| with_rate_limit(req, |req| srv.call(req)))
+							.route(web::get().to(meta::export::export_route)),
+					),
 			)
-			.service(
-				web::resource("/meta/graphql")
-					.route(web::post().to(meta::graphql::server::graphql_meta_route))
-					.route(web::get().to(meta::graphql::server::graphql_meta_route)),
-			)
-			.service(
-				web::resource("/meta/playground")
-					.route(web::get().to(meta::graphql::server::playground_meta_route)),
-			)
-	})
-	.bind(("0.0.0.0", app_port))?
-	.run()
-	.await
+	});
+
+	let server = match CONFIG.app_workers {
+		Some(workers) => server.workers(workers),
+		None => server,
+	};
+
+	let server = match CONFIG.keep_alive_secs {
+		Some(0) => server.keep_alive(KeepAlive::Disabled),
+		Some(secs) => server.keep_alive(Duration::from_secs(secs)),
+		None => server,
+	};
+
+	let server = match CONFIG.client_request_timeout_ms {
+		Some(timeout) => server.client_timeout(timeout),
+		None => server,
+	};
+
+	let server = match CONFIG.client_disconnect_timeout_ms {
+		Some(timeout) => server.client_shutdown(timeout),
+		None => server,
+	};
+
+	let server = match CONFIG.shutdown_timeout_secs {
+		Some(timeout) => server.shutdown_timeout(timeout),
+		None => server,
+	};
+
+	let address = (CONFIG.app_host.as_str(), app_port);
+
+	let server = match (
+		&CONFIG.unix_socket_path,
+		&CONFIG.tls_cert_path,
+		&CONFIG.tls_key_path,
+	) {
+		#[cfg(unix)]
+		(Some(socket_path), _, _) => {
+			tracing::info!(socket = %socket_path, "Listening on a Unix socket");
+
+			lib::socket::remove_stale_socket(socket_path)?;
+
+			let server = server.bind_uds(socket_path)?;
+
+			if let Some(mode) = &CONFIG.unix_socket_mode {
+				lib::socket::set_socket_permissions(socket_path, mode)?;
+			}
+
+			server
+		}
+		(_, Some(cert_path), Some(key_path)) => {
+			tracing::info!(certificate = %cert_path, "Serving HTTPS");
+
+			server.bind_rustls(address, load_rustls_config(cert_path, key_path)?)?
+		}
+		_ => server.bind(address)?,
+	};
+
+	let result = server.run().await;
+
+	shutdown_telemetry();
+
+	result
 }
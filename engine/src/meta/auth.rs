@@ -0,0 +1,31 @@
+use actix_web::{error::ErrorUnauthorized, Error as ActixError, HttpRequest as ActixRequest};
+use sha2::{Digest, Sha256};
+
+use crate::lib::CONFIG;
+
+pub const ADMIN_SECRET_HEADER: &str = "X-Alchemy-Admin-Secret";
+
+/// Check the admin secret header, only development is left open when no secret is configured
+pub fn require_admin(req: &ActixRequest) -> Result<(), ActixError> {
+	let authorized = match CONFIG.admin_secret.as_deref().filter(|s| !s.is_empty()) {
+		Some(secret) => req
+			.headers()
+			.get(ADMIN_SECRET_HEADER)
+			.and_then(|value| value.to_str().ok())
+			.map(|value| matches_secret(value, secret))
+			.unwrap_or(false),
+		None => CONFIG.is_development(),
+	};
+
+	if authorized {
+		Ok(())
+	} else {
+		Err(ErrorUnauthorized("Admin secret required"))
+	}
+}
+
+/// The digests are compared rather than the secrets, so that the time taken tells nothing about
+/// how much of the secret was guessed
+fn matches_secret(value: &str, secret: &str) -> bool {
+	Sha256::digest(value.as_bytes()) == Sha256::digest(secret.as_bytes())
+}
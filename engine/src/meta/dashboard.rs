@@ -0,0 +1,95 @@
+use actix_web::{
+	web::Data, Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use lazy_static::lazy_static;
+use serde_json::{json, Value as JsonValue};
+
+use super::auth::{require_admin, ADMIN_SECRET_HEADER};
+use crate::api::schema::Schemas;
+use crate::lib::database::backend::recent_slow_queries;
+use crate::lib::migrations::migration_status;
+use crate::lib::CONFIG;
+
+const DASHBOARD_PAGE: &str = include_str!("dashboard.html");
+
+lazy_static! {
+	static ref DATA_PATH: String = CONFIG.path("/admin/dashboard/data");
+}
+
+/// The page of the dashboard, it holds no data, which it loads with the admin secret
+pub async fn dashboard_route() -> Result<ActixResponse, ActixError> {
+	let page = DASHBOARD_PAGE
+		.replace("{{DATA_PATH}}", &DATA_PATH)
+		.replace("{{ADMIN_SECRET_HEADER}}", ADMIN_SECRET_HEADER);
+
+	Ok(ActixResponse::Ok()
+		.content_type("text/html; charset=utf-8")
+		.body(page))
+}
+
+/// What the dashboard shows: the collections with their operations, the relationships, the
+/// recent slow queries and the status of the migrations
+pub async fn dashboard_data_route(
+	req: ActixRequest,
+	schemas: Data<Schemas>,
+) -> Result<ActixResponse, ActixError> {
+	require_admin(&req)?;
+
+	let collections: Vec<JsonValue> = schemas
+		.entities()
+		.into_iter()
+		.map(|(entity, _)| {
+			let operations = schemas
+				.operation_names()
+				.into_iter()
+				.find(|(collection, _)| *collection == entity.collection_name)
+				.map(|(_, names)| names)
+				.unwrap_or_default();
+
+			json!({
+				"name": entity.name,
+				"collection": entity.collection_name,
+				"properties": entity.properties.len(),
+				"edge": entity.edge,
+				"operations": operations,
+			})
+		})
+		.collect();
+
+	let relationships: Vec<JsonValue> = schemas
+		.relationships()
+		.iter()
+		.map(|relationship| {
+			json!({
+				"name": relationship.name,
+				"edge": relationship.edge,
+				"from": relationship.from.collection_name,
+				"to": relationship.to.collection_name,
+				"type": format!("{:?}", relationship.relationship_type),
+				"direction": relationship.direction.to_string(),
+				"graph": relationship.graph,
+			})
+		})
+		.collect();
+
+	// The migrations directory may not exist, the rest of the dashboard is still shown
+	let migrations = match migration_status(&CONFIG.migrations_path).await {
+		Ok(migrations) => json!(migrations
+			.iter()
+			.map(|migration| json!({
+				"version": migration.version,
+				"name": migration.name,
+				"applied": migration.applied,
+			}))
+			.collect::<Vec<_>>()),
+		Err(e) => json!({ "error": e.to_string() }),
+	};
+
+	Ok(ActixResponse::Ok().json(json!({
+		"collections": collections,
+		"relationships": relationships,
+		"slowQueries": recent_slow_queries(),
+		"slowQueryThresholdMs": CONFIG.slow_query_threshold_ms,
+		"migrations": migrations,
+	})))
+}
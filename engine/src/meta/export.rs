@@ -0,0 +1,54 @@
+use actix_web::{
+	error::{ErrorBadRequest, ErrorInternalServerError},
+	web::{Bytes, Path, Query},
+	Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use super::auth::require_admin;
+use crate::lib::transfer::{Exporter, TransferFormat};
+
+#[derive(Deserialize)]
+pub struct ExportParameters {
+	#[serde(default)]
+	format: TransferFormat,
+	/// JSON object of attribute values the exported documents must match
+	filter: Option<String>,
+}
+
+/// Stream a whole collection as JSONL or CSV
+pub async fn export_route(
+	req: ActixRequest,
+	collection: Path<String>,
+	parameters: Query<ExportParameters>,
+) -> Result<ActixResponse, ActixError> {
+	require_admin(&req)?;
+
+	let filter = match parameters.filter {
+		Some(ref filter) => Some(
+			serde_json::from_str::<JsonValue>(filter)
+				.map_err(|e| ErrorBadRequest(format!("Invalid filter: {}", e)))?,
+		),
+		None => None,
+	};
+
+	let exporter = Exporter::new(collection.into_inner(), parameters.format, filter)
+		.await
+		.map_err(ErrorBadRequest)?;
+
+	let stream = futures::stream::unfold(Some(exporter), |exporter| async move {
+		let mut exporter = exporter?;
+
+		match exporter.next_chunk().await {
+			Ok(Some(chunk)) => Some((Ok::<_, ActixError>(Bytes::from(chunk)), Some(exporter))),
+			Ok(None) => None,
+			// Stop the stream after reporting the error
+			Err(e) => Some((Err(ErrorInternalServerError(e)), None)),
+		}
+	});
+
+	Ok(ActixResponse::Ok()
+		.content_type(parameters.format.content_type())
+		.streaming(stream))
+}
@@ -1,16 +1,21 @@
+use crate::api::schema::Schemas;
 use crate::lib::database::DATABASE;
+use actix_web::web::Data;
 use rust_arango::Database as ArangoDatabase;
 
 pub struct Context {
 	pub authenticated: bool,
 	pub database: ArangoDatabase,
+	/// The schemas of the generated API, reloaded after the metadata changed
+	pub api_schemas: Option<Data<Schemas>>,
 }
 
 impl Context {
-	pub async fn new() -> Context {
+	pub async fn new(api_schemas: Option<Data<Schemas>>) -> Context {
 		Context {
 			authenticated: false,
 			database: DATABASE.get().await.database.clone(),
+			api_schemas,
 		}
 	}
 }
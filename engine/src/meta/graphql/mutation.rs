@@ -1,18 +1,99 @@
 use super::Context;
 
-use crate::lib::database::arango::{create_collection, delete_collection};
-use crate::lib::schema::SchemaDocumentProperty;
+use crate::api::actions::{delete_action, set_action, ActionInput};
+use crate::api::api_keys::{create_api_key, revoke_api_key};
+use crate::lib::database::arango::{
+	add_collection_property, create_collection, create_relationship, create_search_view,
+	delete_collection, delete_relationship, delete_search_view, remove_collection_property,
+	set_collection_audit, set_collection_cache, set_collection_checks, set_collection_directives,
+	set_collection_expiry, set_collection_field_permissions, set_collection_operations,
+	set_collection_permissions, set_collection_references, set_collection_row_filters,
+	set_collection_strict, set_collection_time_limits, set_collection_timestamps,
+	set_collection_triggers, set_collection_unique, set_collection_validations,
+	set_collection_versioning, update_collection,
+};
+use crate::lib::database::graphs::create_smart_graph;
+use crate::lib::migrations::{migrate, rollback};
+use crate::lib::scheduler::{create_scheduled_event, delete_scheduled_event, ScheduledEventInput};
+use crate::lib::schema::{
+	SchemaCacheOptions, SchemaCheck, SchemaCollectionOptions, SchemaCollectionUpdate,
+	SchemaDirectives, SchemaDocumentProperty, SchemaExpiryOptions, SchemaFieldPermission,
+	SchemaOperation, SchemaPermission, SchemaReference, SchemaRowFilter, SchemaSearchOptions,
+	SchemaSmartGraph, SchemaTimeLimit, SchemaTrigger, SchemaUniqueConstraint, SchemaValidation,
+};
+use crate::lib::CONFIG;
 
 pub struct Mutation;
 
 #[juniper::graphql_object(context = Context)]
 impl Mutation {
+	/// Regenerate the GraphQL API from the current metadata, without restarting the server, the
+	/// current API is kept when the database rejects a query of the new one
+	pub async fn reload_schema(context: &Context) -> bool {
+		return if let Some(schemas) = &context.api_schemas {
+			schemas.reload().await
+		} else {
+			false
+		};
+	}
+
+	/// Apply the pending migrations, returning their versions
+	pub async fn migrate(_context: &Context) -> Option<Vec<i32>> {
+		return match migrate(&CONFIG.migrations_path).await {
+			Ok(versions) => Some(versions.into_iter().map(|v| v as i32).collect()),
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to apply the migrations");
+
+				None
+			}
+		};
+	}
+
+	/// Revert the last applied migration, returning its version
+	pub async fn rollback(_context: &Context) -> Option<i32> {
+		return match rollback(&CONFIG.migrations_path).await {
+			Ok(version) => version.map(|v| v as i32),
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to revert the migration");
+
+				None
+			}
+		};
+	}
+
+	/// Store an API key, the returned key cannot be retrieved later
+	pub async fn create_api_key(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] roles: Vec<String>,
+		#[graphql] expires_at: Option<String>,
+		#[graphql] tenant: Option<String>,
+	) -> Option<String> {
+		return match create_api_key(name, roles, expires_at, tenant).await {
+			Ok(key) => Some(key),
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to create the API key");
+
+				None
+			}
+		};
+	}
+
+	pub async fn revoke_api_key(_context: &Context, #[graphql] name: String) -> bool {
+		return match revoke_api_key(name).await {
+			Ok(revoked) => revoked,
+			Err(_) => false,
+		};
+	}
+
 	pub async fn create_collection(
 		_context: &Context,
 		#[graphql] name: String,
 		#[graphql] properties: Vec<SchemaDocumentProperty>,
+		#[graphql] options: Option<SchemaCollectionOptions>,
 	) -> bool {
-		return if let Ok(_) = create_collection(name, properties).await {
+		return if let Ok(_) = create_collection(name, properties, options.unwrap_or_default()).await
+		{
 			true
 		} else {
 			false
@@ -26,4 +107,389 @@ impl Mutation {
 			false
 		};
 	}
+
+	pub async fn add_collection_property(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] property: SchemaDocumentProperty,
+	) -> bool {
+		return if let Ok(_) = add_collection_property(name, property).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	/// Add or replace properties, replace the required properties or change the validation level
+	/// of an existing collection
+	pub async fn update_collection(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] update: SchemaCollectionUpdate,
+	) -> bool {
+		return match update_collection(name, update).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to update the collection");
+
+				false
+			}
+		};
+	}
+
+	/// Remove a property from the schema, the values already stored are kept
+	pub async fn remove_collection_property(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] property: String,
+	) -> bool {
+		return if let Ok(_) = remove_collection_property(name, property).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	/// Create or update a relationship, `type` is `one_to_one`, `one_to_many` or `many_to_many`
+	/// and `direction` is `outbound`, `inbound` or `any`, the edges of a relationship of a
+	/// SmartGraph are traversed through the graph
+	pub async fn create_relationship(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] edge: String,
+		#[graphql] from: String,
+		#[graphql] to: String,
+		#[graphql(name = "type")] relationship_type: String,
+		#[graphql] direction: String,
+		#[graphql] graph: Option<String>,
+	) -> bool {
+		return if let Ok(_) =
+			create_relationship(name, edge, from, to, relationship_type, direction, graph).await
+		{
+			true
+		} else {
+			false
+		};
+	}
+
+	/// Create a SmartGraph, its collections are then created with the `smartGraph` option and
+	/// its relationships with the `graph` argument, only available on ArangoDB Enterprise
+	pub async fn create_smart_graph(
+		_context: &Context,
+		#[graphql] graph: SchemaSmartGraph,
+	) -> bool {
+		return match create_smart_graph(&graph).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = ?e, graph = %graph.name, "Failed to create the SmartGraph");
+
+				false
+			}
+		};
+	}
+
+	/// Remove a relationship, its edge collection is kept
+	pub async fn delete_relationship(_context: &Context, #[graphql] name: String) -> bool {
+		return if let Ok(_) = delete_relationship(name).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn create_search_view(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] search: SchemaSearchOptions,
+	) -> bool {
+		return if let Ok(_) = create_search_view(name, search).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn delete_search_view(_context: &Context, #[graphql] name: String) -> bool {
+		return if let Ok(_) = delete_search_view(name).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn set_collection_audit(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] enabled: bool,
+	) -> bool {
+		return if let Ok(_) = set_collection_audit(name, enabled).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	/// Keep the previous state of the documents on every update and removal, or stop
+	pub async fn set_collection_versioning(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] enabled: bool,
+	) -> bool {
+		return match set_collection_versioning(name, enabled).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to set the versioning of the collection");
+
+				false
+			}
+		};
+	}
+
+	/// Reject the attributes that are not properties of the collection, or allow them
+	pub async fn set_collection_strict(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] strict: bool,
+	) -> bool {
+		return match set_collection_strict(name, strict).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the strict mode of the collection");
+
+				false
+			}
+		};
+	}
+
+	pub async fn set_collection_timestamps(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] enabled: bool,
+	) -> bool {
+		return if let Ok(_) = set_collection_timestamps(name, enabled).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn set_collection_expiry(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] expiry: Option<SchemaExpiryOptions>,
+	) -> bool {
+		return if let Ok(_) = set_collection_expiry(name, expiry).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	/// Cache the results of `get` and `getAll` for the given seconds, `null` stops caching them
+	pub async fn set_collection_cache(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] cache: Option<Vec<SchemaCacheOptions>>,
+	) -> bool {
+		return match set_collection_cache(name, cache).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the cache of the collection");
+
+				false
+			}
+		};
+	}
+
+	/// Kill the queries of the operations running longer than `maxRuntimeMs`, `null` stops
+	/// limiting them
+	pub async fn set_collection_time_limits(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] time_limits: Option<Vec<SchemaTimeLimit>>,
+	) -> bool {
+		return match set_collection_time_limits(name, time_limits).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the time limits of the collection");
+
+				false
+			}
+		};
+	}
+
+	pub async fn set_collection_permissions(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] permissions: Option<Vec<SchemaPermission>>,
+	) -> bool {
+		return if let Ok(_) = set_collection_permissions(name, permissions).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn set_collection_row_filters(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] row_filters: Option<Vec<SchemaRowFilter>>,
+	) -> bool {
+		return if let Ok(_) = set_collection_row_filters(name, row_filters).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn set_collection_field_permissions(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] field_permissions: Option<Vec<SchemaFieldPermission>>,
+	) -> bool {
+		return if let Ok(_) = set_collection_field_permissions(name, field_permissions).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn set_collection_operations(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] operations: Option<Vec<SchemaOperation>>,
+	) -> bool {
+		return if let Ok(_) = set_collection_operations(name, operations).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn set_collection_triggers(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] triggers: Option<Vec<SchemaTrigger>>,
+	) -> bool {
+		return if let Ok(_) = set_collection_triggers(name, triggers).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	pub async fn set_collection_validations(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] validations: Option<Vec<SchemaValidation>>,
+	) -> bool {
+		return match set_collection_validations(name, validations).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the validations of the collection");
+
+				false
+			}
+		};
+	}
+
+	pub async fn set_collection_unique(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] unique: Option<Vec<SchemaUniqueConstraint>>,
+	) -> bool {
+		return match set_collection_unique(name, unique).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the unique constraints of the collection");
+
+				false
+			}
+		};
+	}
+
+	pub async fn set_collection_references(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] references: Option<Vec<SchemaReference>>,
+	) -> bool {
+		return match set_collection_references(name, references).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the references of the collection");
+
+				false
+			}
+		};
+	}
+
+	pub async fn set_collection_checks(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] checks: Option<Vec<SchemaCheck>>,
+	) -> bool {
+		return match set_collection_checks(name, checks).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the checks of the collection");
+
+				false
+			}
+		};
+	}
+
+	pub async fn set_collection_directives(
+		_context: &Context,
+		#[graphql] name: String,
+		#[graphql] directives: Option<SchemaDirectives>,
+	) -> bool {
+		return if let Ok(_) = set_collection_directives(name, directives).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	/// Create or replace a one-off or cron event, fired by one of the running instances
+	pub async fn create_scheduled_event(
+		_context: &Context,
+		#[graphql] event: ScheduledEventInput,
+	) -> bool {
+		return match create_scheduled_event(event).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to create the scheduled event");
+
+				false
+			}
+		};
+	}
+
+	pub async fn delete_scheduled_event(_context: &Context, #[graphql] name: String) -> bool {
+		return if let Ok(_) = delete_scheduled_event(name).await {
+			true
+		} else {
+			false
+		};
+	}
+
+	/// Create or replace an action, exposed once the schema is reloaded
+	pub async fn set_action(_context: &Context, #[graphql] action: ActionInput) -> bool {
+		return match set_action(action).await {
+			Ok(_) => true,
+			Err(e) => {
+				tracing::error!(error = %e, "Failed to set the action");
+
+				false
+			}
+		};
+	}
+
+	pub async fn delete_action(_context: &Context, #[graphql] name: String) -> bool {
+		return if let Ok(_) = delete_action(name).await {
+			true
+		} else {
+			false
+		};
+	}
 }
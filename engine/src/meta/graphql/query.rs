@@ -1,5 +1,18 @@
 use super::Context;
 
+use crate::api::actions::{get_actions, ActionDefinition};
+use crate::api::api_keys::{list_api_keys, ApiKeyInfo};
+use crate::api::audit::{search_audit_log, AuditEntry, AuditFilter};
+use crate::lib::migrations::{migration_status, MigrationStatus};
+use crate::lib::scheduler::{get_scheduled_events, ScheduledEvent};
+use crate::lib::schema::{
+	check_schema_status, diff_metadata, get_collection_metadata, get_collections_metadata,
+	get_metadata_versions, get_relationships_metadata, CollectionMetadata, MetadataDiff,
+	MetadataVersion, RelationshipMetadata, SchemaStatus,
+};
+use crate::lib::triggers::{get_trigger_deliveries, TriggerDelivery};
+use crate::lib::CONFIG;
+
 pub struct Query;
 
 #[juniper::graphql_object(context = Context)]
@@ -7,4 +20,143 @@ impl Query {
 	fn alchemy_version() -> &'static str {
 		return env!("CARGO_PKG_VERSION");
 	}
+
+	pub async fn collections(_context: &Context) -> Vec<CollectionMetadata> {
+		return get_collections_metadata().await;
+	}
+
+	pub async fn collection(
+		_context: &Context,
+		#[graphql] name: String,
+	) -> Option<CollectionMetadata> {
+		return get_collection_metadata(&name).await;
+	}
+
+	pub async fn relationships(_context: &Context) -> Vec<RelationshipMetadata> {
+		return get_relationships_metadata().await;
+	}
+
+	/// The versions of the metadata, most recent first
+	pub async fn metadata_versions(
+		_context: &Context,
+		#[graphql] limit: Option<i32>,
+	) -> Vec<MetadataVersion> {
+		return match get_metadata_versions(limit.unwrap_or(100)).await {
+			Ok(versions) => versions,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to list the metadata versions");
+
+				Vec::new()
+			}
+		};
+	}
+
+	/// The changes from a version to another one, the latest by default, along with the
+	/// actions pending to reconcile the database with the latest metadata
+	pub async fn metadata_diff(
+		_context: &Context,
+		#[graphql] from: i32,
+		#[graphql] to: Option<i32>,
+	) -> Option<MetadataDiff> {
+		return match diff_metadata(from, to).await {
+			Ok(diff) => diff,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to diff the metadata");
+
+				None
+			}
+		};
+	}
+
+	/// How the collections, indexes and validation schemas of the database drifted from the
+	/// metadata, with the actions reconciling them
+	pub async fn schema_status(_context: &Context) -> Option<SchemaStatus> {
+		return match check_schema_status().await {
+			Ok(status) => Some(status),
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to check the schema status");
+
+				None
+			}
+		};
+	}
+
+	/// The migrations of the migrations path and whether they were applied
+	pub async fn migrations(_context: &Context) -> Vec<MigrationStatus> {
+		return match migration_status(&CONFIG.migrations_path).await {
+			Ok(migrations) => migrations,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to list the migrations");
+
+				Vec::new()
+			}
+		};
+	}
+
+	/// The API keys stored in the database
+	pub async fn api_keys(_context: &Context) -> Vec<ApiKeyInfo> {
+		return match list_api_keys().await {
+			Ok(api_keys) => api_keys,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to list the API keys");
+
+				Vec::new()
+			}
+		};
+	}
+
+	/// The recorded mutations matching the filter, most recent first
+	pub async fn audit_log(
+		_context: &Context,
+		#[graphql] filter: Option<AuditFilter>,
+		#[graphql] limit: Option<i32>,
+	) -> Vec<AuditEntry> {
+		return match search_audit_log(filter.unwrap_or_default(), limit.unwrap_or(100)).await {
+			Ok(entries) => entries,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to search the audit log");
+
+				Vec::new()
+			}
+		};
+	}
+
+	/// The deliveries of the event triggers, most recent first
+	pub async fn trigger_deliveries(
+		_context: &Context,
+		#[graphql] trigger: Option<String>,
+		#[graphql] status: Option<String>,
+		#[graphql] limit: Option<i32>,
+	) -> Vec<TriggerDelivery> {
+		return match get_trigger_deliveries(trigger, status, limit.unwrap_or(100)).await {
+			Ok(deliveries) => deliveries,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to list the trigger deliveries");
+
+				Vec::new()
+			}
+		};
+	}
+
+	pub async fn scheduled_events(_context: &Context) -> Vec<ScheduledEvent> {
+		return match get_scheduled_events().await {
+			Ok(events) => events,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to list the scheduled events");
+
+				Vec::new()
+			}
+		};
+	}
+
+	pub async fn actions(_context: &Context) -> Vec<ActionDefinition> {
+		return match get_actions().await {
+			Ok(actions) => actions,
+			Err(e) => {
+				tracing::error!(error = ?e, "Failed to list the actions");
+
+				Vec::new()
+			}
+		};
+	}
 }
@@ -1,21 +1,36 @@
 use super::{Context, Schema};
+use crate::api::schema::Schemas;
+use crate::api::server::ide_handler;
+use crate::lib::CONFIG;
+use crate::meta::auth::require_admin;
 
 use actix_web::{
 	web::{Data, Payload as ActixPayload},
 	Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
 };
 
-use juniper_actix::{graphql_handler, playground_handler};
+use juniper_actix::graphql_handler;
+use lazy_static::lazy_static;
 
+lazy_static! {
+	static ref GRAPHQL_PATH: String = CONFIG.path("/admin/graphql");
+	static ref SUBSCRIPTIONS_PATH: String = CONFIG.path("/admin/graphql_subscriptions");
+}
+
+/// The management API, served at `/admin/graphql` and still at `/meta/graphql`, is only
+/// protected by the admin secret whatever the authentication of the data API
 pub async fn graphql_meta_route(
 	req: ActixRequest,
 	payload: ActixPayload,
 	schema: Data<Schema>,
+	api_schemas: Option<Data<Schemas>>,
 ) -> Result<ActixResponse, ActixError> {
-	let context = Context::new().await;
+	require_admin(&req)?;
+
+	let context = Context::new(api_schemas).await;
 	graphql_handler(&schema, &context, req, payload).await
 }
 
-pub async fn playground_meta_route() -> Result<ActixResponse, ActixError> {
-	playground_handler("/meta/graphql", Some("/meta/graphql_subscriptions")).await
+pub async fn ide_meta_route() -> Result<ActixResponse, ActixError> {
+	ide_handler(&GRAPHQL_PATH, &SUBSCRIPTIONS_PATH).await
 }
@@ -0,0 +1,59 @@
+use actix_web::{
+	error::{ErrorBadRequest, ErrorInternalServerError},
+	web::{Path, Payload as ActixPayload, Query},
+	Error as ActixError, HttpRequest as ActixRequest, HttpResponse as ActixResponse,
+};
+use futures::StreamExt;
+use serde::Deserialize;
+
+use super::auth::require_admin;
+use crate::lib::transfer::{Importer, TransferFormat};
+
+/// Stripped from the lines, other trailing whitespace can belong to a quoted CSV cell
+const LINE_ENDINGS: &[char] = &['\r', '\n'];
+
+#[derive(Deserialize)]
+pub struct ImportParameters {
+	#[serde(default)]
+	format: TransferFormat,
+}
+
+/// Stream a JSONL or CSV payload into a collection and report rejected rows
+pub async fn import_route(
+	req: ActixRequest,
+	collection: Path<String>,
+	parameters: Query<ImportParameters>,
+	mut payload: ActixPayload,
+) -> Result<ActixResponse, ActixError> {
+	require_admin(&req)?;
+
+	let mut importer = Importer::new(collection.into_inner(), parameters.format)
+		.await
+		.map_err(ErrorBadRequest)?;
+
+	let mut buffer: Vec<u8> = Vec::new();
+
+	while let Some(chunk) = payload.next().await {
+		buffer.extend_from_slice(&chunk?);
+
+		while let Some(position) = buffer.iter().position(|b| *b == b'\n') {
+			let line: Vec<u8> = buffer.drain(..=position).collect();
+
+			importer
+				.push_line(String::from_utf8_lossy(&line).trim_end_matches(LINE_ENDINGS))
+				.await
+				.map_err(ErrorBadRequest)?;
+		}
+	}
+
+	if !buffer.is_empty() {
+		importer
+			.push_line(String::from_utf8_lossy(&buffer).trim_end_matches(LINE_ENDINGS))
+			.await
+			.map_err(ErrorBadRequest)?;
+	}
+
+	let report = importer.finish().await.map_err(ErrorInternalServerError)?;
+
+	Ok(ActixResponse::Ok().json(report))
+}
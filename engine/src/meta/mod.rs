@@ -1 +1,5 @@
+pub mod auth;
+pub mod dashboard;
+pub mod export;
 pub mod graphql;
+pub mod import;